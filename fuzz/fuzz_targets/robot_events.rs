@@ -0,0 +1,183 @@
+//! Feeds arbitrary sequences of robot events to the station and the app, looking for panics
+//! and inconsistent state: events for unknown robots, merges without arrivals, knowledge of
+//! the wrong size, coordinates off the map.
+#![no_main]
+
+use arbitrary::{Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+
+use astro_swarm::{
+    app::{App, RobotCounts, SpawnPolicy},
+    communication::channels::{
+        create_channel, EventSource, ResourceType, RobotEvent, ShutdownReason,
+    },
+    difficulty::Difficulty,
+    map::topology::MapShape,
+    robot::core::knowledge::{RobotKnowledge, TileInfo},
+    settings::ModuleCatalog,
+    station::station::Station,
+};
+use std::sync::Arc;
+
+const WIDTH: usize = 12;
+const HEIGHT: usize = 7;
+/// Events per input, so a single input cannot run for too long
+const MAX_EVENTS: usize = 64;
+
+/// Coordinates mostly on the map, sometimes just off it
+fn coord(u: &mut Unstructured, size: usize) -> Result<usize> {
+    u.int_in_range(0..=size + 2)
+}
+
+fn resource_type(u: &mut Unstructured) -> Result<ResourceType> {
+    Ok(match u.int_in_range(0..=2)? {
+        0 => ResourceType::ENERGY,
+        1 => ResourceType::MINERALS,
+        _ => ResourceType::SCIENCE_POINTS,
+    })
+}
+
+fn knowledge(u: &mut Unstructured) -> Result<RobotKnowledge> {
+    // Usually the map size, sometimes a robot that disagrees about it
+    let (width, height) = if u.ratio(1, 8)? {
+        (
+            u.int_in_range(1..=WIDTH * 2)?,
+            u.int_in_range(1..=HEIGHT * 2)?,
+        )
+    } else {
+        (WIDTH, HEIGHT)
+    };
+    let mut knowledge = RobotKnowledge::new(width, height);
+    for _ in 0..u.int_in_range(0..=16)? {
+        let tile = match u.int_in_range(0..=4)? {
+            0 => TileInfo::Unknown,
+            1 => TileInfo::Walkable,
+            2 => TileInfo::Obstacle,
+            3 => TileInfo::Resource(resource_type(u)?, u.arbitrary()?),
+            _ => TileInfo::Station,
+        };
+        knowledge.update_tile(coord(u, width)?, coord(u, height)?, tile);
+    }
+    Ok(knowledge)
+}
+
+fn event(u: &mut Unstructured) -> Result<RobotEvent> {
+    // Small ids so the same robot shows up several times
+    let id = u.int_in_range(0..=8)?;
+    Ok(match u.int_in_range(0..=11)? {
+        0 => RobotEvent::ExplorationData {
+            id,
+            x: coord(u, WIDTH)?,
+            y: coord(u, HEIGHT)?,
+            is_obstacle: u.arbitrary()?,
+        },
+        1 => RobotEvent::CollectionData {
+            id,
+            x: coord(u, WIDTH)?,
+            y: coord(u, HEIGHT)?,
+            resource_type: if u.arbitrary()? {
+                Some(resource_type(u)?)
+            } else {
+                None
+            },
+            amount: u.arbitrary()?,
+        },
+        2 => RobotEvent::ScienceData {
+            id,
+            x: coord(u, WIDTH)?,
+            y: coord(u, HEIGHT)?,
+            resource_type: resource_type(u)?,
+            amount: u.arbitrary()?,
+            modules: u.arbitrary()?,
+        },
+        3 => RobotEvent::LowEnergy {
+            id,
+            remaining: u.arbitrary()?,
+        },
+        4 => RobotEvent::ReturnToBase { id },
+        5 => RobotEvent::ArrivedAtStation {
+            id,
+            knowledge: knowledge(u)?,
+        },
+        6 => RobotEvent::MergeComplete {
+            id,
+            merged_knowledge: knowledge(u)?,
+            brief: Box::default(),
+        },
+        7 => RobotEvent::Shutdown {
+            id,
+            reason: *u.choose(&[
+                ShutdownReason::Retired,
+                ShutdownReason::StationClosed,
+                ShutdownReason::LoopExited,
+                ShutdownReason::ConnectionLost,
+            ])?,
+        },
+        8 => RobotEvent::RendezvousRequest {
+            id,
+            x: coord(u, WIDTH)?,
+            y: coord(u, HEIGHT)?,
+        },
+        9 => RobotEvent::SampleExtracted {
+            id,
+            x: coord(u, WIDTH)?,
+            y: coord(u, HEIGHT)?,
+        },
+        10 => RobotEvent::MergeAbandoned {
+            id,
+            attempts: u.arbitrary()?,
+        },
+        _ => RobotEvent::ExplorationComplete {
+            known_tiles: u.arbitrary()?,
+        },
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+
+    // No robots: their threads would outlive the input, and unknown ids are part of the point
+    let mut app = App::new(
+        WIDTH,
+        HEIGHT,
+        u.arbitrary().unwrap_or(0),
+        u.arbitrary().unwrap_or(0),
+        RobotCounts {
+            exploration: 0,
+            collection: 0,
+            scientific: 0,
+            scout: 0,
+        },
+        SpawnPolicy::Scattered,
+        ModuleCatalog::default(),
+        Arc::default(),
+        Difficulty::Normal,
+        MapShape {
+            cave_layers: u.int_in_range(0..=2).unwrap_or(0),
+            wraps: u.arbitrary().unwrap_or(false),
+            hex: u.arbitrary().unwrap_or(false),
+        },
+    );
+    let app_events = app.event_sender();
+    let (station_sender, _station_events) = create_channel();
+    let station = Station::new(
+        station_sender.with_source(EventSource::Station),
+        WIDTH,
+        HEIGHT,
+    );
+
+    for _ in 0..MAX_EVENTS {
+        let Ok(event) = event(&mut u) else {
+            break;
+        };
+        station.process_event(&event);
+        let _ = app_events.send(event);
+        if u.ratio(1, 4).unwrap_or(true) {
+            app.update();
+        }
+    }
+    app.update();
+
+    assert!(app.robot_ids().is_empty(), "events must not create robots");
+    assert!(app.total_explored <= WIDTH * HEIGHT);
+});
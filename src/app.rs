@@ -1,30 +1,283 @@
 use std::{
     collections::{HashMap, HashSet},
-    sync::{mpsc, Arc, RwLock},
+    net::SocketAddr,
+    sync::{mpsc, Arc},
+    time::Duration,
 };
 
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use rand::{rngs::StdRng, seq::IndexedRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    communication::channels::{ResourceType, RobotEvent},
-    map::noise::Map,
-    robot::behavior::collection::CollectionRobot,
-    robot::behavior::exploration::ExplorationRobot,
-    robot::behavior::scientific::ScientificRobot,
+    communication::bus::{EventBus, EventSubscriber},
+    communication::channels::{
+        create_channel, create_command_channel, CommandEnvelope, ConfigOverrides, DockingBrief,
+        EventEnvelope, EventSender, EventSource, ResourceType, RobotCommand, RobotEvent,
+        ShutdownReason,
+    },
+    communication::tcp::{Assignment, RemoteStation},
+    difficulty::Difficulty,
+    error::{MapError, SimError, SimInitError},
+    map::hostiles::HostileSystem,
+    map::resources::ResourceCatalog,
+    map::topology::MapShape,
+    map::{noise::Map, shared::SharedMap},
+    memory::{self, MemoryLevel, MemoryUsage},
+    profiler::{self, Section},
+    robot::core::knowledge::{
+        Annotation, DynamicLayer, EntityKind, KnowledgeDivergence, RobotKnowledge, Sighting,
+        TileInfo,
+    },
+    robot::core::science_score::ScienceTargeting,
     robot::core::state::{RobotState, RobotStatus},
-    robot::utils::config,
+    robot::launcher::{self, ParameterJitter, RobotInbox, RobotLoadout},
+    robot::registry::{RobotEntry, RobotRegistry, RobotType},
+    robot::utils::{
+        clock::{self, SharedClock},
+        config,
+        inbox::InboxGauge,
+        preset::SwarmPreset,
+        trace::{Decision, DecisionTrace},
+    },
+    scenario::{Scenario, WorldEvent, WorldEventScheduler},
+    settings::{AlarmSettings, MemorySettings, ModuleCatalog},
+    station::alarms::{AlarmMonitor, SwarmReadings},
+    station::charging::{ChargingBay, ChargingPolicy},
+    station::command_tracker::{CommandTracker, Overdue},
+    station::data_manager::{ConflictStats, KnowledgeCoverage, MergeRecord},
+    station::exclusions::ExclusionZones,
+    station::formation::{Formation, FormationMove},
+    station::idle_monitor::IdleMonitor,
+    station::journal::{EventJournal, Severity},
+    station::kpis::{KpiTotals, KpiTracker, SwarmKpis},
+    station::ledger::{LedgerEntry, ResourceLedger},
+    station::maintenance::{MaintenanceSchedule, MaintenanceState},
+    station::priorities::PriorityZones,
+    station::rendezvous::RendezvousBook,
+    station::squads::{SquadRoster, SquadSummary},
     station::station::Station,
+    station::stock_balancer::StockBalancer,
+    trajectory::TrajectoryLog,
 };
 
+/// Real-time interval between two simulation ticks
+pub const TICK_RATE: Duration = Duration::from_millis(100);
+/// Ticks skipped by the fast-forward UI command
+pub const FAST_FORWARD_TICKS: u64 = 100;
+
+/// How many robots of each type to spawn at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RobotCounts {
+    pub exploration: usize,
+    pub collection: usize,
+    pub scientific: usize,
+    pub scout: usize,
+}
+
+impl RobotCounts {
+    pub fn total(&self) -> usize {
+        self.exploration + self.collection + self.scientific + self.scout
+    }
+}
+
+impl Default for RobotCounts {
+    fn default() -> Self {
+        Self {
+            exploration: 1,
+            collection: 1,
+            scientific: 1,
+            scout: 0,
+        }
+    }
+}
+
+/// Everything a run starts from, picked on the command line or the setup screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSetup {
+    pub width: usize,
+    pub height: usize,
+    pub map_seed: u32,
+    pub resource_seed: u64,
+    pub robot_counts: RobotCounts,
+    pub difficulty: Difficulty,
+    /// Caves, wrapping edges and tile shape of the map
+    pub shape: MapShape,
+}
+
+impl Default for GameSetup {
+    fn default() -> Self {
+        Self {
+            width: 90,
+            height: 15,
+            map_seed: 34,
+            resource_seed: 45,
+            robot_counts: RobotCounts::default(),
+            difficulty: Difficulty::default(),
+            shape: MapShape::default(),
+        }
+    }
+}
+
+impl GameSetup {
+    /// The same setup on freshly rolled map and resource seeds
+    pub fn reseeded(self) -> Self {
+        Self {
+            map_seed: rand::random(),
+            resource_seed: rand::random(),
+            ..self
+        }
+    }
+}
+
+/// Where robots are placed at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SpawnPolicy {
+    /// Random free tiles anywhere on the map
+    #[default]
+    Scattered,
+    /// Free tiles closest to the station, as if deployed from it
+    NearStation,
+}
+
+/// Delivery figures of the robot event channel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventStats {
+    pub received: u64,
+    /// Events older than one already received from the same source
+    pub out_of_order: u64,
+    /// Sequence numbers skipped by a source
+    pub missing: u64,
+    /// Ticks between sending and processing, summed over every event
+    pub total_latency_ticks: u64,
+    pub max_latency_ticks: u64,
+}
+
+impl EventStats {
+    pub fn average_latency_ticks(&self) -> f64 {
+        if self.received == 0 {
+            0.0
+        } else {
+            self.total_latency_ticks as f64 / self.received as f64
+        }
+    }
+}
+
+/// Energy of the robots, as they last reported it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnergySummary {
+    pub min: u32,
+    pub average: u32,
+}
+
+/// How a run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissionOutcome {
+    /// Every robot retired once the map was exhausted
+    Completed,
+    /// Every robot stopped while there was work left
+    Failed,
+    /// The run's tick limit came first
+    TimedOut,
+}
+
+/// Smallest map side, the station alone taking 3 by 3 tiles
+const MIN_MAP_SIDE: usize = 3;
+
+/// Final figures shown once every robot retired or the run's time was up
+#[derive(Debug, Clone)]
+pub struct CompletionSummary {
+    pub outcome: MissionOutcome,
+    pub ticks: u64,
+    pub explored_tiles: usize,
+    pub total_tiles: usize,
+    pub collected_resources: HashMap<ResourceType, u32>,
+    pub scientific_data: u64,
+    pub retired_robots: usize,
+    pub difficulty: Difficulty,
+    pub map_seed: u32,
+    pub resource_seed: u64,
+    pub robot_seed: u64,
+}
+
 pub struct App {
-    pub map: Arc<RwLock<Map>>,
-    pub exploration_robots: HashMap<u32, RobotState>,
-    pub collection_robots: HashMap<u32, RobotState>,
-    pub scientific_robots: HashMap<u32, RobotState>,
-    pub event_receiver: mpsc::Receiver<RobotEvent>,
-    event_sender: mpsc::Sender<RobotEvent>,
-    robot_merge_senders: HashMap<u32, mpsc::Sender<RobotEvent>>,
+    pub map: SharedMap,
+    pub robots: RobotRegistry,
+    pub event_receiver: mpsc::Receiver<EventEnvelope>,
+    event_sender: EventSender,
+    /// Last sequence number seen from each event source
+    last_sequences: HashMap<EventSource, u64>,
+    pub event_stats: EventStats,
+    event_bus: EventBus,
+    pub idle_monitor: IdleMonitor,
+    /// Where collected resources went, audited every `config::LEDGER_AUDIT_INTERVAL_TICKS`
+    pub ledger: ResourceLedger,
+    /// Energy the robots reported using up, recharges aside
+    pub energy_spent: u64,
+    /// How far the station's knowledge was from the real map after the last merge
+    station_divergence: KnowledgeDivergence,
+    kpis: KpiTracker,
+    pub maintenance: MaintenanceSchedule,
+    /// Services carried out at the station
+    pub maintenance_services: u32,
+    /// Tiles the user marked for explorers and collectors to work on first
+    pub priorities: PriorityZones,
+    /// Scientists waiting on joint sites, and the collectors sent to them
+    pub rendezvous: RendezvousBook,
+    /// Samples collectors extracted for a waiting scientist
+    pub joint_samples: u32,
+    /// Docks robots gave up on after reporting them again without a merge, and the last
+    /// robot that did with the tick it told the station
+    pub merges_abandoned: u32,
+    pub last_merge_abandoned: Option<(u32, u64)>,
+    /// Swarm strategy every robot plays its thresholds and pace by
+    pub preset: SwarmPreset,
+    /// How scientists launched from now on rank the science deposits
+    pub science_targeting: ScienceTargeting,
+    /// Chargers docked robots queue for, when the station has a limited number of them
+    pub charging: ChargingBay,
+    /// Approximate memory of the run, estimated every `config::MEMORY_CHECK_INTERVAL_TICKS`
+    pub memory: MemoryUsage,
+    pub memory_level: MemoryLevel,
+    /// Budget `memory` is checked against
+    pub memory_limit: MemorySettings,
+    /// Alarms on the swarm's health
+    pub alarms: AlarmMonitor,
+    /// Energy stock under which `StationEnergyLow` fires, 0 when off, and whether it is
+    /// under it now
+    station_energy_low: (u32, bool),
+    /// Notable events of the run, alarms included
+    pub journal: EventJournal,
+    /// No-go tiles set by the user or by hazards
+    pub exclusions: ExclusionZones,
+    stock_balancer: StockBalancer,
+    pub command_tracker: CommandTracker,
+    pub idle_reassignments: u32,
+    pub formation: Option<FormationMove>,
+    pub squads: SquadRoster,
+    /// Patrol route of each patrolling explorer
+    pub patrols: HashMap<u32, Vec<(usize, usize)>>,
+    /// Explorers told to stay docked because exploration is complete
+    pub parked_robots: HashSet<u32>,
+    pub exploration_complete: bool,
+    /// Robots were told to retire because the map is exhausted
+    pub retiring: bool,
+    retired_robots: usize,
+    /// Robots heading back to the station to be dismantled
+    decommissioning: HashSet<u32>,
+    /// Robots heading back to the station to be relaunched with another behavior
+    conversions: HashMap<u32, RobotType>,
+    pub decommissioned_robots: u32,
+    pub refunded_minerals: u32,
+    /// Worn batteries replaced at the station
+    pub battery_refits: u32,
+    /// Robots decommissioned because their battery wore out with no minerals to replace it
+    pub worn_out_robots: u32,
+    pub completion_summary: Option<CompletionSummary>,
+    /// Tick the run stops at, if time-boxed
+    pub tick_limit: Option<u64>,
+    pub tick: u64,
     pub station: Station,
     pub collected_resources: HashMap<ResourceType, u32>,
     pub scientific_data: u64,
@@ -32,12 +285,38 @@ pub struct App {
     pub explored_tiles: HashSet<(usize, usize)>,
     pub map_width: usize,
     pub map_height: usize,
-}
-
-enum RobotType {
-    Exploration,
-    Collection,
-    Scientific,
+    pub map_seed: u32,
+    pub resource_seed: u64,
+    pub difficulty: Difficulty,
+    pub shape: MapShape,
+    /// Robots the run started with
+    pub robot_counts: RobotCounts,
+    /// Positions of every robot at every tick, once recording was asked for
+    pub trajectories: Option<TrajectoryLog>,
+    /// Energy taken from the stock by the difficulty's station drain
+    pub drained_energy: u32,
+    module_catalog: ModuleCatalog,
+    /// Resource types of the run, shared with its map and robots
+    resource_catalog: Arc<ResourceCatalog>,
+    /// Parameter jitter of the robots, as configured when the run started
+    jitter_percent: u32,
+    /// Robots the run may hold at once, as configured when it started
+    max_robots: usize,
+    /// Time robots launched from now on and the station go by
+    clock: SharedClock,
+    /// Id given to the next robot spawned
+    next_robot_id: u32,
+    /// Accepts robots running in other processes, once `listen` was called
+    remote_station: Option<RemoteStation>,
+    world_events: WorldEventScheduler,
+    /// Tick the current storm ends at
+    pub storm_until: Option<u64>,
+    /// Tick the current solar flare ends at, the robots' radio being down until then
+    pub solar_flare_until: Option<u64>,
+    pub meteor_strikes: u32,
+    pub hostiles: HostileSystem,
+    /// Tiles holding cargo dropped by robots that stopped in the field
+    salvage_sites: HashSet<(usize, usize)>,
 }
 
 impl App {
@@ -47,26 +326,199 @@ impl App {
     ///
     /// * `width` - The width of the simulation map.
     /// * `height` - The height of the simulation map.
-    /// * `map_seed` - Seed for generating the map layout (obstacles).
+    /// * `map_seed` - Seed for generating the map layout (obstacles), derived anew while the
+    ///   map comes out degenerate.
     /// * `resource_seed` - Seed for placing resources on the map.
-    pub fn new(width: usize, height: usize, map_seed: u32, resource_seed: u64) -> Self {
-        let mut map = Map::new(width, height, map_seed);
+    /// * `robot_counts` - How many robots of each type to spawn.
+    /// * `spawn_policy` - Where the robots start.
+    /// * `module_catalog` - Modules robots are fitted with at spawn.
+    /// * `difficulty` - Preset tuning energy costs, resources, meteors and station drain.
+    /// * `shape` - Cave layers, wrapping edges and hex tiles of the map.
+    ///
+    /// # Panics
+    ///
+    /// Panics where `try_new` returns an error, e.g. if the map has fewer free walkable tiles
+    /// than requested robots.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        width: usize,
+        height: usize,
+        map_seed: u32,
+        resource_seed: u64,
+        robot_counts: RobotCounts,
+        spawn_policy: SpawnPolicy,
+        module_catalog: ModuleCatalog,
+        resource_catalog: Arc<ResourceCatalog>,
+        difficulty: Difficulty,
+        shape: MapShape,
+    ) -> Self {
+        Self::try_new(
+            width,
+            height,
+            map_seed,
+            resource_seed,
+            robot_counts,
+            spawn_policy,
+            module_catalog,
+            resource_catalog,
+            difficulty,
+            shape,
+        )
+        .unwrap_or_else(|e| panic!("Cannot start the simulation: {}", e))
+    }
+
+    /// Like `new`, but an error rather than a panic when the simulation cannot be set up;
+    /// no robot is started then
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new(
+        width: usize,
+        height: usize,
+        map_seed: u32,
+        resource_seed: u64,
+        robot_counts: RobotCounts,
+        spawn_policy: SpawnPolicy,
+        module_catalog: ModuleCatalog,
+        resource_catalog: Arc<ResourceCatalog>,
+        difficulty: Difficulty,
+        shape: MapShape,
+    ) -> Result<Self, SimInitError> {
+        Self::try_new_on(
+            clock::real(),
+            width,
+            height,
+            map_seed,
+            resource_seed,
+            robot_counts,
+            spawn_policy,
+            module_catalog,
+            resource_catalog,
+            difficulty,
+            shape,
+        )
+    }
 
-        map.spawn_resources(width * height / 30, resource_seed);
+    /// Like `try_new`, with the station and the starting robots going by `clock`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn try_new_on(
+        clock: SharedClock,
+        width: usize,
+        height: usize,
+        map_seed: u32,
+        resource_seed: u64,
+        robot_counts: RobotCounts,
+        spawn_policy: SpawnPolicy,
+        module_catalog: ModuleCatalog,
+        resource_catalog: Arc<ResourceCatalog>,
+        difficulty: Difficulty,
+        shape: MapShape,
+    ) -> Result<Self, SimInitError> {
+        if width < MIN_MAP_SIDE || height < MIN_MAP_SIDE {
+            return Err(SimInitError::InvalidConfig(format!(
+                "a {}x{} map is smaller than the station, {}x{} at least",
+                width, height, MIN_MAP_SIDE, MIN_MAP_SIDE
+            )));
+        }
+        shape
+            .validate(height)
+            .map_err(SimInitError::InvalidConfig)?;
+        if robot_counts.total() > config::max_robots() {
+            return Err(SimInitError::TooManyRobots {
+                requested: robot_counts.total(),
+                cap: config::max_robots(),
+            });
+        }
+        let profile = difficulty.profile();
+        config::set_energy_cost_percent(profile.energy_cost_percent);
+        // A degenerate map is replaced, the run then goes by the seed of the one kept
+        let (map, map_seed) = Map::generate_playable(
+            width,
+            height,
+            map_seed,
+            resource_seed,
+            profile.tiles_per_resource,
+            shape,
+            resource_catalog.clone(),
+        );
+        // Station and robots work on every layer at once
+        let height = map.height;
+        let dock = map.station_coords();
+        if map.is_station_walled_in() {
+            return Err(SimInitError::StationBlocked { dock });
+        }
 
-        let (main_sender, main_receiver) = mpsc::channel();
-        let map_arc = Arc::new(RwLock::new(map));
+        let (main_sender, main_receiver) = create_channel();
+        let topology = map.topology();
+        let map_arc = SharedMap::new(map);
 
-        let station = Station::new(main_sender.clone(), width, height);
+        let station = Station::docked_at(
+            main_sender.with_source(EventSource::Station),
+            width,
+            height,
+            dock,
+        );
+        station.data_mut().set_topology(topology);
+        station.data_mut().set_clock(clock.clone());
 
         let mut app = Self {
             map: map_arc,
-            exploration_robots: HashMap::new(),
-            collection_robots: HashMap::new(),
-            scientific_robots: HashMap::new(),
+            robots: RobotRegistry::new(),
             event_receiver: main_receiver,
             event_sender: main_sender,
-            robot_merge_senders: HashMap::new(),
+            last_sequences: HashMap::new(),
+            event_stats: EventStats::default(),
+            event_bus: EventBus::new(),
+            idle_monitor: IdleMonitor::new(config::IDLE_THRESHOLD_TICKS),
+            ledger: ResourceLedger::new(),
+            energy_spent: 0,
+            station_divergence: KnowledgeDivergence::default(),
+            kpis: KpiTracker::new(config::KPI_WINDOW_TICKS),
+            maintenance: MaintenanceSchedule::new(
+                config::MAINTENANCE_INTERVAL_TICKS,
+                config::WEAR_PERCENT_PER_INTERVAL,
+                config::MAX_WEAR_PERCENT,
+                config::MAINTENANCE_MAX_SHARE_PERCENT,
+            ),
+            maintenance_services: 0,
+            priorities: PriorityZones::new(),
+            rendezvous: RendezvousBook::new(),
+            joint_samples: 0,
+            merges_abandoned: 0,
+            last_merge_abandoned: None,
+            preset: SwarmPreset::default(),
+            science_targeting: ScienceTargeting::default(),
+            charging: ChargingBay::new(None, config::CHARGE_TICKS),
+            memory: MemoryUsage::default(),
+            memory_level: MemoryLevel::Fine,
+            memory_limit: MemorySettings::default(),
+            alarms: AlarmMonitor::new(AlarmSettings::default(), TICK_RATE),
+            station_energy_low: (0, false),
+            journal: EventJournal::new(),
+            exclusions: ExclusionZones::new(),
+            stock_balancer: StockBalancer::new(
+                config::STOCK_REBALANCE_TOLERANCE_PERCENT,
+                resource_catalog.consumables(),
+            ),
+            command_tracker: CommandTracker::new(
+                config::COMMAND_ACK_TIMEOUT_TICKS,
+                config::COMMAND_MAX_ATTEMPTS,
+            ),
+            idle_reassignments: 0,
+            formation: None,
+            squads: SquadRoster::new(),
+            patrols: HashMap::new(),
+            parked_robots: HashSet::new(),
+            exploration_complete: false,
+            retiring: false,
+            retired_robots: 0,
+            decommissioning: HashSet::new(),
+            conversions: HashMap::new(),
+            decommissioned_robots: 0,
+            refunded_minerals: 0,
+            battery_refits: 0,
+            worn_out_robots: 0,
+            completion_summary: None,
+            tick_limit: None,
+            tick: 0,
             station,
             collected_resources: HashMap::new(),
             scientific_data: 0,
@@ -74,90 +526,139 @@ impl App {
             explored_tiles: HashSet::new(),
             map_width: width,
             map_height: height,
+            map_seed,
+            resource_seed,
+            difficulty,
+            shape,
+            robot_counts,
+            trajectories: None,
+            drained_energy: 0,
+            module_catalog,
+            resource_catalog,
+            jitter_percent: config::jitter_percent(),
+            max_robots: config::max_robots(),
+            clock: clock.clone(),
+            next_robot_id: 0,
+            remote_station: None,
+            world_events: WorldEventScheduler::new(Scenario {
+                meteor_shower: profile.meteor_shower(resource_seed),
+                solar_activity: profile.solar_activity(resource_seed),
+                ..Scenario::default()
+            }),
+            storm_until: None,
+            solar_flare_until: None,
+            meteor_strikes: 0,
+            hostiles: HostileSystem::new(resource_seed, config::HOSTILE_MOVE_INTERVAL_TICKS),
+            salvage_sites: HashSet::new(),
         };
 
-        app.spawn_robots(1, 1, 1, map_seed.into());
-        app
+        app.spawn_robots(robot_counts, spawn_policy, app.robot_seed())?;
+        Ok(app)
     }
 
-    /// Spawns the specified number of each robot type at random valid locations.
+    /// Seed the starting robots are placed and fitted out with, derived from the map seed
+    pub fn robot_seed(&self) -> u64 {
+        self.map_seed.into()
+    }
+
+    /// The setup this run was started from, to play it again
+    /// Resource types of the run, the same its map and robots go by
+    pub fn resource_catalog(&self) -> &Arc<ResourceCatalog> {
+        &self.resource_catalog
+    }
+
+    pub fn game_setup(&self) -> GameSetup {
+        GameSetup {
+            width: self.map_width,
+            height: self.surface_height(),
+            map_seed: self.map_seed,
+            resource_seed: self.resource_seed,
+            robot_counts: self.robot_counts,
+            difficulty: self.difficulty,
+            shape: self.shape,
+        }
+    }
+
+    /// Spawns the specified number of each robot type at valid locations chosen by `policy`,
+    /// none if there are not enough.
     fn spawn_robots(
         &mut self,
-        exploration_count: usize,
-        collection_count: usize,
-        scientific_count: usize,
+        counts: RobotCounts,
+        policy: SpawnPolicy,
         seed: u64,
-    ) {
+    ) -> Result<(), SimInitError> {
         let mut rng = StdRng::seed_from_u64(seed);
-        let walkable_positions = self.find_walkable_spawn_positions();
+        let total_robots_to_spawn = counts.total();
+        let walkable_positions = match policy {
+            SpawnPolicy::Scattered => self.find_walkable_spawn_positions(),
+            SpawnPolicy::NearStation => self
+                .map
+                .read()
+                .free_tiles_near_station(total_robots_to_spawn),
+        };
 
-        let total_robots_to_spawn = exploration_count + collection_count + scientific_count;
         if walkable_positions.len() < total_robots_to_spawn {
             error!(
                 "Insufficient walkable spawn positions (need {}, found {}). Cannot spawn all requested robots.",
                 total_robots_to_spawn,
                 walkable_positions.len()
             );
-            panic!("Robot spawn failed: Insufficient space.");
+            return Err(SimInitError::NoSpawnSpace {
+                requested: total_robots_to_spawn,
+                available: walkable_positions.len(),
+            });
         }
 
-        let mut available_positions: Vec<(usize, usize)> = walkable_positions
-            .choose_multiple(&mut rng, total_robots_to_spawn)
-            .cloned()
-            .collect();
-
-        let mut current_id_counter = 0;
+        let mut available_positions: Vec<(usize, usize)> = match policy {
+            SpawnPolicy::Scattered => walkable_positions
+                .choose_multiple(&mut rng, total_robots_to_spawn)
+                .cloned()
+                .collect(),
+            // Closest tiles last, so the first robots popped start right next to the station
+            SpawnPolicy::NearStation => walkable_positions.into_iter().rev().collect(),
+        };
 
         // Spawn Exploration Robots
-        for _ in 0..exploration_count {
+        for _ in 0..counts.exploration {
             if let Some(pos) = available_positions.pop() {
-                self.spawn_robot_instance(
-                    &mut current_id_counter,
-                    pos,
-                    RobotType::Exploration,
-                    &mut rng,
-                );
+                self.spawn_robot_instance(pos, RobotType::Exploration, &mut rng);
             }
         }
 
         // Spawn Collection Robots
-        for _ in 0..collection_count {
+        for _ in 0..counts.collection {
             if let Some(pos) = available_positions.pop() {
-                self.spawn_robot_instance(
-                    &mut current_id_counter,
-                    pos,
-                    RobotType::Collection,
-                    &mut rng,
-                );
+                self.spawn_robot_instance(pos, RobotType::Collection, &mut rng);
             }
         }
 
         // Spawn Scientific Robots
-        for _ in 0..scientific_count {
+        for _ in 0..counts.scientific {
             if let Some(pos) = available_positions.pop() {
-                self.spawn_robot_instance(
-                    &mut current_id_counter,
-                    pos,
-                    RobotType::Scientific,
-                    &mut rng,
-                );
+                self.spawn_robot_instance(pos, RobotType::Scientific, &mut rng);
+            }
+        }
+
+        // Spawn Scout Robots
+        for _ in 0..counts.scout {
+            if let Some(pos) = available_positions.pop() {
+                self.spawn_robot_instance(pos, RobotType::Scout, &mut rng);
             }
         }
+        Ok(())
     }
 
     fn find_walkable_spawn_positions(&self) -> Vec<(usize, usize)> {
-        let map_guard = self
-            .map
-            .read()
-            .expect("Map lock poisoned during spawn pos search");
-        let (station_x, station_y) = (map_guard.width / 2, map_guard.height / 2);
+        let map_guard = self.map.read();
+        let (station_x, station_y) = (map_guard.width / 2, map_guard.layer_height / 2);
         let mut positions = Vec::new();
 
-        for y in 0..map_guard.height {
+        // Robots are dropped on the surface
+        for y in map_guard.layer_rows(0) {
             for x in 0..map_guard.width {
                 if !map_guard.is_obstacle(x, y)
                     && !map_guard.has_resource(x, y)
-                    && !(x == station_x && y == station_y)
+                    && (x != station_x || y != station_y)
                 {
                     positions.push((x, y));
                 }
@@ -168,221 +669,2778 @@ impl App {
 
     fn spawn_robot_instance(
         &mut self,
-        current_id_counter: &mut u32,
         position: (usize, usize),
         robot_type: RobotType,
         rng: &mut StdRng,
     ) {
-        let id = *current_id_counter;
         let (x, y) = position;
+        let robot_state = launcher::initial_state(robot_type, self.next_robot_id, x, y);
+        self.launch_robot(robot_type, robot_state, rng);
+    }
+
+    /// Starts a robot again from a saved state, keeping its id
+    pub fn spawn_restored(&mut self, robot_type: RobotType, robot_state: RobotState) {
+        let mut rng = StdRng::seed_from_u64(self.resource_seed + u64::from(robot_state.id));
+        self.launch_robot(robot_type, robot_state, &mut rng);
+    }
+
+    /// Fits the robot out, registers it and starts its thread; its id is taken from
+    /// `robot_state`, later robots get higher ones
+    fn launch_robot(&mut self, robot_type: RobotType, robot_state: RobotState, rng: &mut StdRng) {
+        let mut loadout = self.loadout_for(robot_type, rng);
+        let id = robot_state.id;
+        loadout.jitter = self.jitter_for(id);
+        loadout.decision_seed = Some(self.decision_seed_for(id));
+        self.next_robot_id = self.next_robot_id.max(id + 1);
 
         // Create dedicatedd channel for MergeComplete event for thi robot
         let (merge_sender, merge_receiver) = mpsc::channel();
-        self.robot_merge_senders.insert(id, merge_sender);
+        let (command_sender, command_receiver) = create_command_channel();
+        let gauge = InboxGauge::new();
+        let trace = if config::decision_traces() {
+            DecisionTrace::new(config::DECISION_TRACE_CAPACITY)
+        } else {
+            DecisionTrace::default()
+        };
+        self.idle_monitor.track(id, self.tick);
+        self.maintenance.track(id, robot_type, self.tick);
 
-        let map_clone = self.map.clone();
-        let event_sender_clone = self.event_sender.clone();
+        let mut entry = RobotEntry::new(
+            robot_type,
+            robot_state.clone(),
+            command_sender,
+            merge_sender,
+        );
+        entry.telemetry.collector_target = loadout.collector_target;
+        entry.telemetry.drill = loadout.has_drill();
+        entry.telemetry.inbox = gauge.clone();
+        entry.telemetry.trace = trace.clone();
+        entry.set_handle(launcher::launch(
+            robot_type,
+            robot_state,
+            &loadout,
+            RobotInbox {
+                merge_receiver,
+                command_receiver,
+                gauge,
+                trace,
+            },
+            self.event_sender.with_source(EventSource::Robot(id)),
+            self.map.clone(),
+            self.clock.clone(),
+        ));
+        self.robots.insert(entry);
+        if !self.exclusions.is_empty() {
+            let tiles = self.exclusions.tiles().collect();
+            self.send_command(id, RobotCommand::AvoidTiles(tiles));
+        }
+        info!("Spawned {:?} Robot {}", robot_type, id);
+    }
 
-        match robot_type {
-            RobotType::Exploration => {
-                let robot_state = RobotState::new(
-                    id,
-                    x,
-                    y,
-                    RobotStatus::Exploring,
-                    config::EXPLORATION_ROBOT_MAX_ENERGY,
-                );
-                let robot_logic = ExplorationRobot::new(
-                    robot_state.clone(),
-                    self.map_width,
-                    self.map_height,
-                    merge_receiver,
+    /// Makes the station and the robots launched from now on go by `clock`, e.g. a
+    /// simulated one in tests; robots already running keep theirs
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.station.data_mut().set_clock(clock.clone());
+        self.clock = clock;
+    }
+
+    /// Accepts robots running in other processes over TCP, returns the bound address
+    pub fn listen(&mut self, addr: &str) -> Result<SocketAddr, SimError> {
+        let remote_station = RemoteStation::listen(addr)?;
+        let local_addr = remote_station.local_addr();
+        self.attach_remote_station(remote_station);
+        Ok(local_addr)
+    }
+
+    /// Accepts remote robots on a listener opened earlier, e.g. by the previous run
+    pub fn attach_remote_station(&mut self, remote_station: RemoteStation) {
+        self.remote_station = Some(remote_station);
+    }
+
+    /// Takes the listener back, so the next run keeps accepting on the same address
+    pub fn take_remote_station(&mut self) -> Option<RemoteStation> {
+        self.remote_station.take()
+    }
+
+    /// Ends the run: every robot is hung up on, and its thread joined
+    pub fn shutdown(mut self) {
+        info!(
+            "Shutting down the run at tick {} with {} robots",
+            self.tick,
+            self.robots.len()
+        );
+        self.robots.shutdown();
+    }
+
+    /// Registers the robot processes that joined since the last tick, each on a free tile
+    fn accept_remote_robots(&mut self) {
+        let Some(remote_station) = &self.remote_station else {
+            return;
+        };
+        let joins: Vec<_> = std::iter::from_fn(|| remote_station.try_join()).collect();
+        for join in joins {
+            let robot_type = join.robot_type;
+            if self.robots.len() >= self.max_robots {
+                warn!(
+                    "Turning away remote robot from {}: the swarm is at its cap of {}",
+                    join.peer, self.max_robots
                 );
-                self.exploration_robots.insert(id, robot_state);
-                robot_logic.start(event_sender_clone, map_clone);
+                continue;
+            }
+            let Some((x, y)) = self.free_spawn_position() else {
+                warn!("No free tile for remote robot from {}", join.peer);
+                continue;
+            };
+            let mut rng = StdRng::seed_from_u64(self.tick);
+            let mut loadout = self.loadout_for(robot_type, &mut rng);
+            let id = self.next_robot_id;
+            loadout.jitter = self.jitter_for(id);
+            loadout.decision_seed = Some(self.decision_seed_for(id));
+            let assignment = Assignment {
+                id,
+                x,
+                y,
+                map_width: self.map_width,
+                map_height: self.surface_height(),
+                shape: self.shape,
+                continuous: config::continuous_movement(),
+                map_seed: self.map_seed,
+                resource_seed: self.resource_seed,
+                difficulty: self.difficulty,
+                resources: (*self.resource_catalog).clone(),
+                loadout: loadout.clone(),
+                tick: self.tick,
+            };
+            let peer = join.peer;
+            let gauge = InboxGauge::new();
+            let (command_sender, merge_sender) = match join.accept(
+                assignment,
+                self.event_sender.with_source(EventSource::Station),
+                self.event_sender.clock().clone(),
+                gauge.clone(),
+            ) {
+                Ok(senders) => senders,
+                Err(e) => {
+                    warn!("Failed to assign remote robot from {}: {}", peer, e);
+                    continue;
+                }
+            };
+            self.next_robot_id += 1;
+            self.idle_monitor.track(id, self.tick);
+            self.maintenance.track(id, robot_type, self.tick);
+            let mut entry = RobotEntry::new(
+                robot_type,
+                launcher::initial_state(robot_type, id, x, y),
+                command_sender,
+                merge_sender,
+            );
+            entry.telemetry.drill = loadout.has_drill();
+            entry.telemetry.collector_target = loadout.collector_target;
+            entry.telemetry.inbox = gauge;
+            self.robots.insert(entry);
+            info!("Remote {:?} Robot {} joined from {}", robot_type, id, peer);
+        }
+    }
+
+    /// Releases dust devils that wander the map and drain the robots they reach
+    pub fn spawn_hostiles(&mut self, count: usize) -> usize {
+        let mut map = self.map.write();
+        self.hostiles
+            .spawn(count, config::HOSTILE_MIN_STATION_DISTANCE, &mut map)
+    }
+
+    /// Schedules the scenario's world events; the difficulty's meteors and solar flares keep
+    /// coming unless the scenario brings its own
+    pub fn load_scenario(&mut self, mut scenario: Scenario) {
+        let profile = self.difficulty.profile();
+        if scenario.meteor_shower.is_none() {
+            scenario.meteor_shower = profile.meteor_shower(self.resource_seed);
+        }
+        if scenario.solar_activity.is_none() {
+            scenario.solar_activity = profile.solar_activity(self.resource_seed);
+        }
+        if self.tick > 0 {
+            // A resumed run already went through the events up to its tick
+            scenario
+                .events
+                .retain(|scheduled| scheduled.tick > self.tick);
+        }
+        self.world_events = WorldEventScheduler::new(scenario);
+    }
+
+    /// Tick of the next scheduled world event
+    pub fn next_world_event_tick(&self) -> Option<u64> {
+        self.world_events.next_tick()
+    }
+
+    /// Ticks left before an announced solar flare breaks out
+    pub fn solar_flare_warning(&self) -> Option<u64> {
+        self.world_events
+            .next_flare_tick()
+            .map(|tick| tick.saturating_sub(self.tick))
+            .filter(|&ticks| ticks <= config::FLARE_WARNING_TICKS)
+    }
+
+    fn fire_world_events(&mut self) {
+        for event in self
+            .world_events
+            .due(self.tick, self.map_width, self.surface_height())
+        {
+            self.apply_world_event(event);
+        }
+        self.end_solar_flare();
+
+        let Some(until) = self.storm_until else {
+            return;
+        };
+        if self.tick >= until {
+            info!("Storm is over");
+            self.storm_until = None;
+        } else if self
+            .tick
+            .is_multiple_of(config::STORM_RECALL_INTERVAL_TICKS)
+        {
+            self.recall_robots_in_the_open();
+        }
+    }
+
+    /// Brings the robots' radio back once the flare is over. The events they held back
+    /// arrive at once, and the commands they could not acknowledge get a fresh timeout.
+    fn end_solar_flare(&mut self) {
+        if self.solar_flare_until.is_none_or(|until| self.tick < until) {
+            return;
+        }
+        self.solar_flare_until = None;
+        match self.event_sender.restore_link() {
+            Ok(held) => info!("Solar flare is over, {} held events coming in", held),
+            Err(e) => warn!("Solar flare is over, but held events were lost: {}", e),
+        }
+        self.command_tracker.restart_timers(self.tick);
+    }
+
+    /// Takes the difficulty's upkeep from the station's energy stock
+    fn drain_station(&mut self) {
+        let drain = self.difficulty.profile().station_drain;
+        if drain == 0
+            || !self
+                .tick
+                .is_multiple_of(config::STATION_DRAIN_INTERVAL_TICKS)
+        {
+            return;
+        }
+        if let Some(energy) = self.collected_resources.get_mut(&ResourceType::ENERGY) {
+            let drained = drain.min(*energy);
+            *energy -= drained;
+            self.drained_energy += drained;
+        }
+    }
+
+    /// Balance of every collected resource type. The map's side is left out when robots
+    /// also run on remote maps.
+    pub fn ledger_audit(&self) -> Vec<LedgerEntry> {
+        if self.remote_station.is_some() {
+            return self.ledger.audit(None);
+        }
+        let map = self.map.read();
+        self.ledger.audit(Some(map.taken()))
+    }
 
-                info!("Spawned Exploration Robot {}", id);
+    /// Logs every resource type whose units were counted twice or went missing between the
+    /// map, the robots' cargo and the station's stock
+    fn audit_ledger(&self) {
+        if !self
+            .tick
+            .is_multiple_of(config::LEDGER_AUDIT_INTERVAL_TICKS)
+        {
+            return;
+        }
+        for entry in self.ledger_audit() {
+            if !entry.balances() {
+                error!(
+                    "Ledger: {:?} credited {} but delivered {}, carried {}, lost {} and dropped {}",
+                    entry.resource_type,
+                    entry.credited,
+                    entry.delivered,
+                    entry.carried,
+                    entry.lost,
+                    entry.dropped
+                );
             }
-            RobotType::Collection => {
-                let robot_state = RobotState::new(
-                    id,
+            match entry.removed {
+                Some(removed) if removed < entry.credited => error!(
+                    "Ledger: {:?} credited {} but only {} taken from the map",
+                    entry.resource_type, entry.credited, removed
+                ),
+                // Collections still on their way to the app are not credited yet
+                Some(removed) if removed > entry.credited => warn!(
+                    "Ledger: {:?} taken from the map {} but credited {}",
+                    entry.resource_type, removed, entry.credited
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    /// Tells the station where the robots are and whether a storm is raging, so docking
+    /// robots learn of them with the rest of the station's knowledge
+    fn track_entities(&mut self) {
+        let mut sightings: Vec<Sighting> = self
+            .robots
+            .iter()
+            .map(|entry| Sighting {
+                kind: EntityKind::Robot(entry.state.id),
+                x: entry.state.x,
+                y: entry.state.y,
+                tick: self.tick,
+            })
+            .collect();
+        if self.storm_until.is_some() {
+            let (x, y) = self.map.read().station_coords();
+            sightings.push(Sighting {
+                kind: EntityKind::Storm,
+                x,
+                y,
+                tick: self.tick,
+            });
+        }
+        self.station.track_entities(&sightings, self.tick);
+    }
+
+    fn apply_world_event(&mut self, event: WorldEvent) {
+        match event {
+            WorldEvent::Meteor { x, y, radius } => {
+                let robot_tiles: HashSet<(usize, usize)> = self
+                    .robots
+                    .iter()
+                    .map(|entry| (entry.state.x, entry.state.y))
+                    .collect();
+                let crater = self.map.write().add_crater(x, y, radius, &robot_tiles);
+                info!(
+                    "Meteor strike at ({}, {}): {} new obstacles",
                     x,
                     y,
-                    RobotStatus::Collecting,
-                    config::COLLECTION_ROBOT_MAX_ENERGY,
-                );
-                let mut robot_logic = CollectionRobot::new(
-                    robot_state.clone(),
-                    self.map_width,
-                    self.map_height,
-                    merge_receiver,
+                    crater.len()
                 );
+                self.meteor_strikes += 1;
 
-                // Assign target resource type
-                let resource_types = [ResourceType::Energy, ResourceType::Minerals];
-                if let Some(target) = resource_types.choose(rng) {
-                    robot_logic.set_target_resource(target.clone());
+                // Whatever was known around the impact is stale now
+                let impact = self.map.read().layer_tiles_within(x, y, radius);
+                self.station.invalidate_tiles(&impact);
+                for id in self.robots.ids() {
+                    self.send_command(
+                        id,
+                        RobotCommand::MeteorImpact {
+                            x,
+                            y,
+                            radius,
+                            damage: config::METEOR_DAMAGE,
+                        },
+                    );
+                }
+                if self.exploration_complete {
+                    self.exploration_complete = false;
+                    let survey_point = {
+                        let map = self.map.read();
+                        map.layer_tiles_within(x, y, radius + 1)
+                            .into_iter()
+                            .find(|&(tx, ty)| !map.is_obstacle(tx, ty))
+                    };
+                    if let Some(tile) = survey_point {
+                        info!("Sending parked explorers to survey the crater");
+                        self.send_parked_explorers_to(tile);
+                    }
                 }
-                self.collection_robots.insert(id, robot_state);
-                robot_logic.start(event_sender_clone, map_clone);
-                info!("Spawned Collection Robot {}", id);
             }
-            RobotType::Scientific => {
-                let robot_state = RobotState::new(
-                    id,
-                    x,
-                    y,
-                    RobotStatus::Analyzing,
-                    config::SCIENTIFIC_ROBOT_MAX_ENERGY,
-                );
-                let mut robot_logic = ScientificRobot::new(
-                    robot_state.clone(),
-                    self.map_width,
-                    self.map_height,
-                    merge_receiver,
-                );
-
-                // Assign modules
-                let scientific_modules = vec![
-                    ("Chemical Analyzer", 15, 2),
-                    ("Drill", 10, 3),
-                    ("High-Res Camera", 20, 1),
-                    ("Spectrometer", 25, 2),
-                    ("Sample Container", 5, 1),
-                ];
-                if !scientific_modules.is_empty() {
-                    let module_count = rng.random_range(1..=scientific_modules.len().min(3));
-                    for &(name, bonus, cost) in
-                        scientific_modules.choose_multiple(rng, module_count)
+            WorldEvent::ResourceVein {
+                x,
+                y,
+                radius,
+                resource,
+                amount,
+            } => {
+                let Some(resource_type) = self.resource_catalog.from_name(&resource) else {
+                    warn!("No {} in the run's resource types, vein skipped", resource);
+                    return;
+                };
+                let mut map = self.map.write();
+                let mut tiles = 0;
+                let rows = map.layer_rows(map.layer_of(y));
+                let (top, bottom) = (y.saturating_sub(radius).max(rows.start), y + radius);
+                for ty in top..=bottom.min(rows.end.saturating_sub(1)) {
+                    for tx in
+                        x.saturating_sub(radius)..=(x + radius).min(map.width.saturating_sub(1))
                     {
-                        robot_logic.add_module(name, bonus, cost);
+                        if !map.is_obstacle(tx, ty)
+                            && !map.is_station(tx, ty)
+                            && !map.has_resource(tx, ty)
+                        {
+                            map.add_resource(tx, ty, resource_type, amount);
+                            tiles += 1;
+                        }
                     }
                 }
-                self.scientific_robots.insert(id, robot_state);
-                robot_logic.start(event_sender_clone, map_clone);
-                info!("Spawned Scientific Robot {}", id);
+                info!(
+                    "{} vein appeared at ({}, {}) over {} tiles",
+                    resource, x, y, tiles
+                );
+            }
+            WorldEvent::Storm { duration } => {
+                info!("Storm for {} ticks, recalling every robot", duration);
+                self.storm_until = Some(self.tick + duration);
+                self.recall_robots_in_the_open();
+            }
+            WorldEvent::SolarFlare {
+                duration,
+                scramble_percent,
+            } => {
+                info!(
+                    "Solar flare for {} ticks, the robots' radio is down",
+                    duration
+                );
+                for id in self.robots.ids() {
+                    let seed = self.tick + u64::from(id);
+                    self.send_command(
+                        id,
+                        RobotCommand::SolarFlare {
+                            scramble_percent,
+                            seed,
+                        },
+                    );
+                }
+                let until = self.tick + duration;
+                self.solar_flare_until =
+                    Some(self.solar_flare_until.map_or(until, |u| u.max(until)));
+                self.event_sender.cut_link();
+            }
+            WorldEvent::Hazard {
+                x,
+                y,
+                radius,
+                duration,
+            } => {
+                info!(
+                    "Hazard at ({}, {}), closing the area for {} ticks",
+                    x, y, duration
+                );
+                let area = self.area_around((x, y), radius);
+                self.exclusions.add_hazard(area, self.tick + duration);
+                self.publish_exclusions(&[]);
             }
         }
-        *current_id_counter += 1;
     }
 
-    pub fn update(&mut self) {
-        while let Ok(event) = self.event_receiver.try_recv() {
-            if matches!(event, RobotEvent::ArrivedAtStation { .. }) {
-                self.station.process_event(&event);
-            }
+    /// Releases the parked explorers to go and survey `(x, y)`
+    fn send_parked_explorers_to(&mut self, (x, y): (usize, usize)) {
+        let parked: Vec<u32> = self.parked_robots.iter().copied().collect();
+        for id in parked {
+            self.dispatch(id, RobotCommand::MoveTo { x, y });
+        }
+    }
 
-            match event {
-                RobotEvent::ExplorationData { id, x, y, .. } => {
-                    if let Some(robot) = self.get_robot_state_mut(id) {
-                        robot.x = x;
-                        robot.y = y;
-                    }
+    /// Sends home the robots neither docked nor already on their way back
+    fn recall_robots_in_the_open(&mut self) {
+        let exposed: Vec<u32> = self
+            .robots
+            .iter()
+            .filter(|entry| {
+                !matches!(
+                    entry.state.status,
+                    RobotStatus::AtStation | RobotStatus::ReturningToStation
+                )
+            })
+            .map(|entry| entry.state.id)
+            .collect();
+        for id in exposed {
+            self.send_command(id, RobotCommand::ReturnToStation);
+        }
+    }
 
-                    if self.explored_tiles.insert((x, y)) {
-                        self.total_explored += 1;
-                    }
-                }
-                RobotEvent::CollectionData {
-                    id,
-                    x,
-                    y,
-                    resource_type,
-                    amount,
-                } => {
-                    if let Some(robot) = self.get_robot_state_mut(id) {
-                        robot.x = x;
-                        robot.y = y;
-                    }
+    /// Spawns one more robot on the first free tile, returns its id. Refused once the swarm
+    /// is at its cap.
+    pub fn spawn_robot(&mut self, robot_type: RobotType) -> Result<u32, SimError> {
+        if self.robots.len() >= self.max_robots {
+            return Err(SimError::RobotCap(self.max_robots));
+        }
+        let position = self.free_spawn_position().ok_or(SimError::NoFreeTile)?;
+        let id = self.next_robot_id;
+        let mut rng = StdRng::seed_from_u64(self.tick + u64::from(id));
+        self.spawn_robot_instance(position, robot_type, &mut rng);
+        Ok(id)
+    }
 
-                    if let Some(res_type) = resource_type {
-                        if amount > 0 {
-                            *self.collected_resources.entry(res_type).or_insert(0) += amount;
-                        }
-                    }
+    /// Debug tools: the station learns every tile at once, the robots on their next merge.
+    /// Returns how many tiles were new to the swarm.
+    #[cfg(feature = "debug-tools")]
+    pub fn reveal_map(&mut self) -> usize {
+        let knowledge = self.map.with_read(|map| {
+            let mut knowledge = RobotKnowledge::new(map.width, map.height);
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    knowledge.observe_and_update(x, y, map);
                 }
-                RobotEvent::ScienceData {
-                    id, x, y, amount, ..
-                } => {
-                    if let Some(robot) = self.get_robot_state_mut(id) {
-                        robot.x = x;
-                        robot.y = y;
-                    }
+            }
+            knowledge
+        });
+        // No robot saw these tiles, the id only shows in the logs
+        self.station
+            .data_mut()
+            .merge_robot_knowledge(u32::MAX, &knowledge);
+        let revealed = knowledge
+            .tiles()
+            .filter(|&(tile, _)| self.explored_tiles.insert(tile))
+            .count();
+        self.total_explored += revealed;
+        revealed
+    }
 
-                    self.scientific_data += amount as u64;
-                }
-                RobotEvent::LowEnergy { id, remaining } => {
-                    if let Some(robot) = self.get_robot_state_mut(id) {
-                        robot.energy = remaining;
-                    } else {
-                        warn!("Received LowEnergy event for unknown robot ID: {}", id);
-                    }
-                }
-                RobotEvent::MergeComplete { id, .. } => {
-                    let robot_type = if self.exploration_robots.contains_key(&id) {
-                        Some(RobotType::Exploration)
-                    } else if self.collection_robots.contains_key(&id) {
-                        Some(RobotType::Collection)
-                    } else if self.scientific_robots.contains_key(&id) {
-                        Some(RobotType::Scientific)
-                    } else {
-                        None
-                    };
+    /// Debug tools: puts a deposit of `amount` on a free walkable tile
+    #[cfg(feature = "debug-tools")]
+    pub fn place_resource(
+        &mut self,
+        (x, y): (usize, usize),
+        resource_type: ResourceType,
+        amount: u32,
+    ) -> Result<(), MapError> {
+        self.map.with_write(|map| {
+            map.check_walkable(x, y)?;
+            if map.is_station(x, y) || map.is_shaft(x, y) {
+                return Err(MapError::Occupied { x, y });
+            }
+            map.add_resource(x, y, resource_type, amount);
+            Ok(())
+        })
+    }
 
-                    if let Some(robot) = self.get_robot_state_mut(id) {
-                        robot.energy = robot.max_energy;
-                        robot.collected_resources.clear();
+    /// Rows of the surface, the only layer meteors reach
+    fn surface_height(&self) -> usize {
+        self.map.read().layer_height
+    }
 
-                        match robot_type {
-                            Some(RobotType::Exploration) => {
-                                robot.status = RobotStatus::Exploring;
-                            }
-                            Some(RobotType::Collection) => {
-                                robot.status = RobotStatus::Collecting;
-                            }
-                            Some(RobotType::Scientific) => {
-                                robot.status = RobotStatus::Analyzing;
-                            }
-                            None => {
-                                warn!("Robot type not found for ID: {}", id);
-                            }
-                        }
-                    } else {
-                        warn!("Received MergeComplete event for unknown robot ID: {}", id);
-                    }
-                }
-                RobotEvent::ArrivedAtStation { id, .. } => {
-                    if let Some(robot) = self.get_robot_state_mut(id) {
-                        robot.status = RobotStatus::AtStation;
-                    }
-                }
-                RobotEvent::Shutdown { id, reason } => {
-                    info!("Robot {} shutting down: {}", id, reason);
+    /// First walkable tile with neither a resource nor a robot on it
+    fn free_spawn_position(&self) -> Option<(usize, usize)> {
+        let occupied: HashSet<(usize, usize)> = self
+            .robots
+            .iter()
+            .map(|entry| (entry.state.x, entry.state.y))
+            .collect();
+        self.find_walkable_spawn_positions()
+            .into_iter()
+            .find(|position| !occupied.contains(position))
+    }
 
-                    self.exploration_robots.remove(&id);
-                    self.collection_robots.remove(&id);
-                    self.scientific_robots.remove(&id);
-                    self.robot_merge_senders.remove(&id);
-                }
-                RobotEvent::ReturnToBase { id } => {
-                    if let Some(robot) = self.get_robot_state_mut(id) {
-                        robot.status = RobotStatus::ReturningToStation;
-                    } else {
-                        warn!("Received ReturnToBase event for unknown robot ID: {}", id);
+    /// Spread of robot `id`'s parameters, from its own seed so a replay strays them alike
+    fn jitter_for(&self, id: u32) -> Option<ParameterJitter> {
+        let percent = self.jitter_percent;
+        (percent > 0).then(|| ParameterJitter {
+            percent,
+            seed: self.robot_seed().wrapping_add(u64::from(id)),
+        })
+    }
+
+    /// Seed of robot `id`'s random moves and pauses, apart from its jitter's
+    fn decision_seed_for(&self, id: u32) -> u64 {
+        self.robot_seed().rotate_left(32) ^ u64::from(id)
+    }
+
+    /// Picks what a new robot is fitted with: collectors gather the resource the station
+    /// needs most, scientists get random modules from the catalog
+    fn loadout_for(&self, robot_type: RobotType, rng: &mut StdRng) -> RobotLoadout {
+        let mut loadout = RobotLoadout {
+            preset: self.preset,
+            ..RobotLoadout::default()
+        };
+        match robot_type {
+            RobotType::Exploration | RobotType::Scout => {}
+            RobotType::Collection => {
+                loadout.collector_target = Some(
+                    self.stock_balancer
+                        .neediest(&self.collected_resources, &self.robots.collector_targets()),
+                );
+                // Every other collector becomes a miner when there are caves to mine
+                let drills = self
+                    .robots
+                    .iter()
+                    .filter(|entry| entry.telemetry.drill)
+                    .count();
+                if let Some(drill) = self.module_catalog.collector_drill() {
+                    if self.shape.cave_layers > 0
+                        && drills * 2 <= self.robots.count_of(RobotType::Collection)
+                    {
+                        loadout.modules.push(drill.clone());
                     }
                 }
             }
+            RobotType::Scientific => {
+                loadout.science_targeting = self.science_targeting;
+                let scientific_modules = self.module_catalog.for_robot_type(RobotType::Scientific);
+                let max_modules = scientific_modules
+                    .len()
+                    .min(self.module_catalog.max_per_robot);
+                if max_modules > 0 {
+                    let module_count = rng.random_range(1..=max_modules);
+                    loadout.modules = scientific_modules
+                        .choose_multiple(rng, module_count)
+                        .map(|module| (*module).clone())
+                        .collect();
+                }
+            }
         }
+        loadout
     }
 
-    /// Gets a mutable reference to a robot's state regardless of its type.
-    fn get_robot_state_mut(&mut self, robot_id: u32) -> Option<&mut RobotState> {
-        if let Some(robot) = self.exploration_robots.get_mut(&robot_id) {
-            Some(robot)
-        } else if let Some(robot) = self.collection_robots.get_mut(&robot_id) {
-            Some(robot)
-        } else {
-            self.scientific_robots.get_mut(&robot_id)
+    /// Turns map lock recoveries since the last tick into an event, so a panicking thread
+    /// shows up in the log and on the event bus
+    fn report_map_recoveries(&self) {
+        let recoveries = self.map.take_recoveries();
+        if recoveries > 0 {
+            let _ = self
+                .event_sender
+                .send(RobotEvent::MapLockRecovered { recoveries });
         }
     }
+
+    /// Leaves the cargo of robot `id`, stopping in the field, as salvage on its tile, or on
+    /// a tile next to it when that one holds another deposit. The stock gives back what the
+    /// cargo was credited, since collecting the salvage credits it again. Cargo with nowhere
+    /// to go, or stopping at the station, is lost.
+    fn drop_cargo(&mut self, id: u32) {
+        let Some((x, y)) = self
+            .robots
+            .get(id)
+            .map(|entry| (entry.state.x, entry.state.y))
+        else {
+            return;
+        };
+        let cargo = self.ledger.cargo_of(id);
+        if cargo.is_empty() || self.map.read().is_station(x, y) {
+            return;
+        }
+
+        let mut dropped = Vec::new();
+        self.map.with_write(|map| {
+            let (width, height) = (map.width, map.height);
+            for (resource_type, amount) in cargo {
+                let amount = u32::try_from(amount).unwrap_or(u32::MAX);
+                let tile = std::iter::once((x, y))
+                    .chain(Map::valid_neighbors(x, y, width, height))
+                    .find(|&(tx, ty)| map.drop_salvage(tx, ty, resource_type, amount));
+                if let Some(tile) = tile {
+                    dropped.push((tile, resource_type, amount));
+                }
+            }
+        });
+
+        for ((x, y), resource_type, amount) in dropped {
+            self.ledger
+                .record_drop(id, &resource_type, u64::from(amount));
+            if let Some(stock) = self.collected_resources.get_mut(&resource_type) {
+                *stock = stock.saturating_sub(amount);
+            }
+            self.salvage_sites.insert((x, y));
+            // Docking collectors learn of the salvage once the tile is seen again
+            self.station.invalidate_tiles(&[(x, y)]);
+            let _ = self.event_sender.send(RobotEvent::SalvageDropped {
+                id,
+                x,
+                y,
+                resource_type,
+                amount,
+            });
+        }
+    }
+
+    /// Reports robot `id` collecting from a salvage site, and forgets the site once emptied
+    fn recover_salvage(
+        &mut self,
+        id: u32,
+        (x, y): (usize, usize),
+        resource_type: &ResourceType,
+        amount: u32,
+    ) {
+        if !self.salvage_sites.contains(&(x, y)) {
+            return;
+        }
+        if !self.map.read().is_salvage(x, y) {
+            self.salvage_sites.remove(&(x, y));
+        }
+        let _ = self.event_sender.send(RobotEvent::SalvageRecovered {
+            id,
+            x,
+            y,
+            resource_type: *resource_type,
+            amount,
+        });
+    }
+
+    /// Hands the station's merge and the robot's brief to the robot waiting at the dock,
+    /// through its own channel. The station only reports merges to the app, so this is their
+    /// one way to the robot. Returns whether the robot got them.
+    fn deliver_merge(
+        &self,
+        id: u32,
+        merged_knowledge: RobotKnowledge,
+        brief: Box<DockingBrief>,
+    ) -> bool {
+        let Some(entry) = self.robots.get(id) else {
+            return false;
+        };
+        let event = RobotEvent::MergeComplete {
+            id,
+            merged_knowledge,
+            brief,
+        };
+        let delivered = entry.send_merge(event);
+        if !delivered {
+            warn!("Robot {} stopped before its merge was delivered", id);
+        }
+        delivered
+    }
+
+    /// Registers a subscriber notified of every event processed by `update`
+    pub fn subscribe(&mut self, subscriber: Box<dyn EventSubscriber>) {
+        self.event_bus.subscribe(subscriber);
+    }
+
+    /// Sender for injecting events from outside the simulation; they are processed by the
+    /// next `update`
+    pub fn event_sender(&self) -> EventSender {
+        self.event_sender.clone()
+    }
+
+    /// Switches the swarm to `preset`: robots running now are told over their command
+    /// channel, robots launched from now on start with it
+    pub fn set_preset(&mut self, preset: SwarmPreset) {
+        if preset == self.preset {
+            return;
+        }
+        info!("Swarm preset: {}", preset.label());
+        self.preset = preset;
+        for id in self.robots.ids() {
+            self.send_command(id, RobotCommand::SetPreset(preset));
+        }
+    }
+
+    /// Sends a command to a single robot, returns false if the robot is unknown or gone.
+    /// The command is resent until the robot acknowledges or rejects it.
+    pub fn send_command(&mut self, robot_id: u32, command: RobotCommand) -> bool {
+        let envelope = self.command_tracker.register(robot_id, command, self.tick);
+        let command_id = envelope.id;
+        let sent = self.robots.send_command(robot_id, envelope);
+        if !sent {
+            self.command_tracker.cancel(command_id);
+        }
+        sent
+    }
+
+    /// Resends overdue commands and gives up on those out of attempts
+    fn retry_commands(&mut self) {
+        // Acknowledgements are held back with the robots' other events during a flare
+        if self.solar_flare_until.is_some() {
+            return;
+        }
+        for overdue in self.command_tracker.overdue(self.tick) {
+            match overdue {
+                Overdue::Retry(robot_id, envelope) => {
+                    debug!(
+                        "Resending command {} ({:?}) to robot {}",
+                        envelope.id, envelope.command, robot_id
+                    );
+                    self.robots.send_command(robot_id, envelope);
+                }
+                Overdue::Failed(robot_id, envelope) => {
+                    warn!(
+                        "Robot {} never acknowledged command {} ({:?})",
+                        robot_id, envelope.id, envelope.command
+                    );
+                }
+            }
+        }
+    }
+
+    /// Sends a command that puts a robot back to work, releasing it from parking
+    fn dispatch(&mut self, robot_id: u32, command: RobotCommand) -> bool {
+        let releases = match &command {
+            RobotCommand::MoveTo { .. } => true,
+            RobotCommand::Patrol(route) => !route.is_empty(),
+            _ => false,
+        };
+        let sent = self.send_command(robot_id, command);
+        if sent && releases {
+            self.parked_robots.remove(&robot_id);
+        }
+        sent
+    }
+
+    /// Marks the tiles around `center` as high priority, or clears them if `center` was marked
+    pub fn toggle_priority_area(&mut self, center: (usize, usize)) {
+        let area = self.area_around(center, config::PRIORITY_AREA_RADIUS);
+        self.priorities.toggle_area(center, area);
+    }
+
+    /// Closes the tiles around `center` to every robot, or reopens them if `center` was closed
+    pub fn toggle_exclusion_area(&mut self, center: (usize, usize)) {
+        let area = self.area_around(center, config::EXCLUSION_AREA_RADIUS);
+        let reopened = if self.exclusions.toggle_area(center, area.clone()) {
+            Vec::new()
+        } else {
+            area
+        };
+        self.publish_exclusions(&reopened);
+    }
+
+    /// Tiles within `radius` of `center` on its layer, the station excepted
+    fn area_around(&self, center: (usize, usize), radius: usize) -> Vec<(usize, usize)> {
+        let map = self.map.read();
+        map.layer_tiles_within(center.0, center.1, radius)
+            .into_iter()
+            .filter(|&(x, y)| !map.is_station(x, y))
+            .collect()
+    }
+
+    /// Hands the no-go tiles to the station, for robots docking later, and to every robot now.
+    /// Once exploration was complete, parked explorers go survey the `reopened` tiles.
+    fn publish_exclusions(&mut self, reopened: &[(usize, usize)]) {
+        let tiles: Vec<(usize, usize)> = self.exclusions.tiles().collect();
+        self.station.set_exclusions(tiles.iter().copied());
+        for id in self.robots.ids() {
+            self.send_command(id, RobotCommand::AvoidTiles(tiles.clone()));
+        }
+        if !self.exploration_complete {
+            return;
+        }
+        let survey_point = {
+            let map = self.map.read();
+            reopened
+                .iter()
+                .copied()
+                .find(|&(x, y)| !map.is_obstacle(x, y))
+        };
+        if let Some(tile) = survey_point {
+            self.exploration_complete = false;
+            info!("Sending parked explorers to survey the reopened tiles");
+            self.send_parked_explorers_to(tile);
+        }
+    }
+
+    /// Closes hazard tiles whose time ran out
+    fn expire_exclusions(&mut self) {
+        let reopened = self.exclusions.expire(self.tick);
+        if !reopened.is_empty() {
+            info!("{} no-go tiles reopened", reopened.len());
+            self.publish_exclusions(&reopened);
+        }
+    }
+
+    /// Makes the scientists launched from now on rank science deposits by `targeting`
+    pub fn set_science_targeting(&mut self, targeting: ScienceTargeting) {
+        self.science_targeting = targeting;
+    }
+
+    /// Makes docked robots queue for `slots` chargers, or charge all at once with `None`
+    pub fn set_chargers(&mut self, slots: Option<usize>) {
+        self.charging = ChargingBay::new(slots, self.charging.charge_ticks());
+    }
+
+    /// Serves the robots waiting for a charger by `policy` from now on
+    pub fn set_charging_policy(&mut self, policy: ChargingPolicy) {
+        self.charging.set_policy(policy);
+    }
+
+    /// Lets only the `budget` most important changed tiles through each way when a robot
+    /// docks, or every tile with `None`
+    pub fn set_merge_bandwidth(&mut self, budget: Option<usize>) {
+        self.station.set_merge_bandwidth(budget);
+    }
+
+    pub fn merge_bandwidth(&self) -> Option<usize> {
+        self.station
+            .data_manager
+            .read()
+            .ok()
+            .and_then(|data_manager| data_manager.bandwidth())
+    }
+
+    /// Known share of the map and how far the deposits have been worked, as the station sees
+    /// them
+    pub fn knowledge_coverage(&self) -> KnowledgeCoverage {
+        self.station
+            .data_manager
+            .read()
+            .map(|data_manager| data_manager.coverage())
+            .unwrap_or_default()
+    }
+
+    /// The station's most recent merges, oldest first
+    pub fn merge_log(&self) -> Vec<MergeRecord> {
+        self.station
+            .data_manager
+            .read()
+            .map(|data_manager| data_manager.merge_log().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Disagreements between robot reports met by the station so far
+    pub fn conflict_stats(&self) -> ConflictStats {
+        self.station
+            .data_manager
+            .read()
+            .map(|data_manager| data_manager.conflict_stats())
+            .unwrap_or_default()
+    }
+
+    /// Changed tiles left out of merges so far for lack of bandwidth
+    pub fn deferred_deltas(&self) -> u64 {
+        self.station
+            .data_manager
+            .read()
+            .map(|data_manager| data_manager.deferred_deltas())
+            .unwrap_or(0)
+    }
+
+    /// Sends a robot home to be dismantled: it docks, stops, and part of its build cost in
+    /// minerals is refunded to the station. Returns false for unknown robots.
+    pub fn decommission(&mut self, robot_id: u32) -> bool {
+        if self.decommissioning.contains(&robot_id) {
+            return true;
+        }
+        if self.robot_type_of(robot_id).is_none()
+            || !self.send_command(robot_id, RobotCommand::Retire)
+        {
+            return false;
+        }
+        info!("Decommissioning robot {}", robot_id);
+        self.decommissioning.insert(robot_id);
+        self.patrols.remove(&robot_id);
+        self.parked_robots.remove(&robot_id);
+        if let Some(formation) = self.formation.as_mut() {
+            formation.remove_member(robot_id);
+        }
+        true
+    }
+
+    pub fn is_decommissioning(&self, robot_id: u32) -> bool {
+        self.decommissioning.contains(&robot_id)
+    }
+
+    /// Swaps a robot's behavior at the station, e.g. to make an explorer a collector once the
+    /// map is known: the robot docks, its loop stops and a loop of `robot_type` takes over
+    /// with the same id and state
+    pub fn convert_robot(&mut self, robot_id: u32, robot_type: RobotType) -> Result<(), SimError> {
+        let current = self
+            .robot_type_of(robot_id)
+            .ok_or(SimError::UnknownRobot(robot_id))?;
+        let refused = |reason| SimError::ConversionRefused {
+            id: robot_id,
+            reason,
+        };
+        if current == robot_type {
+            return Err(refused("it already runs that behavior"));
+        }
+        if self.retiring || self.decommissioning.contains(&robot_id) {
+            return Err(refused("it is shutting down"));
+        }
+        // Already on its way, only the new behavior changes
+        if self.conversions.insert(robot_id, robot_type).is_some() {
+            return Ok(());
+        }
+        if !self.send_command(robot_id, RobotCommand::Retire) {
+            self.conversions.remove(&robot_id);
+            return Err(SimError::UnknownRobot(robot_id));
+        }
+        info!("Converting robot {} to {:?}", robot_id, robot_type);
+        self.patrols.remove(&robot_id);
+        self.parked_robots.remove(&robot_id);
+        if let Some(formation) = self.formation.as_mut() {
+            formation.remove_member(robot_id);
+        }
+        Ok(())
+    }
+
+    /// Behavior the robot docks to take on, if it is being converted
+    pub fn pending_conversion(&self, robot_id: u32) -> Option<RobotType> {
+        self.conversions.get(&robot_id).copied()
+    }
+
+    /// Ids of all active robots, sorted
+    pub fn robot_ids(&self) -> Vec<u32> {
+        self.robots.ids()
+    }
+
+    /// Gets a robot's state regardless of its type
+    pub fn robot_state(&self, robot_id: u32) -> Option<&RobotState> {
+        self.robots.state(robot_id)
+    }
+
+    /// A robot's last decisions, oldest first; none unless it was launched with decision
+    /// traces on
+    pub fn decision_trace(&self, robot_id: u32) -> Result<Vec<Decision>, SimError> {
+        self.robots
+            .get(robot_id)
+            .map(|entry| entry.telemetry.trace.decisions())
+            .ok_or(SimError::UnknownRobot(robot_id))
+    }
+
+    /// Moves the given robots as a formation towards `target`, replacing any formation
+    /// move in progress
+    pub fn start_formation(
+        &mut self,
+        robot_ids: &[u32],
+        formation: Formation,
+        target: (usize, usize),
+    ) {
+        let positions: Vec<(u32, (usize, usize))> = robot_ids
+            .iter()
+            .filter_map(|&id| self.robot_state(id).map(|robot| (id, (robot.x, robot.y))))
+            .collect();
+        self.formation = FormationMove::new(formation, &positions, target);
+        if self.formation.is_some() {
+            info!(
+                "Starting {:?} formation of {} robots towards {:?}",
+                formation,
+                positions.len(),
+                target
+            );
+        }
+    }
+
+    /// Has collector `id` gather `resource_type` from now on
+    pub fn target_resource(
+        &mut self,
+        id: u32,
+        resource_type: ResourceType,
+    ) -> Result<(), SimError> {
+        let entry = self.robots.get_mut(id).ok_or(SimError::UnknownRobot(id))?;
+        if entry.robot_type != RobotType::Collection {
+            return Err(SimError::NotACollector(id));
+        }
+        entry.telemetry.collector_target = Some(resource_type);
+        if !self.dispatch(id, RobotCommand::SetTargetResource(resource_type)) {
+            return Err(SimError::UnknownRobot(id));
+        }
+        self.idle_monitor.record_progress(id, self.tick);
+        Ok(())
+    }
+
+    /// Sends a command to every member of a squad, returns how many robots received it.
+    /// Target resource changes only go to the squad's collectors.
+    pub fn command_squad(&mut self, name: &str, command: RobotCommand) -> usize {
+        let mut delivered = 0;
+        for id in self.squads.members(name) {
+            if let RobotCommand::SetTargetResource(resource_type) = &command {
+                match self.robots.get_mut(id) {
+                    Some(entry) if entry.robot_type == RobotType::Collection => {
+                        entry.telemetry.collector_target = Some(*resource_type);
+                    }
+                    _ => continue,
+                }
+            }
+            if self.dispatch(id, command.clone()) {
+                self.idle_monitor.record_progress(id, self.tick);
+                delivered += 1;
+            }
+        }
+        info!("Squad {}: {:?} sent to {} robots", name, command, delivered);
+        delivered
+    }
+
+    /// Sends the explorers among `robot_ids` on a patrol along `route`, or stops their patrol
+    /// if the route is empty. Returns how many explorers received it.
+    pub fn assign_patrol(&mut self, robot_ids: &[u32], route: &[(usize, usize)]) -> usize {
+        let mut delivered = 0;
+        for &id in robot_ids {
+            if self.robot_type_of(id) != Some(RobotType::Exploration) {
+                continue;
+            }
+            if !self.dispatch(id, RobotCommand::Patrol(route.to_vec())) {
+                continue;
+            }
+            if route.is_empty() {
+                self.patrols.remove(&id);
+            } else {
+                self.patrols.insert(id, route.to_vec());
+            }
+            self.idle_monitor.record_progress(id, self.tick);
+            delivered += 1;
+        }
+        delivered
+    }
+
+    /// Summary of every squad, in name order
+    pub fn squad_summaries(&self) -> Vec<SquadSummary> {
+        self.squads
+            .names()
+            .into_iter()
+            .map(|name| {
+                let mut summary = SquadSummary {
+                    name: name.to_string(),
+                    explorers: 0,
+                    collectors: 0,
+                    scientists: 0,
+                    scouts: 0,
+                    energy_percent: 0,
+                };
+                let (mut energy, mut max_energy) = (0u64, 0u64);
+                for id in self.squads.members(name) {
+                    match self.robot_type_of(id) {
+                        Some(RobotType::Exploration) => summary.explorers += 1,
+                        Some(RobotType::Collection) => summary.collectors += 1,
+                        Some(RobotType::Scientific) => summary.scientists += 1,
+                        Some(RobotType::Scout) => summary.scouts += 1,
+                        None => continue,
+                    }
+                    if let Some(robot) = self.robot_state(id) {
+                        energy += u64::from(robot.energy);
+                        max_energy += u64::from(robot.max_energy);
+                    }
+                }
+                summary.energy_percent = (energy * 100).checked_div(max_energy).unwrap_or(0) as u32;
+                summary
+            })
+            .collect()
+    }
+
+    /// What the station knows of the map, merged from every robot that docked
+    pub fn station_knowledge(&self) -> RobotKnowledge {
+        self.station.data().get_global_robot_knowledge()
+    }
+
+    /// Moving entities the station knows of, from its own sightings, scout reports and
+    /// docked robots
+    pub fn known_entities(&self) -> DynamicLayer {
+        self.station.data().entities().clone()
+    }
+
+    /// Leaves the user's note on a tile of the station's knowledge, in place of its previous
+    /// one; robots get the notes with the rest of the knowledge as they dock
+    pub fn annotate(&mut self, (x, y): (usize, usize), text: &str) -> Result<(), MapError> {
+        if x >= self.map_width || y >= self.map_height {
+            return Err(MapError::OutOfBounds { x, y });
+        }
+        self.station
+            .data_mut()
+            .annotate(x, y, text.trim().to_string());
+        Ok(())
+    }
+
+    /// Removes the note on a tile, returns whether there was one
+    pub fn clear_annotation(&mut self, (x, y): (usize, usize)) -> bool {
+        self.station.data_mut().clear_annotation(x, y)
+    }
+
+    /// The user's notes, by tile
+    pub fn annotations(&self) -> Vec<Annotation> {
+        self.station.data().entities().annotations().to_vec()
+    }
+
+    /// How far the station's merged knowledge was from the real map after the last merge
+    pub fn station_divergence(&self) -> KnowledgeDivergence {
+        self.station_divergence
+    }
+
+    /// How far each robot's knowledge was from the real map as it last docked, by robot id
+    pub fn robot_divergences(&self) -> Vec<(u32, KnowledgeDivergence)> {
+        self.robots
+            .iter()
+            .filter_map(|entry| Some((entry.state.id, entry.telemetry.divergence?)))
+            .collect()
+    }
+
+    /// Lowest and average robot energy, `None` without robots
+    pub fn energy_summary(&self) -> Option<EnergySummary> {
+        let energies: Vec<u32> = self.robots.iter().map(|entry| entry.state.energy).collect();
+        let min = *energies.iter().min()?;
+        let total: u64 = energies.iter().map(|&energy| u64::from(energy)).sum();
+        Some(EnergySummary {
+            min,
+            average: (total / energies.len() as u64) as u32,
+        })
+    }
+
+    /// Number of robots currently without productive progress for too long
+    pub fn idle_robot_count(&self) -> usize {
+        self.idle_monitor.idle_robots(self.tick).len()
+    }
+
+    /// Records every robot's position from the next tick on
+    pub fn record_trajectories(&mut self) {
+        self.trajectories.get_or_insert_with(TrajectoryLog::new);
+    }
+
+    /// Sets the clock of a resumed run to the tick it was saved at
+    pub fn resume_at(&mut self, tick: u64) {
+        self.tick = tick;
+        self.event_sender.clock().set(tick);
+    }
+
+    /// Advances `ticks` ticks as fast as the robots can keep up, without rendering.
+    /// Robots act `FAST_FORWARD_SPEEDUP` times faster meanwhile, so each tick still covers
+    /// the same amount of robot activity.
+    pub fn fast_forward(&mut self, ticks: u64) {
+        info!("Fast-forwarding {} ticks", ticks);
+        config::set_time_scale(config::FAST_FORWARD_SPEEDUP);
+        let tick_interval = TICK_RATE / config::FAST_FORWARD_SPEEDUP;
+        for _ in 0..ticks {
+            if self.completion_summary.is_some() {
+                break;
+            }
+            self.update();
+            self.clock.sleep(tick_interval);
+        }
+        config::set_time_scale(1);
+    }
+
+    pub fn update(&mut self) {
+        // Once time is up the run stays as it ended
+        if self.out_of_time() {
+            return;
+        }
+        self.tick += 1;
+
+        self.event_sender.clock().set(self.tick);
+        self.report_map_recoveries();
+
+        let events_started = profiler::start();
+        while let Ok(envelope) = self.event_receiver.try_recv() {
+            self.event_sender.taken();
+            self.record_envelope(&envelope);
+            let event = envelope.event;
+            self.event_bus.publish(&event);
+
+            if matches!(
+                event,
+                RobotEvent::ArrivedAtStation { .. } | RobotEvent::HostileSighted { .. }
+            ) {
+                self.station.process_event(&event);
+            }
+            if let Some(entry) = event.robot_id().and_then(|id| self.robots.get_mut(id)) {
+                entry.telemetry.last_report_tick = self.tick;
+            }
+
+            match event {
+                RobotEvent::ExplorationData { id, x, y, .. } => {
+                    if let Some(robot) = self.get_robot_state_mut(id) {
+                        robot.x = x;
+                        robot.y = y;
+                    }
+
+                    if self.explored_tiles.insert((x, y)) {
+                        self.total_explored += 1;
+                        self.idle_monitor.record_progress(id, self.tick);
+                    } else if self.patrols.contains_key(&id) {
+                        // Re-observing known tiles is the point of a patrol
+                        self.idle_monitor.record_progress(id, self.tick);
+                    }
+                }
+                RobotEvent::PositionUpdate {
+                    id,
+                    x,
+                    y,
+                    energy,
+                    status,
+                } => {
+                    if let Some(robot) = self.get_robot_state_mut(id) {
+                        let spent = robot.energy.saturating_sub(energy);
+                        robot.x = x;
+                        robot.y = y;
+                        robot.energy = energy;
+                        robot.status = status;
+                        self.energy_spent += u64::from(spent);
+                    }
+                }
+                RobotEvent::CollectionData {
+                    id,
+                    x,
+                    y,
+                    resource_type,
+                    amount,
+                } => {
+                    if let Some(robot) = self.get_robot_state_mut(id) {
+                        robot.x = x;
+                        robot.y = y;
+                    }
+
+                    if let Some(res_type) = resource_type {
+                        if amount > 0 {
+                            self.recover_salvage(id, (x, y), &res_type, amount);
+                            self.ledger.record_collection(id, &res_type, amount);
+                            *self.collected_resources.entry(res_type).or_insert(0) += amount;
+                            self.idle_monitor.record_progress(id, self.tick);
+                        }
+                    }
+                }
+                RobotEvent::ScienceData {
+                    id, x, y, amount, ..
+                } => {
+                    if let Some(robot) = self.get_robot_state_mut(id) {
+                        robot.x = x;
+                        robot.y = y;
+                    }
+
+                    self.scientific_data += amount as u64;
+                    if amount > 0 {
+                        self.idle_monitor.record_progress(id, self.tick);
+                    }
+                }
+                RobotEvent::LowEnergy { id, remaining } => {
+                    if let Some(robot) = self.get_robot_state_mut(id) {
+                        let spent = robot.energy.saturating_sub(remaining);
+                        robot.energy = remaining;
+                        self.energy_spent += u64::from(spent);
+                    } else {
+                        warn!("Received LowEnergy event for unknown robot ID: {}", id);
+                    }
+                }
+                RobotEvent::MergeComplete {
+                    id,
+                    merged_knowledge,
+                    mut brief,
+                } => {
+                    let robot_type = self.robot_type_of(id);
+                    let parked = self.parked_robots.contains(&id);
+                    self.ledger.record_delivery(id);
+                    // Measured once per merge rather than on every frame of the sidebar
+                    self.station_divergence = self.station.data().divergence(&self.map.read());
+                    // A fresh dock gives the robot a new chance before counting as idle
+                    self.idle_monitor.record_progress(id, self.tick);
+
+                    let queued = self.charging.is_limited();
+                    let energy = self.robot_state(id).map(|robot| robot.energy);
+                    if let Some(robot) = self.get_robot_state_mut(id) {
+                        robot.collected_resources.clear();
+                        if queued {
+                            robot.status = RobotStatus::AtStation;
+                        } else {
+                            robot.recharge();
+                            match robot_type {
+                                Some(robot_type) => {
+                                    robot.status = status_after_dock(robot_type, parked);
+                                }
+                                None => {
+                                    warn!("Robot type not found for ID: {}", id);
+                                }
+                            }
+                        }
+                    } else {
+                        warn!("Received MergeComplete event for unknown robot ID: {}", id);
+                    }
+
+                    if queued {
+                        if let (Some(robot_type), Some(energy)) = (robot_type, energy) {
+                            self.charging.enqueue(id, robot_type, energy, self.tick);
+                        }
+                        brief.charger_queued = true;
+                    }
+                    self.rendezvous.release_robot(id);
+                    self.service_battery(id);
+                    self.service_robot(id);
+                    if robot_type == Some(RobotType::Collection) {
+                        brief.assignments.extend(self.rebalance_collector(id));
+                    }
+                    brief
+                        .assignments
+                        .extend(self.direct_to_priority(id, &merged_knowledge));
+                    brief.claims = self
+                        .priorities
+                        .claimed_by_others(id)
+                        .chain(self.rendezvous.claimed_by_others(id))
+                        .collect();
+                    brief.overrides = ConfigOverrides::current();
+                    if !self.deliver_merge(id, merged_knowledge, brief) {
+                        self.priorities.release(id);
+                    }
+                }
+                RobotEvent::ArrivedAtStation { id, knowledge } => {
+                    if let Some(entry) = self.robots.get_mut(id) {
+                        entry.state.status = RobotStatus::AtStation;
+                        entry.telemetry.divergence = Some(knowledge.divergence(&self.map.read()));
+                        entry.telemetry.last_knowledge = Some(knowledge);
+                    }
+                }
+                RobotEvent::Shutdown { id, reason } => {
+                    info!("Robot {} shutting down: {}", id, reason);
+                    match self.conversions.remove(&id) {
+                        Some(robot_type) if reason == ShutdownReason::Retired => {
+                            self.relaunch_as(id, robot_type)
+                        }
+                        _ => self.forget_robot(id),
+                    }
+                }
+                RobotEvent::SalvageDropped {
+                    id,
+                    x,
+                    y,
+                    resource_type,
+                    amount,
+                } => {
+                    info!(
+                        "Robot {} left {} {:?} as salvage at ({}, {})",
+                        id, amount, resource_type, x, y
+                    );
+                }
+                RobotEvent::SalvageRecovered {
+                    id,
+                    x,
+                    y,
+                    resource_type,
+                    amount,
+                } => {
+                    info!(
+                        "Robot {} recovered {} {:?} of salvage at ({}, {})",
+                        id, amount, resource_type, x, y
+                    );
+                }
+                RobotEvent::RendezvousRequest { id, x, y } => {
+                    if self.rendezvous.request((x, y), id, self.tick) {
+                        // Waiting for a collector is part of the job
+                        self.idle_monitor.record_progress(id, self.tick);
+                    } else {
+                        debug!(
+                            "Robot {} asked for joint site ({}, {}), already held",
+                            id, x, y
+                        );
+                    }
+                }
+                RobotEvent::MergeAbandoned { id, attempts } => {
+                    warn!(
+                        "Robot {} reported its dock {} times without a merge, resuming with stale knowledge",
+                        id, attempts
+                    );
+                    self.merges_abandoned += 1;
+                    self.last_merge_abandoned = Some((id, self.tick));
+                }
+                RobotEvent::SampleExtracted { id, x, y } => {
+                    self.idle_monitor.record_progress(id, self.tick);
+                    match self.rendezvous.complete((x, y)) {
+                        Some(scientist) => {
+                            if self.dispatch(scientist, RobotCommand::SampleReady { x, y }) {
+                                self.joint_samples += 1;
+                            }
+                        }
+                        None => debug!(
+                            "Robot {} sampled ({}, {}) with no scientist waiting",
+                            id, x, y
+                        ),
+                    }
+                }
+                RobotEvent::SwarmAlarm { alarm, raised } => {
+                    let description = self.alarms.describe(alarm);
+                    if raised {
+                        warn!("Alarm: {}", description);
+                        self.journal.record(
+                            self.tick,
+                            Severity::Alarm,
+                            format!("Alarm: {}", description),
+                        );
+                    } else {
+                        info!("Alarm cleared: {}", description);
+                        self.journal.record(
+                            self.tick,
+                            Severity::Info,
+                            format!("Cleared: {}", description),
+                        );
+                    }
+                }
+                RobotEvent::StationEnergyLow { stock } => {
+                    warn!("Station energy low: {} in stock", stock);
+                    self.journal.record(
+                        self.tick,
+                        Severity::Alarm,
+                        format!("Station energy low: {} in stock", stock),
+                    );
+                }
+                RobotEvent::MissionComplete { .. } => {}
+                RobotEvent::MapLockRecovered { recoveries } => {
+                    error!(
+                        "Map lock recovered {} time(s) after a thread panicked",
+                        recoveries
+                    );
+                }
+                RobotEvent::ExplorationComplete { known_tiles } => {
+                    info!(
+                        "Exploration complete with {} known tiles, parking explorers",
+                        known_tiles
+                    );
+                    self.exploration_complete = true;
+                    self.journal.record(
+                        self.tick,
+                        Severity::Info,
+                        format!("Exploration complete, {} tiles known", known_tiles),
+                    );
+                    self.park_explorers();
+                }
+                RobotEvent::ReturnToBase { id } => {
+                    if let Some(robot) = self.get_robot_state_mut(id) {
+                        robot.status = RobotStatus::ReturningToStation;
+                    } else {
+                        warn!("Received ReturnToBase event for unknown robot ID: {}", id);
+                    }
+                }
+                RobotEvent::HostileSighted { id, sightings } => {
+                    debug!("Robot {} sighted {} dust devils", id, sightings.len());
+                    self.idle_monitor.record_progress(id, self.tick);
+                }
+                RobotEvent::CommandAck { id, command_id } => {
+                    if !self.command_tracker.acknowledge(command_id) {
+                        debug!("Robot {} acknowledged stale command {}", id, command_id);
+                    }
+                }
+                RobotEvent::CommandRejected {
+                    id,
+                    command_id,
+                    reason,
+                } => {
+                    if let Some(pending) = self.command_tracker.reject(command_id) {
+                        warn!(
+                            "Robot {} rejected command {} ({:?}): {}",
+                            id, command_id, pending.command, reason
+                        );
+                        if matches!(pending.command, RobotCommand::ExtractSample { .. }) {
+                            self.rendezvous.release_robot(id);
+                        }
+                    }
+                }
+            }
+        }
+        profiler::record(Section::Events, events_started);
+
+        let systems_started = profiler::start();
+        self.accept_remote_robots();
+        self.fire_world_events();
+        self.expire_exclusions();
+        self.hostiles.tick(self.tick, &mut self.map.write());
+        self.track_entities();
+        self.station.forget_stale_terrain(self.tick);
+        self.drain_station();
+        self.run_chargers();
+        self.audit_ledger();
+        self.retry_commands();
+        self.advance_formation();
+        self.reassign_idle_robots();
+        self.coordinate_rendezvous();
+        self.schedule_maintenance();
+        self.retire_when_exhausted();
+        self.detect_mission_failure();
+        self.check_memory();
+        self.check_alarms();
+        if let Some(trajectories) = &mut self.trajectories {
+            trajectories.record(self.tick, self.robots.iter());
+        }
+        self.kpis.record(
+            self.tick,
+            KpiTotals {
+                delivered: self.ledger.delivered_total(),
+                energy_spent: self.energy_spent,
+                explored_tiles: self.total_explored,
+            },
+        );
+        profiler::record(Section::Systems, systems_started);
+        if self.out_of_time() && self.completion_summary.is_none() {
+            info!("Time is up at tick {}", self.tick);
+            self.complete(MissionOutcome::TimedOut);
+        }
+    }
+
+    /// Raises alarms by `settings` from now on, counting minutes in ticks of `tick_rate`
+    pub fn set_alarms(&mut self, settings: AlarmSettings, tick_rate: Duration) {
+        self.alarms = AlarmMonitor::new(settings, tick_rate);
+    }
+
+    /// Fires `StationEnergyLow` each time the energy stock falls under `threshold`, 0 never
+    pub fn set_station_energy_low(&mut self, threshold: u32) {
+        self.station_energy_low = (threshold, false);
+    }
+
+    /// Checks the swarm's health, firing a `SwarmAlarm` event for each alarm raised or
+    /// cleared
+    fn check_alarms(&mut self) {
+        let stock = |resource_type| {
+            self.collected_resources
+                .get(&resource_type)
+                .copied()
+                .unwrap_or(0)
+        };
+        let readings = SwarmReadings {
+            robots: self.robots.len(),
+            low_energy_robots: self
+                .robots
+                .iter()
+                .filter(|entry| {
+                    self.alarms
+                        .is_low_on_energy(entry.state.energy, entry.state.max_energy)
+                })
+                .count(),
+            energy_stock: stock(ResourceType::ENERGY),
+            mineral_stock: stock(ResourceType::MINERALS),
+            explored_tiles: self.total_explored,
+            exploring: !self.exploration_complete,
+        };
+        for (alarm, raised) in self.alarms.check(self.tick, &readings) {
+            let _ = self
+                .event_sender
+                .send(RobotEvent::SwarmAlarm { alarm, raised });
+        }
+
+        let (threshold, was_low) = self.station_energy_low;
+        let low = readings.energy_stock < threshold;
+        if low && !was_low {
+            let _ = self.event_sender.send(RobotEvent::StationEnergyLow {
+                stock: readings.energy_stock,
+            });
+        }
+        self.station_energy_low.1 = low;
+    }
+
+    /// Checks the run's memory against `limits` from now on
+    pub fn set_memory_limit(&mut self, limits: MemorySettings) {
+        self.memory_limit = limits;
+    }
+
+    /// Estimates what the knowledge maps, the queues and the stats take, warning when the
+    /// run gets near or past its limit
+    fn check_memory(&mut self) {
+        if !self
+            .tick
+            .is_multiple_of(config::MEMORY_CHECK_INTERVAL_TICKS)
+        {
+            return;
+        }
+        self.memory = self.memory_usage();
+        let level = self.memory.level(
+            self.memory_limit.limit_bytes(),
+            self.memory_limit.warn_percent,
+        );
+        if level > self.memory_level {
+            let total = memory::format_mb(self.memory.total());
+            match level {
+                MemoryLevel::Over => warn!(
+                    "Memory: about {} taken, past the {} MB limit",
+                    total, self.memory_limit.limit_mb
+                ),
+                MemoryLevel::Near => warn!(
+                    "Memory: about {} taken, {}% of the {} MB limit is near",
+                    total, self.memory_limit.warn_percent, self.memory_limit.limit_mb
+                ),
+                MemoryLevel::Fine => {}
+            }
+        }
+        self.memory_level = level;
+    }
+
+    /// Approximate memory of the run as it stands
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let fresh_knowledge = RobotKnowledge::fresh_bytes(self.map_width, self.map_height);
+        let mut knowledge = self
+            .station
+            .data_manager
+            .read()
+            .map_or(0, |data_manager| data_manager.approx_bytes());
+        let mut queues = self.event_sender.backlog() * size_of::<EventEnvelope>();
+        for entry in self.robots.iter() {
+            // The robot's own map, and the copy it last reported at the dock
+            let reported = entry.telemetry.last_knowledge.as_ref();
+            knowledge += reported.map_or(fresh_knowledge, |known| 2 * known.approx_bytes());
+
+            let inbox = entry.telemetry.inbox.report();
+            let merge_bytes = size_of::<RobotEvent>()
+                + reported.map_or(fresh_knowledge, RobotKnowledge::approx_bytes);
+            queues += inbox.commands as usize * size_of::<CommandEnvelope>()
+                + inbox.merges as usize * merge_bytes;
+        }
+        let stats = memory::hash_map_bytes::<(usize, usize), ()>(self.explored_tiles.len())
+            + self
+                .trajectories
+                .as_ref()
+                .map_or(0, TrajectoryLog::approx_bytes)
+            + self.kpis.approx_bytes()
+            + self
+                .station
+                .data_manager
+                .read()
+                .map_or(0, |data_manager| data_manager.merge_log_bytes());
+        MemoryUsage {
+            knowledge,
+            queues,
+            stats,
+        }
+    }
+
+    /// Stops the run at `limit`, none to let it go on until the mission is complete
+    pub fn set_tick_limit(&mut self, limit: Option<u64>) {
+        self.tick_limit = limit;
+    }
+
+    fn out_of_time(&self) -> bool {
+        self.tick_limit.is_some_and(|limit| self.tick >= limit)
+    }
+
+    /// Swarm efficiency and velocities over the last `config::KPI_WINDOW_TICKS`
+    pub fn live_kpis(&self) -> SwarmKpis {
+        self.kpis.live()
+    }
+
+    /// Swarm efficiency and velocities since the start of the run
+    pub fn run_kpis(&self) -> SwarmKpis {
+        self.kpis.overall()
+    }
+
+    /// Checks that each source's events arrive in sequence and tracks delivery latency
+    fn record_envelope(&mut self, envelope: &EventEnvelope) {
+        self.event_stats.received += 1;
+        let latency = self.tick.saturating_sub(envelope.sim_time);
+        self.event_stats.total_latency_ticks += latency;
+        self.event_stats.max_latency_ticks = self.event_stats.max_latency_ticks.max(latency);
+
+        let last = self.last_sequences.entry(envelope.source).or_insert(0);
+        if envelope.sequence <= *last {
+            warn!(
+                "Event {} from {:?} arrived after event {}",
+                envelope.sequence, envelope.source, last
+            );
+            self.event_stats.out_of_order += 1;
+        } else {
+            if envelope.sequence > *last + 1 {
+                self.event_stats.missing += envelope.sequence - *last - 1;
+            }
+            *last = envelope.sequence;
+        }
+    }
+
+    /// Recalls and stops every robot once exploration is complete and no energy or
+    /// minerals are left, then records the completion summary when the last one is gone
+    fn retire_when_exhausted(&mut self) {
+        if !self.retiring {
+            if !self.exploration_complete {
+                return;
+            }
+            if self.map.read().has_consumable_resources() {
+                return;
+            }
+
+            info!("Map exhausted, retiring all robots");
+            self.retiring = true;
+            self.formation = None;
+            for id in self.robot_ids() {
+                self.send_command(id, RobotCommand::Retire);
+            }
+        }
+
+        if self.completion_summary.is_none() && self.robot_ids().is_empty() {
+            self.complete(MissionOutcome::Completed);
+        }
+    }
+
+    /// Ends the run as failed once no robot is left to finish the mission and none can join
+    fn detect_mission_failure(&mut self) {
+        if self.completion_summary.is_some()
+            || self.retiring
+            || !self.robots.is_empty()
+            || self.remote_station.is_some()
+        {
+            return;
+        }
+        warn!("Every robot stopped before the mission was complete");
+        self.complete(MissionOutcome::Failed);
+    }
+
+    /// Records the final figures of the run
+    fn complete(&mut self, outcome: MissionOutcome) {
+        let summary = CompletionSummary {
+            outcome,
+            ticks: self.tick,
+            explored_tiles: self.total_explored,
+            total_tiles: self.map_width * self.map_height,
+            collected_resources: self.collected_resources.clone(),
+            scientific_data: self.scientific_data,
+            retired_robots: self.retired_robots,
+            difficulty: self.difficulty,
+            map_seed: self.map_seed,
+            resource_seed: self.resource_seed,
+            robot_seed: self.robot_seed(),
+        };
+        info!("Simulation complete: {:?}", summary);
+        self.completion_summary = Some(summary);
+        // Not through the channel, a timed out run takes no more ticks
+        self.event_bus
+            .publish(&RobotEvent::MissionComplete { outcome });
+    }
+
+    /// Issues the next coordinated waypoints of the formation move in progress
+    fn advance_formation(&mut self) {
+        let Some(mut formation) = self.formation.take() else {
+            return;
+        };
+        let positions: HashMap<u32, (usize, usize)> = formation
+            .members()
+            .iter()
+            .filter_map(|&id| self.robot_state(id).map(|robot| (id, (robot.x, robot.y))))
+            .collect();
+
+        let waypoints = {
+            let map_guard = self.map.read();
+            formation.update(&positions, &map_guard)
+        };
+
+        for (id, (x, y)) in waypoints {
+            self.dispatch(id, RobotCommand::MoveTo { x, y });
+            self.idle_monitor.record_progress(id, self.tick);
+        }
+
+        if formation.is_complete() {
+            info!("Formation reached {:?}", formation.target);
+        } else {
+            self.formation = Some(formation);
+        }
+    }
+
+    /// Docks every explorer that is not patrolling, since there is nothing left to discover
+    fn park_explorers(&mut self) {
+        for id in self.robots.ids_of(RobotType::Exploration) {
+            if self.patrols.contains_key(&id) || self.parked_robots.contains(&id) {
+                continue;
+            }
+            if self.send_command(id, RobotCommand::Park) {
+                self.parked_robots.insert(id);
+            }
+        }
+    }
+
+    /// Clears everything kept about a robot that shut down
+    fn forget_robot(&mut self, id: u32) {
+        if self.retiring {
+            self.retired_robots += 1;
+        }
+        if self.decommissioning.remove(&id) {
+            self.refund_build_cost(id);
+        }
+
+        self.drop_cargo(id);
+        self.robots.remove(id);
+        self.ledger.record_loss(id);
+        self.command_tracker.forget_robot(id);
+        self.idle_monitor.untrack(id);
+        self.maintenance.untrack(id);
+        self.priorities.release(id);
+        self.rendezvous.release_robot(id);
+        self.charging.remove(id);
+        self.squads.remove_robot(id);
+        self.patrols.remove(&id);
+        self.parked_robots.remove(&id);
+        if let Some(formation) = self.formation.as_mut() {
+            formation.remove_member(id);
+        }
+    }
+
+    /// Starts a converted robot's new loop, docked where the old one stopped. It keeps its
+    /// id, battery, wear and squad.
+    fn relaunch_as(&mut self, id: u32, robot_type: RobotType) {
+        let Some(mut state) = self.robot_state(id).cloned() else {
+            return;
+        };
+        state.status = launcher::initial_state(robot_type, id, state.x, state.y).status;
+        self.robots.remove(id);
+        self.command_tracker.forget_robot(id);
+        self.idle_monitor.untrack(id);
+        self.maintenance.untrack(id);
+        self.priorities.release(id);
+        self.rendezvous.release_robot(id);
+        // The new loop numbers its events from the start again
+        self.last_sequences.remove(&EventSource::Robot(id));
+        let mut rng = StdRng::seed_from_u64(self.tick + u64::from(id));
+        self.launch_robot(robot_type, state, &mut rng);
+        info!("Robot {} now runs as {:?}", id, robot_type);
+    }
+
+    /// Reassigns robots stuck without progress: collectors switch to the other resource type
+    /// and head for the nearest deposit of it the station knows, explorers head for the
+    /// nearest tile on the edge of the known map, scientists are recalled to the station.
+    fn reassign_idle_robots(&mut self) {
+        // Robots cannot report their progress during a flare
+        if self.retiring || self.solar_flare_until.is_some() {
+            return;
+        }
+        for id in self.idle_monitor.idle_robots(self.tick) {
+            let Some((from, status)) = self
+                .robot_state(id)
+                .map(|robot| ((robot.x, robot.y), robot.status.clone()))
+            else {
+                continue;
+            };
+            if !matches!(
+                status,
+                RobotStatus::Exploring | RobotStatus::Collecting | RobotStatus::Analyzing
+            ) {
+                continue;
+            }
+            let Some(robot_type) = self.robot_type_of(id) else {
+                continue;
+            };
+
+            let mut commands = Vec::new();
+            match robot_type {
+                RobotType::Collection => {
+                    let Some(entry) = self.robots.get_mut(id) else {
+                        continue;
+                    };
+                    let consumables = self.resource_catalog.consumables();
+                    let next = entry
+                        .telemetry
+                        .collector_target
+                        .and_then(|target| consumables.iter().position(|&t| t == target))
+                        .map_or(0, |index| index + 1);
+                    let next_target = consumables[next % consumables.len()];
+                    entry.telemetry.collector_target = Some(next_target);
+                    let deposit = self.station.data().nearest_known(&next_target, from);
+                    commands.push(RobotCommand::SetTargetResource(next_target));
+                    commands.extend(deposit.map(|(x, y)| RobotCommand::MoveTo { x, y }));
+                }
+                RobotType::Exploration => {
+                    let topology = self.map.read().topology();
+                    let frontier = self
+                        .station
+                        .data()
+                        .frontier_tiles()
+                        .into_iter()
+                        .min_by_key(|&tile| topology.distance(from, tile));
+                    commands.push(match frontier {
+                        Some((x, y)) => RobotCommand::MoveTo { x, y },
+                        None => RobotCommand::ReturnToStation,
+                    });
+                }
+                _ => commands.push(RobotCommand::ReturnToStation),
+            }
+
+            info!(
+                "Robot {} idle for {} ticks, sending {:?}",
+                id,
+                self.idle_monitor.idle_ticks(id, self.tick).unwrap_or(0),
+                commands
+            );
+            let mut sent = false;
+            for command in commands {
+                sent |= self.dispatch(id, command);
+            }
+            if sent {
+                self.idle_reassignments += 1;
+            }
+            self.idle_monitor.record_progress(id, self.tick);
+        }
+    }
+
+    /// Closes the joint site missions that went on too long, then sends the nearest free
+    /// collector to each scientist still waiting for one
+    fn coordinate_rendezvous(&mut self) {
+        for (x, y) in self
+            .rendezvous
+            .expire(self.tick, config::RENDEZVOUS_TIMEOUT_TICKS)
+        {
+            debug!("Nobody sampled joint site ({}, {}) in time", x, y);
+        }
+        if self.retiring || self.rendezvous.is_empty() {
+            return;
+        }
+        let topology = self.map.read().topology();
+        for site in self.rendezvous.unassigned() {
+            let collector = self
+                .robots
+                .iter()
+                .filter(|entry| {
+                    entry.robot_type == RobotType::Collection
+                        && entry.state.status == RobotStatus::Collecting
+                        && self.free_for_sampling(entry.state.id)
+                })
+                .min_by_key(|entry| topology.distance((entry.state.x, entry.state.y), site))
+                .map(|entry| entry.state.id);
+            let Some(collector) = collector else {
+                break;
+            };
+            info!("Sending collector {} to joint site {:?}", collector, site);
+            if self.dispatch(
+                collector,
+                RobotCommand::ExtractSample {
+                    x: site.0,
+                    y: site.1,
+                },
+            ) {
+                self.rendezvous.assign(site, collector);
+            }
+        }
+    }
+
+    /// Whether the robot is free of the orders that keep it from sampling a joint site
+    fn free_for_sampling(&self, robot_id: u32) -> bool {
+        !self.rendezvous.is_busy(robot_id)
+            && !self.decommissioning.contains(&robot_id)
+            && !self.conversions.contains_key(&robot_id)
+            && self.maintenance.state_of(robot_id) == Some(MaintenanceState::Operating)
+            && !self
+                .formation
+                .as_ref()
+                .is_some_and(|formation| formation.members().contains(&robot_id))
+    }
+
+    /// Points a docked collector at the scarcer resource when the stock is out of balance
+    fn rebalance_collector(&mut self, id: u32) -> Option<RobotCommand> {
+        if self.retiring {
+            return None;
+        }
+        let targets = self.robots.collector_targets();
+        let current = targets.get(&id)?;
+        let next_target =
+            self.stock_balancer
+                .rebalance(current, &self.collected_resources, &targets)?;
+        info!(
+            "Collector {} switching from {:?} to {:?} to balance the stock",
+            id, current, next_target
+        );
+        self.robots.get_mut(id)?.telemetry.collector_target = Some(next_target);
+        Some(RobotCommand::SetTargetResource(next_target))
+    }
+
+    /// Spreads wear over the fleet as it operates, calls worn robots back a few at a time and
+    /// puts serviced robots back to work
+    fn schedule_maintenance(&mut self) {
+        for (id, percent) in self.maintenance.accrue(self.tick) {
+            if self.send_command(id, RobotCommand::Wear { percent }) {
+                if let Some(robot) = self.get_robot_state_mut(id) {
+                    robot.wear_percent = percent;
+                }
+            }
+        }
+        for id in self.maintenance.finish(self.tick) {
+            if let Some(robot) = self.get_robot_state_mut(id) {
+                robot.wear_percent = 0;
+            }
+            self.idle_monitor.record_progress(id, self.tick);
+        }
+        if self.retiring {
+            return;
+        }
+        for id in self.maintenance.next_recalls(self.tick) {
+            info!("Recalling robot {} for maintenance", id);
+            if !self.send_command(id, RobotCommand::ReturnToStation) {
+                self.maintenance.postpone(id);
+            }
+        }
+    }
+
+    /// Assigns a docked explorer the closest marked tile still unknown, or a collector the
+    /// closest marked resource, each marked tile going to one robot at a time. Robots busy with
+    /// an order of the user or of the station are left alone.
+    fn direct_to_priority(
+        &mut self,
+        robot_id: u32,
+        knowledge: &RobotKnowledge,
+    ) -> Option<RobotCommand> {
+        self.priorities.release(robot_id);
+        if self.priorities.is_empty()
+            || self.retiring
+            || self.parked_robots.contains(&robot_id)
+            || self.patrols.contains_key(&robot_id)
+            || self.decommissioning.contains(&robot_id)
+            || self.conversions.contains_key(&robot_id)
+            || self.maintenance.state_of(robot_id) != Some(MaintenanceState::Operating)
+            || self
+                .formation
+                .as_ref()
+                .is_some_and(|formation| formation.members().contains(&robot_id))
+        {
+            return None;
+        }
+        let wanted: fn(&TileInfo) -> bool = match self.robot_type_of(robot_id) {
+            Some(RobotType::Exploration) => |tile| matches!(tile, TileInfo::Unknown),
+            Some(RobotType::Collection) => |tile| matches!(tile, TileInfo::Resource(..)),
+            _ => return None,
+        };
+        let from = self.robot_state(robot_id).map(|robot| (robot.x, robot.y))?;
+        let topology = self.map.read().topology();
+        let exclusions = &self.exclusions;
+        let (x, y) = self.priorities.claim_nearest(
+            robot_id,
+            from,
+            |(x, y)| wanted(knowledge.get_tile(x, y)) && !exclusions.contains((x, y)),
+            |a, b| topology.distance(a, b),
+        )?;
+        info!("Sending robot {} to priority tile ({}, {})", robot_id, x, y);
+        Some(RobotCommand::MoveTo { x, y })
+    }
+
+    /// Services a docked robot that is due, if the stagger allows it and the stock holds the
+    /// minerals
+    fn service_robot(&mut self, robot_id: u32) {
+        let minerals = self
+            .collected_resources
+            .get(&ResourceType::MINERALS)
+            .copied()
+            .unwrap_or(0);
+        if minerals < config::MAINTENANCE_COST {
+            self.maintenance.postpone(robot_id);
+            return;
+        }
+        if !self
+            .maintenance
+            .start_service(robot_id, self.tick, config::MAINTENANCE_TICKS)
+        {
+            return;
+        }
+        if self.send_command(
+            robot_id,
+            RobotCommand::Service {
+                ticks: config::MAINTENANCE_TICKS,
+            },
+        ) {
+            self.collected_resources
+                .insert(ResourceType::MINERALS, minerals - config::MAINTENANCE_COST);
+            self.maintenance_services += 1;
+            if let Some(robot) = self.get_robot_state_mut(robot_id) {
+                robot.status = RobotStatus::AtStation;
+            }
+            info!("Robot {} in maintenance", robot_id);
+        }
+    }
+
+    /// Frees the chargers whose robot is done and hands them to the next robots waiting
+    fn run_chargers(&mut self) {
+        if !self.charging.is_limited() {
+            return;
+        }
+        for id in self.charging.finish(self.tick) {
+            let robot_type = self.robot_type_of(id);
+            let parked = self.parked_robots.contains(&id);
+            if let Some(robot) = self.get_robot_state_mut(id) {
+                robot.recharge();
+                if let Some(robot_type) = robot_type {
+                    robot.status = status_after_dock(robot_type, parked);
+                }
+            }
+        }
+        let ticks = self.charging.charge_ticks();
+        for id in self.charging.next_grants(self.tick) {
+            if self.send_command(id, RobotCommand::Charge { ticks }) {
+                debug!("Robot {} on a charger", id);
+            } else {
+                self.charging.remove(id);
+            }
+        }
+    }
+
+    /// Replaces the battery of a docked robot once it wore out, paid in minerals from the
+    /// stock; without enough minerals the robot is decommissioned instead
+    fn service_battery(&mut self, robot_id: u32) {
+        if !self
+            .robot_state(robot_id)
+            .is_some_and(|robot| robot.battery_worn())
+            || self.decommissioning.contains(&robot_id)
+        {
+            return;
+        }
+        let minerals = self
+            .collected_resources
+            .get(&ResourceType::MINERALS)
+            .copied()
+            .unwrap_or(0);
+        if minerals >= config::BATTERY_REFIT_COST
+            && self.send_command(robot_id, RobotCommand::RefitBattery)
+        {
+            self.collected_resources.insert(
+                ResourceType::MINERALS,
+                minerals - config::BATTERY_REFIT_COST,
+            );
+            if let Some(robot) = self.get_robot_state_mut(robot_id) {
+                robot.refit_battery();
+            }
+            self.battery_refits += 1;
+            info!("Robot {} fitted with a new battery", robot_id);
+        } else if self.decommission(robot_id) {
+            self.worn_out_robots += 1;
+            info!(
+                "Robot {} battery worn out, no minerals to replace it",
+                robot_id
+            );
+        }
+    }
+
+    /// Credits the station with part of the minerals a dismantled robot was built from
+    fn refund_build_cost(&mut self, robot_id: u32) {
+        let build_cost = match self.robot_type_of(robot_id) {
+            Some(RobotType::Exploration) => config::EXPLORATION_ROBOT_BUILD_COST,
+            Some(RobotType::Collection) => config::COLLECTION_ROBOT_BUILD_COST,
+            Some(RobotType::Scientific) => config::SCIENTIFIC_ROBOT_BUILD_COST,
+            Some(RobotType::Scout) => config::SCOUT_ROBOT_BUILD_COST,
+            None => return,
+        };
+        let refund = build_cost * config::DECOMMISSION_REFUND_PERCENT / 100;
+        *self
+            .collected_resources
+            .entry(ResourceType::MINERALS)
+            .or_insert(0) += refund;
+        self.decommissioned_robots += 1;
+        self.refunded_minerals += refund;
+        info!(
+            "Robot {} decommissioned, {} minerals refunded",
+            robot_id, refund
+        );
+    }
+
+    fn robot_type_of(&self, robot_id: u32) -> Option<RobotType> {
+        self.robots.robot_type(robot_id)
+    }
+
+    /// Gets a mutable reference to a robot's state regardless of its type.
+    fn get_robot_state_mut(&mut self, robot_id: u32) -> Option<&mut RobotState> {
+        self.robots.state_mut(robot_id)
+    }
+}
+
+/// What a robot of `robot_type` goes back to once it leaves the dock charged
+fn status_after_dock(robot_type: RobotType, parked: bool) -> RobotStatus {
+    match robot_type {
+        RobotType::Exploration if parked => RobotStatus::Idle,
+        RobotType::Exploration | RobotType::Scout => RobotStatus::Exploring,
+        RobotType::Collection => RobotStatus::Collecting,
+        RobotType::Scientific => RobotStatus::Analyzing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::core::state::RobotStatus;
+    use std::sync::mpsc::TryRecvError;
+
+    /// App without robots of its own, plus a robot whose merge channel the test holds
+    fn app_with_docking_robot() -> (App, mpsc::Receiver<RobotEvent>) {
+        let counts = RobotCounts {
+            exploration: 0,
+            collection: 0,
+            scientific: 0,
+            scout: 0,
+        };
+        let mut app = App::new(
+            30,
+            10,
+            3,
+            4,
+            counts,
+            SpawnPolicy::NearStation,
+            ModuleCatalog::default(),
+            Arc::default(),
+            Difficulty::Normal,
+            MapShape::default(),
+        );
+        let (merge_sender, merge_receiver) = mpsc::channel();
+        let state = RobotState::new(7, 15, 5, RobotStatus::ReturningToStation, 100);
+        app.robots.insert(RobotEntry::new(
+            RobotType::Exploration,
+            state,
+            mpsc::channel().0,
+            merge_sender,
+        ));
+        (app, merge_receiver)
+    }
+
+    #[test]
+    fn test_impossible_setups_are_errors_not_panics() {
+        let try_new = |width, height, exploration| {
+            App::try_new(
+                width,
+                height,
+                3,
+                4,
+                RobotCounts {
+                    exploration,
+                    collection: 0,
+                    scientific: 0,
+                    scout: 0,
+                },
+                SpawnPolicy::Scattered,
+                ModuleCatalog::default(),
+                Arc::default(),
+                Difficulty::Normal,
+                MapShape::default(),
+            )
+        };
+        assert!(matches!(
+            try_new(2, 10, 1),
+            Err(SimInitError::InvalidConfig(_))
+        ));
+        // The station fills the whole map
+        assert_eq!(
+            try_new(3, 3, 1).err(),
+            Some(SimInitError::StationBlocked { dock: (1, 1) })
+        );
+        assert!(matches!(
+            try_new(30, 10, 250),
+            Err(SimInitError::NoSpawnSpace { requested: 250, .. })
+        ));
+        assert_eq!(
+            try_new(30, 10, 1000).err(),
+            Some(SimInitError::TooManyRobots {
+                requested: 1000,
+                cap: config::DEFAULT_MAX_ROBOTS
+            })
+        );
+    }
+
+    #[test]
+    fn test_spawns_past_the_robot_cap_are_refused() {
+        let (mut app, _merges) = app_with_docking_robot();
+        app.max_robots = app.robots.len() + 1;
+        let id = app.spawn_robot(RobotType::Scout).unwrap();
+        assert!(matches!(
+            app.spawn_robot(RobotType::Scout),
+            Err(SimError::RobotCap(_))
+        ));
+        assert_eq!(app.robots.len(), app.max_robots);
+        app.robots.remove(id);
+        assert!(app.spawn_robot(RobotType::Scout).is_ok());
+    }
+
+    fn dock(app: &App, id: u32) {
+        let sender = app.event_sender.with_source(EventSource::Robot(id));
+        sender
+            .send(RobotEvent::ArrivedAtStation {
+                id,
+                knowledge: RobotKnowledge::new(30, 10),
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_merges_reach_the_docked_robot_exactly_once() {
+        let (mut app, merges) = app_with_docking_robot();
+        dock(&app, 7);
+        app.update();
+        assert!(matches!(
+            merges.try_recv(),
+            Ok(RobotEvent::MergeComplete { id: 7, .. })
+        ));
+        app.update();
+        assert!(matches!(merges.try_recv(), Err(TryRecvError::Empty)));
+
+        // A robot shutting down while the station merges gets nothing, and the app goes on
+        let (mut app, merges) = app_with_docking_robot();
+        dock(&app, 7);
+        app.event_sender
+            .with_source(EventSource::Robot(7))
+            .send(RobotEvent::Shutdown {
+                id: 7,
+                reason: ShutdownReason::StationClosed,
+            })
+            .unwrap();
+        app.update();
+        assert!(matches!(merges.try_recv(), Err(TryRecvError::Disconnected)));
+    }
+
+    #[test]
+    fn test_divergences_are_measured_at_the_dock_not_on_every_frame() {
+        let (mut app, _merges) = app_with_docking_robot();
+        assert!(app.robot_divergences().is_empty());
+        let mut knowledge = RobotKnowledge::new(30, 10);
+        knowledge.observe_and_update(15, 5, &app.map.read());
+        app.event_sender
+            .with_source(EventSource::Robot(7))
+            .send(RobotEvent::ArrivedAtStation { id: 7, knowledge })
+            .unwrap();
+        app.update();
+        let accurate = KnowledgeDivergence { known: 1, wrong: 0 };
+        assert_eq!(app.robot_divergences(), vec![(7, accurate)]);
+        let station = app.station_divergence();
+        assert!(station.known >= 1);
+        assert_eq!(station.wrong, 0);
+
+        // The map changing under the cached numbers leaves them until the next dock
+        app.map
+            .write()
+            .add_resource(15, 5, ResourceType::MINERALS, 5);
+        assert_eq!(app.robot_divergences(), vec![(7, accurate)]);
+        assert_eq!(app.station_divergence(), station);
+    }
+
+    #[test]
+    fn test_docking_robots_get_their_assignment_in_the_brief() {
+        let (mut app, merges) = app_with_docking_robot();
+        app.maintenance.track(7, RobotType::Exploration, 0);
+        app.priorities.toggle((3, 5));
+        app.priorities.toggle((20, 5));
+        app.priorities
+            .claim_nearest(9, (20, 5), |_| true, |a, b| a.0.abs_diff(b.0));
+        dock(&app, 7);
+        app.update();
+        match merges.try_recv() {
+            Ok(RobotEvent::MergeComplete { id: 7, brief, .. }) => {
+                assert_eq!(brief.assignments, vec![RobotCommand::MoveTo { x: 3, y: 5 }]);
+                assert_eq!(brief.claims, vec![(20, 5)]);
+                assert_eq!(brief.exclusions, Some(Vec::new()));
+                // Other tests may change the settings meanwhile
+                assert!(brief.overrides.time_scale.is_some());
+                assert!(brief.overrides.energy_cost_percent.is_some());
+            }
+            other => panic!("Expected a merge for robot 7, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_converted_robots_come_back_with_their_new_behavior() {
+        let (mut app, _merges) = app_with_docking_robot();
+        let (command_sender, commands) = create_command_channel();
+        let state = RobotState::new(8, 15, 5, RobotStatus::Exploring, 100);
+        app.robots.insert(RobotEntry::new(
+            RobotType::Exploration,
+            state,
+            command_sender,
+            mpsc::channel().0,
+        ));
+        assert!(matches!(
+            app.convert_robot(8, RobotType::Exploration),
+            Err(SimError::ConversionRefused { id: 8, .. })
+        ));
+        app.convert_robot(8, RobotType::Collection).unwrap();
+        assert_eq!(commands.try_recv().unwrap().command, RobotCommand::Retire);
+        assert_eq!(app.pending_conversion(8), Some(RobotType::Collection));
+
+        // The old loop stops once docked, the new one starts in its place
+        app.event_sender
+            .with_source(EventSource::Robot(8))
+            .send(RobotEvent::Shutdown {
+                id: 8,
+                reason: ShutdownReason::Retired,
+            })
+            .unwrap();
+        app.update();
+        let entry = app.robots.get(8).expect("the robot is still there");
+        assert_eq!(entry.robot_type, RobotType::Collection);
+        // Its new loop may already have taken a step within the same update
+        assert!(entry.state.x.abs_diff(15) + entry.state.y.abs_diff(5) <= 1);
+        assert_eq!(entry.state.status, RobotStatus::Collecting);
+        assert_eq!(app.pending_conversion(8), None);
+        app.shutdown();
+    }
+
+    #[test]
+    fn test_a_collector_brings_the_waiting_scientist_its_sample() {
+        let (mut app, _merges) = app_with_docking_robot();
+        let (scientist_sender, scientist_commands) = create_command_channel();
+        let (collector_sender, collector_commands) = create_command_channel();
+        app.robots.insert(RobotEntry::new(
+            RobotType::Scientific,
+            RobotState::new(8, 20, 5, RobotStatus::Analyzing, 100),
+            scientist_sender,
+            mpsc::channel().0,
+        ));
+        app.robots.insert(RobotEntry::new(
+            RobotType::Collection,
+            RobotState::new(9, 12, 5, RobotStatus::Collecting, 100),
+            collector_sender,
+            mpsc::channel().0,
+        ));
+        app.maintenance.track(9, RobotType::Collection, 0);
+
+        app.event_sender
+            .with_source(EventSource::Robot(8))
+            .send(RobotEvent::RendezvousRequest { id: 8, x: 20, y: 5 })
+            .unwrap();
+        app.update();
+        assert_eq!(
+            collector_commands.try_recv().unwrap().command,
+            RobotCommand::ExtractSample { x: 20, y: 5 }
+        );
+        assert!(app.rendezvous.is_busy(9));
+
+        app.event_sender
+            .with_source(EventSource::Robot(9))
+            .send(RobotEvent::SampleExtracted { id: 9, x: 20, y: 5 })
+            .unwrap();
+        app.update();
+        assert_eq!(
+            scientist_commands.try_recv().unwrap().command,
+            RobotCommand::SampleReady { x: 20, y: 5 }
+        );
+        assert_eq!(app.joint_samples, 1);
+        assert!(app.rendezvous.is_empty());
+    }
+
+    #[test]
+    fn test_a_time_boxed_run_stops_at_its_limit() {
+        let (mut app, _merges) = app_with_docking_robot();
+        app.set_tick_limit(Some(3));
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(app.tick, 3);
+        let summary = app.completion_summary.as_ref().unwrap();
+        assert_eq!(
+            (summary.outcome, summary.ticks),
+            (MissionOutcome::TimedOut, 3)
+        );
+    }
+
+    #[test]
+    fn test_memory_near_the_limit_is_reported() {
+        let (mut app, _merges) = app_with_docking_robot();
+        app.check_memory();
+        assert!(app.memory.knowledge > 0);
+        assert_eq!(app.memory_level, MemoryLevel::Fine);
+
+        app.event_sender
+            .send(RobotEvent::ReturnToBase { id: 7 })
+            .unwrap();
+        assert_eq!(app.memory_usage().queues, size_of::<EventEnvelope>());
+        app.set_memory_limit(MemorySettings {
+            limit_mb: 1,
+            warn_percent: 1,
+        });
+        app.check_memory();
+        assert_eq!(app.memory_level, MemoryLevel::Near);
+    }
+
+    #[test]
+    fn test_decommissioned_robots_refund_their_build_cost_once() {
+        let (mut app, merges) = app_with_docking_robot();
+        let (command_sender, _commands) = mpsc::channel();
+        let state = app.robots.get(7).unwrap().state.clone();
+        app.robots.remove(7);
+        app.robots.insert(RobotEntry::new(
+            RobotType::Exploration,
+            state,
+            command_sender,
+            mpsc::channel().0,
+        ));
+        drop(merges);
+        let minerals = |app: &App| {
+            app.collected_resources
+                .get(&ResourceType::MINERALS)
+                .copied()
+        };
+        let before = minerals(&app).unwrap_or(0);
+
+        assert!(app.decommission(7));
+        let shutdown = RobotEvent::Shutdown {
+            id: 7,
+            reason: ShutdownReason::Retired,
+        };
+        let robot = app.event_sender.with_source(EventSource::Robot(7));
+        robot.send(shutdown.clone()).unwrap();
+        robot.send(shutdown).unwrap();
+        app.update();
+
+        let refund =
+            config::EXPLORATION_ROBOT_BUILD_COST * config::DECOMMISSION_REFUND_PERCENT / 100;
+        assert_eq!(minerals(&app), Some(before + refund));
+        assert_eq!(app.refunded_minerals, refund);
+        assert_eq!(app.decommissioned_robots, 1);
+    }
+
+    #[test]
+    fn test_event_stats_count_gaps_repeats_and_latency() {
+        let (mut app, _merges) = app_with_docking_robot();
+        app.tick = 10;
+        let envelope = |source, sequence, sim_time| EventEnvelope {
+            source,
+            sequence,
+            sim_time,
+            event: RobotEvent::ReturnToBase { id: 7 },
+        };
+        let robot = EventSource::Robot(7);
+        for (source, sequence, sim_time) in [
+            (robot, 1, 10),
+            (robot, 4, 8), // 2 and 3 missing
+            (robot, 3, 6), // late, after 4
+            (robot, 4, 9), // repeated
+            (EventSource::Station, 1, 10),
+            (robot, 5, 10),
+        ] {
+            app.record_envelope(&envelope(source, sequence, sim_time));
+        }
+
+        let stats = &app.event_stats;
+        assert_eq!(stats.received, 6);
+        assert_eq!(stats.missing, 2);
+        assert_eq!(stats.out_of_order, 2);
+        assert_eq!(stats.total_latency_ticks, 2 + 4 + 1);
+        assert_eq!(stats.max_latency_ticks, 4);
+    }
+
+    #[test]
+    fn test_veins_stay_on_their_layer() {
+        let mut app = App::new(
+            30,
+            12,
+            3,
+            4,
+            RobotCounts {
+                exploration: 0,
+                collection: 0,
+                scientific: 0,
+                scout: 0,
+            },
+            SpawnPolicy::NearStation,
+            ModuleCatalog::default(),
+            Arc::default(),
+            Difficulty::Normal,
+            MapShape {
+                cave_layers: 1,
+                ..MapShape::default()
+            },
+        );
+        let (surface, bottom) = app.map.with_read(|map| (map.layer_rows(0), map.height));
+        assert!(bottom > surface.end);
+        let before: HashSet<(usize, usize)> =
+            app.map.read().get_all_resources().keys().copied().collect();
+
+        app.apply_world_event(WorldEvent::ResourceVein {
+            x: 10,
+            y: surface.end - 1,
+            radius: 4,
+            resource: "minerals".to_string(),
+            amount: 5,
+        });
+        let map = app.map.read();
+        let added: Vec<_> = map
+            .get_all_resources()
+            .keys()
+            .filter(|tile| !before.contains(tile))
+            .collect();
+        assert!(!added.is_empty());
+        assert!(added.iter().all(|(_, y)| surface.contains(y)));
+    }
+
+    #[test]
+    fn test_alarms_go_through_the_event_journal() {
+        let (mut app, _merges) = app_with_docking_robot();
+        let settings = AlarmSettings {
+            mineral_reserve: 10,
+            ..AlarmSettings::default()
+        };
+        app.set_alarms(settings, TICK_RATE);
+        app.update();
+        assert!(app.alarms.is_raised());
+        assert!(app.journal.is_empty());
+
+        // The alarm's event is handled with the next tick's
+        app.update();
+        let entry = app.journal.entries().last().unwrap();
+        assert_eq!(entry.severity, Severity::Alarm);
+        assert_eq!(entry.text, "Alarm: mineral reserve under 10");
+    }
+
+    #[cfg(feature = "debug-tools")]
+    #[test]
+    fn test_revealing_the_map_counts_each_tile_once() {
+        let (mut app, _merges) = app_with_docking_robot();
+        let (width, height, station) = app
+            .map
+            .with_read(|map| (map.width, map.height, map.station_coords()));
+        app.explored_tiles.insert((0, 0));
+        app.total_explored = 1;
+        assert_eq!(app.reveal_map(), width * height - 1);
+        assert_eq!(app.total_explored, width * height);
+        assert_eq!(app.reveal_map(), 0);
+        assert!(app
+            .place_resource(station, ResourceType::ENERGY, 5)
+            .is_err());
+    }
+
+    #[test]
+    fn test_position_updates_move_robots_without_touching_statistics() {
+        let (mut app, _merges) = app_with_docking_robot();
+        let energy = app.robots.get(7).unwrap().state.energy;
+        app.event_sender
+            .with_source(EventSource::Robot(7))
+            .send(RobotEvent::PositionUpdate {
+                id: 7,
+                x: 14,
+                y: 5,
+                energy: 42,
+                status: RobotStatus::Collecting,
+            })
+            .unwrap();
+        app.update();
+
+        let robot = &app.robots.get(7).unwrap().state;
+        assert_eq!((robot.x, robot.y, robot.energy), (14, 5, 42));
+        assert_eq!(robot.status, RobotStatus::Collecting);
+        assert!(app.collected_resources.is_empty());
+        assert_eq!((app.scientific_data, app.total_explored), (0, 0));
+        assert_eq!(
+            app.energy_summary(),
+            Some(EnergySummary {
+                min: 42,
+                average: 42
+            })
+        );
+        // Spent energy counts against the swarm's efficiency, nothing was delivered yet
+        assert_eq!(app.energy_spent, u64::from(energy - 42));
+        assert_eq!(app.live_kpis().efficiency, 0.0);
+    }
+
+    #[test]
+    fn test_cargo_of_a_robot_stopping_in_the_field_becomes_salvage() {
+        let (mut app, _merges) = app_with_docking_robot();
+        let tile = app.map.with_read(|map| {
+            (0..map.height)
+                .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+                .find(|&(x, y)| {
+                    !map.is_obstacle(x, y)
+                        && !map.is_station(x, y)
+                        && !map.is_shaft(x, y)
+                        && map.get_resource(x, y).is_none()
+                })
+                .unwrap()
+        });
+        app.map.with_write(|map| {
+            map.add_resource(tile.0, tile.1, ResourceType::MINERALS, 6);
+            map.try_take_resource(tile.0, tile.1, 6);
+        });
+        if let Some(state) = app.get_robot_state_mut(7) {
+            (state.x, state.y) = tile;
+        }
+        let robot = app.event_sender.with_source(EventSource::Robot(7));
+        let collect = |id| RobotEvent::CollectionData {
+            id,
+            x: tile.0,
+            y: tile.1,
+            resource_type: Some(ResourceType::MINERALS),
+            amount: 6,
+        };
+        robot.send(collect(7)).unwrap();
+        robot
+            .send(RobotEvent::Shutdown {
+                id: 7,
+                reason: ShutdownReason::LoopExited,
+            })
+            .unwrap();
+        app.update();
+
+        let map = app.map.read();
+        assert_eq!(
+            map.get_resource(tile.0, tile.1),
+            Some((ResourceType::MINERALS, 6))
+        );
+        assert!(map.is_salvage(tile.0, tile.1));
+        assert_eq!(map.tile_symbol(tile.0, tile.1), 'm');
+        drop(map);
+        assert_eq!(app.collected_resources[&ResourceType::MINERALS], 0);
+        // It was the last robot, with the map far from exhausted
+        assert_eq!(
+            app.completion_summary
+                .as_ref()
+                .map(|summary| summary.outcome),
+            Some(MissionOutcome::Failed)
+        );
+        let audit = app.ledger_audit();
+        assert_eq!((audit[0].dropped, audit[0].lost), (6, 0));
+        assert!(audit[0].balances() && audit[0].matches_map());
+
+        // Another collector picks the salvage up and the site is gone
+        assert_eq!(
+            app.map.try_collect_at(tile, &ResourceType::MINERALS, 10),
+            Some(6)
+        );
+        robot.send(collect(8)).unwrap();
+        app.update();
+        assert!(!app.map.read().is_salvage(tile.0, tile.1));
+        assert!(app.salvage_sites.is_empty());
+        assert_eq!(app.collected_resources[&ResourceType::MINERALS], 6);
+        assert!(app.ledger_audit()[0].balances());
+    }
 }
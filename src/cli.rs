@@ -0,0 +1,368 @@
+use clap::Parser;
+use std::{num::NonZeroUsize, path::PathBuf, str::FromStr, time::Duration};
+
+use crate::{
+    app::{GameSetup, MissionOutcome, RobotCounts, SpawnPolicy},
+    difficulty::Difficulty,
+    map::topology::MapShape,
+    robot::{core::science_score::ScienceTargeting, registry::RobotType},
+    settings::{InterfaceSettings, DEFAULT_SETTINGS_PATH},
+    station::charging::ChargingPolicy,
+};
+
+/// Command line options of the simulation
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Terminal simulation of a robot swarm exploring a planet"
+)]
+pub struct Cli {
+    /// Number of exploration robots to spawn
+    #[arg(long, default_value_t = 1)]
+    pub explorers: usize,
+
+    /// Number of collection robots to spawn
+    #[arg(long, default_value_t = 1)]
+    pub collectors: usize,
+
+    /// Number of scientific robots to spawn
+    #[arg(long, default_value_t = 1)]
+    pub scientists: usize,
+
+    /// Number of scout robots to spawn, shadowing the dust devils
+    #[arg(long, default_value_t = 0)]
+    pub scouts: usize,
+
+    /// Width of the generated map, in tiles
+    #[arg(long, default_value_t = 90)]
+    pub width: usize,
+
+    /// Height of the generated map, in tiles
+    #[arg(long, default_value_t = 15)]
+    pub height: usize,
+
+    /// Underground cave layers below the surface, reached through shafts
+    #[arg(long, default_value_t = 0)]
+    pub caves: usize,
+
+    /// Make the map wrap around at the edges, like a planet's surface
+    #[arg(long)]
+    pub wrap: bool,
+
+    /// Use hexagonal tiles with six neighbors instead of square ones
+    #[arg(long)]
+    pub hex: bool,
+
+    /// Seed of the terrain noise, defaults to the one in the settings
+    #[arg(long)]
+    pub map_seed: Option<u32>,
+
+    /// Seed of the resource placement, defaults to the one in the settings
+    #[arg(long)]
+    pub resource_seed: Option<u64>,
+
+    /// Start the simulation right away instead of showing the new-game screen
+    #[arg(long)]
+    pub no_setup: bool,
+
+    /// Where robots start: scattered over the map or next to the station
+    #[arg(long, value_enum, default_value_t = SpawnPolicy::Scattered)]
+    pub spawn: SpawnPolicy,
+
+    /// Settings file (TOML), ignored if it does not exist
+    #[arg(long, default_value = DEFAULT_SETTINGS_PATH)]
+    pub config: PathBuf,
+
+    /// Preset tuning energy costs, resource density, hazards and station drain
+    #[arg(long, value_enum, default_value_t = Difficulty::Normal)]
+    pub difficulty: Difficulty,
+
+    /// Number of dust devils wandering the map, draining the robots they reach; defaults to
+    /// the difficulty's
+    #[arg(long)]
+    pub hostiles: Option<usize>,
+
+    /// Scenario file (TOML) scheduling world events such as meteors and storms
+    #[arg(long, value_name = "PATH")]
+    pub scenario: Option<PathBuf>,
+
+    /// Experimental: robots drive on floating-point positions and headings between tiles
+    /// instead of hopping from tile to tile
+    #[arg(long)]
+    pub continuous: bool,
+
+    /// Tiles of knowledge passed each way when a robot docks, the most important first
+    /// (resources, then obstacles, then walkable ground); unlimited by default
+    #[arg(long, value_name = "TILES")]
+    pub merge_bandwidth: Option<usize>,
+
+    /// How scientists pick the science deposit to head for
+    #[arg(long, value_enum, default_value_t = ScienceTargeting::default(), value_name = "SCORER")]
+    pub science_targeting: ScienceTargeting,
+
+    /// Chargers at the station: docked robots queue for a free one and charge over a few
+    /// ticks; every robot charges as it docks by default
+    #[arg(long, value_name = "N")]
+    pub chargers: Option<NonZeroUsize>,
+
+    /// Which queued robot gets the next free charger
+    #[arg(long, value_enum, default_value_t = ChargingPolicy::default(), value_name = "POLICY")]
+    pub charging_policy: ChargingPolicy,
+
+    /// Ticks robots and the station trust a walkable or obstacle tile without seeing it again,
+    /// after which it is explored anew; trusted for good by default
+    #[arg(long, value_name = "TICKS")]
+    pub terrain_memory: Option<u64>,
+
+    /// Ticks to fast-forward before the first frame is drawn
+    #[arg(long, default_value_t = 0, value_name = "TICKS")]
+    pub warmup: u64,
+
+    /// Save the run every TICKS ticks, so it can be resumed after a crash; off at 0
+    #[arg(long, default_value_t = 0, value_name = "TICKS")]
+    pub autosave: u64,
+
+    /// Number of autosaves kept, older ones are removed
+    #[arg(long, default_value_t = 3, value_name = "N")]
+    pub autosave_keep: usize,
+
+    /// Resume the most recent autosave instead of starting a new run
+    #[arg(long)]
+    pub resume_latest: bool,
+
+    /// Record every robot's position at every tick and export them as CSV to PATH when
+    /// the run ends
+    #[arg(long, value_name = "PATH")]
+    pub trajectories: Option<PathBuf>,
+
+    /// Time rendering, event processing, map lock waits and robot decisions, and export the
+    /// figures as JSON to PATH when the run ends
+    #[arg(long, value_name = "PATH")]
+    pub profile: Option<PathBuf>,
+
+    /// Run a second simulation on the same seeds next to the first, configured by this
+    /// settings file (TOML), and compare how both do
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["listen", "connect", "resume_latest", "warmup", "autosave", "trajectories"]
+    )]
+    pub compare: Option<PathBuf>,
+
+    /// Stop the run after this long: a number of ticks, or of minutes with an `m` suffix
+    /// (`30m`), counted at the tick rate
+    #[arg(long, value_name = "TICKS|MINUTESm")]
+    pub duration: Option<RunDuration>,
+
+    /// Run without the UI until the mission ends, print its result as JSON and exit with
+    /// status 0 if it was completed, 1 if every robot stopped first, 3 if time was up
+    #[arg(long, conflicts_with_all = ["compare", "connect"])]
+    pub headless: bool,
+
+    /// Only write robot ID's debug messages to the log, the other robots' warnings and errors
+    /// still going through; highlighting a robot on the map follows it instead
+    #[arg(long, value_name = "ID")]
+    pub log_robot: Option<u32>,
+
+    /// Generate the map the options describe, print it with its station, resources and
+    /// regions, and exit without running the simulation
+    #[arg(long, conflicts_with_all = ["headless", "compare", "connect", "listen", "resume_latest"])]
+    pub preview: bool,
+
+    /// Have every robot record its last decisions, what it went by and what it chose, to
+    /// dump one's with the `trace` console command or the control API
+    #[arg(long)]
+    pub trace_decisions: bool,
+
+    /// Print the summaries of past runs and exit
+    #[arg(long)]
+    pub history: bool,
+
+    /// Accept robots running in other processes on this address, e.g. 0.0.0.0:7878
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Option<String>,
+
+    /// Run a single robot without the UI, joining the station listening on this address
+    #[arg(
+        long,
+        value_name = "ADDR",
+        requires = "robot",
+        conflicts_with = "listen"
+    )]
+    pub connect: Option<String>,
+
+    /// Type of the robot run with --connect
+    #[arg(long, value_enum, requires = "connect")]
+    pub robot: Option<RobotType>,
+
+    /// Serve the HTTP control API on this address, e.g. 127.0.0.1:8080
+    #[cfg(feature = "control-api")]
+    #[arg(long, value_name = "ADDR")]
+    pub control: Option<String>,
+}
+
+/// How long a time-boxed run lasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunDuration {
+    Ticks(u64),
+    Minutes(u64),
+}
+
+impl RunDuration {
+    /// Ticks the run lasts at `tick_rate`
+    pub fn ticks(self, tick_rate: Duration) -> u64 {
+        match self {
+            RunDuration::Ticks(ticks) => ticks,
+            RunDuration::Minutes(minutes) => {
+                let millis = u128::from(minutes) * 60_000 / tick_rate.as_millis().max(1);
+                u64::try_from(millis).unwrap_or(u64::MAX)
+            }
+        }
+    }
+}
+
+impl FromStr for RunDuration {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (number, duration): (_, fn(u64) -> Self) = match value.strip_suffix('m') {
+            Some(minutes) => (minutes, RunDuration::Minutes),
+            None => (value, RunDuration::Ticks),
+        };
+        match number.parse() {
+            Ok(0) | Err(_) => Err(format!(
+                "{} is neither a number of ticks nor of minutes such as 30m",
+                value
+            )),
+            Ok(amount) => Ok(duration(amount)),
+        }
+    }
+}
+
+/// Exit status of a headless run, by how its mission ended; 2 is left for invalid options
+pub fn exit_code(outcome: MissionOutcome) -> i32 {
+    match outcome {
+        MissionOutcome::Completed => 0,
+        MissionOutcome::Failed => 1,
+        MissionOutcome::TimedOut => 3,
+    }
+}
+
+impl Cli {
+    /// Checks option combinations clap cannot express
+    pub fn validate(&self) -> Result<(), String> {
+        if self.robot_counts().total() == 0 {
+            return Err("At least one robot must be spawned".to_string());
+        }
+        MapShape {
+            cave_layers: self.caves,
+            wraps: self.wrap,
+            hex: self.hex,
+        }
+        .validate(self.height)
+    }
+
+    pub fn robot_counts(&self) -> RobotCounts {
+        RobotCounts {
+            exploration: self.explorers,
+            collection: self.collectors,
+            scientific: self.scientists,
+            scout: self.scouts,
+        }
+    }
+
+    /// The new game described by the options, prefilling the setup screen
+    pub fn game_setup(&self, defaults: &InterfaceSettings) -> GameSetup {
+        GameSetup {
+            width: self.width,
+            height: self.height,
+            map_seed: self.map_seed.unwrap_or(defaults.map_seed),
+            resource_seed: self.resource_seed.unwrap_or(defaults.resource_seed),
+            robot_counts: self.robot_counts(),
+            difficulty: self.difficulty,
+            shape: MapShape {
+                cave_layers: self.caves,
+                wraps: self.wrap,
+                hex: self.hex,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robot_counts_default_to_one_of_each() {
+        let cli = Cli::parse_from(["astro-swarm"]);
+        assert_eq!(cli.robot_counts(), RobotCounts::default());
+        assert_eq!(
+            cli.game_setup(&InterfaceSettings::default()),
+            GameSetup::default()
+        );
+
+        let cli = Cli::parse_from(["astro-swarm", "--explorers", "4", "--scientists", "0"]);
+        assert_eq!(
+            cli.robot_counts(),
+            RobotCounts {
+                exploration: 4,
+                collection: 1,
+                scientific: 0,
+                scout: 0,
+            }
+        );
+        assert!(cli.validate().is_ok());
+
+        let cli = Cli::parse_from([
+            "astro-swarm",
+            "--explorers",
+            "0",
+            "--collectors",
+            "0",
+            "--scientists",
+            "0",
+        ]);
+        assert!(cli.validate().is_err());
+    }
+
+    #[test]
+    fn test_connect_needs_a_robot_type() {
+        assert!(Cli::try_parse_from(["astro-swarm", "--connect", "127.0.0.1:7878"]).is_err());
+        let cli = Cli::try_parse_from([
+            "astro-swarm",
+            "--connect",
+            "127.0.0.1:7878",
+            "--robot",
+            "collection",
+        ])
+        .unwrap();
+        assert_eq!(cli.robot, Some(RobotType::Collection));
+    }
+
+    #[test]
+    fn test_duration_is_given_in_ticks_or_minutes() {
+        let cli = Cli::parse_from(["astro-swarm", "--duration", "5000", "--headless"]);
+        assert_eq!(cli.duration, Some(RunDuration::Ticks(5000)));
+        assert!(cli.headless);
+
+        let minutes: RunDuration = "2m".parse().unwrap();
+        assert_eq!(minutes.ticks(Duration::from_millis(100)), 1200);
+        assert!("0".parse::<RunDuration>().is_err());
+        assert!("2h".parse::<RunDuration>().is_err());
+    }
+
+    #[test]
+    fn test_comparison_runs_alone_in_the_process() {
+        let cli = Cli::try_parse_from(["astro-swarm", "--compare", "greedy.toml"]).unwrap();
+        assert_eq!(cli.compare, Some(PathBuf::from("greedy.toml")));
+        assert!(Cli::try_parse_from([
+            "astro-swarm",
+            "--compare",
+            "greedy.toml",
+            "--listen",
+            "0.0.0.0:7878"
+        ])
+        .is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use crate::communication::channels::RobotEvent;
+
+/// Something that wants to observe the robot events processed by the App
+pub trait EventSubscriber {
+    fn on_event(&mut self, event: &RobotEvent);
+}
+
+/// Fans out every event handled by `App::update` to the registered subscribers, in order
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Box<dyn EventSubscriber>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, subscriber: Box<dyn EventSubscriber>) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn publish(&mut self, event: &RobotEvent) {
+        for subscriber in &mut self.subscribers {
+            subscriber.on_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(Arc<Mutex<Vec<u32>>>);
+
+    impl EventSubscriber for Recorder {
+        fn on_event(&mut self, event: &RobotEvent) {
+            if let RobotEvent::ReturnToBase { id } = event {
+                self.0.lock().unwrap().push(*id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_publish_reaches_every_subscriber_in_order() {
+        let first = Arc::new(Mutex::new(Vec::new()));
+        let second = Arc::new(Mutex::new(Vec::new()));
+        let mut bus = EventBus::new();
+        bus.subscribe(Box::new(Recorder(first.clone())));
+        bus.subscribe(Box::new(Recorder(second.clone())));
+
+        bus.publish(&RobotEvent::ReturnToBase { id: 1 });
+        bus.publish(&RobotEvent::ReturnToBase { id: 2 });
+
+        assert_eq!(*first.lock().unwrap(), vec![1, 2]);
+        assert_eq!(*second.lock().unwrap(), vec![1, 2]);
+    }
+}
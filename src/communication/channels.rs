@@ -1,15 +1,19 @@
-use crate::robot::core::knowledge::RobotKnowledge;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use crate::app::MissionOutcome;
+use crate::robot::core::knowledge::{RobotKnowledge, Sighting};
+use crate::robot::core::state::RobotStatus;
+use crate::robot::utils::config;
+use crate::robot::utils::preset::SwarmPreset;
+use crate::station::alarms::Alarm;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, SendError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 
-/// Types of resources robots can collect
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum ResourceType {
-    Energy,
-    Minerals,
-    SciencePoints,
-}
+pub use crate::map::resources::ResourceType;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RobotEvent {
     ExplorationData {
         id: u32,
@@ -17,6 +21,14 @@ pub enum RobotEvent {
         y: usize,
         is_obstacle: bool,
     },
+    /// The robot moved; reports where it is now, its energy left and what it is doing
+    PositionUpdate {
+        id: u32,
+        x: usize,
+        y: usize,
+        energy: u32,
+        status: RobotStatus,
+    },
     CollectionData {
         id: u32,
         x: usize,
@@ -46,14 +58,484 @@ pub enum RobotEvent {
     MergeComplete {
         id: u32,
         merged_knowledge: RobotKnowledge,
+        /// What the robot is to do on leaving the dock, boxed so events stay small
+        #[serde(default)]
+        brief: Box<DockingBrief>,
     },
     Shutdown {
         id: u32,
+        reason: ShutdownReason,
+    },
+    /// Sent by the station once every reachable tile is known
+    ExplorationComplete {
+        known_tiles: usize,
+    },
+    /// The robot carried out command `command_id`
+    CommandAck {
+        id: u32,
+        command_id: u64,
+    },
+    /// The robot cannot carry out command `command_id`
+    CommandRejected {
+        id: u32,
+        command_id: u64,
         reason: String,
     },
+    /// A scout reports the hostiles it currently sees
+    HostileSighted {
+        id: u32,
+        sightings: Vec<Sighting>,
+    },
+    /// Robot `id` stopped in the field and left `amount` of its cargo on (x, y) as salvage
+    SalvageDropped {
+        id: u32,
+        x: usize,
+        y: usize,
+        resource_type: ResourceType,
+        amount: u32,
+    },
+    /// Robot `id` picked up `amount` from the salvage on (x, y)
+    SalvageRecovered {
+        id: u32,
+        x: usize,
+        y: usize,
+        resource_type: ResourceType,
+        amount: u32,
+    },
+    /// Scientist `id` stands on the joint site (x, y) and waits for a collector's sample
+    RendezvousRequest {
+        id: u32,
+        x: usize,
+        y: usize,
+    },
+    /// Collector `id` extracted a sample on the joint site (x, y)
+    SampleExtracted {
+        id: u32,
+        x: usize,
+        y: usize,
+    },
+    /// Robot `id` reported its dock `attempts` times without a MergeComplete and went on with
+    /// the knowledge it had
+    MergeAbandoned {
+        id: u32,
+        attempts: u32,
+    },
+    /// A thread panicked while holding the map lock, `recoveries` times since the last
+    /// report; the map was taken over as left and the simulation went on
+    MapLockRecovered {
+        recoveries: u64,
+    },
+    /// The station's watch on the swarm's health raised `alarm`, or cleared it
+    SwarmAlarm {
+        alarm: Alarm,
+        raised: bool,
+    },
+    /// The station's energy stock fell under the notification threshold
+    StationEnergyLow {
+        stock: u32,
+    },
+    /// The run ended, published straight to the event bus
+    MissionComplete {
+        outcome: MissionOutcome,
+    },
+}
+
+impl RobotEvent {
+    /// Robot the event is about, if any
+    pub fn robot_id(&self) -> Option<u32> {
+        match self {
+            RobotEvent::ExplorationData { id, .. }
+            | RobotEvent::PositionUpdate { id, .. }
+            | RobotEvent::CollectionData { id, .. }
+            | RobotEvent::ScienceData { id, .. }
+            | RobotEvent::LowEnergy { id, .. }
+            | RobotEvent::ReturnToBase { id }
+            | RobotEvent::ArrivedAtStation { id, .. }
+            | RobotEvent::MergeComplete { id, .. }
+            | RobotEvent::Shutdown { id, .. }
+            | RobotEvent::CommandAck { id, .. }
+            | RobotEvent::CommandRejected { id, .. }
+            | RobotEvent::HostileSighted { id, .. }
+            | RobotEvent::SalvageDropped { id, .. }
+            | RobotEvent::SalvageRecovered { id, .. }
+            | RobotEvent::RendezvousRequest { id, .. }
+            | RobotEvent::SampleExtracted { id, .. }
+            | RobotEvent::MergeAbandoned { id, .. } => Some(*id),
+            RobotEvent::ExplorationComplete { .. }
+            | RobotEvent::MapLockRecovered { .. }
+            | RobotEvent::SwarmAlarm { .. }
+            | RobotEvent::StationEnergyLow { .. }
+            | RobotEvent::MissionComplete { .. } => None,
+        }
+    }
+}
+
+/// Why a robot stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShutdownReason {
+    /// The station retired it
+    Retired,
+    /// The station hung up on it
+    StationClosed,
+    /// Its loop ended on its own, e.g. with its battery empty
+    LoopExited,
+    /// Reported by the station for a remote robot whose connection dropped
+    ConnectionLost,
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ShutdownReason::Retired => "Retired",
+            ShutdownReason::StationClosed => "Station closed",
+            ShutdownReason::LoopExited => "Thread loop exited",
+            ShutdownReason::ConnectionLost => "Connection lost",
+        })
+    }
+}
+
+/// Orders sent by the station to a single robot over its command channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RobotCommand {
+    /// Stop the current task and head back to the station
+    ReturnToStation,
+    /// Collection robots only: harvest another resource type
+    SetTargetResource(ResourceType),
+    /// Walk to the given tile, then resume the current task
+    MoveTo { x: usize, y: usize },
+    /// Exploration robots only: visit the tiles in order, over and over; an empty route stops
+    /// patrolling
+    Patrol(Vec<(usize, usize)>),
+    /// Exploration robots only: dock at the station and stay there until given a new
+    /// waypoint or patrol
+    Park,
+    /// Head back to the station, dock one last time and stop the robot's thread
+    Retire,
+    /// The station replaced the robot's worn battery while it was docked
+    RefitBattery,
+    /// Hours of operation wore the robot down: its actions cost `percent` more energy
+    Wear { percent: u32 },
+    /// Stay docked for `ticks` while the station services the robot, clearing its wear
+    Service { ticks: u64 },
+    /// A charger freed up for the robot waiting docked: charge for `ticks`, then go on
+    Charge { ticks: u64 },
+    /// Neither enter nor target these tiles any more, in place of the previous no-go zones
+    AvoidTiles(Vec<(usize, usize)>),
+    /// A meteor struck around (x, y): forget the tiles within `radius`, and lose `damage`
+    /// energy if standing among them
+    MeteorImpact {
+        x: usize,
+        y: usize,
+        radius: usize,
+        damage: u32,
+    },
+    /// A solar flare hit: garble about `scramble_percent` % of the known tiles, picked with
+    /// `seed`
+    SolarFlare { scramble_percent: u32, seed: u64 },
+    /// Collection robots only: walk to the joint site (x, y) and extract a sample there
+    ExtractSample { x: usize, y: usize },
+    /// Scientific robots only: a collector extracted the sample awaited on (x, y)
+    SampleReady { x: usize, y: usize },
+    /// Debug console: stand on (x, y) at once
+    Teleport { x: usize, y: usize },
+    /// Debug console: set the battery to this level, up to its maximum
+    SetEnergy(u32),
+    /// Play the robot's thresholds and pace as the swarm's new strategy has it
+    SetPreset(SwarmPreset),
+}
+
+impl RobotCommand {
+    /// Name of the command, without its arguments
+    pub fn name(&self) -> &'static str {
+        match self {
+            RobotCommand::ReturnToStation => "ReturnToStation",
+            RobotCommand::SetTargetResource(_) => "SetTargetResource",
+            RobotCommand::MoveTo { .. } => "MoveTo",
+            RobotCommand::Patrol(_) => "Patrol",
+            RobotCommand::Park => "Park",
+            RobotCommand::Retire => "Retire",
+            RobotCommand::RefitBattery => "RefitBattery",
+            RobotCommand::Wear { .. } => "Wear",
+            RobotCommand::Service { .. } => "Service",
+            RobotCommand::Charge { .. } => "Charge",
+            RobotCommand::AvoidTiles(_) => "AvoidTiles",
+            RobotCommand::MeteorImpact { .. } => "MeteorImpact",
+            RobotCommand::SolarFlare { .. } => "SolarFlare",
+            RobotCommand::ExtractSample { .. } => "ExtractSample",
+            RobotCommand::SampleReady { .. } => "SampleReady",
+            RobotCommand::Teleport { .. } => "Teleport",
+            RobotCommand::SetEnergy(_) => "SetEnergy",
+            RobotCommand::SetPreset(_) => "SetPreset",
+        }
+    }
+}
+
+/// What the station hands a docked robot along with the merged knowledge, so robots are
+/// coordinated at the dock rather than through orders chasing them in the field
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockingBrief {
+    /// Orders to carry out on leaving the dock, in turn, like commands but unacknowledged
+    pub assignments: Vec<RobotCommand>,
+    /// Tiles the station sent other robots to, left to them
+    pub claims: Vec<(usize, usize)>,
+    /// No-go tiles in place of the previous ones, `None` keeps them
+    pub exclusions: Option<Vec<(usize, usize)>>,
+    pub overrides: ConfigOverrides,
+    /// Every charger is taken: stay docked, uncharged, until a `Charge` command
+    pub charger_queued: bool,
+}
+
+/// Settings the station runs with, for robots to run with too, e.g. in another process;
+/// those left unset are kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigOverrides {
+    pub time_scale: Option<u32>,
+    pub energy_cost_percent: Option<u32>,
+    pub merge_timeout_ms: Option<u64>,
+    pub merge_retries: Option<u32>,
+}
+
+impl ConfigOverrides {
+    /// The settings of this process
+    pub fn current() -> Self {
+        Self {
+            time_scale: Some(config::time_scale()),
+            energy_cost_percent: Some(config::energy_cost_percent()),
+            merge_timeout_ms: Some(config::merge_timeout().as_millis() as u64),
+            merge_retries: Some(config::merge_retries()),
+        }
+    }
+
+    /// Makes the settings given those of this process
+    pub fn apply(&self) {
+        if let Some(scale) = self.time_scale {
+            config::set_time_scale(scale);
+        }
+        if let Some(percent) = self.energy_cost_percent {
+            config::set_energy_cost_percent(percent);
+        }
+        if let Some(timeout) = self.merge_timeout_ms {
+            config::set_merge_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(retries) = self.merge_retries {
+            config::set_merge_retries(retries);
+        }
+    }
 }
 
-/// Creates a new communication channel for robot-station communication
-pub fn create_channel() -> (Sender<RobotEvent>, Receiver<RobotEvent>) {
+/// Who sent an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventSource {
+    Robot(u32),
+    Station,
+    /// Injected from outside the simulation (tests, tools)
+    External,
+}
+
+/// A robot event with its origin, the sender's sequence number and the tick it was sent at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub source: EventSource,
+    /// Starts at 1 and grows by one with each event of the same sender
+    pub sequence: u64,
+    /// Simulation tick when the event was sent
+    pub sim_time: u64,
+    pub event: RobotEvent,
+}
+
+/// Simulation tick shared between the app, which advances it, and the event senders
+#[derive(Debug, Clone, Default)]
+pub struct SimClock(Arc<AtomicU64>);
+
+impl SimClock {
+    pub fn now(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, tick: u64) {
+        self.0.store(tick, Ordering::Relaxed);
+    }
+}
+
+/// Radio link between the robots and the station, shared by every sender of a channel.
+/// While it is down, robots hold their events back, in order, until it is restored.
+#[derive(Debug, Default)]
+struct RadioLink {
+    down: AtomicBool,
+    held: Mutex<Vec<EventEnvelope>>,
+    /// Events on the channel the station has not taken yet
+    queued: AtomicU64,
+}
+
+/// Sending half of the event channel; wraps each event in an `EventEnvelope`.
+///
+/// Clones share the sequence counter, `with_source` starts a new sender with its own.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    sender: Sender<EventEnvelope>,
+    source: EventSource,
+    sequence: Arc<AtomicU64>,
+    clock: SimClock,
+    link: Arc<RadioLink>,
+}
+
+impl EventSender {
+    /// A sender for another source on the same channel and clock
+    pub fn with_source(&self, source: EventSource) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            source,
+            sequence: Arc::new(AtomicU64::new(0)),
+            clock: self.clock.clone(),
+            link: self.link.clone(),
+        }
+    }
+
+    pub fn source(&self) -> EventSource {
+        self.source
+    }
+
+    pub fn clock(&self) -> &SimClock {
+        &self.clock
+    }
+
+    /// Passes on an envelope built by another sender, e.g. one received over the network
+    pub fn forward(&self, envelope: EventEnvelope) -> Result<(), SendError<EventEnvelope>> {
+        self.put(envelope)
+    }
+
+    pub fn send(&self, event: RobotEvent) -> Result<(), SendError<EventEnvelope>> {
+        let envelope = EventEnvelope {
+            source: self.source,
+            sequence: self.sequence.fetch_add(1, Ordering::Relaxed) + 1,
+            sim_time: self.clock.now(),
+            event,
+        };
+        if matches!(self.source, EventSource::Robot(_)) && self.link_down() {
+            let mut held = self.held();
+            // Checked again under the lock, in case the link came back meanwhile
+            if self.link_down() {
+                held.push(envelope);
+                return Ok(());
+            }
+        }
+        self.put(envelope)
+    }
+
+    /// Tells the link the station took an event off the channel
+    pub fn taken(&self) {
+        let _ = self
+            .link
+            .queued
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |queued| {
+                queued.checked_sub(1)
+            });
+    }
+
+    /// Events sent or held back and not taken by the station yet
+    pub fn backlog(&self) -> usize {
+        self.link.queued.load(Ordering::Relaxed) as usize + self.held().len()
+    }
+
+    /// Whether the robots' radio link is down
+    pub fn link_down(&self) -> bool {
+        self.link.down.load(Ordering::Relaxed)
+    }
+
+    /// Takes the robots' radio link down: from now on their events are held back
+    pub fn cut_link(&self) {
+        self.link.down.store(true, Ordering::Relaxed);
+    }
+
+    /// Brings the robots' radio link back up, first sending the events held meanwhile.
+    /// Returns how many there were.
+    pub fn restore_link(&self) -> Result<usize, SendError<EventEnvelope>> {
+        let mut held = self.held();
+        let count = held.len();
+        for envelope in held.drain(..) {
+            self.put(envelope)?;
+        }
+        self.link.down.store(false, Ordering::Relaxed);
+        Ok(count)
+    }
+
+    fn put(&self, envelope: EventEnvelope) -> Result<(), SendError<EventEnvelope>> {
+        // Counted first so the station cannot take it before it is counted
+        self.link.queued.fetch_add(1, Ordering::Relaxed);
+        self.sender.send(envelope).inspect_err(|_| self.taken())
+    }
+
+    fn held(&self) -> std::sync::MutexGuard<'_, Vec<EventEnvelope>> {
+        self.link
+            .held
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Creates a new communication channel for robot-station communication, with its own clock.
+/// The returned sender is `External`; use `with_source` to hand it to robots and the station.
+pub fn create_channel() -> (EventSender, Receiver<EventEnvelope>) {
+    let (sender, receiver) = channel();
+    let sender = EventSender {
+        sender,
+        source: EventSource::External,
+        sequence: Arc::new(AtomicU64::new(0)),
+        clock: SimClock::default(),
+        link: Arc::default(),
+    };
+    (sender, receiver)
+}
+
+/// A command with the id its acknowledgement refers to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandEnvelope {
+    pub id: u64,
+    pub command: RobotCommand,
+}
+
+/// Creates a new command channel from the station to a single robot
+pub fn create_command_channel() -> (Sender<CommandEnvelope>, Receiver<CommandEnvelope>) {
     channel()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robot_events_are_held_while_the_link_is_down() {
+        let (sender, receiver) = create_channel();
+        let robot = sender.with_source(EventSource::Robot(3));
+        sender.cut_link();
+        assert!(robot.link_down());
+
+        robot.send(RobotEvent::ReturnToBase { id: 3 }).unwrap();
+        robot
+            .send(RobotEvent::LowEnergy {
+                id: 3,
+                remaining: 5,
+            })
+            .unwrap();
+        // Only the robots' radio is down
+        sender.send(RobotEvent::ReturnToBase { id: 9 }).unwrap();
+        assert_eq!(sender.backlog(), 3);
+        let through: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(through.len(), 1);
+        sender.taken();
+        assert_eq!(sender.backlog(), 2);
+        assert_eq!(through[0].source, EventSource::External);
+
+        assert_eq!(sender.restore_link().unwrap(), 2);
+        assert!(!robot.link_down());
+        let held: Vec<u64> = receiver
+            .try_iter()
+            .map(|envelope| envelope.sequence)
+            .collect();
+        assert_eq!(held, vec![1, 2]);
+        robot.send(RobotEvent::ReturnToBase { id: 3 }).unwrap();
+        assert_eq!(receiver.try_recv().unwrap().sequence, 3);
+    }
+}
@@ -1 +1,3 @@
+pub mod bus;
 pub mod channels;
+pub mod tcp;
@@ -0,0 +1,465 @@
+//! TCP transport letting robots run in other processes or on other hosts.
+//!
+//! Both directions carry one JSON message per line. A robot process connects, sends
+//! `Join`, receives its `Assignment` and then streams its event envelopes; the station
+//! streams back commands, merge results and its clock.
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::communication::channels::{
+    create_channel, create_command_channel, CommandEnvelope, EventEnvelope, EventSender,
+    EventSource, RobotEvent, ShutdownReason, SimClock,
+};
+use crate::difficulty::Difficulty;
+use crate::error::{CommError, SimError};
+use crate::map::noise::Map;
+use crate::map::resources::ResourceCatalog;
+use crate::map::shared::SharedMap;
+use crate::map::topology::MapShape;
+use crate::robot::launcher::{self, RobotInbox, RobotLoadout};
+use crate::robot::registry::RobotType;
+use crate::robot::utils::inbox::InboxGauge;
+use crate::robot::utils::trace::DecisionTrace;
+use crate::robot::utils::{clock, config};
+
+/// How often the station tells remote robots the current tick
+const CLOCK_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a new connection may take to send its join
+const JOIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Messages from a robot process to the station
+#[derive(Debug, Serialize, Deserialize)]
+pub enum RobotMessage {
+    Join { robot_type: RobotType },
+    Event(EventEnvelope),
+}
+
+/// Messages from the station to a robot process
+#[derive(Debug, Serialize, Deserialize)]
+pub enum StationMessage {
+    Assignment(Assignment),
+    Clock(u64),
+    Command(CommandEnvelope),
+    Merge(RobotEvent),
+}
+
+/// Everything a robot process needs to build its robot and its copy of the map
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub id: u32,
+    pub x: usize,
+    pub y: usize,
+    pub map_width: usize,
+    /// Rows of the surface
+    pub map_height: usize,
+    /// Caves, wrapping edges and tile shape of the map
+    #[serde(default)]
+    pub shape: MapShape,
+    /// Robots drive on continuous poses, see `config::continuous_movement`
+    #[serde(default)]
+    pub continuous: bool,
+    pub map_seed: u32,
+    pub resource_seed: u64,
+    /// Shapes the map's resources and the robot's energy costs
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// Resource types of the run, which the robot's copy of the map and its messages use
+    #[serde(default)]
+    pub resources: ResourceCatalog,
+    pub loadout: RobotLoadout,
+    pub tick: u64,
+}
+
+fn write_message<T: Serialize>(stream: &mut TcpStream, message: &T) -> Result<(), CommError> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    Ok(stream.write_all(line.as_bytes())?)
+}
+
+fn read_message<T: for<'de> Deserialize<'de>>(
+    reader: &mut BufReader<TcpStream>,
+) -> Result<Option<T>, CommError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+/// A robot process that connected and is waiting for its assignment
+pub struct RemoteJoin {
+    pub robot_type: RobotType,
+    pub peer: SocketAddr,
+    reader: BufReader<TcpStream>,
+}
+
+impl RemoteJoin {
+    /// Sends the assignment and starts relaying: events from the robot go to `events`,
+    /// commands and merge results sent on the returned channels go to the robot, counted on
+    /// `gauge` as they leave. Events not about the assigned robot are dropped. If the
+    /// connection drops before the robot shut down, a `Shutdown` is reported in its name.
+    pub fn accept(
+        self,
+        assignment: Assignment,
+        events: EventSender,
+        clock: SimClock,
+        gauge: InboxGauge,
+    ) -> Result<(Sender<CommandEnvelope>, Sender<RobotEvent>), CommError> {
+        let mut stream = self.reader.get_ref().try_clone()?;
+        let id = assignment.id;
+        write_message(&mut stream, &StationMessage::Assignment(assignment))?;
+
+        let (command_sender, command_receiver) = create_command_channel();
+        let (merge_sender, merge_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            relay_to_robot(stream, command_receiver, merge_receiver, clock, gauge)
+        });
+
+        let mut reader = self.reader;
+        thread::spawn(move || {
+            let mut shut_down = false;
+            loop {
+                match read_message::<RobotMessage>(&mut reader) {
+                    Ok(Some(RobotMessage::Event(envelope)))
+                        if envelope.source != EventSource::Robot(id)
+                            || envelope.event.robot_id() != Some(id) =>
+                    {
+                        warn!(
+                            "Remote robot {} sent an event as {:?} about robot {:?}, dropping it",
+                            id,
+                            envelope.source,
+                            envelope.event.robot_id()
+                        );
+                    }
+                    Ok(Some(RobotMessage::Event(envelope))) => {
+                        shut_down |= matches!(envelope.event, RobotEvent::Shutdown { .. });
+                        if events.forward(envelope).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(Some(RobotMessage::Join { .. })) => {
+                        warn!("Remote robot {} sent a second join, ignoring", id);
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("Remote robot {}: {}", id, e);
+                        break;
+                    }
+                }
+            }
+            info!("Remote robot {} disconnected", id);
+            if !shut_down {
+                let _ = events.send(RobotEvent::Shutdown {
+                    id,
+                    reason: ShutdownReason::ConnectionLost,
+                });
+            }
+        });
+
+        Ok((command_sender, merge_sender))
+    }
+}
+
+/// Forwards commands, merge results and clock ticks until the station drops the robot, then
+/// closes the connection so the robot process stops too
+fn relay_to_robot(
+    mut stream: TcpStream,
+    commands: Receiver<CommandEnvelope>,
+    merges: Receiver<RobotEvent>,
+    clock: SimClock,
+    gauge: InboxGauge,
+) {
+    let mut last_tick = None;
+    loop {
+        let mut messages = Vec::new();
+        match commands.recv_timeout(CLOCK_INTERVAL) {
+            Ok(envelope) => {
+                gauge.took_command(&envelope.command);
+                messages.push(StationMessage::Command(envelope));
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                let _ = stream.shutdown(Shutdown::Both);
+                return;
+            }
+        }
+        messages.extend(merges.try_iter().map(|event| {
+            gauge.took_merge(&event);
+            StationMessage::Merge(event)
+        }));
+        let tick = clock.now();
+        if last_tick != Some(tick) {
+            last_tick = Some(tick);
+            messages.push(StationMessage::Clock(tick));
+        }
+        for message in messages {
+            if let Err(e) = write_message(&mut stream, &message) {
+                debug!("Stopped relaying to remote robot: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Station side: accepts robot processes in the background
+pub struct RemoteStation {
+    local_addr: SocketAddr,
+    joins: Receiver<RemoteJoin>,
+}
+
+impl RemoteStation {
+    pub fn listen(addr: impl ToSocketAddrs) -> Result<Self, CommError> {
+        let listener = TcpListener::bind(addr).map_err(|e| CommError::Listen {
+            service: "robots",
+            reason: e.to_string(),
+        })?;
+        let local_addr = listener.local_addr()?;
+        let (join_sender, joins) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept robot connection: {}", e);
+                        continue;
+                    }
+                };
+                let peer = match stream.peer_addr() {
+                    Ok(peer) => peer,
+                    Err(_) => continue,
+                };
+                // A silent peer must not hold up the robots connecting after it
+                let _ = stream.set_read_timeout(Some(JOIN_TIMEOUT));
+                let mut reader = BufReader::new(stream);
+                match read_message::<RobotMessage>(&mut reader) {
+                    Ok(Some(RobotMessage::Join { robot_type })) => {
+                        let _ = reader.get_ref().set_read_timeout(None);
+                        let join = RemoteJoin {
+                            robot_type,
+                            peer,
+                            reader,
+                        };
+                        if join_sender.send(join).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => warn!("{} did not start with a join, closing", peer),
+                    Err(e) => warn!("{}: {}", peer, e),
+                }
+            }
+        });
+        info!("Listening for remote robots on {}", local_addr);
+        Ok(Self { local_addr, joins })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Next robot process waiting to be assigned, if any
+    pub fn try_join(&self) -> Option<RemoteJoin> {
+        self.joins.try_recv().ok()
+    }
+}
+
+/// Robot side: joins the station at `addr` and runs one robot until it shuts down.
+///
+/// The robot works on its own copy of the map, built from the station's seeds; resources
+/// collected by robots in other processes stay on it.
+pub fn run_remote_robot(addr: impl ToSocketAddrs, robot_type: RobotType) -> Result<(), SimError> {
+    let mut stream = TcpStream::connect(addr).map_err(CommError::Unreachable)?;
+    write_message(&mut stream, &RobotMessage::Join { robot_type })?;
+    let mut reader = BufReader::new(stream.try_clone().map_err(CommError::from)?);
+    let assignment = match read_message::<StationMessage>(&mut reader)? {
+        Some(StationMessage::Assignment(assignment)) => assignment,
+        Some(other) => {
+            return Err(
+                CommError::Unexpected(format!("expected an assignment, got {:?}", other)).into(),
+            )
+        }
+        None => return Err(CommError::Closed.into()),
+    };
+    info!(
+        "Joined the station as {:?} robot {}",
+        robot_type, assignment.id
+    );
+
+    let profile = assignment.difficulty.profile();
+    config::set_energy_cost_percent(profile.energy_cost_percent);
+    config::set_continuous_movement(assignment.continuous);
+    let map = Map::generate(
+        assignment.map_width,
+        assignment.map_height,
+        assignment.map_seed,
+        assignment.resource_seed,
+        profile.tiles_per_resource,
+        assignment.shape,
+        Arc::new(assignment.resources),
+    );
+    let (sender, events) = create_channel();
+    sender.clock().set(assignment.tick);
+    let clock = sender.clock().clone();
+    let robot_sender = sender.with_source(EventSource::Robot(assignment.id));
+    drop(sender);
+
+    let (command_sender, command_receiver) = create_command_channel();
+    let (merge_sender, merge_receiver) = mpsc::channel();
+    let handle = launcher::launch(
+        robot_type,
+        launcher::initial_state(robot_type, assignment.id, assignment.x, assignment.y),
+        &assignment.loadout,
+        RobotInbox {
+            merge_receiver,
+            command_receiver,
+            // The station gauges this robot's inbox on its relay
+            gauge: InboxGauge::new(),
+            // Traces are read in the station's process, a remote robot keeps none
+            trace: DecisionTrace::default(),
+        },
+        robot_sender,
+        SharedMap::new(map),
+        clock::real(),
+    );
+
+    thread::spawn(move || loop {
+        match read_message::<StationMessage>(&mut reader) {
+            Ok(Some(StationMessage::Clock(tick))) => clock.set(tick),
+            Ok(Some(StationMessage::Command(envelope))) => {
+                let _ = command_sender.send(envelope);
+            }
+            Ok(Some(StationMessage::Merge(event))) => {
+                // Robots in the station's process share its settings already
+                if let RobotEvent::MergeComplete { brief, .. } = &event {
+                    brief.overrides.apply();
+                }
+                let _ = merge_sender.send(event);
+            }
+            Ok(Some(StationMessage::Assignment(_))) => {
+                warn!("Ignoring a second assignment from the station");
+            }
+            Ok(None) => return,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        }
+    });
+
+    // Ends once the robot thread exits and drops its sender
+    for envelope in events {
+        write_message(&mut stream, &RobotMessage::Event(envelope))?;
+    }
+    if handle.join().is_err() {
+        return Err(SimError::RobotPanicked(assignment.id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::core::knowledge::{RobotKnowledge, TileInfo};
+
+    #[test]
+    fn test_remote_robot_events_reach_the_station() {
+        let station = RemoteStation::listen("127.0.0.1:0").unwrap();
+        let mut robot = TcpStream::connect(station.local_addr()).unwrap();
+        write_message(
+            &mut robot,
+            &RobotMessage::Join {
+                robot_type: RobotType::Exploration,
+            },
+        )
+        .unwrap();
+
+        let join = loop {
+            if let Some(join) = station.try_join() {
+                break join;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!(join.robot_type, RobotType::Exploration);
+
+        let (sender, events) = create_channel();
+        let assignment = Assignment {
+            id: 4,
+            x: 1,
+            y: 2,
+            map_width: 10,
+            map_height: 5,
+            shape: MapShape {
+                cave_layers: 1,
+                wraps: true,
+                hex: true,
+            },
+            continuous: true,
+            map_seed: 1,
+            resource_seed: 2,
+            difficulty: Difficulty::Harsh,
+            resources: ResourceCatalog::default(),
+            loadout: RobotLoadout::default(),
+            tick: 0,
+        };
+        let (commands, _merges) = join
+            .accept(
+                assignment.clone(),
+                sender.with_source(EventSource::Station),
+                sender.clock().clone(),
+                InboxGauge::new(),
+            )
+            .unwrap();
+
+        let mut reader = BufReader::new(robot.try_clone().unwrap());
+        match read_message::<StationMessage>(&mut reader).unwrap() {
+            Some(StationMessage::Assignment(received)) => assert_eq!(received, assignment),
+            other => panic!("Expected an assignment, got {:?}", other),
+        }
+
+        // Events in the name of another robot never reach the station
+        for (source, id) in [(EventSource::Robot(4), 3), (EventSource::Robot(3), 3)] {
+            let spoofed = EventEnvelope {
+                source,
+                sequence: 1,
+                sim_time: 0,
+                event: RobotEvent::Shutdown {
+                    id,
+                    reason: ShutdownReason::Retired,
+                },
+            };
+            write_message(&mut robot, &RobotMessage::Event(spoofed)).unwrap();
+        }
+
+        let mut knowledge = RobotKnowledge::new(10, 5);
+        knowledge.update_tile(3, 1, TileInfo::Obstacle);
+        let envelope = EventEnvelope {
+            source: EventSource::Robot(4),
+            sequence: 1,
+            sim_time: 0,
+            event: RobotEvent::ArrivedAtStation { id: 4, knowledge },
+        };
+        write_message(&mut robot, &RobotMessage::Event(envelope)).unwrap();
+        let received = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(received.source, EventSource::Robot(4));
+        match received.event {
+            RobotEvent::ArrivedAtStation { knowledge, .. } => {
+                assert_eq!(knowledge.get_tile(3, 1), &TileInfo::Obstacle);
+                assert_eq!(knowledge.get_tile(0, 0), &TileInfo::Unknown);
+            }
+            other => panic!("Expected ArrivedAtStation, got {:?}", other),
+        }
+
+        drop(robot);
+        drop(reader);
+        let lost = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(lost.source, EventSource::Station);
+        assert!(matches!(lost.event, RobotEvent::Shutdown { id: 4, .. }));
+        drop(commands);
+    }
+}
@@ -0,0 +1,426 @@
+//! Command console of the simulation screen, opened with `:` like in vim, for debugging and
+//! demos. Cheats are only there with the `debug-tools` feature.
+
+use std::path::PathBuf;
+
+use crate::{
+    app::App,
+    communication::channels::{ResourceType, RobotCommand},
+    map::resources::ResourceCatalog,
+    robot::{
+        registry::RobotType,
+        utils::{config, preset::SwarmPreset, trace},
+    },
+    save::SaveGame,
+    station::charging::ChargingPolicy,
+};
+
+/// Commands understood by the console, one per line typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsoleCommand {
+    /// `spawn <type> [count]`
+    Spawn { robot_type: RobotType, count: usize },
+    /// `recall <id>`
+    Recall { id: u32 },
+    /// `convert <id> <type>`
+    Convert { id: u32, robot_type: RobotType },
+    /// `note <text>`, on the cursor tile
+    Note { text: String },
+    /// `unnote`: removes the cursor tile's note
+    Unnote,
+    /// `save <path>`
+    Save { path: PathBuf },
+    /// `preset <name>`, see `SwarmPreset::name`
+    Preset { preset: SwarmPreset },
+    /// `trace <id> [path]`: writes the robot's last decisions as JSON
+    Trace { id: u32, path: Option<PathBuf> },
+    /// `charging <policy>`, see `ChargingPolicy::name`
+    Charging { policy: ChargingPolicy },
+    /// `gather <id> <resource>`: the collector's new target, by resource name
+    Gather {
+        id: u32,
+        resource_type: ResourceType,
+    },
+    /// `help`
+    Help,
+    /// `teleport <id> <x> <y>`
+    #[cfg(feature = "debug-tools")]
+    Teleport { id: u32, x: usize, y: usize },
+    /// `set energy <id> <amount>`
+    #[cfg(feature = "debug-tools")]
+    SetEnergy { id: u32, energy: u32 },
+    /// `refill <id>`, or `refill all`
+    #[cfg(feature = "debug-tools")]
+    Refill { id: Option<u32> },
+    /// `reveal`: the station learns the whole map
+    #[cfg(feature = "debug-tools")]
+    Reveal,
+    /// `resource <type> <amount>`, on the cursor tile
+    #[cfg(feature = "debug-tools")]
+    Resource {
+        resource_type: ResourceType,
+        amount: u32,
+    },
+}
+
+const USAGE: &str =
+    "spawn <type> [count] | recall <id> | convert <id> <type> | note <text> | unnote | save <path> \
+     | preset aggressive_exploration|balanced|conservative_energy | trace <id> [path] \
+     | charging fifo|lowest-energy|mission-critical | gather <id> <resource>";
+#[cfg(feature = "debug-tools")]
+const CHEATS_USAGE: &str = "teleport <id> <x> <y> | set energy <id> <amount> | \
+                            refill <id>|all | reveal | resource <type> <amount>";
+
+impl ConsoleCommand {
+    /// Reads `line`, resources going by their names in `resources`
+    pub fn parse(line: &str, resources: &ResourceCatalog) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["spawn", robot_type, rest @ ..] if rest.len() <= 1 => Ok(Self::Spawn {
+                robot_type: parse_robot_type(robot_type)?,
+                count: rest.first().map_or(Ok(1), |count| number(count, "count"))?,
+            }),
+            ["recall", id] => Ok(Self::Recall {
+                id: number(id, "robot id")?,
+            }),
+            ["convert", id, robot_type] => Ok(Self::Convert {
+                id: number(id, "robot id")?,
+                robot_type: parse_robot_type(robot_type)?,
+            }),
+            ["note", text @ ..] if !text.is_empty() => Ok(Self::Note {
+                text: text.join(" "),
+            }),
+            ["unnote"] => Ok(Self::Unnote),
+            ["save", path] => Ok(Self::Save {
+                path: PathBuf::from(path),
+            }),
+            ["preset", name] => Ok(Self::Preset {
+                preset: SwarmPreset::from_name(name).ok_or_else(|| {
+                    format!(
+                        "{} is not a preset: aggressive_exploration, balanced or conservative_energy",
+                        name
+                    )
+                })?,
+            }),
+            ["trace", id, rest @ ..] if rest.len() <= 1 => Ok(Self::Trace {
+                id: number(id, "robot id")?,
+                path: rest.first().map(PathBuf::from),
+            }),
+            ["charging", name] => Ok(Self::Charging {
+                policy: ChargingPolicy::from_name(name).ok_or_else(|| {
+                    format!(
+                        "{} is not a charging policy: fifo, lowest-energy or mission-critical",
+                        name
+                    )
+                })?,
+            }),
+            ["gather", id, name] => {
+                let resource_type = parse_resource(name, resources)?;
+                if !resources.is_consumable(resource_type) {
+                    return Err(format!(
+                        "{} is analyzed, not gathered",
+                        resources.name(resource_type)
+                    ));
+                }
+                Ok(Self::Gather {
+                    id: number(id, "robot id")?,
+                    resource_type,
+                })
+            }
+            ["help"] => Ok(Self::Help),
+            [] => Err("Type a command, or help".to_string()),
+            #[cfg(feature = "debug-tools")]
+            words => Self::parse_cheat(words, resources),
+            #[cfg(not(feature = "debug-tools"))]
+            _ => Err(format!("Unknown command, try {}", USAGE)),
+        }
+    }
+
+    #[cfg(feature = "debug-tools")]
+    fn parse_cheat(words: &[&str], resources: &ResourceCatalog) -> Result<Self, String> {
+        match words {
+            ["teleport", id, x, y] => Ok(Self::Teleport {
+                id: number(id, "robot id")?,
+                x: number(x, "x")?,
+                y: number(y, "y")?,
+            }),
+            ["set", "energy", id, energy] => Ok(Self::SetEnergy {
+                id: number(id, "robot id")?,
+                energy: number(energy, "energy")?,
+            }),
+            ["refill", "all"] => Ok(Self::Refill { id: None }),
+            ["refill", id] => Ok(Self::Refill {
+                id: Some(number(id, "robot id")?),
+            }),
+            ["reveal"] => Ok(Self::Reveal),
+            ["resource", resource_type, amount] => Ok(Self::Resource {
+                resource_type: parse_resource(resource_type, resources)?,
+                amount: number(amount, "amount")?,
+            }),
+            _ => Err(format!("Unknown command, try {} | {}", USAGE, CHEATS_USAGE)),
+        }
+    }
+
+    /// Carries the command out on `app`, returns what to tell the user. Notes and resources
+    /// are put on the `cursor` tile.
+    pub fn execute(self, app: &mut App, cursor: (usize, usize)) -> Result<String, String> {
+        match self {
+            Self::Spawn { robot_type, count } => {
+                let ids = (0..count)
+                    .map(|_| app.spawn_robot(robot_type))
+                    .collect::<Result<Vec<u32>, _>>()
+                    .map_err(|e| e.to_string())?;
+                Ok(format!("Spawned {:?} robots {:?}", robot_type, ids))
+            }
+            Self::Recall { id } => {
+                command(app, id, RobotCommand::ReturnToStation)?;
+                Ok(format!("Recalled robot {}", id))
+            }
+            Self::Convert { id, robot_type } => {
+                app.convert_robot(id, robot_type)
+                    .map_err(|e| e.to_string())?;
+                Ok(format!("Robot {} docks to become a {:?}", id, robot_type))
+            }
+            Self::Note { text } => {
+                app.annotate(cursor, &text).map_err(|e| e.to_string())?;
+                Ok(format!("Noted {:?} on {:?}", text, cursor))
+            }
+            Self::Unnote => {
+                if app.clear_annotation(cursor) {
+                    Ok(format!("Removed the note on {:?}", cursor))
+                } else {
+                    Err(format!("No note on {:?}", cursor))
+                }
+            }
+            Self::Save { path } => {
+                SaveGame::from_app(app)?.write(&path)?;
+                Ok(format!("Saved to {}", path.display()))
+            }
+            Self::Preset { preset } => {
+                app.set_preset(preset);
+                Ok(format!("Swarm preset: {}", preset.label()))
+            }
+            Self::Trace { id, path } => {
+                let decisions = app.decision_trace(id).map_err(|e| e.to_string())?;
+                if !config::decision_traces() {
+                    return Err("Decision traces are off, start with --trace-decisions".to_string());
+                }
+                let path =
+                    path.unwrap_or_else(|| PathBuf::from(format!("astro-swarm-trace-{}.json", id)));
+                trace::write_json(&decisions, &path)?;
+                Ok(format!(
+                    "Wrote robot {}'s last {} decisions to {}",
+                    id,
+                    decisions.len(),
+                    path.display()
+                ))
+            }
+            Self::Charging { policy } => {
+                app.set_charging_policy(policy);
+                if app.charging.is_limited() {
+                    Ok(format!("Charging policy: {}", policy.name()))
+                } else {
+                    Ok(format!(
+                        "Charging policy: {}, used once chargers run short (--chargers)",
+                        policy.name()
+                    ))
+                }
+            }
+            Self::Gather { id, resource_type } => {
+                app.target_resource(id, resource_type)
+                    .map_err(|e| e.to_string())?;
+                Ok(format!(
+                    "Robot {} gathers {}",
+                    id,
+                    app.resource_catalog().name(resource_type)
+                ))
+            }
+            #[cfg(not(feature = "debug-tools"))]
+            Self::Help => Ok(USAGE.to_string()),
+            #[cfg(feature = "debug-tools")]
+            Self::Help => Ok(format!("{} | {}", USAGE, CHEATS_USAGE)),
+            #[cfg(feature = "debug-tools")]
+            Self::Teleport { id, x, y } => {
+                app.map
+                    .with_read(|map| map.check_walkable(x, y))
+                    .map_err(|e| e.to_string())?;
+                command(app, id, RobotCommand::Teleport { x, y })?;
+                Ok(format!("Teleporting robot {} to ({}, {})", id, x, y))
+            }
+            #[cfg(feature = "debug-tools")]
+            Self::SetEnergy { id, energy } => {
+                command(app, id, RobotCommand::SetEnergy(energy))?;
+                Ok(format!("Setting robot {}'s energy to {}", id, energy))
+            }
+            #[cfg(feature = "debug-tools")]
+            Self::Refill { id: Some(id) } => {
+                command(app, id, RobotCommand::SetEnergy(u32::MAX))?;
+                Ok(format!("Refilling robot {}", id))
+            }
+            #[cfg(feature = "debug-tools")]
+            Self::Refill { id: None } => {
+                let ids = app.robot_ids();
+                for &id in &ids {
+                    app.send_command(id, RobotCommand::SetEnergy(u32::MAX));
+                }
+                Ok(format!("Refilling {} robots", ids.len()))
+            }
+            #[cfg(feature = "debug-tools")]
+            Self::Reveal => Ok(format!("Revealed {} tiles", app.reveal_map())),
+            #[cfg(feature = "debug-tools")]
+            Self::Resource {
+                resource_type,
+                amount,
+            } => {
+                app.place_resource(cursor, resource_type, amount)
+                    .map_err(|e| e.to_string())?;
+                Ok(format!(
+                    "Put {} {} on ({}, {})",
+                    amount,
+                    app.resource_catalog().name(resource_type),
+                    cursor.0,
+                    cursor.1
+                ))
+            }
+        }
+    }
+}
+
+/// Parses and runs one console line, see `ConsoleCommand::execute`
+pub fn run(line: &str, app: &mut App, cursor: (usize, usize)) -> Result<String, String> {
+    ConsoleCommand::parse(line, app.resource_catalog())?.execute(app, cursor)
+}
+
+fn command(app: &mut App, id: u32, command: RobotCommand) -> Result<(), String> {
+    if app.send_command(id, command) {
+        Ok(())
+    } else {
+        Err(format!("No robot {}", id))
+    }
+}
+
+fn number<T: std::str::FromStr>(word: &str, what: &str) -> Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("{} is not a valid {}", word, what))
+}
+
+fn parse_robot_type(word: &str) -> Result<RobotType, String> {
+    match word {
+        "explorer" | "exploration" => Ok(RobotType::Exploration),
+        "collector" | "collection" => Ok(RobotType::Collection),
+        "scientist" | "scientific" => Ok(RobotType::Scientific),
+        "scout" => Ok(RobotType::Scout),
+        _ => Err(format!(
+            "{} is not a robot type: explorer, collector, scientist or scout",
+            word
+        )),
+    }
+}
+
+/// A resource type by name, `science` standing for science points
+fn parse_resource(word: &str, resources: &ResourceCatalog) -> Result<ResourceType, String> {
+    if word == "science" {
+        return Ok(ResourceType::SCIENCE_POINTS);
+    }
+    resources.from_name(word).ok_or_else(|| {
+        let names: Vec<String> = resources
+            .all()
+            .into_iter()
+            .map(|resource_type| resources.name(resource_type).to_lowercase())
+            .collect();
+        format!("{} is not a resource: {}", word, names.join(", "))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_lines_parse_into_commands() {
+        assert_eq!(
+            ConsoleCommand::parse("spawn collector 3", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Spawn {
+                robot_type: RobotType::Collection,
+                count: 3
+            })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("save foo.sav", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Save {
+                path: PathBuf::from("foo.sav")
+            })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("convert 4 collector", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Convert {
+                id: 4,
+                robot_type: RobotType::Collection
+            })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("note  dangerous pass", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Note {
+                text: "dangerous pass".to_string()
+            })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("preset conservative_energy", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Preset {
+                preset: SwarmPreset::ConservativeEnergy
+            })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("trace 3", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Trace { id: 3, path: None })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("charging lowest-energy", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Charging {
+                policy: ChargingPolicy::LowestEnergy
+            })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("gather 5 Minerals", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Gather {
+                id: 5,
+                resource_type: ResourceType::MINERALS
+            })
+        );
+        assert!(ConsoleCommand::parse("gather 5 science", &ResourceCatalog::default()).is_err());
+        assert!(ConsoleCommand::parse("preset reckless", &ResourceCatalog::default()).is_err());
+        assert!(ConsoleCommand::parse("note", &ResourceCatalog::default()).is_err());
+        assert!(ConsoleCommand::parse("spawn robot", &ResourceCatalog::default()).is_err());
+        assert!(ConsoleCommand::parse("recall five", &ResourceCatalog::default()).is_err());
+        assert!(ConsoleCommand::parse("fly 5", &ResourceCatalog::default()).is_err());
+    }
+
+    #[cfg(feature = "debug-tools")]
+    #[test]
+    fn test_cheats_parse_with_the_debug_tools() {
+        assert_eq!(
+            ConsoleCommand::parse(" teleport 2 10  12 ", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Teleport {
+                id: 2,
+                x: 10,
+                y: 12
+            })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("set energy 4 100", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::SetEnergy { id: 4, energy: 100 })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("refill all", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Refill { id: None })
+        );
+        assert_eq!(
+            ConsoleCommand::parse("resource minerals 40", &ResourceCatalog::default()),
+            Ok(ConsoleCommand::Resource {
+                resource_type: ResourceType::MINERALS,
+                amount: 40
+            })
+        );
+        assert!(ConsoleCommand::parse("resource gold 40", &ResourceCatalog::default()).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! HTTP control API for driving a running simulation from external tools.
+//!
+//! Requests are accepted in the background and answered by `handle_pending`, called from
+//! the main loop so they see the same `App` as the UI. Every response is JSON.
+//!
+//! - `GET /state`: tick, stock, science and exploration progress, the conflicts met
+//!   merging robot reports, by kind, and the alarms raised
+//! - `GET /robots`, `GET /robots/{id}`: robot positions, energy and status
+//! - `POST /robots/{type}`: spawns an `exploration`, `collection`, `scientific` or `scout`
+//!   robot
+//! - `POST /robots/{id}/recall`: sends the robot back to the station
+//! - `POST /preset/{name}`: switches the swarm to the `aggressive_exploration`, `balanced`
+//!   or `conservative_energy` preset
+//! - `GET /charging`: the chargers, the robots on them and the robots queued for one
+//! - `POST /charging/{policy}`: serves the charger queue `fifo`, `lowest-energy` first or
+//!   `mission-critical` first
+//! - `GET /knowledge`: the station's merged map knowledge
+
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::app::App;
+use crate::communication::channels::{ResourceType, RobotCommand};
+use crate::error::CommError;
+use crate::robot::core::state::RobotStatus;
+use crate::robot::registry::{RobotEntry, RobotType};
+use crate::robot::utils::preset::SwarmPreset;
+use crate::station::alarms::Alarm;
+use crate::station::charging::{ChargeRequest, Charging, ChargingPolicy};
+use crate::station::data_manager::ConflictStats;
+use crate::station::kpis::SwarmKpis;
+
+#[derive(Debug, Serialize)]
+struct RobotView {
+    id: u32,
+    robot_type: RobotType,
+    x: usize,
+    y: usize,
+    energy: u32,
+    max_energy: u32,
+    charge_cycles: u32,
+    status: RobotStatus,
+}
+
+impl From<&RobotEntry> for RobotView {
+    fn from(entry: &RobotEntry) -> Self {
+        Self {
+            id: entry.state.id,
+            robot_type: entry.robot_type,
+            x: entry.state.x,
+            y: entry.state.y,
+            energy: entry.state.energy,
+            max_energy: entry.state.max_energy,
+            charge_cycles: entry.state.charge_cycles,
+            status: entry.state.status.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StateView<'a> {
+    tick: u64,
+    robots: usize,
+    explored_tiles: usize,
+    total_tiles: usize,
+    collected_resources: &'a HashMap<ResourceType, u32>,
+    scientific_data: u64,
+    exploration_complete: bool,
+    conflicts: ConflictStats,
+    /// Over the last few hundred ticks
+    kpis: SwarmKpis,
+    preset: SwarmPreset,
+    /// Alarms raised on the swarm's health
+    alarms: Vec<Alarm>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChargingView<'a> {
+    /// `None` when every docked robot charges at once
+    slots: Option<usize>,
+    policy: ChargingPolicy,
+    charging: &'a [Charging],
+    /// The next robot served first
+    queue: Vec<ChargeRequest>,
+}
+
+/// Background HTTP server whose requests wait for the main loop
+pub struct ControlServer {
+    local_addr: SocketAddr,
+    requests: Receiver<Request>,
+}
+
+impl ControlServer {
+    pub fn start(addr: &str) -> Result<Self, CommError> {
+        let listen_error = |reason: String| CommError::Listen {
+            service: "the control API",
+            reason,
+        };
+        let server = Server::http(addr).map_err(|e| listen_error(e.to_string()))?;
+        let local_addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| listen_error("not bound to an IP address".to_string()))?;
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                if sender.send(request).is_err() {
+                    return;
+                }
+            }
+        });
+        info!("Control API listening on http://{}", local_addr);
+        Ok(Self {
+            local_addr,
+            requests,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Answers every request received since the last call
+    pub fn handle_pending(&self, app: &mut App) {
+        for request in self.requests.try_iter() {
+            let (status, body) = route(app, request.method(), request.url());
+            let content_type = Header::from_bytes("Content-Type", "application/json")
+                .expect("Static header is valid");
+            let response = Response::from_string(body)
+                .with_status_code(status)
+                .with_header(content_type);
+            if let Err(e) = request.respond(response) {
+                warn!("Failed to answer control request: {}", e);
+            }
+        }
+    }
+}
+
+fn json<T: Serialize>(status: u16, value: &T) -> (u16, String) {
+    match serde_json::to_string(value) {
+        Ok(body) => (status, body),
+        Err(e) => error(500, &e.to_string()),
+    }
+}
+
+fn error(status: u16, message: &str) -> (u16, String) {
+    (status, serde_json::json!({ "error": message }).to_string())
+}
+
+fn parse_robot_type(name: &str) -> Option<RobotType> {
+    match name {
+        "exploration" => Some(RobotType::Exploration),
+        "collection" => Some(RobotType::Collection),
+        "scientific" => Some(RobotType::Scientific),
+        "scout" => Some(RobotType::Scout),
+        _ => None,
+    }
+}
+
+/// Runs one request against the app, returns the status code and JSON body
+fn route(app: &mut App, method: &Method, url: &str) -> (u16, String) {
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match (method, segments.as_slice()) {
+        (Method::Get, ["state"]) => json(
+            200,
+            &StateView {
+                tick: app.tick,
+                robots: app.robots.len(),
+                explored_tiles: app.total_explored,
+                total_tiles: app.map_width * app.map_height,
+                collected_resources: &app.collected_resources,
+                scientific_data: app.scientific_data,
+                exploration_complete: app.exploration_complete,
+                conflicts: app.conflict_stats(),
+                kpis: app.live_kpis(),
+                preset: app.preset,
+                alarms: app.alarms.active().collect(),
+            },
+        ),
+        (Method::Get, ["robots"]) => {
+            let robots: Vec<RobotView> = app.robots.iter().map(RobotView::from).collect();
+            json(200, &robots)
+        }
+        (Method::Get, ["robots", id, "trace"]) => {
+            match id.parse().map(|id| app.decision_trace(id)) {
+                Ok(Ok(decisions)) => json(200, &decisions),
+                _ => error(404, "Unknown robot"),
+            }
+        }
+        (Method::Get, ["robots", id]) => match id.parse().ok().and_then(|id| app.robots.get(id)) {
+            Some(entry) => json(200, &RobotView::from(entry)),
+            None => error(404, "Unknown robot"),
+        },
+        (Method::Post, ["robots", id, "recall"]) => match id.parse() {
+            Ok(id) if app.send_command(id, RobotCommand::ReturnToStation) => {
+                json(202, &serde_json::json!({ "id": id }))
+            }
+            _ => error(404, "Unknown robot"),
+        },
+        (Method::Post, ["robots", robot_type]) => match parse_robot_type(robot_type) {
+            Some(robot_type) => match app.spawn_robot(robot_type) {
+                Ok(id) => json(201, &serde_json::json!({ "id": id })),
+                Err(e) => error(409, &e.to_string()),
+            },
+            None => error(
+                400,
+                "Robot type must be exploration, collection, scientific or scout",
+            ),
+        },
+        (Method::Post, ["preset", name]) => match SwarmPreset::from_name(name) {
+            Some(preset) => {
+                app.set_preset(preset);
+                json(200, &serde_json::json!({ "preset": preset }))
+            }
+            None => error(
+                400,
+                "Preset must be aggressive_exploration, balanced or conservative_energy",
+            ),
+        },
+        (Method::Get, ["charging"]) => json(
+            200,
+            &ChargingView {
+                slots: app.charging.slots(),
+                policy: app.charging.policy(),
+                charging: app.charging.charging(),
+                queue: app.charging.queue(),
+            },
+        ),
+        (Method::Post, ["charging", name]) => match ChargingPolicy::from_name(name) {
+            Some(policy) => {
+                app.set_charging_policy(policy);
+                json(200, &serde_json::json!({ "policy": policy }))
+            }
+            None => error(
+                400,
+                "Charging policy must be fifo, lowest-energy or mission-critical",
+            ),
+        },
+        (Method::Get, ["knowledge"]) => json(200, &app.station_knowledge()),
+        _ => error(404, "No such endpoint"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{RobotCounts, SpawnPolicy};
+    use crate::difficulty::Difficulty;
+    use crate::map::topology::MapShape;
+    use crate::settings::ModuleCatalog;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_routes_query_and_spawn_robots() {
+        let counts = RobotCounts {
+            exploration: 1,
+            collection: 0,
+            scientific: 0,
+            scout: 0,
+        };
+        let mut app = App::new(
+            30,
+            10,
+            3,
+            4,
+            counts,
+            SpawnPolicy::NearStation,
+            ModuleCatalog::default(),
+            Arc::default(),
+            Difficulty::Normal,
+            MapShape::default(),
+        );
+
+        let (status, body) = route(&mut app, &Method::Post, "/robots/collection");
+        assert_eq!((status, body.as_str()), (201, r#"{"id":1}"#));
+        assert_eq!(route(&mut app, &Method::Post, "/robots/drone").0, 400);
+
+        let (status, body) = route(&mut app, &Method::Get, "/robots");
+        assert_eq!(status, 200);
+        let robots: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(robots.as_array().map(Vec::len), Some(2));
+        assert_eq!(robots[1]["robot_type"], "collection");
+
+        assert_eq!(route(&mut app, &Method::Get, "/robots/1").0, 200);
+        assert_eq!(route(&mut app, &Method::Get, "/robots/1/trace").0, 200);
+        assert_eq!(route(&mut app, &Method::Get, "/robots/9/trace").0, 404);
+        assert_eq!(route(&mut app, &Method::Post, "/robots/1/recall").0, 202);
+        assert_eq!(route(&mut app, &Method::Post, "/robots/9/recall").0, 404);
+        assert_eq!(route(&mut app, &Method::Post, "/preset/balanced").0, 200);
+        assert_eq!(route(&mut app, &Method::Post, "/preset/reckless").0, 400);
+        assert_eq!(
+            route(&mut app, &Method::Post, "/charging/mission-critical").0,
+            200
+        );
+        assert_eq!(app.charging.policy(), ChargingPolicy::MissionCritical);
+        assert_eq!(route(&mut app, &Method::Post, "/charging/random").0, 400);
+        assert_eq!(route(&mut app, &Method::Get, "/charging").0, 200);
+        assert_eq!(route(&mut app, &Method::Get, "/state").0, 200);
+        assert_eq!(route(&mut app, &Method::Delete, "/state").0, 404);
+    }
+}
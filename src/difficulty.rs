@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+use crate::robot::utils::config;
+use crate::scenario::{MeteorShower, SolarActivity};
+
+/// Preset bundling how hard the planet is on the swarm
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    /// Cheap moves, plenty of resources and no hazards
+    Sandbox,
+    #[default]
+    Normal,
+    /// Costly moves, scarce resources, dust devils, meteors and a station eating its stock
+    Harsh,
+}
+
+/// Tuning behind a difficulty preset
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifficultyProfile {
+    /// Robot energy costs, in percent of the base costs
+    pub energy_cost_percent: u32,
+    /// Dust devils released at startup
+    pub hostiles: usize,
+    /// Mean ticks between two random meteor strikes, none if 0
+    pub meteor_interval: u64,
+    /// Mean ticks between two random solar flares, none if 0
+    pub flare_interval: u64,
+    /// The generated map holds one resource deposit per this many tiles
+    pub tiles_per_resource: usize,
+    /// Energy the station takes from its stock every `STATION_DRAIN_INTERVAL_TICKS`
+    pub station_drain: u32,
+}
+
+impl Difficulty {
+    pub fn profile(self) -> DifficultyProfile {
+        match self {
+            Difficulty::Sandbox => DifficultyProfile {
+                energy_cost_percent: 50,
+                hostiles: 0,
+                meteor_interval: 0,
+                flare_interval: 0,
+                tiles_per_resource: 20,
+                station_drain: 0,
+            },
+            Difficulty::Normal => DifficultyProfile {
+                energy_cost_percent: 100,
+                hostiles: 0,
+                meteor_interval: 0,
+                flare_interval: 8000,
+                tiles_per_resource: 30,
+                station_drain: 0,
+            },
+            Difficulty::Harsh => DifficultyProfile {
+                energy_cost_percent: 150,
+                hostiles: 3,
+                meteor_interval: 400,
+                flare_interval: 3000,
+                tiles_per_resource: 45,
+                station_drain: 1,
+            },
+        }
+    }
+}
+
+impl DifficultyProfile {
+    /// Random meteor strikes of the preset, if it has any
+    pub fn meteor_shower(&self, seed: u64) -> Option<MeteorShower> {
+        (self.meteor_interval > 0).then_some(MeteorShower {
+            mean_interval: self.meteor_interval,
+            max_radius: 2,
+            seed,
+        })
+    }
+
+    /// Random solar flares of the preset, if it has any
+    pub fn solar_activity(&self, seed: u64) -> Option<SolarActivity> {
+        (self.flare_interval > 0).then_some(SolarActivity {
+            mean_interval: self.flare_interval,
+            duration: config::FLARE_DURATION_TICKS,
+            scramble_percent: config::FLARE_SCRAMBLE_PERCENT,
+            // Not the meteors' seed, or both would strike on the same ticks
+            seed: seed.wrapping_add(1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_get_harder() {
+        let [sandbox, normal, harsh] =
+            [Difficulty::Sandbox, Difficulty::Normal, Difficulty::Harsh].map(Difficulty::profile);
+        assert!(sandbox.energy_cost_percent < normal.energy_cost_percent);
+        assert!(normal.energy_cost_percent < harsh.energy_cost_percent);
+        assert!(sandbox.tiles_per_resource < harsh.tiles_per_resource);
+        assert!(normal.meteor_shower(1).is_none());
+        assert!(harsh.meteor_shower(1).is_some());
+    }
+}
@@ -0,0 +1,104 @@
+//! Error types of the library: setting a simulation up, running it, its map and its links to
+//! other processes
+
+use std::io;
+
+use thiserror::Error;
+
+/// Why a simulation could not be set up
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SimInitError {
+    /// Fewer free walkable tiles than robots to spawn
+    #[error("{requested} robots requested but only {available} free walkable tiles")]
+    NoSpawnSpace { requested: usize, available: usize },
+    /// No walkable tile around the station to leave it by
+    #[error("The station at {dock:?} is walled in")]
+    StationBlocked { dock: (usize, usize) },
+    /// More starting robots than the configured cap
+    #[error("{requested} robots requested but at most {cap} are allowed")]
+    TooManyRobots { requested: usize, cap: usize },
+    /// Parameters no simulation can be built from
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+}
+
+/// Failures of a running simulation
+#[derive(Debug, Error)]
+pub enum SimError {
+    #[error(transparent)]
+    Init(#[from] SimInitError),
+    #[error(transparent)]
+    Map(#[from] MapError),
+    #[error(transparent)]
+    Comm(#[from] CommError),
+    #[error("No robot {0}")]
+    UnknownRobot(u32),
+    #[error("No free tile to spawn a robot on")]
+    NoFreeTile,
+    /// The run already holds as many robots as allowed
+    #[error("The swarm is at its cap of {0} robots")]
+    RobotCap(usize),
+    /// The robot cannot take on another behavior now
+    #[error("Robot {id} cannot be converted: {reason}")]
+    ConversionRefused { id: u32, reason: &'static str },
+    #[error("Robot {0} is not a collector")]
+    NotACollector(u32),
+    #[error("Robot {0} panicked")]
+    RobotPanicked(u32),
+}
+
+/// A tile that cannot be used as asked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum MapError {
+    #[error("({x}, {y}) is off the map")]
+    OutOfBounds { x: usize, y: usize },
+    #[error("({x}, {y}) is not a walkable tile")]
+    NotWalkable { x: usize, y: usize },
+    /// Walkable, but already holding the station, a shaft or a deposit
+    #[error("({x}, {y}) is taken")]
+    Occupied { x: usize, y: usize },
+}
+
+/// Failures of the links with robots and clients in other processes
+#[derive(Debug, Error)]
+pub enum CommError {
+    #[error("Cannot listen for {service}: {reason}")]
+    Listen {
+        service: &'static str,
+        reason: String,
+    },
+    #[error("Cannot reach the station: {0}")]
+    Unreachable(#[source] io::Error),
+    #[error("Connection failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed message: {0}")]
+    Malformed(#[from] serde_json::Error),
+    /// The peer sent a valid message out of turn
+    #[error("Unexpected message: {0}")]
+    Unexpected(String),
+    #[error("The other end closed the connection")]
+    Closed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_keep_their_message_through_sim_error() {
+        let error = SimError::from(MapError::NotWalkable { x: 3, y: 4 });
+        assert_eq!(error.to_string(), "(3, 4) is not a walkable tile");
+        assert!(matches!(error, SimError::Map(MapError::NotWalkable { .. })));
+
+        let error = SimError::from(CommError::Closed);
+        assert_eq!(error.to_string(), "The other end closed the connection");
+        let error = SimError::from(SimInitError::NoSpawnSpace {
+            requested: 4,
+            available: 1,
+        });
+        assert_eq!(
+            error.to_string(),
+            "4 robots requested but only 1 free walkable tiles"
+        );
+    }
+}
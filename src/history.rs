@@ -0,0 +1,419 @@
+use chrono::Local;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use crate::{
+    app::{App, MissionOutcome, RobotCounts, SpawnPolicy},
+    communication::channels::ResourceType,
+    difficulty::Difficulty,
+    settings::Settings,
+    station::{charging::ChargingPolicy, kpis::SwarmKpis},
+};
+
+/// File the end-of-run summaries are appended to
+pub const DEFAULT_HISTORY_PATH: &str = "astro-swarm-history.toml";
+
+/// Summary of one finished run, kept to compare configurations over time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub finished_at: String,
+    pub map_seed: u32,
+    pub resource_seed: u64,
+    /// Runs are only comparable at the same difficulty
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// Fingerprint of the robot counts, spawn policy, difficulty and settings
+    pub config_hash: String,
+    /// Tiles merged each way per dock, when knowledge sharing was limited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge_bandwidth: Option<usize>,
+    pub robots: usize,
+    pub ticks: u64,
+    pub explored_tiles: usize,
+    pub total_tiles: usize,
+    pub energy: u32,
+    pub minerals: u32,
+    pub scientific_data: u64,
+    /// Every robot retired because the map was exhausted
+    pub completed: bool,
+    /// Swarm figures over the whole run
+    #[serde(default)]
+    pub kpis: SwarmKpis,
+}
+
+impl RunRecord {
+    pub fn from_app(app: &App, config_hash: &str) -> Self {
+        let resource = |resource_type| app.collected_resources.get(&resource_type).copied();
+        Self {
+            finished_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            map_seed: app.map_seed,
+            resource_seed: app.resource_seed,
+            difficulty: app.difficulty,
+            config_hash: config_hash.to_string(),
+            merge_bandwidth: app.merge_bandwidth(),
+            robots: app.robot_ids().len(),
+            ticks: app.tick,
+            explored_tiles: app.total_explored,
+            total_tiles: app.map_width * app.map_height,
+            energy: resource(ResourceType::ENERGY).unwrap_or(0),
+            minerals: resource(ResourceType::MINERALS).unwrap_or(0),
+            scientific_data: app.scientific_data,
+            completed: app
+                .completion_summary
+                .as_ref()
+                .is_some_and(|summary| summary.outcome == MissionOutcome::Completed),
+            kpis: app.run_kpis(),
+        }
+    }
+
+    fn explored_percent(&self) -> usize {
+        (self.explored_tiles * 100)
+            .checked_div(self.total_tiles)
+            .unwrap_or(0)
+    }
+}
+
+/// What a headless run prints once over, one JSON object for scripts to read
+#[derive(Debug, Clone, Serialize)]
+pub struct RunResult {
+    pub outcome: MissionOutcome,
+    pub robot_counts: RobotCounts,
+    #[serde(flatten)]
+    pub record: RunRecord,
+}
+
+impl RunResult {
+    /// The result of a run that ended, `None` while it goes on
+    pub fn from_app(app: &App, config_hash: &str) -> Option<Self> {
+        let summary = app.completion_summary.as_ref()?;
+        Some(Self {
+            outcome: summary.outcome,
+            robot_counts: app.robot_counts,
+            record: RunRecord::from_app(app, config_hash),
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Past runs, oldest first
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunHistory {
+    #[serde(default)]
+    pub run: Vec<RunRecord>,
+}
+
+impl RunHistory {
+    /// Reads the history at `path`, empty if the file does not exist yet
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("Invalid history file: {}", e))
+    }
+
+    /// Appends a run to the history file without rewriting earlier entries
+    pub fn append(path: &Path, record: &RunRecord) -> Result<(), String> {
+        let entry = toml::to_string(&RunHistory {
+            run: vec![record.clone()],
+        })
+        .map_err(|e| format!("Failed to serialize run: {}", e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        writeln!(file, "{}", entry)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        info!("Run summary saved to {}", path.display());
+        Ok(())
+    }
+
+    /// One line per run, for the `--history` listing
+    pub fn format_table(&self) -> String {
+        if self.run.is_empty() {
+            return "No runs recorded yet".to_string();
+        }
+        let mut table = format!(
+            "{:<19}  {:>5}  {:>5}  {:<7}  {:<16}  {:>6}  {:>7}  {:>8}  {:>6}  {:>8}  {:>7}  {:>6}  Done\n",
+            "Finished",
+            "Map",
+            "Res",
+            "Level",
+            "Config",
+            "Robots",
+            "Ticks",
+            "Explored",
+            "Energy",
+            "Minerals",
+            "Science",
+            "Effic."
+        );
+        for run in &self.run {
+            table.push_str(&format!(
+                "{:<19}  {:>5}  {:>5}  {:<7}  {:<16}  {:>6}  {:>7}  {:>7}%  {:>6}  {:>8}  {:>7}  {:>6.1}  {}\n",
+                run.finished_at,
+                run.map_seed,
+                run.resource_seed,
+                format!("{:?}", run.difficulty),
+                run.config_hash,
+                run.robots,
+                run.ticks,
+                run.explored_percent(),
+                run.energy,
+                run.minerals,
+                run.scientific_data,
+                run.kpis.efficiency,
+                if run.completed { "yes" } else { "no" }
+            ));
+        }
+        table
+    }
+}
+
+/// Stable fingerprint (FNV-1a) of everything that shapes a run besides the seeds
+pub fn config_hash(
+    robot_counts: &RobotCounts,
+    spawn: SpawnPolicy,
+    difficulty: Difficulty,
+    settings: &Settings,
+    chargers: Option<usize>,
+    charging_policy: ChargingPolicy,
+) -> String {
+    // Interface preferences other than the pace do not change how a run plays out, nor does
+    // the robot cap: it only refuses spawns past it
+    let robots = &settings.robots;
+    let fields = [
+        ("exploration", robot_counts.exploration.to_string()),
+        ("collection", robot_counts.collection.to_string()),
+        ("scientific", robot_counts.scientific.to_string()),
+        ("scout", robot_counts.scout.to_string()),
+        ("spawn", value_name(spawn)),
+        ("difficulty", value_name(difficulty)),
+        ("tick_rate_ms", settings.interface.tick_rate_ms.to_string()),
+        (
+            "modules",
+            toml::to_string(&settings.modules).unwrap_or_default(),
+        ),
+        (
+            "resources",
+            toml::to_string(&settings.resources).unwrap_or_default(),
+        ),
+        ("jitter_percent", robots.jitter_percent.to_string()),
+        ("merge_timeout_ms", robots.merge_timeout_ms.to_string()),
+        ("merge_retries", robots.merge_retries.to_string()),
+        ("preset", robots.preset.name().to_string()),
+        (
+            "chargers",
+            chargers.map_or_else(|| "all".to_string(), |count| count.to_string()),
+        ),
+        ("charging_policy", value_name(charging_policy)),
+    ];
+    let canonical = fields
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("|");
+    let hash = canonical
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+/// Name a value goes by on the command line
+fn value_name(value: impl clap::ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(ticks: u64) -> RunRecord {
+        RunRecord {
+            finished_at: "2025-01-01 12:00:00".to_string(),
+            map_seed: 34,
+            resource_seed: 45,
+            difficulty: Difficulty::Harsh,
+            config_hash: "abc".to_string(),
+            merge_bandwidth: None,
+            robots: 3,
+            ticks,
+            explored_tiles: 50,
+            total_tiles: 200,
+            energy: 10,
+            minerals: 20,
+            scientific_data: 30,
+            completed: false,
+            kpis: SwarmKpis {
+                efficiency: 12.5,
+                delivery_rate: 3.0,
+                exploration_velocity: 40.0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_appended_runs_are_read_back_in_order() {
+        let path = std::env::temp_dir().join(format!("astro-history-{}.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        RunHistory::append(&path, &record(10)).unwrap();
+        RunHistory::append(&path, &record(20)).unwrap();
+        let history = RunHistory::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(history.run, vec![record(10), record(20)]);
+        assert!(history.format_table().contains(" 25%"));
+        assert!(history.format_table().contains("Harsh"));
+        assert!(history.format_table().contains("  12.5  no"));
+    }
+
+    #[test]
+    fn test_headless_result_is_one_flat_json_object() {
+        let result = RunResult {
+            outcome: MissionOutcome::TimedOut,
+            robot_counts: RobotCounts::default(),
+            record: record(10),
+        };
+        let json: serde_json::Value = serde_json::from_str(&result.to_json()).unwrap();
+        assert_eq!(json["outcome"], "timed_out");
+        assert_eq!(json["map_seed"], 34);
+        assert_eq!(json["robot_counts"]["collection"], 1);
+        assert_eq!(json["kpis"]["efficiency"], 12.5);
+    }
+
+    #[test]
+    fn test_config_hash_changes_with_config() {
+        let settings = Settings::default();
+        let counts = RobotCounts::default();
+        let hash = config_hash(
+            &counts,
+            SpawnPolicy::Scattered,
+            Difficulty::Normal,
+            &settings,
+            None,
+            ChargingPolicy::Fifo,
+        );
+        assert_eq!(
+            hash,
+            config_hash(
+                &counts,
+                SpawnPolicy::Scattered,
+                Difficulty::Normal,
+                &settings,
+                None,
+                ChargingPolicy::Fifo,
+            )
+        );
+        assert_ne!(
+            hash,
+            config_hash(
+                &counts,
+                SpawnPolicy::NearStation,
+                Difficulty::Normal,
+                &settings,
+                None,
+                ChargingPolicy::Fifo,
+            )
+        );
+        assert_ne!(
+            hash,
+            config_hash(
+                &counts,
+                SpawnPolicy::Scattered,
+                Difficulty::Harsh,
+                &settings,
+                None,
+                ChargingPolicy::Fifo,
+            )
+        );
+
+        let mut emoji = settings.clone();
+        emoji.interface.theme = "Emoji".to_string();
+        assert_eq!(
+            hash,
+            config_hash(
+                &counts,
+                SpawnPolicy::Scattered,
+                Difficulty::Normal,
+                &emoji,
+                None,
+                ChargingPolicy::Fifo,
+            )
+        );
+        let mut jittered = settings.clone();
+        jittered.robots.jitter_percent = 10;
+        assert_ne!(
+            hash,
+            config_hash(
+                &counts,
+                SpawnPolicy::Scattered,
+                Difficulty::Normal,
+                &jittered,
+                None,
+                ChargingPolicy::Fifo,
+            )
+        );
+        let mut patient = settings.clone();
+        patient.robots.merge_timeout_ms += 1000;
+        let mut persistent = settings.clone();
+        persistent.robots.merge_retries += 1;
+        for other in [&patient, &persistent] {
+            assert_ne!(
+                hash,
+                config_hash(
+                    &counts,
+                    SpawnPolicy::Scattered,
+                    Difficulty::Normal,
+                    other,
+                    None,
+                    ChargingPolicy::Fifo,
+                )
+            );
+        }
+        assert_ne!(
+            hash,
+            config_hash(
+                &counts,
+                SpawnPolicy::Scattered,
+                Difficulty::Normal,
+                &settings,
+                Some(2),
+                ChargingPolicy::Fifo,
+            )
+        );
+        assert_ne!(
+            config_hash(
+                &counts,
+                SpawnPolicy::Scattered,
+                Difficulty::Normal,
+                &settings,
+                Some(2),
+                ChargingPolicy::Fifo,
+            ),
+            config_hash(
+                &counts,
+                SpawnPolicy::Scattered,
+                Difficulty::Normal,
+                &settings,
+                Some(2),
+                ChargingPolicy::LowestEnergy,
+            )
+        );
+    }
+}
@@ -1,8 +1,25 @@
 pub mod app;
+pub mod cli;
 pub mod communication;
+pub mod console;
+#[cfg(feature = "control-api")]
+pub mod control;
+pub mod difficulty;
+pub mod error;
+pub mod history;
 pub mod logging;
 pub mod map;
+pub mod memory;
+pub mod pacer;
+pub mod prelude;
+pub mod profiler;
 pub mod robot;
+pub mod save;
+pub mod scenario;
+pub mod settings;
+pub mod simulation;
 pub mod station;
+#[cfg(feature = "tui")]
 pub mod terminal;
+pub mod trajectory;
 pub mod ui;
@@ -1,14 +1,69 @@
 use chrono::Local;
 use color_eyre::Result;
 use fern::Dispatch;
-use log::LevelFilter;
+use log::{Level, LevelFilter};
 use std::{
+    cell::Cell,
     fs::{self, OpenOptions},
     path::PathBuf,
+    sync::atomic::{AtomicU32, Ordering},
 };
 
 const LOG_DIR: &str = "logs";
 
+/// Start of the target robots log under, followed by their ID: `robot::7`
+pub const ROBOT_TARGET_PREFIX: &str = "robot::";
+
+/// Robot whose messages are all kept, `NOT_FOLLOWING` for none
+static FOLLOWED_ROBOT: AtomicU32 = AtomicU32::new(NOT_FOLLOWING);
+const NOT_FOLLOWING: u32 = u32::MAX;
+
+thread_local! {
+    /// Robot the calling thread runs, for helpers shared between robot types
+    static THREAD_ROBOT: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Log target of robot `id`'s messages
+pub fn robot_target(id: u32) -> String {
+    format!("{}{}", ROBOT_TARGET_PREFIX, id)
+}
+
+/// Robot a log target belongs to, if it is one of theirs
+pub fn robot_of_target(target: &str) -> Option<u32> {
+    target.strip_prefix(ROBOT_TARGET_PREFIX)?.parse().ok()
+}
+
+/// Marks the calling thread as robot `id`'s, see `thread_target`
+pub fn enter_robot(id: u32) {
+    THREAD_ROBOT.set(Some(id));
+}
+
+/// Target of the robot running the calling thread, for code that does not know which robot
+/// it works for; plain `robot` off robot threads
+pub fn thread_target() -> String {
+    THREAD_ROBOT
+        .get()
+        .map_or_else(|| "robot".to_string(), robot_target)
+}
+
+/// Keeps only `id`'s debug messages from now on, the other robots' warnings and errors
+/// still going through; `None` lets every robot's through again
+pub fn follow_robot(id: Option<u32>) {
+    FOLLOWED_ROBOT.store(id.unwrap_or(NOT_FOLLOWING), Ordering::Relaxed);
+}
+
+pub fn followed_robot() -> Option<u32> {
+    Some(FOLLOWED_ROBOT.load(Ordering::Relaxed)).filter(|&id| id != NOT_FOLLOWING)
+}
+
+/// Whether a message at `level` logged under `target` is written while `followed` is
+fn keeps(target: &str, level: Level, followed: Option<u32>) -> bool {
+    match (followed, robot_of_target(target)) {
+        (Some(followed), Some(robot)) => robot == followed || level <= Level::Warn,
+        _ => true,
+    }
+}
+
 pub fn setup_logging() -> Result<()> {
     fs::create_dir_all(LOG_DIR)?;
 
@@ -21,12 +76,12 @@ pub fn setup_logging() -> Result<()> {
 
     let log_file = OpenOptions::new()
         .create(true)
-        .write(true)
         .append(true)
         .open(&full_log_path)?;
 
     Dispatch::new()
         .level(LevelFilter::Trace)
+        .filter(|metadata| keeps(metadata.target(), metadata.level(), followed_robot()))
         .format(|out, message, record| {
             out.finish(format_args!(
                 "[{}][{}][{}] {}",
@@ -46,3 +101,24 @@ pub fn setup_logging() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_following_a_robot_quiets_the_others() {
+        assert_eq!(robot_of_target(&robot_target(7)), Some(7));
+        assert_eq!(robot_of_target("astro_swarm::app"), None);
+
+        assert!(keeps("robot::3", Level::Debug, None));
+        assert!(keeps("robot::7", Level::Debug, Some(7)));
+        assert!(!keeps("robot::3", Level::Debug, Some(7)));
+        assert!(keeps("robot::3", Level::Warn, Some(7)));
+        assert!(keeps("astro_swarm::app", Level::Trace, Some(7)));
+
+        assert_eq!(thread_target(), "robot");
+        enter_robot(4);
+        assert_eq!(thread_target(), "robot::4");
+    }
+}
@@ -1,60 +1,729 @@
-use astro_swarm::{app::App, logging, terminal::TerminalManager, ui::map_renderer::render_app};
-
-use color_eyre::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
-use ratatui::prelude::Backend;
-use std::time::{Duration, Instant};
-
-const TICK_RATE: Duration = Duration::from_millis(100);
-
-fn main() -> Result<()> {
-    setup()?;
-
-    let mut app = App::new(90, 15, 34, 45);
-    let mut terminal_manager = TerminalManager::new()?;
-
-    run_app(&mut app, terminal_manager.get_terminal())?;
-
-    log::info!("Application terminated");
-    Ok(())
-}
-
-fn setup() -> Result<()> {
-    logging::setup_logging()?;
-    log::info!("Application starting...");
-    Ok(())
-}
-
-fn run_app<B: Backend>(app: &mut App, terminal: &mut ratatui::Terminal<B>) -> Result<()> {
-    let mut last_tick = Instant::now();
-
-    loop {
-        terminal.draw(|frame| render_app(frame, frame.area(), app))?;
-
-        if check_events()? {
-            break;
-        }
-
-        if last_tick.elapsed() >= TICK_RATE {
-            app.update();
-            last_tick = Instant::now();
-        }
-
-        if let Some(timeout) = TICK_RATE.checked_sub(last_tick.elapsed()) {
-            std::thread::sleep(std::cmp::min(timeout, Duration::from_millis(10)));
-        }
-    }
-
-    Ok(())
-}
-
-fn check_events() -> Result<bool> {
-    if event::poll(Duration::from_millis(10))? {
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('q') {
-                return Ok(true);
-            }
-        }
-    }
-    Ok(false)
-}
+use astro_swarm::{
+    app::{App, GameSetup, MissionOutcome},
+    cli::{self, Cli},
+    communication::tcp::{self, RemoteStation},
+    error::{CommError, SimInitError},
+    history::{self, RunHistory, RunRecord, RunResult, DEFAULT_HISTORY_PATH},
+    logging,
+    map::{noise::Map, preview::MapPreview},
+    pacer::TickPacer,
+    profiler::{self, Section},
+    robot::utils::config,
+    save::{self, Autosaver, SaveGame, DEFAULT_SAVE_DIR},
+    scenario::Scenario,
+    settings::Settings,
+    terminal::TerminalManager,
+    ui::{
+        comparison::{handle_comparison_event, render_comparison, ComparisonSide},
+        input::{handle_event, InputAction},
+        map_renderer::render_app,
+        menu::{render_menu, MainMenu, MenuAction, MenuItem},
+        notifications::{BellNotifier, NotificationKind},
+        settings_screen::{render_settings, SettingsAction, SettingsScreen},
+        setup::{render_setup, SetupAction, SetupWizard},
+        state::UiState,
+    },
+};
+
+#[cfg(feature = "control-api")]
+use astro_swarm::control::ControlServer;
+use clap::Parser;
+use color_eyre::Result;
+use crossterm::event;
+use ratatui::prelude::Backend;
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    if let Err(e) = cli.validate() {
+        eprintln!("Invalid options: {}", e);
+        std::process::exit(2);
+    }
+    if cli.history {
+        match RunHistory::load(Path::new(DEFAULT_HISTORY_PATH)) {
+            Ok(history) => print!("{}", history.format_table()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    // Exits with 2 like the other errors before a run, 1 is a failed mission
+    if let Err(e) = setup() {
+        eprintln!("Cannot start logging: {}", e);
+        std::process::exit(2);
+    }
+    logging::follow_robot(cli.log_robot);
+    config::set_continuous_movement(cli.continuous);
+    config::set_decision_traces(cli.trace_decisions);
+    config::set_terrain_memory(cli.terrain_memory);
+
+    let settings = match Settings::load(&cli.config) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let (Some(addr), Some(robot_type)) = (&cli.connect, cli.robot) {
+        if let Err(e) = tcp::run_remote_robot(addr.as_str(), robot_type) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    settings.robots.apply();
+    if cli.preview {
+        let setup = cli.game_setup(&settings.interface);
+        let resources = match settings.resources.catalog() {
+            Ok(resources) => resources,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        };
+        let (map, map_seed) = Map::generate_playable(
+            setup.width,
+            setup.height,
+            setup.map_seed,
+            setup.resource_seed,
+            setup.difficulty.profile().tiles_per_resource,
+            setup.shape,
+            Arc::new(resources),
+        );
+        print!("{}", MapPreview::new(&map, map_seed, setup.resource_seed));
+        return Ok(());
+    }
+
+    let scenario = match cli.scenario.as_deref().map(Scenario::load).transpose() {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let remote_station = match cli.listen.as_deref().map(RemoteStation::listen).transpose() {
+        Ok(remote_station) => remote_station,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let resume = match cli.resume_latest.then(load_latest_save).transpose() {
+        Ok(resume) => resume,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let services = match Services::start(&cli) {
+        Ok(services) => services,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let compared = match cli
+        .compare
+        .as_deref()
+        .map(|path| load_compared_settings(path, &settings))
+        .transpose()
+    {
+        Ok(compared) => compared,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let mut launcher = GameLauncher {
+        cli: &cli,
+        settings,
+        scenario,
+    };
+    if cli.headless {
+        let outcome = match run_headless(&launcher, resume, remote_station, &services) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        };
+        log::info!("Application terminated");
+        std::process::exit(cli::exit_code(outcome));
+    }
+    let mut terminal_manager = TerminalManager::new()?;
+
+    if let Some(compared) = compared {
+        run_comparison(
+            &launcher,
+            &compared,
+            &services,
+            terminal_manager.get_terminal(),
+        )?;
+        log::info!("Application terminated");
+        return Ok(());
+    }
+
+    run_screens(
+        &mut launcher,
+        resume,
+        remote_station,
+        &services,
+        terminal_manager.get_terminal(),
+    )?;
+
+    log::info!("Application terminated");
+    Ok(())
+}
+
+fn setup() -> Result<()> {
+    logging::setup_logging()?;
+    log::info!("Application starting...");
+    Ok(())
+}
+
+/// The most recent autosave, an error if there is none
+fn load_latest_save() -> std::result::Result<SaveGame, String> {
+    let dir = Path::new(DEFAULT_SAVE_DIR);
+    match save::latest_save(dir)? {
+        Some(path) => SaveGame::load(&path),
+        None => Err(format!("No autosave to resume in {}", dir.display())),
+    }
+}
+
+/// Autosaves of the compared run, kept apart from the main run's
+fn compared_save_dir() -> PathBuf {
+    Path::new(DEFAULT_SAVE_DIR).join("compared")
+}
+
+/// Settings of the run compared with the main one; unlike those, they must exist. Both runs
+/// share the process and its pace, so they must agree on what applies to the whole process.
+fn load_compared_settings(path: &Path, main: &Settings) -> std::result::Result<Settings, String> {
+    if !path.exists() {
+        return Err(format!("No settings file to compare at {}", path.display()));
+    }
+    let compared = Settings::load(path)?;
+    if !compared.robots.same_process_settings(&main.robots) {
+        return Err(format!(
+            "{} must keep the merge timeout and retries of the main settings, both runs' \
+             robots share them",
+            path.display()
+        ));
+    }
+    if compared.interface.tick_rate_ms != main.interface.tick_rate_ms {
+        return Err(format!(
+            "{} must keep the tick rate of the main settings, both runs tick together",
+            path.display()
+        ));
+    }
+    Ok(compared)
+}
+
+/// Optional servers polled by the main loop alongside the UI
+#[derive(Default)]
+struct Services {
+    #[cfg(feature = "control-api")]
+    control: Option<ControlServer>,
+}
+
+impl Services {
+    #[cfg_attr(not(feature = "control-api"), allow(unused_variables))]
+    fn start(cli: &Cli) -> std::result::Result<Self, CommError> {
+        #[cfg(feature = "control-api")]
+        return Ok(Self {
+            control: cli
+                .control
+                .as_deref()
+                .map(ControlServer::start)
+                .transpose()?,
+        });
+        #[cfg(not(feature = "control-api"))]
+        Ok(Self::default())
+    }
+
+    #[cfg_attr(not(feature = "control-api"), allow(unused_variables))]
+    fn poll(&self, app: &mut App) {
+        #[cfg(feature = "control-api")]
+        if let Some(control) = &self.control {
+            control.handle_pending(app);
+        }
+    }
+}
+
+/// Everything needed to start a game from a setup, again and again
+struct GameLauncher<'a> {
+    cli: &'a Cli,
+    settings: Settings,
+    scenario: Option<Scenario>,
+}
+
+impl GameLauncher<'_> {
+    /// The new game the options describe, seeds defaulting to the settings'
+    fn game_setup(&self) -> GameSetup {
+        self.cli.game_setup(&self.settings.interface)
+    }
+
+    /// Saves the settings edited on the settings screen and applies them to the current run
+    fn update_settings(&mut self, settings: Settings, session: Option<&mut Session>) {
+        if let Err(e) = settings.save(&self.cli.config) {
+            log::warn!("{}", e);
+        }
+        if let Some(session) = session {
+            session.ui_state.apply_settings(&settings.interface);
+        }
+        self.settings = settings;
+    }
+
+    fn start(
+        &self,
+        game: &GameSetup,
+        remote_station: Option<RemoteStation>,
+    ) -> std::result::Result<Session, SimInitError> {
+        self.start_with(
+            &self.settings,
+            game,
+            remote_station,
+            Path::new(DEFAULT_SAVE_DIR),
+        )
+    }
+
+    /// Starts the game configured by `settings` rather than the launcher's, autosaving into
+    /// `save_dir`
+    fn start_with(
+        &self,
+        settings: &Settings,
+        game: &GameSetup,
+        remote_station: Option<RemoteStation>,
+        save_dir: &Path,
+    ) -> std::result::Result<Session, SimInitError> {
+        let cli = self.cli;
+        let config_hash = history::config_hash(
+            &game.robot_counts,
+            cli.spawn,
+            game.difficulty,
+            settings,
+            cli.chargers.map(NonZeroUsize::get),
+            cli.charging_policy,
+        );
+        let resources = settings
+            .resources
+            .catalog()
+            .map_err(SimInitError::InvalidConfig)?;
+        // Taken by the app as it starts
+        settings.robots.apply();
+        let mut app = App::try_new(
+            game.width,
+            game.height,
+            game.map_seed,
+            game.resource_seed,
+            game.robot_counts,
+            cli.spawn,
+            settings.modules.clone(),
+            Arc::new(resources),
+            game.difficulty,
+            game.shape,
+        )?;
+        app.set_preset(settings.robots.preset);
+        let mut session = self.prepare(settings, app, config_hash, remote_station, save_dir);
+        if cli.warmup > 0 {
+            session.app.fast_forward(cli.warmup);
+        }
+        Ok(session)
+    }
+
+    /// Picks a saved run up at the tick it was saved at
+    fn resume(
+        &self,
+        save: &SaveGame,
+        remote_station: Option<RemoteStation>,
+    ) -> std::result::Result<Session, SimInitError> {
+        let game = &save.setup;
+        let config_hash = history::config_hash(
+            &game.robot_counts,
+            self.cli.spawn,
+            game.difficulty,
+            &self.settings,
+            self.cli.chargers.map(NonZeroUsize::get),
+            self.cli.charging_policy,
+        );
+        let app = save.restore(self.settings.modules.clone())?;
+        Ok(self.prepare(
+            &self.settings,
+            app,
+            config_hash,
+            remote_station,
+            Path::new(DEFAULT_SAVE_DIR),
+        ))
+    }
+
+    /// Sets up what the run of `settings` gets besides its map and robots
+    fn prepare(
+        &self,
+        settings: &Settings,
+        mut app: App,
+        config_hash: String,
+        remote_station: Option<RemoteStation>,
+        save_dir: &Path,
+    ) -> Session {
+        let cli = self.cli;
+        // Headless runs keep stdout for their result
+        if !cli.headless {
+            app.subscribe(Box::new(BellNotifier::stdout(
+                settings.notifications.clone(),
+            )));
+        }
+        if settings
+            .notifications
+            .is_enabled(NotificationKind::StationEnergyLow)
+        {
+            app.set_station_energy_low(settings.notifications.station_energy_low);
+        }
+        let hostiles = cli.hostiles.unwrap_or(app.difficulty.profile().hostiles);
+        if hostiles > 0 {
+            app.spawn_hostiles(hostiles);
+        }
+        if let Some(scenario) = &self.scenario {
+            app.load_scenario(scenario.clone());
+        }
+        if let Some(remote_station) = remote_station {
+            app.attach_remote_station(remote_station);
+        }
+        app.set_merge_bandwidth(cli.merge_bandwidth);
+        app.set_science_targeting(cli.science_targeting);
+        app.set_chargers(cli.chargers.map(NonZeroUsize::get));
+        app.set_charging_policy(cli.charging_policy);
+        app.set_memory_limit(settings.memory);
+        app.set_alarms(settings.alarms, settings.interface.tick_rate());
+        if let Some(duration) = cli.duration {
+            let ticks = duration.ticks(settings.interface.tick_rate());
+            app.set_tick_limit(Some(app.tick.saturating_add(ticks)));
+        }
+        if cli.trajectories.is_some() {
+            app.record_trajectories();
+        }
+        if cli.profile.is_some() {
+            profiler::enable();
+        }
+        let mut ui_state = UiState::new();
+        ui_state.apply_settings(&settings.interface);
+        let autosaver = Autosaver::new(save_dir, cli.autosave, cli.autosave_keep, app.tick);
+        Session {
+            app,
+            ui_state,
+            config_hash,
+            autosaver,
+            trajectory_path: cli.trajectories.clone(),
+            profile_path: cli.profile.clone(),
+        }
+    }
+}
+
+/// A run in progress
+struct Session {
+    app: App,
+    ui_state: UiState,
+    config_hash: String,
+    autosaver: Autosaver,
+    /// Where the robots' positions are exported when the run ends
+    trajectory_path: Option<PathBuf>,
+    /// Where the profiler's figures are exported when the run ends
+    profile_path: Option<PathBuf>,
+}
+
+impl Session {
+    /// Records the run in the history and stops its robots, handing back the remote listener
+    fn end(mut self) -> Option<RemoteStation> {
+        let record = RunRecord::from_app(&self.app, &self.config_hash);
+        if let Err(e) = RunHistory::append(Path::new(DEFAULT_HISTORY_PATH), &record) {
+            log::warn!("{}", e);
+        }
+        if let (Some(path), Some(trajectories)) = (&self.trajectory_path, &self.app.trajectories) {
+            if let Err(e) = trajectories.write_csv(path) {
+                log::warn!("{}", e);
+            }
+        }
+        if let Some(path) = &self.profile_path {
+            if let Err(e) = profiler::report().write_json(path) {
+                log::warn!("{}", e);
+            }
+        }
+        let remote_station = self.app.take_remote_station();
+        self.app.shutdown();
+        remote_station
+    }
+}
+
+/// Screen the main loop is showing
+enum Screen {
+    Menu(MainMenu),
+    Setup(SetupWizard),
+    Settings(SettingsScreen),
+    Running,
+}
+
+/// Moves between the menu, the setup screen and the simulation until the player quits
+fn run_screens<B: Backend>(
+    launcher: &mut GameLauncher,
+    resume: Option<SaveGame>,
+    mut remote_station: Option<RemoteStation>,
+    services: &Services,
+    terminal: &mut ratatui::Terminal<B>,
+) -> Result<()> {
+    let mut session = None;
+    let mut screen = if let Some(save) = resume {
+        session = Some(launcher.resume(&save, remote_station.take())?);
+        Screen::Running
+    } else if launcher.cli.no_setup {
+        session = Some(launcher.start(&launcher.game_setup(), remote_station.take())?);
+        Screen::Running
+    } else {
+        Screen::Menu(MainMenu::new(false))
+    };
+
+    loop {
+        screen = match (screen, &mut session) {
+            (Screen::Running, Some(running)) => {
+                let tick_rate = launcher.settings.interface.tick_rate();
+                match run_app(running, tick_rate, services, terminal)? {
+                    InputAction::OpenMenu => Screen::Menu(MainMenu::new(true)),
+                    InputAction::Restart { new_seeds } => {
+                        let mut game = running.app.game_setup();
+                        if new_seeds {
+                            game = game.reseeded();
+                        }
+                        log::info!(
+                            "Restarting on map seed {}, resource seed {}",
+                            game.map_seed,
+                            game.resource_seed
+                        );
+                        if let Some(previous) = session.take() {
+                            remote_station = previous.end().or(remote_station);
+                        }
+                        session = Some(launcher.start(&game, remote_station.take())?);
+                        Screen::Running
+                    }
+                    _ => break,
+                }
+            }
+            (Screen::Running, None) => Screen::Menu(MainMenu::new(false)),
+            (Screen::Menu(mut menu), _) => {
+                terminal.draw(|frame| render_menu(frame, frame.area(), &menu))?;
+                match next_event()?.map(|event| menu.handle_event(&event)) {
+                    Some(MenuAction::Open(MenuItem::Resume)) => Screen::Running,
+                    Some(MenuAction::Open(MenuItem::NewGame)) => {
+                        Screen::Setup(SetupWizard::new(launcher.game_setup()))
+                    }
+                    Some(MenuAction::Open(MenuItem::Settings)) => {
+                        Screen::Settings(SettingsScreen::new(launcher.settings.clone()))
+                    }
+                    Some(MenuAction::Open(MenuItem::Quit)) => break,
+                    _ => Screen::Menu(menu),
+                }
+            }
+            (Screen::Setup(mut wizard), _) => {
+                terminal.draw(|frame| render_setup(frame, frame.area(), &wizard))?;
+                match next_event()?.map(|event| wizard.handle_event(&event)) {
+                    Some(SetupAction::Launch(game)) => {
+                        if let Some(previous) = session.take() {
+                            log::info!("Abandoning the run at tick {}", previous.app.tick);
+                            remote_station = previous.end().or(remote_station);
+                        }
+                        session = Some(launcher.start(&game, remote_station.take())?);
+                        Screen::Running
+                    }
+                    Some(SetupAction::Cancel) => Screen::Menu(MainMenu::new(session.is_some())),
+                    _ => Screen::Setup(wizard),
+                }
+            }
+            (Screen::Settings(mut settings_screen), _) => {
+                terminal.draw(|frame| render_settings(frame, frame.area(), &settings_screen))?;
+                match next_event()?.map(|event| settings_screen.handle_event(&event)) {
+                    Some(SettingsAction::Save(settings)) => {
+                        launcher.update_settings(*settings, session.as_mut());
+                        Screen::Menu(MainMenu::new(session.is_some()))
+                    }
+                    _ => Screen::Settings(settings_screen),
+                }
+            }
+        };
+    }
+
+    if let Some(session) = session {
+        session.end();
+    }
+    Ok(())
+}
+
+/// Runs the game of the options, or the resumed one, without the UI until its mission
+/// ends, then prints the result as JSON
+fn run_headless(
+    launcher: &GameLauncher,
+    resume: Option<SaveGame>,
+    remote_station: Option<RemoteStation>,
+    services: &Services,
+) -> std::result::Result<MissionOutcome, SimInitError> {
+    let mut session = match resume {
+        Some(save) => launcher.resume(&save, remote_station)?,
+        None => launcher.start(&launcher.game_setup(), remote_station)?,
+    };
+    let tick_rate = launcher.settings.interface.tick_rate();
+
+    let result = loop {
+        if let Some(result) = RunResult::from_app(&session.app, &session.config_hash) {
+            break result;
+        }
+        let started = Instant::now();
+        services.poll(&mut session.app);
+        session.app.update();
+        if let Err(e) = session.autosaver.tick(&session.app) {
+            log::warn!("{}", e);
+        }
+        if let Some(rest) = tick_rate.checked_sub(started.elapsed()) {
+            std::thread::sleep(rest);
+        }
+    };
+
+    log::info!("Headless run over: {:?}", result.outcome);
+    println!("{}", result.to_json());
+    session.end();
+    Ok(result.outcome)
+}
+
+/// Runs the game of the options twice side by side, once as configured and once with the
+/// `compared` settings, in step until the player quits; both runs are recorded
+fn run_comparison<B: Backend>(
+    launcher: &GameLauncher,
+    compared: &Settings,
+    services: &Services,
+    terminal: &mut ratatui::Terminal<B>,
+) -> Result<()> {
+    let cli = launcher.cli;
+    let game = launcher.game_setup();
+    let mut left = launcher.start(&game, None)?;
+    let mut right = launcher.start_with(compared, &game, None, &compared_save_dir())?;
+    let labels = [
+        cli.config.display().to_string(),
+        cli.compare
+            .as_deref()
+            .unwrap_or(Path::new(""))
+            .display()
+            .to_string(),
+    ];
+    log::info!("Comparing {} with {}", labels[0], labels[1]);
+    let tick_rate = launcher.settings.interface.tick_rate();
+    let mut pacer = TickPacer::new(tick_rate);
+
+    loop {
+        let frame_started = Instant::now();
+        terminal.draw(|frame| {
+            render_comparison(
+                frame,
+                frame.area(),
+                ComparisonSide {
+                    label: &labels[0],
+                    app: &left.app,
+                },
+                ComparisonSide {
+                    label: &labels[1],
+                    app: &right.app,
+                },
+                left.ui_state.theme(),
+            )
+        })?;
+        let frame_time = frame_started.elapsed();
+        services.poll(&mut left.app);
+
+        if event::poll(Duration::from_millis(10))?
+            && handle_comparison_event(&event::read()?, &mut left.ui_state) == InputAction::Quit
+        {
+            break;
+        }
+
+        if pacer.due() {
+            let update_started = Instant::now();
+            left.app.update();
+            right.app.update();
+            pacer.ticked(frame_time + update_started.elapsed());
+        }
+
+        if let Some(timeout) = pacer.until_next() {
+            std::thread::sleep(std::cmp::min(timeout, Duration::from_millis(10)));
+        }
+    }
+
+    left.end();
+    right.end();
+    Ok(())
+}
+
+/// The next terminal event, if one comes within a short wait
+fn next_event() -> Result<Option<event::Event>> {
+    if event::poll(Duration::from_millis(100))? {
+        return Ok(Some(event::read()?));
+    }
+    Ok(None)
+}
+
+/// Runs the simulation until the player quits or opens the menu, returns which
+fn run_app<B: Backend>(
+    session: &mut Session,
+    tick_rate: Duration,
+    services: &Services,
+    terminal: &mut ratatui::Terminal<B>,
+) -> Result<InputAction> {
+    let Session {
+        app,
+        ui_state,
+        autosaver,
+        ..
+    } = session;
+    let mut pacer = TickPacer::new(tick_rate);
+    let mut selected = ui_state.selected_robot;
+
+    loop {
+        // The log follows the robot highlighted on the map
+        if ui_state.selected_robot != selected {
+            selected = ui_state.selected_robot;
+            logging::follow_robot(selected);
+        }
+        ui_state.running_behind = pacer.behind();
+        let frame_started = Instant::now();
+        profiler::time(Section::Render, || {
+            terminal.draw(|frame| render_app(frame, frame.area(), app, ui_state))
+        })?;
+        let frame_time = frame_started.elapsed();
+        services.poll(app);
+
+        let action = check_events(app, ui_state)?;
+        if action != InputAction::None {
+            return Ok(action);
+        }
+
+        if pacer.due() {
+            let update_started = Instant::now();
+            app.update();
+            if let Err(e) = autosaver.tick(app) {
+                log::warn!("{}", e);
+            }
+            pacer.ticked(frame_time + update_started.elapsed());
+        }
+
+        if let Some(timeout) = pacer.until_next() {
+            std::thread::sleep(std::cmp::min(timeout, Duration::from_millis(10)));
+        }
+    }
+}
+
+fn check_events(app: &mut App, ui_state: &mut UiState) -> Result<InputAction> {
+    if event::poll(Duration::from_millis(10))? {
+        return Ok(handle_event(&event::read()?, app, ui_state));
+    }
+    Ok(InputAction::None)
+}
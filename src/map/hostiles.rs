@@ -0,0 +1,117 @@
+use log::{debug, info};
+use rand::{rngs::StdRng, seq::IndexedRandom, SeedableRng};
+
+use super::noise::Map;
+
+/// Distance (in moves) at which a hostile drains a robot; robots keep out of it
+pub const HOSTILE_REACH: usize = 1;
+
+/// A dust devil wandering the map, draining the robots it reaches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hostile {
+    pub id: u32,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Moves the hostiles and publishes their positions on the map, where robots see them
+pub struct HostileSystem {
+    hostiles: Vec<Hostile>,
+    rng: StdRng,
+    move_interval_ticks: u64,
+}
+
+impl HostileSystem {
+    pub fn new(seed: u64, move_interval_ticks: u64) -> Self {
+        Self {
+            hostiles: Vec::new(),
+            rng: StdRng::seed_from_u64(seed),
+            move_interval_ticks: move_interval_ticks.max(1),
+        }
+    }
+
+    /// Adds up to `count` hostiles on walkable tiles at least `min_station_distance` away
+    /// from the station, returns how many were placed
+    pub fn spawn(&mut self, count: usize, min_station_distance: usize, map: &mut Map) -> usize {
+        let (station_x, station_y) = map.station_coords();
+        let mut candidates = Vec::new();
+        // Dust devils only blow on the surface
+        for y in map.layer_rows(0) {
+            for x in 0..map.width {
+                if !map.is_obstacle(x, y)
+                    && x.abs_diff(station_x) + y.abs_diff(station_y) >= min_station_distance
+                {
+                    candidates.push((x, y));
+                }
+            }
+        }
+        let picked: Vec<(usize, usize)> = candidates
+            .choose_multiple(&mut self.rng, count)
+            .copied()
+            .collect();
+        for (x, y) in picked {
+            let id = self.hostiles.len() as u32;
+            info!("Dust devil {} appeared at ({}, {})", id, x, y);
+            self.hostiles.push(Hostile { id, x, y });
+        }
+        map.set_hostiles(self.positions());
+        self.hostiles.len()
+    }
+
+    /// Every `move_interval_ticks`, moves each hostile to a random walkable neighbor outside
+    /// the station
+    pub fn tick(&mut self, tick: u64, map: &mut Map) {
+        if self.hostiles.is_empty() || !tick.is_multiple_of(self.move_interval_ticks) {
+            return;
+        }
+        for hostile in &mut self.hostiles {
+            let moves: Vec<(usize, usize)> = map
+                .neighbors(hostile.x, hostile.y)
+                .filter(|&(x, y)| !map.is_obstacle(x, y) && !map.is_station(x, y))
+                .collect();
+            if let Some(&(x, y)) = moves.choose(&mut self.rng) {
+                debug!("Dust devil {} moves to ({}, {})", hostile.id, x, y);
+                hostile.x = x;
+                hostile.y = y;
+            }
+        }
+        map.set_hostiles(self.positions());
+    }
+
+    pub fn hostiles(&self) -> &[Hostile] {
+        &self.hostiles
+    }
+
+    pub fn positions(&self) -> Vec<(usize, usize)> {
+        self.hostiles
+            .iter()
+            .map(|hostile| (hostile.x, hostile.y))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hostiles_wander_on_walkable_tiles_away_from_the_station() {
+        let mut map = Map::new(40, 20, 5);
+        let mut system = HostileSystem::new(1, 2);
+        assert_eq!(system.spawn(3, 6, &mut map), 3);
+        assert_eq!(map.hostiles(), system.positions().as_slice());
+
+        let start = system.positions();
+        system.tick(1, &mut map);
+        assert_eq!(system.positions(), start);
+
+        for tick in 2..200 {
+            system.tick(tick, &mut map);
+            for &(x, y) in map.hostiles() {
+                assert!(!map.is_obstacle(x, y));
+                assert!(!map.is_station(x, y));
+            }
+        }
+        assert_ne!(system.positions(), start);
+    }
+}
@@ -1,2 +1,7 @@
+pub mod hostiles;
 pub mod noise;
+pub mod preview;
 pub mod resources;
+pub mod shared;
+pub mod tile_keyed;
+pub mod topology;
@@ -1,16 +1,70 @@
 use noise::{NoiseFn, Perlin};
 use rand::prelude::*;
-use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::sync::{Arc, OnceLock};
 
-use super::resources::{Resource, ResourceManager, ResourceType};
+use log::{info, warn};
 
+use super::hostiles::HOSTILE_REACH;
+use super::resources::{Resource, ResourceCatalog, ResourceManager, ResourceType};
+use super::topology::{MapShape, Shaft, Topology};
+use crate::error::MapError;
+
+/// Shafts linking a layer to the one below
+const SHAFTS_PER_LAYER: usize = 3;
+/// Rich mineral veins in each cave layer
+const VEINS_PER_LAYER: usize = 4;
+/// Minerals in a vein tile, more than any surface deposit holds
+pub const VEIN_AMOUNT: std::ops::RangeInclusive<u32> = 200..=400;
+/// Surface obstacle share, in percent, above which a generated map is too cluttered to play
+pub const MAX_OBSTACLE_PERCENT: usize = 55;
+/// Distance around the station checked for room to move
+const STATION_CLEARING_RADIUS: usize = 6;
+/// Walkable tiles needed within that distance, the station's own aside
+const MIN_CLEARING_TILES: usize = 8;
+/// One science deposit in this many is a joint site
+pub const JOINT_SITE_ONE_IN: u32 = 4;
+/// Mixed into the resource seed to pick the joint sites, leaving the deposits as they were
+const JOINT_SITE_SALT: u64 = 0x6a01_7e5c;
+/// Seeds tried in all before settling for a degenerate map
+pub const MAP_GENERATION_ATTEMPTS: u32 = 5;
+
+/// The planet's tiles. Cave layers are stacked below the surface in the same grid, each
+/// `layer_height` rows tall and separated from the previous layer by a solid seam row, so a
+/// tile's layer (its z) follows from its row; robots change layers through shafts.
+#[derive(Serialize, Deserialize)]
 pub struct Map {
     pub width: usize,
+    /// Rows of all layers, seams included
     pub height: usize,
+    /// Rows of a single layer
+    pub layer_height: usize,
+    /// Edges wrap around, each layer being a torus
+    pub wraps: bool,
+    /// Hexagonal tiles in offset rows, see `Topology`
+    pub hex: bool,
     pub station_area: Vec<(usize, usize)>,
     data: Vec<Vec<bool>>, // true = obstacle (#), false = walkable (.)
     resource_manager: ResourceManager,
+    terrain_version: u64,
+    /// Where the hostiles are, as last published by the `HostileSystem`
+    hostiles: Vec<(usize, usize)>,
+    /// Shaft tiles, each linked to the one right below it on the next layer
+    #[serde(with = "super::tile_keyed")]
+    shafts: HashMap<(usize, usize), (usize, usize)>,
+    /// `shafts` as the topology hands them out, listed on first use
+    #[serde(skip)]
+    shaft_list: OnceLock<Arc<[Shaft]>>,
+    /// Amounts robots took from deposits so far, by type
+    taken: HashMap<ResourceType, u64>,
+    /// Deposits made of cargo dropped by robots that stopped in the field
+    salvage: HashSet<(usize, usize)>,
+    /// Science deposits analyzed only with a collector extracting samples alongside
+    joint_sites: HashSet<(usize, usize)>,
+    /// Resource types its deposits are of
+    catalog: Arc<ResourceCatalog>,
 }
 
 impl Map {
@@ -48,9 +102,20 @@ impl Map {
         let mut map = Self {
             width,
             height,
+            layer_height: height,
+            wraps: false,
+            hex: false,
             data,
             station_area,
             resource_manager: ResourceManager::new(),
+            terrain_version: 0,
+            hostiles: Vec::new(),
+            shafts: HashMap::new(),
+            shaft_list: OnceLock::new(),
+            taken: HashMap::new(),
+            salvage: HashSet::new(),
+            joint_sites: HashSet::new(),
+            catalog: Arc::default(),
         };
 
         // Ensure station is walkable
@@ -62,6 +127,252 @@ impl Map {
         map
     }
 
+    /// Digs a cave layer below the deepest one, reached through shafts from the layer above
+    pub fn add_cave_layer(&mut self, seed: u32) {
+        let upper = self.layers() - 1;
+        let top = self.height + 1;
+        let perlin = Perlin::new(seed);
+        self.data.push(vec![true; self.width]);
+        for y in 0..self.layer_height {
+            self.data.push(
+                (0..self.width)
+                    // Caves are tighter than the surface
+                    .map(|x| perlin.get([x as f64 / 6.0, y as f64 / 6.0]) > -0.1)
+                    .collect(),
+            );
+        }
+        self.height = self.data.len();
+
+        let mut rng = StdRng::seed_from_u64(u64::from(seed));
+        let candidates: Vec<(usize, usize)> = self
+            .layer_rows(upper)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                !self.is_obstacle(x, y)
+                    && !self.is_station(x, y)
+                    && !self.is_shaft(x, y)
+                    && !self.has_resource(x, y)
+            })
+            .collect();
+        for &(x, y) in candidates.choose_multiple(&mut rng, SHAFTS_PER_LAYER) {
+            let below = (x, y - self.layer_rows(upper).start + top);
+            self.data[below.1][below.0] = false;
+            self.shafts.insert((x, y), below);
+            self.shafts.insert(below, (x, y));
+        }
+        self.shaft_list = OnceLock::new();
+
+        self.connect_isolated_regions();
+        self.terrain_version += 1;
+    }
+
+    /// Number of layers, the surface included
+    pub fn layers(&self) -> usize {
+        (self.height + 1) / (self.layer_height + 1)
+    }
+
+    pub fn topology(&self) -> Topology {
+        Topology {
+            width: self.width,
+            height: self.height,
+            layer_height: self.layer_height,
+            wraps: self.wraps,
+            hex: self.hex,
+            shafts: self
+                .shaft_list
+                .get_or_init(|| {
+                    let mut shafts: Vec<Shaft> = self
+                        .shafts
+                        .iter()
+                        .map(|(&shaft, &exit)| (shaft, exit))
+                        .collect();
+                    shafts.sort_unstable();
+                    shafts.into()
+                })
+                .clone(),
+        }
+    }
+
+    /// Moves between two tiles, see `Topology::distance`
+    pub fn distance(&self, a: (usize, usize), b: (usize, usize)) -> usize {
+        self.topology().distance(a, b)
+    }
+
+    /// Tiles one move away from (x, y), across the edges when the map wraps
+    pub fn neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.topology().neighbors(x, y)
+    }
+
+    /// Layer of a row: 0 for the surface, then one per cave layer
+    pub fn layer_of(&self, y: usize) -> usize {
+        y / (self.layer_height + 1)
+    }
+
+    /// Rows of a layer
+    pub fn layer_rows(&self, layer: usize) -> std::ops::Range<usize> {
+        let start = layer * (self.layer_height + 1);
+        start..(start + self.layer_height).min(self.height)
+    }
+
+    pub fn is_shaft(&self, x: usize, y: usize) -> bool {
+        self.shafts.contains_key(&(x, y))
+    }
+
+    /// Tile reached by climbing the shaft at (x, y)
+    pub fn shaft_exit(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        self.shafts.get(&(x, y)).copied()
+    }
+
+    /// Shaft tiles on `layer` leading to `toward`, the next layer in that direction
+    pub fn shafts_toward(&self, layer: usize, toward: usize) -> Vec<(usize, usize)> {
+        let mut shafts: Vec<(usize, usize)> = self
+            .shafts
+            .iter()
+            .filter(|(&(_, y), &(_, exit_y))| {
+                self.layer_of(y) == layer && self.layer_of(exit_y).cmp(&layer) == toward.cmp(&layer)
+            })
+            .map(|(&shaft, _)| shaft)
+            .collect();
+        shafts.sort_unstable();
+        shafts
+    }
+
+    /// The simulation map for the given seeds and shape: terrain, the shape's caves below it
+    /// with their mineral veins, plus one resource per `tiles_per_resource` tiles, of the
+    /// types in `catalog`. Processes sharing the parameters build the same map.
+    pub fn generate(
+        width: usize,
+        height: usize,
+        map_seed: u32,
+        resource_seed: u64,
+        tiles_per_resource: usize,
+        shape: MapShape,
+        catalog: Arc<ResourceCatalog>,
+    ) -> Self {
+        let mut map = Self::new(width, height, map_seed);
+        map.catalog = catalog;
+        map.wraps = shape.wraps;
+        map.hex = shape.hex;
+        for layer in 1..=shape.cave_layers {
+            map.add_cave_layer(map_seed.wrapping_add(layer as u32));
+        }
+        map.spawn_resources(
+            (width * map.height)
+                .checked_div(tiles_per_resource)
+                .unwrap_or(0),
+            resource_seed,
+        );
+        map.spawn_veins(resource_seed);
+        map
+    }
+
+    /// Like `generate`, but a degenerate map (see `degeneracy`) is generated again from a
+    /// seed derived from `map_seed`, up to `MAP_GENERATION_ATTEMPTS` seeds in all. Returns the
+    /// map with the seed it was generated from, which `generate` turns into the same map.
+    pub fn generate_playable(
+        width: usize,
+        height: usize,
+        map_seed: u32,
+        resource_seed: u64,
+        tiles_per_resource: usize,
+        shape: MapShape,
+        catalog: Arc<ResourceCatalog>,
+    ) -> (Self, u32) {
+        let mut seed = map_seed;
+        let mut attempt = 1;
+        loop {
+            let map = Self::generate(
+                width,
+                height,
+                seed,
+                resource_seed,
+                tiles_per_resource,
+                shape,
+                catalog.clone(),
+            );
+            let Some(reason) = map.degeneracy() else {
+                if seed != map_seed {
+                    info!("Map seed {} replaced by {}", map_seed, seed);
+                }
+                return (map, seed);
+            };
+            if attempt == MAP_GENERATION_ATTEMPTS {
+                warn!(
+                    "Map seed {} {}, still degenerate after {} attempts; playing it anyway",
+                    seed, reason, attempt
+                );
+                return (map, seed);
+            }
+            warn!("Map seed {} {}, regenerating", seed, reason);
+            seed = derived_seed(map_seed, attempt);
+            attempt += 1;
+        }
+    }
+
+    /// Why the map is not worth playing on, if it is not: the surface is mostly obstacles, or
+    /// the station is walled in
+    pub fn degeneracy(&self) -> Option<String> {
+        let surface = self.layer_rows(0).len() * self.width;
+        let obstacles = self
+            .layer_rows(0)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.is_obstacle(x, y))
+            .count();
+        let obstacle_percent = (obstacles * 100).checked_div(surface).unwrap_or(100);
+        if obstacle_percent > MAX_OBSTACLE_PERCENT {
+            return Some(format!(
+                "covers {}% of the surface in obstacles",
+                obstacle_percent
+            ));
+        }
+
+        let station = self.station_coords();
+        let clearing = self
+            .layer_rows(0)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                self.distance(station, (x, y)) <= STATION_CLEARING_RADIUS
+                    && !self.is_obstacle(x, y)
+                    && !self.is_station(x, y)
+            })
+            .count();
+        if clearing < MIN_CLEARING_TILES {
+            return Some(format!(
+                "leaves {} walkable tiles around the station",
+                clearing
+            ));
+        }
+        None
+    }
+
+    /// Fills a few spots of every cave layer with rich minerals, each spreading to the free
+    /// tiles around it
+    fn spawn_veins(&mut self, seed: u64) {
+        for layer in 1..self.layers() {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(layer as u64));
+            let candidates: Vec<(usize, usize)> = self
+                .layer_rows(layer)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .filter(|&(x, y)| !self.is_obstacle(x, y) && !self.is_shaft(x, y))
+                .collect();
+            for &(x, y) in candidates.choose_multiple(&mut rng, VEINS_PER_LAYER) {
+                let vein: Vec<(usize, usize)> = std::iter::once((x, y))
+                    .chain(Self::valid_neighbors(x, y, self.width, self.height))
+                    .filter(|&(vx, vy)| {
+                        self.layer_of(vy) == layer
+                            && !self.is_obstacle(vx, vy)
+                            && !self.is_shaft(vx, vy)
+                    })
+                    .collect();
+                for (vx, vy) in vein {
+                    let amount = rng.random_range(VEIN_AMOUNT);
+                    self.resource_manager
+                        .add_resource(vx, vy, ResourceType::MINERALS, amount);
+                }
+            }
+        }
+    }
+
     /// Spawns resources at random walkable positions
     pub fn spawn_resources(&mut self, count: usize, seed: u64) {
         let mut rng = StdRng::seed_from_u64(seed);
@@ -74,23 +385,50 @@ impl Map {
                     .enumerate()
                     .filter_map(move |(x, &cell)| if !cell { Some((x, y)) } else { None })
             })
+            .filter(|&(x, y)| !self.is_shaft(x, y))
             .collect();
-        let resource_types = [
-            ResourceType::Energy,
-            ResourceType::Minerals,
-            ResourceType::SciencePoints,
-        ];
+        let resource_types = self.catalog.all();
         for &(x, y) in walkable_positions.choose_multiple(&mut rng, count) {
-            let resource_type = resource_types.choose(&mut rng).unwrap().clone();
+            let resource_type = *resource_types.choose(&mut rng).unwrap();
 
-            let resource_amount = match resource_type {
-                ResourceType::SciencePoints => rng.random_range(1..=5),
-                _ => rng.random_range(10..100),
+            // Samples to analyze come in small deposits, stock in large ones
+            let resource_amount = if self.catalog.is_consumable(resource_type) {
+                rng.random_range(10..100)
+            } else {
+                rng.random_range(1..=5)
             };
 
             self.resource_manager
                 .add_resource(x, y, resource_type, resource_amount);
         }
+        self.pick_joint_sites(seed);
+    }
+
+    /// Makes one science deposit in `JOINT_SITE_ONE_IN` a joint site, drawing from its own
+    /// generator so the deposits come out the same as without joint sites
+    fn pick_joint_sites(&mut self, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed ^ JOINT_SITE_SALT);
+        let mut science: Vec<(usize, usize)> = self
+            .resource_manager
+            .get_all_resources()
+            .iter()
+            .filter(|(_, resource)| resource.resource_type == ResourceType::SCIENCE_POINTS)
+            .map(|(&coords, _)| coords)
+            .collect();
+        science.sort_unstable();
+        self.joint_sites = science
+            .into_iter()
+            .filter(|_| rng.random_ratio(1, JOINT_SITE_ONE_IN))
+            .collect();
+    }
+
+    /// Whether the science deposit on `(x, y)` needs a scientist and a collector together
+    pub fn is_joint_site(&self, x: usize, y: usize) -> bool {
+        self.joint_sites.contains(&(x, y))
+            && self
+                .resource_manager
+                .get_resource(x, y)
+                .is_some_and(|resource| resource.resource_type == ResourceType::SCIENCE_POINTS)
     }
 
     /// Ensures all walkable areas are connected
@@ -106,14 +444,16 @@ impl Map {
             }
         }
 
-        if regions.len() <= 1 {
-            return;
-        }
-        let main_region = &regions[0];
-        for region in regions.iter().skip(1) {
-            let (main_x, main_y) = main_region[0];
+        // Each layer's first region is its main one; paths never cross a seam
+        let mut main_regions: HashMap<usize, (usize, usize)> = HashMap::new();
+        for region in &regions {
             let (other_x, other_y) = region[0];
-            self.create_path(main_x, main_y, other_x, other_y);
+            match main_regions.get(&self.layer_of(other_y)) {
+                Some(&(main_x, main_y)) => self.create_path(main_x, main_y, other_x, other_y),
+                None => {
+                    main_regions.insert(self.layer_of(other_y), (other_x, other_y));
+                }
+            }
         }
     }
 
@@ -129,7 +469,7 @@ impl Map {
         &self,
         start_x: usize,
         start_y: usize,
-        visited: &mut Vec<Vec<bool>>,
+        visited: &mut [Vec<bool>],
     ) -> Vec<(usize, usize)> {
         let mut region = Vec::new();
         let mut queue = VecDeque::new();
@@ -195,90 +535,257 @@ impl Map {
         }
     }
 
-    pub fn get_resource(
-        &self,
-        x: usize,
-        y: usize,
-    ) -> Option<(crate::communication::channels::ResourceType, u32)> {
-        self.resource_manager.get_resource(x, y).map(|ressource| {
-            let channel_resource_type = match ressource.resource_type {
-                ResourceType::Energy => crate::communication::channels::ResourceType::Energy,
-                ResourceType::Minerals => crate::communication::channels::ResourceType::Minerals,
-                ResourceType::SciencePoints => {
-                    crate::communication::channels::ResourceType::SciencePoints
-                }
-            };
-            (channel_resource_type, ressource.amount)
-        })
+    pub fn get_resource(&self, x: usize, y: usize) -> Option<(ResourceType, u32)> {
+        self.resource_manager
+            .get_resource(x, y)
+            .map(|resource| (resource.resource_type, resource.amount))
     }
 
-    /// Removes a resource at the given coordinates if it's consumable (Energy, Minerals)
-    /// For SciencePoints (non-consumable), just returns the resource info without removing it
+    /// Removes a resource at the given coordinates if it's consumable
+    /// For non-consumable resources such as science points, just returns the resource info
+    /// without removing it
     ///
     /// # Parameters
     /// - `x`, `y`: The coordinates to get/remove a resource from
     ///
     /// # Returns
     /// Some((resource_type, amount)) if a resource was found, None if no resource existed
-    pub fn remove_resource(
-        &mut self,
-        x: usize,
-        y: usize,
-    ) -> Option<(crate::communication::channels::ResourceType, u32)> {
+    pub fn remove_resource(&mut self, x: usize, y: usize) -> Option<(ResourceType, u32)> {
         let (r_type, amount) = {
             let resource = self.resource_manager.get_resource(x, y)?;
-            (resource.resource_type.clone(), resource.amount)
+            (resource.resource_type, resource.amount)
         };
 
-        let is_consumable = matches!(r_type, ResourceType::Energy | ResourceType::Minerals);
-        let channel_resource_type = match r_type {
-            ResourceType::Energy => crate::communication::channels::ResourceType::Energy,
-            ResourceType::Minerals => crate::communication::channels::ResourceType::Minerals,
-            ResourceType::SciencePoints => {
-                crate::communication::channels::ResourceType::SciencePoints
-            }
-        };
+        if self.catalog.is_consumable(r_type) {
+            self.resource_manager.remove_resource(x, y);
+            self.salvage.remove(&(x, y));
+        }
 
-        if is_consumable {
+        Some((r_type, amount))
+    }
+
+    /// Takes up to `max_amount` from the consumable deposit at the given coordinates in one
+    /// step, leaving the rest in place and removing the deposit once it is empty.
+    ///
+    /// # Returns
+    /// The type and amount taken, `None` if there was nothing consumable to take
+    pub fn try_take_resource(
+        &mut self,
+        x: usize,
+        y: usize,
+        max_amount: u32,
+    ) -> Option<(ResourceType, u32)> {
+        let (resource_type, amount) = self.get_resource(x, y)?;
+        let taken = amount.min(max_amount);
+        if taken == 0 || !self.catalog.is_consumable(resource_type) {
+            return None;
+        }
+        if taken == amount {
             self.resource_manager.remove_resource(x, y);
+            self.salvage.remove(&(x, y));
+        } else {
+            self.add_resource(x, y, resource_type, amount - taken);
         }
+        *self.taken.entry(resource_type).or_insert(0) += u64::from(taken);
+        Some((resource_type, taken))
+    }
+
+    /// Resource types of the run, shared with its robots
+    pub fn catalog(&self) -> &Arc<ResourceCatalog> {
+        &self.catalog
+    }
 
-        Some((channel_resource_type, amount))
+    /// Amounts taken from deposits with `try_take_resource` so far, by type
+    pub fn taken(&self) -> &HashMap<ResourceType, u64> {
+        &self.taken
     }
 
-    pub fn add_resource(
+    /// Leaves `amount` of a robot's cargo on `(x, y)` as a salvage deposit, adding to a deposit
+    /// of the same type already there. Fails on tiles robots cannot collect from and on
+    /// deposits of another type.
+    pub fn drop_salvage(
         &mut self,
         x: usize,
         y: usize,
-        resource_type: crate::communication::channels::ResourceType,
+        resource_type: ResourceType,
         amount: u32,
-    ) {
-        use super::resources::ResourceType as InternalResourceType;
-        let internal_type = match resource_type {
-            crate::communication::channels::ResourceType::Energy => InternalResourceType::Energy,
-            crate::communication::channels::ResourceType::Minerals => {
-                InternalResourceType::Minerals
-            }
-            crate::communication::channels::ResourceType::SciencePoints => {
-                InternalResourceType::SciencePoints
-            }
+    ) -> bool {
+        if amount == 0
+            || !self.catalog.is_consumable(resource_type)
+            || self.is_obstacle(x, y)
+            || self.is_station(x, y)
+            || self.is_shaft(x, y)
+        {
+            return false;
+        }
+        let amount = match self.get_resource(x, y) {
+            None => amount,
+            Some((existing, present)) if existing == resource_type => present + amount,
+            Some(_) => return false,
         };
+        self.add_resource(x, y, resource_type, amount);
+        self.salvage.insert((x, y));
+        true
+    }
+
+    /// Whether the deposit on `(x, y)` was dropped by a robot
+    pub fn is_salvage(&self, x: usize, y: usize) -> bool {
+        self.salvage.contains(&(x, y))
+    }
+
+    pub fn add_resource(&mut self, x: usize, y: usize, resource_type: ResourceType, amount: u32) {
         self.resource_manager
-            .add_resource(x, y, internal_type, amount);
+            .add_resource(x, y, resource_type, amount);
     }
 
     pub fn set_walkable(&mut self, x: usize, y: usize) {
         if let Some(row) = self.data.get_mut(y) {
             if let Some(cell) = row.get_mut(x) {
-                *cell = false;
+                if *cell {
+                    *cell = false;
+                    self.terrain_version += 1;
+                }
             }
         }
     }
 
+    /// Puts back the terrain of a saved run: `obstacles` become the only obstacle tiles and
+    /// `resources` the only resources
+    pub fn restore_terrain(
+        &mut self,
+        obstacles: &HashSet<(usize, usize)>,
+        resources: &[((usize, usize), ResourceType, u32)],
+    ) {
+        for (y, row) in self.data.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = obstacles.contains(&(x, y));
+            }
+        }
+        let stale: Vec<(usize, usize)> = self
+            .resource_manager
+            .get_all_resources()
+            .keys()
+            .copied()
+            .collect();
+        for (x, y) in stale {
+            self.resource_manager.remove_resource(x, y);
+        }
+        for ((x, y), resource_type, amount) in resources {
+            self.add_resource(*x, *y, *resource_type, *amount);
+        }
+        self.terrain_version += 1;
+    }
+
+    /// Tiles of the disc of `radius` around (x, y) that lie on a `width` x `height` map
+    pub fn tiles_within(
+        x: usize,
+        y: usize,
+        radius: usize,
+        width: usize,
+        height: usize,
+    ) -> Vec<(usize, usize)> {
+        let mut tiles = Vec::new();
+        for ty in y.saturating_sub(radius)..=(y + radius).min(height.saturating_sub(1)) {
+            for tx in x.saturating_sub(radius)..=(x + radius).min(width.saturating_sub(1)) {
+                let (dx, dy) = (tx.abs_diff(x), ty.abs_diff(y));
+                if dx * dx + dy * dy <= radius * radius {
+                    tiles.push((tx, ty));
+                }
+            }
+        }
+        tiles
+    }
+
+    /// Whether no walkable tile borders the station to leave it by
+    pub fn is_station_walled_in(&self) -> bool {
+        self.station_area.iter().all(|&(x, y)| {
+            self.neighbors(x, y)
+                .all(|(nx, ny)| self.is_station(nx, ny) || self.is_obstacle(nx, ny))
+        })
+    }
+
+    /// Tiles of the disc of `radius` around (x, y) on the layer of (x, y)
+    pub fn layer_tiles_within(&self, x: usize, y: usize, radius: usize) -> Vec<(usize, usize)> {
+        let rows = self.layer_rows(self.layer_of(y));
+        Self::tiles_within(x, y, radius, self.width, self.height)
+            .into_iter()
+            .filter(|(_, ty)| rows.contains(ty))
+            .collect()
+    }
+
+    /// Blasts a crater of `radius` around (x, y): its tiles become obstacles and lose their
+    /// resources, sparing the station and `keep_clear`. A path is then reopened to any region
+    /// the crater cut off. Returns the tiles that became obstacles.
+    pub fn add_crater(
+        &mut self,
+        x: usize,
+        y: usize,
+        radius: usize,
+        keep_clear: &HashSet<(usize, usize)>,
+    ) -> Vec<(usize, usize)> {
+        let mut crater = Vec::new();
+        for (tx, ty) in Self::tiles_within(x, y, radius, self.width, self.height) {
+            if self.data[ty][tx]
+                || self.is_station(tx, ty)
+                || self.is_shaft(tx, ty)
+                || self.layer_of(ty) != self.layer_of(y)
+                || keep_clear.contains(&(tx, ty))
+            {
+                continue;
+            }
+            self.resource_manager.remove_resource(tx, ty);
+            self.data[ty][tx] = true;
+            crater.push((tx, ty));
+        }
+        if !crater.is_empty() {
+            self.connect_isolated_regions();
+            self.terrain_version += 1;
+        }
+        crater
+    }
+
+    pub fn set_hostiles(&mut self, positions: Vec<(usize, usize)>) {
+        self.hostiles = positions;
+    }
+
+    pub fn hostiles(&self) -> &[(usize, usize)] {
+        &self.hostiles
+    }
+
+    /// Whether a hostile is within reach of the tile. The station is always safe.
+    pub fn is_threatened(&self, x: usize, y: usize) -> bool {
+        !self.is_station(x, y)
+            && self
+                .hostiles
+                .iter()
+                .any(|&hostile| self.distance(hostile, (x, y)) <= HOSTILE_REACH)
+    }
+
+    /// Bumped whenever an obstacle tile changes, so renderers know to rebuild the terrain
+    pub fn terrain_version(&self) -> u64 {
+        self.terrain_version
+    }
+
+    pub fn resource_change_counter(&self) -> u64 {
+        self.resource_manager.change_counter()
+    }
+
+    /// Tiles whose resource changed after `counter`, see `ResourceManager::changes_since`
+    pub fn resource_changes_since(&self, counter: u64) -> Option<Vec<(usize, usize)>> {
+        self.resource_manager.changes_since(counter)
+    }
+
     pub fn get_all_resources(&self) -> &HashMap<(usize, usize), Resource> {
         self.resource_manager.get_all_resources()
     }
 
+    /// Whether any consumable resource is left to collect
+    pub fn has_consumable_resources(&self) -> bool {
+        self.get_all_resources().values().any(|resource| {
+            resource.amount > 0 && self.catalog.is_consumable(resource.resource_type)
+        })
+    }
+
     pub fn has_resource(&self, x: usize, y: usize) -> bool {
         self.resource_manager.has_resource(x, y)
     }
@@ -290,35 +797,269 @@ impl Map {
         self.data[y][x]
     }
 
+    /// Whether robots can stand on (x, y), and why not
+    pub fn check_walkable(&self, x: usize, y: usize) -> Result<(), MapError> {
+        if x >= self.width || y >= self.height {
+            Err(MapError::OutOfBounds { x, y })
+        } else if self.is_obstacle(x, y) {
+            Err(MapError::NotWalkable { x, y })
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn is_station(&self, x: usize, y: usize) -> bool {
         self.station_area.contains(&(x, y))
     }
+
+    /// Center tile of the station, where robots dock
+    pub fn station_coords(&self) -> (usize, usize) {
+        let (min_x, max_x) = self
+            .station_area
+            .iter()
+            .fold((usize::MAX, 0), |(lo, hi), &(x, _)| (lo.min(x), hi.max(x)));
+        let (min_y, max_y) = self
+            .station_area
+            .iter()
+            .fold((usize::MAX, 0), |(lo, hi), &(_, y)| (lo.min(y), hi.max(y)));
+        if self.station_area.is_empty() {
+            (self.width / 2, self.height / 2)
+        } else {
+            ((min_x + max_x) / 2, (min_y + max_y) / 2)
+        }
+    }
+
+    /// Up to `count` free walkable tiles closest to the station (by walking distance), excluding
+    /// the docking tile and resource tiles
+    pub fn free_tiles_near_station(&self, count: usize) -> Vec<(usize, usize)> {
+        let dock = self.station_coords();
+        let mut tiles = Vec::with_capacity(count);
+        let mut queue = VecDeque::from([dock]);
+        let mut seen = HashSet::from([dock]);
+
+        while let Some((x, y)) = queue.pop_front() {
+            if tiles.len() >= count {
+                break;
+            }
+            if (x, y) != dock && !self.has_resource(x, y) {
+                tiles.push((x, y));
+            }
+            for neighbor in self.neighbors(x, y) {
+                if !self.is_obstacle(neighbor.0, neighbor.1) && seen.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        tiles
+    }
+
+    /// Character used to draw a single tile, shared by `Display` and the UI renderer
+    pub fn tile_symbol(&self, x: usize, y: usize) -> char {
+        if self.is_station(x, y) {
+            '⌂'
+        } else if self.is_shaft(x, y) {
+            '≡'
+        } else if self.is_obstacle(x, y) {
+            '█'
+        } else if let Some(resource) = self.resource_manager.get_resource(x, y) {
+            let symbol = self.catalog.spec(resource.resource_type).symbol;
+            if self.is_salvage(x, y) {
+                return symbol.to_ascii_lowercase();
+            }
+            if self.is_joint_site(x, y) {
+                return 's';
+            }
+            symbol
+        } else {
+            ' '
+        }
+    }
 }
 
-// Formats the `Map` as a grid of characters (`#` for obstacles, `.` for walkable tiles)
+// Formats the `Map` as a grid of characters (`█` for obstacles, ` ` for walkable tiles)
 impl fmt::Display for Map {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let resources = self.resource_manager.get_all_resources();
-
         for y in 0..self.height {
             for x in 0..self.width {
-                let symbol = if self.is_station(x, y) {
-                    '⌂'
-                } else if self.data[y][x] {
-                    '█'
-                } else if let Some(resource) = resources.get(&(x, y)) {
-                    match resource.resource_type {
-                        ResourceType::Energy => 'E',        // ⚡
-                        ResourceType::Minerals => 'M',      // ⛏
-                        ResourceType::SciencePoints => 'S', // 🧪
-                    }
-                } else {
-                    ' '
-                };
-                write!(f, "{symbol}")?;
+                write!(f, "{}", self.tile_symbol(x, y))?;
             }
             writeln!(f)?;
         }
         Ok(())
     }
 }
+
+/// Seed of the `attempt`th regeneration of a degenerate map, far from the seeds its cave
+/// layers use
+fn derived_seed(map_seed: u32, attempt: u32) -> u32 {
+    map_seed ^ attempt.wrapping_mul(0x9E37_79B9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_tiles_near_station_start_next_to_the_dock() {
+        let map = Map::new(40, 20, 5);
+        let dock = map.station_coords();
+        assert_eq!(dock, (20, 10));
+
+        let tiles = map.free_tiles_near_station(5);
+        assert_eq!(tiles.len(), 5);
+        assert!(!tiles.contains(&dock));
+        // The first ring is the station itself, always walkable
+        for &(x, y) in &tiles[..4] {
+            assert_eq!(x.abs_diff(dock.0) + y.abs_diff(dock.1), 1);
+        }
+        assert!(tiles.iter().all(|&(x, y)| !map.is_obstacle(x, y)));
+    }
+
+    #[test]
+    fn test_taking_a_resource_never_exceeds_the_deposit_or_the_limit() {
+        let mut map = Map::new(10, 10, 3);
+        map.add_resource(1, 1, ResourceType::ENERGY, 10);
+        map.add_resource(2, 1, ResourceType::SCIENCE_POINTS, 4);
+
+        assert_eq!(
+            map.try_take_resource(1, 1, 6),
+            Some((ResourceType::ENERGY, 6))
+        );
+        assert_eq!(map.get_resource(1, 1), Some((ResourceType::ENERGY, 4)));
+        assert_eq!(map.try_take_resource(1, 1, 0), None);
+        assert_eq!(
+            map.try_take_resource(1, 1, 50),
+            Some((ResourceType::ENERGY, 4))
+        );
+        assert_eq!(map.get_resource(1, 1), None);
+        assert_eq!(map.try_take_resource(2, 1, 50), None);
+        assert_eq!(map.try_take_resource(3, 3, 50), None);
+    }
+
+    #[test]
+    fn test_joint_sites_are_science_deposits_picked_alike_by_every_process() {
+        let generate = || Map::generate(60, 30, 7, 11, 20, MapShape::default(), Arc::default());
+        let (map, copy) = (generate(), generate());
+        let joint: Vec<(usize, usize)> = map
+            .get_all_resources()
+            .keys()
+            .copied()
+            .filter(|&(x, y)| map.is_joint_site(x, y))
+            .collect();
+        assert!(!joint.is_empty());
+        for &(x, y) in &joint {
+            assert!(copy.is_joint_site(x, y));
+            assert_eq!(map.tile_symbol(x, y), 's');
+            assert!(matches!(
+                map.get_resource(x, y),
+                Some((ResourceType::SCIENCE_POINTS, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_maps_survive_a_json_round_trip() {
+        let shape = MapShape {
+            cave_layers: 1,
+            ..MapShape::default()
+        };
+        let map = Map::generate(40, 20, 7, 11, 20, shape, Arc::default());
+        let json = serde_json::to_string(&map).unwrap();
+        let copy: Map = serde_json::from_str(&json).unwrap();
+
+        assert_eq!((copy.width, copy.height), (map.width, map.height));
+        assert_eq!(copy.shafts_toward(0, 1), map.shafts_toward(0, 1));
+        for y in 0..map.height {
+            for x in 0..map.width {
+                assert_eq!(copy.is_obstacle(x, y), map.is_obstacle(x, y));
+                assert_eq!(copy.get_resource(x, y), map.get_resource(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_crater_destroys_resources_but_spares_station_and_robots() {
+        let mut map = Map::new(40, 20, 5);
+        let dock = map.station_coords();
+        let robot = (dock.0 + 2, dock.1);
+        let target = (dock.0 + 3, dock.1);
+        map.set_walkable(target.0, target.1);
+        map.add_resource(target.0, target.1, ResourceType::MINERALS, 50);
+        let version = map.terrain_version();
+
+        let crater = map.add_crater(dock.0 + 2, dock.1, 2, &HashSet::from([robot]));
+        assert!(crater.contains(&target));
+        assert!(!crater.contains(&robot));
+        assert!(map.is_obstacle(target.0, target.1));
+        assert!(!map.has_resource(target.0, target.1));
+        assert!(!map.is_obstacle(robot.0, robot.1));
+        assert!(!map.is_obstacle(dock.0 + 1, dock.1));
+        assert!(map.terrain_version() > version);
+    }
+
+    #[test]
+    fn test_cave_layers_are_only_reached_through_shafts() {
+        let mut map = Map::new(40, 20, 5);
+        map.add_cave_layer(6);
+        assert_eq!((map.layers(), map.height), (2, 41));
+        assert_eq!(map.layer_rows(1), 21..41);
+        assert!((0..40).all(|x| map.is_obstacle(x, 20)));
+
+        let down = map.shafts_toward(0, 1);
+        assert_eq!(down.len(), SHAFTS_PER_LAYER);
+        for (x, y) in down {
+            let (exit_x, exit_y) = map.shaft_exit(x, y).unwrap();
+            assert_eq!((exit_x, exit_y), (x, y + 21));
+            assert!(!map.is_obstacle(exit_x, exit_y));
+            assert_eq!(map.shaft_exit(exit_x, exit_y), Some((x, y)));
+        }
+        assert_eq!(map.shafts_toward(1, 0).len(), SHAFTS_PER_LAYER);
+        assert_eq!(map.station_coords(), (20, 10));
+    }
+
+    #[test]
+    fn test_rich_veins_lie_underground_only() {
+        let map = Map::generate(
+            40,
+            12,
+            5,
+            6,
+            30,
+            MapShape {
+                cave_layers: 1,
+                ..MapShape::default()
+            },
+            Arc::default(),
+        );
+        let rich: Vec<(usize, usize)> = map
+            .resource_manager
+            .get_all_resources()
+            .iter()
+            .filter(|(_, resource)| resource.amount >= *VEIN_AMOUNT.start())
+            .map(|(&coords, _)| coords)
+            .collect();
+        assert!(rich.len() >= VEINS_PER_LAYER);
+        assert!(rich.iter().all(|&(_, y)| map.layer_of(y) == 1));
+    }
+
+    #[test]
+    fn test_degenerate_maps_are_generated_again_from_a_derived_seed() {
+        let shape = MapShape::default();
+        // Seed 3 walls the station in
+        assert!(Map::new(90, 15, 3).degeneracy().is_some());
+        let (map, seed) = Map::generate_playable(90, 15, 3, 1, 50, shape, Arc::default());
+        assert_ne!(seed, 3);
+        assert_eq!(map.degeneracy(), None);
+        // The final seed alone rebuilds the same map, e.g. in another process
+        assert_eq!(
+            Map::generate(90, 15, seed, 1, 50, shape, Arc::default()).to_string(),
+            map.to_string()
+        );
+
+        assert!(Map::new(90, 15, 34).degeneracy().is_none());
+        assert_eq!(
+            Map::generate_playable(90, 15, 34, 1, 50, shape, Arc::default()).1,
+            34
+        );
+    }
+}
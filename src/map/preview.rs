@@ -0,0 +1,214 @@
+//! A generated map described without running the simulation on it, to pick seeds by: the
+//! drawing with a ruler, where the station is and what the resources and regions look like.
+
+use std::fmt::{self, Write};
+
+use super::noise::Map;
+use super::resources::{ResourceSpec, ResourceType};
+
+/// Names of the bands each layer is split into, west to east
+const BAND_NAMES: [&str; 3] = ["west", "center", "east"];
+
+/// Deposits of one resource type over the map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepositTally {
+    pub resource_type: ResourceType,
+    /// What the map's catalog names and draws the type as
+    pub spec: ResourceSpec,
+    pub deposits: usize,
+    pub amount: u64,
+    /// Steps from the dock to the closest deposit, as the crow flies
+    pub nearest: Option<usize>,
+}
+
+/// A third of a layer, west to east
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionStats {
+    pub name: String,
+    pub walkable_percent: usize,
+    /// Deposits by type, in the order of `ResourceType::all`
+    pub deposits: Vec<usize>,
+}
+
+/// What `--preview` prints of a map
+#[derive(Debug, Clone)]
+pub struct MapPreview {
+    pub width: usize,
+    pub height: usize,
+    pub map_seed: u32,
+    pub resource_seed: u64,
+    pub dock: (usize, usize),
+    pub walkable_percent: usize,
+    pub resources: Vec<DepositTally>,
+    pub regions: Vec<RegionStats>,
+    /// The map as `Display` draws it, a row per line
+    rows: Vec<String>,
+}
+
+impl MapPreview {
+    /// Describes `map`, generated from `map_seed` and `resource_seed`
+    pub fn new(map: &Map, map_seed: u32, resource_seed: u64) -> Self {
+        let dock = map.station_coords();
+        let all_tiles = (0..map.height).flat_map(|y| (0..map.width).map(move |x| (x, y)));
+        let (mut tiles, mut walkable) = (0, 0);
+        for (x, y) in all_tiles.filter(|&(_, y)| !is_seam(map, y)) {
+            tiles += 1;
+            walkable += usize::from(!map.is_obstacle(x, y));
+        }
+
+        let resource_types = map.catalog().all();
+        let resources = resource_types
+            .iter()
+            .map(|resource_type| {
+                let deposits: Vec<_> = map
+                    .get_all_resources()
+                    .iter()
+                    .filter(|(_, resource)| resource.resource_type == *resource_type)
+                    .collect();
+                DepositTally {
+                    resource_type: *resource_type,
+                    spec: map.catalog().spec(*resource_type).clone(),
+                    deposits: deposits.len(),
+                    amount: deposits.iter().map(|(_, r)| u64::from(r.amount)).sum(),
+                    nearest: deposits
+                        .iter()
+                        .map(|(&tile, _)| map.distance(dock, tile))
+                        .min(),
+                }
+            })
+            .collect();
+
+        let mut regions = Vec::new();
+        for layer in 0..map.layers() {
+            for (band, name) in BAND_NAMES.iter().enumerate() {
+                let columns =
+                    band * map.width / BAND_NAMES.len()..(band + 1) * map.width / BAND_NAMES.len();
+                let tiles: Vec<_> = map
+                    .layer_rows(layer)
+                    .flat_map(|y| columns.clone().map(move |x| (x, y)))
+                    .collect();
+                let open = tiles.iter().filter(|&&(x, y)| !map.is_obstacle(x, y));
+                let mut deposits = vec![0; resource_types.len()];
+                for &(x, y) in &tiles {
+                    if let Some((resource_type, _)) = map.get_resource(x, y) {
+                        let at = resource_types.iter().position(|t| *t == resource_type);
+                        deposits[at.unwrap_or(0)] += 1;
+                    }
+                }
+                regions.push(RegionStats {
+                    name: match layer {
+                        0 => name.to_string(),
+                        _ => format!("cave {} {}", layer, name),
+                    },
+                    walkable_percent: (open.count() * 100).checked_div(tiles.len()).unwrap_or(0),
+                    deposits,
+                });
+            }
+        }
+
+        Self {
+            width: map.width,
+            height: map.height,
+            map_seed,
+            resource_seed,
+            dock,
+            walkable_percent: (walkable * 100).checked_div(tiles).unwrap_or(0),
+            resources,
+            regions,
+            rows: map.to_string().lines().map(str::to_string).collect(),
+        }
+    }
+}
+
+/// Whether row `y` is the solid row between two layers
+fn is_seam(map: &Map, y: usize) -> bool {
+    !map.layer_rows(map.layer_of(y)).contains(&y)
+}
+
+impl fmt::Display for MapPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Map {}x{}, map seed {}, resource seed {}",
+            self.width, self.height, self.map_seed, self.resource_seed
+        )?;
+
+        // Tens then units of the column numbers above the drawing, row numbers on its left
+        let mut tens = String::from("     ");
+        let mut units = String::from("     ");
+        for x in 0..self.width {
+            tens.push(if x % 10 == 0 {
+                char::from_digit((x / 10 % 10) as u32, 10).unwrap_or(' ')
+            } else {
+                ' '
+            });
+            write!(units, "{}", x % 10)?;
+        }
+        writeln!(f, "{}\n{}", tens.trim_end(), units)?;
+        for (y, row) in self.rows.iter().enumerate() {
+            writeln!(f, "{:>4} {}", y, row)?;
+        }
+        write!(f, "     ⌂ station  █ obstacle  ≡ shaft")?;
+        for tally in &self.resources {
+            write!(f, "  {} {}", tally.spec.symbol, tally.spec.name)?;
+        }
+        writeln!(f, "  s joint site")?;
+
+        writeln!(f)?;
+        writeln!(f, "Station dock at ({}, {})", self.dock.0, self.dock.1)?;
+        writeln!(f, "Walkable: {}% of the map", self.walkable_percent)?;
+        writeln!(f, "Resources:")?;
+        for tally in &self.resources {
+            let nearest = match tally.nearest {
+                Some(steps) => format!("nearest {} steps from the dock", steps),
+                None => "none".to_string(),
+            };
+            writeln!(
+                f,
+                "  {:<14} {:>3} deposits, {:>5} units, {}",
+                tally.spec.name, tally.deposits, tally.amount, nearest
+            )?;
+        }
+        writeln!(f, "Regions:")?;
+        for region in &self.regions {
+            write!(
+                f,
+                "  {:<16} {:>3}% walkable,",
+                region.name, region.walkable_percent
+            )?;
+            for (tally, deposits) in self.resources.iter().zip(&region.deposits) {
+                write!(f, " {} {:>2} ", tally.spec.symbol, deposits)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::topology::MapShape;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_preview_counts_what_the_map_holds() {
+        let shape = MapShape {
+            cave_layers: 1,
+            ..MapShape::default()
+        };
+        let (map, seed) = Map::generate_playable(60, 12, 7, 9, 30, shape, Arc::default());
+        let preview = MapPreview::new(&map, seed, 9);
+
+        let deposits: usize = preview.resources.iter().map(|t| t.deposits).sum();
+        assert_eq!(deposits, map.get_all_resources().len());
+        let by_region: usize = preview.regions.iter().flat_map(|r| &r.deposits).sum();
+        assert_eq!(by_region, deposits);
+        assert_eq!(preview.regions.len(), 6);
+        assert_eq!(preview.dock, map.station_coords());
+
+        let text = preview.to_string();
+        assert!(text.contains(&format!("map seed {}", seed)));
+        assert!(text.contains("cave 1 east"));
+    }
+}
@@ -1,13 +1,273 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::LazyLock;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum ResourceType {
-    Energy,
-    Minerals,
-    SciencePoints,
+/// Number of tile changes remembered by `ResourceManager` for incremental consumers.
+/// Older changes are dropped, forcing consumers that fell behind to rebuild from scratch.
+const CHANGE_LOG_CAPACITY: usize = 4096;
+
+/// Letters robots are drawn with, which resources cannot use
+const RESERVED_SYMBOLS: [char; 3] = ['X', 'C', 'R'];
+
+/// A kind of resource, as configured
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceSpec {
+    pub name: String,
+    /// Capital letter its deposits are drawn with, lower case for salvage
+    pub symbol: char,
+    /// Color of its deposits on the map, e.g. `yellow` or `#ff8800`
+    pub color: String,
+    /// Collected and stocked by the station to be spent; otherwise analyzed on site by
+    /// scientists for science
+    pub consumable: bool,
+    /// Cargo room one unit takes
+    #[serde(default = "one")]
+    pub weight: u32,
+    /// Worth of one unit in the stock, or science per unit analyzed
+    #[serde(default = "one")]
+    pub value: u32,
+}
+
+impl ResourceSpec {
+    fn new(name: &str, symbol: char, color: &str, consumable: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            symbol,
+            color: color.to_string(),
+            consumable,
+            weight: 1,
+            value: 1,
+        }
+    }
+}
+
+/// What a type outside the catalog reads as, e.g. one a corrupted message names
+static UNKNOWN_SPEC: LazyLock<ResourceSpec> = LazyLock::new(|| ResourceSpec {
+    value: 0,
+    ..ResourceSpec::new("Unknown", '?', "gray", false)
+});
+
+fn builtin_specs() -> Vec<ResourceSpec> {
+    vec![
+        ResourceSpec::new("Energy", 'E', "yellow", true),
+        ResourceSpec::new("Minerals", 'M', "blue", true),
+        ResourceSpec::new("SciencePoints", 'S', "green", false),
+    ]
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Checks resource types to add to the built-in ones, or to change the symbol, color,
+/// weight or value of, going by name
+pub fn validate_specs(specs: &[ResourceSpec]) -> Result<(), String> {
+    merged_specs(specs).map(|_| ())
 }
 
-#[derive(Debug, Clone)]
+fn merged_specs(specs: &[ResourceSpec]) -> Result<Vec<ResourceSpec>, String> {
+    let mut merged = builtin_specs();
+    for spec in specs {
+        if spec.name.trim().is_empty() {
+            return Err("Resource names must not be empty".to_string());
+        }
+        if spec.weight == 0 {
+            return Err(format!("Resource {} must weigh at least 1", spec.name));
+        }
+        match merged.iter_mut().find(|known| known.name == spec.name) {
+            Some(builtin) if builtin.consumable != spec.consumable => {
+                return Err(format!(
+                    "Resource {} is built in, whether it is consumable cannot change",
+                    spec.name
+                ));
+            }
+            Some(builtin) => *builtin = spec.clone(),
+            None => merged.push(spec.clone()),
+        }
+    }
+    for (index, spec) in merged.iter().enumerate() {
+        if !spec.symbol.is_ascii_uppercase() || RESERVED_SYMBOLS.contains(&spec.symbol) {
+            return Err(format!(
+                "Resource {} needs a capital letter other than X, C or R as its symbol",
+                spec.name
+            ));
+        }
+        if merged[..index]
+            .iter()
+            .any(|other| other.symbol == spec.symbol || other.name.eq_ignore_ascii_case(&spec.name))
+        {
+            return Err(format!(
+                "Resource {} shares its name or symbol with another",
+                spec.name
+            ));
+        }
+    }
+    Ok(merged)
+}
+
+/// The resource types of a run: the built-in ones, then those added by the settings. Owned
+/// by the run's map and handed to its robots, so two runs in one process may differ. Saved
+/// and sent as its list of specs, checked again when read back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<ResourceSpec>", into = "Vec<ResourceSpec>")]
+pub struct ResourceCatalog {
+    specs: Vec<ResourceSpec>,
+}
+
+impl Default for ResourceCatalog {
+    fn default() -> Self {
+        Self {
+            specs: builtin_specs(),
+        }
+    }
+}
+
+impl TryFrom<Vec<ResourceSpec>> for ResourceCatalog {
+    type Error = String;
+
+    fn try_from(specs: Vec<ResourceSpec>) -> Result<Self, String> {
+        Self::with_specs(&specs)
+    }
+}
+
+impl From<ResourceCatalog> for Vec<ResourceSpec> {
+    fn from(catalog: ResourceCatalog) -> Self {
+        catalog.specs
+    }
+}
+
+impl ResourceCatalog {
+    /// The built-in types with `specs` added or changed, see `validate_specs`
+    pub fn with_specs(specs: &[ResourceSpec]) -> Result<Self, String> {
+        Ok(Self {
+            specs: merged_specs(specs)?,
+        })
+    }
+
+    /// Every type known, the built-in ones first
+    pub fn all(&self) -> Vec<ResourceType> {
+        (0..self.specs.len() as u16).map(ResourceType).collect()
+    }
+
+    /// Types collectors gather and the station stocks
+    pub fn consumables(&self) -> Vec<ResourceType> {
+        self.all()
+            .into_iter()
+            .filter(|&resource_type| self.is_consumable(resource_type))
+            .collect()
+    }
+
+    /// The type called `name`, whatever the case
+    pub fn from_name(&self, name: &str) -> Option<ResourceType> {
+        self.find(|spec| spec.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The type whose deposits are drawn with `symbol`
+    pub fn from_symbol(&self, symbol: char) -> Option<ResourceType> {
+        self.find(|spec| spec.symbol == symbol)
+    }
+
+    fn find(&self, matches: impl Fn(&ResourceSpec) -> bool) -> Option<ResourceType> {
+        let index = self.specs.iter().position(matches)?;
+        Some(ResourceType(index as u16))
+    }
+
+    /// What the type is configured as; a type from another catalog reads as an unknown,
+    /// worthless one
+    pub fn spec(&self, resource_type: ResourceType) -> &ResourceSpec {
+        self.specs
+            .get(usize::from(resource_type.0))
+            .unwrap_or(&UNKNOWN_SPEC)
+    }
+
+    pub fn name(&self, resource_type: ResourceType) -> &str {
+        &self.spec(resource_type).name
+    }
+
+    pub fn is_consumable(&self, resource_type: ResourceType) -> bool {
+        self.spec(resource_type).consumable
+    }
+
+    pub fn weight(&self, resource_type: ResourceType) -> u32 {
+        self.spec(resource_type).weight
+    }
+
+    pub fn value(&self, resource_type: ResourceType) -> u32 {
+        self.spec(resource_type).value
+    }
+}
+
+/// Types of resources on the map and in the robots' cargo, the one type shared by the map,
+/// the robots and the messages between them. A plain index into the run's
+/// `ResourceCatalog`, the built-in types being the constants; sent and saved as the index.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct ResourceType(u16);
+
+impl ResourceType {
+    pub const ENERGY: Self = Self(0);
+    pub const MINERALS: Self = Self(1);
+    pub const SCIENCE_POINTS: Self = Self(2);
+
+    /// The built-in type called `name`, as saves written before the catalog was the run's
+    /// named them
+    fn builtin_named(name: &str) -> Option<Self> {
+        let index = builtin_specs().iter().position(|spec| spec.name == name)?;
+        Some(Self(index as u16))
+    }
+}
+
+/// Built-in types by name, the others by index: the names live in the run's catalog
+impl fmt::Debug for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::ENERGY => f.write_str("Energy"),
+            Self::MINERALS => f.write_str("Minerals"),
+            Self::SCIENCE_POINTS => f.write_str("SciencePoints"),
+            Self(index) => write!(f, "ResourceType({})", index),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ResourceType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = ResourceType;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a resource type index or built-in name")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, index: u64) -> Result<ResourceType, E> {
+                u16::try_from(index)
+                    .map(ResourceType)
+                    .map_err(|_| E::custom(format!("resource type {} out of range", index)))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, index: i64) -> Result<ResourceType, E> {
+                u64::try_from(index)
+                    .map_err(|_| E::custom(format!("resource type {} out of range", index)))
+                    .and_then(|index| self.visit_u64(index))
+            }
+
+            // Map keys come as strings in JSON
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<ResourceType, E> {
+                match name.parse::<u64>() {
+                    Ok(index) => self.visit_u64(index),
+                    Err(_) => ResourceType::builtin_named(name)
+                        .ok_or_else(|| E::custom(format!("unknown resource type {}", name))),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Resource {
     pub resource_type: ResourceType,
     pub amount: u32,
@@ -22,15 +282,17 @@ impl Resource {
     }
 }
 
+#[derive(Default, Serialize, Deserialize)]
 pub struct ResourceManager {
+    #[serde(with = "super::tile_keyed")]
     resources: HashMap<(usize, usize), Resource>,
+    change_counter: u64,
+    change_log: VecDeque<(u64, (usize, usize))>,
 }
 
 impl ResourceManager {
     pub fn new() -> Self {
-        Self {
-            resources: HashMap::new(),
-        }
+        Self::default()
     }
 
     pub fn get_all_resources(&self) -> &HashMap<(usize, usize), Resource> {
@@ -42,7 +304,11 @@ impl ResourceManager {
     }
 
     pub fn remove_resource(&mut self, x: usize, y: usize) -> Option<Resource> {
-        self.resources.remove(&(x, y))
+        let removed = self.resources.remove(&(x, y));
+        if removed.is_some() {
+            self.record_change(x, y);
+        }
+        removed
     }
 
     pub fn has_resource(&self, x: usize, y: usize) -> bool {
@@ -52,5 +318,120 @@ impl ResourceManager {
     pub fn add_resource(&mut self, x: usize, y: usize, resource_type: ResourceType, amount: u32) {
         self.resources
             .insert((x, y), Resource::new(resource_type, amount));
+        self.record_change(x, y);
+    }
+
+    /// Monotonic counter bumped every time a tile's resource is added or removed
+    pub fn change_counter(&self) -> u64 {
+        self.change_counter
+    }
+
+    /// Returns the tiles changed after `counter`, or `None` if the change log no longer
+    /// reaches back that far and the caller has to treat every tile as changed.
+    pub fn changes_since(&self, counter: u64) -> Option<Vec<(usize, usize)>> {
+        if counter >= self.change_counter {
+            return Some(Vec::new());
+        }
+        match self.change_log.front() {
+            Some(&(oldest, _)) if oldest <= counter + 1 => Some(
+                self.change_log
+                    .iter()
+                    .filter(|(seq, _)| *seq > counter)
+                    .map(|&(_, pos)| pos)
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn record_change(&mut self, x: usize, y: usize) {
+        self.change_counter += 1;
+        if self.change_log.len() == CHANGE_LOG_CAPACITY {
+            self.change_log.pop_front();
+        }
+        self.change_log.push_back((self.change_counter, (x, y)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_since_tracks_added_and_removed_tiles() {
+        let mut manager = ResourceManager::new();
+        manager.add_resource(1, 1, ResourceType::ENERGY, 10);
+        let checkpoint = manager.change_counter();
+
+        manager.add_resource(2, 3, ResourceType::MINERALS, 5);
+        manager.remove_resource(1, 1);
+        manager.remove_resource(9, 9); // Nothing there, not a change
+
+        assert_eq!(manager.change_counter(), checkpoint + 2);
+        assert_eq!(
+            manager.changes_since(checkpoint),
+            Some(vec![(2, 3), (1, 1)])
+        );
+        assert_eq!(
+            manager.changes_since(manager.change_counter()),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn test_changes_since_reports_truncated_log() {
+        let mut manager = ResourceManager::new();
+        for i in 0..=CHANGE_LOG_CAPACITY {
+            manager.add_resource(i, 0, ResourceType::ENERGY, 1);
+        }
+        assert_eq!(manager.changes_since(0), None);
+        assert!(manager.changes_since(1).is_some());
+    }
+
+    #[test]
+    fn test_resource_specs_are_checked_against_the_built_in_ones() {
+        let mut gas = ResourceSpec::new("Gas", 'G', "magenta", true);
+        gas.weight = 2;
+        assert_eq!(validate_specs(std::slice::from_ref(&gas)), Ok(()));
+
+        let mut energy = ResourceSpec::new("Energy", 'P', "red", false);
+        assert!(validate_specs(&[energy.clone()]).is_err());
+        energy.consumable = true;
+        assert_eq!(validate_specs(&[energy]), Ok(()));
+
+        gas.symbol = 'M';
+        assert!(validate_specs(std::slice::from_ref(&gas)).is_err());
+        gas.symbol = 'R';
+        assert!(validate_specs(&[gas]).is_err());
+
+        let catalog = ResourceCatalog::default();
+        assert_eq!(catalog.from_name("minerals"), Some(ResourceType::MINERALS));
+        assert_eq!(catalog.from_symbol('S'), Some(ResourceType::SCIENCE_POINTS));
+        assert_eq!(catalog.name(ResourceType::ENERGY), "Energy");
+    }
+
+    #[test]
+    fn test_types_are_sent_by_index_and_read_back_against_the_catalog() {
+        let gas = ResourceSpec::new("Gas", 'G', "magenta", true);
+        let catalog = ResourceCatalog::with_specs(&[gas]).unwrap();
+        let gas = catalog.from_name("gas").unwrap();
+        let sent = serde_json::to_string(&Resource::new(gas, 4)).unwrap();
+        let received: Resource = serde_json::from_str(&sent).unwrap();
+        assert_eq!(received.resource_type, gas);
+        assert_eq!(catalog.name(received.resource_type), "Gas");
+
+        // The catalog travels whole and is checked again
+        let copy: ResourceCatalog =
+            serde_json::from_str(&serde_json::to_string(&catalog).unwrap()).unwrap();
+        assert_eq!(copy, catalog);
+        assert!(serde_json::from_str::<ResourceCatalog>(
+            r#"[{"name": "", "symbol": "Q", "color": "red", "consumable": true}]"#
+        )
+        .is_err());
+
+        // Old saves name the built-in types, other catalogs' types read as unknown
+        let minerals: ResourceType = serde_json::from_str(r#""Minerals""#).unwrap();
+        assert_eq!(minerals, ResourceType::MINERALS);
+        assert_eq!(ResourceCatalog::default().value(gas), 0);
     }
 }
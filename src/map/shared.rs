@@ -0,0 +1,166 @@
+use log::error;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
+
+use crate::communication::channels::ResourceType;
+use crate::map::noise::Map;
+use crate::profiler::{self, Section};
+use crate::robot::core::knowledge::RobotKnowledge;
+use crate::robot::core::movement::{next_position, Direction};
+
+/// The map shared by the app and every robot thread.
+///
+/// A thread panicking while it holds the lock poisons it. Instead of failing every later
+/// access, which left robots stopped without a word, the map is taken over as the panicking
+/// thread left it, the poisoning is cleared and the recovery counted, so the app can report
+/// it.
+#[derive(Clone)]
+pub struct SharedMap {
+    map: Arc<RwLock<Map>>,
+    recoveries: Arc<AtomicU64>,
+    /// Recoveries already reported by `take_recoveries`
+    reported: Arc<AtomicU64>,
+}
+
+impl SharedMap {
+    pub fn new(map: Map) -> Self {
+        Self {
+            map: Arc::new(RwLock::new(map)),
+            recoveries: Arc::default(),
+            reported: Arc::default(),
+        }
+    }
+
+    /// Reads the map, recovering it if a thread panicked while holding the lock
+    pub fn read(&self) -> RwLockReadGuard<'_, Map> {
+        profiler::time(Section::MapReadWait, || {
+            self.map
+                .read()
+                .unwrap_or_else(|poisoned| self.recover(poisoned))
+        })
+    }
+
+    /// Writes the map, recovering it if a thread panicked while holding the lock
+    pub fn write(&self) -> RwLockWriteGuard<'_, Map> {
+        profiler::time(Section::MapWriteWait, || {
+            self.map
+                .write()
+                .unwrap_or_else(|poisoned| self.recover(poisoned))
+        })
+    }
+
+    /// Runs `f` on the map under a read lock held only for the call
+    pub fn with_read<R>(&self, f: impl FnOnce(&Map) -> R) -> R {
+        f(&self.read())
+    }
+
+    /// Runs `f` on the map under a write lock held only for the call
+    pub fn with_write<R>(&self, f: impl FnOnce(&mut Map) -> R) -> R {
+        f(&mut self.write())
+    }
+
+    /// Records in `knowledge` what lies on `(x, y)` and on every tile next to it
+    pub fn observe_area(&self, knowledge: &mut RobotKnowledge, (x, y): (usize, usize)) {
+        let map = self.read();
+        knowledge.observe_and_update(x, y, &map);
+        for direction in Direction::all() {
+            let (nx, ny) = next_position(x, y, &direction, &map);
+            if (nx, ny) != (x, y) {
+                knowledge.observe_and_update(nx, ny, &map);
+            }
+        }
+    }
+
+    /// Takes up to `max_amount` of the `wanted` resource on `(x, y)`, checking the type and
+    /// taking under a single write lock so no other robot can take it in between. Returns
+    /// the amount taken.
+    pub fn try_collect_at(
+        &self,
+        (x, y): (usize, usize),
+        wanted: &ResourceType,
+        max_amount: u32,
+    ) -> Option<u32> {
+        let mut map = self.write();
+        match map.get_resource(x, y) {
+            Some((resource_type, _)) if resource_type == *wanted => map
+                .try_take_resource(x, y, max_amount)
+                .map(|(_, amount)| amount),
+            _ => None,
+        }
+    }
+
+    fn recover<G>(&self, poisoned: PoisonError<G>) -> G {
+        // Only one of the threads that saw the poisoning counts it
+        if self.map.is_poisoned() {
+            self.map.clear_poison();
+            self.recoveries.fetch_add(1, Ordering::Relaxed);
+            error!("Map lock poisoned by a panicking thread; carrying on with the map as left");
+        }
+        poisoned.into_inner()
+    }
+
+    /// Times the lock was recovered since the last call
+    pub fn take_recoveries(&self) -> u64 {
+        let total = self.recoveries.load(Ordering::Relaxed);
+        total - self.reported.swap(total, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::core::knowledge::TileInfo;
+    use std::thread;
+
+    #[test]
+    fn test_map_outlives_a_thread_panicking_with_the_lock() {
+        let map = SharedMap::new(Map::new(8, 6, 1));
+        let width = map.read().width;
+
+        let panicking = map.clone();
+        let result = thread::spawn(move || {
+            let _guard = panicking.write();
+            panic!("robot thread crashed");
+        })
+        .join();
+        assert!(result.is_err());
+
+        assert_eq!(map.read().width, width);
+        assert_eq!(map.take_recoveries(), 1);
+        // Once cleared, the lock works as before
+        drop(map.write());
+        assert_eq!(map.take_recoveries(), 0);
+    }
+
+    #[test]
+    fn test_deposits_are_taken_only_when_wanted_and_up_to_the_limit() {
+        let map = SharedMap::new(Map::new(8, 6, 1));
+        let tile = map.with_read(|map| {
+            (0..map.height)
+                .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+                .find(|&(x, y)| !map.is_obstacle(x, y))
+                .unwrap()
+        });
+        map.with_write(|map| map.add_resource(tile.0, tile.1, ResourceType::MINERALS, 7));
+
+        let mut knowledge = RobotKnowledge::new(8, 6);
+        map.observe_area(&mut knowledge, tile);
+        assert_eq!(
+            knowledge.get_tile(tile.0, tile.1),
+            &TileInfo::Resource(ResourceType::MINERALS, 7)
+        );
+
+        assert_eq!(map.try_collect_at(tile, &ResourceType::ENERGY, 10), None);
+        assert_eq!(
+            map.try_collect_at(tile, &ResourceType::MINERALS, 5),
+            Some(5)
+        );
+        assert_eq!(
+            map.try_collect_at(tile, &ResourceType::MINERALS, 5),
+            Some(2)
+        );
+        assert_eq!(map.with_read(|map| map.get_resource(tile.0, tile.1)), None);
+    }
+}
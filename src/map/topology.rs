@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+/// A shaft tile and the tile on the next layer that climbing it leads to
+pub type Shaft = ((usize, usize), (usize, usize));
+
+/// Options a map is created with, besides its size and seeds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapShape {
+    /// Underground layers below the surface
+    pub cave_layers: usize,
+    /// Edges wrap around, each layer being a torus
+    pub wraps: bool,
+    /// Hexagonal tiles with six neighbors instead of square ones with four
+    pub hex: bool,
+}
+
+impl MapShape {
+    /// Checks the shape suits layers of `layer_height` rows. Hex rows lean left and right in
+    /// turn, so a wrapping hex layer needs an even number of them to join up.
+    pub fn validate(&self, layer_height: usize) -> Result<(), String> {
+        if self.hex && self.wraps && layer_height % 2 == 1 {
+            return Err(format!(
+                "A wrapping hex map needs an even height, not {}",
+                layer_height
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Shape of the map: flat, or wrapping around at the edges like a planet's surface, where
+/// leaving a layer on one side enters it on the opposite side. Hex maps use offset rows: odd
+/// rows sit half a tile to the right of even ones. Robots change layers through the shafts.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Topology {
+    pub width: usize,
+    /// Rows of all layers, seams included
+    pub height: usize,
+    /// Rows of a single layer
+    pub layer_height: usize,
+    pub wraps: bool,
+    pub hex: bool,
+    /// Every shaft, both ways
+    pub shafts: Arc<[Shaft]>,
+}
+
+impl Topology {
+    /// Tile one step of (dx, dy) away from (x, y), `None` past the edge of a flat map
+    pub fn step(&self, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        if self.wraps && self.width > 0 && self.layer_height > 0 {
+            let top = (y - y % (self.layer_height + 1)) as isize;
+            return Some((
+                nx.rem_euclid(self.width as isize) as usize,
+                (top + (ny - top).rem_euclid(self.layer_height as isize)) as usize,
+            ));
+        }
+        let on_map =
+            (0..self.width as isize).contains(&nx) && (0..self.height as isize).contains(&ny);
+        on_map.then_some((nx as usize, ny as usize))
+    }
+
+    /// Column shift of the hex neighbors above and below row `y` that are not straight up or
+    /// down: to the left on even rows, to the right on odd ones
+    pub fn diagonal_shift(&self, y: usize) -> isize {
+        if y % 2 == 1 {
+            1
+        } else {
+            -1
+        }
+    }
+
+    /// Tiles one move away from (x, y): four on a square grid, six on a hex grid
+    pub fn neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let shift = self.diagonal_shift(y);
+        let moves = if self.hex { 6 } else { 4 };
+        [(-1, 0), (1, 0), (0, -1), (0, 1), (shift, -1), (shift, 1)]
+            .map(|(dx, dy)| self.step(x, y, dx, dy))
+            .into_iter()
+            .take(moves)
+            .flatten()
+    }
+
+    /// Layer of a row: 0 for the surface, then one per cave layer
+    pub fn layer_of(&self, y: usize) -> usize {
+        y / (self.layer_height + 1)
+    }
+
+    /// Moves between two tiles, the short way round when the map wraps. Tiles on different
+    /// layers are reached through the shafts, a move to climb each; `usize::MAX` when no
+    /// shafts lead there.
+    pub fn distance(&self, a: (usize, usize), b: (usize, usize)) -> usize {
+        let (from, to) = (self.layer_of(a.1), self.layer_of(b.1));
+        // Fewest moves to each shaft exit on the layers climbed so far
+        let mut reached = vec![(a, 0_usize)];
+        let mut layer = from;
+        while layer != to {
+            let toward = if to > layer { layer + 1 } else { layer - 1 };
+            reached = self
+                .shafts
+                .iter()
+                .filter(|(shaft, exit)| {
+                    self.layer_of(shaft.1) == layer && self.layer_of(exit.1) == toward
+                })
+                .filter_map(|&(shaft, exit)| {
+                    let moves = reached
+                        .iter()
+                        .map(|&(tile, moves)| {
+                            moves.saturating_add(self.layer_distance(tile, shaft))
+                        })
+                        .min()?;
+                    Some((exit, moves.saturating_add(1)))
+                })
+                .collect();
+            layer = toward;
+        }
+        reached
+            .iter()
+            .map(|&(tile, moves)| moves.saturating_add(self.layer_distance(tile, b)))
+            .min()
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Moves between two tiles of the same layer
+    fn layer_distance(&self, a: (usize, usize), b: (usize, usize)) -> usize {
+        if self.hex {
+            return self.hex_distance(a, b);
+        }
+        self.axis_distance(a.0, b.0, self.width) + self.axis_distance(a.1, b.1, self.layer_height)
+    }
+
+    /// Way to go along an axis of `len` tiles to get from `from` to `to` soonest:
+    /// `Greater` towards higher coordinates
+    pub fn heading(&self, from: usize, to: usize, len: usize) -> Ordering {
+        let forward = to.cmp(&from);
+        if self.wraps && from.abs_diff(to) * 2 > len {
+            forward.reverse()
+        } else {
+            forward
+        }
+    }
+
+    fn axis_distance(&self, a: usize, b: usize, len: usize) -> usize {
+        let distance = a.abs_diff(b);
+        if self.wraps && distance < len {
+            distance.min(len - distance)
+        } else {
+            distance
+        }
+    }
+
+    /// Hex moves between offset coordinates of a layer, through cube coordinates. On a
+    /// wrapping map the copies of `b` across the edges are tried too.
+    fn hex_distance(&self, a: (usize, usize), b: (usize, usize)) -> usize {
+        let cube = |(x, y): (isize, isize)| (x - (y - (y & 1)) / 2, y);
+        let (aq, ar) = cube((a.0 as isize, a.1 as isize));
+        let (width, height) = (self.width as isize, self.layer_height as isize);
+        let shifts: &[isize] = if self.wraps { &[-1, 0, 1] } else { &[0] };
+        let mut best = usize::MAX;
+        for &sx in shifts {
+            for &sy in shifts {
+                let (bq, br) = cube((b.0 as isize + sx * width, b.1 as isize + sy * height));
+                let (dq, dr) = (aq - bq, ar - br);
+                best = best.min(((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as usize);
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrapping_topology_crosses_the_edges_of_each_layer() {
+        let flat = Topology {
+            width: 10,
+            height: 13,
+            layer_height: 6,
+            wraps: false,
+            hex: false,
+            ..Topology::default()
+        };
+        let torus = Topology {
+            wraps: true,
+            ..flat.clone()
+        };
+
+        assert_eq!(flat.step(0, 2, -1, 0), None);
+        assert_eq!(torus.step(0, 2, -1, 0), Some((9, 2)));
+        // Up from the top of the cave layer lands on its bottom row, not in the seam
+        assert_eq!(torus.step(4, 7, 0, -1), Some((4, 12)));
+
+        assert_eq!(flat.distance((1, 1), (9, 1)), 8);
+        assert_eq!(torus.distance((1, 1), (9, 1)), 2);
+        assert_eq!(torus.heading(1, 9, 10), Ordering::Less);
+        assert_eq!(torus.heading(1, 4, 10), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_hex_tiles_have_six_neighbors_one_move_away() {
+        let hex = Topology {
+            width: 10,
+            height: 10,
+            layer_height: 10,
+            wraps: false,
+            hex: true,
+            ..Topology::default()
+        };
+        for (x, y) in [(4, 4), (4, 5)] {
+            let neighbors: Vec<_> = hex.neighbors(x, y).collect();
+            assert_eq!(neighbors.len(), 6);
+            assert!(neighbors
+                .iter()
+                .all(|&tile| hex.distance((x, y), tile) == 1));
+        }
+        // Even rows lean left, odd rows right
+        assert!(hex.neighbors(4, 4).any(|tile| tile == (3, 3)));
+        assert!(hex.neighbors(4, 5).any(|tile| tile == (5, 4)));
+
+        // Two rows down and one column over is two moves, not three
+        assert_eq!(hex.distance((4, 4), (5, 6)), 2);
+    }
+
+    #[test]
+    fn test_other_layers_are_reached_through_the_shafts() {
+        let layered = Topology {
+            width: 10,
+            height: 11,
+            layer_height: 5,
+            wraps: false,
+            hex: false,
+            ..Topology::default()
+        };
+        // No shaft, no way down
+        assert_eq!(layered.distance((1, 2), (1, 8)), usize::MAX);
+
+        let layered = Topology {
+            shafts: vec![((8, 2), (8, 8)), ((8, 8), (8, 2)), ((2, 4), (2, 10))].into(),
+            ..layered
+        };
+        // Over to the nearest shaft leading the right way, a climb, and back
+        assert_eq!(layered.distance((1, 2), (1, 8)), 3 + 1 + 3);
+        // The shaft at (2, 4) only leads down, up is by the one at (8, 8)
+        assert_eq!(layered.distance((1, 8), (1, 2)), 7 + 1 + 7);
+        assert_eq!(layered.distance((2, 4), (2, 10)), 1);
+        assert_eq!(layered.distance((2, 10), (2, 4)), 8 + 1 + 8);
+    }
+
+    #[test]
+    fn test_wrapping_hex_neighbors_are_mutual() {
+        let shape = MapShape {
+            cave_layers: 1,
+            wraps: true,
+            hex: true,
+        };
+        assert!(shape.validate(5).is_err());
+        assert_eq!(shape.validate(6), Ok(()));
+
+        // Two layers of six rows around a seam
+        let torus = Topology {
+            width: 7,
+            height: 13,
+            layer_height: 6,
+            wraps: true,
+            hex: true,
+            ..Topology::default()
+        };
+        for y in (0..13).filter(|&y| y != 6) {
+            for x in 0..7 {
+                for (nx, ny) in torus.neighbors(x, y) {
+                    assert!(
+                        torus.neighbors(nx, ny).any(|tile| tile == (x, y)),
+                        "({}, {}) is next to ({}, {}) but not the other way",
+                        nx,
+                        ny,
+                        x,
+                        y
+                    );
+                    assert_eq!(torus.distance((x, y), (nx, ny)), 1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Rough memory accounting: what the knowledge maps, the event queues and the stats buffers
+//! of a run take, worked out from their lengths rather than measured, to see a run outgrow its
+//! budget as robots and map size go up.
+
+use std::mem::size_of;
+
+/// Bytes of a hash table holding `len` entries of `K` and `V`: each pair, its control byte,
+/// and the spare room the table keeps to stay at most 7/8 full
+pub fn hash_map_bytes<K, V>(len: usize) -> usize {
+    len * (size_of::<(K, V)>() + 1) * 8 / 7
+}
+
+/// Bytes of a B-tree holding `len` entries of `T`, its nodes about two thirds full
+pub fn btree_bytes<T>(len: usize) -> usize {
+    len * size_of::<T>() * 3 / 2
+}
+
+/// Bytes of a vector or deque with room for `capacity` entries of `T`
+pub fn vec_bytes<T>(capacity: usize) -> usize {
+    capacity * size_of::<T>()
+}
+
+/// Approximate bytes held by a run, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The station's knowledge and the robots', the copies they report at the dock included
+    pub knowledge: usize,
+    /// Events, commands and merges sent and not processed yet
+    pub queues: usize,
+    /// Logs and figures kept over the run
+    pub stats: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.knowledge + self.queues + self.stats
+    }
+
+    /// How close the run is to `limit` bytes, warned of from `warn_percent` of it
+    pub fn level(&self, limit: usize, warn_percent: u32) -> MemoryLevel {
+        let total = self.total() as u128;
+        if total > limit as u128 {
+            MemoryLevel::Over
+        } else if total * 100 >= limit as u128 * u128::from(warn_percent) {
+            MemoryLevel::Near
+        } else {
+            MemoryLevel::Fine
+        }
+    }
+}
+
+/// Where a run's memory stands against its configured limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemoryLevel {
+    #[default]
+    Fine,
+    Near,
+    Over,
+}
+
+/// `bytes` in mebibytes, with one decimal
+pub fn format_mb(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_rise_as_the_limit_is_approached() {
+        let usage = |bytes| MemoryUsage {
+            knowledge: bytes,
+            queues: 0,
+            stats: 0,
+        };
+        assert_eq!(usage(50).level(100, 80), MemoryLevel::Fine);
+        assert_eq!(usage(80).level(100, 80), MemoryLevel::Near);
+        assert_eq!(usage(101).level(100, 80), MemoryLevel::Over);
+        assert!(hash_map_bytes::<u64, u64>(7) >= 7 * 16);
+        assert_eq!(format_mb(3 * 1024 * 1024 / 2), "1.5 MB");
+    }
+}
@@ -0,0 +1,89 @@
+//! When the simulation ticks. Past the configured rate, as long as a tick's work fits in it;
+//! otherwise the tick is stretched to what the work takes, so rendering and input keep up and
+//! the run visibly falls behind instead of robot events silently backing up in the channel.
+
+use std::time::{Duration, Instant};
+
+use crate::robot::utils::config;
+
+/// Weight of the last tick in the smoothed work time, as one in this many
+const SMOOTHING: u32 = 4;
+
+pub struct TickPacer {
+    rate: Duration,
+    interval: Duration,
+    /// Work per tick, smoothed over the last few
+    load: Duration,
+    last_tick: Instant,
+}
+
+impl TickPacer {
+    pub fn new(rate: Duration) -> Self {
+        Self {
+            rate,
+            interval: rate,
+            load: Duration::ZERO,
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// Whether the next tick is due
+    pub fn due(&self) -> bool {
+        self.last_tick.elapsed() >= self.interval
+    }
+
+    /// Time left before the next tick is due, if any
+    pub fn until_next(&self) -> Option<Duration> {
+        self.interval.checked_sub(self.last_tick.elapsed())
+    }
+
+    /// Starts the next tick's wait, after one whose update and frame took `work`
+    pub fn ticked(&mut self, work: Duration) {
+        self.last_tick = Instant::now();
+        self.load = (self.load * (SMOOTHING - 1) + work) / SMOOTHING;
+        let needed = self.load * config::TICK_HEADROOM_PERCENT / 100;
+        self.interval = needed.clamp(self.rate, self.rate * config::MAX_TICK_STRETCH);
+    }
+
+    /// Time between two ticks now
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The stretched interval while the run is behind its tick rate
+    pub fn behind(&self) -> Option<Duration> {
+        (self.interval > self.rate).then_some(self.interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticks_stretch_under_load_and_recover() {
+        let rate = Duration::from_millis(100);
+        let mut pacer = TickPacer::new(rate);
+        pacer.ticked(Duration::from_millis(40));
+        assert_eq!(pacer.behind(), None);
+
+        for _ in 0..20 {
+            pacer.ticked(Duration::from_millis(200));
+        }
+        let behind = pacer.behind().expect("stretched");
+        assert!(behind > Duration::from_millis(200), "{:?}", behind);
+        assert!(!pacer.due());
+
+        // Far more work than that is capped
+        for _ in 0..20 {
+            pacer.ticked(Duration::from_secs(10));
+        }
+        assert_eq!(pacer.interval(), rate * config::MAX_TICK_STRETCH);
+
+        for _ in 0..40 {
+            pacer.ticked(Duration::from_millis(10));
+        }
+        assert_eq!(pacer.behind(), None);
+        assert_eq!(pacer.interval(), rate);
+    }
+}
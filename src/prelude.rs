@@ -0,0 +1,20 @@
+//! What embedding a simulation takes, in one import: `use astro_swarm::prelude::*;`
+
+pub use crate::app::{
+    App, CompletionSummary, GameSetup, MissionOutcome, RobotCounts, SpawnPolicy, TICK_RATE,
+};
+pub use crate::communication::bus::EventSubscriber;
+pub use crate::communication::channels::{
+    EventEnvelope, EventSource, ResourceType, RobotCommand, RobotEvent,
+};
+pub use crate::difficulty::Difficulty;
+pub use crate::error::{SimError, SimInitError};
+pub use crate::map::topology::MapShape;
+pub use crate::robot::core::knowledge::{RobotKnowledge, TileInfo};
+pub use crate::robot::core::science_score::{ScienceCandidate, ScienceScorer, ScienceTargeting};
+pub use crate::robot::core::state::{RobotState, RobotStatus};
+pub use crate::robot::registry::RobotType;
+pub use crate::robot::utils::clock::SteppedClock;
+pub use crate::robot::utils::preset::SwarmPreset;
+pub use crate::settings::{ModuleCatalog, Settings};
+pub use crate::simulation::SimulationBuilder;
@@ -0,0 +1,251 @@
+//! Where the time goes: drawing frames, processing robot events, the station's periodic
+//! systems, waiting for the map lock, and each robot's decision steps. Off until enabled, so
+//! a normal run only pays for checking a flag.
+
+use log::info;
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Parts of a run timed as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Render,
+    Events,
+    Systems,
+    /// Waiting to read the map, by any thread
+    MapReadWait,
+    /// Waiting to write the map, by any thread
+    MapWriteWait,
+}
+
+impl Section {
+    pub const ALL: [Section; 5] = [
+        Section::Render,
+        Section::Events,
+        Section::Systems,
+        Section::MapReadWait,
+        Section::MapWriteWait,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Section::Render => "Render",
+            Section::Events => "Events",
+            Section::Systems => "Systems",
+            Section::MapReadWait => "Map read wait",
+            Section::MapWriteWait => "Map write wait",
+        }
+    }
+}
+
+/// Times of one section, added up from every thread
+struct Timing {
+    count: AtomicU64,
+    total_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+impl Timing {
+    const fn new() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            total_us: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+        }
+    }
+
+    fn add(&self, elapsed: Duration) {
+        let micros = elapsed.as_micros() as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_us.fetch_add(micros, Ordering::Relaxed);
+        self.max_us.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> TimingStats {
+        TimingStats {
+            count: self.count.load(Ordering::Relaxed),
+            total_us: self.total_us.load(Ordering::Relaxed),
+            max_us: self.max_us.load(Ordering::Relaxed),
+        }
+    }
+
+    fn clear(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.total_us.store(0, Ordering::Relaxed);
+        self.max_us.store(0, Ordering::Relaxed);
+    }
+}
+
+/// How often something was timed, for how long in all and at most, in microseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TimingStats {
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+}
+
+impl TimingStats {
+    pub fn mean_us(&self) -> u64 {
+        self.total_us.checked_div(self.count).unwrap_or(0)
+    }
+
+    fn add(&mut self, elapsed: Duration) {
+        let micros = elapsed.as_micros() as u64;
+        self.count += 1;
+        self.total_us += micros;
+        self.max_us = self.max_us.max(micros);
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SECTIONS: [Timing; Section::ALL.len()] = [const { Timing::new() }; Section::ALL.len()];
+static ROBOT_STEPS: Mutex<BTreeMap<u32, TimingStats>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    /// Time this thread spent waiting on purpose, left out of its robot steps
+    static IDLE: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// Starts measuring from scratch, unless already measuring
+pub fn enable() {
+    if !ENABLED.swap(true, Ordering::Relaxed) {
+        reset();
+        info!("Profiler enabled");
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Forgets every time measured so far
+pub fn reset() {
+    for timing in &SECTIONS {
+        timing.clear();
+    }
+    robot_steps().clear();
+}
+
+/// Now, if measuring, to hand to `record` once the section is over
+pub fn start() -> Option<Instant> {
+    is_enabled().then(Instant::now)
+}
+
+/// Adds the time since `started` to `section`
+pub fn record(section: Section, started: Option<Instant>) {
+    if let Some(started) = started {
+        SECTIONS[section as usize].add(started.elapsed());
+    }
+}
+
+/// Runs `f`, timing it as `section`
+pub fn time<R>(section: Section, f: impl FnOnce() -> R) -> R {
+    let started = start();
+    let result = f();
+    record(section, started);
+    result
+}
+
+/// Tells the profiler the current thread spent `duration` waiting on purpose, sleeping
+/// between moves or for the station's answer, which is no part of its decisions
+pub fn idle(duration: Duration) {
+    if is_enabled() {
+        IDLE.with(|idle| idle.set(idle.get() + duration));
+    }
+}
+
+/// Times one pass of a robot's loop, less the time it slept, until dropped
+pub struct RobotStep {
+    robot_id: u32,
+    started: Option<(Instant, Duration)>,
+}
+
+/// Starts timing a decision step of `robot_id`
+pub fn robot_step(robot_id: u32) -> RobotStep {
+    RobotStep {
+        robot_id,
+        started: start().map(|now| (now, IDLE.with(Cell::get))),
+    }
+}
+
+impl Drop for RobotStep {
+    fn drop(&mut self) {
+        let Some((started, idle_before)) = self.started else {
+            return;
+        };
+        let idle = IDLE.with(Cell::get).saturating_sub(idle_before);
+        let busy = started.elapsed().saturating_sub(idle);
+        robot_steps().entry(self.robot_id).or_default().add(busy);
+    }
+}
+
+fn robot_steps() -> std::sync::MutexGuard<'static, BTreeMap<u32, TimingStats>> {
+    ROBOT_STEPS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Everything measured since the profiler was enabled
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProfileReport {
+    pub sections: BTreeMap<&'static str, TimingStats>,
+    /// Decision steps by robot id
+    pub robot_steps: BTreeMap<u32, TimingStats>,
+}
+
+impl ProfileReport {
+    pub fn section(&self, section: Section) -> TimingStats {
+        self.sections
+            .get(section.label())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Writes the report to `path` as JSON, replacing the file
+    pub fn write_json(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        info!("Profile exported to {}", path.display());
+        Ok(())
+    }
+}
+
+pub fn report() -> ProfileReport {
+    ProfileReport {
+        sections: Section::ALL
+            .iter()
+            .map(|&section| (section.label(), SECTIONS[section as usize].stats()))
+            .collect(),
+        robot_steps: robot_steps().clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robot_steps_leave_out_the_time_slept() {
+        enable();
+        {
+            let _step = robot_step(u32::MAX);
+            std::thread::sleep(Duration::from_millis(50));
+            idle(Duration::from_millis(50));
+        }
+        time(Section::Render, || {
+            std::thread::sleep(Duration::from_millis(2))
+        });
+
+        let report = report();
+        let step = report.robot_steps[&u32::MAX];
+        assert_eq!(step.count, 1);
+        assert!(step.max_us < 40_000, "{:?}", step);
+        let render = report.section(Section::Render);
+        assert!(render.count >= 1 && render.max_us >= 2_000, "{:?}", render);
+    }
+}
@@ -1,28 +1,97 @@
 use log::{debug, error, info, warn};
-use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
-use std::sync::{Arc, RwLock};
-use std::thread;
+use std::ops::Range;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::Duration;
 
-use crate::communication::channels::{ResourceType, RobotEvent};
+use crate::communication::channels::{
+    CommandEnvelope, EventSender, ResourceType, RobotCommand, RobotEvent, ShutdownReason,
+};
+use crate::logging;
 use crate::map::noise::Map;
-use crate::robot::core::knowledge::{self, RobotKnowledge, TileInfo};
+use crate::map::resources::ResourceCatalog;
+use crate::map::shared::SharedMap;
+use crate::map::topology::Topology;
+use crate::profiler;
+use crate::robot::core::knowledge::{self, RobotKnowledge};
 use crate::robot::core::movement;
 use crate::robot::core::movement::Direction;
 use crate::robot::core::state::RobotStatus;
+use crate::robot::core::trail::Trail;
+use crate::robot::core::waypoint::{Waypoint, WaypointStep};
+use crate::robot::launcher::RobotRuntime;
+use crate::robot::registry::RobotType;
+use crate::robot::utils::clock::{self, SharedClock};
 use crate::robot::utils::common;
 use crate::robot::utils::config;
+use crate::robot::utils::inbox::InboxGauge;
+use crate::robot::utils::preset::RobotTuning;
+use crate::robot::utils::rng::RobotRng;
+use crate::robot::utils::trace::DecisionTrace;
 use crate::robot::RobotState;
 
 const RANDOM_MOVE_ATTEMPTS: usize = 4;
 
+/// Where the collector heads next
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TargetPlan {
+    /// An affordable deposit, or the nearest unknown tile when no deposit is known
+    MoveTo((usize, usize)),
+    /// Known deposits exist but none can be reached and brought back on the remaining energy
+    ReturnEarly,
+    /// No deposit and no unknown tile nearby: the nearest frontier tile, to help exploring
+    AssistExploration((usize, usize)),
+    /// Nothing to head for, wander instead
+    Explore,
+}
+
+impl TargetPlan {
+    /// Name of the plan in decision traces
+    fn action(self) -> &'static str {
+        match self {
+            TargetPlan::MoveTo(_) => "move_to_target",
+            TargetPlan::ReturnEarly => "return_early",
+            TargetPlan::AssistExploration(_) => "assist_exploration",
+            TargetPlan::Explore => "explore",
+        }
+    }
+}
+
 pub struct CollectionRobot {
     state: RobotState,
+    /// Logs under `robot::<id>`, so one robot's messages can be picked out
+    log_target: String,
     target_resource_type: Option<ResourceType>,
     knowledge: RobotKnowledge,
     merge_complete_receiver: Receiver<RobotEvent>,
+    command_receiver: Receiver<CommandEnvelope>,
     current_target_coords: Option<(usize, usize)>,
-    config: config::RobotTypeConfig,
+    /// Helping exploration until a deposit or an unknown tile turns up nearby
+    assisting_exploration: bool,
+    waypoint: Option<Waypoint>,
+    /// Joint site the station sent the robot to extract a sample on
+    sample_site: Option<(usize, usize)>,
+    /// Way walked since the last dock, retraced to get back
+    trail: Trail,
+    /// Rows of the surface, the only layer the robot works on without a drill
+    surface_rows: Range<usize>,
+    /// Lets the robot descend to the caves, whose rich veins it then goes for first
+    has_drill: bool,
+    /// Measures the distances to deposits, wrapping around the edges if the map does
+    topology: Topology,
+    /// Stops the thread after the next visit to the station
+    retiring: bool,
+    /// The station dropped its end of the command channel, the thread stops right away
+    station_lost: bool,
+    /// Own thresholds, sleeps and costs, as the swarm preset plays them
+    tuning: RobotTuning,
+    clock: SharedClock,
+    inbox: InboxGauge,
+    trace: DecisionTrace,
+    rng: RobotRng,
+    /// Resource types of the run, by which cargo is weighed and deposits told apart
+    resources: Arc<ResourceCatalog>,
 }
 
 impl CollectionRobot {
@@ -31,119 +100,287 @@ impl CollectionRobot {
         map_width: usize,
         map_height: usize,
         merge_complete_receiver: Receiver<RobotEvent>,
+        command_receiver: Receiver<CommandEnvelope>,
+        runtime: RobotRuntime,
     ) -> Self {
         Self {
-            knowledge: RobotKnowledge::new(map_width, map_height),
+            knowledge: runtime.knowledge(map_width, map_height),
+            log_target: logging::robot_target(initial_state.id),
             state: initial_state,
-            target_resource_type: Some(ResourceType::Minerals),
+            target_resource_type: Some(ResourceType::MINERALS),
             merge_complete_receiver,
+            command_receiver,
             current_target_coords: None,
-            config: config::COLLECTION_CONFIG.clone(),
+            assisting_exploration: false,
+            waypoint: None,
+            sample_site: None,
+            trail: Trail::default(),
+            surface_rows: 0..map_height,
+            has_drill: false,
+            topology: Topology {
+                width: map_width,
+                height: map_height,
+                layer_height: map_height,
+                wraps: false,
+                hex: false,
+                ..Topology::default()
+            },
+            retiring: false,
+            station_lost: false,
+            tuning: runtime.tuning(RobotType::Collection, &config::COLLECTION_CONFIG),
+            clock: runtime.clock,
+            inbox: runtime.gauge,
+            trace: runtime.trace,
+            rng: runtime.rng,
+            resources: runtime.resources,
         }
     }
 
-    pub fn set_target_resource(&mut self, resource_type: ResourceType) {
-        if matches!(resource_type, ResourceType::Energy | ResourceType::Minerals) {
+    /// Switches the harvested resource, returns false for types collectors cannot gather
+    pub fn set_target_resource(&mut self, resource_type: ResourceType) -> bool {
+        if self.resources.is_consumable(resource_type) {
             info!(
-                "Robot {}: Setting target resource type to {:?}",
-                self.state.id, resource_type
+                target: &self.log_target,
+                "Robot {}: Setting target resource type to {}",
+                self.state.id, self.resources.name(resource_type)
             );
             self.target_resource_type = Some(resource_type);
+            true
         } else {
             warn!(
+                target: &self.log_target,
                 "Robot {}: Attempted to set invalid target resource type: {:?}",
                 self.state.id, resource_type
             );
+            false
         }
     }
 
-    fn find_nearest_target_resource(&self) -> Option<(usize, usize)> {
-        let target_type = self.target_resource_type.as_ref()?;
+    /// Rows of the surface, when the map has caves below it
+    pub fn set_surface_rows(&mut self, rows: Range<usize>) {
+        self.surface_rows = rows;
+    }
+
+    /// Shape of the map the robot works on, when it is not flat and single-layered
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    fn distance(&self, a: (usize, usize), b: (usize, usize)) -> u32 {
+        u32::try_from(self.topology.distance(a, b)).unwrap_or(u32::MAX)
+    }
+
+    /// Mounts a drill, each move costing `energy_cost` more
+    pub fn fit_drill(&mut self, energy_cost: u32) {
+        info!(target: &self.log_target, "Robot {}: Fitted with a drill", self.state.id);
+        self.has_drill = true;
+        self.tuning.add_movement_cost(energy_cost);
+    }
+
+    /// Whether the robot may go to the tile: anywhere with a drill, on the surface otherwise
+    fn can_reach(&self, (_, y): (usize, usize)) -> bool {
+        self.has_drill || self.surface_rows.contains(&y)
+    }
+
+    /// Reachable, outside the no-go zones and not left to another robot
+    fn can_target(&self, (x, y): (usize, usize)) -> bool {
+        self.can_reach((x, y))
+            && !self.knowledge.is_excluded(x, y)
+            && !self.knowledge.is_claimed(x, y)
+    }
 
-        let known_resource = self
+    /// Energy needed to reach `target`, collect there and carry the load back to the station.
+    /// The way there goes over the tiles known to be passable, the way back down the
+    /// station's field; either is measured on the map's grid when no way is known.
+    fn round_trip_energy_cost(&self, target: (usize, usize)) -> u32 {
+        let position = (self.state.x, self.state.y);
+        // Ways longer than the robot's charge lasts are only followed that far
+        let limit = self.state.energy / self.tuning.config().movement_energy_cost.max(1);
+        let there = self
             .knowledge
-            .map
-            .iter()
-            .filter_map(|(&(x, y), tile_info)| {
-                if let TileInfo::Resource(res_type, amount) = tile_info {
-                    if res_type == target_type && *amount > 0 {
-                        Some((
-                            (x, y),
-                            (x as isize - self.state.x as isize).pow(2)
-                                + (y as isize - self.state.y as isize).pow(2),
-                        ))
-                    } else {
-                        None
+            .known_path_length(position, target, &self.topology, limit as usize)
+            .map_or_else(
+                || self.distance(position, target),
+                |moves| u32::try_from(moves).unwrap_or(u32::MAX),
+            );
+        let back = self
+            .knowledge
+            .station_field()
+            .and_then(|field| field.distance(target))
+            .unwrap_or_else(|| self.distance(target, self.knowledge.get_station_coords()));
+        self.energy_for_moves(there.saturating_add(back))
+    }
+
+    /// Like `round_trip_energy_cost`, on the map's grid alone, which no way is shorter than
+    fn grid_round_trip_energy_cost(&self, target: (usize, usize)) -> u32 {
+        let position = (self.state.x, self.state.y);
+        let station = self.knowledge.get_station_coords();
+        self.energy_for_moves(
+            self.distance(position, target)
+                .saturating_add(self.distance(target, station)),
+        )
+    }
+
+    /// Energy for `moves` moves and collecting at the end of them
+    fn energy_for_moves(&self, moves: u32) -> u32 {
+        moves
+            .saturating_mul(self.tuning.config().movement_energy_cost)
+            .saturating_add(self.tuning.config().action_energy_cost.unwrap_or(0))
+    }
+
+    /// Whether the trip to `target` leaves the robot above its low energy threshold. The grid
+    /// cost rules most deposits out before searching the known tiles for a way.
+    fn can_afford(&self, target: (usize, usize)) -> bool {
+        let affordable = |cost: u32| {
+            self.state.energy >= cost.saturating_add(self.tuning.config().low_energy_threshold)
+        };
+        affordable(self.grid_round_trip_energy_cost(target))
+            && affordable(self.round_trip_energy_cost(target))
+    }
+
+    /// Picks the nearest known deposit the robot can afford a round trip to. Out-of-reach
+    /// deposits are skipped; if none is affordable the robot returns early to recharge, unless
+    /// it is already full, in which case it heads for unknown tiles instead: a nearby one, or
+    /// past `COLLECTOR_SEARCH_RADIUS` the frontier, as explorers sent there by the station do.
+    fn find_nearest_target_resource(&self) -> TargetPlan {
+        let position = (self.state.x, self.state.y);
+        let Some(target_type) = self.target_resource_type.as_ref() else {
+            return self.find_unknown_tile(position);
+        };
+
+        // Drilling robots leave the surface deposits to the others, looking there last
+        let passes: &[Option<bool>] = if self.has_drill {
+            &[Some(false), Some(true)]
+        } else {
+            &[None]
+        };
+        let mut nearest_deposit = None;
+        if let Some(deposits) = self.knowledge.index().deposits(target_type) {
+            for &surface in passes {
+                for (coords, _) in deposits.nearest_first(position, &self.topology) {
+                    if !self.can_target(coords)
+                        || surface
+                            .is_some_and(|surface| self.surface_rows.contains(&coords.1) != surface)
+                    {
+                        continue;
+                    }
+                    nearest_deposit.get_or_insert(coords);
+                    if self.can_afford(coords) {
+                        debug!(
+                            target: &self.log_target,
+                            "Robot: {} Found known target resource at {:?}",
+                            self.state.id, coords
+                        );
+                        return TargetPlan::MoveTo(coords);
                     }
-                } else {
-                    None
                 }
-            })
-            .min_by_key(|&(_, dist)| dist)
-            .map(|(coords, _)| coords);
+            }
+        }
 
-        if let Some(coords) = known_resource {
+        if let Some(nearest) = nearest_deposit {
+            if self.state.energy < self.state.max_energy {
+                info!(
+                    target: &self.log_target,
+                    "Robot: {} Deposit at {:?} needs {} energy round trip, {} left. Returning early.",
+                    self.state.id, nearest, self.round_trip_energy_cost(nearest), self.state.energy
+                );
+                return TargetPlan::ReturnEarly;
+            }
             debug!(
-                "Robot: {} Found known target resource at {:?}",
-                self.state.id, coords
+                target: &self.log_target,
+                "Robot: {} No deposit within reach even on a full charge.",
+                self.state.id
             );
-            return Some(coords);
         }
 
-        let unknown_tile = self
-            .knowledge
-            .map
-            .iter()
-            .filter_map(|(&(x, y), tile_info)| {
-                if matches!(tile_info, TileInfo::Unknown) {
-                    Some((
-                        (x, y),
-                        (x as isize - self.state.x as isize).pow(2)
-                            + (y as isize - self.state.y as isize).pow(2),
-                    ))
-                } else {
-                    None
-                }
-            })
-            .min_by_key(|&(_, dist)| dist)
-            .map(|(coords, _)| coords);
+        self.find_unknown_tile(position)
+    }
 
-        if let Some(coords) = unknown_tile {
+    /// Where to look for deposits when none is known or affordable
+    fn find_unknown_tile(&self, position: (usize, usize)) -> TargetPlan {
+        let accept = |coords| self.can_target(coords);
+        let unknown_tile =
+            self.knowledge
+                .index()
+                .unknown()
+                .nearest(position, &self.topology, accept);
+
+        if let Some((coords, distance)) = unknown_tile {
+            // Out of the way unknown tiles are looked for from the known ground instead
+            if distance > config::COLLECTOR_SEARCH_RADIUS {
+                let frontier = self
+                    .knowledge
+                    .nearest_frontier(position, &self.topology, accept);
+                if let Some((frontier, _)) = frontier {
+                    debug!(
+                        target: &self.log_target,
+                        "Robot: {} Nothing to collect nearby, exploring from the frontier at {:?}",
+                        self.state.id,
+                        frontier
+                    );
+                    return TargetPlan::AssistExploration(frontier);
+                }
+            }
             debug!(
-                "Robot: {} No known target resource, found unknown tile at {:?}",
+                target: &self.log_target,
+                "Robot: {} No affordable target resource, found unknown tile at {:?}",
                 self.state.id, coords
             );
-            Some(coords)
+            TargetPlan::MoveTo(coords)
         } else {
             debug!(
+                target: &self.log_target,
                 "Robot: {} No known target resource or unknown tiles found.",
                 self.state.id
             );
-            None
+            TargetPlan::Explore
         }
     }
 
-    pub fn start(mut self, sender: Sender<RobotEvent>, map: Arc<RwLock<Map>>) {
+    pub fn start(mut self, sender: EventSender, map: SharedMap) -> JoinHandle<()> {
         let robot_id = self.state.id;
         let station_coords = self.knowledge.get_station_coords();
-        let config = self.config.clone();
+        let config = self.tuning.config().clone();
         let collection_action_cost = config
             .action_energy_cost
             .expect("Collection config must have action cost");
 
-        thread::spawn(move || {
+        let clock = self.clock.clone();
+        clock::spawn_robot(&clock, robot_id, move || {
             debug!(
+                target: &self.log_target,
                 "Robot: {} Carrying {}/{} units",
                 self.state.id,
-                self.state.collected_resources.values().sum::<u32>(),
+                self.state.cargo_load(&self.resources),
                 self.state.max_capacity
             );
             info!(
+                target: &self.log_target,
                 "Robot {}: Starting collection thread with capacity {}",
                 robot_id, self.state.max_capacity
             );
 
             loop {
+                let _step = profiler::robot_step(robot_id);
+                self.process_commands(&sender);
+                common::suffer_hostile_contact(&mut self.state, &map);
+                common::soak_up_flare(&mut self.state, &sender);
+                self.knowledge.expire_sightings(sender.clock().now());
+                self.knowledge
+                    .forget_stale_terrain(sender.clock().now(), config::terrain_memory());
+                if self.retiring && self.state.status == RobotStatus::AtStation {
+                    info!(target: &self.log_target, "Robot {}: Retired at station.", robot_id);
+                    break;
+                }
+                if self.station_lost {
+                    info!(
+                        target: &self.log_target,
+                        "Robot {}: Station closed, stopping.",
+                        robot_id
+                    );
+                    break;
+                }
+
                 match self.state.status {
                     RobotStatus::Collecting => {
                         self.handle_collecting(&sender, &map, collection_action_cost, &config);
@@ -156,28 +393,193 @@ impl CollectionRobot {
                     }
                     _ => {
                         error!(
+                            target: &self.log_target,
                             "Robot: {} Unhandled state {:?}.",
                             robot_id, self.state.status
                         );
                         self.state.status = RobotStatus::Collecting;
-                        thread::sleep(config::UNHANDLED_STATE_SLEEP);
+                        self.clock.sleep(config::UNHANDLED_STATE_SLEEP);
                     }
                 }
             }
-        });
+
+            let reason = if self.retiring {
+                ShutdownReason::Retired
+            } else if self.station_lost {
+                ShutdownReason::StationClosed
+            } else {
+                ShutdownReason::LoopExited
+            };
+            if let Err(e) = sender.send(RobotEvent::Shutdown {
+                id: robot_id,
+                reason,
+            }) {
+                error!(target: &self.log_target, "Robot: {} Failed send Shutdown: {}", robot_id, e);
+            }
+        })
+    }
+
+    fn process_commands(&mut self, sender: &EventSender) {
+        while let Some(CommandEnvelope {
+            id: command_id,
+            command,
+        }) = common::next_command(&self.command_receiver, &self.inbox, &mut self.station_lost)
+        {
+            let outcome = self.carry_out(sender, command);
+            common::acknowledge(sender, self.state.id, command_id, outcome);
+        }
+    }
+
+    /// Carries out an order of the station, or says why it cannot
+    fn carry_out(&mut self, sender: &EventSender, command: RobotCommand) -> Result<(), String> {
+        let mut outcome = Ok(());
+        match command {
+            RobotCommand::ReturnToStation => {
+                self.waypoint = None;
+                if self.state.status == RobotStatus::Collecting {
+                    info!(
+                        target: &self.log_target,
+                        "Robot: {} Recalled to station.",
+                        self.state.id
+                    );
+                    self.state.status = RobotStatus::ReturningToStation;
+                    self.current_target_coords = None;
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            RobotCommand::SetTargetResource(resource_type) => {
+                if self.set_target_resource(resource_type) {
+                    self.current_target_coords = None;
+                } else {
+                    outcome = Err(format!("Cannot collect {:?}", resource_type));
+                }
+            }
+            RobotCommand::MoveTo { x, y } if self.knowledge.is_excluded(x, y) => {
+                outcome = Err(format!("({}, {}) lies in a no-go zone", x, y));
+            }
+            RobotCommand::MoveTo { x, y } if !self.can_reach((x, y)) => {
+                outcome = Err(format!("No drill to reach ({}, {})", x, y));
+            }
+            RobotCommand::MoveTo { x, y } => {
+                debug!(
+                    target: &self.log_target,
+                    "Robot: {} New waypoint ({}, {}).",
+                    self.state.id, x, y
+                );
+                self.waypoint = Some(Waypoint::new(x, y));
+                self.current_target_coords = None;
+            }
+            RobotCommand::ExtractSample { x, y }
+                if self.state.status != RobotStatus::Collecting =>
+            {
+                outcome = Err(format!("Not out collecting, cannot sample ({}, {})", x, y));
+            }
+            RobotCommand::ExtractSample { x, y } if self.knowledge.is_excluded(x, y) => {
+                outcome = Err(format!("({}, {}) lies in a no-go zone", x, y));
+            }
+            RobotCommand::ExtractSample { x, y } if !self.can_reach((x, y)) => {
+                outcome = Err(format!("No drill to reach ({}, {})", x, y));
+            }
+            RobotCommand::ExtractSample { x, y } => {
+                info!(
+                    target: &self.log_target,
+                    "Robot: {} Heading to sample ({}, {}).",
+                    self.state.id, x, y
+                );
+                self.sample_site = Some((x, y));
+                self.waypoint = Some(Waypoint::new(x, y));
+                self.current_target_coords = None;
+            }
+            RobotCommand::AvoidTiles(tiles) => self.knowledge.set_exclusions(tiles),
+            RobotCommand::Wear { percent } => self.state.wear_percent = percent,
+            RobotCommand::SetPreset(preset) => {
+                info!(
+                    target: &self.log_target,
+                    "Robot {}: Switching to the {} preset.",
+                    self.state.id, preset.label()
+                );
+                self.tuning.set_preset(preset);
+            }
+            RobotCommand::Service { ticks } => {
+                common::undergo_service(&mut self.state, sender, &*self.clock, ticks)
+            }
+            RobotCommand::Charge { ticks } => {
+                common::charge(&mut self.state, sender, &*self.clock, ticks);
+                if self.state.status == RobotStatus::AtStation {
+                    self.state.status = self.status_after_dock();
+                }
+            }
+            RobotCommand::RefitBattery => {
+                info!(target: &self.log_target, "Robot {}: Battery refitted.", self.state.id);
+                self.state.refit_battery();
+            }
+            RobotCommand::MeteorImpact {
+                x,
+                y,
+                radius,
+                damage,
+            } => common::suffer_meteor_impact(
+                &mut self.state,
+                &mut self.knowledge,
+                (x, y),
+                radius,
+                damage,
+            ),
+            RobotCommand::SolarFlare {
+                scramble_percent,
+                seed,
+            } => {
+                common::suffer_solar_flare(&self.state, &mut self.knowledge, scramble_percent, seed)
+            }
+            RobotCommand::Teleport { x, y } => common::teleport(&mut self.state, sender, (x, y)),
+            RobotCommand::SetEnergy(energy) => common::set_energy(&mut self.state, sender, energy),
+            RobotCommand::Retire => {
+                info!(target: &self.log_target, "Robot: {} Retiring.", self.state.id);
+                self.waypoint = None;
+                self.retiring = true;
+                if self.state.status == RobotStatus::Collecting {
+                    self.state.status = RobotStatus::ReturningToStation;
+                    self.current_target_coords = None;
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            other @ (RobotCommand::Patrol(_)
+            | RobotCommand::Park
+            | RobotCommand::SampleReady { .. }) => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} Ignoring unsupported command {:?}",
+                    self.state.id, other
+                );
+                outcome = Err(format!("Unsupported command {:?}", other));
+            }
+        }
+        outcome
     }
 
     fn handle_collecting(
         &mut self,
-        sender: &Sender<RobotEvent>,
-        map: &Arc<RwLock<Map>>,
+        sender: &EventSender,
+        map: &SharedMap,
         collection_action_cost: u32,
         config: &config::RobotTypeConfig,
     ) {
         let robot_id = self.state.id;
 
-        if self.state.energy <= config.low_energy_threshold || self.state.is_full() {
+        if self.state.energy <= config.low_energy_threshold || self.state.is_full(&self.resources) {
+            self.trace
+                .record(sender.clock().now(), &self.state, "return", || {
+                    if self.state.is_full(&self.resources) {
+                        format!("cargo full at {} units", self.state.max_capacity)
+                    } else {
+                        format!(
+                            "energy {} at or below threshold {}",
+                            self.state.energy, config.low_energy_threshold
+                        )
+                    }
+                });
             info!(
+                target: &self.log_target,
                 "Robot: {} {}",
                 robot_id,
                 if self.state.energy <= config.low_energy_threshold {
@@ -191,9 +593,38 @@ impl CollectionRobot {
             return;
         }
 
+        if let Some(waypoint) = &self.waypoint {
+            self.trace
+                .record(sender.clock().now(), &self.state, "follow_waypoint", || {
+                    format!("ordered to {:?}", waypoint.target)
+                });
+            self.follow_waypoint(sender, map, config);
+            return;
+        }
+
         let (current_x, current_y) = (self.state.x, self.state.y);
+        if let Some(site) = self.sample_site.take() {
+            if site == (current_x, current_y) {
+                self.trace
+                    .record(sender.clock().now(), &self.state, "extract_sample", || {
+                        format!("at the joint site {:?} a scientist asked for", site)
+                    });
+                self.extract_sample(site, collection_action_cost, sender);
+                self.clock.sleep(config::random_sleep_duration(
+                    &mut self.rng,
+                    config.primary_action_sleep_min_ms,
+                    config.primary_action_sleep_max_ms,
+                ));
+                return;
+            }
+            warn!(
+                target: &self.log_target,
+                "Robot: {} Could not reach sample site {:?}.",
+                robot_id, site
+            );
+        }
 
-        let target_type = self.target_resource_type.clone();
+        let target_type = self.target_resource_type;
         if let Some(target_type) = target_type {
             if self.try_collect_resource(
                 current_x,
@@ -203,7 +634,12 @@ impl CollectionRobot {
                 map,
                 sender,
             ) {
-                thread::sleep(config::random_sleep_duration(
+                self.trace
+                    .record(sender.clock().now(), &self.state, "collect", || {
+                        format!("{:?} deposit under the robot", target_type)
+                    });
+                self.clock.sleep(config::random_sleep_duration(
+                    &mut self.rng,
                     config.primary_action_sleep_min_ms,
                     config.primary_action_sleep_max_ms,
                 ));
@@ -211,13 +647,65 @@ impl CollectionRobot {
             }
         }
 
-        self.update_knowledge_around(map);
+        map.observe_area(&mut self.knowledge, (self.state.x, self.state.y));
 
-        let direction = if let Some(target_coords) = self.find_nearest_target_resource() {
+        let plan = self.find_nearest_target_resource();
+        self.trace
+            .record(sender.clock().now(), &self.state, plan.action(), || {
+                let wanted = &self.target_resource_type;
+                match plan {
+                    TargetPlan::MoveTo(coords) => match self.knowledge.get_tile(coords.0, coords.1)
+                    {
+                        knowledge::TileInfo::Resource(..) => {
+                            format!("{:?} deposit at {:?} within a round trip", wanted, coords)
+                        }
+                        _ => format!(
+                            "no {:?} deposit within a round trip, unknown tile at {:?}",
+                            wanted, coords
+                        ),
+                    },
+                    TargetPlan::ReturnEarly => format!(
+                        "nearest {:?} deposit out of reach on {} energy",
+                        wanted, self.state.energy
+                    ),
+                    TargetPlan::AssistExploration(coords) => format!(
+                        "no {:?} deposit or unknown tile within {} moves, frontier at {:?}",
+                        wanted,
+                        config::COLLECTOR_SEARCH_RADIUS,
+                        coords
+                    ),
+                    TargetPlan::Explore => format!("no {:?} deposit or unknown tile left", wanted),
+                }
+            });
+        if plan == TargetPlan::ReturnEarly {
+            self.state.status = RobotStatus::ReturningToStation;
+            self.current_target_coords = None;
+            return;
+        }
+        let assisting = matches!(plan, TargetPlan::AssistExploration(_));
+        if assisting && !self.assisting_exploration {
+            info!(
+                target: &self.log_target,
+                "Robot: {} Nothing to collect nearby, helping with exploration.",
+                robot_id
+            );
+        } else if !assisting && self.assisting_exploration {
+            info!(
+                target: &self.log_target,
+                "Robot: {} Back to collecting.",
+                robot_id
+            );
+        }
+        self.assisting_exploration = assisting;
+
+        let direction = if let TargetPlan::MoveTo(target_coords)
+        | TargetPlan::AssistExploration(target_coords) = plan
+        {
             debug!(
+                target: &self.log_target,
                 "Robot: {} Moving towards {:?} @ {:?} from {:?}",
                 robot_id,
-                self.target_resource_type.as_ref().unwrap(),
+                self.target_resource_type,
                 target_coords,
                 (self.state.x, self.state.y)
             );
@@ -229,174 +717,199 @@ impl CollectionRobot {
                 target_coords.0,
                 target_coords.1,
                 &self.knowledge,
-                &*map.read().unwrap(),
+                &map.read(),
+                &mut self.rng,
             )
         } else {
             debug!(
+                target: &self.log_target,
                 "Robot: {} No target {:?}. Enhanced exploring.",
                 robot_id, self.target_resource_type
             );
             self.current_target_coords = None;
-            self.choose_best_explore_direction(&*map.read().unwrap())
+            self.choose_best_explore_direction(&map.read())
         };
 
         self.try_move(direction, map, config, sender);
 
-        thread::sleep(config::random_sleep_duration(
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
+            config.primary_action_sleep_min_ms,
+            config.primary_action_sleep_max_ms,
+        ));
+    }
+
+    fn follow_waypoint(
+        &mut self,
+        sender: &EventSender,
+        map: &SharedMap,
+        config: &config::RobotTypeConfig,
+    ) {
+        let Some(mut waypoint) = self.waypoint.take() else {
+            return;
+        };
+        let step = waypoint.step(
+            &mut self.state,
+            &mut self.knowledge,
+            &map.read(),
+            config.movement_energy_cost,
+            &mut self.rng,
+        );
+
+        match step {
+            WaypointStep::Moved | WaypointStep::Blocked => self.waypoint = Some(waypoint),
+            WaypointStep::Reached => {
+                info!(
+                    target: &self.log_target,
+                    "Robot: {} Reached waypoint {:?}.",
+                    self.state.id, waypoint.target
+                );
+            }
+            WaypointStep::GaveUp => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} Giving up on unreachable waypoint {:?}.",
+                    self.state.id, waypoint.target
+                );
+            }
+        }
+        if matches!(step, WaypointStep::Moved | WaypointStep::Reached) {
+            self.trail.record((self.state.x, self.state.y));
+            common::report_position(&self.state, sender);
+        }
+
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
             config.primary_action_sleep_min_ms,
             config.primary_action_sleep_max_ms,
         ));
     }
 
+    /// Extracts a sample on the joint site the robot stands on, for the scientist there
+    fn extract_sample(&mut self, site: (usize, usize), action_cost: u32, sender: &EventSender) {
+        if !self.state.use_energy(action_cost) {
+            warn!(
+                target: &self.log_target,
+                "Robot: {} No energy ({}) to sample @ {:?}",
+                self.state.id, self.state.energy, site
+            );
+            return;
+        }
+        info!(
+            target: &self.log_target,
+            "Robot: {} Extracted a sample @ {:?}.",
+            self.state.id, site
+        );
+        let event = RobotEvent::SampleExtracted {
+            id: self.state.id,
+            x: site.0,
+            y: site.1,
+        };
+        if let Err(e) = sender.send(event) {
+            error!(
+                target: &self.log_target,
+                "Robot: {} Failed send SampleExtracted: {}.",
+                self.state.id, e
+            );
+        }
+    }
+
     fn try_collect_resource(
         &mut self,
         x: usize,
         y: usize,
         target_type: &ResourceType,
         collection_action_cost: u32,
-        map: &Arc<RwLock<Map>>,
-        sender: &Sender<RobotEvent>,
+        map: &SharedMap,
+        sender: &EventSender,
     ) -> bool {
         let robot_id = self.state.id;
-        let resource_present = {
-            let guard = match map.read() {
-                Ok(g) => g,
-                Err(p) => {
-                    error!("Robot: {} Map read poisoned! {}", robot_id, p);
-                    return false;
-                }
-            };
-            guard
-                .get_resource(x, y)
-                .map_or(false, |(rt, amount)| rt == *target_type && amount > 0)
-        };
+        let resource_present = map.with_read(|map| {
+            map.get_resource(x, y)
+                .is_some_and(|(rt, amount)| rt == *target_type && amount > 0)
+        });
 
         if !resource_present {
-            debug!("Robot: {} No resource present at ({}, {})", robot_id, x, y);
+            debug!(
+                target: &self.log_target,
+                "Robot: {} No resource present at ({}, {})",
+                robot_id, x, y
+            );
             return false;
         }
 
         if !self.state.use_energy(collection_action_cost) {
             warn!(
+                target: &self.log_target,
                 "Robot: {} No energy ({}) to collect @ {:?}",
-                robot_id,
-                self.state.energy,
-                (x, y)
+                robot_id, self.state.energy, (x, y)
             );
             return false;
         }
 
-        let mut amount_collected = 0;
-        let mut remove_successful = false;
-        {
-            let mut guard = match map.write() {
-                Ok(g) => g,
-                Err(p) => {
-                    error!("Robot: {} Map write poisoned! {}", robot_id, p);
-                    return false;
-                }
-            };
-            if let Some((res_type, amount)) = guard.get_resource(x, y) {
-                debug!(
-                    "Robot: {} Resource at ({}, {}): {:?} amount={}",
-                    robot_id, x, y, res_type, amount
-                );
-                let current_total = self.state.collected_resources.values().sum::<u32>();
-                let available_capacity = self.state.max_capacity.saturating_sub(current_total);
-                let to_collect = amount.min(available_capacity);
-
-                debug!(
-                    "Robot: {} Carrying {}/{} before collecting. Trying to collect {}.",
-                    robot_id, current_total, self.state.max_capacity, to_collect
-                );
-
-                if res_type == *target_type && amount > 0 {
-                    if self.state.collect_resource(target_type.clone(), amount) {
-                        amount_collected = amount;
-                        if guard.remove_resource(x, y).is_some() {
-                            remove_successful = true;
-                            info!(
-                                "Robot: {} Collected/removed {} {:?} @ {:?}. Now carrying {}/{}.",
-                                robot_id,
-                                amount_collected,
-                                target_type,
-                                (x, y),
-                                self.state.collected_resources.values().sum::<u32>(),
-                                self.state.max_capacity
-                            );
-                        } else {
-                            error!("Robot: {} Failed remove map @ {:?}", robot_id, (x, y));
-                        }
-                    } else {
-                        warn!(
-                            "Robot: {} Collect failed (capacity?) @ {:?}",
-                            robot_id,
-                            (x, y)
-                        );
-                        if self.state.is_full() {
-                            self.state.status = RobotStatus::ReturningToStation;
-                            // self.current_target_coords = None;
-                        }
-                    }
-                } else {
-                    debug!(
-                        "Robot: {} Resource changed pre-write @ {:?}",
-                        robot_id,
-                        (x, y)
-                    );
-                }
-            } else {
-                debug!("Robot: {} Resource gone pre-write @ {:?}", robot_id, (x, y));
-            }
-        }
-        if remove_successful {
-            self.knowledge.update_tile(x, y, TileInfo::Walkable);
-            let event = RobotEvent::CollectionData {
-                id: robot_id,
-                x,
-                y,
-                resource_type: Some(target_type.clone()),
-                amount: amount_collected,
-            };
-            if let Err(e) = sender.send(event) {
-                error!("Robot: {} Failed send CollectionData: {}.", robot_id, e);
-            }
+        // Units of the resource the cargo has room for
+        let capacity = self
+            .state
+            .max_capacity
+            .saturating_sub(self.state.cargo_load(&self.resources))
+            / self.resources.weight(*target_type);
+        if capacity == 0 {
+            warn!(target: &self.log_target, "Robot: {} No capacity left @ {:?}", robot_id, (x, y));
+            self.state.status = RobotStatus::ReturningToStation;
+            return false;
         }
-        remove_successful
-    }
-
-    fn update_knowledge_around(&mut self, map: &Arc<RwLock<Map>>) {
-        let map_read_guard = match map.read() {
-            Ok(g) => g,
-            Err(p) => {
-                error!("Robot: {} Map read poisoned! {}", self.state.id, p);
-                return;
-            }
+        let Some(amount_collected) = map.try_collect_at((x, y), target_type, capacity) else {
+            debug!(
+                target: &self.log_target,
+                "Robot: {} Resource gone pre-write @ {:?}",
+                robot_id, (x, y)
+            );
+            return false;
         };
-        let map_read = &*map_read_guard;
-        let (x, y) = (self.state.x, self.state.y);
-        self.knowledge.observe_and_update(x, y, map_read);
+        self.state
+            .collect_resource(*target_type, amount_collected, &self.resources);
 
-        for dir in Direction::all().iter() {
-            let (nx, ny) = movement::next_position(x, y, dir, map_read);
-            if (nx, ny) != (x, y) {
-                self.knowledge.observe_and_update(nx, ny, map_read);
-            }
+        info!(
+            target: &self.log_target,
+            "Robot: {} Collected {} {:?} @ {:?}. Now carrying {}/{}.",
+            robot_id,
+            amount_collected,
+            target_type,
+            (x, y),
+            self.state.cargo_load(&self.resources),
+            self.state.max_capacity
+        );
+        // Whatever did not fit stays in the deposit
+        map.with_read(|map| self.knowledge.observe_and_update(x, y, map));
+        let event = RobotEvent::CollectionData {
+            id: robot_id,
+            x,
+            y,
+            resource_type: Some(*target_type),
+            amount: amount_collected,
+        };
+        if let Err(e) = sender.send(event) {
+            error!(
+                target: &self.log_target,
+                "Robot: {} Failed send CollectionData: {}.",
+                robot_id, e
+            );
         }
+        true
     }
 
-    fn choose_best_explore_direction(&self, map: &Map) -> Direction {
+    fn choose_best_explore_direction(&mut self, map: &Map) -> Direction {
         let directions = Direction::all();
-        let mut best_direction = Direction::random();
+        let mut best_direction = Direction::random(&mut self.rng);
         let mut best_score = -1;
 
         for dir in directions {
             let (nx, ny) = movement::next_position(self.state.x, self.state.y, &dir, map);
             if movement::is_valid_move(nx, ny, map)
-                && !matches!(
-                    self.knowledge.get_tile(nx, ny),
-                    knowledge::TileInfo::Obstacle
-                )
+                && self.can_reach((nx, ny))
+                && !self
+                    .knowledge
+                    .blocks_move((self.state.x, self.state.y), (nx, ny))
             {
                 let score = match self.knowledge.get_tile(nx, ny) {
                     knowledge::TileInfo::Unknown => 2,
@@ -415,28 +928,23 @@ impl CollectionRobot {
     fn try_move(
         &mut self,
         direction: Direction,
-        map: &Arc<RwLock<Map>>,
+        map: &SharedMap,
         config: &config::RobotTypeConfig,
-        sender: &Sender<RobotEvent>,
+        sender: &EventSender,
     ) {
-        let map_read_guard = match map.read() {
-            Ok(g) => g,
-            Err(p) => {
-                error!("Robot: {} Map read poisoned! {}", self.state.id, p);
-                return;
-            }
-        };
+        let map_read_guard = map.read();
         let map_read = &*map_read_guard;
         let (new_x, new_y) =
             movement::next_position(self.state.x, self.state.y, &direction, map_read);
 
         if movement::is_valid_move(new_x, new_y, map_read)
-            && !matches!(
-                self.knowledge.get_tile(new_x, new_y),
-                knowledge::TileInfo::Obstacle
-            )
+            && self.can_reach((new_x, new_y))
+            && !self
+                .knowledge
+                .blocks_move((self.state.x, self.state.y), (new_x, new_y))
         {
             debug!(
+                target: &self.log_target,
                 "Robot: {} Moving from {:?} to {:?} (capacity: {}, energy: {})",
                 self.state.id,
                 (self.state.x, self.state.y),
@@ -446,20 +954,14 @@ impl CollectionRobot {
             );
 
             if self.state.energy >= config.movement_energy_cost {
-                self.state.x = new_x;
-                self.state.y = new_y;
+                self.state.move_to(new_x, new_y, map_read);
                 self.state.use_energy(config.movement_energy_cost);
+                self.trail.record((self.state.x, self.state.y));
 
-                // Send position update to App/UI
-                let _ = sender.send(RobotEvent::CollectionData {
-                    id: self.state.id,
-                    x: self.state.x,
-                    y: self.state.y,
-                    resource_type: None,
-                    amount: 0,
-                });
+                common::report_position(&self.state, sender);
             } else {
                 warn!(
+                    target: &self.log_target,
                     "Robot: {} Not enough energy to movEnergy: {}/{}",
                     self.state.id, self.state.energy, config.movement_energy_cost
                 );
@@ -468,95 +970,128 @@ impl CollectionRobot {
             }
         } else {
             debug!(
+                target: &self.log_target,
                 "Robot: {} Move to {:?} blocked or invalid.",
-                self.state.id,
-                (new_x, new_y)
+                self.state.id, (new_x, new_y)
             );
         }
     }
 
     fn handle_returning_to_station(
         &mut self,
-        sender: &Sender<RobotEvent>,
-        map: &Arc<RwLock<Map>>,
+        sender: &EventSender,
+        map: &SharedMap,
         station_coords: (usize, usize),
         config: &config::RobotTypeConfig,
     ) {
         let robot_id = self.state.id;
         let (station_x, station_y) = station_coords;
         if self.state.x == station_x && self.state.y == station_y {
-            info!("Robot: {} Arrived station.", robot_id);
+            info!(target: &self.log_target, "Robot: {} Arrived station.", robot_id);
             self.state.status = RobotStatus::AtStation;
-            let k_clone = self.knowledge.clone();
-            let ev = RobotEvent::ArrivedAtStation {
-                id: robot_id,
-                knowledge: k_clone,
-            };
-            if let Err(e) = sender.send(ev) {
-                error!("Robot: {} Failed send Arrived: {}", robot_id, e);
-                return;
-            }
-            info!("Robot: {} Waiting MergeComplete...", robot_id);
-
-            match self
-                .merge_complete_receiver
-                .recv_timeout(config::MERGE_TIMEOUT)
-            {
+            self.trail = Trail::starting_at(station_coords);
+            self.sample_site = None;
+            common::discard_stale_merges(&self.merge_complete_receiver, &self.inbox, robot_id);
+            match common::report_dock(
+                sender,
+                &*self.clock,
+                &self.merge_complete_receiver,
+                &self.inbox,
+                robot_id,
+                &self.knowledge,
+            ) {
                 Ok(RobotEvent::MergeComplete {
-                    merged_knowledge, ..
+                    merged_knowledge,
+                    brief,
+                    ..
                 }) => {
-                    info!("Robot: {} MergeComplete OK.", robot_id);
+                    info!(target: &self.log_target, "Robot: {} MergeComplete OK.", robot_id);
                     self.knowledge = merged_knowledge;
-                    self.state.energy = self.state.max_energy;
                     self.state.collected_resources.clear();
-                    self.state.status = RobotStatus::Collecting;
-                    info!("Robot: {} Resuming collection.", robot_id);
+                    let resume = self.status_after_dock();
+                    self.state.status = common::recharge_at_dock(&mut self.state, &brief, resume);
+                    for assignment in common::take_brief(&mut self.knowledge, *brief) {
+                        if let Err(reason) = self.carry_out(sender, assignment) {
+                            warn!(
+                                target: &self.log_target,
+                                "Robot: {} Assignment dropped: {}",
+                                robot_id, reason
+                            );
+                        }
+                    }
+                    info!(target: &self.log_target, "Robot: {} Resuming collection.", robot_id);
                 }
                 Ok(o) => {
-                    warn!("Robot: {} Unexpected event: {:?}", robot_id, o);
-                    self.state.status = RobotStatus::Collecting;
+                    warn!(
+                        target: &self.log_target,
+                        "Robot: {} Unexpected event: {:?}",
+                        robot_id, o
+                    );
+                    self.state.status = self.status_after_dock();
                 }
                 Err(RecvTimeoutError::Timeout) => {
-                    warn!("Robot: {} Merge Timeout.", robot_id);
-                    self.state.status = RobotStatus::Collecting;
+                    warn!(
+                        target: &self.log_target,
+                        "Robot: {} No merge, resuming with own knowledge.",
+                        robot_id
+                    );
+                    self.state.status = self.status_after_dock();
                 }
                 Err(RecvTimeoutError::Disconnected) => {
-                    error!("Robot: {} Merge channel disconnected.", robot_id);
+                    error!(
+                        target: &self.log_target,
+                        "Robot: {} Merge channel disconnected.",
+                        robot_id
+                    );
                 }
             }
             return;
         }
 
-        let map_read_guard = match map.read() {
-            Ok(g) => g,
-            Err(p) => {
-                error!("Robot: {} Map read poisoned! {}", robot_id, p);
-                return;
-            }
-        };
+        let map_read_guard = map.read();
         let map_read = &*map_read_guard;
 
-        let direction = common::move_towards_target(
-            self.state.x,
-            self.state.y,
-            station_x,
-            station_y,
-            &self.knowledge,
-            map_read,
-        );
-
-        let (new_x, new_y) =
-            movement::next_position(self.state.x, self.state.y, &direction, map_read);
+        let descent =
+            common::descend_station_field(self.state.x, self.state.y, &self.knowledge, map_read)
+                .map(|direction| {
+                    movement::next_position(self.state.x, self.state.y, &direction, map_read)
+                });
+        let trail_step = descent
+            .is_none()
+            .then(|| self.step_back_along_trail(map_read))
+            .flatten();
+        self.trace
+            .record(sender.clock().now(), &self.state, "head_home", || {
+                let route = match (descent, trail_step) {
+                    (Some(_), _) => "down the station's flow field",
+                    (None, Some(_)) => "back along its trail",
+                    (None, None) => "straight for the station",
+                };
+                format!("{} at {:?}", route, station_coords)
+            });
+        let (new_x, new_y) = match descent.or(trail_step) {
+            Some(tile) => tile,
+            None => {
+                let direction = common::move_towards_target(
+                    self.state.x,
+                    self.state.y,
+                    station_x,
+                    station_y,
+                    &self.knowledge,
+                    map_read,
+                    &mut self.rng,
+                );
+                movement::next_position(self.state.x, self.state.y, &direction, map_read)
+            }
+        };
 
         let mut moved = false;
         if movement::is_valid_move(new_x, new_y, map_read)
-            && !matches!(
-                self.knowledge.get_tile(new_x, new_y),
-                knowledge::TileInfo::Obstacle
-            )
+            && !self
+                .knowledge
+                .blocks_move((self.state.x, self.state.y), (new_x, new_y))
         {
-            self.state.x = new_x;
-            self.state.y = new_y;
+            self.state.move_to(new_x, new_y, map_read);
 
             // For the moment return to station cost 0, but i would like to find a way to
             // let die robots if they dont have enough energy to comes back.
@@ -568,17 +1103,19 @@ impl CollectionRobot {
         }
 
         if !moved {
+            self.trace
+                .record(sender.clock().now(), &self.state, "detour", || {
+                    format!("{:?} is blocked, trying random steps", (new_x, new_y))
+                });
             for _ in 0..RANDOM_MOVE_ATTEMPTS {
-                let rd = movement::Direction::random();
+                let rd = movement::Direction::random(&mut self.rng);
                 let (rx, ry) = movement::next_position(self.state.x, self.state.y, &rd, map_read);
                 if movement::is_valid_move(rx, ry, map_read)
-                    && !matches!(
-                        self.knowledge.get_tile(rx, ry),
-                        knowledge::TileInfo::Obstacle
-                    )
+                    && !self
+                        .knowledge
+                        .blocks_move((self.state.x, self.state.y), (rx, ry))
                 {
-                    self.state.x = rx;
-                    self.state.y = ry;
+                    self.state.move_to(rx, ry, map_read);
 
                     if self.state.status != RobotStatus::ReturningToStation {
                         self.state.use_energy(config.movement_energy_cost);
@@ -588,21 +1125,230 @@ impl CollectionRobot {
                 }
             }
         }
-        if !moved {
+        if moved {
+            common::report_position(&self.state, sender);
+        } else {
             debug!(
+                target: &self.log_target,
                 "Robot: {} Path to station blocked @ {:?}.",
-                robot_id,
-                (self.state.x, self.state.y)
+                robot_id, (self.state.x, self.state.y)
             );
         }
 
-        thread::sleep(config::random_sleep_duration(
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
             config::RETURN_SLEEP_MIN_MS,
             config::RETURN_SLEEP_MAX_MS,
         ));
     }
 
+    /// Next tile of the way walked out, if it is still open. A blocked trail is dropped and
+    /// the rest of the way searched step by step.
+    fn step_back_along_trail(&mut self, map: &Map) -> Option<(usize, usize)> {
+        let current = (self.state.x, self.state.y);
+        let next = self.trail.next_back(current);
+        let open = next.filter(|&(x, y)| {
+            Direction::all()
+                .iter()
+                .any(|dir| movement::next_position(current.0, current.1, dir, map) == (x, y))
+                && movement::is_valid_move(x, y, map)
+                && !self.knowledge.blocks_move(current, (x, y))
+        });
+        if open.is_none() && !self.trail.is_empty() {
+            debug!(
+                target: &self.log_target,
+                "Robot: {} Trail back blocked @ {:?}, re-planning.",
+                self.state.id, current
+            );
+            self.trail.clear();
+        }
+        open
+    }
+
+    /// Status after docking: retiring robots stay at the station
+    fn status_after_dock(&self) -> RobotStatus {
+        if self.retiring {
+            RobotStatus::AtStation
+        } else {
+            RobotStatus::Collecting
+        }
+    }
+
     fn handle_at_station(&mut self) {
-        thread::sleep(Duration::from_millis(config::AT_STATION_SLEEP_MS));
+        self.clock.sleep(config::scaled(Duration::from_millis(
+            config::AT_STATION_SLEEP_MS,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::core::flow_field::FlowField;
+    use crate::robot::core::knowledge::TileInfo;
+    use std::sync::mpsc;
+
+    fn robot_at(x: usize, y: usize, energy: u32, max_energy: u32) -> CollectionRobot {
+        let mut state = RobotState::new(1, x, y, RobotStatus::Collecting, max_energy);
+        state.energy = energy;
+        let (_, merge_rx) = mpsc::channel();
+        let (_, command_rx) = mpsc::channel();
+        let mut robot =
+            CollectionRobot::new(state, 20, 5, merge_rx, command_rx, RobotRuntime::default());
+        // Station at (10, 2); (1, 2) is nearer to the robot but farther from the station
+        robot
+            .knowledge
+            .update_tile(1, 2, TileInfo::Resource(ResourceType::MINERALS, 10));
+        robot
+            .knowledge
+            .update_tile(10, 0, TileInfo::Resource(ResourceType::MINERALS, 10));
+        robot
+    }
+
+    #[test]
+    fn test_collector_skips_deposits_it_cannot_afford() {
+        assert_eq!(
+            robot_at(5, 2, 60, 100).find_nearest_target_resource(),
+            TargetPlan::MoveTo((1, 2))
+        );
+        assert_eq!(
+            robot_at(5, 2, 50, 100).find_nearest_target_resource(),
+            TargetPlan::MoveTo((10, 0))
+        );
+        assert_eq!(
+            robot_at(5, 2, 40, 100).find_nearest_target_resource(),
+            TargetPlan::ReturnEarly
+        );
+
+        // A full robot has nothing to gain from recharging, it explores instead
+        let plan = robot_at(5, 2, 40, 40).find_nearest_target_resource();
+        assert!(
+            matches!(plan, TargetPlan::MoveTo(coords) if coords != (1, 2) && coords != (10, 0))
+        );
+    }
+
+    #[test]
+    fn test_collectors_go_round_the_walls_they_know() {
+        // A wall at x = 3 with a gap on the bottom row, between the robot and (1, 2)
+        let wall = |(x, y): (usize, usize)| x == 3 && y < 4;
+        let walled_robot = |energy| {
+            let mut robot = robot_at(5, 2, energy, 100);
+            for x in 0..20 {
+                for y in 0..5 {
+                    if wall((x, y)) {
+                        robot.knowledge.update_tile(x, y, TileInfo::Obstacle);
+                    } else if !matches!(robot.knowledge.get_tile(x, y), TileInfo::Resource(..)) {
+                        robot.knowledge.update_tile(x, y, TileInfo::Walkable);
+                    }
+                }
+            }
+            robot
+                .knowledge
+                .set_station_field(FlowField::towards([(10, 2)], 20, 5, |tile| !wall(tile)));
+            robot
+        };
+
+        // 8 moves there through the gap and 13 back: more than the 4 and 9 of a bare grid
+        let robot = walled_robot(60);
+        let (moves, action) = (
+            robot.tuning.config().movement_energy_cost,
+            robot.tuning.config().action_energy_cost.unwrap_or(0),
+        );
+        assert_eq!(robot.round_trip_energy_cost((1, 2)), 21 * moves + action);
+        // Affordable on the grid, not round the wall
+        assert!(
+            robot.grid_round_trip_energy_cost((1, 2)) + robot.tuning.config().low_energy_threshold
+                <= 60
+        );
+        assert_eq!(
+            robot.find_nearest_target_resource(),
+            TargetPlan::MoveTo((10, 0))
+        );
+        assert_eq!(
+            walled_robot(80).find_nearest_target_resource(),
+            TargetPlan::MoveTo((1, 2))
+        );
+    }
+
+    #[test]
+    fn test_collectors_with_nothing_nearby_explore_from_the_frontier() {
+        let mut robot = robot_at(5, 2, 100, 100);
+        robot.set_target_resource(ResourceType::ENERGY);
+        for x in 0..13 {
+            for y in 0..5 {
+                robot.knowledge.update_tile(x, y, TileInfo::Walkable);
+            }
+        }
+        // The unknown tiles past x = 12 are 8 moves away or more
+        assert_eq!(
+            robot.find_nearest_target_resource(),
+            TargetPlan::AssistExploration((13, 2))
+        );
+
+        robot.knowledge.update_tile(8, 2, TileInfo::Unknown);
+        assert_eq!(
+            robot.find_nearest_target_resource(),
+            TargetPlan::MoveTo((8, 2))
+        );
+    }
+
+    #[test]
+    fn test_only_drilling_collectors_go_underground() {
+        let mut robot = robot_at(5, 2, 100, 100);
+        robot.set_surface_rows(0..3);
+        robot
+            .knowledge
+            .update_tile(6, 4, TileInfo::Resource(ResourceType::MINERALS, 300));
+        assert_eq!(
+            robot.find_nearest_target_resource(),
+            TargetPlan::MoveTo((1, 2))
+        );
+
+        robot.fit_drill(3);
+        assert_eq!(
+            robot.find_nearest_target_resource(),
+            TargetPlan::MoveTo((6, 4))
+        );
+    }
+
+    #[test]
+    fn test_drilling_collectors_count_the_way_through_the_shafts() {
+        let state = RobotState::new(1, 5, 2, RobotStatus::Collecting, 1000);
+        let (_, merge_rx) = mpsc::channel();
+        let (_, command_rx) = mpsc::channel();
+        let runtime = RobotRuntime {
+            station: Some((10, 2)),
+            ..RobotRuntime::default()
+        };
+        let mut robot = CollectionRobot::new(state, 20, 11, merge_rx, command_rx, runtime);
+        robot.set_surface_rows(0..5);
+        // A cave layer right below, reached through a single shaft at the east end
+        robot.set_topology(Topology {
+            width: 20,
+            height: 11,
+            layer_height: 5,
+            shafts: vec![((18, 2), (18, 8)), ((18, 8), (18, 2))].into(),
+            ..Topology::default()
+        });
+        robot.fit_drill(1);
+        robot
+            .knowledge
+            .update_tile(6, 8, TileInfo::Resource(ResourceType::MINERALS, 300));
+
+        // 13 moves east, the climb, 12 moves west, then 12, the climb and 8 back
+        let (moves, action) = (
+            robot.tuning.config().movement_energy_cost,
+            robot.tuning.config().action_energy_cost.unwrap_or(0),
+        );
+        assert_eq!(
+            robot.round_trip_energy_cost((6, 8)),
+            (13 + 1 + 12 + 12 + 1 + 8) * moves + action
+        );
+        // Straight through the seam it would look affordable
+        robot.state.energy = 30 * moves + action;
+        assert_eq!(
+            robot.find_nearest_target_resource(),
+            TargetPlan::ReturnEarly
+        );
     }
 }
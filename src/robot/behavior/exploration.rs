@@ -1,24 +1,51 @@
 use log::{debug, error, info, warn};
 use std::collections::HashSet;
-use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
-use std::sync::{Arc, RwLock};
-use std::thread;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread::JoinHandle;
 use std::time::Duration;
 
-use crate::communication::channels::RobotEvent;
+use crate::communication::channels::{
+    CommandEnvelope, EventSender, RobotCommand, RobotEvent, ShutdownReason,
+};
+use crate::logging;
 use crate::map::noise::Map;
-use crate::robot::core::knowledge::{RobotKnowledge, TileInfo};
+use crate::map::shared::SharedMap;
+use crate::profiler;
+use crate::robot::core::knowledge::RobotKnowledge;
 use crate::robot::core::movement;
-use crate::robot::core::movement::Direction;
 use crate::robot::core::state::{RobotState, RobotStatus};
+use crate::robot::core::waypoint::{PatrolRoute, Waypoint, WaypointStep};
+use crate::robot::launcher::RobotRuntime;
+use crate::robot::registry::RobotType;
+use crate::robot::utils::clock::{self, SharedClock};
 use crate::robot::utils::common;
 use crate::robot::utils::config;
+use crate::robot::utils::inbox::InboxGauge;
+use crate::robot::utils::preset::RobotTuning;
+use crate::robot::utils::rng::RobotRng;
+use crate::robot::utils::trace::DecisionTrace;
 
 pub struct ExplorationRobot {
     state: RobotState,
+    /// Logs under `robot::<id>`, so one robot's messages can be picked out
+    log_target: String,
     knowledge: RobotKnowledge,
     merge_complete_receiver: Receiver<RobotEvent>,
-    config: config::RobotTypeConfig,
+    command_receiver: Receiver<CommandEnvelope>,
+    waypoint: Option<Waypoint>,
+    patrol: Option<PatrolRoute>,
+    /// Stays docked after the next visit to the station
+    parked: bool,
+    /// Stops the thread after the next visit to the station
+    retiring: bool,
+    /// The station dropped its end of the command channel, the thread stops right away
+    station_lost: bool,
+    /// Own thresholds, sleeps and costs, as the swarm preset plays them
+    tuning: RobotTuning,
+    clock: SharedClock,
+    inbox: InboxGauge,
+    trace: DecisionTrace,
+    rng: RobotRng,
 }
 
 impl ExplorationRobot {
@@ -27,32 +54,90 @@ impl ExplorationRobot {
         map_width: usize,
         map_height: usize,
         merge_complete_receiver: Receiver<RobotEvent>,
+        command_receiver: Receiver<CommandEnvelope>,
+        runtime: RobotRuntime,
     ) -> Self {
         Self {
-            knowledge: RobotKnowledge::new(map_width, map_height),
+            knowledge: runtime.knowledge(map_width, map_height),
+            log_target: logging::robot_target(initial_state.id),
             state: initial_state,
             merge_complete_receiver,
-            config: config::EXPLORATION_CONFIG.clone(),
+            command_receiver,
+            waypoint: None,
+            patrol: None,
+            parked: false,
+            retiring: false,
+            station_lost: false,
+            tuning: runtime.tuning(RobotType::Exploration, &config::EXPLORATION_CONFIG),
+            clock: runtime.clock,
+            inbox: runtime.gauge,
+            trace: runtime.trace,
+            rng: runtime.rng,
         }
     }
 
-    pub fn start(mut self, sender: Sender<RobotEvent>, map: Arc<RwLock<Map>>) {
+    pub fn start(mut self, sender: EventSender, map: SharedMap) -> JoinHandle<()> {
         let robot_id = self.state.id;
         let station_coords = self.knowledge.get_station_coords();
 
-        thread::spawn(move || {
+        let clock = self.clock.clone();
+        clock::spawn_robot(&clock, robot_id, move || {
             let mut visited: HashSet<(usize, usize)> = HashSet::new();
-            info!("Robot {}: Starting exploration thread.", robot_id);
+            info!(target: &self.log_target, "Robot {}: Starting exploration thread.", robot_id);
 
             loop {
+                let _step = profiler::robot_step(robot_id);
+                self.process_commands(&sender, &mut visited);
+                common::suffer_hostile_contact(&mut self.state, &map);
+                common::soak_up_flare(&mut self.state, &sender);
+                self.knowledge.expire_sightings(sender.clock().now());
+                self.knowledge
+                    .forget_stale_terrain(sender.clock().now(), config::terrain_memory());
+                if self.retiring && self.state.status == RobotStatus::AtStation {
+                    info!(target: &self.log_target, "Robot {}: Retired at station.", robot_id);
+                    break;
+                }
+                if self.station_lost {
+                    info!(
+                        target: &self.log_target,
+                        "Robot {}: Station closed, stopping.",
+                        robot_id
+                    );
+                    break;
+                }
+
                 match self.state.status {
                     RobotStatus::Exploring => {
                         if self.low_energy() {
+                            self.trace
+                                .record(sender.clock().now(), &self.state, "return", || {
+                                    format!(
+                                        "energy {} at or below threshold {}",
+                                        self.state.energy,
+                                        self.tuning.config().low_energy_threshold
+                                    )
+                                });
                             self.transition_to_returning(&mut visited);
                             continue;
                         }
+                        if self.waypoint.is_none() {
+                            self.waypoint = self.patrol.as_mut().map(PatrolRoute::next_waypoint);
+                        }
+                        if let Some(waypoint) = &self.waypoint {
+                            self.trace.record(
+                                sender.clock().now(),
+                                &self.state,
+                                "follow_waypoint",
+                                || match &self.patrol {
+                                    Some(_) => format!("patrol point {:?}", waypoint.target),
+                                    None => format!("ordered to {:?}", waypoint.target),
+                                },
+                            );
+                            self.follow_waypoint(&sender, &map);
+                            continue;
+                        }
                         if let Err(e) = self.explore_step(&sender, &map, &mut visited) {
-                            error!("Robot {}: {}", robot_id, e);
+                            error!(target: &self.log_target, "Robot {}: {}", robot_id, e);
                             break;
                         }
                     }
@@ -67,32 +152,250 @@ impl ExplorationRobot {
                         }
                     }
                     RobotStatus::AtStation => {
-                        thread::sleep(Duration::from_millis(config::AT_STATION_SLEEP_MS));
+                        self.clock.sleep(config::scaled(Duration::from_millis(
+                            config::AT_STATION_SLEEP_MS,
+                        )));
                     }
                     _ => {
                         error!(
+                            target: &self.log_target,
                             "Robot: {} Unhandled state {:?}.",
                             robot_id, self.state.status
                         );
                         self.state.status = RobotStatus::Exploring;
-                        thread::sleep(config::UNHANDLED_STATE_SLEEP);
+                        self.clock.sleep(config::UNHANDLED_STATE_SLEEP);
                     }
                 }
             }
-            info!("Robot {}: Thread shutting down.", robot_id);
+            info!(target: &self.log_target, "Robot {}: Thread shutting down.", robot_id);
+            let reason = if self.retiring {
+                ShutdownReason::Retired
+            } else if self.station_lost {
+                ShutdownReason::StationClosed
+            } else {
+                ShutdownReason::LoopExited
+            };
             let _ = sender.send(RobotEvent::Shutdown {
                 id: robot_id,
-                reason: "Thread loop exited".to_string(),
+                reason,
+            });
+        })
+    }
+
+    fn process_commands(&mut self, sender: &EventSender, visited: &mut HashSet<(usize, usize)>) {
+        while let Some(CommandEnvelope {
+            id: command_id,
+            command,
+        }) = common::next_command(&self.command_receiver, &self.inbox, &mut self.station_lost)
+        {
+            let outcome = self.carry_out(sender, visited, command);
+            common::acknowledge(sender, self.state.id, command_id, outcome);
+        }
+    }
+
+    /// Carries out an order of the station, or says why it cannot
+    fn carry_out(
+        &mut self,
+        sender: &EventSender,
+        visited: &mut HashSet<(usize, usize)>,
+        command: RobotCommand,
+    ) -> Result<(), String> {
+        let mut outcome = Ok(());
+        match command {
+            RobotCommand::ReturnToStation => {
+                self.waypoint = None;
+                if self.state.status == RobotStatus::Exploring {
+                    info!(
+                        target: &self.log_target,
+                        "Robot {}: Recalled to station.",
+                        self.state.id
+                    );
+                    self.state.status = RobotStatus::ReturningToStation;
+                    visited.clear();
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            RobotCommand::MoveTo { x, y } if self.knowledge.is_excluded(x, y) => {
+                outcome = Err(format!("({}, {}) lies in a no-go zone", x, y));
+            }
+            RobotCommand::MoveTo { x, y } => {
+                debug!(
+                    target: &self.log_target,
+                    "Robot {}: New waypoint ({}, {}).",
+                    self.state.id, x, y
+                );
+                self.unpark();
+                self.waypoint = Some(Waypoint::new(x, y));
+            }
+            RobotCommand::Patrol(points) => {
+                self.waypoint = None;
+                self.patrol = PatrolRoute::new(points);
+                if self.patrol.is_some() {
+                    self.unpark();
+                }
+                match &self.patrol {
+                    Some(route) => {
+                        info!(
+                            target: &self.log_target,
+                            "Robot {}: Patrolling {:?}.",
+                            self.state.id, route.points()
+                        )
+                    }
+                    None => {
+                        info!(target: &self.log_target, "Robot {}: Patrol stopped.", self.state.id)
+                    }
+                }
+            }
+            RobotCommand::AvoidTiles(tiles) => self.knowledge.set_exclusions(tiles),
+            RobotCommand::Wear { percent } => self.state.wear_percent = percent,
+            RobotCommand::SetPreset(preset) => {
+                info!(
+                    target: &self.log_target,
+                    "Robot {}: Switching to the {} preset.",
+                    self.state.id, preset.label()
+                );
+                self.tuning.set_preset(preset);
+            }
+            RobotCommand::Service { ticks } => {
+                common::undergo_service(&mut self.state, sender, &*self.clock, ticks)
+            }
+            RobotCommand::Charge { ticks } => {
+                common::charge(&mut self.state, sender, &*self.clock, ticks);
+                if self.state.status == RobotStatus::AtStation {
+                    self.state.status = self.status_after_dock();
+                }
+            }
+            RobotCommand::RefitBattery => {
+                info!(target: &self.log_target, "Robot {}: Battery refitted.", self.state.id);
+                self.state.refit_battery();
+            }
+            RobotCommand::MeteorImpact {
+                x,
+                y,
+                radius,
+                damage,
+            } => common::suffer_meteor_impact(
+                &mut self.state,
+                &mut self.knowledge,
+                (x, y),
+                radius,
+                damage,
+            ),
+            RobotCommand::SolarFlare {
+                scramble_percent,
+                seed,
+            } => {
+                common::suffer_solar_flare(&self.state, &mut self.knowledge, scramble_percent, seed)
+            }
+            RobotCommand::Teleport { x, y } => common::teleport(&mut self.state, sender, (x, y)),
+            RobotCommand::SetEnergy(energy) => common::set_energy(&mut self.state, sender, energy),
+            RobotCommand::Retire => {
+                info!(target: &self.log_target, "Robot {}: Retiring.", self.state.id);
+                self.waypoint = None;
+                self.patrol = None;
+                self.retiring = true;
+                if self.state.status == RobotStatus::Exploring {
+                    self.state.status = RobotStatus::ReturningToStation;
+                    visited.clear();
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            RobotCommand::Park => {
+                info!(target: &self.log_target, "Robot {}: Parking at station.", self.state.id);
+                self.waypoint = None;
+                self.patrol = None;
+                self.parked = true;
+                if self.state.status == RobotStatus::Exploring {
+                    self.state.status = RobotStatus::ReturningToStation;
+                    visited.clear();
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            other => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot {}: Ignoring unsupported command {:?}",
+                    self.state.id, other
+                );
+                outcome = Err(format!("Unsupported command {:?}", other));
+            }
+        }
+        outcome
+    }
+
+    /// Leaves the station again if the robot was parked there
+    fn unpark(&mut self) {
+        if self.parked && !self.retiring {
+            self.parked = false;
+            if self.state.status == RobotStatus::AtStation {
+                info!(target: &self.log_target, "Robot {}: Leaving parking.", self.state.id);
+                self.state.status = RobotStatus::Exploring;
+            }
+        }
+    }
+
+    /// Status after docking: parked and retiring robots stay at the station
+    fn status_after_dock(&self) -> RobotStatus {
+        if self.parked || self.retiring {
+            RobotStatus::AtStation
+        } else {
+            RobotStatus::Exploring
+        }
+    }
+
+    fn follow_waypoint(&mut self, sender: &EventSender, map: &SharedMap) {
+        let Some(mut waypoint) = self.waypoint.take() else {
+            return;
+        };
+        let step = waypoint.step(
+            &mut self.state,
+            &mut self.knowledge,
+            &map.read(),
+            self.tuning.config().movement_energy_cost,
+            &mut self.rng,
+        );
+
+        match step {
+            WaypointStep::Moved | WaypointStep::Blocked => self.waypoint = Some(waypoint),
+            WaypointStep::Reached => {
+                info!(
+                    target: &self.log_target,
+                    "Robot {}: Reached waypoint {:?}.",
+                    self.state.id, waypoint.target
+                );
+            }
+            WaypointStep::GaveUp => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot {}: Giving up on unreachable waypoint {:?}.",
+                    self.state.id, waypoint.target
+                );
+            }
+        }
+        if matches!(step, WaypointStep::Moved | WaypointStep::Reached) {
+            let _ = sender.send(RobotEvent::ExplorationData {
+                id: self.state.id,
+                x: self.state.x,
+                y: self.state.y,
+                is_obstacle: false,
             });
-        });
+            common::report_position(&self.state, sender);
+        }
+
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
+            self.tuning.config().primary_action_sleep_min_ms,
+            self.tuning.config().primary_action_sleep_max_ms,
+        ));
     }
 
     fn low_energy(&self) -> bool {
-        self.state.energy <= self.config.low_energy_threshold
+        self.state.energy <= self.tuning.config().low_energy_threshold
     }
 
     fn transition_to_returning(&mut self, visited: &mut HashSet<(usize, usize)>) {
         info!(
+            target: &self.log_target,
             "Robot {}: Low energy ({}), returning to station.",
             self.state.id, self.state.energy
         );
@@ -102,29 +405,36 @@ impl ExplorationRobot {
 
     fn explore_step(
         &mut self,
-        sender: &Sender<RobotEvent>,
-        map: &Arc<RwLock<Map>>,
+        sender: &EventSender,
+        map: &SharedMap,
         visited: &mut HashSet<(usize, usize)>,
     ) -> Result<(), String> {
-        let map_read_guard = map
-            .read()
-            .map_err(|e| format!("Map lock poisoned: {}", e))?;
+        map.observe_area(&mut self.knowledge, (self.state.x, self.state.y));
+        let map_read_guard = map.read();
         let map_read = &*map_read_guard;
 
-        self.observe_surroundings(map_read);
-
-        let direction = movement::smart_direction(
+        let smart = movement::smart_direction(
             self.state.x,
             self.state.y,
             &self.knowledge,
             visited,
             map_read,
-        )
-        .unwrap_or_else(movement::Direction::random);
+            &mut self.rng,
+        );
+        let direction = smart.unwrap_or_else(|| movement::Direction::random(&mut self.rng));
 
         let (new_x, new_y) =
             movement::next_position(self.state.x, self.state.y, &direction, map_read);
 
+        self.trace.record(
+            sender.clock().now(),
+            &self.state,
+            "explore",
+            || match smart {
+                Some(_) => format!("{:?}, to a known open neighbor, unvisited first", direction),
+                None => format!("{:?} at random, no known open neighbor", direction),
+            },
+        );
         let moved = self.try_move(new_x, new_y, visited, map_read);
 
         let is_obstacle = map_read.is_obstacle(self.state.x, self.state.y);
@@ -140,26 +450,17 @@ impl ExplorationRobot {
             sender
                 .send(event)
                 .map_err(|e| format!("Failed to send ExplorationData: {}", e))?;
+            common::report_position(&self.state, sender);
         }
 
-        thread::sleep(config::random_sleep_duration(
-            self.config.primary_action_sleep_min_ms,
-            self.config.primary_action_sleep_max_ms,
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
+            self.tuning.config().primary_action_sleep_min_ms,
+            self.tuning.config().primary_action_sleep_max_ms,
         ));
         Ok(())
     }
 
-    fn observe_surroundings(&mut self, map: &Map) {
-        let (x, y) = (self.state.x, self.state.y);
-        self.knowledge.observe_and_update(x, y, map);
-        for dir in Direction::all().iter() {
-            let (nx, ny) = movement::next_position(x, y, dir, map);
-            if (nx, ny) != (x, y) {
-                self.knowledge.observe_and_update(nx, ny, map);
-            }
-        }
-    }
-
     fn try_move(
         &mut self,
         new_x: usize,
@@ -168,12 +469,14 @@ impl ExplorationRobot {
         map: &Map,
     ) -> bool {
         if movement::is_valid_move(new_x, new_y, map)
-            && !matches!(self.knowledge.get_tile(new_x, new_y), TileInfo::Obstacle)
+            && !self
+                .knowledge
+                .blocks_move((self.state.x, self.state.y), (new_x, new_y))
         {
-            self.state.x = new_x;
-            self.state.y = new_y;
+            self.state.move_to(new_x, new_y, map);
             visited.insert((new_x, new_y));
-            self.state.use_energy(self.config.movement_energy_cost);
+            self.state
+                .use_energy(self.tuning.config().movement_energy_cost);
             true
         } else {
             false
@@ -182,8 +485,8 @@ impl ExplorationRobot {
 
     fn handle_returning_to_station(
         &mut self,
-        sender: &Sender<RobotEvent>,
-        map: &Arc<RwLock<Map>>,
+        sender: &EventSender,
+        map: &SharedMap,
         station_coords: (usize, usize),
         visited: &mut HashSet<(usize, usize)>,
     ) -> bool {
@@ -193,174 +496,208 @@ impl ExplorationRobot {
             return true;
         }
 
-        let map_read_guard = match map.read() {
-            Ok(g) => g,
-            Err(p) => {
-                error!("Robot: {} Map read poisoned! {}", self.state.id, p);
-                return true;
-            }
-        };
+        let map_read_guard = map.read();
         let map_read = &*map_read_guard;
-        let direction = common::move_towards_target(
+        let direction = common::direction_home(
             self.state.x,
             self.state.y,
-            station_x,
-            station_y,
             &self.knowledge,
             map_read,
+            &mut self.rng,
         );
         let (new_x, new_y) =
             movement::next_position(self.state.x, self.state.y, &direction, map_read);
 
+        self.trace
+            .record(sender.clock().now(), &self.state, "head_home", || {
+                format!("{:?} toward the station at {:?}", direction, station_coords)
+            });
         let mut moved = false;
         if movement::is_valid_move(new_x, new_y, map_read)
-            && !matches!(self.knowledge.get_tile(new_x, new_y), TileInfo::Obstacle)
+            && !self
+                .knowledge
+                .blocks_move((self.state.x, self.state.y), (new_x, new_y))
         {
-            self.state.x = new_x;
-            self.state.y = new_y;
+            self.state.move_to(new_x, new_y, map_read);
             moved = true;
         }
         if !moved {
+            self.trace
+                .record(sender.clock().now(), &self.state, "detour", || {
+                    format!("{:?} is blocked, trying random steps", direction)
+                });
             for _ in 0..4 {
-                let rd = movement::Direction::random();
+                let rd = movement::Direction::random(&mut self.rng);
                 let (rx, ry) = movement::next_position(self.state.x, self.state.y, &rd, map_read);
                 if movement::is_valid_move(rx, ry, map_read)
-                    && !matches!(self.knowledge.get_tile(rx, ry), TileInfo::Obstacle)
+                    && !self
+                        .knowledge
+                        .blocks_move((self.state.x, self.state.y), (rx, ry))
                 {
-                    self.state.x = rx;
-                    self.state.y = ry;
+                    self.state.move_to(rx, ry, map_read);
                     moved = true;
                     break;
                 }
             }
         }
-        if !moved {
+        if moved {
+            common::report_position(&self.state, sender);
+        } else {
             debug!(
+                target: &self.log_target,
                 "Robot: {} Path to station blocked @ {:?}.",
-                self.state.id,
-                (self.state.x, self.state.y)
+                self.state.id, (self.state.x, self.state.y)
             );
         }
         drop(map_read_guard);
         debug!(
+            target: &self.log_target,
             "Robot: {} Returning @ {:?}, Energy: {}",
-            self.state.id,
-            (self.state.x, self.state.y),
-            self.state.energy
+            self.state.id, (self.state.x, self.state.y), self.state.energy
         );
-        thread::sleep(config::random_sleep_duration(
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
             config::RETURN_SLEEP_MIN_MS,
             config::RETURN_SLEEP_MAX_MS,
         ));
         true
     }
 
-    fn arrive_at_station(
-        &mut self,
-        sender: &Sender<RobotEvent>,
-        visited: &mut HashSet<(usize, usize)>,
-    ) {
-        info!("Robot: {} Arrived station.", self.state.id);
+    fn arrive_at_station(&mut self, sender: &EventSender, visited: &mut HashSet<(usize, usize)>) {
+        info!(target: &self.log_target, "Robot: {} Arrived station.", self.state.id);
         self.state.status = RobotStatus::AtStation;
-        let k_clone = self.knowledge.clone();
-        let ev = RobotEvent::ArrivedAtStation {
-            id: self.state.id,
-            knowledge: k_clone,
-        };
-        if let Err(e) = sender.send(ev) {
-            error!("Robot: {} Failed send Arrived: {}", self.state.id, e);
-            return;
-        }
-        info!("Robot: {} Waiting MergeComplete...", self.state.id);
-
-        match self
-            .merge_complete_receiver
-            .recv_timeout(config::MERGE_TIMEOUT)
-        {
+        common::discard_stale_merges(&self.merge_complete_receiver, &self.inbox, self.state.id);
+        match common::report_dock(
+            sender,
+            &*self.clock,
+            &self.merge_complete_receiver,
+            &self.inbox,
+            self.state.id,
+            &self.knowledge,
+        ) {
             Ok(RobotEvent::MergeComplete {
-                merged_knowledge, ..
+                merged_knowledge,
+                brief,
+                ..
             }) => {
-                info!("Robot: {} MergeComplete OK.", self.state.id);
+                info!(target: &self.log_target, "Robot: {} MergeComplete OK.", self.state.id);
                 self.knowledge = merged_knowledge;
-                self.state.energy = self.state.max_energy;
-                self.state.status = RobotStatus::Exploring;
+                let resume = self.status_after_dock();
+                self.state.status = common::recharge_at_dock(&mut self.state, &brief, resume);
                 visited.clear();
-                info!("Robot: {} Resuming exploration.", self.state.id);
+                for assignment in common::take_brief(&mut self.knowledge, *brief) {
+                    if let Err(reason) = self.carry_out(sender, visited, assignment) {
+                        warn!(
+                            target: &self.log_target,
+                            "Robot: {} Assignment dropped: {}",
+                            self.state.id, reason
+                        );
+                    }
+                }
+                if self.parked {
+                    info!(target: &self.log_target, "Robot: {} Parked at station.", self.state.id);
+                } else {
+                    info!(
+                        target: &self.log_target,
+                        "Robot: {} Resuming exploration.",
+                        self.state.id
+                    );
+                }
             }
             Ok(o) => {
-                warn!("Robot: {} Unexpected event: {:?}", self.state.id, o);
-                self.state.status = RobotStatus::Exploring;
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} Unexpected event: {:?}",
+                    self.state.id, o
+                );
+                self.state.status = self.status_after_dock();
             }
             Err(RecvTimeoutError::Timeout) => {
-                warn!("Robot: {} Merge Timeout.", self.state.id);
-                self.state.status = RobotStatus::Exploring;
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} No merge, resuming with own knowledge.",
+                    self.state.id
+                );
+                self.state.status = self.status_after_dock();
             }
             Err(RecvTimeoutError::Disconnected) => {
-                error!("Robot: {} Merge channel disconnected.", self.state.id);
+                error!(
+                    target: &self.log_target,
+                    "Robot: {} Merge channel disconnected.",
+                    self.state.id
+                );
             }
         }
     }
 }
 
-// NOT WORKING - FIX TODO
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use crate::robot::core::state::{RobotState, RobotStatus};
-//     use crate::communication::channels::{RobotEvent, create_channel, ResourceType};
-//     use crate::map::noise::Map;
-//     use std::sync::{Arc, RwLock};
-//     use std::thread;
-//     use std::time::Duration;
-
-//     #[test]
-//     fn exploration_robot_finds_and_reports_resource_then_returns_to_station() {
-//         let width = 7;
-//         let height = 7;
-//         let (tx, rx) = create_channel();
-//         let mut map = Map::new(width, height, 42);
-//         map.set_walkable(0, 0);
-//         map.set_walkable(1, 0);
-//         map.add_resource(1, 0, ResourceType::Minerals, 100);
-//         let map = Arc::new(RwLock::new(map));
-
-//         let robot_state = RobotState::new(1, 0, 0, RobotStatus::Exploring, 10);
-//         let (_merge_tx, merge_rx) = create_channel();
-//         let robot = ExplorationRobot::new(robot_state, width, height, merge_rx);
-
-//         let tx_clone = tx.clone();
-//         let map_clone = Arc::clone(&map);
-//         thread::spawn(move || {
-//             robot.start(tx_clone, map_clone);
-//         });
-
-//         let mut found_resource = false;
-//         let mut returned_to_station = false;
-
-//         let start = std::time::Instant::now();
-//         while start.elapsed() < Duration::from_secs(10) {
-//             if let Ok(event) = rx.recv_timeout(Duration::from_millis(200)) {
-//                 match event {
-//                     RobotEvent::ExplorationData { x, y, .. } if (x, y) == (1, 0) => {
-//                         found_resource = true;
-//                     }
-//                     RobotEvent::ArrivedAtStation { id, .. } if id == 1 => {
-//                         returned_to_station = true;
-//                         let _ = tx.send(RobotEvent::MergeComplete {
-//                             id,
-//                             merged_knowledge: RobotKnowledge::new(width, height),
-//                         });
-//                     }
-//                     RobotEvent::Shutdown { .. } => break,
-//                     _ => {}
-//                 }
-//             }
-//             if found_resource && returned_to_station {
-//                 break;
-//             }
-//         }
-
-//         assert!(found_resource, "Robot did not find the resource");
-//         assert!(returned_to_station, "Robot did not return to station");
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::communication::channels::{
+        create_channel, create_command_channel, EventSource, ResourceType,
+    };
+    use crate::robot::core::knowledge::TileInfo;
+    use crate::robot::utils::clock::{Clock, SimulatedClock};
+    use std::sync::{mpsc, Arc};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_robot_finds_a_resource_then_returns_to_station() {
+        let (width, height) = (7, 7);
+        let mut map = Map::new(width, height, 42);
+        for y in 0..height {
+            for x in 0..width {
+                map.set_walkable(x, y);
+            }
+        }
+        map.add_resource(1, 0, ResourceType::MINERALS, 100);
+        let map = SharedMap::new(map);
+
+        let (sender, events) = create_channel();
+        let (merge_sender, merge_receiver) = mpsc::channel();
+        let (command_sender, command_receiver) = create_command_channel();
+        let state = RobotState::new(1, 0, 0, RobotStatus::Exploring, 30);
+        let clock = Arc::new(SimulatedClock::default());
+        let start = clock.now();
+        let robot = ExplorationRobot::new(
+            state,
+            width,
+            height,
+            merge_receiver,
+            command_receiver,
+            RobotRuntime {
+                clock: clock.clone(),
+                ..RobotRuntime::default()
+            },
+        );
+        let handle = robot.start(sender.with_source(EventSource::Robot(1)), map);
+
+        // Ten moves at 300 ms or more each, then the way back, all on the simulated clock
+        let started = Instant::now();
+        let knowledge = loop {
+            match events.recv_timeout(Duration::from_secs(5)).unwrap().event {
+                RobotEvent::ArrivedAtStation { knowledge, .. } => break knowledge,
+                RobotEvent::Shutdown { reason, .. } => panic!("Robot stopped: {}", reason),
+                _ => {}
+            }
+        };
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(clock.now() - start >= chrono::Duration::seconds(3));
+        assert_eq!(
+            knowledge.get_tile(1, 0),
+            &TileInfo::Resource(ResourceType::MINERALS, 100)
+        );
+
+        merge_sender
+            .send(RobotEvent::MergeComplete {
+                id: 1,
+                merged_knowledge: knowledge,
+                brief: Box::default(),
+            })
+            .unwrap();
+        drop(merge_sender);
+        drop(command_sender);
+        handle.join().unwrap();
+    }
+}
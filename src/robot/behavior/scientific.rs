@@ -1,17 +1,32 @@
+use chrono::{DateTime, Utc};
 use log::{debug, error, info, warn};
 use std::collections::HashSet;
-use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
-use std::sync::{Arc, RwLock};
-use std::thread;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::Duration;
 
-use crate::communication::channels::{ResourceType, RobotEvent};
+use crate::communication::channels::{
+    CommandEnvelope, EventSender, ResourceType, RobotCommand, RobotEvent, ShutdownReason,
+};
+use crate::logging;
 use crate::map::noise::Map;
-use crate::robot::core::movement::Direction;
+use crate::map::resources::ResourceCatalog;
+use crate::map::shared::SharedMap;
+use crate::profiler;
 use crate::robot::core::state::RobotStatus;
+use crate::robot::core::waypoint::{Waypoint, WaypointStep};
 
 use crate::robot::core::knowledge::{RobotKnowledge, TileInfo};
 use crate::robot::core::movement;
+use crate::robot::core::science_score::{self, ScienceCandidate, ScienceTargeting, SharedScorer};
+use crate::robot::launcher::RobotRuntime;
+use crate::robot::registry::RobotType;
+use crate::robot::utils::clock::{self, SharedClock};
+use crate::robot::utils::inbox::InboxGauge;
+use crate::robot::utils::preset::RobotTuning;
+use crate::robot::utils::rng::RobotRng;
+use crate::robot::utils::trace::DecisionTrace;
 use crate::robot::utils::{common, config};
 use crate::robot::RobotState;
 
@@ -22,12 +37,49 @@ pub struct Module {
     pub energy_cost: u32, // Passive energy cost per move
 }
 
+/// A joint site the robot stands on, waiting for a collector's sample
+#[derive(Debug, Clone, Copy)]
+struct Rendezvous {
+    site: (usize, usize),
+    since: DateTime<Utc>,
+    sample_ready: bool,
+}
+
+/// Whether the joint site the robot stands on can be analyzed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SampleWait {
+    Ready,
+    Waiting,
+    /// Analyzed or given up on since the last dock
+    Skip,
+}
+
 pub struct ScientificRobot {
     state: RobotState,
+    /// Logs under `robot::<id>`, so one robot's messages can be picked out
+    log_target: String,
     modules: Vec<Module>,
     knowledge: RobotKnowledge,
     merge_complete_receiver: Receiver<RobotEvent>,
-    config: config::RobotTypeConfig,
+    command_receiver: Receiver<CommandEnvelope>,
+    waypoint: Option<Waypoint>,
+    rendezvous: Option<Rendezvous>,
+    /// Joint sites analyzed or given up on since the last dock
+    done_sites: HashSet<(usize, usize)>,
+    /// Ranks the known science deposits to head for
+    scorer: SharedScorer,
+    /// Stops the thread after the next visit to the station
+    retiring: bool,
+    /// The station dropped its end of the command channel, the thread stops right away
+    station_lost: bool,
+    /// Own thresholds, sleeps and costs, as the swarm preset plays them
+    tuning: RobotTuning,
+    clock: SharedClock,
+    inbox: InboxGauge,
+    trace: DecisionTrace,
+    rng: RobotRng,
+    /// Resource types of the run, by which cargo is weighed and deposits told apart
+    resources: Arc<ResourceCatalog>,
 }
 
 impl ScientificRobot {
@@ -36,18 +88,34 @@ impl ScientificRobot {
         map_width: usize,
         map_height: usize,
         merge_complete_receiver: Receiver<RobotEvent>,
+        command_receiver: Receiver<CommandEnvelope>,
+        runtime: RobotRuntime,
     ) -> Self {
         Self {
-            knowledge: RobotKnowledge::new(map_width, map_height),
+            knowledge: runtime.knowledge(map_width, map_height),
+            log_target: logging::robot_target(initial_state.id),
             state: initial_state,
             modules: Vec::new(),
             merge_complete_receiver,
-            config: config::SCIENTIFIC_CONFIG.clone(),
+            command_receiver,
+            waypoint: None,
+            rendezvous: None,
+            done_sites: HashSet::new(),
+            scorer: ScienceTargeting::default().scorer(),
+            retiring: false,
+            station_lost: false,
+            tuning: runtime.tuning(RobotType::Scientific, &config::SCIENTIFIC_CONFIG),
+            clock: runtime.clock,
+            inbox: runtime.gauge,
+            trace: runtime.trace,
+            rng: runtime.rng,
+            resources: runtime.resources,
         }
     }
 
     pub fn add_module(&mut self, name: &str, science_bonus: u32, energy_cost: u32) {
         info!(
+            target: &self.log_target,
             "Robot {}: Adding module '{}' (Bonus: {}, Cost: {})",
             self.state.id, name, science_bonus, energy_cost
         );
@@ -58,54 +126,118 @@ impl ScientificRobot {
         });
     }
 
-    fn analyze_science_point(&self, base_value: u32) -> u32 {
+    /// Science yielded analyzing a deposit of `base_value` units of `resource_type`
+    fn analyze_science_point(&self, resource_type: ResourceType, base_value: u32) -> u32 {
         let module_bonus: u32 = self.modules.iter().map(|module| module.science_bonus).sum();
-        base_value.saturating_add(module_bonus)
+        base_value
+            .saturating_mul(self.resources.value(resource_type))
+            .saturating_add(module_bonus)
     }
 
     fn get_module_passive_energy_cost(&self) -> u32 {
         self.modules.iter().map(|m| m.energy_cost).sum()
     }
 
-    fn find_nearest_known_science_point(&self) -> Option<(usize, usize)> {
-        self.knowledge
-            .map
-            .iter()
-            .filter_map(|(&(x, y), tile_info)| {
-                if matches!(
-                    tile_info,
-                    TileInfo::Resource(ResourceType::SciencePoints, _)
-                ) {
-                    let dist_sq = (x as isize - self.state.x as isize).pow(2)
-                        + (y as isize - self.state.y as isize).pow(2);
-                    Some(((x, y), dist_sq))
-                } else {
-                    None
-                }
+    /// The known science deposit the scorer rates highest, with its score
+    fn find_best_known_science_point(&self) -> Option<(ScienceCandidate, f64)> {
+        let passive_cost = self.get_module_passive_energy_cost();
+        let move_cost = self
+            .tuning
+            .config()
+            .movement_energy_cost
+            .saturating_add(passive_cost);
+        let analysis_cost = self
+            .tuning
+            .config()
+            .action_energy_cost
+            .unwrap_or(0)
+            .saturating_add(passive_cost);
+        let candidates = self.knowledge.tiles().filter_map(|((x, y), tile_info)| {
+            let TileInfo::Resource(resource_type, base_value) = *tile_info else {
+                return None;
+            };
+            if self.resources.is_consumable(resource_type) {
+                return None;
+            }
+            if self.knowledge.is_excluded(x, y)
+                || self.knowledge.is_claimed(x, y)
+                || self.done_sites.contains(&(x, y))
+            {
+                return None;
+            }
+            let moves = x.abs_diff(self.state.x) + y.abs_diff(self.state.y);
+            Some(ScienceCandidate {
+                tile: (x, y),
+                value: self.analyze_science_point(resource_type, base_value),
+                moves,
+                energy_cost: move_cost
+                    .saturating_mul(u32::try_from(moves).unwrap_or(u32::MAX))
+                    .saturating_add(analysis_cost),
+                spare_energy: self
+                    .state
+                    .energy
+                    .saturating_sub(self.tuning.config().low_energy_threshold),
             })
-            .min_by_key(|&(_, dist_sq)| dist_sq)
-            .map(|(coords, _)| coords)
+        });
+        science_score::best(candidates, &*self.scorer)
     }
 
-    pub fn start(mut self, sender: Sender<RobotEvent>, map: Arc<RwLock<Map>>) {
+    /// Makes the robot pick the science deposits to head for by `scorer`
+    pub fn set_science_scorer(&mut self, scorer: SharedScorer) {
+        self.scorer = scorer;
+    }
+
+    pub fn start(mut self, sender: EventSender, map: SharedMap) -> JoinHandle<()> {
         let robot_id = self.state.id;
         let station_coords = self.knowledge.get_station_coords();
-        let config = self.config.clone();
+        let config = self.tuning.config().clone();
         let analysis_action_cost = config
             .action_energy_cost
             .expect("Scientific config must have an action cost");
 
-        thread::spawn(move || {
+        let clock = self.clock.clone();
+        clock::spawn_robot(&clock, robot_id, move || {
             let mut visited_in_cycle: HashSet<(usize, usize)> = HashSet::new();
-            info!("Robot {}: Starting scientific analysis thread.", robot_id);
+            info!(
+                target: &self.log_target,
+                "Robot {}: Starting scientific analysis thread.",
+                robot_id
+            );
 
             loop {
+                let _step = profiler::robot_step(robot_id);
+                self.process_commands(&sender, &mut visited_in_cycle);
+                common::suffer_hostile_contact(&mut self.state, &map);
+                common::soak_up_flare(&mut self.state, &sender);
+                self.knowledge.expire_sightings(sender.clock().now());
+                self.knowledge
+                    .forget_stale_terrain(sender.clock().now(), config::terrain_memory());
+                if self.retiring && self.state.status == RobotStatus::AtStation {
+                    info!(target: &self.log_target, "Robot {}: Retired at station.", robot_id);
+                    break;
+                }
+                if self.station_lost {
+                    info!(
+                        target: &self.log_target,
+                        "Robot {}: Station closed, stopping.",
+                        robot_id
+                    );
+                    break;
+                }
                 let passive_module_cost = self.get_module_passive_energy_cost();
 
                 match self.state.status {
                     RobotStatus::Analyzing => {
                         if self.state.energy <= config.low_energy_threshold {
+                            self.trace
+                                .record(sender.clock().now(), &self.state, "return", || {
+                                    format!(
+                                        "energy {} at or below threshold {}",
+                                        self.state.energy, config.low_energy_threshold
+                                    )
+                                });
                             info!(
+                                target: &self.log_target,
                                 "Robot: {} Low energy ({}), returning.",
                                 robot_id, self.state.energy
                             );
@@ -114,25 +246,35 @@ impl ScientificRobot {
                             continue;
                         }
 
-                        let map_read_guard = match map.read() {
-                            Ok(g) => g,
-                            Err(p) => {
-                                error!("Robot: {} Map read poisoned! {}", robot_id, p);
-                                break;
-                            }
-                        };
-                        let map_read = &*map_read_guard;
+                        if let Some(waypoint) = &self.waypoint {
+                            self.trace.record(
+                                sender.clock().now(),
+                                &self.state,
+                                "follow_waypoint",
+                                || format!("ordered to {:?}", waypoint.target),
+                            );
+                            self.follow_waypoint(&sender, &map, passive_module_cost, &config);
+                            continue;
+                        }
 
-                        self.update_knowledge_around(&map_read);
+                        map.observe_area(&mut self.knowledge, (self.state.x, self.state.y));
+                        let map_read_guard = map.read();
+                        let map_read = &*map_read_guard;
 
                         if self.try_analyze_current_tile(
                             &sender,
+                            map_read,
                             analysis_action_cost,
                             passive_module_cost,
                         ) {
                             // Analysis done, sleep and continue
                             drop(map_read_guard);
-                            thread::sleep(config::random_sleep_duration(
+                            self.trace
+                                .record(sender.clock().now(), &self.state, "analyze", || {
+                                    "science deposit under the robot".to_string()
+                                });
+                            self.clock.sleep(config::random_sleep_duration(
+                                &mut self.rng,
                                 config.primary_action_sleep_min_ms,
                                 config.primary_action_sleep_max_ms,
                             ));
@@ -141,7 +283,7 @@ impl ScientificRobot {
 
                         if !self.try_move_towards_science(
                             &sender,
-                            &map_read,
+                            map_read,
                             &mut visited_in_cycle,
                             passive_module_cost,
                             &config,
@@ -152,7 +294,8 @@ impl ScientificRobot {
                         }
 
                         drop(map_read_guard);
-                        thread::sleep(config::random_sleep_duration(
+                        self.clock.sleep(config::random_sleep_duration(
+                            &mut self.rng,
                             config.primary_action_sleep_min_ms,
                             config.primary_action_sleep_max_ms,
                         ));
@@ -163,6 +306,7 @@ impl ScientificRobot {
                             &sender,
                             &map,
                             station_coords,
+                            &mut visited_in_cycle,
                             // passive_module_cost,
                             // &config,
                         ) {
@@ -171,73 +315,302 @@ impl ScientificRobot {
                     }
 
                     RobotStatus::AtStation => {
-                        thread::sleep(Duration::from_millis(100));
+                        self.clock.sleep(config::scaled(Duration::from_millis(
+                            config::AT_STATION_SLEEP_MS,
+                        )));
                     }
 
                     _ => {
                         error!(
+                            target: &self.log_target,
                             "Robot: {} In unhandled state {:?}. Defaulting to Analyzing.",
                             robot_id, self.state.status
                         );
                         self.state.status = RobotStatus::Analyzing;
-                        thread::sleep(config::UNHANDLED_STATE_SLEEP);
+                        self.clock.sleep(config::UNHANDLED_STATE_SLEEP);
                     }
                 }
             }
 
-            info!("Robot {}: Thread shutting down", robot_id);
+            info!(target: &self.log_target, "Robot {}: Thread shutting down", robot_id);
             if sender
                 .send(RobotEvent::Shutdown {
                     id: robot_id,
-                    reason: "Thread loop exited".to_string(),
+                    reason: if self.retiring {
+                        ShutdownReason::Retired
+                    } else if self.station_lost {
+                        ShutdownReason::StationClosed
+                    } else {
+                        ShutdownReason::LoopExited
+                    },
                 })
                 .is_err()
+                && !self.station_lost
             {
-                error!("Robot {}: Failed send final shutdown", robot_id);
+                error!(target: &self.log_target, "Robot {}: Failed send final shutdown", robot_id);
             }
-        });
+        })
+    }
+
+    fn process_commands(
+        &mut self,
+        sender: &EventSender,
+        visited_in_cycle: &mut HashSet<(usize, usize)>,
+    ) {
+        while let Some(CommandEnvelope {
+            id: command_id,
+            command,
+        }) = common::next_command(&self.command_receiver, &self.inbox, &mut self.station_lost)
+        {
+            let outcome = self.carry_out(sender, visited_in_cycle, command);
+            common::acknowledge(sender, self.state.id, command_id, outcome);
+        }
+    }
+
+    /// Carries out an order of the station, or says why it cannot
+    fn carry_out(
+        &mut self,
+        sender: &EventSender,
+        visited_in_cycle: &mut HashSet<(usize, usize)>,
+        command: RobotCommand,
+    ) -> Result<(), String> {
+        let mut outcome = Ok(());
+        match command {
+            RobotCommand::ReturnToStation => {
+                self.waypoint = None;
+                self.rendezvous = None;
+                if self.state.status == RobotStatus::Analyzing {
+                    info!(
+                        target: &self.log_target,
+                        "Robot: {} Recalled to station.",
+                        self.state.id
+                    );
+                    self.state.status = RobotStatus::ReturningToStation;
+                    visited_in_cycle.clear();
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            RobotCommand::MoveTo { x, y } if self.knowledge.is_excluded(x, y) => {
+                outcome = Err(format!("({}, {}) lies in a no-go zone", x, y));
+            }
+            RobotCommand::MoveTo { x, y } => {
+                debug!(
+                    target: &self.log_target,
+                    "Robot: {} New waypoint ({}, {}).",
+                    self.state.id, x, y
+                );
+                self.waypoint = Some(Waypoint::new(x, y));
+            }
+            RobotCommand::SampleReady { x, y } => match self.rendezvous.as_mut() {
+                Some(rendezvous) if rendezvous.site == (x, y) => rendezvous.sample_ready = true,
+                _ => outcome = Err(format!("No sample awaited on ({}, {})", x, y)),
+            },
+            RobotCommand::AvoidTiles(tiles) => self.knowledge.set_exclusions(tiles),
+            RobotCommand::Wear { percent } => self.state.wear_percent = percent,
+            RobotCommand::SetPreset(preset) => {
+                info!(
+                    target: &self.log_target,
+                    "Robot {}: Switching to the {} preset.",
+                    self.state.id, preset.label()
+                );
+                self.tuning.set_preset(preset);
+            }
+            RobotCommand::Service { ticks } => {
+                common::undergo_service(&mut self.state, sender, &*self.clock, ticks)
+            }
+            RobotCommand::Charge { ticks } => {
+                common::charge(&mut self.state, sender, &*self.clock, ticks);
+                if self.state.status == RobotStatus::AtStation {
+                    self.state.status = self.status_after_dock();
+                }
+            }
+            RobotCommand::RefitBattery => {
+                info!(target: &self.log_target, "Robot {}: Battery refitted.", self.state.id);
+                self.state.refit_battery();
+            }
+            RobotCommand::MeteorImpact {
+                x,
+                y,
+                radius,
+                damage,
+            } => common::suffer_meteor_impact(
+                &mut self.state,
+                &mut self.knowledge,
+                (x, y),
+                radius,
+                damage,
+            ),
+            RobotCommand::SolarFlare {
+                scramble_percent,
+                seed,
+            } => {
+                common::suffer_solar_flare(&self.state, &mut self.knowledge, scramble_percent, seed)
+            }
+            RobotCommand::Teleport { x, y } => common::teleport(&mut self.state, sender, (x, y)),
+            RobotCommand::SetEnergy(energy) => common::set_energy(&mut self.state, sender, energy),
+            RobotCommand::Retire => {
+                info!(target: &self.log_target, "Robot: {} Retiring.", self.state.id);
+                self.waypoint = None;
+                self.rendezvous = None;
+                self.retiring = true;
+                if self.state.status == RobotStatus::Analyzing {
+                    self.state.status = RobotStatus::ReturningToStation;
+                    visited_in_cycle.clear();
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            other => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} Ignoring unsupported command {:?}",
+                    self.state.id, other
+                );
+                outcome = Err(format!("Unsupported command {:?}", other));
+            }
+        }
+        outcome
     }
 
-    fn update_knowledge_around(&mut self, map: &Map) {
-        let (x, y) = (self.state.x, self.state.y);
-        self.knowledge.observe_and_update(x, y, map);
-        for dir in Direction::all().iter() {
-            let (nx, ny) = movement::next_position(x, y, dir, map);
-            if (nx, ny) != (x, y) {
-                self.knowledge.observe_and_update(nx, ny, map);
+    fn follow_waypoint(
+        &mut self,
+        sender: &EventSender,
+        map: &SharedMap,
+        passive_module_cost: u32,
+        config: &config::RobotTypeConfig,
+    ) {
+        let Some(mut waypoint) = self.waypoint.take() else {
+            return;
+        };
+        let move_total_cost = config
+            .movement_energy_cost
+            .saturating_add(passive_module_cost);
+        let step = waypoint.step(
+            &mut self.state,
+            &mut self.knowledge,
+            &map.read(),
+            move_total_cost,
+            &mut self.rng,
+        );
+
+        match step {
+            WaypointStep::Moved | WaypointStep::Blocked => self.waypoint = Some(waypoint),
+            WaypointStep::Reached => {
+                info!(
+                    target: &self.log_target,
+                    "Robot: {} Reached waypoint {:?}.",
+                    self.state.id, waypoint.target
+                );
+            }
+            WaypointStep::GaveUp => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} Giving up on unreachable waypoint {:?}.",
+                    self.state.id, waypoint.target
+                );
+            }
+        }
+        if matches!(step, WaypointStep::Moved | WaypointStep::Reached) {
+            common::report_position(&self.state, sender);
+        }
+
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
+            config.primary_action_sleep_min_ms,
+            config.primary_action_sleep_max_ms,
+        ));
+    }
+
+    /// Where the robot stands with the sample of the joint site it is on: asks the station
+    /// for a collector on arrival, then waits for the sample up to `config::RENDEZVOUS_TIMEOUT`
+    fn await_sample(&mut self, sender: &EventSender, site: (usize, usize)) -> SampleWait {
+        if self.done_sites.contains(&site) {
+            return SampleWait::Skip;
+        }
+        let now = self.clock.now();
+        match self.rendezvous {
+            Some(rendezvous) if rendezvous.site == site && rendezvous.sample_ready => {
+                SampleWait::Ready
+            }
+            Some(rendezvous) if rendezvous.site == site => {
+                let waited = (now - rendezvous.since).to_std().unwrap_or_default();
+                if waited < config::scaled(config::RENDEZVOUS_TIMEOUT) {
+                    return SampleWait::Waiting;
+                }
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} No sample came to {:?}, moving on.",
+                    self.state.id, site
+                );
+                self.rendezvous = None;
+                self.done_sites.insert(site);
+                SampleWait::Skip
+            }
+            _ => {
+                info!(
+                    target: &self.log_target,
+                    "Robot: {} On joint site {:?}, calling for a collector.",
+                    self.state.id, site
+                );
+                let _ = sender.send(RobotEvent::RendezvousRequest {
+                    id: self.state.id,
+                    x: site.0,
+                    y: site.1,
+                });
+                self.rendezvous = Some(Rendezvous {
+                    site,
+                    since: now,
+                    sample_ready: false,
+                });
+                SampleWait::Waiting
             }
         }
     }
 
     fn try_analyze_current_tile(
         &mut self,
-        sender: &Sender<RobotEvent>,
+        sender: &EventSender,
+        map: &Map,
         analysis_action_cost: u32,
         passive_module_cost: u32,
     ) -> bool {
         let (current_x, current_y) = (self.state.x, self.state.y);
-        if let TileInfo::Resource(ResourceType::SciencePoints, base_amount) =
-            self.knowledge.get_tile(current_x, current_y)
+        if let TileInfo::Resource(resource_type, base_amount) =
+            *self.knowledge.get_tile(current_x, current_y)
         {
-            if *base_amount > 0 {
+            if base_amount > 0 && !self.resources.is_consumable(resource_type) {
+                let joint = map.is_joint_site(current_x, current_y);
+                if joint {
+                    match self.await_sample(sender, (current_x, current_y)) {
+                        SampleWait::Ready => {}
+                        SampleWait::Waiting => return true,
+                        SampleWait::Skip => return false,
+                    }
+                }
                 let analysis_total_cost = analysis_action_cost.saturating_add(passive_module_cost);
                 if self.state.use_energy(analysis_total_cost) {
-                    let science_value = self.analyze_science_point(*base_amount);
+                    let mut science_value = self.analyze_science_point(resource_type, base_amount);
+                    if joint {
+                        science_value =
+                            science_value.saturating_mul(config::JOINT_SCIENCE_MULTIPLIER);
+                        self.rendezvous = None;
+                        self.done_sites.insert((current_x, current_y));
+                    }
                     info!(
+                        target: &self.log_target,
                         "Robot: {} Analyzed science point at {:?}, value: {}",
-                        self.state.id,
-                        (current_x, current_y),
-                        science_value
+                        self.state.id, (current_x, current_y), science_value
                     );
-                    if !self
-                        .state
-                        .collect_resource(ResourceType::SciencePoints, science_value)
-                    {
+                    if !self.state.collect_resource(
+                        ResourceType::SCIENCE_POINTS,
+                        science_value,
+                        &self.resources,
+                    ) {
                         warn!(
+                            target: &self.log_target,
                             "Robot: {} Failed to record science value (internal capacity?), value: {}",
                             self.state.id, science_value
                         );
-                        if self.state.is_full() {
+                        if self.state.is_full(&self.resources) {
                             self.state.status = RobotStatus::ReturningToStation;
                         }
                     }
@@ -245,7 +618,7 @@ impl ScientificRobot {
                         id: self.state.id,
                         x: current_x,
                         y: current_y,
-                        resource_type: ResourceType::SciencePoints,
+                        resource_type: ResourceType::SCIENCE_POINTS,
                         amount: science_value,
                         modules: self.modules.iter().map(|m| m.name.clone()).collect(),
                     };
@@ -253,10 +626,9 @@ impl ScientificRobot {
                     return true;
                 } else {
                     warn!(
+                        target: &self.log_target,
                         "Robot: {} Not enough energy ({}) for analysis @ {:?}",
-                        self.state.id,
-                        self.state.energy,
-                        (current_x, current_y)
+                        self.state.id, self.state.energy, (current_x, current_y)
                     );
                 }
             }
@@ -266,7 +638,7 @@ impl ScientificRobot {
 
     fn try_move_towards_science(
         &mut self,
-        sender: &Sender<RobotEvent>,
+        sender: &EventSender,
         map: &Map,
         visited_in_cycle: &mut HashSet<(usize, usize)>,
         passive_module_cost: u32,
@@ -276,7 +648,15 @@ impl ScientificRobot {
             .movement_energy_cost
             .saturating_add(passive_module_cost);
         if !self.state.use_energy(move_total_cost) {
+            self.trace
+                .record(sender.clock().now(), &self.state, "return", || {
+                    format!(
+                        "energy {} short of the {} a move takes",
+                        self.state.energy, move_total_cost
+                    )
+                });
             warn!(
+                target: &self.log_target,
                 "Robot: {} Not enough energy ({}) to move. Returning.",
                 self.state.id, self.state.energy
             );
@@ -285,8 +665,17 @@ impl ScientificRobot {
             return false;
         }
 
-        let direction = if let Some(target_coords) = self.find_nearest_known_science_point() {
+        let direction = if let Some((target, score)) = self.find_best_known_science_point() {
+            let target_coords = target.tile;
+            self.trace
+                .record(sender.clock().now(), &self.state, "move_to_science", || {
+                    format!(
+                        "science deposit at {:?} worth {} for {} energy, scored {:.2}",
+                        target.tile, target.value, target.energy_cost, score
+                    )
+                });
             debug!(
+                target: &self.log_target,
                 "Robot: {} Moving towards known Science Point @ {:?}",
                 self.state.id, target_coords
             );
@@ -297,9 +686,15 @@ impl ScientificRobot {
                 target_coords.1,
                 &self.knowledge,
                 map,
+                &mut self.rng,
             )
         } else {
+            self.trace
+                .record(sender.clock().now(), &self.state, "explore", || {
+                    "no known science deposit worth heading for".to_string()
+                });
             debug!(
+                target: &self.log_target,
                 "Robot: {} No known Science Points. Exploring.",
                 self.state.id
             );
@@ -309,83 +704,110 @@ impl ScientificRobot {
                 &self.knowledge,
                 visited_in_cycle,
                 map,
+                &mut self.rng,
             )
-            .unwrap_or_else(movement::Direction::random)
+            .unwrap_or_else(|| movement::Direction::random(&mut self.rng))
         };
 
         let (new_x, new_y) = movement::next_position(self.state.x, self.state.y, &direction, map);
 
         if movement::is_valid_move(new_x, new_y, map)
-            && !matches!(self.knowledge.get_tile(new_x, new_y), TileInfo::Obstacle)
+            && !self
+                .knowledge
+                .blocks_move((self.state.x, self.state.y), (new_x, new_y))
         {
-            self.state.x = new_x;
-            self.state.y = new_y;
+            self.state.move_to(new_x, new_y, map);
             visited_in_cycle.insert((new_x, new_y));
-            let _ = sender.send(RobotEvent::ScienceData {
-                id: self.state.id,
-                x: self.state.x,
-                y: self.state.y,
-                resource_type: ResourceType::SciencePoints,
-                amount: 0,
-                modules: self.modules.iter().map(|m| m.name.clone()).collect(),
-            });
+            common::report_position(&self.state, sender);
             true
         } else {
             debug!(
+                target: &self.log_target,
                 "Robot: {} Move {:?} blocked or invalid.",
-                self.state.id,
-                (new_x, new_y)
+                self.state.id, (new_x, new_y)
             );
             false
         }
     }
 
+    /// Status after docking: retiring robots stay at the station
+    fn status_after_dock(&self) -> RobotStatus {
+        if self.retiring {
+            RobotStatus::AtStation
+        } else {
+            RobotStatus::Analyzing
+        }
+    }
+
     fn handle_returning_to_station(
         &mut self,
-        sender: &Sender<RobotEvent>,
-        map: &Arc<RwLock<Map>>,
+        sender: &EventSender,
+        map: &SharedMap,
         station_coords: (usize, usize),
+        visited_in_cycle: &mut HashSet<(usize, usize)>,
         // passive_module_cost: u32,
         // config: &config::RobotTypeConfig,
     ) -> bool {
         let (station_x, station_y) = station_coords;
         if self.state.x == station_x && self.state.y == station_y {
-            info!("Robot: {} Arrived at station", self.state.id);
+            info!(target: &self.log_target, "Robot: {} Arrived at station", self.state.id);
             self.state.status = RobotStatus::AtStation;
-            let k_clone = self.knowledge.clone();
-            let ev = RobotEvent::ArrivedAtStation {
-                id: self.state.id,
-                knowledge: k_clone,
-            };
-            let _ = sender.send(ev);
-            info!("Robot: {} Waiting MergeComplete...", self.state.id);
-
-            match self
-                .merge_complete_receiver
-                .recv_timeout(config::MERGE_TIMEOUT)
-            {
+            common::discard_stale_merges(&self.merge_complete_receiver, &self.inbox, self.state.id);
+            match common::report_dock(
+                sender,
+                &*self.clock,
+                &self.merge_complete_receiver,
+                &self.inbox,
+                self.state.id,
+                &self.knowledge,
+            ) {
                 Ok(RobotEvent::MergeComplete {
-                    merged_knowledge, ..
+                    merged_knowledge,
+                    brief,
+                    ..
                 }) => {
-                    info!("Robot: {} MergeComplete OK.", self.state.id);
+                    info!(target: &self.log_target, "Robot: {} MergeComplete OK.", self.state.id);
                     self.knowledge = merged_knowledge;
-                    self.state.energy = self.state.max_energy;
                     self.state
                         .collected_resources
-                        .remove(&ResourceType::SciencePoints);
-                    self.state.status = RobotStatus::Analyzing;
-                    info!("Robot: {} Resuming analysis.", self.state.id);
+                        .remove(&ResourceType::SCIENCE_POINTS);
+                    let resume = self.status_after_dock();
+                    self.state.status = common::recharge_at_dock(&mut self.state, &brief, resume);
+                    self.rendezvous = None;
+                    self.done_sites.clear();
+                    for assignment in common::take_brief(&mut self.knowledge, *brief) {
+                        if let Err(reason) = self.carry_out(sender, visited_in_cycle, assignment) {
+                            warn!(
+                                target: &self.log_target,
+                                "Robot: {} Assignment dropped: {}",
+                                self.state.id, reason
+                            );
+                        }
+                    }
+                    info!(target: &self.log_target, "Robot: {} Resuming analysis.", self.state.id);
                 }
                 Ok(o) => {
-                    warn!("Robot: {} Unexpected event: {:?}", self.state.id, o);
-                    self.state.status = RobotStatus::Analyzing;
+                    warn!(
+                        target: &self.log_target,
+                        "Robot: {} Unexpected event: {:?}",
+                        self.state.id, o
+                    );
+                    self.state.status = self.status_after_dock();
                 }
                 Err(RecvTimeoutError::Timeout) => {
-                    warn!("Robot: {} Merge Timeout.", self.state.id);
-                    self.state.status = RobotStatus::Analyzing;
+                    warn!(
+                        target: &self.log_target,
+                        "Robot: {} No merge, resuming with own knowledge.",
+                        self.state.id
+                    );
+                    self.state.status = self.status_after_dock();
                 }
                 Err(RecvTimeoutError::Disconnected) => {
-                    error!("Robot: {} Merge channel disconnected.", self.state.id);
+                    error!(
+                        target: &self.log_target,
+                        "Robot: {} Merge channel disconnected.",
+                        self.state.id
+                    );
                 }
             }
             return true;
@@ -401,57 +823,63 @@ impl ScientificRobot {
         //     return true;
         // }
 
-        let map_read_guard = match map.read() {
-            Ok(g) => g,
-            Err(p) => {
-                error!("Robot: {} Map read poisoned! {}", self.state.id, p);
-                return true;
-            }
-        };
+        let map_read_guard = map.read();
         let map_read = &*map_read_guard;
-        let direction = common::move_towards_target(
+        let direction = common::direction_home(
             self.state.x,
             self.state.y,
-            station_x,
-            station_y,
             &self.knowledge,
             map_read,
+            &mut self.rng,
         );
         let (new_x, new_y) =
             movement::next_position(self.state.x, self.state.y, &direction, map_read);
 
+        self.trace
+            .record(sender.clock().now(), &self.state, "head_home", || {
+                format!("{:?} toward the station at {:?}", direction, station_coords)
+            });
         let mut moved = false;
         if movement::is_valid_move(new_x, new_y, map_read)
-            && !matches!(self.knowledge.get_tile(new_x, new_y), TileInfo::Obstacle)
+            && !self
+                .knowledge
+                .blocks_move((self.state.x, self.state.y), (new_x, new_y))
         {
-            self.state.x = new_x;
-            self.state.y = new_y;
+            self.state.move_to(new_x, new_y, map_read);
             moved = true;
         }
         if !moved {
+            self.trace
+                .record(sender.clock().now(), &self.state, "detour", || {
+                    format!("{:?} is blocked, trying random steps", direction)
+                });
             for _ in 0..4 {
-                let rd = movement::Direction::random();
+                let rd = movement::Direction::random(&mut self.rng);
                 let (rx, ry) = movement::next_position(self.state.x, self.state.y, &rd, map_read);
                 if movement::is_valid_move(rx, ry, map_read)
-                    && !matches!(self.knowledge.get_tile(rx, ry), TileInfo::Obstacle)
+                    && !self
+                        .knowledge
+                        .blocks_move((self.state.x, self.state.y), (rx, ry))
                 {
-                    self.state.x = rx;
-                    self.state.y = ry;
+                    self.state.move_to(rx, ry, map_read);
                     moved = true;
                     break;
                 }
             }
         }
-        if !moved {
+        if moved {
+            common::report_position(&self.state, sender);
+        } else {
             debug!(
+                target: &self.log_target,
                 "Robot: {} Path to station blocked @ {:?}.",
-                self.state.id,
-                (self.state.x, self.state.y)
+                self.state.id, (self.state.x, self.state.y)
             );
         }
         drop(map_read_guard);
 
-        thread::sleep(config::random_sleep_duration(
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
             config::RETURN_SLEEP_MIN_MS,
             config::RETURN_SLEEP_MAX_MS,
         ));
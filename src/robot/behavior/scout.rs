@@ -0,0 +1,591 @@
+use log::{debug, error, info, warn};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::communication::channels::{
+    CommandEnvelope, EventSender, RobotCommand, RobotEvent, ShutdownReason,
+};
+use crate::logging;
+use crate::map::noise::Map;
+use crate::map::shared::SharedMap;
+use crate::profiler;
+use crate::robot::core::knowledge::{EntityKind, RobotKnowledge, Sighting};
+use crate::robot::core::movement::{self, Direction};
+use crate::robot::core::state::{RobotState, RobotStatus};
+use crate::robot::launcher::RobotRuntime;
+use crate::robot::registry::RobotType;
+use crate::robot::utils::clock::{self, SharedClock};
+use crate::robot::utils::common;
+use crate::robot::utils::config;
+use crate::robot::utils::inbox::InboxGauge;
+use crate::robot::utils::preset::RobotTuning;
+use crate::robot::utils::rng::RobotRng;
+use crate::robot::utils::trace::DecisionTrace;
+
+/// Shadows hostiles from a safe distance and reports where they are, so the station can
+/// warn the other robots
+pub struct ScoutRobot {
+    state: RobotState,
+    /// Logs under `robot::<id>`, so one robot's messages can be picked out
+    log_target: String,
+    knowledge: RobotKnowledge,
+    merge_complete_receiver: Receiver<RobotEvent>,
+    command_receiver: Receiver<CommandEnvelope>,
+    /// Direction kept while searching, until something blocks it
+    heading: Direction,
+    /// Hostile positions last reported to the station, and the tick they were reported at
+    reported: Vec<(usize, usize)>,
+    reported_tick: u64,
+    /// Stops the thread after the next visit to the station
+    retiring: bool,
+    /// The station dropped its end of the command channel, the thread stops right away
+    station_lost: bool,
+    /// Own thresholds, sleeps and costs, as the swarm preset plays them
+    tuning: RobotTuning,
+    clock: SharedClock,
+    inbox: InboxGauge,
+    trace: DecisionTrace,
+    rng: RobotRng,
+}
+
+impl ScoutRobot {
+    pub fn new(
+        initial_state: RobotState,
+        map_width: usize,
+        map_height: usize,
+        merge_complete_receiver: Receiver<RobotEvent>,
+        command_receiver: Receiver<CommandEnvelope>,
+        runtime: RobotRuntime,
+    ) -> Self {
+        let knowledge = runtime.knowledge(map_width, map_height);
+        let tuning = runtime.tuning(RobotType::Scout, &config::SCOUT_CONFIG);
+        let mut rng = runtime.rng;
+        Self {
+            knowledge,
+            log_target: logging::robot_target(initial_state.id),
+            state: initial_state,
+            merge_complete_receiver,
+            command_receiver,
+            heading: Direction::random(&mut rng),
+            reported: Vec::new(),
+            reported_tick: 0,
+            retiring: false,
+            station_lost: false,
+            tuning,
+            clock: runtime.clock,
+            inbox: runtime.gauge,
+            trace: runtime.trace,
+            rng,
+        }
+    }
+
+    pub fn start(mut self, sender: EventSender, map: SharedMap) -> JoinHandle<()> {
+        let robot_id = self.state.id;
+        let station_coords = self.knowledge.get_station_coords();
+
+        let clock = self.clock.clone();
+        clock::spawn_robot(&clock, robot_id, move || {
+            info!(target: &self.log_target, "Robot {}: Starting scout thread.", robot_id);
+
+            loop {
+                let _step = profiler::robot_step(robot_id);
+                self.process_commands(&sender);
+                common::suffer_hostile_contact(&mut self.state, &map);
+                common::soak_up_flare(&mut self.state, &sender);
+                self.knowledge.expire_sightings(sender.clock().now());
+                self.knowledge
+                    .forget_stale_terrain(sender.clock().now(), config::terrain_memory());
+                if self.retiring && self.state.status == RobotStatus::AtStation {
+                    info!(target: &self.log_target, "Robot {}: Retired at station.", robot_id);
+                    break;
+                }
+                if self.station_lost {
+                    info!(
+                        target: &self.log_target,
+                        "Robot {}: Station closed, stopping.",
+                        robot_id
+                    );
+                    break;
+                }
+
+                match self.state.status {
+                    RobotStatus::Exploring => {
+                        if self.state.energy <= self.tuning.config().low_energy_threshold {
+                            self.trace
+                                .record(sender.clock().now(), &self.state, "return", || {
+                                    format!(
+                                        "energy {} at or below threshold {}",
+                                        self.state.energy,
+                                        self.tuning.config().low_energy_threshold
+                                    )
+                                });
+                            info!(
+                                target: &self.log_target,
+                                "Robot {}: Low energy ({}), returning to station.",
+                                robot_id, self.state.energy
+                            );
+                            self.state.status = RobotStatus::ReturningToStation;
+                            continue;
+                        }
+                        if let Err(e) = self.scout_step(&sender, &map) {
+                            error!(target: &self.log_target, "Robot {}: {}", robot_id, e);
+                            break;
+                        }
+                    }
+                    RobotStatus::ReturningToStation => {
+                        self.return_step(&sender, &map, station_coords);
+                    }
+                    RobotStatus::AtStation => {
+                        self.clock.sleep(config::scaled(Duration::from_millis(
+                            config::AT_STATION_SLEEP_MS,
+                        )));
+                    }
+                    _ => {
+                        error!(
+                            target: &self.log_target,
+                            "Robot: {} Unhandled state {:?}.",
+                            robot_id, self.state.status
+                        );
+                        self.state.status = RobotStatus::Exploring;
+                        self.clock.sleep(config::UNHANDLED_STATE_SLEEP);
+                    }
+                }
+            }
+            info!(target: &self.log_target, "Robot {}: Thread shutting down.", robot_id);
+            let reason = if self.retiring {
+                ShutdownReason::Retired
+            } else if self.station_lost {
+                ShutdownReason::StationClosed
+            } else {
+                ShutdownReason::LoopExited
+            };
+            let _ = sender.send(RobotEvent::Shutdown {
+                id: robot_id,
+                reason,
+            });
+        })
+    }
+
+    fn process_commands(&mut self, sender: &EventSender) {
+        while let Some(CommandEnvelope {
+            id: command_id,
+            command,
+        }) = common::next_command(&self.command_receiver, &self.inbox, &mut self.station_lost)
+        {
+            let outcome = self.carry_out(sender, command);
+            common::acknowledge(sender, self.state.id, command_id, outcome);
+        }
+    }
+
+    /// Carries out an order of the station, or says why it cannot
+    fn carry_out(&mut self, sender: &EventSender, command: RobotCommand) -> Result<(), String> {
+        let mut outcome = Ok(());
+        match command {
+            RobotCommand::ReturnToStation => {
+                if self.state.status == RobotStatus::Exploring {
+                    info!(
+                        target: &self.log_target,
+                        "Robot {}: Recalled to station.",
+                        self.state.id
+                    );
+                    self.state.status = RobotStatus::ReturningToStation;
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            RobotCommand::AvoidTiles(tiles) => self.knowledge.set_exclusions(tiles),
+            RobotCommand::Wear { percent } => self.state.wear_percent = percent,
+            RobotCommand::SetPreset(preset) => {
+                info!(
+                    target: &self.log_target,
+                    "Robot {}: Switching to the {} preset.",
+                    self.state.id, preset.label()
+                );
+                self.tuning.set_preset(preset);
+            }
+            RobotCommand::Service { ticks } => {
+                common::undergo_service(&mut self.state, sender, &*self.clock, ticks)
+            }
+            RobotCommand::Charge { ticks } => {
+                common::charge(&mut self.state, sender, &*self.clock, ticks);
+                if self.state.status == RobotStatus::AtStation {
+                    self.state.status = self.status_after_dock();
+                }
+            }
+            RobotCommand::RefitBattery => {
+                info!(target: &self.log_target, "Robot {}: Battery refitted.", self.state.id);
+                self.state.refit_battery();
+            }
+            RobotCommand::MeteorImpact {
+                x,
+                y,
+                radius,
+                damage,
+            } => common::suffer_meteor_impact(
+                &mut self.state,
+                &mut self.knowledge,
+                (x, y),
+                radius,
+                damage,
+            ),
+            RobotCommand::SolarFlare {
+                scramble_percent,
+                seed,
+            } => {
+                common::suffer_solar_flare(&self.state, &mut self.knowledge, scramble_percent, seed)
+            }
+            RobotCommand::Teleport { x, y } => common::teleport(&mut self.state, sender, (x, y)),
+            RobotCommand::SetEnergy(energy) => common::set_energy(&mut self.state, sender, energy),
+            RobotCommand::Retire => {
+                info!(target: &self.log_target, "Robot {}: Retiring.", self.state.id);
+                self.retiring = true;
+                if self.state.status == RobotStatus::Exploring {
+                    self.state.status = RobotStatus::ReturningToStation;
+                    let _ = sender.send(RobotEvent::ReturnToBase { id: self.state.id });
+                }
+            }
+            other => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot {}: Ignoring unsupported command {:?}",
+                    self.state.id, other
+                );
+                outcome = Err(format!("Unsupported command {:?}", other));
+            }
+        }
+        outcome
+    }
+
+    /// Follows the closest visible hostile, heads for the last known sighting when none is
+    /// in view, and sweeps the map otherwise
+    fn scout_step(&mut self, sender: &EventSender, map: &SharedMap) -> Result<(), String> {
+        let map_guard = map.read();
+        let map_read = &*map_guard;
+        let (x, y) = (self.state.x, self.state.y);
+        self.knowledge.observe_and_update(x, y, map_read);
+
+        let hostiles = visible_hostiles(x, y, config::SCOUT_SENSOR_RANGE, map_read);
+        let closest = hostiles
+            .iter()
+            .min_by_key(|&&hostile| map_read.distance(hostile, (x, y)))
+            .copied();
+        let last_sighting = self
+            .knowledge
+            .entities()
+            .hostiles()
+            .map(|sighting| (sighting.x, sighting.y))
+            .filter(|&hostile| map_read.distance(hostile, (x, y)) > config::SCOUT_SENSOR_RANGE)
+            .min_by_key(|&hostile| map_read.distance(hostile, (x, y)));
+
+        let action = match (closest, last_sighting) {
+            (Some(_), _) => "shadow",
+            (None, Some(_)) => "chase_sighting",
+            (None, None) => "wander",
+        };
+        let (new_x, new_y) = match (closest, last_sighting) {
+            (Some(hostile), _) => {
+                keep_distance(x, y, hostile, config::SCOUT_FOLLOW_DISTANCE, map_read)
+            }
+            (None, Some((target_x, target_y))) => {
+                let direction = common::move_towards_target(
+                    x,
+                    y,
+                    target_x,
+                    target_y,
+                    &self.knowledge,
+                    map_read,
+                    &mut self.rng,
+                );
+                self.step_if_free(direction, map_read)
+            }
+            (None, None) => {
+                let mut next = self.step_if_free(self.heading, map_read);
+                if next == (x, y) {
+                    self.heading = Direction::random(&mut self.rng);
+                    next = self.step_if_free(self.heading, map_read);
+                }
+                next
+            }
+        };
+        let is_obstacle = map_read.is_obstacle(new_x, new_y);
+        if (new_x, new_y) != (x, y) {
+            self.state.move_to(new_x, new_y, map_read);
+        }
+        drop(map_guard);
+        self.trace
+            .record(sender.clock().now(), &self.state, action, || {
+                match (closest, last_sighting) {
+                    (Some(hostile), _) => format!(
+                        "hostile in sight at {:?}, keeping {} moves away",
+                        hostile,
+                        config::SCOUT_FOLLOW_DISTANCE
+                    ),
+                    (None, Some(sighting)) => {
+                        format!("none in sight, last sighting at {:?}", sighting)
+                    }
+                    (None, None) => format!("none in sight or known, heading {:?}", self.heading),
+                }
+            });
+
+        // Reported again before the station forgets them, even if they did not move
+        let refresh_due = sender.clock().now().saturating_sub(self.reported_tick)
+            >= config::HOSTILE_SIGHTING_TTL_TICKS / 2;
+        if !hostiles.is_empty() && (hostiles != self.reported || refresh_due) {
+            self.report_sightings(sender, hostiles);
+        }
+        if (new_x, new_y) != (x, y) {
+            self.state
+                .use_energy(self.tuning.config().movement_energy_cost);
+            sender
+                .send(RobotEvent::ExplorationData {
+                    id: self.state.id,
+                    x: new_x,
+                    y: new_y,
+                    is_obstacle,
+                })
+                .map_err(|e| format!("Failed to send ExplorationData: {}", e))?;
+            common::report_position(&self.state, sender);
+        }
+
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
+            self.tuning.config().primary_action_sleep_min_ms,
+            self.tuning.config().primary_action_sleep_max_ms,
+        ));
+        Ok(())
+    }
+
+    /// The tile one step in `direction`, or the current one if it cannot go there
+    fn step_if_free(&self, direction: Direction, map: &Map) -> (usize, usize) {
+        let (nx, ny) = movement::next_position(self.state.x, self.state.y, &direction, map);
+        if movement::is_valid_move(nx, ny, map)
+            && !self
+                .knowledge
+                .blocks_move((self.state.x, self.state.y), (nx, ny))
+        {
+            (nx, ny)
+        } else {
+            (self.state.x, self.state.y)
+        }
+    }
+
+    fn report_sightings(&mut self, sender: &EventSender, positions: Vec<(usize, usize)>) {
+        debug!(
+            target: &self.log_target,
+            "Robot {}: Dust devils sighted at {:?}",
+            self.state.id, positions
+        );
+        let tick = sender.clock().now();
+        let sightings: Vec<Sighting> = positions
+            .iter()
+            .map(|&(x, y)| Sighting {
+                kind: EntityKind::Hostile,
+                x,
+                y,
+                tick,
+            })
+            .collect();
+        for sighting in &sightings {
+            self.knowledge.record_sighting(sighting.clone());
+        }
+        self.reported = positions;
+        self.reported_tick = tick;
+        if let Err(e) = sender.send(RobotEvent::HostileSighted {
+            id: self.state.id,
+            sightings,
+        }) {
+            warn!(
+                target: &self.log_target,
+                "Robot {}: Failed to report sightings: {}",
+                self.state.id, e
+            );
+        }
+    }
+
+    fn return_step(
+        &mut self,
+        sender: &EventSender,
+        map: &SharedMap,
+        (station_x, station_y): (usize, usize),
+    ) {
+        if (self.state.x, self.state.y) == (station_x, station_y) {
+            self.arrive_at_station(sender);
+            return;
+        }
+
+        {
+            let map_read = map.read();
+            let direction = common::direction_home(
+                self.state.x,
+                self.state.y,
+                &self.knowledge,
+                &map_read,
+                &mut self.rng,
+            );
+            let (new_x, new_y) = self.step_if_free(direction, &map_read);
+            self.trace
+                .record(sender.clock().now(), &self.state, "head_home", || {
+                    format!(
+                        "{:?} toward the station at {:?}",
+                        direction,
+                        (station_x, station_y)
+                    )
+                });
+            if (new_x, new_y) == (self.state.x, self.state.y) {
+                debug!(
+                    target: &self.log_target,
+                    "Robot: {} Path to station blocked @ {:?}.",
+                    self.state.id, (self.state.x, self.state.y)
+                );
+            } else {
+                self.state.move_to(new_x, new_y, &map_read);
+                common::report_position(&self.state, sender);
+            }
+        }
+
+        self.clock.sleep(config::random_sleep_duration(
+            &mut self.rng,
+            config::RETURN_SLEEP_MIN_MS,
+            config::RETURN_SLEEP_MAX_MS,
+        ));
+    }
+
+    /// Status after docking: retiring robots stay at the station
+    fn status_after_dock(&self) -> RobotStatus {
+        if self.retiring {
+            RobotStatus::AtStation
+        } else {
+            RobotStatus::Exploring
+        }
+    }
+
+    fn arrive_at_station(&mut self, sender: &EventSender) {
+        info!(target: &self.log_target, "Robot: {} Arrived station.", self.state.id);
+        self.state.status = RobotStatus::AtStation;
+        common::discard_stale_merges(&self.merge_complete_receiver, &self.inbox, self.state.id);
+        let status_after_dock = self.status_after_dock();
+        match common::report_dock(
+            sender,
+            &*self.clock,
+            &self.merge_complete_receiver,
+            &self.inbox,
+            self.state.id,
+            &self.knowledge,
+        ) {
+            Ok(RobotEvent::MergeComplete {
+                merged_knowledge,
+                brief,
+                ..
+            }) => {
+                info!(target: &self.log_target, "Robot: {} MergeComplete OK.", self.state.id);
+                self.knowledge = merged_knowledge;
+                self.state.status =
+                    common::recharge_at_dock(&mut self.state, &brief, status_after_dock);
+                for assignment in common::take_brief(&mut self.knowledge, *brief) {
+                    if let Err(reason) = self.carry_out(sender, assignment) {
+                        warn!(
+                            target: &self.log_target,
+                            "Robot: {} Assignment dropped: {}",
+                            self.state.id, reason
+                        );
+                    }
+                }
+            }
+            Ok(o) => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} Unexpected event: {:?}",
+                    self.state.id, o
+                );
+                self.state.status = status_after_dock;
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                warn!(
+                    target: &self.log_target,
+                    "Robot: {} No merge, resuming with own knowledge.",
+                    self.state.id
+                );
+                self.state.status = status_after_dock;
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                error!(
+                    target: &self.log_target,
+                    "Robot: {} Merge channel disconnected.",
+                    self.state.id
+                );
+            }
+        }
+    }
+}
+
+/// Hostiles within `range` moves of (x, y), in map order
+pub fn visible_hostiles(x: usize, y: usize, range: usize, map: &Map) -> Vec<(usize, usize)> {
+    let mut visible: Vec<(usize, usize)> = map
+        .hostiles()
+        .iter()
+        .filter(|&&hostile| map.distance(hostile, (x, y)) <= range)
+        .copied()
+        .collect();
+    visible.sort_unstable_by_key(|&(hx, hy)| (hy, hx));
+    visible
+}
+
+/// The tile among (x, y) and its walkable neighbors whose distance to `hostile` is closest
+/// to `distance`, staying put on ties
+pub fn keep_distance(
+    x: usize,
+    y: usize,
+    hostile: (usize, usize),
+    distance: usize,
+    map: &Map,
+) -> (usize, usize) {
+    let gap = |tile: (usize, usize)| map.distance(tile, hostile).abs_diff(distance);
+    map.neighbors(x, y)
+        .filter(|&(nx, ny)| movement::is_valid_move(nx, ny, map))
+        .fold(
+            (x, y),
+            |best, tile| {
+                if gap(tile) < gap(best) {
+                    tile
+                } else {
+                    best
+                }
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_map(hostile: (usize, usize)) -> Map {
+        let mut map = Map::new(16, 8, 1);
+        for y in 0..map.height {
+            for x in 0..map.width {
+                map.set_walkable(x, y);
+            }
+        }
+        map.set_hostiles(vec![hostile]);
+        map
+    }
+
+    #[test]
+    fn test_scout_keeps_its_distance_from_a_hostile() {
+        let map = open_map((12, 2));
+
+        // Too far: closes in
+        assert_eq!(keep_distance(6, 2, (12, 2), 3, &map), (7, 2));
+        // Too close: backs off
+        let (x, y) = keep_distance(10, 2, (12, 2), 3, &map);
+        assert_eq!(x.abs_diff(12) + y.abs_diff(2), 3);
+        // Right distance: holds position
+        assert_eq!(keep_distance(9, 2, (12, 2), 3, &map), (9, 2));
+    }
+
+    #[test]
+    fn test_scout_only_sees_hostiles_in_range() {
+        let map = open_map((12, 2));
+        assert_eq!(visible_hostiles(6, 2, 6, &map), vec![(12, 2)]);
+        assert!(visible_hostiles(6, 3, 6, &map).is_empty());
+    }
+}
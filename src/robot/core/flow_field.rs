@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::map::noise::Map;
+use crate::memory;
+
+/// Steps from each tile to the nearest goal over passable tiles, so a robot gets to a goal
+/// by always moving to a neighbor with a lower distance
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowField {
+    width: usize,
+    height: usize,
+    /// Row-major, `None` for tiles no goal can be reached from
+    distances: Vec<Option<u32>>,
+}
+
+impl FlowField {
+    /// Breadth-first search from every goal at once through the tiles `passable` accepts
+    pub fn towards(
+        goals: impl IntoIterator<Item = (usize, usize)>,
+        width: usize,
+        height: usize,
+        passable: impl Fn((usize, usize)) -> bool,
+    ) -> Self {
+        let mut field = Self {
+            width,
+            height,
+            distances: vec![None; width * height],
+        };
+        let mut queue = VecDeque::new();
+        for goal in goals {
+            if let Some(index) = field.index(goal) {
+                field.distances[index] = Some(0);
+                queue.push_back(goal);
+            }
+        }
+
+        while let Some((x, y)) = queue.pop_front() {
+            let next = field.distance((x, y)).unwrap_or(0) + 1;
+            for neighbor in Map::valid_neighbors(x, y, width, height) {
+                let Some(index) = field.index(neighbor) else {
+                    continue;
+                };
+                if field.distances[index].is_none() && passable(neighbor) {
+                    field.distances[index] = Some(next);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        field
+    }
+
+    fn index(&self, (x, y): (usize, usize)) -> Option<usize> {
+        (x < self.width && y < self.height).then_some(y * self.width + x)
+    }
+
+    /// Steps from `tile` to the nearest goal, `None` if no goal is known to be reachable
+    pub fn distance(&self, tile: (usize, usize)) -> Option<u32> {
+        self.index(tile).and_then(|index| self.distances[index])
+    }
+
+    /// Tiles a goal can be reached from
+    pub fn reachable(&self) -> usize {
+        self.distances.iter().flatten().count()
+    }
+
+    /// Approximate bytes held, see `crate::memory`
+    pub fn approx_bytes(&self) -> usize {
+        memory::vec_bytes::<Option<u32>>(self.distances.capacity())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_flows_around_walls_to_the_nearest_goal() {
+        // A wall at x = 2 with a gap at the bottom row
+        let passable = |(x, y): (usize, usize)| x != 2 || y == 3;
+        let field = FlowField::towards([(0, 0)], 5, 4, passable);
+
+        assert_eq!(field.distance((0, 0)), Some(0));
+        assert_eq!(field.distance((1, 0)), Some(1));
+        assert_eq!(field.distance((2, 0)), None);
+        assert_eq!(field.distance((3, 0)), Some(9));
+        assert_eq!(field.distance((9, 9)), None);
+        assert_eq!(field.reachable(), 17);
+
+        let two_goals = FlowField::towards([(0, 0), (4, 0)], 5, 4, passable);
+        assert_eq!(two_goals.distance((3, 0)), Some(1));
+    }
+}
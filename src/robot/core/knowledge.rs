@@ -1,10 +1,17 @@
 use log::error;
-use std::collections::HashMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 
 use crate::communication::channels::ResourceType;
 use crate::map::noise::Map;
+use crate::map::topology::Topology;
+use crate::memory;
+use crate::robot::core::flow_field::FlowField;
+use crate::robot::core::spatial_index::TileIndex;
+use crate::robot::utils::config;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TileInfo {
     Unknown,
     Walkable,
@@ -13,33 +20,356 @@ pub enum TileInfo {
     Station,
 }
 
-#[derive(Clone, Debug)]
+impl TileInfo {
+    /// What a robot observing the tile right now would record, i.e. the ground truth
+    pub fn observe(x: usize, y: usize, map: &Map) -> Self {
+        if map.is_station(x, y) {
+            TileInfo::Station
+        } else if map.is_obstacle(x, y) {
+            TileInfo::Obstacle
+        } else if let Some((res_type, amount)) = map.get_resource(x, y) {
+            if amount > 0 {
+                TileInfo::Resource(res_type, amount)
+            } else {
+                TileInfo::Walkable
+            }
+        } else {
+            TileInfo::Walkable
+        }
+    }
+}
+
+/// How a tile of a knowledge map compares with the real map
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileAccuracy {
+    Unknown,
+    Accurate,
+    /// Known, but wrong or out of date
+    Wrong,
+}
+
+/// How far a knowledge map is from the real map
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KnowledgeDivergence {
+    /// Tiles that are not `Unknown`
+    pub known: usize,
+    /// Known tiles that are wrong or out of date
+    pub wrong: usize,
+}
+
+impl KnowledgeDivergence {
+    /// Share of the known tiles that are wrong, in percent
+    pub fn wrong_percent(&self) -> usize {
+        (self.wrong * 100).checked_div(self.known).unwrap_or(0)
+    }
+}
+
+/// Something that moves around the map, unlike the terrain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EntityKind {
+    Robot(u32),
+    Hostile,
+    Storm,
+}
+
+impl EntityKind {
+    /// Ticks a sighting of this kind stays relevant
+    pub fn lifetime(&self) -> u64 {
+        match self {
+            EntityKind::Robot(_) => config::ROBOT_SIGHTING_TTL_TICKS,
+            EntityKind::Hostile => config::HOSTILE_SIGHTING_TTL_TICKS,
+            EntityKind::Storm => config::STORM_SIGHTING_TTL_TICKS,
+        }
+    }
+}
+
+/// A moving entity seen on a tile at a given tick
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sighting {
+    pub kind: EntityKind,
+    pub x: usize,
+    pub y: usize,
+    pub tick: u64,
+}
+
+impl Sighting {
+    /// Robots are told apart by id, other entities by where they were seen
+    fn is_same_entity(&self, other: &Sighting) -> bool {
+        match (self.kind, other.kind) {
+            (EntityKind::Robot(a), EntityKind::Robot(b)) => a == b,
+            (a, b) => a == b && (self.x, self.y) == (other.x, other.y),
+        }
+    }
+
+    pub fn is_stale(&self, now: u64) -> bool {
+        now.saturating_sub(self.tick) > self.kind.lifetime()
+    }
+}
+
+/// A note the user left on a tile, e.g. "rich vein here"
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub x: usize,
+    pub y: usize,
+    pub text: String,
+}
+
+/// Sightings of moving entities, kept apart from the terrain and forgotten as they age, and
+/// what the station hands out on top of the terrain: the no-go tiles, which robots neither
+/// enter nor target, the tiles other robots were sent to, the user's notes and the distances
+/// back to it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DynamicLayer {
+    sightings: Vec<Sighting>,
+    #[serde(default)]
+    exclusions: BTreeSet<(usize, usize)>,
+    #[serde(default)]
+    claims: BTreeSet<(usize, usize)>,
+    #[serde(default)]
+    station_field: Option<FlowField>,
+    /// By tile
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+}
+
+impl DynamicLayer {
+    /// Records a sighting, replacing an older one of the same entity
+    pub fn record(&mut self, sighting: Sighting) {
+        match self
+            .sightings
+            .iter_mut()
+            .find(|known| known.is_same_entity(&sighting))
+        {
+            Some(known) if known.tick <= sighting.tick => *known = sighting,
+            Some(_) => {}
+            None => self.sightings.push(sighting),
+        }
+    }
+
+    /// Takes in another layer's sightings, the most recent one of each entity wins. No-go
+    /// tiles, notes and distances only come from the station.
+    pub fn merge(&mut self, other: &DynamicLayer) {
+        for sighting in &other.sightings {
+            self.record(sighting.clone());
+        }
+    }
+
+    /// Forgets the sightings older than their kind's lifetime at tick `now`
+    pub fn expire(&mut self, now: u64) {
+        self.sightings.retain(|sighting| !sighting.is_stale(now));
+    }
+
+    /// Leaves `text` on (x, y) in place of the tile's previous note
+    pub fn annotate(&mut self, x: usize, y: usize, text: String) {
+        let annotation = Annotation { x, y, text };
+        match self
+            .annotations
+            .binary_search_by_key(&(y, x), |known| (known.y, known.x))
+        {
+            Ok(at) => self.annotations[at] = annotation,
+            Err(at) => self.annotations.insert(at, annotation),
+        }
+    }
+
+    /// Removes the note on (x, y), returns whether there was one
+    pub fn clear_annotation(&mut self, x: usize, y: usize) -> bool {
+        let before = self.annotations.len();
+        self.annotations
+            .retain(|annotation| (annotation.x, annotation.y) != (x, y));
+        self.annotations.len() != before
+    }
+
+    pub fn annotation(&self, x: usize, y: usize) -> Option<&str> {
+        self.annotations
+            .iter()
+            .find(|annotation| (annotation.x, annotation.y) == (x, y))
+            .map(|annotation| annotation.text.as_str())
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    pub fn sightings(&self) -> &[Sighting] {
+        &self.sightings
+    }
+
+    pub fn hostiles(&self) -> impl Iterator<Item = &Sighting> {
+        self.sightings
+            .iter()
+            .filter(|sighting| sighting.kind == EntityKind::Hostile)
+    }
+
+    /// Whether (x, y) is within `distance` moves of a sighted hostile
+    pub fn is_near_hostile(&self, x: usize, y: usize, distance: usize) -> bool {
+        self.hostiles()
+            .any(|sighting| sighting.x.abs_diff(x) + sighting.y.abs_diff(y) <= distance)
+    }
+
+    /// Approximate bytes held, see `crate::memory`
+    pub fn approx_bytes(&self) -> usize {
+        memory::vec_bytes::<Sighting>(self.sightings.capacity())
+            + memory::btree_bytes::<(usize, usize)>(self.exclusions.len() + self.claims.len())
+            + self
+                .station_field
+                .as_ref()
+                .map_or(0, FlowField::approx_bytes)
+            + memory::vec_bytes::<Annotation>(self.annotations.capacity())
+            + self
+                .annotations
+                .iter()
+                .map(|annotation| annotation.text.capacity())
+                .sum::<usize>()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "KnowledgeRows", into = "KnowledgeRows")]
 pub struct RobotKnowledge {
-    pub map: HashMap<(usize, usize), TileInfo>,
+    map: HashMap<(usize, usize), TileInfo>,
     pub width: usize,
     pub height: usize,
+    /// Boxed so knowledge-carrying events stay small
+    extras: Box<KnowledgeExtras>,
+}
+
+/// What a knowledge map holds besides its tiles
+#[derive(Clone, Debug, Default)]
+struct KnowledgeExtras {
+    station: (usize, usize),
+    entities: DynamicLayer,
+    /// Deposits and unknown tiles of the map, for nearest-tile queries
+    index: TileIndex,
+    /// Tick each walkable or obstacle tile was last seen at
+    seen: HashMap<(usize, usize), u64>,
+    /// Tick the knowledge was last aged to, stamped on the tiles recorded since
+    tick: u64,
+}
+
+/// Row-major form of a knowledge map, since tuple keys do not serialize to JSON
+#[derive(Serialize, Deserialize)]
+struct KnowledgeRows {
+    width: usize,
+    height: usize,
+    station: (usize, usize),
+    tiles: Vec<TileInfo>,
+    #[serde(default)]
+    entities: DynamicLayer,
+    #[serde(default)]
+    tick: u64,
+    /// Walkable and obstacle tiles with the tick they were last seen at, by row
+    #[serde(default)]
+    seen: Vec<((usize, usize), u64)>,
+}
+
+impl From<RobotKnowledge> for KnowledgeRows {
+    fn from(knowledge: RobotKnowledge) -> Self {
+        let tiles = (0..knowledge.height)
+            .flat_map(|y| (0..knowledge.width).map(move |x| (x, y)))
+            .map(|(x, y)| knowledge.get_tile(x, y).clone())
+            .collect();
+        let mut seen: Vec<_> = knowledge.extras.seen.into_iter().collect();
+        seen.sort_unstable_by_key(|&((x, y), _)| (y, x));
+        Self {
+            width: knowledge.width,
+            height: knowledge.height,
+            station: knowledge.extras.station,
+            tiles,
+            entities: knowledge.extras.entities,
+            tick: knowledge.extras.tick,
+            seen,
+        }
+    }
+}
+
+impl From<KnowledgeRows> for RobotKnowledge {
+    fn from(rows: KnowledgeRows) -> Self {
+        let mut knowledge = RobotKnowledge::new(rows.width, rows.height);
+        knowledge.extras.station = rows.station;
+        knowledge.extras.entities = rows.entities;
+        for (index, tile) in rows
+            .tiles
+            .into_iter()
+            .enumerate()
+            .take(rows.width * rows.height)
+        {
+            knowledge.update_tile(index % rows.width, index / rows.width, tile);
+        }
+        for ((x, y), tick) in rows.seen {
+            knowledge.mark_seen(x, y, tick);
+        }
+        knowledge.extras.tick = rows.tick;
+        knowledge
+    }
 }
 
 impl RobotKnowledge {
     pub fn new(width: usize, height: usize) -> Self {
         let capacity = width * height;
-        let mut map = HashMap::with_capacity(capacity);
+        let center_x = width / 2;
+        let center_y = height / 2;
+        let mut knowledge = Self {
+            map: HashMap::with_capacity(capacity),
+            width,
+            height,
+            extras: Box::new(KnowledgeExtras {
+                station: (center_x, center_y),
+                ..KnowledgeExtras::default()
+            }),
+        };
 
         for y in 0..height {
             for x in 0..width {
-                map.insert((x, y), TileInfo::Unknown);
+                knowledge.set((x, y), TileInfo::Unknown);
             }
         }
+        knowledge.set((center_x, center_y), TileInfo::Station);
+        knowledge
+    }
 
-        let center_x = width / 2;
-        let center_y = height / 2;
-        map.insert((center_x, center_y), TileInfo::Station);
-        Self { map, width, height }
+    /// Every tile with what is known of it, in no particular order
+    pub fn tiles(&self) -> impl Iterator<Item = ((usize, usize), &TileInfo)> {
+        self.map.iter().map(|(&tile, info)| (tile, info))
+    }
+
+    /// Where the known deposits and the unknown tiles are, for nearest-tile queries
+    pub fn index(&self) -> &TileIndex {
+        &self.extras.index
+    }
+
+    /// Approximate bytes of a knowledge map of this size just made by `new`
+    pub fn fresh_bytes(width: usize, height: usize) -> usize {
+        let tiles = width * height;
+        size_of::<Self>()
+            + size_of::<KnowledgeExtras>()
+            + memory::hash_map_bytes::<(usize, usize), TileInfo>(tiles)
+            + memory::btree_bytes::<(usize, usize)>(tiles)
+    }
+
+    /// Approximate bytes held, see `crate::memory`
+    pub fn approx_bytes(&self) -> usize {
+        size_of::<Self>()
+            + size_of::<KnowledgeExtras>()
+            + memory::hash_map_bytes::<(usize, usize), TileInfo>(self.map.len())
+            + self.extras.index.approx_bytes()
+            + self.extras.entities.approx_bytes()
+            + memory::hash_map_bytes::<(usize, usize), u64>(self.extras.seen.len())
+    }
+
+    /// Every change of a tile goes through here, keeping the index in step
+    fn set(&mut self, tile: (usize, usize), info: TileInfo) {
+        if matches!(info, TileInfo::Walkable | TileInfo::Obstacle) {
+            self.extras.seen.insert(tile, self.extras.tick);
+        } else {
+            self.extras.seen.remove(&tile);
+        }
+        let before = self.map.insert(tile, info.clone());
+        self.extras.index.update(tile, before.as_ref(), &info);
     }
 
     pub fn update_tile(&mut self, x: usize, y: usize, info: TileInfo) {
         if x < self.width && y < self.height {
-            self.map.insert((x, y), info);
+            self.set((x, y), info);
         } else {
             error!(
                 "Attempted to update knowledge out of bounds at ({}, {})",
@@ -48,6 +378,95 @@ impl RobotKnowledge {
         }
     }
 
+    /// Marks the tiles within `radius` of (x, y) as unknown, except the station, so they
+    /// get explored again
+    pub fn forget_area(&mut self, x: usize, y: usize, radius: usize) {
+        for tile in Map::tiles_within(x, y, radius, self.width, self.height) {
+            if tile != self.extras.station {
+                self.set(tile, TileInfo::Unknown);
+            }
+        }
+    }
+
+    /// Garbles about `percent` % of the known tiles, as a solar flare does: obstacles read as
+    /// walkable, anything else as an obstacle. The station is kept. Returns how many were hit.
+    pub fn scramble(&mut self, percent: u32, rng: &mut impl Rng) -> usize {
+        let mut known: Vec<(usize, usize)> = self
+            .map
+            .iter()
+            .filter(|(_, info)| !matches!(info, TileInfo::Unknown | TileInfo::Station))
+            .map(|(&tile, _)| tile)
+            .collect();
+        // Sorted, so a seeded generator garbles the same tiles every time
+        known.sort_unstable();
+
+        let mut scrambled = 0;
+        for tile in known {
+            if rng.random_range(0..100) < percent {
+                let garbled = match self.map[&tile] {
+                    TileInfo::Obstacle => TileInfo::Walkable,
+                    _ => TileInfo::Obstacle,
+                };
+                self.set(tile, garbled);
+                scrambled += 1;
+            }
+        }
+        scrambled
+    }
+
+    /// Tick (x, y) was last seen at, if it is known to be walkable or an obstacle
+    pub fn seen_at(&self, x: usize, y: usize) -> Option<u64> {
+        self.extras.seen.get(&(x, y)).copied()
+    }
+
+    /// Records that walkable or obstacle tile (x, y) was seen at `tick`, e.g. by another robot
+    pub fn mark_seen(&mut self, x: usize, y: usize, tick: u64) {
+        if let Some(seen) = self.extras.seen.get_mut(&(x, y)) {
+            *seen = tick;
+        }
+    }
+
+    /// Ages the knowledge to tick `now`: tiles recorded from now on are stamped with it, and
+    /// walkable or obstacle tiles not seen for more than `memory` ticks become unknown again,
+    /// since the terrain may have changed. Nothing is forgotten without a `memory`. Returns
+    /// how many tiles were forgotten.
+    pub fn forget_stale_terrain(&mut self, now: u64, memory: Option<u64>) -> usize {
+        self.extras.tick = now;
+        let Some(memory) = memory else {
+            return 0;
+        };
+        let stale: Vec<(usize, usize)> = self
+            .extras
+            .seen
+            .iter()
+            .filter(|&(_, &seen)| now.saturating_sub(seen) > memory)
+            .map(|(&tile, _)| tile)
+            .collect();
+        for &tile in &stale {
+            self.set(tile, TileInfo::Unknown);
+        }
+        stale.len()
+    }
+
+    /// The nearest unknown tile `accept` takes next to a tile known to be walkable, where
+    /// exploring goes on from, with its distance
+    pub fn nearest_frontier(
+        &self,
+        from: (usize, usize),
+        topology: &Topology,
+        mut accept: impl FnMut((usize, usize)) -> bool,
+    ) -> Option<((usize, usize), usize)> {
+        self.extras.index.unknown().nearest(from, topology, |tile| {
+            accept(tile)
+                && topology.neighbors(tile.0, tile.1).any(|(x, y)| {
+                    matches!(
+                        self.get_tile(x, y),
+                        TileInfo::Walkable | TileInfo::Resource(..) | TileInfo::Station
+                    )
+                })
+        })
+    }
+
     pub fn get_tile(&self, x: usize, y: usize) -> &TileInfo {
         self.map.get(&(x, y)).unwrap_or(&TileInfo::Unknown)
     }
@@ -58,24 +477,163 @@ impl RobotKnowledge {
             return;
         }
 
-        let info = if map.is_station(x, y) {
-            TileInfo::Station
-        } else if map.is_obstacle(x, y) {
-            TileInfo::Obstacle
-        } else if let Some((res_type, amount)) = map.get_resource(x, y) {
-            if amount > 0 {
-                TileInfo::Resource(res_type, amount)
-            } else {
-                TileInfo::Walkable
+        self.update_tile(x, y, TileInfo::observe(x, y, map));
+    }
+
+    /// Counts the known tiles and those that disagree with the real map
+    pub fn divergence(&self, map: &Map) -> KnowledgeDivergence {
+        let mut divergence = KnowledgeDivergence::default();
+        for &(x, y) in self.map.keys() {
+            match self.accuracy_at(x, y, map) {
+                TileAccuracy::Unknown => {}
+                TileAccuracy::Accurate => divergence.known += 1,
+                TileAccuracy::Wrong => {
+                    divergence.known += 1;
+                    divergence.wrong += 1;
+                }
             }
-        } else {
-            TileInfo::Walkable
+        }
+        divergence
+    }
+
+    /// Compares the known tile with the real map
+    pub fn accuracy_at(&self, x: usize, y: usize, map: &Map) -> TileAccuracy {
+        match self.get_tile(x, y) {
+            TileInfo::Unknown => TileAccuracy::Unknown,
+            info if *info == TileInfo::observe(x, y, map) => TileAccuracy::Accurate,
+            _ => TileAccuracy::Wrong,
+        }
+    }
+
+    /// Moves from `from` to `to` over the tiles known to be passable, climbing shafts on the
+    /// way. `Some(limit + 1)` once the way is known to be longer than `limit`, `None` if no
+    /// known way leads there.
+    pub fn known_path_length(
+        &self,
+        from: (usize, usize),
+        to: (usize, usize),
+        topology: &Topology,
+        limit: usize,
+    ) -> Option<usize> {
+        let passable = |(x, y): (usize, usize)| {
+            (x, y) == to
+                || matches!(
+                    self.get_tile(x, y),
+                    TileInfo::Walkable | TileInfo::Resource(..) | TileInfo::Station
+                )
         };
-        self.update_tile(x, y, info);
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([(from, 0)]);
+        let mut cut_short = false;
+        while let Some((tile, moves)) = queue.pop_front() {
+            if tile == to {
+                return Some(moves);
+            }
+            if moves == limit {
+                cut_short = true;
+                continue;
+            }
+            let climbs = topology
+                .shafts
+                .iter()
+                .filter(|&&(shaft, _)| shaft == tile)
+                .map(|&(_, exit)| exit);
+            for next in topology.neighbors(tile.0, tile.1).chain(climbs) {
+                if passable(next) && seen.insert(next) {
+                    queue.push_back((next, moves + 1));
+                }
+            }
+        }
+        cut_short.then_some(limit + 1)
+    }
+
+    pub fn set_station_field(&mut self, field: FlowField) {
+        self.extras.entities.station_field = Some(field);
+    }
+
+    /// Distances to the station over the tiles it knew to be walkable, if it sent them
+    pub fn station_field(&self) -> Option<&FlowField> {
+        self.extras.entities.station_field.as_ref()
+    }
+
+    /// Moving entities seen by the robot or passed on by the station
+    pub fn entities(&self) -> &DynamicLayer {
+        &self.extras.entities
+    }
+
+    pub fn record_sighting(&mut self, sighting: Sighting) {
+        self.extras.entities.record(sighting);
+    }
+
+    pub fn merge_entities(&mut self, layer: &DynamicLayer) {
+        self.extras.entities.merge(layer);
+    }
+
+    /// Forgets the sightings that are too old at tick `now`
+    pub fn expire_sightings(&mut self, now: u64) {
+        self.extras.entities.expire(now);
+    }
+
+    /// Replaces the no-go tiles with those the station currently defines
+    pub fn set_exclusions(&mut self, tiles: impl IntoIterator<Item = (usize, usize)>) {
+        self.extras.entities.exclusions = tiles.into_iter().collect();
+    }
+
+    pub fn exclusions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.extras.entities.exclusions.iter().copied()
+    }
+
+    pub fn is_excluded(&self, x: usize, y: usize) -> bool {
+        self.extras.entities.exclusions.contains(&(x, y))
+    }
+
+    /// Replaces the tiles the station sent other robots to
+    pub fn set_claims(&mut self, tiles: impl IntoIterator<Item = (usize, usize)>) {
+        self.extras.entities.claims = tiles.into_iter().collect();
+    }
+
+    /// Replaces the user's notes with those the station currently holds
+    pub fn set_annotations(&mut self, annotations: &[Annotation]) {
+        self.extras.entities.annotations = annotations.to_vec();
+    }
+
+    /// The user's note on (x, y), if any
+    pub fn annotation(&self, x: usize, y: usize) -> Option<&str> {
+        self.extras.entities.annotation(x, y)
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        self.extras.entities.annotations()
+    }
+
+    /// Whether another robot was sent to (x, y), so it is best left to that robot
+    pub fn is_claimed(&self, x: usize, y: usize) -> bool {
+        self.extras.entities.claims.contains(&(x, y))
+    }
+
+    /// Whether a move from `from` to `to` runs into a known obstacle or into a no-go zone. A
+    /// robot caught inside a zone may still cross it to get out.
+    pub fn blocks_move(&self, from: (usize, usize), (x, y): (usize, usize)) -> bool {
+        matches!(self.get_tile(x, y), TileInfo::Obstacle)
+            || (self.is_excluded(x, y) && !self.is_excluded(from.0, from.1))
     }
 
     pub fn get_station_coords(&self) -> (usize, usize) {
-        (self.width / 2, self.height / 2)
+        self.extras.station
+    }
+
+    /// Moves the known station location, e.g. when the station is not at the map center
+    pub fn set_station_coords(&mut self, x: usize, y: usize) {
+        if x >= self.width || y >= self.height {
+            error!("Station location ({}, {}) is out of bounds", x, y);
+            return;
+        }
+        let (old_x, old_y) = self.extras.station;
+        if matches!(self.get_tile(old_x, old_y), TileInfo::Station) {
+            self.update_tile(old_x, old_y, TileInfo::Unknown);
+        }
+        self.update_tile(x, y, TileInfo::Station);
+        self.extras.station = (x, y);
     }
 }
 
@@ -100,6 +658,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_index_follows_the_tiles() {
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        assert_eq!(knowledge.index().unknown().len(), 99);
+
+        knowledge.update_tile(2, 2, TileInfo::Resource(ResourceType::MINERALS, 5));
+        let minerals = |knowledge: &RobotKnowledge| {
+            knowledge
+                .index()
+                .deposits(&ResourceType::MINERALS)
+                .is_some_and(|deposits| deposits.contains((2, 2)))
+        };
+        assert!(minerals(&knowledge));
+        assert!(!knowledge.index().unknown().contains((2, 2)));
+
+        knowledge.update_tile(2, 2, TileInfo::Resource(ResourceType::MINERALS, 0));
+        assert!(!minerals(&knowledge));
+        knowledge.forget_area(2, 2, 1);
+        assert_eq!(knowledge.index().unknown().len(), 99);
+    }
+
+    #[test]
+    fn test_scramble_garbles_only_known_tiles() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        for x in 0..10 {
+            knowledge.update_tile(x, 0, TileInfo::Walkable);
+            knowledge.update_tile(x, 1, TileInfo::Obstacle);
+        }
+        let scrambled = knowledge.scramble(100, &mut StdRng::seed_from_u64(1));
+        assert_eq!(scrambled, 20);
+        assert_eq!(knowledge.get_tile(0, 0), &TileInfo::Obstacle);
+        assert_eq!(knowledge.get_tile(0, 1), &TileInfo::Walkable);
+        assert_eq!(knowledge.get_tile(0, 2), &TileInfo::Unknown);
+        assert_eq!(knowledge.get_tile(5, 5), &TileInfo::Station);
+        assert_eq!(knowledge.scramble(0, &mut StdRng::seed_from_u64(1)), 0);
+    }
+
+    #[test]
+    fn test_unseen_terrain_is_forgotten() {
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        knowledge.update_tile(1, 1, TileInfo::Obstacle);
+        knowledge.forget_stale_terrain(50, Some(100));
+        knowledge.update_tile(2, 2, TileInfo::Walkable);
+        knowledge.update_tile(3, 3, TileInfo::Resource(ResourceType::ENERGY, 5));
+        assert_eq!(knowledge.seen_at(2, 2), Some(50));
+        assert_eq!(knowledge.seen_at(3, 3), None);
+
+        assert_eq!(knowledge.forget_stale_terrain(120, None), 0);
+        assert_eq!(knowledge.forget_stale_terrain(120, Some(100)), 1);
+        assert_eq!(knowledge.get_tile(1, 1), &TileInfo::Unknown);
+        assert_eq!(knowledge.get_tile(2, 2), &TileInfo::Walkable);
+        assert!(knowledge.index().unknown().contains((1, 1)));
+
+        let json = serde_json::to_string(&knowledge).unwrap();
+        let restored: RobotKnowledge = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.seen_at(2, 2), Some(50));
+        assert_eq!(knowledge.forget_stale_terrain(151, Some(100)), 1);
+        assert_eq!(
+            knowledge.get_tile(3, 3),
+            &TileInfo::Resource(ResourceType::ENERGY, 5)
+        );
+    }
+
     #[test]
     fn test_update_and_get_tile() {
         let mut knowledge = RobotKnowledge::new(5, 5);
@@ -119,4 +742,71 @@ mod tests {
         let knowledge = RobotKnowledge::new(8, 6);
         assert_eq!(knowledge.get_station_coords(), (4, 3));
     }
+
+    #[test]
+    fn test_set_station_coords_moves_station_tile() {
+        let mut knowledge = RobotKnowledge::new(8, 6);
+        knowledge.set_station_coords(1, 1);
+        assert_eq!(knowledge.get_station_coords(), (1, 1));
+        assert_eq!(knowledge.get_tile(1, 1), &TileInfo::Station);
+        assert_eq!(knowledge.get_tile(4, 3), &TileInfo::Unknown);
+    }
+
+    fn sighting(kind: EntityKind, (x, y): (usize, usize), tick: u64) -> Sighting {
+        Sighting { kind, x, y, tick }
+    }
+
+    #[test]
+    fn test_dynamic_layer_keeps_the_latest_sighting_of_each_entity() {
+        let mut layer = DynamicLayer::default();
+        layer.record(sighting(EntityKind::Robot(1), (1, 1), 10));
+        layer.record(sighting(EntityKind::Robot(1), (2, 1), 4));
+        layer.record(sighting(EntityKind::Hostile, (2, 2), 10));
+
+        let mut other = DynamicLayer::default();
+        other.record(sighting(EntityKind::Robot(1), (3, 1), 12));
+        other.record(sighting(EntityKind::Hostile, (6, 5), 12));
+        layer.merge(&other);
+
+        assert_eq!(layer.sightings().len(), 3);
+        assert_eq!(
+            layer.sightings()[0],
+            sighting(EntityKind::Robot(1), (3, 1), 12)
+        );
+        assert!(layer.is_near_hostile(3, 3, 2));
+        assert!(!layer.is_near_hostile(4, 3, 2));
+    }
+
+    #[test]
+    fn test_stale_sightings_expire() {
+        let mut knowledge = RobotKnowledge::new(8, 6);
+        knowledge.record_sighting(sighting(EntityKind::Hostile, (2, 2), 10));
+        knowledge.record_sighting(sighting(EntityKind::Robot(3), (1, 1), 10));
+        knowledge.record_sighting(sighting(EntityKind::Storm, (4, 3), 10));
+
+        knowledge.expire_sightings(10 + config::ROBOT_SIGHTING_TTL_TICKS);
+        assert_eq!(knowledge.entities().sightings().len(), 2);
+        knowledge.expire_sightings(11 + config::HOSTILE_SIGHTING_TTL_TICKS);
+        assert!(knowledge.entities().sightings().is_empty());
+    }
+
+    #[test]
+    fn test_accuracy_flags_stale_resources() {
+        let mut map = Map::new(8, 6, 1);
+        map.set_walkable(1, 1);
+        map.add_resource(1, 1, ResourceType::MINERALS, 5);
+
+        let mut knowledge = RobotKnowledge::new(8, 6);
+        knowledge.observe_and_update(1, 1, &map);
+        assert_eq!(knowledge.accuracy_at(1, 1, &map), TileAccuracy::Accurate);
+        assert_eq!(knowledge.accuracy_at(2, 1, &map), TileAccuracy::Unknown);
+
+        map.remove_resource(1, 1);
+        assert_eq!(knowledge.accuracy_at(1, 1, &map), TileAccuracy::Wrong);
+        // The station tile plus the depleted deposit
+        assert_eq!(
+            knowledge.divergence(&map),
+            KnowledgeDivergence { known: 2, wrong: 1 }
+        );
+    }
 }
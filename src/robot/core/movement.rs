@@ -1,7 +1,8 @@
 use crate::map::noise::Map;
 use crate::robot::core::knowledge::{RobotKnowledge, TileInfo};
 use rand::seq::IndexedRandom;
-use rand::{rng, Rng};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
 #[derive(Debug, Clone, Copy, PartialEq, Hash)]
@@ -10,20 +11,32 @@ pub enum Direction {
     Down,
     Left,
     Right,
+    /// Hex grids only: to the neighbor above that is not straight up, see
+    /// `Topology::diagonal_shift`
+    UpDiagonal,
+    /// Hex grids only: to the neighbor below that is not straight down
+    DownDiagonal,
+    /// Through the shaft the robot stands on, to the layer above or below
+    Climb,
 }
 
 impl Direction {
-    pub fn all() -> [Self; 4] {
+    /// Every move; climbing leads nowhere off a shaft and diagonals nowhere on a square grid,
+    /// like walking into the map edge
+    pub fn all() -> [Self; 7] {
         [
             Direction::Up,
             Direction::Down,
             Direction::Left,
             Direction::Right,
+            Direction::UpDiagonal,
+            Direction::DownDiagonal,
+            Direction::Climb,
         ]
     }
 
-    pub fn random() -> Self {
-        let mut rng = rng();
+    /// One of the four straight moves, drawn from `rng`
+    pub fn random(rng: &mut dyn RngCore) -> Self {
         match rng.random_range(0..4) {
             0 => Direction::Up,
             1 => Direction::Down,
@@ -33,18 +46,26 @@ impl Direction {
     }
 }
 
+/// Tile reached by moving in `dir`, (x, y) itself when the move leads off the map
 pub fn next_position(x: usize, y: usize, dir: &Direction, map: &Map) -> (usize, usize) {
-    match dir {
-        Direction::Up if y > 0 => (x, y.saturating_sub(1)),
-        Direction::Down if y < map.height.saturating_sub(1) => (x, y + 1),
-        Direction::Left if x > 0 => (x.saturating_sub(1), y),
-        Direction::Right if x < map.width.saturating_sub(1) => (x + 1, y),
-        _ => (x, y),
-    }
+    let topology = map.topology();
+    let (dx, dy) = match dir {
+        Direction::Up => (0, -1),
+        Direction::Down => (0, 1),
+        Direction::Left => (-1, 0),
+        Direction::Right => (1, 0),
+        Direction::UpDiagonal | Direction::DownDiagonal if !topology.hex => return (x, y),
+        Direction::UpDiagonal => (topology.diagonal_shift(y), -1),
+        Direction::DownDiagonal => (topology.diagonal_shift(y), 1),
+        Direction::Climb => return map.shaft_exit(x, y).unwrap_or((x, y)),
+    };
+    topology.step(x, y, dx, dy).unwrap_or((x, y))
 }
 
+/// Whether a robot may step on the tile: on the map, not an obstacle, and out of reach of
+/// hostiles
 pub fn is_valid_move(x: usize, y: usize, map: &Map) -> bool {
-    x < map.width && y < map.height && !map.is_obstacle(x, y)
+    x < map.width && y < map.height && !map.is_obstacle(x, y) && !map.is_threatened(x, y)
 }
 
 pub fn smart_direction(
@@ -53,8 +74,8 @@ pub fn smart_direction(
     knowledge: &RobotKnowledge,
     visited_in_cycle: &HashSet<(usize, usize)>,
     map: &Map,
+    rng: &mut dyn RngCore,
 ) -> Option<Direction> {
-    let mut rng = rng();
     let mut resource_candidates = Vec::new();
     let mut walkable_candidates = Vec::new();
     let mut fallback_candidates = Vec::new();
@@ -62,7 +83,7 @@ pub fn smart_direction(
     for dir in Direction::all().iter() {
         let (nx, ny) = next_position(x, y, dir, map);
 
-        if (nx, ny) == (x, y) {
+        if (nx, ny) == (x, y) || knowledge.blocks_move((x, y), (nx, ny)) {
             continue;
         }
 
@@ -90,25 +111,27 @@ pub fn smart_direction(
 
     // Prioritize unvisited resources
     if !resource_candidates.is_empty() {
-        return resource_candidates.choose(&mut rng).copied();
+        return resource_candidates.choose(rng).copied();
     }
     // Then unvisited walkable tiles
     if !walkable_candidates.is_empty() {
-        return walkable_candidates.choose(&mut rng).copied();
+        return walkable_candidates.choose(rng).copied();
     }
     // Finally, already visited
     if !fallback_candidates.is_empty() {
-        return fallback_candidates.choose(&mut rng).copied();
+        return fallback_candidates.choose(rng).copied();
     }
 
     None
 }
 
-/// UI/Animation
-#[derive(Debug, Clone, Copy)]
+/// Floating-point pose of a robot in continuous movement mode. Tile (x, y) has its center at
+/// (x, y), and the heading is in radians, 0 towards +x and growing towards +y.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SmoothPos {
     pub x: f32,
     pub y: f32,
+    pub heading: f32,
 }
 
 impl SmoothPos {
@@ -116,8 +139,10 @@ impl SmoothPos {
         Self {
             x: x as f32,
             y: y as f32,
+            heading: 0.0,
         }
     }
+
     pub fn move_towards(&mut self, tx: usize, ty: usize, speed: f32) {
         let dx = (tx as f32) - self.x;
         let dy = (ty as f32) - self.y;
@@ -134,4 +159,98 @@ impl SmoothPos {
             self.y = ty as f32;
         }
     }
+
+    /// Tile the pose lies in
+    pub fn tile(&self) -> (usize, usize) {
+        (
+            self.x.round().max(0.0) as usize,
+            self.y.round().max(0.0) as usize,
+        )
+    }
+
+    /// Turns towards the center of tile (tx, ty) by at most `max_turn`, then drives forward by
+    /// up to `speed` once facing it within a right angle. The pose stays put when the tile it
+    /// would enter is blocked. Returns whether the pose moved.
+    pub fn drive_towards(
+        &mut self,
+        (tx, ty): (usize, usize),
+        speed: f32,
+        max_turn: f32,
+        map: &Map,
+    ) -> bool {
+        let (dx, dy) = (tx as f32 - self.x, ty as f32 - self.y);
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist < 0.0001 {
+            return false;
+        }
+
+        let error = wrap_angle(dy.atan2(dx) - self.heading);
+        self.heading = wrap_angle(self.heading + error.clamp(-max_turn, max_turn));
+        if error.abs() >= std::f32::consts::FRAC_PI_2 {
+            return false;
+        }
+
+        let step = speed.min(dist);
+        let next = SmoothPos {
+            x: self.x + self.heading.cos() * step,
+            y: self.y + self.heading.sin() * step,
+            heading: self.heading,
+        };
+        let (nx, ny) = next.tile();
+        if next.x < -0.5 || next.y < -0.5 {
+            return false;
+        }
+        if (nx, ny) != self.tile() && !is_valid_move(nx, ny, map) {
+            return false;
+        }
+        *self = next;
+        // Close enough to snap, so a robot does not circle around its target center
+        if (tx as f32 - self.x).abs() + (ty as f32 - self.y).abs() < 0.01 {
+            self.x = tx as f32;
+            self.y = ty as f32;
+        }
+        true
+    }
+}
+
+/// Angle brought back within (-pi, pi]
+fn wrap_angle(angle: f32) -> f32 {
+    let turn = std::f32::consts::TAU;
+    let angle = angle.rem_euclid(turn);
+    if angle > std::f32::consts::PI {
+        angle - turn
+    } else {
+        angle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_continuous_pose_turns_gradually_and_stops_at_obstacles() {
+        let mut map = Map::new(10, 10, 3);
+        for y in 0..10 {
+            for x in 0..10 {
+                map.set_walkable(x, y);
+            }
+        }
+        map.add_crater(2, 1, 0, &HashSet::new());
+        let quarter = std::f32::consts::FRAC_PI_4;
+
+        // Facing +x, the robot first turns towards the tile below
+        let mut pose = SmoothPos::new(1, 1);
+        assert!(!pose.drive_towards((1, 2), 0.4, quarter, &map));
+        assert!(pose.drive_towards((1, 2), 0.4, quarter, &map));
+        assert_eq!(pose.heading, std::f32::consts::FRAC_PI_2);
+        assert!(pose.drive_towards((1, 2), 0.4, quarter, &map));
+        assert_eq!(pose.tile(), (1, 2));
+
+        // Heading into the obstacle tile keeps the pose in its own tile
+        let mut pose = SmoothPos::new(1, 1);
+        assert!(pose.drive_towards((2, 1), 0.4, quarter, &map));
+        assert!(!pose.drive_towards((2, 1), 0.4, quarter, &map));
+        assert_eq!(pose.tile(), (1, 1));
+    }
 }
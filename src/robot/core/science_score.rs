@@ -0,0 +1,122 @@
+//! How a scientist ranks the known science deposits it could head for. The ranking is a
+//! `ScienceScorer`, swapped with `ScientificRobot::set_science_scorer` to try others; runs
+//! pick one of the built-in ones with `ScienceTargeting`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// A known science deposit as a scientist weighs it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScienceCandidate {
+    pub tile: (usize, usize),
+    /// Science the deposit yields analyzed with the robot's modules
+    pub value: u32,
+    /// Moves from the robot to the deposit
+    pub moves: usize,
+    /// Energy the moves and the analysis take, the modules' passive cost included
+    pub energy_cost: u32,
+    /// Energy the robot can spend before it has to head back
+    pub spare_energy: u32,
+}
+
+/// Rates science deposits for a scientist to head for
+pub trait ScienceScorer: Send + Sync + fmt::Debug {
+    /// How worth heading for `candidate` is, the higher the better; `None` rules it out
+    fn score(&self, candidate: &ScienceCandidate) -> Option<f64>;
+}
+
+/// A scorer shared by the robots it was handed to
+pub type SharedScorer = Arc<dyn ScienceScorer>;
+
+/// The nearest deposit first, whatever it is worth
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Nearest;
+
+impl ScienceScorer for Nearest {
+    fn score(&self, candidate: &ScienceCandidate) -> Option<f64> {
+        Some(-(candidate.moves as f64))
+    }
+}
+
+/// Science per unit of energy spent getting it, leaving out the deposits the spare energy
+/// does not cover
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExpectedValue;
+
+impl ScienceScorer for ExpectedValue {
+    fn score(&self, candidate: &ScienceCandidate) -> Option<f64> {
+        (candidate.energy_cost <= candidate.spare_energy)
+            .then(|| f64::from(candidate.value) / f64::from(candidate.energy_cost.max(1)))
+    }
+}
+
+/// The built-in scorers, e.g. for `--science-targeting`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScienceTargeting {
+    /// The nearest deposit first, whatever it is worth
+    Nearest,
+    /// The most science for the energy the trip and the analysis take, among the deposits
+    /// within reach
+    #[default]
+    ExpectedValue,
+}
+
+impl ScienceTargeting {
+    pub fn scorer(self) -> SharedScorer {
+        match self {
+            ScienceTargeting::Nearest => Arc::new(Nearest),
+            ScienceTargeting::ExpectedValue => Arc::new(ExpectedValue),
+        }
+    }
+}
+
+/// The candidate `scorer` rates highest with its score, ties going to the lowest coordinates
+pub fn best(
+    candidates: impl IntoIterator<Item = ScienceCandidate>,
+    scorer: &dyn ScienceScorer,
+) -> Option<(ScienceCandidate, f64)> {
+    candidates
+        .into_iter()
+        .filter_map(|candidate| Some((candidate, scorer.score(&candidate)?)))
+        .max_by(|(a, a_score), (b, b_score)| {
+            a_score
+                .total_cmp(b_score)
+                .then_with(|| (b.tile.1, b.tile.0).cmp(&(a.tile.1, a.tile.0)))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(tile: (usize, usize), value: u32, moves: usize) -> ScienceCandidate {
+        ScienceCandidate {
+            tile,
+            value,
+            moves,
+            energy_cost: moves as u32 * 5 + 10,
+            spare_energy: 200,
+        }
+    }
+
+    #[test]
+    fn test_expected_value_weighs_science_against_travel() {
+        let near_poor = candidate((2, 0), 5, 2);
+        let far_rich = candidate((9, 0), 60, 9);
+        let out_of_reach = candidate((40, 0), 500, 40);
+        let candidates = [near_poor, far_rich, out_of_reach];
+
+        let (chosen, _) = best(candidates, &ExpectedValue).unwrap();
+        assert_eq!(chosen.tile, (9, 0));
+        let (chosen, _) = best(candidates, &Nearest).unwrap();
+        assert_eq!(chosen.tile, (2, 0));
+
+        assert!(best([out_of_reach], &ExpectedValue).is_none());
+        // Equal scores go to the lowest coordinates, whatever the order
+        let twins = [candidate((1, 3), 5, 2), candidate((4, 1), 5, 2)];
+        let (chosen, _) = best(twins, &Nearest).unwrap();
+        assert_eq!(chosen.tile, (4, 1));
+    }
+}
@@ -0,0 +1,302 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+
+use crate::communication::channels::ResourceType;
+use crate::map::topology::Topology;
+use crate::memory;
+use crate::robot::core::knowledge::TileInfo;
+
+/// Side of the square cells tiles are bucketed in
+const CELL_SIDE: usize = 8;
+
+type Tile = (usize, usize);
+
+/// A set of tiles bucketed into square cells, so the tiles nearest to a point are found by
+/// opening the closest cells first and stopping once no farther cell can hold a nearer tile,
+/// instead of measuring every tile
+#[derive(Clone, Debug, Default)]
+pub struct SpatialIndex {
+    /// Non-empty cells only
+    cells: HashMap<Tile, BTreeSet<Tile>>,
+    len: usize,
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the tile, returns whether it was new
+    pub fn insert(&mut self, tile: Tile) -> bool {
+        let added = self.cells.entry(cell_of(tile)).or_default().insert(tile);
+        self.len += usize::from(added);
+        added
+    }
+
+    /// Takes the tile out, returns whether it was there
+    pub fn remove(&mut self, tile: Tile) -> bool {
+        let cell = cell_of(tile);
+        let Some(tiles) = self.cells.get_mut(&cell) else {
+            return false;
+        };
+        let removed = tiles.remove(&tile);
+        if tiles.is_empty() {
+            self.cells.remove(&cell);
+        }
+        self.len -= usize::from(removed);
+        removed
+    }
+
+    pub fn contains(&self, tile: Tile) -> bool {
+        self.cells
+            .get(&cell_of(tile))
+            .is_some_and(|tiles| tiles.contains(&tile))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Approximate bytes held, see `crate::memory`
+    pub fn approx_bytes(&self) -> usize {
+        memory::hash_map_bytes::<Tile, BTreeSet<Tile>>(self.cells.len())
+            + memory::btree_bytes::<Tile>(self.len)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Tile> + '_ {
+        self.cells.values().flatten().copied()
+    }
+
+    /// The tiles with their distance from `from` on `topology`, nearest first, ties going to
+    /// the lowest coordinates. Cells are opened lazily, so taking the first few is cheap.
+    pub fn nearest_first<'a>(&'a self, from: Tile, topology: &'a Topology) -> NearestFirst<'a> {
+        let mut cells: Vec<(usize, Tile)> = self
+            .cells
+            .keys()
+            .map(|&cell| (cell_lower_bound(from, cell, topology), cell))
+            .collect();
+        cells.sort_unstable();
+        NearestFirst {
+            index: self,
+            from,
+            topology,
+            cells,
+            next_cell: 0,
+            candidates: BinaryHeap::new(),
+        }
+    }
+
+    /// The nearest tile `accept` takes, with its distance, see `nearest_first`
+    pub fn nearest(
+        &self,
+        from: Tile,
+        topology: &Topology,
+        mut accept: impl FnMut(Tile) -> bool,
+    ) -> Option<(Tile, usize)> {
+        self.nearest_first(from, topology)
+            .find(|&(tile, _)| accept(tile))
+    }
+}
+
+/// Iterator of `SpatialIndex::nearest_first`
+pub struct NearestFirst<'a> {
+    index: &'a SpatialIndex,
+    from: Tile,
+    topology: &'a Topology,
+    /// Cells by the least distance a tile of theirs can be at
+    cells: Vec<(usize, Tile)>,
+    next_cell: usize,
+    candidates: BinaryHeap<Reverse<(usize, Tile)>>,
+}
+
+impl Iterator for NearestFirst<'_> {
+    type Item = (Tile, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let unopened = self.cells.get(self.next_cell);
+            match (self.candidates.peek(), unopened) {
+                // Strictly nearer than any unopened tile, so ties come out in tile order
+                (Some(&Reverse((distance, tile))), next)
+                    if next.is_none_or(|&(bound, _)| distance < bound) =>
+                {
+                    self.candidates.pop();
+                    return Some((tile, distance));
+                }
+                (_, Some(&(_, cell))) => {
+                    self.next_cell += 1;
+                    let (from, topology) = (self.from, self.topology);
+                    self.candidates.extend(
+                        self.index.cells[&cell]
+                            .iter()
+                            .map(|&tile| Reverse((topology.distance(from, tile), tile))),
+                    );
+                }
+                (_, None) => return None,
+            }
+        }
+    }
+}
+
+fn cell_of((x, y): Tile) -> Tile {
+    (x / CELL_SIDE, y / CELL_SIDE)
+}
+
+/// Fewest moves from `from` to any tile of `cell`. A move changes each coordinate by one at
+/// most, on square and hex grids alike, so the larger of the two gaps is never too much; a
+/// climb changes the row alone.
+fn cell_lower_bound(from: Tile, (cell_x, cell_y): Tile, topology: &Topology) -> usize {
+    let gap = |at: usize, cell: usize, len: usize| {
+        let (low, high) = (cell * CELL_SIDE, cell * CELL_SIDE + CELL_SIDE - 1);
+        let straight = if at < low {
+            low - at
+        } else {
+            at.saturating_sub(high)
+        };
+        if topology.wraps {
+            // The other way round is no shorter than the length minus the far side's gap
+            let far = at.abs_diff(low).max(at.abs_diff(high));
+            straight.min(len.saturating_sub(far))
+        } else {
+            straight
+        }
+    };
+    let across = gap(from.0, cell_x, topology.width);
+    // Tiles on another layer are reached through shafts, which keep the column
+    let layer = topology.layer_of(from.1);
+    let rows = [cell_y * CELL_SIDE, cell_y * CELL_SIDE + CELL_SIDE - 1];
+    if rows.iter().all(|&y| topology.layer_of(y) == layer) {
+        across.max(gap(from.1, cell_y, topology.layer_height))
+    } else {
+        across
+    }
+}
+
+/// Where the deposits of each type and the unknown tiles of a knowledge map are, kept in
+/// step with its tiles
+#[derive(Clone, Debug, Default)]
+pub struct TileIndex {
+    deposits: HashMap<ResourceType, SpatialIndex>,
+    unknown: SpatialIndex,
+}
+
+impl TileIndex {
+    /// Moves the tile from where `before` was indexed to where `after` goes
+    pub fn update(&mut self, tile: Tile, before: Option<&TileInfo>, after: &TileInfo) {
+        match before {
+            Some(TileInfo::Unknown) => {
+                self.unknown.remove(tile);
+            }
+            Some(TileInfo::Resource(resource_type, _)) => {
+                if let Some(deposits) = self.deposits.get_mut(resource_type) {
+                    deposits.remove(tile);
+                }
+            }
+            _ => {}
+        }
+        match after {
+            TileInfo::Unknown => {
+                self.unknown.insert(tile);
+            }
+            // Worked-out deposits are plain ground to whoever looks for resources
+            TileInfo::Resource(resource_type, amount) if *amount > 0 => {
+                self.deposits
+                    .entry(*resource_type)
+                    .or_default()
+                    .insert(tile);
+            }
+            _ => {}
+        }
+    }
+
+    /// Deposits of `resource_type` with something left in them
+    pub fn deposits(&self, resource_type: &ResourceType) -> Option<&SpatialIndex> {
+        self.deposits.get(resource_type)
+    }
+
+    pub fn unknown(&self) -> &SpatialIndex {
+        &self.unknown
+    }
+
+    /// Approximate bytes held, see `crate::memory`
+    pub fn approx_bytes(&self) -> usize {
+        memory::hash_map_bytes::<ResourceType, SpatialIndex>(self.deposits.len())
+            + self
+                .deposits
+                .values()
+                .map(SpatialIndex::approx_bytes)
+                .sum::<usize>()
+            + self.unknown.approx_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_first_matches_a_full_scan_on_every_topology() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut index = SpatialIndex::new();
+        let tiles: Vec<Tile> = (0..300)
+            .map(|_| (rng.random_range(0..70), rng.random_range(0..50)))
+            .collect();
+        for &tile in &tiles {
+            index.insert(tile);
+        }
+        assert!(index.insert((70, 50)));
+        assert!(!index.insert((70, 50)));
+        assert!(index.remove((70, 50)));
+        assert!(!index.contains((70, 50)));
+
+        // Flat, then in two layers of 24 rows joined by two shafts
+        let layers = [
+            (50, Vec::new()),
+            (
+                24,
+                vec![
+                    ((10, 5), (10, 30)),
+                    ((10, 30), (10, 5)),
+                    ((60, 20), (60, 45)),
+                    ((60, 45), (60, 20)),
+                ],
+            ),
+        ];
+        for ((wraps, hex), (layer_height, shafts)) in
+            [(false, false), (true, false), (false, true), (true, true)]
+                .into_iter()
+                .flat_map(|shape| layers.iter().map(move |layers| (shape, layers)))
+        {
+            let topology = Topology {
+                width: 70,
+                height: 50,
+                layer_height: *layer_height,
+                wraps,
+                hex,
+                shafts: shafts.clone().into(),
+            };
+            for from in [(0, 0), (35, 25), (69, 49), (3, 47)] {
+                let mut scan: Vec<(usize, Tile)> = index
+                    .iter()
+                    .map(|tile| (topology.distance(from, tile), tile))
+                    .collect();
+                scan.sort_unstable();
+                let nearest: Vec<(usize, Tile)> = index
+                    .nearest_first(from, &topology)
+                    .map(|(tile, distance)| (distance, tile))
+                    .collect();
+                assert_eq!(
+                    nearest, scan,
+                    "wraps {}, hex {}, layers of {}, from {:?}",
+                    wraps, hex, layer_height, from
+                );
+            }
+        }
+    }
+}
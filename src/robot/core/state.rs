@@ -1,7 +1,12 @@
 use crate::communication::channels::ResourceType;
+use crate::map::noise::Map;
+use crate::map::resources::ResourceCatalog;
+use crate::robot::core::movement::SmoothPos;
+use crate::robot::utils::config;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RobotStatus {
     Idle,
     Exploring,
@@ -11,16 +16,25 @@ pub enum RobotStatus {
     AtStation,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RobotState {
     pub id: u32,
     pub x: usize,
     pub y: usize,
     pub energy: u32,
     pub max_energy: u32,
+    /// Capacity of the battery when new; `max_energy` shrinks below it as the battery wears
+    pub rated_energy: u32,
+    /// Recharges since the battery was fitted
+    pub charge_cycles: u32,
+    /// Extra energy cost of every action, in percent, from wear since the last maintenance
+    pub wear_percent: u32,
     pub collected_resources: HashMap<ResourceType, u32>,
     pub max_capacity: u32,
     pub status: RobotStatus,
+    /// Continuous movement mode only: where the robot is within its tiles and where it faces.
+    /// (x, y) is then the tile the pose lies in, which sensing still works on.
+    pub pose: Option<SmoothPos>,
 }
 
 impl RobotState {
@@ -37,13 +51,57 @@ impl RobotState {
             y: start_y,
             energy: max_energy,
             max_energy,
+            rated_energy: max_energy,
+            charge_cycles: 0,
+            wear_percent: 0,
             collected_resources: HashMap::new(),
             max_capacity: 700,
             status: initial_status,
+            pose: None,
         }
     }
 
+    /// Drives on a continuous pose instead of hopping between tiles, see `move_to`
+    pub fn with_pose(mut self) -> Self {
+        self.pose = Some(SmoothPos::new(self.x, self.y));
+        self
+    }
+
+    /// Moves towards the neighboring tile (x, y). Without a pose the robot gets there at once;
+    /// with one it drives a stretch towards the tile and its tile follows the pose, so getting
+    /// there takes a few moves. Tiles that are not next to the robot's, across a wrapping edge
+    /// or up a shaft, are reached at once either way. Returns whether the robot is on (x, y).
+    pub fn move_to(&mut self, x: usize, y: usize, map: &Map) -> bool {
+        let adjacent = self.x.abs_diff(x) <= 1 && self.y.abs_diff(y) <= 1;
+        match &mut self.pose {
+            Some(pose) if adjacent => {
+                pose.drive_towards(
+                    (x, y),
+                    config::CONTINUOUS_SPEED,
+                    config::CONTINUOUS_MAX_TURN,
+                    map,
+                );
+                (self.x, self.y) = pose.tile();
+            }
+            _ => self.teleport(x, y),
+        }
+        (self.x, self.y) == (x, y)
+    }
+
+    /// Puts the robot on (x, y) at once, keeping its heading
+    pub fn teleport(&mut self, x: usize, y: usize) {
+        if let Some(pose) = &mut self.pose {
+            *pose = SmoothPos {
+                heading: pose.heading,
+                ..SmoothPos::new(x, y)
+            };
+        }
+        (self.x, self.y) = (x, y);
+    }
+
+    /// Spends `amount` energy, more when the robot is worn
     pub fn use_energy(&mut self, amount: u32) -> bool {
+        let amount = (amount * (100 + self.wear_percent)).div_ceil(100);
         if self.energy >= amount {
             self.energy -= amount;
             true
@@ -53,10 +111,42 @@ impl RobotState {
         }
     }
 
-    pub fn collect_resource(&mut self, resource_type: ResourceType, amount: u32) -> bool {
-        let current_total: u32 = self.collected_resources.values().sum();
+    /// Fills the battery, which loses a little capacity with every charge cycle
+    pub fn recharge(&mut self) {
+        self.charge_cycles += 1;
+        let wear = self.rated_energy * config::BATTERY_WEAR_PERMILLE / 1000;
+        self.max_energy = self.max_energy.saturating_sub(wear).max(1);
+        self.energy = self.max_energy;
+    }
+
+    /// Whether the battery has worn down enough to be replaced
+    pub fn battery_worn(&self) -> bool {
+        self.max_energy * 100 < self.rated_energy * config::BATTERY_WORN_PERCENT
+    }
 
-        if current_total + amount <= self.max_capacity {
+    /// Fits a new, fully charged battery
+    pub fn refit_battery(&mut self) {
+        self.max_energy = self.rated_energy;
+        self.energy = self.max_energy;
+        self.charge_cycles = 0;
+    }
+
+    /// Cargo room taken, each resource weighing its units times its weight in `resources`
+    pub fn cargo_load(&self, resources: &ResourceCatalog) -> u32 {
+        self.collected_resources
+            .iter()
+            .map(|(&resource_type, &amount)| amount.saturating_mul(resources.weight(resource_type)))
+            .sum()
+    }
+
+    pub fn collect_resource(
+        &mut self,
+        resource_type: ResourceType,
+        amount: u32,
+        resources: &ResourceCatalog,
+    ) -> bool {
+        let load = amount.saturating_mul(resources.weight(resource_type));
+        if self.cargo_load(resources) + load <= self.max_capacity {
             *self.collected_resources.entry(resource_type).or_insert(0) += amount;
             true
         } else {
@@ -64,12 +154,33 @@ impl RobotState {
         }
     }
 
-    pub fn is_full(&self) -> bool {
-        let current_capacity = self.collected_resources.values().sum::<u32>();
-        current_capacity >= self.max_capacity
+    pub fn is_full(&self, resources: &ResourceCatalog) -> bool {
+        self.cargo_load(resources) >= self.max_capacity
     }
 
     pub fn needs_recharge(&self) -> bool {
         self.energy < 20
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_battery_wears_with_each_charge_until_refitted() {
+        let mut state = RobotState::new(1, 0, 0, RobotStatus::Exploring, 500);
+        let mut cycles = 0;
+        while !state.battery_worn() {
+            state.recharge();
+            cycles += 1;
+            assert_eq!(state.energy, state.max_energy);
+        }
+        assert_eq!((cycles, state.charge_cycles), (31, 31));
+        assert_eq!(state.max_energy, 345);
+
+        state.refit_battery();
+        assert_eq!((state.max_energy, state.energy), (500, 500));
+        assert_eq!(state.charge_cycles, 0);
+    }
+}
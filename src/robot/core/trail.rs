@@ -0,0 +1,73 @@
+/// Tiles walked since leaving a starting tile, with every loop cut out, so the way back
+/// retraces a known route instead of being searched again step by step
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trail {
+    tiles: Vec<(usize, usize)>,
+}
+
+impl Trail {
+    /// Trail anchored at `start`
+    pub fn starting_at(start: (usize, usize)) -> Self {
+        Self { tiles: vec![start] }
+    }
+
+    /// Records a step onto `tile`. Stepping back onto a tile of the trail drops the detour
+    /// made since. An empty trail has no anchor and records nothing.
+    pub fn record(&mut self, tile: (usize, usize)) {
+        if self.tiles.is_empty() {
+            return;
+        }
+        match self.tiles.iter().position(|&visited| visited == tile) {
+            Some(index) => self.tiles.truncate(index + 1),
+            None => self.tiles.push(tile),
+        }
+    }
+
+    /// Next tile back towards the anchor from `current`, `None` once there or if `current`
+    /// is not on the trail
+    pub fn next_back(&mut self, current: (usize, usize)) -> Option<(usize, usize)> {
+        let index = self.tiles.iter().rposition(|&tile| tile == current)?;
+        self.tiles.truncate(index + 1);
+        index.checked_sub(1).map(|previous| self.tiles[previous])
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trail_leads_back_without_the_detours() {
+        let mut unanchored = Trail::default();
+        unanchored.record((1, 0));
+        assert!(unanchored.is_empty());
+
+        let mut trail = Trail::starting_at((0, 0));
+        for tile in [(1, 0), (1, 1), (2, 1), (2, 0), (1, 0), (2, 0), (3, 0)] {
+            trail.record(tile);
+        }
+        // The loop through (1, 1) and (2, 1) is gone
+        assert_eq!(trail.len(), 4);
+
+        let mut current = (3, 0);
+        let mut way_back = Vec::new();
+        while let Some(tile) = trail.next_back(current) {
+            way_back.push(tile);
+            current = tile;
+        }
+        assert_eq!(way_back, vec![(2, 0), (1, 0), (0, 0)]);
+        assert_eq!(trail.next_back((5, 5)), None);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::map::noise::Map;
+use crate::robot::core::knowledge::RobotKnowledge;
+use crate::robot::core::movement::{self, Direction};
+use crate::robot::core::state::RobotState;
+use crate::robot::utils::common;
+use rand::RngCore;
+
+/// Consecutive blocked steps after which a robot gives up on a waypoint
+const MAX_BLOCKED_STEPS: u32 = 20;
+
+/// Outcome of a single step towards a waypoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaypointStep {
+    Moved,
+    Blocked,
+    Reached,
+    GaveUp,
+}
+
+/// Destination imposed by the station, taking priority over the robot's own task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Waypoint {
+    pub target: (usize, usize),
+    blocked_steps: u32,
+}
+
+impl Waypoint {
+    pub fn new(x: usize, y: usize) -> Self {
+        Self {
+            target: (x, y),
+            blocked_steps: 0,
+        }
+    }
+
+    /// Moves the robot one tile towards the target, observing the tiles around its new
+    /// position on the way. Detours are drawn from `rng`.
+    pub fn step(
+        &mut self,
+        state: &mut RobotState,
+        knowledge: &mut RobotKnowledge,
+        map: &Map,
+        movement_cost: u32,
+        rng: &mut dyn RngCore,
+    ) -> WaypointStep {
+        if (state.x, state.y) == self.target {
+            return WaypointStep::Reached;
+        }
+
+        let direction = common::move_towards_target(
+            state.x,
+            state.y,
+            self.target.0,
+            self.target.1,
+            knowledge,
+            map,
+            rng,
+        );
+        let (new_x, new_y) = movement::next_position(state.x, state.y, &direction, map);
+
+        if (new_x, new_y) != (state.x, state.y)
+            && movement::is_valid_move(new_x, new_y, map)
+            && !knowledge.blocks_move((state.x, state.y), (new_x, new_y))
+        {
+            state.move_to(new_x, new_y, map);
+            state.use_energy(movement_cost);
+            observe_around(knowledge, new_x, new_y, map);
+            self.blocked_steps = 0;
+            if (state.x, state.y) == self.target {
+                WaypointStep::Reached
+            } else {
+                WaypointStep::Moved
+            }
+        } else {
+            self.blocked_steps += 1;
+            if self.blocked_steps >= MAX_BLOCKED_STEPS {
+                WaypointStep::GaveUp
+            } else {
+                WaypointStep::Blocked
+            }
+        }
+    }
+}
+
+/// Closed route of waypoints visited in order, over and over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatrolRoute {
+    points: Vec<(usize, usize)>,
+    next: usize,
+}
+
+impl PatrolRoute {
+    /// Creates a route through `points`, `None` if there are none
+    pub fn new(points: Vec<(usize, usize)>) -> Option<Self> {
+        if points.is_empty() {
+            None
+        } else {
+            Some(Self { points, next: 0 })
+        }
+    }
+
+    pub fn points(&self) -> &[(usize, usize)] {
+        &self.points
+    }
+
+    /// Waypoint to the next point of the route, wrapping around after the last one
+    pub fn next_waypoint(&mut self) -> Waypoint {
+        let (x, y) = self.points[self.next];
+        self.next = (self.next + 1) % self.points.len();
+        Waypoint::new(x, y)
+    }
+}
+
+fn observe_around(knowledge: &mut RobotKnowledge, x: usize, y: usize, map: &Map) {
+    knowledge.observe_and_update(x, y, map);
+    for dir in Direction::all().iter() {
+        let (nx, ny) = movement::next_position(x, y, dir, map);
+        if (nx, ny) != (x, y) {
+            knowledge.observe_and_update(nx, ny, map);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::core::knowledge::TileInfo;
+    use crate::robot::core::state::RobotStatus;
+    use crate::robot::utils::rng;
+
+    fn open_map(width: usize, height: usize) -> Map {
+        let mut map = Map::new(width, height, 1);
+        for y in 0..height {
+            for x in 0..width {
+                map.set_walkable(x, y);
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn test_waypoint_is_reached_on_open_ground() {
+        let map = open_map(10, 10);
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        let mut state = RobotState::new(1, 0, 0, RobotStatus::Exploring, 100);
+        let mut waypoint = Waypoint::new(3, 2);
+        let mut rng = rng::seeded(1);
+
+        let mut steps = 0;
+        while waypoint.step(&mut state, &mut knowledge, &map, 1, &mut rng) != WaypointStep::Reached
+        {
+            steps += 1;
+            assert!(steps < 50, "waypoint never reached");
+        }
+        assert_eq!((state.x, state.y), (3, 2));
+        assert_eq!(state.energy, 95);
+        assert_eq!(knowledge.get_tile(3, 3), &TileInfo::Walkable);
+    }
+
+    #[test]
+    fn test_waypoint_detours_around_no_go_tiles() {
+        let map = open_map(10, 10);
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        knowledge.set_exclusions([(1, 0), (2, 0)]);
+        let mut state = RobotState::new(1, 0, 0, RobotStatus::Exploring, 100);
+        let mut waypoint = Waypoint::new(3, 0);
+        let mut rng = rng::seeded(1);
+
+        let mut steps = 0;
+        while waypoint.step(&mut state, &mut knowledge, &map, 1, &mut rng) != WaypointStep::Reached
+        {
+            assert!(!knowledge.is_excluded(state.x, state.y));
+            steps += 1;
+            assert!(steps < 50, "waypoint never reached");
+        }
+
+        // A robot caught inside a zone may still walk out of it
+        assert!(knowledge.blocks_move((0, 0), (1, 0)));
+        assert!(!knowledge.blocks_move((1, 0), (2, 0)));
+    }
+
+    #[test]
+    fn test_patrol_route_wraps_around() {
+        assert!(PatrolRoute::new(Vec::new()).is_none());
+
+        let mut route = PatrolRoute::new(vec![(1, 1), (4, 2)]).unwrap();
+        let targets: Vec<_> = (0..5).map(|_| route.next_waypoint().target).collect();
+        assert_eq!(targets, vec![(1, 1), (4, 2), (1, 1), (4, 2), (1, 1)]);
+    }
+}
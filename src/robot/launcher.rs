@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::communication::channels::{CommandEnvelope, EventSender, ResourceType, RobotEvent};
+use crate::map::resources::ResourceCatalog;
+use crate::map::shared::SharedMap;
+use crate::robot::behavior::collection::CollectionRobot;
+use crate::robot::behavior::exploration::ExplorationRobot;
+use crate::robot::behavior::scientific::ScientificRobot;
+use crate::robot::behavior::scout::ScoutRobot;
+use crate::robot::core::knowledge::RobotKnowledge;
+use crate::robot::core::science_score::ScienceTargeting;
+use crate::robot::core::state::{RobotState, RobotStatus};
+use crate::robot::registry::RobotType;
+use crate::robot::utils::clock::{self, SharedClock};
+use crate::robot::utils::config;
+use crate::robot::utils::inbox::InboxGauge;
+use crate::robot::utils::preset::{RobotTuning, SwarmPreset};
+use crate::robot::utils::rng;
+use crate::robot::utils::trace::DecisionTrace;
+use crate::settings::{ModuleSpec, DRILL_MODULE};
+
+/// What a robot is fitted with before its thread starts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RobotLoadout {
+    /// Collection robots only: resource to gather first
+    pub collector_target: Option<ResourceType>,
+    /// Mounted modules: any for scientific robots, the drill for collection robots
+    pub modules: Vec<ModuleSpec>,
+    /// Spread applied to the robot's thresholds, sleeps and costs, none for the stock ones
+    #[serde(default)]
+    pub jitter: Option<ParameterJitter>,
+    /// Seed of the robot's random moves and pauses, drawn from the system if none
+    #[serde(default)]
+    pub decision_seed: Option<u64>,
+    /// Swarm strategy the robot starts under
+    #[serde(default)]
+    pub preset: SwarmPreset,
+    /// Scientific robots only: how they rank the science deposits to head for
+    #[serde(default)]
+    pub science_targeting: ScienceTargeting,
+}
+
+/// How far a robot's parameters stray from its type's, drawn from `seed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParameterJitter {
+    pub percent: u32,
+    pub seed: u64,
+}
+
+impl RobotLoadout {
+    pub fn has_drill(&self) -> bool {
+        self.modules
+            .iter()
+            .any(|module| module.name == DRILL_MODULE)
+    }
+}
+
+/// State of a freshly built robot of the given type, with a pose in continuous movement mode
+pub fn initial_state(robot_type: RobotType, id: u32, x: usize, y: usize) -> RobotState {
+    let (status, max_energy) = match robot_type {
+        RobotType::Exploration => (RobotStatus::Exploring, config::EXPLORATION_ROBOT_MAX_ENERGY),
+        RobotType::Collection => (RobotStatus::Collecting, config::COLLECTION_ROBOT_MAX_ENERGY),
+        RobotType::Scientific => (RobotStatus::Analyzing, config::SCIENTIFIC_ROBOT_MAX_ENERGY),
+        RobotType::Scout => (RobotStatus::Exploring, config::SCOUT_ROBOT_MAX_ENERGY),
+    };
+    let state = RobotState::new(id, x, y, status, max_energy);
+    if config::continuous_movement() {
+        state.with_pose()
+    } else {
+        state
+    }
+}
+
+fn decision_rng(loadout: &RobotLoadout) -> rng::RobotRng {
+    loadout
+        .decision_seed
+        .map_or_else(rng::from_entropy, rng::seeded)
+}
+
+/// What a robot is handed besides its state and channels, shared with the station or the
+/// test that runs it
+pub struct RobotRuntime {
+    /// Where the robot takes its pauses, e.g. a simulated clock in tests
+    pub clock: SharedClock,
+    /// Counts what the robot takes from its channels
+    pub gauge: InboxGauge,
+    /// Where the robot records its decisions
+    pub trace: DecisionTrace,
+    /// Draws the robot's random moves and pauses, e.g. a seeded one to replay a run
+    pub rng: rng::RobotRng,
+    /// Swarm strategy the robot starts under
+    pub preset: SwarmPreset,
+    /// Spread applied to the robot's thresholds, sleeps and costs, none for the stock ones
+    pub jitter: Option<ParameterJitter>,
+    /// Where the station actually is, the map center if none
+    pub station: Option<(usize, usize)>,
+    /// Resource types of the run the robot is part of
+    pub resources: Arc<ResourceCatalog>,
+}
+
+impl RobotRuntime {
+    /// Parameters of a `robot_type` robot built on `base`, jittered and under the preset
+    pub fn tuning(&self, robot_type: RobotType, base: &config::RobotTypeConfig) -> RobotTuning {
+        RobotTuning::new(robot_type, base, self.preset, self.jitter)
+    }
+
+    /// What a robot knows of a `width` x `height` map before it starts: the station
+    pub fn knowledge(&self, width: usize, height: usize) -> RobotKnowledge {
+        let mut knowledge = RobotKnowledge::new(width, height);
+        if let Some((x, y)) = self.station {
+            knowledge.set_station_coords(x, y);
+        }
+        knowledge
+    }
+}
+
+impl Default for RobotRuntime {
+    fn default() -> Self {
+        Self {
+            clock: clock::real(),
+            gauge: InboxGauge::default(),
+            trace: DecisionTrace::default(),
+            rng: rng::from_entropy(),
+            preset: SwarmPreset::default(),
+            jitter: None,
+            station: None,
+            resources: Arc::default(),
+        }
+    }
+}
+
+/// Channels a robot thread listens on, and what it shares with the station besides
+pub struct RobotInbox {
+    pub merge_receiver: Receiver<RobotEvent>,
+    pub command_receiver: Receiver<CommandEnvelope>,
+    /// Counts what the robot takes from the receivers, shared with the station
+    pub gauge: InboxGauge,
+    /// Where the robot records its decisions
+    pub trace: DecisionTrace,
+}
+
+/// Builds the robot logic for `state` and starts its thread, whether the station runs in
+/// the same process or across the network. The robot pauses on `clock`, and decides from the
+/// loadout's seed.
+pub fn launch(
+    robot_type: RobotType,
+    state: RobotState,
+    loadout: &RobotLoadout,
+    inbox: RobotInbox,
+    sender: EventSender,
+    map: SharedMap,
+    clock: SharedClock,
+) -> JoinHandle<()> {
+    let (map_width, map_height, station, surface_rows, topology, resources) = {
+        let map = map.read();
+        (
+            map.width,
+            map.height,
+            map.station_coords(),
+            map.layer_rows(0),
+            map.topology(),
+            map.catalog().clone(),
+        )
+    };
+    let RobotInbox {
+        merge_receiver,
+        command_receiver,
+        gauge,
+        trace,
+    } = inbox;
+    let runtime = RobotRuntime {
+        clock,
+        gauge,
+        trace,
+        rng: decision_rng(loadout),
+        preset: loadout.preset,
+        jitter: loadout.jitter,
+        station: Some(station),
+        resources,
+    };
+
+    match robot_type {
+        RobotType::Exploration => {
+            let robot = ExplorationRobot::new(
+                state,
+                map_width,
+                map_height,
+                merge_receiver,
+                command_receiver,
+                runtime,
+            );
+            robot.start(sender, map)
+        }
+        RobotType::Collection => {
+            let mut robot = CollectionRobot::new(
+                state,
+                map_width,
+                map_height,
+                merge_receiver,
+                command_receiver,
+                runtime,
+            );
+            if let Some(target) = &loadout.collector_target {
+                robot.set_target_resource(*target);
+            }
+            robot.set_surface_rows(surface_rows);
+            robot.set_topology(topology);
+            if let Some(drill) = loadout
+                .modules
+                .iter()
+                .find(|module| module.name == DRILL_MODULE)
+            {
+                robot.fit_drill(drill.energy_cost);
+            }
+            robot.start(sender, map)
+        }
+        RobotType::Scientific => {
+            let mut robot = ScientificRobot::new(
+                state,
+                map_width,
+                map_height,
+                merge_receiver,
+                command_receiver,
+                runtime,
+            );
+            for module in &loadout.modules {
+                robot.add_module(&module.name, module.science_bonus, module.energy_cost);
+            }
+            robot.set_science_scorer(loadout.science_targeting.scorer());
+            robot.start(sender, map)
+        }
+        RobotType::Scout => {
+            let robot = ScoutRobot::new(
+                state,
+                map_width,
+                map_height,
+                merge_receiver,
+                command_receiver,
+                runtime,
+            );
+            robot.start(sender, map)
+        }
+    }
+}
@@ -2,17 +2,31 @@ pub mod behavior {
     pub mod collection;
     pub mod exploration;
     pub mod scientific;
+    pub mod scout;
 }
 
 pub mod core {
+    pub mod flow_field;
     pub mod knowledge;
     pub mod movement;
+    pub mod science_score;
+    pub mod spatial_index;
     pub mod state;
+    pub mod trail;
+    pub mod waypoint;
 }
 
+pub mod launcher;
+pub mod registry;
+
 pub mod utils {
+    pub mod clock;
     pub mod common;
     pub mod config;
+    pub mod inbox;
+    pub mod preset;
+    pub mod rng;
+    pub mod trace;
 }
 
 // Re-export commonly used types if needed
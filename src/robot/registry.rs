@@ -0,0 +1,288 @@
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+
+use crate::communication::channels::{CommandEnvelope, ResourceType, RobotEvent};
+use crate::robot::core::knowledge::{KnowledgeDivergence, RobotKnowledge};
+use crate::robot::utils::inbox::InboxGauge;
+use crate::robot::utils::trace::DecisionTrace;
+use crate::robot::RobotState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RobotType {
+    Exploration,
+    Collection,
+    Scientific,
+    Scout,
+}
+
+/// What the station has learned about a robot from its reports
+#[derive(Debug, Clone, Default)]
+pub struct RobotTelemetry {
+    /// Resource a collector was last told to gather
+    pub collector_target: Option<ResourceType>,
+    /// Collector fitted with a drill, able to mine the caves
+    pub drill: bool,
+    /// Knowledge the robot reported when it last docked
+    pub last_knowledge: Option<RobotKnowledge>,
+    /// How far `last_knowledge` was from the real map as the robot docked
+    pub divergence: Option<KnowledgeDivergence>,
+    /// Tick of the last event received from the robot
+    pub last_report_tick: u64,
+    /// What the robot has yet to take from its command and merge channels
+    pub inbox: InboxGauge,
+    /// The robot's last decisions, if it records them
+    pub trace: DecisionTrace,
+}
+
+/// Everything the app keeps about one running robot
+pub struct RobotEntry {
+    pub robot_type: RobotType,
+    pub state: RobotState,
+    pub telemetry: RobotTelemetry,
+    command_sender: Sender<CommandEnvelope>,
+    merge_sender: Sender<RobotEvent>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RobotEntry {
+    pub fn new(
+        robot_type: RobotType,
+        state: RobotState,
+        command_sender: Sender<CommandEnvelope>,
+        merge_sender: Sender<RobotEvent>,
+    ) -> Self {
+        Self {
+            robot_type,
+            state,
+            telemetry: RobotTelemetry::default(),
+            command_sender,
+            merge_sender,
+            handle: None,
+        }
+    }
+
+    /// Keeps the robot thread so it can be joined once the robot shuts down
+    pub fn set_handle(&mut self, handle: JoinHandle<()>) {
+        self.handle = Some(handle);
+    }
+
+    /// Hands the robot the answer to its `ArrivedAtStation`, returns false if it is gone
+    pub fn send_merge(&self, event: RobotEvent) -> bool {
+        let sent = self.merge_sender.send(event).is_ok();
+        if sent {
+            self.telemetry.inbox.merge_sent();
+        }
+        sent
+    }
+
+    /// Joins the robot thread if it already exited, otherwise lets it finish on its own
+    fn finish(mut self) {
+        let id = self.state.id;
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+        if !handle.is_finished() {
+            debug!("Robot {} thread still winding down, detaching", id);
+        } else if handle.join().is_err() {
+            warn!("Robot {} thread panicked", id);
+        }
+    }
+}
+
+/// Running robots by id, whatever their type
+#[derive(Default)]
+pub struct RobotRegistry {
+    robots: BTreeMap<u32, RobotEntry>,
+}
+
+impl RobotRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, entry: RobotEntry) {
+        self.robots.insert(entry.state.id, entry);
+    }
+
+    /// Forgets a robot that shut down, returning its type
+    pub fn remove(&mut self, robot_id: u32) -> Option<RobotType> {
+        let entry = self.robots.remove(&robot_id)?;
+        let robot_type = entry.robot_type;
+        entry.finish();
+        Some(robot_type)
+    }
+
+    /// Hangs up on every robot and waits for their threads to stop
+    pub fn shutdown(&mut self) {
+        // Dropping every entry first lets all robots notice the closed channels at once
+        let handles: Vec<(u32, JoinHandle<()>)> = std::mem::take(&mut self.robots)
+            .into_values()
+            .filter_map(|mut entry| Some((entry.state.id, entry.handle.take()?)))
+            .collect();
+        for (id, handle) in handles {
+            if handle.join().is_err() {
+                warn!("Robot {} thread panicked", id);
+            }
+        }
+    }
+
+    pub fn get(&self, robot_id: u32) -> Option<&RobotEntry> {
+        self.robots.get(&robot_id)
+    }
+
+    pub fn get_mut(&mut self, robot_id: u32) -> Option<&mut RobotEntry> {
+        self.robots.get_mut(&robot_id)
+    }
+
+    pub fn state(&self, robot_id: u32) -> Option<&RobotState> {
+        self.get(robot_id).map(|entry| &entry.state)
+    }
+
+    pub fn state_mut(&mut self, robot_id: u32) -> Option<&mut RobotState> {
+        self.get_mut(robot_id).map(|entry| &mut entry.state)
+    }
+
+    pub fn robot_type(&self, robot_id: u32) -> Option<RobotType> {
+        self.get(robot_id).map(|entry| entry.robot_type)
+    }
+
+    /// Sends a command to a single robot, returns false if the robot is unknown or gone
+    pub fn send_command(&self, robot_id: u32, envelope: CommandEnvelope) -> bool {
+        match self.get(robot_id) {
+            Some(entry) => {
+                let sent = entry.command_sender.send(envelope).is_ok();
+                if sent {
+                    entry.telemetry.inbox.command_sent();
+                }
+                sent
+            }
+            None => {
+                warn!("Cannot send command to unknown robot ID: {}", robot_id);
+                false
+            }
+        }
+    }
+
+    /// All robots, by id
+    pub fn iter(&self) -> impl Iterator<Item = &RobotEntry> {
+        self.robots.values()
+    }
+
+    /// Robots of one type, by id
+    pub fn of_type(&self, robot_type: RobotType) -> impl Iterator<Item = &RobotEntry> {
+        self.iter()
+            .filter(move |entry| entry.robot_type == robot_type)
+    }
+
+    /// States of the robots of one type, by id
+    pub fn states_of(&self, robot_type: RobotType) -> impl Iterator<Item = &RobotState> {
+        self.of_type(robot_type).map(|entry| &entry.state)
+    }
+
+    /// Ids of all robots, sorted
+    pub fn ids(&self) -> Vec<u32> {
+        self.robots.keys().copied().collect()
+    }
+
+    /// Ids of the robots of one type, sorted
+    pub fn ids_of(&self, robot_type: RobotType) -> Vec<u32> {
+        self.of_type(robot_type)
+            .map(|entry| entry.state.id)
+            .collect()
+    }
+
+    pub fn count_of(&self, robot_type: RobotType) -> usize {
+        self.of_type(robot_type).count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.robots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.robots.is_empty()
+    }
+
+    /// Resource each collector is gathering, by collector id
+    pub fn collector_targets(&self) -> HashMap<u32, ResourceType> {
+        self.iter()
+            .filter_map(|entry| {
+                let target = entry.telemetry.collector_target?;
+                Some((entry.state.id, target))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::communication::channels::RobotCommand;
+    use crate::robot::core::state::RobotStatus;
+    use std::sync::mpsc;
+
+    fn entry(id: u32, robot_type: RobotType) -> (RobotEntry, mpsc::Receiver<CommandEnvelope>) {
+        let (command_sender, command_receiver) = mpsc::channel();
+        let (merge_sender, _) = mpsc::channel();
+        let state = RobotState::new(id, 0, 0, RobotStatus::Idle, 100);
+        (
+            RobotEntry::new(robot_type, state, command_sender, merge_sender),
+            command_receiver,
+        )
+    }
+
+    #[test]
+    fn test_registry_queries_by_type_and_routes_commands() {
+        let mut registry = RobotRegistry::new();
+        let (explorer, _) = entry(2, RobotType::Exploration);
+        let (collector, collector_commands) = entry(1, RobotType::Collection);
+        registry.insert(explorer);
+        registry.insert(collector);
+
+        assert_eq!(registry.ids(), vec![1, 2]);
+        assert_eq!(registry.ids_of(RobotType::Exploration), vec![2]);
+        assert_eq!(registry.robot_type(1), Some(RobotType::Collection));
+
+        let envelope = CommandEnvelope {
+            id: 1,
+            command: RobotCommand::ReturnToStation,
+        };
+        assert!(registry.send_command(1, envelope.clone()));
+        assert_eq!(collector_commands.try_recv(), Ok(envelope.clone()));
+        // Sent, but the collector has yet to take it
+        assert_eq!(
+            registry.get(1).unwrap().telemetry.inbox.report().commands,
+            1
+        );
+        assert!(!registry.send_command(7, envelope));
+
+        assert_eq!(registry.remove(2), Some(RobotType::Exploration));
+        assert_eq!(registry.remove(2), None);
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_shutdown_waits_for_robots_to_notice_the_hang_up() {
+        let mut registry = RobotRegistry::new();
+        let (mut robot, commands) = entry(3, RobotType::Scout);
+        robot.set_handle(std::thread::spawn(move || {
+            let mut station_lost = false;
+            while !station_lost {
+                crate::robot::utils::common::next_command(
+                    &commands,
+                    &InboxGauge::new(),
+                    &mut station_lost,
+                );
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        }));
+        registry.insert(robot);
+
+        registry.shutdown();
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+//! Wall-clock time as robots and the station see it: the real clock in a run, a simulated
+//! one in tests, which moves forward instantly, or a stepped one, which moves the robots in
+//! turn to replay a run exactly. Not to be confused with `SimClock`, the simulation's tick
+//! counter.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::communication::channels::RobotEvent;
+use crate::logging;
+use crate::profiler;
+use crate::robot::utils::config;
+
+/// Source of the current time, and of the pauses robots take between actions
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Lets `duration` pass
+    fn sleep(&self, duration: Duration);
+
+    /// Waits up to `timeout` for the next event on `receiver`, e.g. the station's merge
+    fn recv_timeout(
+        &self,
+        receiver: &Receiver<RobotEvent>,
+        timeout: Duration,
+    ) -> Result<RobotEvent, RecvTimeoutError> {
+        receiver.recv_timeout(timeout)
+    }
+
+    /// Robot `robot_id` is about to start on this clock, told before its thread is spawned
+    fn admit(&self, _robot_id: u32) {}
+
+    /// First thing on the thread of robot `robot_id`
+    fn enter(&self, _robot_id: u32) {}
+
+    /// Last thing on the thread of robot `robot_id`
+    fn leave(&self, _robot_id: u32) {}
+}
+
+/// A clock shared between the threads of a run
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system clock, sleeps blocking the thread
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+        profiler::idle(duration);
+    }
+}
+
+/// The clock of a real run
+pub fn real() -> SharedClock {
+    Arc::new(RealClock)
+}
+
+/// Time that only moves when slept through or advanced, so sleeping returns at once
+#[derive(Debug, Default)]
+pub struct SimulatedClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl SimulatedClock {
+    /// Starts at `start`, the Unix epoch by default
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        *now += duration;
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+thread_local! {
+    /// Robot whose thread this is, once it entered a stepped clock
+    static STEPPED_ROBOT: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Time that only moves when the driving thread steps it. Meanwhile the robots due run one at
+/// a time, by the time they are due then by id, so seeded robots play a run the same way
+/// every time. Any thread but a robot's steps the clock when it sleeps.
+#[derive(Debug, Default)]
+pub struct SteppedClock {
+    schedule: Mutex<Schedule>,
+    turns: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Schedule {
+    now: DateTime<Utc>,
+    /// Robots waiting for their turn, with the time they are due at
+    due: BTreeMap<u32, DateTime<Utc>>,
+    /// Robot whose turn it is
+    running: Option<u32>,
+    /// Robots run on their own, like on a simulated clock
+    released: bool,
+}
+
+impl SteppedClock {
+    /// Lets `duration` pass, running every robot due meanwhile until it sleeps again
+    pub fn step(&self, duration: Duration) {
+        let mut schedule = self.schedule();
+        let until = schedule.now + duration;
+        while !schedule.released {
+            let Some((id, at)) = schedule
+                .due
+                .iter()
+                .filter(|&(_, &at)| at <= until)
+                .min_by_key(|&(&id, &at)| (at, id))
+                .map(|(&id, &at)| (id, at))
+            else {
+                break;
+            };
+            schedule.due.remove(&id);
+            schedule.now = schedule.now.max(at);
+            schedule.running = Some(id);
+            self.turns.notify_all();
+            schedule = self
+                .turns
+                .wait_while(schedule, |schedule| schedule.running == Some(id))
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        schedule.now = schedule.now.max(until);
+    }
+
+    /// Lets the robots run on their own from now on, e.g. before the run shuts down
+    pub fn release(&self) {
+        self.schedule().released = true;
+        self.turns.notify_all();
+    }
+
+    fn schedule(&self) -> MutexGuard<'_, Schedule> {
+        self.schedule.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits for robot `id`'s turn
+    fn wait_turn(&self, schedule: MutexGuard<'_, Schedule>, id: u32) {
+        drop(
+            self.turns
+                .wait_while(schedule, |schedule| {
+                    !schedule.released && schedule.running != Some(id)
+                })
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+    }
+}
+
+impl Clock for SteppedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.schedule().now
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let Some(id) = STEPPED_ROBOT.get() else {
+            self.step(duration);
+            return;
+        };
+        let mut schedule = self.schedule();
+        if schedule.released {
+            schedule.now += duration;
+            return;
+        }
+        let at = schedule.now + duration;
+        schedule.due.insert(id, at);
+        schedule.running = None;
+        self.turns.notify_all();
+        self.wait_turn(schedule, id);
+    }
+
+    /// Looks for the event at each of the robot's turns, so it arrives at the same time in
+    /// every replay
+    fn recv_timeout(
+        &self,
+        receiver: &Receiver<RobotEvent>,
+        timeout: Duration,
+    ) -> Result<RobotEvent, RecvTimeoutError> {
+        let until = self.now() + timeout;
+        loop {
+            match receiver.try_recv() {
+                Ok(event) => return Ok(event),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) if self.now() >= until => {
+                    return Err(RecvTimeoutError::Timeout)
+                }
+                Err(TryRecvError::Empty) => {
+                    self.sleep(Duration::from_millis(config::AT_STATION_SLEEP_MS));
+                }
+            }
+        }
+    }
+
+    fn admit(&self, robot_id: u32) {
+        let mut schedule = self.schedule();
+        let now = schedule.now;
+        schedule.due.insert(robot_id, now);
+    }
+
+    fn enter(&self, robot_id: u32) {
+        STEPPED_ROBOT.set(Some(robot_id));
+        self.wait_turn(self.schedule(), robot_id);
+    }
+
+    fn leave(&self, robot_id: u32) {
+        STEPPED_ROBOT.set(None);
+        let mut schedule = self.schedule();
+        schedule.due.remove(&robot_id);
+        if schedule.running == Some(robot_id) {
+            schedule.running = None;
+            self.turns.notify_all();
+        }
+    }
+}
+
+/// Leaves the clock as the robot's thread ends, even by a panic
+struct Leave {
+    clock: SharedClock,
+    robot_id: u32,
+}
+
+impl Drop for Leave {
+    fn drop(&mut self) {
+        self.clock.leave(self.robot_id);
+    }
+}
+
+/// Starts the thread of robot `robot_id` on `clock`, which may hold it until its turn
+pub fn spawn_robot<F>(clock: &SharedClock, robot_id: u32, body: F) -> JoinHandle<()>
+where
+    F: FnOnce() + Send + 'static,
+{
+    clock.admit(robot_id);
+    let clock = clock.clone();
+    thread::spawn(move || {
+        logging::enter_robot(robot_id);
+        clock.enter(robot_id);
+        let _leave = Leave { clock, robot_id };
+        body();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulated_sleeps_move_time_without_waiting() {
+        let clock = SimulatedClock::default();
+        let start = clock.now();
+        let waited = std::time::Instant::now();
+        clock.sleep(Duration::from_secs(3600));
+        clock.advance(Duration::from_secs(60));
+        assert!(waited.elapsed() < Duration::from_secs(1));
+        assert_eq!((clock.now() - start).num_seconds(), 3660);
+    }
+
+    #[test]
+    fn test_stepped_robots_take_turns_in_the_order_they_are_due() {
+        let clock: Arc<SteppedClock> = Arc::default();
+        let turns = Arc::new(Mutex::new(Vec::new()));
+        let handles: Vec<_> = [(2, 300), (1, 200)]
+            .into_iter()
+            .map(|(id, pause)| {
+                let (turns, robot_clock) = (turns.clone(), clock.clone());
+                spawn_robot(&(clock.clone() as SharedClock), id, move || {
+                    for _ in 0..3 {
+                        turns.lock().unwrap().push(id);
+                        robot_clock.sleep(Duration::from_millis(pause));
+                    }
+                })
+            })
+            .collect();
+
+        // Both start at once, the lower id first; nothing runs until the clock is stepped
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(turns.lock().unwrap().is_empty());
+        clock.step(Duration::ZERO);
+        assert_eq!(*turns.lock().unwrap(), [1, 2]);
+        clock.step(Duration::from_millis(400));
+        assert_eq!(*turns.lock().unwrap(), [1, 2, 1, 2, 1]);
+        clock.release();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(turns.lock().unwrap().len(), 6);
+    }
+}
@@ -1,7 +1,359 @@
+use crate::communication::channels::{
+    CommandEnvelope, DockingBrief, EventSender, RobotCommand, RobotEvent,
+};
+use crate::logging;
 use crate::map::noise::Map;
-use crate::robot::core::knowledge::{RobotKnowledge, TileInfo};
+use crate::map::shared::SharedMap;
+use crate::profiler;
+use crate::robot::core::knowledge::RobotKnowledge;
 use crate::robot::core::movement::{is_valid_move, next_position, Direction};
-use log::debug;
+use crate::robot::core::state::RobotStatus;
+use crate::robot::utils::clock::Clock;
+use crate::robot::utils::config;
+use crate::robot::utils::inbox::InboxGauge;
+use crate::robot::RobotState;
+use log::{debug, error, info, warn};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use std::cmp::Ordering;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::time::{Duration, Instant};
+
+/// Tells the app where the robot moved, how much energy it has left and what it is doing
+pub fn report_position(state: &RobotState, sender: &EventSender) {
+    let _ = sender.send(RobotEvent::PositionUpdate {
+        id: state.id,
+        x: state.x,
+        y: state.y,
+        energy: state.energy,
+        status: state.status.clone(),
+    });
+}
+
+/// Charges the robot's solar cells while a flare keeps the radio down, unless docked
+pub fn soak_up_flare(state: &mut RobotState, sender: &EventSender) {
+    if sender.link_down() && state.status != RobotStatus::AtStation {
+        state.energy = (state.energy + config::FLARE_RECHARGE).min(state.max_energy);
+    }
+}
+
+/// Garbles part of what the robot knows, after a solar flare
+pub fn suffer_solar_flare(
+    state: &RobotState,
+    knowledge: &mut RobotKnowledge,
+    scramble_percent: u32,
+    seed: u64,
+) {
+    let scrambled = knowledge.scramble(scramble_percent, &mut StdRng::seed_from_u64(seed));
+    info!(
+        target: &logging::robot_target(state.id),
+        "Robot {}: Solar flare garbled {} known tiles",
+        state.id, scrambled
+    );
+}
+
+/// Jumps to (x, y) at once, for the debug console
+pub fn teleport(state: &mut RobotState, sender: &EventSender, (x, y): (usize, usize)) {
+    state.teleport(x, y);
+    info!(
+        target: &logging::robot_target(state.id),
+        "Robot {}: Teleported to ({}, {})",
+        state.id, x, y
+    );
+    report_position(state, sender);
+}
+
+/// Sets the battery level, for the debug console
+pub fn set_energy(state: &mut RobotState, sender: &EventSender, energy: u32) {
+    state.energy = energy.min(state.max_energy);
+    info!(
+        target: &logging::robot_target(state.id),
+        "Robot {}: Energy set to {}",
+        state.id, state.energy
+    );
+    report_position(state, sender);
+}
+
+/// Drains the robot if a hostile reached it, returns whether it did
+pub fn suffer_hostile_contact(state: &mut RobotState, map: &SharedMap) -> bool {
+    let threatened = map.with_read(|map| map.is_threatened(state.x, state.y));
+    if threatened {
+        state.energy = state.energy.saturating_sub(config::HOSTILE_DRAIN);
+        debug!(
+            target: &logging::robot_target(state.id),
+            "Robot {}: Drained by a dust devil, {} energy left",
+            state.id, state.energy
+        );
+    }
+    threatened
+}
+
+/// Forgets the struck tiles and takes the damage if the robot stood in the blast
+pub fn suffer_meteor_impact(
+    state: &mut RobotState,
+    knowledge: &mut RobotKnowledge,
+    (x, y): (usize, usize),
+    radius: usize,
+    damage: u32,
+) {
+    knowledge.forget_area(x, y, radius);
+    let (dx, dy) = (state.x.abs_diff(x), state.y.abs_diff(y));
+    if dx * dx + dy * dy <= radius * radius {
+        state.energy = state.energy.saturating_sub(damage);
+        info!(
+            target: &logging::robot_target(state.id),
+            "Robot {}: Hit by a meteor, {} energy left",
+            state.id, state.energy
+        );
+    }
+}
+
+/// Sleeps at the dock until `ticks` more have passed
+fn stay_docked(sender: &EventSender, clock: &dyn Clock, ticks: u64) {
+    let until = sender.clock().now() + ticks;
+    while sender.clock().now() < until {
+        clock.sleep(config::scaled(Duration::from_millis(
+            config::AT_STATION_SLEEP_MS,
+        )));
+    }
+}
+
+/// Stays docked while the station services the robot, then gets back to work without wear
+pub fn undergo_service(
+    state: &mut RobotState,
+    sender: &EventSender,
+    clock: &dyn Clock,
+    ticks: u64,
+) {
+    info!(
+        target: &logging::robot_target(state.id),
+        "Robot {}: In maintenance for {} ticks.",
+        state.id, ticks
+    );
+    stay_docked(sender, clock, ticks);
+    state.wear_percent = 0;
+    info!(target: &logging::robot_target(state.id), "Robot {}: Maintenance done.", state.id);
+}
+
+/// Fills the battery of a robot that docked, unless every charger was taken, and returns the
+/// status to go on with: `resume`, or docked until the station sends `Charge`
+pub fn recharge_at_dock(
+    state: &mut RobotState,
+    brief: &DockingBrief,
+    resume: RobotStatus,
+) -> RobotStatus {
+    if brief.charger_queued {
+        info!(
+            target: &logging::robot_target(state.id),
+            "Robot {}: Waiting for a charger.",
+            state.id
+        );
+        RobotStatus::AtStation
+    } else {
+        state.recharge();
+        resume
+    }
+}
+
+/// Stays on the charger the station freed up for `ticks`, then leaves it charged
+pub fn charge(state: &mut RobotState, sender: &EventSender, clock: &dyn Clock, ticks: u64) {
+    info!(
+        target: &logging::robot_target(state.id),
+        "Robot {}: Charging for {} ticks.",
+        state.id, ticks
+    );
+    stay_docked(sender, clock, ticks);
+    state.recharge();
+    report_position(state, sender);
+}
+
+/// Takes the next pending command, setting `station_lost` once the station hung up
+pub fn next_command(
+    receiver: &Receiver<CommandEnvelope>,
+    gauge: &InboxGauge,
+    station_lost: &mut bool,
+) -> Option<CommandEnvelope> {
+    match receiver.try_recv() {
+        Ok(envelope) => {
+            gauge.took_command(&envelope.command);
+            Some(envelope)
+        }
+        Err(TryRecvError::Empty) => None,
+        Err(TryRecvError::Disconnected) => {
+            *station_lost = true;
+            None
+        }
+    }
+}
+
+/// Drops merges of earlier docks the robot gave up waiting for, so the wait after the next
+/// report returns that report's merge
+pub fn discard_stale_merges(receiver: &Receiver<RobotEvent>, gauge: &InboxGauge, robot_id: u32) {
+    let stale = receiver.try_iter().count();
+    if stale > 0 {
+        gauge.dropped_merges(stale as u64);
+        warn!(
+            target: &logging::robot_target(robot_id),
+            "Robot: {} Dropped {} stale merge(s).",
+            robot_id, stale
+        );
+    }
+}
+
+/// Reports the dock with `knowledge` and waits for the station's merge, reporting again
+/// each time `merge_timeout` passes, up to `merge_retries` times. When the station never
+/// answers, tells it with a `MergeAbandoned` and returns the timeout.
+pub fn report_dock(
+    sender: &EventSender,
+    clock: &dyn Clock,
+    receiver: &Receiver<RobotEvent>,
+    gauge: &InboxGauge,
+    robot_id: u32,
+    knowledge: &RobotKnowledge,
+) -> Result<RobotEvent, RecvTimeoutError> {
+    let wait = MergeWait {
+        timeout: config::merge_timeout(),
+        attempts: config::merge_retries() + 1,
+    };
+    report_dock_with(sender, clock, receiver, gauge, robot_id, knowledge, wait)
+}
+
+struct MergeWait {
+    timeout: Duration,
+    attempts: u32,
+}
+
+fn report_dock_with(
+    sender: &EventSender,
+    clock: &dyn Clock,
+    receiver: &Receiver<RobotEvent>,
+    gauge: &InboxGauge,
+    robot_id: u32,
+    knowledge: &RobotKnowledge,
+    MergeWait { timeout, attempts }: MergeWait,
+) -> Result<RobotEvent, RecvTimeoutError> {
+    for attempt in 1..=attempts {
+        let arrived = RobotEvent::ArrivedAtStation {
+            id: robot_id,
+            knowledge: knowledge.clone(),
+        };
+        if let Err(e) = sender.send(arrived) {
+            error!(
+                target: &logging::robot_target(robot_id),
+                "Robot: {} Failed send Arrived: {}",
+                robot_id, e
+            );
+            return Err(RecvTimeoutError::Disconnected);
+        }
+        info!(
+            target: &logging::robot_target(robot_id),
+            "Robot: {} Waiting MergeComplete...",
+            robot_id
+        );
+        let waiting = Instant::now();
+        let received = clock.recv_timeout(receiver, timeout);
+        profiler::idle(waiting.elapsed());
+        match received {
+            Ok(event) => {
+                gauge.took_merge(&event);
+                return Ok(event);
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                gauge.merge_timed_out();
+                warn!(
+                    target: &logging::robot_target(robot_id),
+                    "Robot: {} Merge Timeout ({}/{}).",
+                    robot_id, attempt, attempts
+                );
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+        }
+    }
+    let _ = sender.send(RobotEvent::MergeAbandoned {
+        id: robot_id,
+        attempts,
+    });
+    Err(RecvTimeoutError::Timeout)
+}
+
+/// Takes in what the station handed over at the dock besides the knowledge: the no-go
+/// tiles and the tiles left to other robots. Returns the assignments, to carry out like
+/// commands. The settings in the brief are for robots in another process, which take them in
+/// as the merge arrives.
+pub fn take_brief(knowledge: &mut RobotKnowledge, brief: DockingBrief) -> Vec<RobotCommand> {
+    if let Some(exclusions) = brief.exclusions {
+        knowledge.set_exclusions(exclusions);
+    }
+    knowledge.set_claims(brief.claims);
+    brief.assignments
+}
+
+/// Tells the station whether a command was carried out
+pub fn acknowledge(
+    sender: &EventSender,
+    robot_id: u32,
+    command_id: u64,
+    outcome: Result<(), String>,
+) {
+    let event = match outcome {
+        Ok(()) => RobotEvent::CommandAck {
+            id: robot_id,
+            command_id,
+        },
+        Err(reason) => RobotEvent::CommandRejected {
+            id: robot_id,
+            command_id,
+            reason,
+        },
+    };
+    if let Err(e) = sender.send(event) {
+        warn!(
+            target: &logging::robot_target(robot_id),
+            "Robot {} failed to acknowledge command {}: {}",
+            robot_id, command_id, e
+        );
+    }
+}
+
+/// Way back to the station: down the distance field the station handed out where it leads
+/// through open tiles, straight towards the station otherwise
+pub fn direction_home(
+    x: usize,
+    y: usize,
+    knowledge: &RobotKnowledge,
+    map: &Map,
+    rng: &mut dyn RngCore,
+) -> Direction {
+    if let Some(direction) = descend_station_field(x, y, knowledge, map) {
+        return direction;
+    }
+    let (station_x, station_y) = knowledge.get_station_coords();
+    move_towards_target(x, y, station_x, station_y, knowledge, map, rng)
+}
+
+/// Move to the open neighbor closest to the station according to the station's distance
+/// field, if one is closer than (x, y)
+pub fn descend_station_field(
+    x: usize,
+    y: usize,
+    knowledge: &RobotKnowledge,
+    map: &Map,
+) -> Option<Direction> {
+    let field = knowledge.station_field()?;
+    let here = field.distance((x, y)).unwrap_or(u32::MAX);
+    Direction::all()
+        .into_iter()
+        .filter_map(|dir| {
+            let (nx, ny) = next_position(x, y, &dir, map);
+            let distance = field.distance((nx, ny))?;
+            ((nx, ny) != (x, y)
+                && distance < here
+                && is_valid_move(nx, ny, map)
+                && !knowledge.blocks_move((x, y), (nx, ny)))
+            .then_some((distance, dir))
+        })
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, dir)| dir)
+}
 
 pub fn move_towards_target(
     current_x: usize,
@@ -10,29 +362,52 @@ pub fn move_towards_target(
     target_y: usize,
     knowledge: &RobotKnowledge,
     map: &Map,
+    rng: &mut dyn RngCore,
 ) -> Direction {
     debug!(
+        target: &logging::thread_target(),
         "Moving from ({},{}) towards ({},{})",
         current_x, current_y, target_x, target_y
     );
 
-    let try_horizontal = if target_x > current_x {
-        Some(Direction::Right)
-    } else if target_x < current_x {
-        Some(Direction::Left)
+    // A target on another layer is reached through the closest shaft leading its way
+    let (layer, target_layer) = (map.layer_of(current_y), map.layer_of(target_y));
+    let (target_x, target_y) = if layer == target_layer {
+        (target_x, target_y)
     } else {
-        None
+        let toward = if target_layer > layer {
+            layer + 1
+        } else {
+            layer - 1
+        };
+        let shafts = map.shafts_toward(layer, toward);
+        if shafts.contains(&(current_x, current_y)) {
+            return Direction::Climb;
+        }
+        match shafts
+            .into_iter()
+            .min_by_key(|&shaft| map.distance(shaft, (current_x, current_y)))
+        {
+            Some(shaft) => shaft,
+            None => (target_x, target_y),
+        }
     };
 
-    let try_vertical = if target_y > current_y {
-        Some(Direction::Down)
-    } else if target_y < current_y {
-        Some(Direction::Up)
-    } else {
-        None
+    // On a wrapping map the short way may cross an edge
+    let topology = map.topology();
+    let try_horizontal = match topology.heading(current_x, target_x, map.width) {
+        Ordering::Greater => Some(Direction::Right),
+        Ordering::Less => Some(Direction::Left),
+        Ordering::Equal => None,
+    };
+
+    let try_vertical = match topology.heading(current_y, target_y, map.layer_height) {
+        Ordering::Greater => Some(Direction::Down),
+        Ordering::Less => Some(Direction::Up),
+        Ordering::Equal => None,
     };
 
-    let directions_to_try = vec![
+    let mut directions_to_try = vec![
         try_horizontal,
         try_vertical,
         Some(Direction::Up),
@@ -40,32 +415,116 @@ pub fn move_towards_target(
         Some(Direction::Left),
         Some(Direction::Right),
     ];
+    // Hex rows are offset, so the closest neighbor is found by measuring rather than by axis
+    if topology.hex {
+        let mut moves = Direction::all();
+        moves.sort_by_key(|dir| {
+            let next = next_position(current_x, current_y, dir, map);
+            topology.distance(next, (target_x, target_y))
+        });
+        directions_to_try = moves
+            .into_iter()
+            .filter(|dir| *dir != Direction::Climb)
+            .map(Some)
+            .collect();
+    }
 
-    for dir_opt in directions_to_try {
-        if let Some(dir) = dir_opt {
-            let (nx, ny) = next_position(current_x, current_y, &dir, map);
-            if (nx, ny) != (current_x, current_y) && // Ensure we actually move
-               is_valid_move(nx, ny, map) &&
-               !matches!(knowledge.get_tile(nx, ny), TileInfo::Obstacle)
-            {
-                debug!("Selected direction: {:?} -> new pos: ({},{})", dir, nx, ny);
-                return dir;
-            }
+    // Known hostiles are given a wide berth, unless the robot is already close to one
+    let entities = knowledge.entities();
+    let avoid = |x: usize, y: usize| {
+        entities.is_near_hostile(x, y, config::HOSTILE_AVOID_DISTANCE)
+            && !entities.is_near_hostile(current_x, current_y, config::HOSTILE_AVOID_DISTANCE)
+    };
+
+    for dir in directions_to_try.into_iter().flatten() {
+        let (nx, ny) = next_position(current_x, current_y, &dir, map);
+        if (nx, ny) != (current_x, current_y) && // Ensure we actually move
+           is_valid_move(nx, ny, map) &&
+           !knowledge.blocks_move((current_x, current_y), (nx, ny)) &&
+           !avoid(nx, ny)
+        {
+            debug!(
+                target: &logging::thread_target(),
+                "Selected direction: {:?} -> new pos: ({},{})",
+                dir, nx, ny
+            );
+            return dir;
         }
     }
 
     for _ in 0..8 {
-        let random_dir = Direction::random();
+        let random_dir = Direction::random(rng);
         let (nx, ny) = next_position(current_x, current_y, &random_dir, map);
         if (nx, ny) != (current_x, current_y)
             && is_valid_move(nx, ny, map)
-            && !matches!(knowledge.get_tile(nx, ny), TileInfo::Obstacle)
+            && !knowledge.blocks_move((current_x, current_y), (nx, ny))
         {
-            debug!("Using random direction: {:?}", random_dir);
+            debug!(target: &logging::thread_target(), "Using random direction: {:?}", random_dir);
             return random_dir;
         }
     }
 
-    debug!("No valid direction found, returning random");
-    Direction::random()
+    debug!(target: &logging::thread_target(), "No valid direction found, returning random");
+    Direction::random(rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::communication::channels::{create_channel, DockingBrief};
+    use crate::robot::utils::clock::RealClock;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_a_late_merge_is_asked_for_again_then_given_up_on() {
+        let (sender, events) = create_channel();
+        let (merges, receiver) = mpsc::channel();
+        let gauge = InboxGauge::new();
+        let knowledge = RobotKnowledge::new(4, 4);
+        let wait = || MergeWait {
+            timeout: Duration::from_millis(10),
+            attempts: 3,
+        };
+
+        let outcome = report_dock_with(
+            &sender,
+            &RealClock,
+            &receiver,
+            &gauge,
+            7,
+            &knowledge,
+            wait(),
+        );
+        assert_eq!(outcome.err(), Some(RecvTimeoutError::Timeout));
+        let sent: Vec<RobotEvent> = events.try_iter().map(|envelope| envelope.event).collect();
+        assert_eq!(sent.len(), 4);
+        assert!(sent[..3]
+            .iter()
+            .all(|event| matches!(event, RobotEvent::ArrivedAtStation { id: 7, .. })));
+        assert!(matches!(
+            sent[3],
+            RobotEvent::MergeAbandoned { id: 7, attempts: 3 }
+        ));
+        assert_eq!(gauge.report().merge_timeouts, 3);
+
+        // A merge waiting in the channel is taken on the first try
+        merges
+            .send(RobotEvent::MergeComplete {
+                id: 7,
+                merged_knowledge: knowledge.clone(),
+                brief: Box::new(DockingBrief::default()),
+            })
+            .unwrap();
+        let outcome = report_dock_with(
+            &sender,
+            &RealClock,
+            &receiver,
+            &gauge,
+            7,
+            &knowledge,
+            wait(),
+        );
+        assert!(matches!(outcome, Ok(RobotEvent::MergeComplete { .. })));
+        assert_eq!(events.try_iter().count(), 1);
+    }
 }
@@ -1,22 +1,253 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::time::Duration;
 
 /// Minimum sleep duration during the return-to-station phase (milliseconds)
 pub const RETURN_SLEEP_MIN_MS: u64 = 150;
 /// Maximum sleep duration during the return-to-station phase (milliseconds)
 pub const RETURN_SLEEP_MAX_MS: u64 = 400;
-/// Timeout duration for waiting for a MergeComplete message (seconds)
-pub const MERGE_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long a docked robot waits for its MergeComplete unless configured otherwise
+pub const DEFAULT_MERGE_TIMEOUT_MS: u64 = 3000;
+/// Times a docked robot reports again before giving up on its merge, unless configured
+/// otherwise
+pub const DEFAULT_MERGE_RETRIES: u32 = 2;
+/// Merges kept in the station's log
+pub const MERGE_LOG_LEN: usize = 12;
+/// Entries kept in the event journal
+pub const JOURNAL_LEN: usize = 50;
 /// Default sleep duration when in the AtStation state (milliseconds)
 pub const AT_STATION_SLEEP_MS: u64 = 100;
 /// Default sleep duration when encountering an unhandled state (seconds)
 pub const UNHANDLED_STATE_SLEEP: Duration = Duration::from_secs(1);
+/// App ticks without progress (new tile, collection, analysis) before a robot counts as idle
+pub const IDLE_THRESHOLD_TICKS: u64 = 300;
+/// How far ahead (percent) a collector's resource stock must be before the station moves it
+/// to the scarcer one
+pub const STOCK_REBALANCE_TOLERANCE_PERCENT: u32 = 25;
+/// Energy a robot loses on each step it ends within reach of a hostile
+pub const HOSTILE_DRAIN: u32 = 10;
+/// Robots steer clear of tiles this many moves from a hostile they know of
+pub const HOSTILE_AVOID_DISTANCE: usize = 2;
+/// Scouts see hostiles up to this many moves away
+pub const SCOUT_SENSOR_RANGE: usize = 8;
+/// Distance (in moves) scouts keep from the hostile they follow
+pub const SCOUT_FOLLOW_DISTANCE: usize = 3;
+/// Ticks a sighting of a moving entity is kept before it is forgotten, per kind
+pub const HOSTILE_SIGHTING_TTL_TICKS: u64 = 30;
+pub const ROBOT_SIGHTING_TTL_TICKS: u64 = 10;
+pub const STORM_SIGHTING_TTL_TICKS: u64 = 5;
+/// Ticks between two moves of a hostile
+pub const HOSTILE_MOVE_INTERVAL_TICKS: u64 = 3;
+/// Hostiles appear at least this many moves away from the station
+pub const HOSTILE_MIN_STATION_DISTANCE: usize = 8;
+/// Energy a robot loses when a meteor strikes next to it
+pub const METEOR_DAMAGE: u32 = 150;
+/// Ticks between the announcement of a random solar flare and its arrival
+pub const FLARE_WARNING_TICKS: u64 = 100;
+/// Ticks the radio stays down during a random solar flare
+pub const FLARE_DURATION_TICKS: u64 = 150;
+/// Share of their known tiles robots garble in a random solar flare, in percent
+pub const FLARE_SCRAMBLE_PERCENT: u32 = 10;
+/// Energy a robot's solar cells gain per action while a solar flare lasts
+pub const FLARE_RECHARGE: u32 = 2;
+/// Ticks between two recalls of the robots that left the station during a storm
+pub const STORM_RECALL_INTERVAL_TICKS: u64 = 20;
+/// Ticks the station waits for a command acknowledgement before resending it
+pub const COMMAND_ACK_TIMEOUT_TICKS: u64 = 50;
+/// Sends of one command before the station reports it as failed
+pub const COMMAND_MAX_ATTEMPTS: u32 = 3;
+
+/// Ticks between two draws of the difficulty's station drain on the energy stock
+pub const STATION_DRAIN_INTERVAL_TICKS: u64 = 10;
+/// Ticks between two audits of the resource ledger
+pub const LEDGER_AUDIT_INTERVAL_TICKS: u64 = 100;
+/// Ticks the live swarm figures are rated over
+pub const KPI_WINDOW_TICKS: u64 = 500;
+/// Ticks between two estimates of the memory a run takes
+pub const MEMORY_CHECK_INTERVAL_TICKS: u64 = 50;
+/// Decisions kept per robot while decision traces are on
+pub const DECISION_TRACE_CAPACITY: usize = 512;
+/// Ticks a robot spends on a charger when the station has a limited number of them
+pub const CHARGE_TICKS: u64 = 40;
+
+/// A tick stretched under load lasts this share of the work it takes, in percent, leaving
+/// time for input
+pub const TICK_HEADROOM_PERCENT: u32 = 125;
+/// A tick is stretched to at most this many times the configured tick rate
+pub const MAX_TICK_STRETCH: u32 = 10;
+
+/// How many times faster than real time robots act while the simulation fast-forwards
+pub const FAST_FORWARD_SPEEDUP: u32 = 20;
+
+/// Divides every robot sleep, 1 is real time
+static TIME_SCALE: AtomicU32 = AtomicU32::new(1);
+
+/// Speeds robots up by `scale` (1 is real time)
+pub fn set_time_scale(scale: u32) {
+    TIME_SCALE.store(scale.max(1), Ordering::Relaxed);
+}
+
+pub fn time_scale() -> u32 {
+    TIME_SCALE.load(Ordering::Relaxed)
+}
+
+/// Robot energy costs in percent of the base costs, 100 on normal difficulty
+static ENERGY_COST_PERCENT: AtomicU32 = AtomicU32::new(100);
+
+pub fn set_energy_cost_percent(percent: u32) {
+    ENERGY_COST_PERCENT.store(percent, Ordering::Relaxed);
+}
+
+pub fn energy_cost_percent() -> u32 {
+    ENERGY_COST_PERCENT.load(Ordering::Relaxed)
+}
+
+/// Robots get floating-point poses and drive between tiles instead of hopping from one to the
+/// next, see `RobotState::move_to`
+static CONTINUOUS_MOVEMENT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_continuous_movement(enabled: bool) {
+    CONTINUOUS_MOVEMENT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn continuous_movement() -> bool {
+    CONTINUOUS_MOVEMENT.load(Ordering::Relaxed)
+}
+
+/// Robots launched from now on record their decisions, see `DecisionTrace`
+static DECISION_TRACES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_decision_traces(enabled: bool) {
+    DECISION_TRACES.store(enabled, Ordering::Relaxed);
+}
+
+pub fn decision_traces() -> bool {
+    DECISION_TRACES.load(Ordering::Relaxed)
+}
+
+/// Ticks a walkable or obstacle tile is trusted without being seen again, by robots and the
+/// station alike; 0 trusts them for good
+static TERRAIN_MEMORY_TICKS: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_terrain_memory(ticks: Option<u64>) {
+    TERRAIN_MEMORY_TICKS.store(ticks.unwrap_or(0), Ordering::Relaxed);
+}
+
+pub fn terrain_memory() -> Option<u64> {
+    Some(TERRAIN_MEMORY_TICKS.load(Ordering::Relaxed)).filter(|&ticks| ticks > 0)
+}
+
+/// Distance a robot drives per move in continuous movement mode, in tiles
+pub const CONTINUOUS_SPEED: f32 = 0.4;
+/// Largest change of heading per move in continuous movement mode, in radians
+pub const CONTINUOUS_MAX_TURN: f32 = std::f32::consts::FRAC_PI_4;
+
+/// A robot sleep adjusted to the current time scale
+pub fn scaled(duration: Duration) -> Duration {
+    duration / time_scale()
+}
 
 /// Max energy of each robots
 pub const COLLECTION_ROBOT_MAX_ENERGY: u32 = 500;
 pub const EXPLORATION_ROBOT_MAX_ENERGY: u32 = 800;
 pub const SCIENTIFIC_ROBOT_MAX_ENERGY: u32 = 500;
+pub const SCOUT_ROBOT_MAX_ENERGY: u32 = 600;
+
+/// Battery capacity lost with every recharge, in thousandths of the rated capacity
+pub const BATTERY_WEAR_PERMILLE: u32 = 10;
+/// Capacity, in percent of the rated one, below which the station refits or retires a robot
+pub const BATTERY_WORN_PERCENT: u32 = 70;
+/// Minerals the station spends to fit a robot with a new battery
+pub const BATTERY_REFIT_COST: u32 = 30;
+
+/// Ticks of operation after which a robot's wear grows by another `WEAR_PERCENT_PER_INTERVAL`
+pub const MAINTENANCE_INTERVAL_TICKS: u64 = 3000;
+/// Extra energy cost, in percent, per interval without maintenance
+pub const WEAR_PERCENT_PER_INTERVAL: u32 = 10;
+/// Wear stops growing there
+pub const MAX_WEAR_PERCENT: u32 = 50;
+/// Ticks a robot spends docked while serviced
+pub const MAINTENANCE_TICKS: u64 = 150;
+/// Minerals the station spends on a service
+pub const MAINTENANCE_COST: u32 = 20;
+/// Share of the fleet, and of each robot type, allowed in maintenance at once (percent)
+pub const MAINTENANCE_MAX_SHARE_PERCENT: usize = 25;
+
+/// Collectors with nothing to collect head for unknown tiles up to this many moves away;
+/// past it they help exploring from the frontier instead
+pub const COLLECTOR_SEARCH_RADIUS: usize = 6;
+
+/// Radius of the area marked at once as high priority
+pub const PRIORITY_AREA_RADIUS: usize = 2;
+/// Radius of the no-go area closed at once by the user
+pub const EXCLUSION_AREA_RADIUS: usize = 2;
+
+/// How long a scientist waits on a joint site for a collector's sample before moving on
+pub const RENDEZVOUS_TIMEOUT: Duration = Duration::from_secs(20);
+/// Ticks the station keeps a joint site's mission open
+pub const RENDEZVOUS_TIMEOUT_TICKS: u64 = 250;
+/// Science a joint analysis yields, times what the scientist alone would get
+pub const JOINT_SCIENCE_MULTIPLIER: u32 = 3;
+
+/// Minerals used to build each robot type
+pub const EXPLORATION_ROBOT_BUILD_COST: u32 = 40;
+pub const COLLECTION_ROBOT_BUILD_COST: u32 = 60;
+pub const SCIENTIFIC_ROBOT_BUILD_COST: u32 = 80;
+pub const SCOUT_ROBOT_BUILD_COST: u32 = 50;
+/// Share of the build cost refunded to the station when a robot is decommissioned (percent)
+pub const DECOMMISSION_REFUND_PERCENT: u32 = 50;
 
-#[derive(Debug, Clone)]
+/// Largest per-robot parameter jitter accepted, in percent
+pub const MAX_JITTER_PERCENT: u32 = 50;
+
+/// How far each robot's parameters stray from its type's, in percent; 0 keeps them all equal
+static JITTER_PERCENT: AtomicU32 = AtomicU32::new(0);
+
+pub fn set_jitter_percent(percent: u32) {
+    JITTER_PERCENT.store(percent.min(MAX_JITTER_PERCENT), Ordering::Relaxed);
+}
+
+pub fn jitter_percent() -> u32 {
+    JITTER_PERCENT.load(Ordering::Relaxed)
+}
+
+/// Robots a run holds at most unless configured otherwise
+pub const DEFAULT_MAX_ROBOTS: usize = 256;
+
+/// Robots a run may hold at once; spawns past it are refused
+static MAX_ROBOTS: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_ROBOTS);
+
+pub fn set_max_robots(max: usize) {
+    MAX_ROBOTS.store(max.max(1), Ordering::Relaxed);
+}
+
+pub fn max_robots() -> usize {
+    MAX_ROBOTS.load(Ordering::Relaxed)
+}
+
+/// How long a docked robot waits for its MergeComplete before reporting again
+static MERGE_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_MERGE_TIMEOUT_MS);
+
+pub fn set_merge_timeout(timeout: Duration) {
+    MERGE_TIMEOUT_MS.store(timeout.as_millis().max(1) as u64, Ordering::Relaxed);
+}
+
+pub fn merge_timeout() -> Duration {
+    Duration::from_millis(MERGE_TIMEOUT_MS.load(Ordering::Relaxed))
+}
+
+/// Reports a docked robot sends again when its merge is late, before resuming with the
+/// knowledge it has
+static MERGE_RETRIES: AtomicU32 = AtomicU32::new(DEFAULT_MERGE_RETRIES);
+
+pub fn set_merge_retries(retries: u32) {
+    MERGE_RETRIES.store(retries, Ordering::Relaxed);
+}
+
+pub fn merge_retries() -> u32 {
+    MERGE_RETRIES.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct RobotTypeConfig {
     pub low_energy_threshold: u32,
     pub primary_action_sleep_min_ms: u64,
@@ -25,6 +256,48 @@ pub struct RobotTypeConfig {
     pub action_energy_cost: Option<u32>,
 }
 
+impl RobotTypeConfig {
+    /// The configuration with its energy costs adjusted to the current difficulty, rounded
+    /// up so a cost never drops to zero
+    pub fn adjusted(&self) -> Self {
+        let percent = energy_cost_percent();
+        let adjust = |cost: u32| (cost * percent).div_ceil(100);
+        Self {
+            movement_energy_cost: adjust(self.movement_energy_cost),
+            action_energy_cost: self.action_energy_cost.map(adjust),
+            ..self.clone()
+        }
+    }
+
+    /// The configuration with its threshold, sleeps and costs each moved by up to `percent`
+    /// either way, drawn from `seed`. Costs never drop to zero and the longest sleep stays
+    /// the longest.
+    pub fn jittered(&self, percent: u32, seed: u64) -> Self {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(seed);
+        let percent = u64::from(percent.min(MAX_JITTER_PERCENT));
+        // In hundredths
+        let mut jitter = |value: u64| value * rng.random_range(100 - percent..=100 + percent);
+        let cost = |hundredths: u64| hundredths.div_ceil(100) as u32;
+
+        let low_energy_threshold = cost(jitter(self.low_energy_threshold.into()));
+        let movement_energy_cost = cost(jitter(self.movement_energy_cost.into()));
+        let action_energy_cost = self
+            .action_energy_cost
+            .map(|action| cost(jitter(action.into())));
+        let primary_action_sleep_min_ms = jitter(self.primary_action_sleep_min_ms) / 100;
+        let primary_action_sleep_max_ms =
+            (jitter(self.primary_action_sleep_max_ms) / 100).max(primary_action_sleep_min_ms);
+        Self {
+            low_energy_threshold,
+            primary_action_sleep_min_ms,
+            primary_action_sleep_max_ms,
+            movement_energy_cost,
+            action_energy_cost,
+        }
+    }
+}
+
 pub const EXPLORATION_CONFIG: RobotTypeConfig = RobotTypeConfig {
     low_energy_threshold: 20,
     primary_action_sleep_min_ms: 300,
@@ -49,11 +322,48 @@ pub const SCIENTIFIC_CONFIG: RobotTypeConfig = RobotTypeConfig {
     action_energy_cost: Some(5),
 };
 
-pub fn random_sleep_duration(min_ms: u64, max_ms: u64) -> Duration {
-    use rand::{rng, Rng};
-    if min_ms >= max_ms {
+pub const SCOUT_CONFIG: RobotTypeConfig = RobotTypeConfig {
+    low_energy_threshold: 20,
+    primary_action_sleep_min_ms: 250,
+    primary_action_sleep_max_ms: 500,
+    movement_energy_cost: 1,
+    action_energy_cost: None,
+};
+
+/// A pause between `min_ms` and `max_ms`, drawn from `rng`, then scaled
+pub fn random_sleep_duration(rng: &mut dyn rand::RngCore, min_ms: u64, max_ms: u64) -> Duration {
+    use rand::Rng;
+    let duration = if min_ms >= max_ms {
         Duration::from_millis(min_ms)
     } else {
-        Duration::from_millis(rng().random_range(min_ms..=max_ms))
+        Duration::from_millis(rng.random_range(min_ms..=max_ms))
+    };
+    scaled(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_bounds_and_follows_the_seed() {
+        assert_eq!(COLLECTION_CONFIG.jittered(0, 5), COLLECTION_CONFIG);
+        assert_eq!(
+            COLLECTION_CONFIG.jittered(10, 5),
+            COLLECTION_CONFIG.jittered(10, 5)
+        );
+
+        let configs: Vec<RobotTypeConfig> = (0..50)
+            .map(|seed| SCIENTIFIC_CONFIG.jittered(10, seed))
+            .collect();
+        for config in &configs {
+            assert!((27..=33).contains(&config.low_energy_threshold));
+            assert!((720..=880).contains(&config.primary_action_sleep_min_ms));
+            assert!((1350..=1650).contains(&config.primary_action_sleep_max_ms));
+            assert!(config.movement_energy_cost >= 1);
+            assert!(config.action_energy_cost.is_some_and(|cost| cost >= 5));
+        }
+        // Robots do differ
+        assert!(configs.iter().any(|config| config != &configs[0]));
     }
 }
@@ -0,0 +1,122 @@
+//! How far behind a robot is on its command and merge channels. The station counts what it
+//! sends, the robot what it takes, so a merge handshake that got stuck shows in the
+//! inspection panel instead of only in the robot's log.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::communication::channels::{RobotCommand, RobotEvent};
+
+#[derive(Debug, Default)]
+struct Counters {
+    commands_sent: AtomicU64,
+    commands_taken: AtomicU64,
+    merges_sent: AtomicU64,
+    merges_taken: AtomicU64,
+    merge_timeouts: AtomicU64,
+    last_received: Mutex<Option<&'static str>>,
+}
+
+/// Counters shared by the station and one robot thread
+#[derive(Debug, Clone, Default)]
+pub struct InboxGauge(Arc<Counters>);
+
+/// What an `InboxGauge` read at one moment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InboxReport {
+    /// Commands sent and not taken yet
+    pub commands: u64,
+    /// Merge results sent and not taken yet
+    pub merges: u64,
+    /// Merges the robot gave up waiting for
+    pub merge_timeouts: u64,
+    /// Last command or merge the robot took, if any
+    pub last_received: Option<&'static str>,
+}
+
+impl InboxGauge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn command_sent(&self) {
+        self.0.commands_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn merge_sent(&self) {
+        self.0.merges_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn took_command(&self, command: &RobotCommand) {
+        self.0.commands_taken.fetch_add(1, Ordering::Relaxed);
+        self.set_last(command.name());
+    }
+
+    pub fn took_merge(&self, event: &RobotEvent) {
+        self.0.merges_taken.fetch_add(1, Ordering::Relaxed);
+        self.set_last(match event {
+            RobotEvent::MergeComplete { .. } => "MergeComplete",
+            _ => "Unexpected event",
+        });
+    }
+
+    /// Counts merges dropped unread, e.g. stale ones of an earlier dock
+    pub fn dropped_merges(&self, count: u64) {
+        self.0.merges_taken.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn merge_timed_out(&self) {
+        self.0.merge_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn report(&self) -> InboxReport {
+        let depth = |sent: &AtomicU64, taken: &AtomicU64| {
+            // Taken is read first so a send in between cannot make it pass sent
+            let taken = taken.load(Ordering::Relaxed);
+            sent.load(Ordering::Relaxed).saturating_sub(taken)
+        };
+        InboxReport {
+            commands: depth(&self.0.commands_sent, &self.0.commands_taken),
+            merges: depth(&self.0.merges_sent, &self.0.merges_taken),
+            merge_timeouts: self.0.merge_timeouts.load(Ordering::Relaxed),
+            last_received: *self
+                .0
+                .last_received
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+
+    fn set_last(&self, label: &'static str) {
+        *self
+            .0
+            .last_received
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depths_count_what_was_sent_but_not_taken() {
+        let station = InboxGauge::new();
+        let robot = station.clone();
+        assert_eq!(station.report(), InboxReport::default());
+
+        station.command_sent();
+        station.command_sent();
+        station.merge_sent();
+        station.merge_sent();
+        robot.took_command(&RobotCommand::Park);
+        robot.dropped_merges(1);
+        robot.merge_timed_out();
+
+        let report = station.report();
+        assert_eq!((report.commands, report.merges), (1, 1));
+        assert_eq!(report.merge_timeouts, 1);
+        assert_eq!(report.last_received, Some("Park"));
+    }
+}
@@ -0,0 +1,218 @@
+//! High-level strategies for the whole swarm. A preset moves each robot type's energy
+//! threshold and pace together, so switching one changes how the swarm plays rather than a
+//! single knob.
+
+use serde::{Deserialize, Serialize};
+
+use crate::robot::launcher::ParameterJitter;
+use crate::robot::registry::RobotType;
+use crate::robot::utils::config::RobotTypeConfig;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwarmPreset {
+    /// Explorers and scouts range further and move faster; the others keep their pace
+    AggressiveExploration,
+    /// Every robot type as built
+    #[default]
+    Balanced,
+    /// Every robot heads back with a wider margin and takes its time
+    ConservativeEnergy,
+}
+
+/// How a preset moves one robot type's parameters, in percent of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresetTuning {
+    pub low_energy_percent: u32,
+    pub sleep_percent: u32,
+}
+
+impl SwarmPreset {
+    pub const ALL: [SwarmPreset; 3] = [
+        SwarmPreset::AggressiveExploration,
+        SwarmPreset::Balanced,
+        SwarmPreset::ConservativeEnergy,
+    ];
+
+    /// As typed in the console and the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            SwarmPreset::AggressiveExploration => "aggressive_exploration",
+            SwarmPreset::Balanced => "balanced",
+            SwarmPreset::ConservativeEnergy => "conservative_energy",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SwarmPreset::AggressiveExploration => "Aggressive exploration",
+            SwarmPreset::Balanced => "Balanced",
+            SwarmPreset::ConservativeEnergy => "Conservative energy",
+        }
+    }
+
+    /// The preset after this one, wrapping around
+    pub fn next(self) -> Self {
+        let at = Self::ALL
+            .iter()
+            .position(|&preset| preset == self)
+            .unwrap_or(0);
+        Self::ALL[(at + 1) % Self::ALL.len()]
+    }
+
+    pub fn tuning(self, robot_type: RobotType) -> PresetTuning {
+        let (low_energy_percent, sleep_percent) = match (self, robot_type) {
+            (SwarmPreset::Balanced, _) => (100, 100),
+            (SwarmPreset::AggressiveExploration, RobotType::Exploration | RobotType::Scout) => {
+                (60, 70)
+            }
+            (SwarmPreset::AggressiveExploration, _) => (100, 100),
+            (SwarmPreset::ConservativeEnergy, _) => (160, 130),
+        };
+        PresetTuning {
+            low_energy_percent,
+            sleep_percent,
+        }
+    }
+
+    /// `config`, a `robot_type` robot's own parameters, as this preset plays them
+    pub fn apply(self, config: &RobotTypeConfig, robot_type: RobotType) -> RobotTypeConfig {
+        let tuning = self.tuning(robot_type);
+        let scale = |value: u64, percent: u32| value * u64::from(percent) / 100;
+        let primary_action_sleep_min_ms =
+            scale(config.primary_action_sleep_min_ms, tuning.sleep_percent);
+        RobotTypeConfig {
+            low_energy_threshold: (config.low_energy_threshold * tuning.low_energy_percent)
+                .div_ceil(100)
+                .max(1),
+            primary_action_sleep_min_ms,
+            primary_action_sleep_max_ms: scale(
+                config.primary_action_sleep_max_ms,
+                tuning.sleep_percent,
+            )
+            .max(primary_action_sleep_min_ms),
+            ..config.clone()
+        }
+    }
+}
+
+/// A robot's own parameters and the preset it plays them under
+#[derive(Debug, Clone, PartialEq)]
+pub struct RobotTuning {
+    robot_type: RobotType,
+    /// Own thresholds, sleeps and costs, before the swarm preset
+    base: RobotTypeConfig,
+    preset: SwarmPreset,
+    /// What the robot goes by: `base` as `preset` plays it
+    config: RobotTypeConfig,
+}
+
+impl RobotTuning {
+    /// `base` adjusted to the difficulty, strayed by `jitter` if any, as `preset` plays it
+    pub fn new(
+        robot_type: RobotType,
+        base: &RobotTypeConfig,
+        preset: SwarmPreset,
+        jitter: Option<ParameterJitter>,
+    ) -> Self {
+        let mut base = base.adjusted();
+        if let Some(jitter) = jitter {
+            base = base.jittered(jitter.percent, jitter.seed);
+        }
+        Self {
+            config: preset.apply(&base, robot_type),
+            robot_type,
+            base,
+            preset,
+        }
+    }
+
+    pub fn config(&self) -> &RobotTypeConfig {
+        &self.config
+    }
+
+    /// Makes the robot play its parameters as `preset` has it
+    pub fn set_preset(&mut self, preset: SwarmPreset) {
+        self.preset = preset;
+        self.config = preset.apply(&self.base, self.robot_type);
+    }
+
+    /// Each move costs `energy_cost` more from now on, whatever the preset
+    pub fn add_movement_cost(&mut self, energy_cost: u32) {
+        self.base.movement_energy_cost += energy_cost;
+        self.set_preset(self.preset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::utils::config::{COLLECTION_CONFIG, EXPLORATION_CONFIG};
+
+    #[test]
+    fn test_presets_move_thresholds_and_pace_together() {
+        assert_eq!(
+            SwarmPreset::Balanced.apply(&EXPLORATION_CONFIG, RobotType::Exploration),
+            EXPLORATION_CONFIG
+        );
+        let aggressive =
+            SwarmPreset::AggressiveExploration.apply(&EXPLORATION_CONFIG, RobotType::Exploration);
+        assert!(aggressive.low_energy_threshold < EXPLORATION_CONFIG.low_energy_threshold);
+        assert!(
+            aggressive.primary_action_sleep_max_ms < EXPLORATION_CONFIG.primary_action_sleep_max_ms
+        );
+        assert_eq!(
+            SwarmPreset::AggressiveExploration.apply(&COLLECTION_CONFIG, RobotType::Collection),
+            COLLECTION_CONFIG
+        );
+        let conservative =
+            SwarmPreset::ConservativeEnergy.apply(&COLLECTION_CONFIG, RobotType::Collection);
+        assert_eq!(conservative.low_energy_threshold, 40);
+        assert_eq!(
+            conservative.movement_energy_cost,
+            COLLECTION_CONFIG.movement_energy_cost
+        );
+
+        assert_eq!(
+            SwarmPreset::ConservativeEnergy.next(),
+            SwarmPreset::AggressiveExploration
+        );
+        assert_eq!(
+            SwarmPreset::from_name("conservative_energy"),
+            Some(SwarmPreset::ConservativeEnergy)
+        );
+    }
+
+    #[test]
+    fn test_a_preset_switch_keeps_the_robot_s_own_parameters() {
+        let jitter = ParameterJitter {
+            percent: 20,
+            seed: 7,
+        };
+        let mut tuning = RobotTuning::new(
+            RobotType::Collection,
+            &COLLECTION_CONFIG,
+            SwarmPreset::Balanced,
+            Some(jitter),
+        );
+        let jittered = COLLECTION_CONFIG.adjusted().jittered(20, 7);
+        assert_eq!(tuning.config(), &jittered);
+
+        // A drill's cost stays through the switches, the jitter as well
+        tuning.add_movement_cost(3);
+        tuning.set_preset(SwarmPreset::ConservativeEnergy);
+        tuning.set_preset(SwarmPreset::Balanced);
+        assert_eq!(
+            tuning.config().movement_energy_cost,
+            jittered.movement_energy_cost + 3
+        );
+        assert_eq!(
+            tuning.config().low_energy_threshold,
+            jittered.low_energy_threshold
+        );
+    }
+}
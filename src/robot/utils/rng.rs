@@ -0,0 +1,31 @@
+//! Randomness of robot decisions, the way the clock abstracts their pauses: drawn from the
+//! system in a run, seeded or scripted in tests so a reported run can be played exactly again.
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// Source of a robot's random moves and pauses, owned by its thread
+pub type RobotRng = Box<dyn RngCore + Send>;
+
+/// Decisions nobody needs to replay
+pub fn from_entropy() -> RobotRng {
+    Box::new(StdRng::from_os_rng())
+}
+
+/// The same decisions for the same seed
+pub fn seeded(seed: u64) -> RobotRng {
+    Box::new(StdRng::seed_from_u64(seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::core::movement::Direction;
+
+    #[test]
+    fn test_seeded_rngs_repeat_their_decisions() {
+        let draw = |rng: &mut RobotRng| (0..16).map(|_| Direction::random(rng)).collect::<Vec<_>>();
+        assert_eq!(draw(&mut seeded(7)), draw(&mut seeded(7)));
+        assert_ne!(draw(&mut seeded(7)), draw(&mut seeded(8)));
+    }
+}
@@ -0,0 +1,116 @@
+//! Decision traces: what a robot went by and what it chose, step by step. The robot records
+//! into a ring buffer it shares with the station, which dumps it on demand, so "why did robot
+//! 3 walk into a corner for 5 minutes" can be answered after the fact. Recording is off unless
+//! `--trace-decisions` is given.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::robot::core::state::RobotStatus;
+use crate::robot::RobotState;
+
+/// One choice of a robot, with what it saw when making it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Decision {
+    pub tick: u64,
+    pub position: (usize, usize),
+    pub energy: u32,
+    pub status: RobotStatus,
+    /// What the robot went for, e.g. `explore` or `return`
+    pub action: &'static str,
+    /// What the choice went by, e.g. its energy against its threshold or the tile it aims for
+    pub reason: String,
+}
+
+#[derive(Debug)]
+struct Ring {
+    capacity: usize,
+    decisions: VecDeque<Decision>,
+}
+
+/// The last decisions of one robot, shared by its thread and the station; a default trace
+/// records nothing
+#[derive(Debug, Clone, Default)]
+pub struct DecisionTrace(Option<Arc<Mutex<Ring>>>);
+
+impl DecisionTrace {
+    /// A trace keeping the last `capacity` decisions
+    pub fn new(capacity: usize) -> Self {
+        Self(Some(Arc::new(Mutex::new(Ring {
+            capacity,
+            decisions: VecDeque::with_capacity(capacity),
+        }))))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Records that the robot in `state` chose `action` at `tick`; `reason` is only worked
+    /// out while recording
+    pub fn record(
+        &self,
+        tick: u64,
+        state: &RobotState,
+        action: &'static str,
+        reason: impl FnOnce() -> String,
+    ) {
+        let Some(ring) = &self.0 else {
+            return;
+        };
+        let decision = Decision {
+            tick,
+            position: (state.x, state.y),
+            energy: state.energy,
+            status: state.status.clone(),
+            action,
+            reason: reason(),
+        };
+        let mut ring = ring.lock().unwrap_or_else(PoisonError::into_inner);
+        if ring.decisions.len() == ring.capacity {
+            ring.decisions.pop_front();
+        }
+        ring.decisions.push_back(decision);
+    }
+
+    /// The decisions kept, oldest first
+    pub fn decisions(&self) -> Vec<Decision> {
+        self.0.as_ref().map_or_else(Vec::new, |ring| {
+            let ring = ring.lock().unwrap_or_else(PoisonError::into_inner);
+            ring.decisions.iter().cloned().collect()
+        })
+    }
+}
+
+/// Writes `decisions` to `path` as JSON, replacing the file
+pub fn write_json(decisions: &[Decision], path: &Path) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(decisions).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces_keep_the_last_decisions() {
+        let robot = DecisionTrace::new(2);
+        let station = robot.clone();
+        let state = RobotState::new(3, 4, 5, RobotStatus::Exploring, 100);
+        for tick in 1..=3 {
+            robot.record(tick, &state, "explore", || format!("step {}", tick));
+        }
+        let decisions = station.decisions();
+        assert_eq!(decisions.len(), 2);
+        assert_eq!((decisions[0].tick, decisions[1].tick), (2, 3));
+        assert_eq!(decisions[1].position, (4, 5));
+        assert_eq!(decisions[1].reason, "step 3");
+
+        let off = DecisionTrace::default();
+        off.record(1, &state, "explore", || unreachable!());
+        assert!(!off.is_recording() && off.decisions().is_empty());
+    }
+}
@@ -0,0 +1,346 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    app::{App, GameSetup, RobotCounts, SpawnPolicy},
+    communication::channels::ResourceType,
+    error::SimInitError,
+    map::resources::ResourceCatalog,
+    robot::core::knowledge::RobotKnowledge,
+    robot::launcher,
+    robot::registry::RobotType,
+    robot::utils::preset::SwarmPreset,
+    settings::ModuleCatalog,
+};
+
+/// Directory the autosaves are written to
+pub const DEFAULT_SAVE_DIR: &str = "astro-swarm-saves";
+const SAVE_PREFIX: &str = "autosave-";
+
+/// A robot as saved: where it stands and how worn its battery is. Its current task is not
+/// kept, it starts over from there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedRobot {
+    pub id: u32,
+    pub robot_type: RobotType,
+    pub x: usize,
+    pub y: usize,
+    pub energy: u32,
+    pub max_energy: u32,
+    pub rated_energy: u32,
+    pub charge_cycles: u32,
+}
+
+/// What a run needs to go on after a restart: the setup it was generated from, the terrain
+/// as it is now, the station's knowledge, notes and stock, the robots and the swarm preset.
+/// Robot orders, squads, zones and hazards are not kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub setup: GameSetup,
+    pub tick: u64,
+    pub obstacles: Vec<(usize, usize)>,
+    pub resources: Vec<((usize, usize), ResourceType, u32)>,
+    pub knowledge: RobotKnowledge,
+    pub collected_resources: Vec<(ResourceType, u32)>,
+    pub scientific_data: u64,
+    pub explored_tiles: Vec<(usize, usize)>,
+    pub robots: Vec<SavedRobot>,
+    #[serde(default)]
+    pub preset: SwarmPreset,
+    /// Resource types of the run, which the saved deposits and stock go by
+    #[serde(default)]
+    pub catalog: ResourceCatalog,
+}
+
+impl SaveGame {
+    pub fn from_app(app: &App) -> Result<Self, String> {
+        let setup = app.game_setup();
+        let map = app.map.read();
+        let mut robots = Vec::new();
+        for entry in app.robots.iter() {
+            let state = &entry.state;
+            robots.push(SavedRobot {
+                id: state.id,
+                robot_type: entry.robot_type,
+                x: state.x,
+                y: state.y,
+                energy: state.energy,
+                max_energy: state.max_energy,
+                rated_energy: state.rated_energy,
+                charge_cycles: state.charge_cycles,
+            });
+        }
+        robots.sort_by_key(|robot| robot.id);
+
+        let obstacles = (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.is_obstacle(x, y))
+            .collect();
+        let mut resources: Vec<_> = map
+            .get_all_resources()
+            .keys()
+            .filter_map(|&(x, y)| {
+                map.get_resource(x, y)
+                    .map(|(resource_type, amount)| ((x, y), resource_type, amount))
+            })
+            .collect();
+        resources.sort_by_key(|&(tile, _, _)| tile);
+        let mut explored_tiles: Vec<_> = app.explored_tiles.iter().copied().collect();
+        explored_tiles.sort_unstable();
+
+        Ok(Self {
+            setup,
+            tick: app.tick,
+            obstacles,
+            resources,
+            knowledge: app
+                .station
+                .data_manager
+                .read()
+                .map_err(|e| format!("Station lock poisoned: {}", e))?
+                .get_global_robot_knowledge(),
+            collected_resources: app
+                .collected_resources
+                .iter()
+                .map(|(resource_type, &amount)| (*resource_type, amount))
+                .collect(),
+            scientific_data: app.scientific_data,
+            explored_tiles,
+            robots,
+            preset: app.preset,
+            catalog: (**app.resource_catalog()).clone(),
+        })
+    }
+
+    /// Rebuilds the run: the map from the setup's seeds, reshaped as it was saved, then the
+    /// station and the robots, which start again from their saved tiles. A save no run can
+    /// be rebuilt from, e.g. edited by hand, is an error.
+    pub fn restore(&self, module_catalog: ModuleCatalog) -> Result<App, SimInitError> {
+        let setup = &self.setup;
+        let mut app = App::try_new(
+            setup.width,
+            setup.height,
+            setup.map_seed,
+            setup.resource_seed,
+            RobotCounts {
+                exploration: 0,
+                collection: 0,
+                scientific: 0,
+                scout: 0,
+            },
+            SpawnPolicy::default(),
+            module_catalog,
+            Arc::new(self.catalog.clone()),
+            setup.difficulty,
+            setup.shape,
+        )?;
+        app.resume_at(self.tick);
+        app.robot_counts = setup.robot_counts;
+
+        let obstacles: HashSet<(usize, usize)> = self.obstacles.iter().copied().collect();
+        {
+            let mut map = app.map.write();
+            map.restore_terrain(&obstacles, &self.resources);
+            if map.is_station_walled_in() {
+                return Err(SimInitError::StationBlocked {
+                    dock: map.station_coords(),
+                });
+            }
+            if let Some(robot) = self
+                .robots
+                .iter()
+                .find(|robot| map.check_walkable(robot.x, robot.y).is_err())
+            {
+                return Err(SimInitError::InvalidConfig(format!(
+                    "robot {} saved on ({}, {}), not a walkable tile",
+                    robot.id, robot.x, robot.y
+                )));
+            }
+        }
+        app.station
+            .data_mut()
+            .merge_robot_knowledge(0, &self.knowledge);
+        for annotation in self.knowledge.annotations() {
+            app.station
+                .data_mut()
+                .annotate(annotation.x, annotation.y, annotation.text.clone());
+        }
+
+        app.collected_resources = self.collected_resources.iter().cloned().collect();
+        app.scientific_data = self.scientific_data;
+        app.explored_tiles = self.explored_tiles.iter().copied().collect();
+        app.total_explored = app.explored_tiles.len();
+        app.set_preset(self.preset);
+
+        for robot in &self.robots {
+            let mut state = launcher::initial_state(robot.robot_type, robot.id, robot.x, robot.y);
+            state.max_energy = robot.max_energy;
+            state.rated_energy = robot.rated_energy;
+            state.charge_cycles = robot.charge_cycles;
+            state.energy = robot.energy.min(robot.max_energy);
+            app.spawn_restored(robot.robot_type, state);
+        }
+        info!(
+            "Resumed the run at tick {} with {} robots",
+            self.tick,
+            self.robots.len()
+        );
+        Ok(app)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid save {}: {}", path.display(), e))
+    }
+
+    /// Writes the save next to `path` first and renames it into place, so a crash while
+    /// writing never leaves a truncated save behind
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let content =
+            serde_json::to_string(self).map_err(|e| format!("Failed to serialize save: {}", e))?;
+        let partial = path.with_extension("partial");
+        fs::write(&partial, content)
+            .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+        fs::rename(&partial, path)
+            .map_err(|e| format!("Failed to move save to {}: {}", path.display(), e))
+    }
+}
+
+/// Saves the run every `every` ticks into `dir`, keeping the `keep` most recent saves
+#[derive(Debug, Clone)]
+pub struct Autosaver {
+    dir: PathBuf,
+    every: u64,
+    keep: usize,
+    last_saved: u64,
+}
+
+impl Autosaver {
+    /// `every` at 0 never saves. Counts from `tick`, the run's current tick.
+    pub fn new(dir: &Path, every: u64, keep: usize, tick: u64) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            every,
+            keep: keep.max(1),
+            last_saved: tick,
+        }
+    }
+
+    /// Saves the run if it is time to, returns where
+    pub fn tick(&mut self, app: &App) -> Result<Option<PathBuf>, String> {
+        if self.every == 0 || app.tick < self.last_saved + self.every {
+            return Ok(None);
+        }
+        self.last_saved = app.tick;
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+        let path = self
+            .dir
+            .join(format!("{}{:010}.json", SAVE_PREFIX, app.tick));
+        SaveGame::from_app(app)?.write(&path)?;
+        info!("Autosaved to {}", path.display());
+
+        let saves = list_saves(&self.dir)?;
+        for old in saves.iter().take(saves.len().saturating_sub(self.keep)) {
+            fs::remove_file(old)
+                .map_err(|e| format!("Failed to remove {}: {}", old.display(), e))?;
+        }
+        Ok(Some(path))
+    }
+}
+
+/// The most recent autosave in `dir`, if any
+pub fn latest_save(dir: &Path) -> Result<Option<PathBuf>, String> {
+    Ok(list_saves(dir)?.pop())
+}
+
+/// Autosaves in `dir`, oldest first; their names sort by tick
+fn list_saves(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut saves: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "json")
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(SAVE_PREFIX))
+        })
+        .collect();
+    saves.sort();
+    Ok(saves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::map::topology::MapShape;
+
+    #[test]
+    fn test_autosaves_are_pruned_and_the_latest_resumes_the_run() {
+        let counts = RobotCounts {
+            exploration: 1,
+            collection: 1,
+            scientific: 0,
+            scout: 0,
+        };
+        let mut app = App::new(
+            30,
+            10,
+            3,
+            4,
+            counts,
+            SpawnPolicy::NearStation,
+            ModuleCatalog::default(),
+            Arc::default(),
+            Difficulty::Normal,
+            MapShape::default(),
+        );
+        app.scientific_data = 7;
+        app.annotate((4, 2), "rich vein here").unwrap();
+        let dir = std::env::temp_dir().join(format!("astro-saves-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut autosaver = Autosaver::new(&dir, 10, 2, app.tick);
+        let mut written = Vec::new();
+        for tick in [5, 10, 15, 20, 30] {
+            app.tick = tick;
+            written.extend(autosaver.tick(&app).unwrap());
+        }
+        assert_eq!(written.len(), 3);
+        assert_eq!(list_saves(&dir).unwrap(), written[1..].to_vec());
+
+        let save = SaveGame::load(&latest_save(&dir).unwrap().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let resumed = save.restore(ModuleCatalog::default()).unwrap();
+        let mut broken = save.clone();
+        broken.setup.width = 2;
+        assert!(matches!(
+            broken.restore(ModuleCatalog::default()),
+            Err(SimInitError::InvalidConfig(_))
+        ));
+        assert_eq!(resumed.tick, 30);
+        assert_eq!(resumed.scientific_data, 7);
+        assert_eq!(resumed.annotations(), app.annotations());
+        assert_eq!(SaveGame::from_app(&resumed).unwrap().robots, save.robots);
+        assert_eq!(
+            SaveGame::from_app(&resumed).unwrap().obstacles,
+            save.obstacles
+        );
+
+        resumed.shutdown();
+        app.shutdown();
+    }
+}
@@ -0,0 +1,301 @@
+use log::info;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fs, path::Path};
+
+use crate::robot::utils::config;
+
+/// Something that happens to the world at a scheduled tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorldEvent {
+    /// Walkable tiles within `radius` of (x, y) become obstacles
+    Meteor { x: usize, y: usize, radius: usize },
+    /// Free tiles within `radius` of (x, y) get `amount` of the resource each, named as in
+    /// the run's catalog
+    ResourceVein {
+        x: usize,
+        y: usize,
+        radius: usize,
+        resource: String,
+        amount: u32,
+    },
+    /// Every robot is recalled to the station, and recalled again whenever it heads out,
+    /// for `duration` ticks
+    Storm { duration: u64 },
+    /// Tiles within `radius` of (x, y) are closed to robots for `duration` ticks, e.g. after
+    /// a gas leak
+    Hazard {
+        x: usize,
+        y: usize,
+        radius: usize,
+        duration: u64,
+    },
+    /// The robots' radio is down for `duration` ticks, while their solar cells charge fast;
+    /// every robot garbles about `scramble_percent` % of what it knows
+    SolarFlare {
+        duration: u64,
+        scramble_percent: u32,
+    },
+}
+
+/// A world event and the tick it fires at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledEvent {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub event: WorldEvent,
+}
+
+/// Meteors striking random tiles, on average once every `mean_interval` ticks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeteorShower {
+    pub mean_interval: u64,
+    /// Radius of the largest craters, the smallest are a single tile
+    pub max_radius: usize,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+/// Solar flares breaking out at random, on average once every `mean_interval` ticks, each
+/// announced `FLARE_WARNING_TICKS` ahead
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolarActivity {
+    pub mean_interval: u64,
+    pub duration: u64,
+    pub scramble_percent: u32,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+/// Timed world events read from a TOML scenario file, one `[[event]]` table each, plus an
+/// optional `[meteor_shower]` and `[solar_activity]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default, rename = "event")]
+    pub events: Vec<ScheduledEvent>,
+    #[serde(default)]
+    pub meteor_shower: Option<MeteorShower>,
+    #[serde(default)]
+    pub solar_activity: Option<SolarActivity>,
+}
+
+impl Scenario {
+    pub fn from_toml_str(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("Invalid scenario: {}", e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let scenario = Self::from_toml_str(&content)?;
+        info!(
+            "Loaded {} world events from {}",
+            scenario.events.len(),
+            path.display()
+        );
+        Ok(scenario)
+    }
+}
+
+/// Hands out the scenario's events as their tick comes
+#[derive(Debug, Default)]
+pub struct WorldEventScheduler {
+    pending: VecDeque<ScheduledEvent>,
+    meteor_shower: Option<(MeteorShower, StdRng)>,
+    solar_activity: Option<(SolarActivity, StdRng)>,
+}
+
+impl WorldEventScheduler {
+    pub fn new(scenario: Scenario) -> Self {
+        let mut events = scenario.events;
+        // Stable, so events sharing a tick fire in file order
+        events.sort_by_key(|scheduled| scheduled.tick);
+        Self {
+            pending: events.into(),
+            meteor_shower: scenario
+                .meteor_shower
+                .filter(|shower| shower.mean_interval > 0)
+                .map(|shower| {
+                    let rng = StdRng::seed_from_u64(shower.seed);
+                    (shower, rng)
+                }),
+            solar_activity: scenario
+                .solar_activity
+                .filter(|activity| activity.mean_interval > 0)
+                .map(|activity| {
+                    let rng = StdRng::seed_from_u64(activity.seed);
+                    (activity, rng)
+                }),
+        }
+    }
+
+    /// Events scheduled at or before `tick` that have not fired yet, then this tick's random
+    /// meteor, if any, somewhere on a `width` x `height` map. A random solar flare breaking
+    /// out is scheduled `FLARE_WARNING_TICKS` later.
+    pub fn due(&mut self, tick: u64, width: usize, height: usize) -> Vec<WorldEvent> {
+        self.roll_solar_flare(tick);
+        let mut due = Vec::new();
+        while self
+            .pending
+            .front()
+            .is_some_and(|scheduled| scheduled.tick <= tick)
+        {
+            if let Some(scheduled) = self.pending.pop_front() {
+                due.push(scheduled.event);
+            }
+        }
+        if let Some((shower, rng)) = &mut self.meteor_shower {
+            if width > 0 && height > 0 && rng.random_range(0..shower.mean_interval) == 0 {
+                due.push(WorldEvent::Meteor {
+                    x: rng.random_range(0..width),
+                    y: rng.random_range(0..height),
+                    radius: rng.random_range(0..=shower.max_radius),
+                });
+            }
+        }
+        due
+    }
+
+    /// Tick of the next event, if any is left
+    pub fn next_tick(&self) -> Option<u64> {
+        self.pending.front().map(|scheduled| scheduled.tick)
+    }
+
+    /// Tick of the next solar flare, if one is coming
+    pub fn next_flare_tick(&self) -> Option<u64> {
+        self.pending
+            .iter()
+            .find(|scheduled| matches!(scheduled.event, WorldEvent::SolarFlare { .. }))
+            .map(|scheduled| scheduled.tick)
+    }
+
+    fn roll_solar_flare(&mut self, tick: u64) {
+        // One flare announced at a time
+        if self.next_flare_tick().is_some() {
+            return;
+        }
+        let Some((activity, rng)) = &mut self.solar_activity else {
+            return;
+        };
+        if rng.random_range(0..activity.mean_interval) != 0 {
+            return;
+        }
+        let flare = ScheduledEvent {
+            tick: tick + config::FLARE_WARNING_TICKS,
+            event: WorldEvent::SolarFlare {
+                duration: activity.duration,
+                scramble_percent: activity.scramble_percent,
+            },
+        };
+        let at = self
+            .pending
+            .partition_point(|scheduled| scheduled.tick <= flare.tick);
+        self.pending.insert(at, flare);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scenario_events_fire_in_tick_order() {
+        let scenario = Scenario::from_toml_str(
+            r#"
+            [[event]]
+            tick = 50
+            type = "storm"
+            duration = 20
+
+            [[event]]
+            tick = 10
+            type = "meteor"
+            x = 4
+            y = 2
+            radius = 1
+
+            [[event]]
+            tick = 50
+            type = "resource_vein"
+            x = 8
+            y = 3
+            radius = 0
+            resource = "Minerals"
+            amount = 120
+            "#,
+        )
+        .unwrap();
+
+        let mut scheduler = WorldEventScheduler::new(scenario);
+        assert!(scheduler.due(9, 20, 10).is_empty());
+        assert_eq!(
+            scheduler.due(10, 20, 10),
+            vec![WorldEvent::Meteor {
+                x: 4,
+                y: 2,
+                radius: 1
+            }]
+        );
+        assert_eq!(scheduler.next_tick(), Some(50));
+
+        let due = scheduler.due(70, 20, 10);
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0], WorldEvent::Storm { duration: 20 });
+        assert!(matches!(
+            due[1],
+            WorldEvent::ResourceVein { amount: 120, .. }
+        ));
+        assert_eq!(scheduler.next_tick(), None);
+    }
+
+    #[test]
+    fn test_meteor_shower_strikes_inside_the_map() {
+        let scenario = Scenario::from_toml_str(
+            "[meteor_shower]\nmean_interval = 5\nmax_radius = 2\nseed = 3\n",
+        )
+        .unwrap();
+        let mut scheduler = WorldEventScheduler::new(scenario);
+        let meteors: Vec<WorldEvent> = (0..200)
+            .flat_map(|tick| scheduler.due(tick, 20, 10))
+            .collect();
+
+        assert!(meteors.len() > 10 && meteors.len() < 100);
+        for meteor in meteors {
+            let WorldEvent::Meteor { x, y, radius } = meteor else {
+                panic!("Expected a meteor, got {:?}", meteor);
+            };
+            assert!(x < 20 && y < 10 && radius <= 2);
+        }
+    }
+
+    #[test]
+    fn test_random_solar_flares_are_announced_ahead() {
+        let scenario = Scenario::from_toml_str(
+            "[solar_activity]\nmean_interval = 1\nduration = 40\nscramble_percent = 5\n",
+        )
+        .unwrap();
+        let mut scheduler = WorldEventScheduler::new(scenario);
+        assert!(scheduler.due(10, 20, 10).is_empty());
+        let arrival = 10 + config::FLARE_WARNING_TICKS;
+        assert_eq!(scheduler.next_flare_tick(), Some(arrival));
+
+        // Announced once, however many ticks go by before it breaks out
+        for tick in 11..arrival {
+            assert!(scheduler.due(tick, 20, 10).is_empty());
+        }
+        assert_eq!(
+            scheduler.due(arrival, 20, 10),
+            vec![WorldEvent::SolarFlare {
+                duration: 40,
+                scramble_percent: 5
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unknown_event_type_is_rejected() {
+        let result = Scenario::from_toml_str("[[event]]\ntick = 1\ntype = \"flood\"\n");
+        assert!(result.is_err());
+    }
+}
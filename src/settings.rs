@@ -0,0 +1,442 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, time::Duration};
+
+use crate::{
+    app::TICK_RATE,
+    map::resources::{self, ResourceCatalog, ResourceSpec},
+    robot::{
+        registry::RobotType,
+        utils::{config, preset::SwarmPreset},
+    },
+    ui::{keys::KeyBindings, notifications::NotificationConfig},
+};
+
+/// Config file read at startup when no other path is given
+pub const DEFAULT_SETTINGS_PATH: &str = "astro-swarm.toml";
+
+/// Module letting a collector descend to the caves and mine their veins
+pub const DRILL_MODULE: &str = "Drill";
+
+/// User settings loaded from a TOML file. Every section is optional and falls back to the
+/// built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub modules: ModuleCatalog,
+    pub interface: InterfaceSettings,
+    pub robots: RobotSettings,
+    pub memory: MemorySettings,
+    pub alarms: AlarmSettings,
+    pub resources: ResourceSettings,
+    pub notifications: NotificationConfig,
+}
+
+impl Settings {
+    pub fn from_toml_str(content: &str) -> Result<Self, String> {
+        let settings: Self =
+            toml::from_str(content).map_err(|e| format!("Invalid settings: {}", e))?;
+        settings.modules.validate()?;
+        settings.interface.validate()?;
+        settings.robots.validate()?;
+        settings.memory.validate()?;
+        settings.alarms.validate()?;
+        resources::validate_specs(&settings.resources.types)?;
+        Ok(settings)
+    }
+
+    /// Reads the settings at `path`, falling back to the defaults if the file does not exist
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            info!("No settings file at {}, using defaults", path.display());
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let settings = Self::from_toml_str(&content)?;
+        info!("Loaded settings from {}", path.display());
+        Ok(settings)
+    }
+
+    /// Writes the settings to `path`, replacing the file
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        info!("Saved settings to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Preferences edited from the settings screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterfaceSettings {
+    /// Name of the map theme, see `Theme::all`
+    pub theme: String,
+    /// Seeds a new game starts with unless given on the command line
+    pub map_seed: u32,
+    pub resource_seed: u64,
+    /// Milliseconds between two simulation ticks
+    pub tick_rate_ms: u64,
+    pub keys: KeyBindings,
+}
+
+impl InterfaceSettings {
+    pub const TICK_RATE_RANGE_MS: (u64, u64) = (20, 1000);
+
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_rate_ms)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let (min, max) = Self::TICK_RATE_RANGE_MS;
+        if !(min..=max).contains(&self.tick_rate_ms) {
+            return Err(format!("Tick rate must be between {} and {} ms", min, max));
+        }
+        self.keys.validate()
+    }
+}
+
+impl Default for InterfaceSettings {
+    fn default() -> Self {
+        Self {
+            theme: "Classic".to_string(),
+            map_seed: 34,
+            resource_seed: 45,
+            tick_rate_ms: TICK_RATE.as_millis() as u64,
+            keys: KeyBindings::default(),
+        }
+    }
+}
+
+/// Tuning shared by every robot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RobotSettings {
+    /// Each robot's thresholds, sleeps and costs stray up to this many percent from its
+    /// type's, 0 keeps them all alike
+    pub jitter_percent: u32,
+    /// Robots a run holds at most, starting ones included
+    pub max_robots: usize,
+    /// Milliseconds a docked robot waits for the station's merge before reporting again
+    pub merge_timeout_ms: u64,
+    /// Reports a docked robot sends again before resuming without a merge
+    pub merge_retries: u32,
+    /// Swarm strategy a new game starts with
+    pub preset: SwarmPreset,
+}
+
+impl Default for RobotSettings {
+    fn default() -> Self {
+        Self {
+            jitter_percent: 0,
+            max_robots: config::DEFAULT_MAX_ROBOTS,
+            merge_timeout_ms: config::DEFAULT_MERGE_TIMEOUT_MS,
+            merge_retries: config::DEFAULT_MERGE_RETRIES,
+            preset: SwarmPreset::default(),
+        }
+    }
+}
+
+impl RobotSettings {
+    pub const MERGE_TIMEOUT_RANGE_MS: (u64, u64) = (100, 60_000);
+
+    /// Whether two runs can share the process: each app takes the jitter and the cap as it
+    /// starts, but robots read the merge timeout and retries of the process as they dock
+    pub fn same_process_settings(&self, other: &Self) -> bool {
+        self.merge_timeout_ms == other.merge_timeout_ms && self.merge_retries == other.merge_retries
+    }
+
+    pub fn merge_timeout(&self) -> Duration {
+        Duration::from_millis(self.merge_timeout_ms)
+    }
+
+    /// Makes the robots of this process run with these settings
+    pub fn apply(&self) {
+        config::set_jitter_percent(self.jitter_percent);
+        config::set_max_robots(self.max_robots);
+        config::set_merge_timeout(self.merge_timeout());
+        config::set_merge_retries(self.merge_retries);
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.jitter_percent > config::MAX_JITTER_PERCENT {
+            return Err(format!(
+                "Robot jitter must be at most {}%",
+                config::MAX_JITTER_PERCENT
+            ));
+        }
+        if self.max_robots == 0 {
+            return Err("The robot cap must be at least 1".to_string());
+        }
+        let (min, max) = Self::MERGE_TIMEOUT_RANGE_MS;
+        if !(min..=max).contains(&self.merge_timeout_ms) {
+            return Err(format!(
+                "Merge timeout must be between {} and {} ms",
+                min, max
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Budget the approximate memory of a run is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemorySettings {
+    /// Mebibytes the knowledge maps, event queues and stats of a run may take
+    pub limit_mb: u64,
+    /// Percent of the limit past which the run is warned about
+    pub warn_percent: u32,
+}
+
+impl Default for MemorySettings {
+    fn default() -> Self {
+        Self {
+            limit_mb: 1024,
+            warn_percent: 80,
+        }
+    }
+}
+
+impl MemorySettings {
+    pub fn limit_bytes(&self) -> usize {
+        usize::try_from(self.limit_mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.limit_mb == 0 {
+            return Err("The memory limit must be at least 1 MB".to_string());
+        }
+        if !(1..=100).contains(&self.warn_percent) {
+            return Err("The memory warning must be between 1 and 100%".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// When the swarm's health raises an alarm; 0 turns the alarm off
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlarmSettings {
+    /// Percent of the robots that may run low on energy before the alarm goes off
+    pub low_energy_robots_percent: u32,
+    /// Percent of its battery under which a robot runs low
+    pub low_energy_percent: u32,
+    /// Energy and minerals in stock under which the station runs low
+    pub energy_reserve: u32,
+    pub mineral_reserve: u32,
+    /// Minutes, at the tick rate, without a new tile explored
+    pub stalled_exploration_minutes: u64,
+}
+
+impl Default for AlarmSettings {
+    fn default() -> Self {
+        Self {
+            low_energy_robots_percent: 50,
+            low_energy_percent: 20,
+            energy_reserve: 0,
+            mineral_reserve: 0,
+            stalled_exploration_minutes: 5,
+        }
+    }
+}
+
+impl AlarmSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.low_energy_robots_percent > 100 || self.low_energy_percent > 100 {
+            return Err("Alarm percentages must be at most 100%".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Resource types added to the built-in ones, or changing them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceSettings {
+    #[serde(rename = "resource")]
+    pub types: Vec<ResourceSpec>,
+}
+
+impl ResourceSettings {
+    /// The resource types of a run under these settings
+    pub fn catalog(&self) -> Result<ResourceCatalog, String> {
+        ResourceCatalog::with_specs(&self.types)
+    }
+}
+
+/// A module robots can be fitted with at spawn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleSpec {
+    pub name: String,
+    /// Science value added to every analysis
+    pub science_bonus: u32,
+    /// Passive energy cost per move
+    pub energy_cost: u32,
+    /// Robot types allowed to mount the module
+    pub robot_types: Vec<RobotType>,
+}
+
+impl ModuleSpec {
+    fn new(name: &str, science_bonus: u32, energy_cost: u32, robot_types: &[RobotType]) -> Self {
+        Self {
+            name: name.to_string(),
+            science_bonus,
+            energy_cost,
+            robot_types: robot_types.to_vec(),
+        }
+    }
+}
+
+/// Modules available to robots and how many each robot gets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModuleCatalog {
+    /// Each robot gets between 1 and this many modules it is allowed to mount
+    pub max_per_robot: usize,
+    #[serde(rename = "module")]
+    pub modules: Vec<ModuleSpec>,
+}
+
+impl ModuleCatalog {
+    /// Robot types whose behavior makes use of modules
+    const SUPPORTED_TYPES: [RobotType; 2] = [RobotType::Scientific, RobotType::Collection];
+
+    /// The drill, if collectors may mount one
+    pub fn collector_drill(&self) -> Option<&ModuleSpec> {
+        self.for_robot_type(RobotType::Collection)
+            .into_iter()
+            .find(|module| module.name == DRILL_MODULE)
+    }
+
+    /// Modules the given robot type may mount
+    pub fn for_robot_type(&self, robot_type: RobotType) -> Vec<&ModuleSpec> {
+        self.modules
+            .iter()
+            .filter(|module| module.robot_types.contains(&robot_type))
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for module in &self.modules {
+            if module.name.trim().is_empty() {
+                return Err("Module names must not be empty".to_string());
+            }
+            if module.robot_types.is_empty() {
+                return Err(format!("Module {} has no robot types", module.name));
+            }
+            for robot_type in &module.robot_types {
+                if !Self::SUPPORTED_TYPES.contains(robot_type) {
+                    warn!(
+                        "Module {} lists {:?} robots, which do not use modules yet",
+                        module.name, robot_type
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ModuleCatalog {
+    fn default() -> Self {
+        let scientific = [RobotType::Scientific];
+        let drill = [RobotType::Scientific, RobotType::Collection];
+        Self {
+            max_per_robot: 3,
+            modules: vec![
+                ModuleSpec::new("Chemical Analyzer", 15, 2, &scientific),
+                ModuleSpec::new(DRILL_MODULE, 10, 3, &drill),
+                ModuleSpec::new("High-Res Camera", 20, 1, &scientific),
+                ModuleSpec::new("Spectrometer", 25, 2, &scientific),
+                ModuleSpec::new("Sample Container", 5, 1, &scientific),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_module_catalog_replaces_defaults() {
+        let settings = Settings::from_toml_str(
+            r#"
+            [modules]
+            max_per_robot = 1
+
+            [[modules.module]]
+            name = "Seismometer"
+            science_bonus = 30
+            energy_cost = 4
+            robot_types = ["scientific"]
+            "#,
+        )
+        .unwrap();
+
+        let modules = settings.modules.for_robot_type(RobotType::Scientific);
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].name, "Seismometer");
+        assert_eq!(settings.modules.max_per_robot, 1);
+        assert!(settings
+            .modules
+            .for_robot_type(RobotType::Exploration)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_empty_settings_use_default_catalog() {
+        let settings = Settings::from_toml_str("").unwrap();
+        assert_eq!(settings, Settings::default());
+        assert!(Settings::from_toml_str("[[modules.module]]\nname = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_saved_interface_settings_are_read_back() {
+        let path = std::env::temp_dir().join(format!("astro-settings-{}.toml", std::process::id()));
+        let mut settings = Settings::default();
+        settings.interface.theme = "Emoji".to_string();
+        settings.interface.tick_rate_ms = 50;
+        settings
+            .interface
+            .keys
+            .bind(crate::ui::keys::KeyAction::Quit, 'Q')
+            .unwrap();
+
+        settings.save(&path).unwrap();
+        let loaded = Settings::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, settings);
+
+        assert!(Settings::from_toml_str("[interface]\ntick_rate_ms = 1").is_err());
+        assert!(Settings::from_toml_str("[interface.keys]\nquit = \"r\"").is_err());
+        assert!(Settings::from_toml_str("[robots]\njitter_percent = 90").is_err());
+        assert!(Settings::from_toml_str("[robots]\nmax_robots = 0").is_err());
+        assert!(Settings::from_toml_str("[robots]\nmerge_timeout_ms = 0").is_err());
+        assert!(Settings::from_toml_str("[memory]\nwarn_percent = 0").is_err());
+    }
+
+    #[test]
+    fn test_only_process_wide_robot_settings_keep_runs_apart() {
+        let robots = RobotSettings::default();
+        let other_preset = RobotSettings {
+            preset: SwarmPreset::ConservativeEnergy,
+            ..RobotSettings::default()
+        };
+        assert!(robots.same_process_settings(&other_preset));
+        let other_jitter = RobotSettings {
+            jitter_percent: 10,
+            max_robots: 8,
+            ..RobotSettings::default()
+        };
+        assert!(robots.same_process_settings(&other_jitter));
+        let other_retries = RobotSettings {
+            merge_retries: 7,
+            ..RobotSettings::default()
+        };
+        assert!(!robots.same_process_settings(&other_retries));
+    }
+}
@@ -0,0 +1,157 @@
+//! Starting a run from another program, without the terminal interface. The builder gives
+//! the `App`, the handle the run is driven through: `update` once per tick, `subscribe` to
+//! hear of robot events, `send_command` to give orders.
+
+use crate::{
+    app::{App, GameSetup, RobotCounts, SpawnPolicy},
+    difficulty::Difficulty,
+    error::SimInitError,
+    map::{resources::ResourceCatalog, topology::MapShape},
+    robot::utils::{
+        clock::{self, SharedClock},
+        preset::SwarmPreset,
+    },
+    settings::ModuleCatalog,
+};
+use std::sync::Arc;
+
+/// What a run starts from, each part falling back to the game's defaults
+#[derive(Debug, Clone, Default)]
+pub struct SimulationBuilder {
+    setup: GameSetup,
+    spawn: SpawnPolicy,
+    modules: ModuleCatalog,
+    resources: Arc<ResourceCatalog>,
+    preset: SwarmPreset,
+    tick_limit: Option<u64>,
+    clock: Option<SharedClock>,
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from a setup picked elsewhere, e.g. one saved with a run
+    pub fn from_setup(setup: GameSetup) -> Self {
+        Self {
+            setup,
+            ..Self::default()
+        }
+    }
+
+    pub fn size(mut self, width: usize, height: usize) -> Self {
+        self.setup.width = width;
+        self.setup.height = height;
+        self
+    }
+
+    pub fn seeds(mut self, map_seed: u32, resource_seed: u64) -> Self {
+        self.setup.map_seed = map_seed;
+        self.setup.resource_seed = resource_seed;
+        self
+    }
+
+    pub fn robots(mut self, counts: RobotCounts) -> Self {
+        self.setup.robot_counts = counts;
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.setup.difficulty = difficulty;
+        self
+    }
+
+    pub fn shape(mut self, shape: MapShape) -> Self {
+        self.setup.shape = shape;
+        self
+    }
+
+    pub fn spawn(mut self, policy: SpawnPolicy) -> Self {
+        self.spawn = policy;
+        self
+    }
+
+    pub fn modules(mut self, catalog: ModuleCatalog) -> Self {
+        self.modules = catalog;
+        self
+    }
+
+    /// Resource types of the run, the built-in ones if not given
+    pub fn resources(mut self, catalog: Arc<ResourceCatalog>) -> Self {
+        self.resources = catalog;
+        self
+    }
+
+    pub fn preset(mut self, preset: SwarmPreset) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    /// Stops the run after `ticks`
+    pub fn tick_limit(mut self, ticks: u64) -> Self {
+        self.tick_limit = Some(ticks);
+        self
+    }
+
+    /// Time the station and the robots go by, the real one if not given; a stepped clock
+    /// replays a seeded run exactly
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Generates the map and launches the robots
+    pub fn build(self) -> Result<App, SimInitError> {
+        let setup = self.setup;
+        let mut app = App::try_new_on(
+            self.clock.unwrap_or_else(clock::real),
+            setup.width,
+            setup.height,
+            setup.map_seed,
+            setup.resource_seed,
+            setup.robot_counts,
+            self.spawn,
+            self.modules,
+            self.resources,
+            setup.difficulty,
+            setup.shape,
+        )?;
+        app.set_preset(self.preset);
+        app.set_tick_limit(self.tick_limit);
+        Ok(app)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::MissionOutcome;
+
+    #[test]
+    fn test_built_runs_start_from_the_chosen_setup() {
+        let one_explorer = RobotCounts {
+            exploration: 1,
+            collection: 0,
+            scientific: 0,
+            scout: 0,
+        };
+        let mut app = SimulationBuilder::new()
+            .size(30, 12)
+            .seeds(3, 4)
+            .robots(one_explorer)
+            .preset(SwarmPreset::ConservativeEnergy)
+            .tick_limit(2)
+            .build()
+            .unwrap();
+        assert_eq!((app.map_width, app.map_seed), (30, 3));
+        assert_eq!(app.preset, SwarmPreset::ConservativeEnergy);
+        for _ in 0..3 {
+            app.update();
+        }
+        let summary = app.completion_summary.as_ref().unwrap();
+        assert_eq!(summary.outcome, MissionOutcome::TimedOut);
+
+        assert!(SimulationBuilder::new().size(2, 2).build().is_err());
+    }
+}
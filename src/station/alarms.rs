@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use crate::settings::AlarmSettings;
+
+/// Something wrong with the swarm as a whole, raised and cleared by `AlarmMonitor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Alarm {
+    /// Too many robots run low on energy
+    LowSwarmEnergy,
+    /// The station's energy stock runs low
+    LowEnergyReserve,
+    /// The station's mineral stock runs low
+    LowMineralReserve,
+    /// No new tile explored for a while
+    ExplorationStalled,
+}
+
+/// What the alarms are checked against, read from the app each tick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SwarmReadings {
+    pub robots: usize,
+    /// Robots under the low energy percentage of their battery
+    pub low_energy_robots: usize,
+    pub energy_stock: u32,
+    pub mineral_stock: u32,
+    pub explored_tiles: usize,
+    /// Whether tiles are left to explore
+    pub exploring: bool,
+}
+
+/// Watches the swarm's health against the alarm settings, keeping the alarms raised until
+/// what set them off is over
+#[derive(Debug, Clone)]
+pub struct AlarmMonitor {
+    settings: AlarmSettings,
+    /// Ticks without a new tile before exploration counts as stalled, `None` when off
+    stall_ticks: Option<u64>,
+    active: BTreeSet<Alarm>,
+    /// Explored tiles last time the count went up, and the tick it did
+    progress: (usize, u64),
+}
+
+impl AlarmMonitor {
+    /// Alarms set off by `settings`, the stall counted in ticks of `tick_rate`
+    pub fn new(settings: AlarmSettings, tick_rate: Duration) -> Self {
+        let stall_ticks = (settings.stalled_exploration_minutes > 0).then(|| {
+            let millis = u128::from(settings.stalled_exploration_minutes) * 60_000
+                / tick_rate.as_millis().max(1);
+            u64::try_from(millis).unwrap_or(u64::MAX)
+        });
+        Self {
+            settings,
+            stall_ticks,
+            active: BTreeSet::new(),
+            progress: (0, 0),
+        }
+    }
+
+    /// Checks `readings` at `tick`, returns the alarms raised (`true`) or cleared (`false`)
+    pub fn check(&mut self, tick: u64, readings: &SwarmReadings) -> Vec<(Alarm, bool)> {
+        if readings.explored_tiles > self.progress.0 {
+            self.progress = (readings.explored_tiles, tick);
+        }
+        let settings = &self.settings;
+        let conditions = [
+            (
+                Alarm::LowSwarmEnergy,
+                settings.low_energy_robots_percent > 0
+                    && readings.robots > 0
+                    && readings.low_energy_robots * 100
+                        > readings.robots * settings.low_energy_robots_percent as usize,
+            ),
+            (
+                Alarm::LowEnergyReserve,
+                readings.energy_stock < settings.energy_reserve,
+            ),
+            (
+                Alarm::LowMineralReserve,
+                readings.mineral_stock < settings.mineral_reserve,
+            ),
+            (
+                Alarm::ExplorationStalled,
+                readings.exploring
+                    && self
+                        .stall_ticks
+                        .is_some_and(|ticks| tick.saturating_sub(self.progress.1) >= ticks),
+            ),
+        ];
+
+        let mut changes = Vec::new();
+        for (alarm, on) in conditions {
+            let changed = if on {
+                self.active.insert(alarm)
+            } else {
+                self.active.remove(&alarm)
+            };
+            if changed {
+                changes.push((alarm, on));
+            }
+        }
+        changes
+    }
+
+    /// Alarms raised now
+    pub fn active(&self) -> impl Iterator<Item = Alarm> + '_ {
+        self.active.iter().copied()
+    }
+
+    pub fn is_raised(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    /// What the alarm means with the thresholds in place
+    pub fn describe(&self, alarm: Alarm) -> String {
+        let settings = &self.settings;
+        match alarm {
+            Alarm::LowSwarmEnergy => format!(
+                "over {}% of the robots below {}% energy",
+                settings.low_energy_robots_percent, settings.low_energy_percent
+            ),
+            Alarm::LowEnergyReserve => {
+                format!("energy reserve under {}", settings.energy_reserve)
+            }
+            Alarm::LowMineralReserve => {
+                format!("mineral reserve under {}", settings.mineral_reserve)
+            }
+            Alarm::ExplorationStalled => format!(
+                "no new tile explored for {} minutes",
+                settings.stalled_exploration_minutes
+            ),
+        }
+    }
+
+    /// Whether a robot with `energy` of `max_energy` runs low
+    pub fn is_low_on_energy(&self, energy: u32, max_energy: u32) -> bool {
+        u64::from(energy) * 100
+            < u64::from(max_energy) * u64::from(self.settings.low_energy_percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alarms_are_raised_and_cleared_once() {
+        // One minute is 600 ticks of 100 ms
+        let settings = AlarmSettings {
+            energy_reserve: 50,
+            stalled_exploration_minutes: 1,
+            ..AlarmSettings::default()
+        };
+        let mut monitor = AlarmMonitor::new(settings, Duration::from_millis(100));
+        let mut readings = SwarmReadings {
+            robots: 4,
+            low_energy_robots: 2,
+            energy_stock: 100,
+            mineral_stock: 0,
+            explored_tiles: 10,
+            exploring: true,
+        };
+        assert!(monitor.check(1, &readings).is_empty());
+
+        readings.low_energy_robots = 3;
+        readings.energy_stock = 20;
+        assert_eq!(
+            monitor.check(2, &readings),
+            vec![
+                (Alarm::LowSwarmEnergy, true),
+                (Alarm::LowEnergyReserve, true)
+            ]
+        );
+        assert!(monitor.check(3, &readings).is_empty());
+
+        readings.energy_stock = 60;
+        assert_eq!(
+            monitor.check(601, &readings),
+            vec![
+                (Alarm::LowEnergyReserve, false),
+                (Alarm::ExplorationStalled, true)
+            ]
+        );
+        readings.explored_tiles = 11;
+        assert_eq!(
+            monitor.check(602, &readings),
+            vec![(Alarm::ExplorationStalled, false)]
+        );
+        assert_eq!(
+            monitor.active().collect::<Vec<_>>(),
+            [Alarm::LowSwarmEnergy]
+        );
+        assert!(monitor.is_low_on_energy(99, 500));
+    }
+}
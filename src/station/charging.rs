@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+use crate::robot::registry::RobotType;
+
+/// Which docked robot gets the next free charger
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChargingPolicy {
+    /// In the order they docked
+    #[default]
+    Fifo,
+    /// The emptiest battery first
+    LowestEnergy,
+    /// Collectors first, as they keep the stock going, then scientists, explorers and scouts
+    MissionCritical,
+}
+
+impl ChargingPolicy {
+    pub const ALL: [ChargingPolicy; 3] = [
+        ChargingPolicy::Fifo,
+        ChargingPolicy::LowestEnergy,
+        ChargingPolicy::MissionCritical,
+    ];
+
+    /// Name on the command line, in the console and the control API
+    pub fn name(self) -> &'static str {
+        match self {
+            ChargingPolicy::Fifo => "fifo",
+            ChargingPolicy::LowestEnergy => "lowest-energy",
+            ChargingPolicy::MissionCritical => "mission-critical",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|policy| policy.name() == name)
+    }
+
+    /// The policy after this one, to cycle through them
+    pub fn next(self) -> Self {
+        let at = Self::ALL.iter().position(|&policy| policy == self);
+        Self::ALL[at.map_or(0, |at| (at + 1) % Self::ALL.len())]
+    }
+}
+
+/// How much a robot type's charge matters to the mission, lowest first
+fn mission_rank(robot_type: RobotType) -> u8 {
+    match robot_type {
+        RobotType::Collection => 0,
+        RobotType::Scientific => 1,
+        RobotType::Exploration => 2,
+        RobotType::Scout => 3,
+    }
+}
+
+/// A docked robot waiting for a charger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChargeRequest {
+    pub robot_id: u32,
+    pub robot_type: RobotType,
+    /// Energy left when it docked
+    pub energy: u32,
+    /// Tick it docked at
+    pub since: u64,
+}
+
+/// A robot on a charger until the given tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Charging {
+    pub robot_id: u32,
+    pub until: u64,
+}
+
+/// The station's chargers. Without a limit every docked robot charges at once, as it always
+/// did; with `slots` chargers a charge takes `charge_ticks` and docked robots queue for a
+/// free one, served in the order `policy` has them.
+#[derive(Debug, Clone, Default)]
+pub struct ChargingBay {
+    slots: Option<usize>,
+    charge_ticks: u64,
+    policy: ChargingPolicy,
+    queue: Vec<ChargeRequest>,
+    charging: Vec<Charging>,
+}
+
+impl ChargingBay {
+    pub fn new(slots: Option<usize>, charge_ticks: u64) -> Self {
+        Self {
+            slots,
+            charge_ticks,
+            ..Self::default()
+        }
+    }
+
+    /// Whether robots queue for a charger instead of charging as they dock
+    pub fn is_limited(&self) -> bool {
+        self.slots.is_some()
+    }
+
+    pub fn slots(&self) -> Option<usize> {
+        self.slots
+    }
+
+    pub fn charge_ticks(&self) -> u64 {
+        self.charge_ticks
+    }
+
+    pub fn policy(&self) -> ChargingPolicy {
+        self.policy
+    }
+
+    /// Serves the robots still waiting by `policy` from now on
+    pub fn set_policy(&mut self, policy: ChargingPolicy) {
+        self.policy = policy;
+    }
+
+    /// Queues a robot that docked at `since` with `energy` left
+    pub fn enqueue(&mut self, robot_id: u32, robot_type: RobotType, energy: u32, since: u64) {
+        self.remove(robot_id);
+        self.queue.push(ChargeRequest {
+            robot_id,
+            robot_type,
+            energy,
+            since,
+        });
+    }
+
+    /// Forgets a robot, waiting or charging
+    pub fn remove(&mut self, robot_id: u32) {
+        self.queue.retain(|request| request.robot_id != robot_id);
+        self.charging
+            .retain(|charging| charging.robot_id != robot_id);
+    }
+
+    /// Robots whose charge is over at `tick`, freeing their chargers
+    pub fn finish(&mut self, tick: u64) -> Vec<u32> {
+        let (done, charging) = self
+            .charging
+            .iter()
+            .partition(|charging| tick >= charging.until);
+        self.charging = charging;
+        done.into_iter()
+            .map(|charging: Charging| charging.robot_id)
+            .collect()
+    }
+
+    /// Robots to put on the chargers free at `tick`, in the policy's order
+    pub fn next_grants(&mut self, tick: u64) -> Vec<u32> {
+        let free = self.slots.map_or(usize::MAX, |slots| {
+            slots.saturating_sub(self.charging.len())
+        });
+        let mut queue = self.queue();
+        queue.truncate(free);
+        for request in &queue {
+            self.remove(request.robot_id);
+            self.charging.push(Charging {
+                robot_id: request.robot_id,
+                until: tick + self.charge_ticks,
+            });
+        }
+        queue.into_iter().map(|request| request.robot_id).collect()
+    }
+
+    /// Robots waiting, the next to be served first
+    pub fn queue(&self) -> Vec<ChargeRequest> {
+        let mut queue = self.queue.clone();
+        match self.policy {
+            ChargingPolicy::Fifo => queue.sort_by_key(|r| (r.since, r.robot_id)),
+            ChargingPolicy::LowestEnergy => queue.sort_by_key(|r| (r.energy, r.since, r.robot_id)),
+            ChargingPolicy::MissionCritical => {
+                queue.sort_by_key(|r| (mission_rank(r.robot_type), r.since, r.robot_id))
+            }
+        }
+        queue
+    }
+
+    /// Robots on a charger
+    pub fn charging(&self) -> &[Charging] {
+        &self.charging
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chargers_serve_the_queue_in_policy_order() {
+        let mut bay = ChargingBay::new(Some(1), 10);
+        bay.enqueue(1, RobotType::Exploration, 50, 0);
+        bay.enqueue(2, RobotType::Scout, 10, 1);
+        bay.enqueue(3, RobotType::Collection, 30, 2);
+        bay.enqueue(4, RobotType::Scientific, 20, 3);
+
+        assert_eq!(bay.next_grants(5), vec![1]);
+        assert!(bay.next_grants(6).is_empty());
+        assert!(bay.finish(14).is_empty());
+
+        bay.set_policy(ChargingPolicy::LowestEnergy);
+        assert_eq!(bay.finish(15), vec![1]);
+        assert_eq!(bay.next_grants(15), vec![2]);
+        bay.set_policy(ChargingPolicy::MissionCritical);
+        assert_eq!(
+            bay.queue().iter().map(|r| r.robot_id).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert_eq!(
+            bay.charging(),
+            &[Charging {
+                robot_id: 2,
+                until: 25
+            }]
+        );
+
+        assert!(!ChargingBay::default().is_limited());
+        assert_eq!(
+            ChargingPolicy::from_name("lowest-energy"),
+            Some(ChargingPolicy::LowestEnergy)
+        );
+        assert_eq!(ChargingPolicy::MissionCritical.next(), ChargingPolicy::Fifo);
+    }
+}
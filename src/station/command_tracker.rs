@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use crate::communication::channels::{CommandEnvelope, RobotCommand};
+
+/// A command sent to a robot that has not been acknowledged yet
+#[derive(Debug, Clone)]
+pub struct PendingCommand {
+    pub robot_id: u32,
+    pub command: RobotCommand,
+    pub sent_tick: u64,
+    pub attempts: u32,
+}
+
+/// What to do with a command whose acknowledgement is overdue
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overdue {
+    Retry(u32, CommandEnvelope),
+    Failed(u32, CommandEnvelope),
+}
+
+/// Delivery counters shown in the sidebar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommandStats {
+    pub acked: u64,
+    pub rejected: u64,
+    pub retried: u64,
+    pub failed: u64,
+}
+
+/// Tracks commands until the robot acknowledges or rejects them
+pub struct CommandTracker {
+    next_id: u64,
+    pending: BTreeMap<u64, PendingCommand>,
+    timeout_ticks: u64,
+    max_attempts: u32,
+    pub stats: CommandStats,
+}
+
+impl CommandTracker {
+    pub fn new(timeout_ticks: u64, max_attempts: u32) -> Self {
+        Self {
+            next_id: 1,
+            pending: BTreeMap::new(),
+            timeout_ticks,
+            max_attempts,
+            stats: CommandStats::default(),
+        }
+    }
+
+    /// Assigns an id to a command about to be sent at `tick`
+    pub fn register(&mut self, robot_id: u32, command: RobotCommand, tick: u64) -> CommandEnvelope {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(
+            id,
+            PendingCommand {
+                robot_id,
+                command: command.clone(),
+                sent_tick: tick,
+                attempts: 1,
+            },
+        );
+        CommandEnvelope { id, command }
+    }
+
+    /// Counts the pending commands as sent at `tick`, after their acknowledgements could not
+    /// get through for a while
+    pub fn restart_timers(&mut self, tick: u64) {
+        for pending in self.pending.values_mut() {
+            pending.sent_tick = tick;
+        }
+    }
+
+    /// Drops a command that could not be sent at all
+    pub fn cancel(&mut self, command_id: u64) {
+        self.pending.remove(&command_id);
+    }
+
+    /// Returns false for unknown ids, e.g. a late ack of a command already retried
+    pub fn acknowledge(&mut self, command_id: u64) -> bool {
+        let known = self.pending.remove(&command_id).is_some();
+        if known {
+            self.stats.acked += 1;
+        }
+        known
+    }
+
+    /// Returns the rejected command, if it was still pending
+    pub fn reject(&mut self, command_id: u64) -> Option<PendingCommand> {
+        let pending = self.pending.remove(&command_id)?;
+        self.stats.rejected += 1;
+        Some(pending)
+    }
+
+    /// Forgets the pending commands of a robot that shut down
+    pub fn forget_robot(&mut self, robot_id: u32) {
+        self.pending
+            .retain(|_, pending| pending.robot_id != robot_id);
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Commands unacknowledged for `timeout_ticks`: retried with the same id until
+    /// `max_attempts` sends, then reported as failed and dropped
+    pub fn overdue(&mut self, tick: u64) -> Vec<Overdue> {
+        let mut overdue = Vec::new();
+        let timeout_ticks = self.timeout_ticks;
+        let max_attempts = self.max_attempts;
+        self.pending.retain(|&id, pending| {
+            if tick.saturating_sub(pending.sent_tick) < timeout_ticks {
+                return true;
+            }
+            let envelope = CommandEnvelope {
+                id,
+                command: pending.command.clone(),
+            };
+            if pending.attempts >= max_attempts {
+                overdue.push(Overdue::Failed(pending.robot_id, envelope));
+                return false;
+            }
+            pending.attempts += 1;
+            pending.sent_tick = tick;
+            overdue.push(Overdue::Retry(pending.robot_id, envelope));
+            true
+        });
+        for entry in &overdue {
+            match entry {
+                Overdue::Retry(..) => self.stats.retried += 1,
+                Overdue::Failed(..) => self.stats.failed += 1,
+            }
+        }
+        overdue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unacknowledged_command_is_retried_then_fails() {
+        let mut tracker = CommandTracker::new(10, 2);
+        let envelope = tracker.register(1, RobotCommand::ReturnToStation, 0);
+        let acked = tracker.register(2, RobotCommand::Park, 0);
+
+        assert!(tracker.acknowledge(acked.id));
+        assert!(!tracker.acknowledge(acked.id));
+        assert!(tracker.overdue(9).is_empty());
+
+        assert_eq!(
+            tracker.overdue(10),
+            vec![Overdue::Retry(1, envelope.clone())]
+        );
+        assert!(tracker.overdue(15).is_empty());
+        assert_eq!(tracker.overdue(20), vec![Overdue::Failed(1, envelope)]);
+        assert_eq!(tracker.pending_count(), 0);
+        assert_eq!(
+            tracker.stats,
+            CommandStats {
+                acked: 1,
+                rejected: 0,
+                retried: 1,
+                failed: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_rejected_and_forgotten_commands_stop_pending() {
+        let mut tracker = CommandTracker::new(10, 3);
+        let rejected = tracker.register(1, RobotCommand::Park, 0);
+        tracker.register(2, RobotCommand::Retire, 0);
+
+        assert_eq!(tracker.reject(rejected.id).map(|p| p.robot_id), Some(1));
+        tracker.forget_robot(2);
+        assert!(tracker.overdue(100).is_empty());
+        assert_eq!(tracker.stats.rejected, 1);
+    }
+}
@@ -1,9 +1,20 @@
 use crate::communication::channels::ResourceType;
-use crate::robot::core::knowledge::{RobotKnowledge, TileInfo};
+use crate::map::noise::Map;
+use crate::map::topology::Topology;
+use crate::memory;
+use crate::robot::core::flow_field::FlowField;
+use crate::robot::core::knowledge::{
+    DynamicLayer, KnowledgeDivergence, RobotKnowledge, Sighting, TileInfo,
+};
+use crate::robot::core::spatial_index::TileIndex;
+use crate::robot::utils::clock::{self, SharedClock};
+use crate::robot::utils::config;
 use chrono::{DateTime, Utc};
 use log::{debug, trace, warn};
+use serde::Serialize;
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 
 #[derive(Clone, Debug)]
 pub struct ResourceVersion {
@@ -24,16 +35,199 @@ pub enum GlobalTileInfo {
 
 pub struct DataManager {
     global_knowledge: HashMap<(usize, usize), GlobalTileInfo>,
+    /// Known deposits and unknown tiles of `global_knowledge`, for nearest-tile queries
+    index: TileIndex,
     map_width: usize,
     map_height: usize,
+    /// Distances and neighbors of nearest-tile queries
+    topology: Topology,
+    /// Moving entities reported by robots or seen by the station, apart from the terrain
+    entities: DynamicLayer,
+    /// No-go tiles passed on to docking robots
+    exclusions: BTreeSet<(usize, usize)>,
+    /// Tiles that can change hands each way per dock, all of them when `None`
+    bandwidth: Option<usize>,
+    /// Changed tiles left out of merges for lack of bandwidth
+    deferred_deltas: u64,
+    /// Amount each deposit held when first reported
+    deposits: HashMap<(usize, usize), u32>,
+    /// Tick each walkable or obstacle tile was last seen at by a robot
+    seen: HashMap<(usize, usize), u64>,
+    /// Most recent merges, oldest first
+    merge_log: VecDeque<MergeRecord>,
+    conflict_stats: ConflictStats,
+    /// Stamps merged tiles
+    clock: SharedClock,
+}
+
+/// What one robot's report brought to the station's knowledge
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MergeStats {
+    /// Tiles the station learned or corrected from the report
+    pub contributed: usize,
+    /// Reported tiles that disagreed with what the station knew, whichever way it went
+    pub conflicts: usize,
+}
+
+/// How two reports of the same tile disagree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// One report has an obstacle where the other can walk
+    ObstacleWalkable,
+    /// Both report the same resource, in different amounts
+    ResourceAmount,
+    /// Both report a resource, of different types
+    ResourceType,
+    /// One report has a resource where the other has none
+    ResourcePresence,
+}
+
+/// Conflicts met while merging robot reports, by kind, to judge how well robots sense and
+/// how often the merge has to arbitrate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ConflictStats {
+    pub obstacle_walkable: u64,
+    pub resource_amount: u64,
+    pub resource_type: u64,
+    pub resource_presence: u64,
+    /// Conflicts the incoming report won by being newer
+    pub overturned: u64,
+}
+
+impl ConflictStats {
+    pub fn total(&self) -> u64 {
+        self.obstacle_walkable + self.resource_amount + self.resource_type + self.resource_presence
+    }
+
+    fn record(&mut self, kind: ConflictKind, overturned: bool) {
+        match kind {
+            ConflictKind::ObstacleWalkable => self.obstacle_walkable += 1,
+            ConflictKind::ResourceAmount => self.resource_amount += 1,
+            ConflictKind::ResourceType => self.resource_type += 1,
+            ConflictKind::ResourcePresence => self.resource_presence += 1,
+        }
+        if overturned {
+            self.overturned += 1;
+        }
+    }
+}
+
+/// A past merge, for the station's log
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergeRecord {
+    pub tick: u64,
+    pub robot_id: u32,
+    pub stats: MergeStats,
+}
+
+/// How much of the map the station knows and how far its deposits have been worked
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KnowledgeCoverage {
+    pub total_tiles: usize,
+    pub known_tiles: usize,
+    /// Deposits ever reported
+    pub deposits: usize,
+    /// Deposits something was taken from
+    pub claimed: usize,
+    /// Deposits with nothing left
+    pub exhausted: usize,
+    /// Amounts still known to lie in deposits, by type
+    pub remaining: HashMap<ResourceType, u32>,
+}
+
+impl KnowledgeCoverage {
+    pub fn known_percent(&self) -> u16 {
+        percent(self.known_tiles, self.total_tiles)
+    }
+
+    pub fn claimed_percent(&self) -> u16 {
+        percent(self.claimed, self.deposits)
+    }
+
+    pub fn exhausted_percent(&self) -> u16 {
+        percent(self.exhausted, self.deposits)
+    }
+
+    pub fn remaining(&self, resource_type: &ResourceType) -> u32 {
+        self.remaining.get(resource_type).copied().unwrap_or(0)
+    }
+}
+
+fn percent(part: usize, whole: usize) -> u16 {
+    (part * 100).checked_div(whole).unwrap_or(0).min(100) as u16
+}
+
+/// A tile and what it holds, as passed between a robot and the station
+type TileDelta = ((usize, usize), TileInfo);
+
+/// How much a changed tile is worth passing on when bandwidth is short
+fn importance(info: &TileInfo) -> u8 {
+    match info {
+        TileInfo::Resource(..) => 3,
+        TileInfo::Obstacle => 2,
+        TileInfo::Walkable | TileInfo::Station => 1,
+        TileInfo::Unknown => 0,
+    }
+}
+
+/// The `budget` most important changed tiles, and how many were left out
+fn most_important(mut deltas: Vec<TileDelta>, budget: usize) -> (Vec<TileDelta>, usize) {
+    deltas.sort_by_key(|(tile, info)| (Reverse(importance(info)), *tile));
+    let deferred = deltas.len().saturating_sub(budget);
+    deltas.truncate(budget);
+    (deltas, deferred)
+}
+
+impl GlobalTileInfo {
+    /// The tile as robots record it
+    fn to_tile_info(&self) -> TileInfo {
+        match self {
+            GlobalTileInfo::Unknown => TileInfo::Unknown,
+            GlobalTileInfo::Walkable(_) => TileInfo::Walkable,
+            GlobalTileInfo::Obstacle(_) => TileInfo::Obstacle,
+            GlobalTileInfo::Resource(version) => {
+                TileInfo::Resource(version.resource_type, version.amount)
+            }
+            GlobalTileInfo::Station => TileInfo::Station,
+        }
+    }
+
+    /// How a report of `other` for this tile disagrees with it. Unknown and station tiles
+    /// never conflict.
+    fn conflict_with(&self, other: &GlobalTileInfo) -> Option<ConflictKind> {
+        use GlobalTileInfo::*;
+        match (self, other) {
+            (Walkable(_), Obstacle(_)) | (Obstacle(_), Walkable(_)) => {
+                Some(ConflictKind::ObstacleWalkable)
+            }
+            (Resource(current), Resource(new)) if current.resource_type != new.resource_type => {
+                Some(ConflictKind::ResourceType)
+            }
+            (Resource(current), Resource(new)) if current.amount != new.amount => {
+                Some(ConflictKind::ResourceAmount)
+            }
+            (Resource(_), Walkable(_) | Obstacle(_)) | (Walkable(_) | Obstacle(_), Resource(_)) => {
+                Some(ConflictKind::ResourcePresence)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl DataManager {
     pub fn new(width: usize, height: usize) -> Self {
+        Self::with_station(width, height, (width / 2, height / 2))
+    }
+
+    /// Knowledge base of a map whose station is not at the center, e.g. above cave layers
+    pub fn with_station(
+        width: usize,
+        height: usize,
+        (station_x, station_y): (usize, usize),
+    ) -> Self {
         let capacity = width * height;
         let mut global_knowledge = HashMap::with_capacity(capacity);
-        let station_x = width / 2;
-        let station_y = height / 2;
+        let mut index = TileIndex::default();
 
         for y in 0..height {
             for x in 0..width {
@@ -43,6 +237,7 @@ impl DataManager {
                     global_knowledge.insert((x, y), GlobalTileInfo::Station);
                 } else {
                     global_knowledge.insert((x, y), GlobalTileInfo::Unknown);
+                    index.update((x, y), None, &TileInfo::Unknown);
                 }
             }
         }
@@ -53,17 +248,88 @@ impl DataManager {
         );
         Self {
             global_knowledge,
+            index,
             map_width: width,
             map_height: height,
+            topology: Topology {
+                width,
+                height,
+                layer_height: height,
+                wraps: false,
+                hex: false,
+                ..Topology::default()
+            },
+            entities: DynamicLayer::default(),
+            exclusions: BTreeSet::new(),
+            bandwidth: None,
+            deferred_deltas: 0,
+            deposits: HashMap::new(),
+            seen: HashMap::new(),
+            merge_log: VecDeque::new(),
+            conflict_stats: ConflictStats::default(),
+            clock: clock::real(),
         }
     }
 
+    /// Stamps merged tiles with `clock`'s time rather than the system's
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    /// Measures nearest-tile queries on `topology` instead of a flat square grid
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// Limits merges to the `budget` most important changed tiles each way, resources first,
+    /// then obstacles, then walkable ground; `None` lifts the limit
+    pub fn set_bandwidth(&mut self, budget: Option<usize>) {
+        self.bandwidth = budget;
+    }
+
+    pub fn bandwidth(&self) -> Option<usize> {
+        self.bandwidth
+    }
+
+    pub fn deferred_deltas(&self) -> u64 {
+        self.deferred_deltas
+    }
+
+    /// Known tiles of `knowledge` that differ from the station's
+    fn changed_tiles(&self, knowledge: &RobotKnowledge) -> Vec<TileDelta> {
+        knowledge
+            .tiles()
+            .filter(|(tile, info)| {
+                !matches!(info, TileInfo::Unknown)
+                    && self
+                        .global_knowledge
+                        .get(tile)
+                        .is_none_or(|global| global.to_tile_info() != **info)
+            })
+            .map(|(tile, info)| (tile, info.clone()))
+            .collect()
+    }
+
     /// My Logic : Merges knowledge reported by a specific robot into the global knowledge base
     /// Uses timestamps to resolve conflicts, prioritizing newer information
-    pub fn merge_robot_knowledge(&mut self, robot_id: u32, knowledge: &RobotKnowledge) {
-        let now = Utc::now();
+    pub fn merge_robot_knowledge(
+        &mut self,
+        robot_id: u32,
+        knowledge: &RobotKnowledge,
+    ) -> MergeStats {
+        let now = self.clock.now();
+        let mut stats = MergeStats::default();
         trace!("Merging knowledge from Robot {}", robot_id);
-        for (&(x, y), robot_tile_info) in &knowledge.map {
+        self.entities.merge(knowledge.entities());
+        let sent: Option<HashSet<(usize, usize)>> = self.bandwidth.map(|budget| {
+            let (sent, deferred) = most_important(self.changed_tiles(knowledge), budget);
+            self.deferred_deltas += deferred as u64;
+            sent.into_iter().map(|(tile, _)| tile).collect()
+        });
+        for ((x, y), robot_tile_info) in knowledge.tiles() {
+            if sent.as_ref().is_some_and(|sent| !sent.contains(&(x, y))) {
+                continue;
+            }
             if x >= self.map_width || y >= self.map_height {
                 warn!(
                     "Robot {} reported knowledge for out-of-bounds tile ({}, {}). Skipping.",
@@ -89,11 +355,12 @@ impl DataManager {
                 TileInfo::Walkable => Some(GlobalTileInfo::Walkable(now)),
                 TileInfo::Obstacle => Some(GlobalTileInfo::Obstacle(now)),
                 TileInfo::Resource(res_type, amount) => {
+                    self.deposits.entry((x, y)).or_insert(*amount);
                     let version = ResourceVersion {
                         amount: *amount,
                         timestamp: now,
                         robot_id,
-                        resource_type: res_type.clone(),
+                        resource_type: *res_type,
                     };
                     Some(GlobalTileInfo::Resource(version))
                 }
@@ -101,16 +368,81 @@ impl DataManager {
             };
 
             if let Some(new_info) = potential_update {
-                self.update_global_tile(x, y, new_info);
+                let before = self
+                    .global_knowledge
+                    .get(&(x, y))
+                    .map(|info| info.to_tile_info());
+                if self.update_global_tile(x, y, new_info).is_some() {
+                    stats.conflicts += 1;
+                }
+                let after = self
+                    .global_knowledge
+                    .get(&(x, y))
+                    .map(|info| info.to_tile_info());
+                if after != before {
+                    stats.contributed += 1;
+                    self.seen.remove(&(x, y));
+                }
+                if let (Some(seen), true) = (
+                    knowledge.seen_at(x, y),
+                    after.as_ref() == Some(robot_tile_info),
+                ) {
+                    let known = self.seen.entry((x, y)).or_insert(seen);
+                    *known = (*known).max(seen);
+                }
             }
         }
+        stats
+    }
+
+    /// Adds a merge to the log, which keeps the `config::MERGE_LOG_LEN` most recent ones
+    pub fn log_merge(&mut self, tick: u64, robot_id: u32, stats: MergeStats) {
+        if self.merge_log.len() == config::MERGE_LOG_LEN {
+            self.merge_log.pop_front();
+        }
+        self.merge_log.push_back(MergeRecord {
+            tick,
+            robot_id,
+            stats,
+        });
+    }
+
+    pub fn merge_log(&self) -> impl Iterator<Item = &MergeRecord> {
+        self.merge_log.iter()
+    }
+
+    /// Conflicts met in every merge so far
+    pub fn conflict_stats(&self) -> ConflictStats {
+        self.conflict_stats
     }
 
-    // Update global tile, resolving conflicts (latest timestamp wins)
-    pub fn update_global_tile(&mut self, x: usize, y: usize, new_info: GlobalTileInfo) {
+    /// Approximate bytes held by the station's knowledge, see `crate::memory`
+    pub fn approx_bytes(&self) -> usize {
+        memory::hash_map_bytes::<(usize, usize), GlobalTileInfo>(self.global_knowledge.len())
+            + self.index.approx_bytes()
+            + self.entities.approx_bytes()
+            + memory::btree_bytes::<(usize, usize)>(self.exclusions.len())
+            + memory::hash_map_bytes::<(usize, usize), u32>(self.deposits.len())
+            + memory::hash_map_bytes::<(usize, usize), u64>(self.seen.len())
+    }
+
+    /// Approximate bytes held by the merge log
+    pub fn merge_log_bytes(&self) -> usize {
+        memory::vec_bytes::<MergeRecord>(self.merge_log.capacity())
+    }
+
+    // Update global tile, resolving conflicts (latest timestamp wins).
+    // Returns how the update disagreed with the known tile, if it did.
+    pub fn update_global_tile(
+        &mut self,
+        x: usize,
+        y: usize,
+        new_info: GlobalTileInfo,
+    ) -> Option<ConflictKind> {
         match self.global_knowledge.entry((x, y)) {
             Entry::Occupied(mut occ) => {
                 let current: &GlobalTileInfo = occ.get();
+                let conflict = current.conflict_with(&new_info);
                 let should_update = match (current, &new_info) {
                     (GlobalTileInfo::Station, _) => false,
                     (_, GlobalTileInfo::Station) => true,
@@ -138,6 +470,10 @@ impl DataManager {
                     _ => false,
                 };
 
+                if let Some(kind) = conflict {
+                    self.conflict_stats.record(kind, should_update);
+                }
+
                 if should_update {
                     trace!(
                         "Updating tile ({},{}): {:?} -> {:?}",
@@ -146,62 +482,306 @@ impl DataManager {
                         current,
                         new_info
                     );
+                    self.index.update(
+                        (x, y),
+                        Some(&current.to_tile_info()),
+                        &new_info.to_tile_info(),
+                    );
                     *occ.get_mut() = new_info;
                 } else {
                     trace!("Keeping existing tile ({},{}): {:?}", x, y, current);
                 }
+                conflict
             }
 
             Entry::Vacant(vac) => {
                 trace!("Inserting new tile ({},{}): {:?}", x, y, new_info);
+                self.index.update((x, y), None, &new_info.to_tile_info());
                 vac.insert(new_info);
+                None
+            }
+        }
+    }
+
+    /// True once no known walkable tile reachable from the station borders an unknown tile,
+    /// i.e. every tile robots can reach has been reported. No-go tiles are not reachable.
+    pub fn is_exploration_complete(&self) -> bool {
+        let mut queue: VecDeque<(usize, usize)> = self
+            .global_knowledge
+            .iter()
+            .filter(|(_, info)| matches!(info, GlobalTileInfo::Station))
+            .map(|(&pos, _)| pos)
+            .collect();
+        let mut seen: HashSet<(usize, usize)> = queue.iter().copied().collect();
+
+        while let Some((x, y)) = queue.pop_front() {
+            for neighbor in
+                crate::map::noise::Map::valid_neighbors(x, y, self.map_width, self.map_height)
+            {
+                if self.exclusions.contains(&neighbor) {
+                    continue;
+                }
+                match self.global_knowledge.get(&neighbor) {
+                    Some(GlobalTileInfo::Unknown) | None => return false,
+                    Some(GlobalTileInfo::Obstacle(_)) => {}
+                    Some(_) => {
+                        if seen.insert(neighbor) {
+                            queue.push_back(neighbor);
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Marks tiles as unknown again, e.g. after a meteor reshaped them. Station tiles are kept.
+    pub fn forget_tiles(&mut self, tiles: &[(usize, usize)]) {
+        for tile in tiles {
+            self.seen.remove(tile);
+            if let Some(info) = self.global_knowledge.get_mut(tile) {
+                if !matches!(info, GlobalTileInfo::Station) {
+                    self.index
+                        .update(*tile, Some(&info.to_tile_info()), &TileInfo::Unknown);
+                    *info = GlobalTileInfo::Unknown;
+                }
+            }
+        }
+    }
+
+    /// Forgets the walkable and obstacle tiles no robot has seen for more than `memory` ticks
+    /// at tick `now`, as robots do, see `RobotKnowledge::forget_stale_terrain`. Returns how
+    /// many were forgotten.
+    pub fn forget_stale_terrain(&mut self, now: u64, memory: u64) -> usize {
+        let stale: Vec<(usize, usize)> = self
+            .seen
+            .iter()
+            .filter(|&(_, &seen)| now.saturating_sub(seen) > memory)
+            .map(|(&tile, _)| tile)
+            .collect();
+        self.forget_tiles(&stale);
+        stale.len()
+    }
+
+    /// Known share of the map and state of the reported deposits
+    pub fn coverage(&self) -> KnowledgeCoverage {
+        let mut coverage = KnowledgeCoverage {
+            total_tiles: self.map_width * self.map_height,
+            known_tiles: self.known_tile_count(),
+            deposits: self.deposits.len(),
+            ..KnowledgeCoverage::default()
+        };
+        for (tile, &found) in &self.deposits {
+            let left = match self.global_knowledge.get(tile) {
+                Some(GlobalTileInfo::Resource(version)) => {
+                    *coverage.remaining.entry(version.resource_type).or_insert(0) += version.amount;
+                    version.amount
+                }
+                Some(GlobalTileInfo::Walkable(_)) => 0,
+                // Reshaped since, nobody worked it
+                _ => found,
+            };
+            if left < found {
+                coverage.claimed += 1;
             }
+            if left == 0 {
+                coverage.exhausted += 1;
+            }
+        }
+        coverage
+    }
+
+    /// Where the reported deposits and the tiles nobody reported yet are, for nearest-tile
+    /// queries
+    pub fn index(&self) -> &TileIndex {
+        &self.index
+    }
+
+    /// The reported deposit of `resource_type` with something left in it nearest to `from`,
+    /// outside no-go zones
+    pub fn nearest_known(
+        &self,
+        resource_type: &ResourceType,
+        from: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        self.index
+            .deposits(resource_type)?
+            .nearest(from, &self.topology, |tile| {
+                !self.exclusions.contains(&tile)
+            })
+            .map(|(tile, _)| tile)
+    }
+
+    /// Unknown tiles outside no-go zones next to a tile known to be walkable, where
+    /// exploring goes on from, in coordinate order
+    pub fn frontier_tiles(&self) -> Vec<(usize, usize)> {
+        let mut frontier: Vec<(usize, usize)> = self
+            .index
+            .unknown()
+            .iter()
+            .filter(|tile| !self.exclusions.contains(tile))
+            .filter(|&(x, y)| {
+                self.topology.neighbors(x, y).any(|neighbor| {
+                    matches!(
+                        self.global_knowledge.get(&neighbor),
+                        Some(
+                            GlobalTileInfo::Walkable(_)
+                                | GlobalTileInfo::Resource(_)
+                                | GlobalTileInfo::Station
+                        )
+                    )
+                })
+            })
+            .collect();
+        frontier.sort_unstable();
+        frontier
+    }
+
+    /// Number of tiles whose content the station knows
+    pub fn known_tile_count(&self) -> usize {
+        self.global_knowledge
+            .values()
+            .filter(|info| !matches!(info, GlobalTileInfo::Unknown))
+            .count()
+    }
+
+    /// Distances to the station over the tiles known to be walkable outside no-go zones,
+    /// handed to docking robots so they all get back the shortest known way
+    pub fn station_field(&self) -> FlowField {
+        let station_tiles = self
+            .global_knowledge
+            .iter()
+            .filter(|(_, info)| matches!(info, GlobalTileInfo::Station))
+            .map(|(&tile, _)| tile);
+        FlowField::towards(station_tiles, self.map_width, self.map_height, |tile| {
+            !self.exclusions.contains(&tile)
+                && matches!(
+                    self.global_knowledge.get(&tile),
+                    Some(
+                        GlobalTileInfo::Walkable(_)
+                            | GlobalTileInfo::Resource(_)
+                            | GlobalTileInfo::Station
+                    )
+                )
+        })
+    }
+
+    /// Replaces the no-go tiles handed to robots
+    pub fn set_exclusions(&mut self, tiles: impl IntoIterator<Item = (usize, usize)>) {
+        self.exclusions = tiles.into_iter().collect();
+    }
+
+    pub fn exclusions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.exclusions.iter().copied()
+    }
+
+    /// Leaves the user's note on (x, y), in place of the tile's previous one
+    pub fn annotate(&mut self, x: usize, y: usize, text: String) {
+        self.entities.annotate(x, y, text);
+    }
+
+    /// Removes the note on (x, y), returns whether there was one
+    pub fn clear_annotation(&mut self, x: usize, y: usize) -> bool {
+        self.entities.clear_annotation(x, y)
+    }
+
+    pub fn record_sightings(&mut self, sightings: &[Sighting]) {
+        for sighting in sightings {
+            self.entities.record(sighting.clone());
         }
     }
 
+    /// Forgets the sightings that are too old at tick `now`
+    pub fn expire_sightings(&mut self, now: u64) {
+        self.entities.expire(now);
+    }
+
+    pub fn entities(&self) -> &DynamicLayer {
+        &self.entities
+    }
+
     /// This is sent back to robots after they dock.
+    /// Counts the known tiles and those that disagree with the real map, see
+    /// `RobotKnowledge::divergence`
+    pub fn divergence(&self, map: &Map) -> KnowledgeDivergence {
+        let mut divergence = KnowledgeDivergence::default();
+        for (&(x, y), global_info) in &self.global_knowledge {
+            let info = global_info.to_tile_info();
+            if info == TileInfo::Unknown {
+                continue;
+            }
+            divergence.known += 1;
+            if info != TileInfo::observe(x, y, map) {
+                divergence.wrong += 1;
+            }
+        }
+        divergence
+    }
+
     pub fn get_global_robot_knowledge(&self) -> RobotKnowledge {
         let mut robot_knowledge = RobotKnowledge::new(self.map_width, self.map_height);
         for (&(x, y), global_info) in &self.global_knowledge {
-            let tile_info = match global_info {
-                GlobalTileInfo::Unknown => TileInfo::Unknown,
-                GlobalTileInfo::Walkable(_) => TileInfo::Walkable,
-                GlobalTileInfo::Obstacle(_) => TileInfo::Obstacle,
-                GlobalTileInfo::Resource(version) => {
-                    TileInfo::Resource(version.resource_type.clone(), version.amount)
-                }
-                GlobalTileInfo::Station => TileInfo::Station,
-            };
-            robot_knowledge.update_tile(x, y, tile_info);
+            robot_knowledge.update_tile(x, y, global_info.to_tile_info());
+        }
+        for (&(x, y), &seen) in &self.seen {
+            robot_knowledge.mark_seen(x, y, seen);
         }
+        robot_knowledge.merge_entities(&self.entities);
+        robot_knowledge.set_annotations(self.entities.annotations());
+        robot_knowledge.set_exclusions(self.exclusions.iter().copied());
+        robot_knowledge.set_station_field(self.station_field());
+        robot_knowledge
+    }
+
+    /// What a robot docking with `knowledge` takes back: everything the station knows, or
+    /// its own knowledge updated with the most important tiles the bandwidth allows
+    pub fn knowledge_for(&mut self, knowledge: &RobotKnowledge) -> RobotKnowledge {
+        let Some(budget) = self.bandwidth else {
+            return self.get_global_robot_knowledge();
+        };
+        let mut robot_knowledge = knowledge.clone();
+        let deltas = self
+            .global_knowledge
+            .iter()
+            .map(|(&tile, global_info)| (tile, global_info.to_tile_info()))
+            .filter(|((x, y), info)| {
+                !matches!(info, TileInfo::Unknown) && knowledge.get_tile(*x, *y) != info
+            })
+            .collect();
+        let (sent, deferred) = most_important(deltas, budget);
+        self.deferred_deltas += deferred as u64;
+        for ((x, y), info) in sent {
+            robot_knowledge.update_tile(x, y, info);
+            if let Some(&seen) = self.seen.get(&(x, y)) {
+                robot_knowledge.mark_seen(x, y, seen);
+            }
+        }
+        robot_knowledge.merge_entities(&self.entities);
+        robot_knowledge.set_annotations(self.entities.annotations());
+        robot_knowledge.set_exclusions(self.exclusions.iter().copied());
+        robot_knowledge.set_station_field(self.station_field());
         robot_knowledge
     }
 
     // This should be called periodically by the App/Station
     pub fn update_simulation_map(&self, map: &mut crate::map::noise::Map) {
         for (&(x, y), global_info) in &self.global_knowledge {
-            match global_info {
-                GlobalTileInfo::Resource(version) => {
-                    let map_resource = map.get_resource(x, y);
-                    let map_resource_type = map_resource.as_ref().map(|(rt, _)| rt);
-
-                    if map_resource.is_none() && version.amount > 0 {
-                    } else if let Some((_, map_amount)) = map_resource.as_ref() {
-                        if version.amount == 0
-                            && *map_amount > 0
-                            && map_resource_type == Some(&version.resource_type)
-                        {
-                            // Remove resource if fully consumed
-                            if matches!(
-                                version.resource_type,
-                                ResourceType::Energy | ResourceType::Minerals
-                            ) {
-                                map.remove_resource(x, y);
-                            }
+            if let GlobalTileInfo::Resource(version) = global_info {
+                let map_resource = map.get_resource(x, y);
+                let map_resource_type = map_resource.as_ref().map(|(rt, _)| rt);
+
+                if map_resource.is_none() && version.amount > 0 {
+                } else if let Some((_, map_amount)) = map_resource.as_ref() {
+                    if version.amount == 0
+                        && *map_amount > 0
+                        && map_resource_type == Some(&version.resource_type)
+                    {
+                        // Remove resource if fully consumed
+                        if map.catalog().is_consumable(version.resource_type) {
+                            map.remove_resource(x, y);
                         }
                     }
                 }
-                _ => {}
             }
         }
     }
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+/// No-go tiles robots must neither enter nor target. The user's zones stay until lifted,
+/// hazard zones close at a given tick.
+#[derive(Debug, Clone, Default)]
+pub struct ExclusionZones {
+    /// Tick each tile opens again at, `None` for the user's zones
+    tiles: BTreeMap<(usize, usize), Option<u64>>,
+}
+
+impl ExclusionZones {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Closes every tile of the `area`, or lifts them all if `center` was closed. Returns
+    /// whether they are closed now.
+    pub fn toggle_area(
+        &mut self,
+        center: (usize, usize),
+        area: impl IntoIterator<Item = (usize, usize)>,
+    ) -> bool {
+        let close = !self.tiles.contains_key(&center);
+        for tile in area {
+            if close {
+                self.tiles.insert(tile, None);
+            } else {
+                self.tiles.remove(&tile);
+            }
+        }
+        close
+    }
+
+    /// Closes the `area` until `tick`, leaving the user's zones in place
+    pub fn add_hazard(&mut self, area: impl IntoIterator<Item = (usize, usize)>, until: u64) {
+        for tile in area {
+            let entry = self.tiles.entry(tile).or_insert(Some(until));
+            if let Some(open_at) = entry {
+                *open_at = (*open_at).max(until);
+            }
+        }
+    }
+
+    /// Opens the hazard tiles whose time ran out at `tick`, and returns them
+    pub fn expire(&mut self, tick: u64) -> Vec<(usize, usize)> {
+        let mut opened = Vec::new();
+        self.tiles.retain(|&tile, open_at| {
+            let open = open_at.is_some_and(|open_at| open_at <= tick);
+            if open {
+                opened.push(tile);
+            }
+            !open
+        });
+        opened
+    }
+
+    pub fn contains(&self, tile: (usize, usize)) -> bool {
+        self.tiles.contains_key(&tile)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.tiles.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hazard_zones_open_again_but_user_zones_stay() {
+        let mut zones = ExclusionZones::new();
+        assert!(zones.toggle_area((1, 1), [(1, 1), (2, 1)]));
+        zones.add_hazard([(2, 1), (3, 1)], 50);
+        zones.add_hazard([(4, 1)], 80);
+        assert_eq!(zones.len(), 4);
+
+        assert!(zones.expire(49).is_empty());
+        assert_eq!(zones.expire(50), vec![(3, 1)]);
+        // The user's tile under the hazard stays closed
+        assert_eq!(
+            zones.tiles().collect::<Vec<_>>(),
+            vec![(1, 1), (2, 1), (4, 1)]
+        );
+
+        assert!(!zones.toggle_area((1, 1), [(1, 1), (2, 1)]));
+        assert_eq!(zones.expire(80), vec![(4, 1)]);
+        assert!(zones.is_empty());
+    }
+}
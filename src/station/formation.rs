@@ -0,0 +1,272 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::map::noise::Map;
+
+/// Distance between neighbouring slots of a formation, in tiles
+const SLOT_SPACING: f64 = 2.0;
+/// Distance the formation anchor advances once every member is in position, in tiles
+const ANCHOR_STEP: f64 = 2.0;
+/// Members within this many tiles (Manhattan) of their slot count as in position
+const IN_POSITION_RADIUS: usize = 1;
+/// Updates to wait for stragglers before advancing anyway
+const MAX_WAIT_UPDATES: u32 = 50;
+
+/// Shapes a group of robots can hold while moving
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Formation {
+    /// Side by side, perpendicular to the direction of travel (sector sweep)
+    LineAbreast,
+    /// One behind the other along the direction of travel (escort)
+    Column,
+}
+
+/// A group of robots moving together towards a target tile.
+///
+/// The formation is laid out around an anchor point that starts at the group's centroid.
+/// Every update re-issues slots as waypoints, and the anchor only advances towards the
+/// target once all members reached their slot, so the group stays together.
+#[derive(Debug, Clone)]
+pub struct FormationMove {
+    pub formation: Formation,
+    pub target: (usize, usize),
+    members: Vec<u32>,
+    anchor: (f64, f64),
+    heading: (f64, f64),
+    slots: HashMap<u32, (usize, usize)>,
+    waited_updates: u32,
+    complete: bool,
+}
+
+impl FormationMove {
+    /// Plans a formation move for the given robot positions, `None` if there are no robots
+    pub fn new(
+        formation: Formation,
+        positions: &[(u32, (usize, usize))],
+        target: (usize, usize),
+    ) -> Option<Self> {
+        if positions.is_empty() {
+            return None;
+        }
+
+        let count = positions.len() as f64;
+        let centroid = (
+            positions.iter().map(|(_, (x, _))| *x as f64).sum::<f64>() / count,
+            positions.iter().map(|(_, (_, y))| *y as f64).sum::<f64>() / count,
+        );
+        let heading = unit_vector(target.0 as f64 - centroid.0, target.1 as f64 - centroid.1);
+
+        // Order members along the formation axis so they don't cross paths
+        let axis = match formation {
+            Formation::LineAbreast => (-heading.1, heading.0),
+            Formation::Column => (-heading.0, -heading.1),
+        };
+        let mut ordered: Vec<_> = positions.to_vec();
+        ordered.sort_by(|(_, a), (_, b)| {
+            let pa = a.0 as f64 * axis.0 + a.1 as f64 * axis.1;
+            let pb = b.0 as f64 * axis.0 + b.1 as f64 * axis.1;
+            pa.total_cmp(&pb)
+        });
+
+        Some(Self {
+            formation,
+            target,
+            members: ordered.into_iter().map(|(id, _)| id).collect(),
+            anchor: centroid,
+            heading,
+            slots: HashMap::new(),
+            waited_updates: 0,
+            complete: false,
+        })
+    }
+
+    pub fn members(&self) -> &[u32] {
+        &self.members
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Drops a member, e.g. because its robot shut down
+    pub fn remove_member(&mut self, robot_id: u32) {
+        self.members.retain(|&id| id != robot_id);
+        self.slots.remove(&robot_id);
+        if self.members.is_empty() {
+            self.complete = true;
+        }
+    }
+
+    /// Advances the formation and returns the waypoints that changed since the last update
+    pub fn update(
+        &mut self,
+        positions: &HashMap<u32, (usize, usize)>,
+        map: &Map,
+    ) -> Vec<(u32, (usize, usize))> {
+        self.members.retain(|id| positions.contains_key(id));
+        if self.members.is_empty() {
+            self.complete = true;
+        }
+        if self.complete {
+            return Vec::new();
+        }
+
+        if !self.slots.is_empty() {
+            let in_position = self.members.iter().all(|id| {
+                let (px, py) = positions[id];
+                self.slots.get(id).is_some_and(|&(sx, sy)| {
+                    px.abs_diff(sx) + py.abs_diff(sy) <= IN_POSITION_RADIUS
+                })
+            });
+            if in_position || self.waited_updates >= MAX_WAIT_UPDATES {
+                if self.anchor_at_target() {
+                    self.complete = true;
+                    return Vec::new();
+                }
+                self.advance_anchor();
+                self.waited_updates = 0;
+            } else {
+                self.waited_updates += 1;
+            }
+        }
+
+        let mut changed = Vec::new();
+        let mut taken = HashSet::new();
+        for (index, slot) in self.slot_positions().into_iter().enumerate() {
+            let id = self.members[index];
+            let tile = snap_to_walkable(map, slot, &taken);
+            taken.insert(tile);
+            if self.slots.insert(id, tile) != Some(tile) {
+                changed.push((id, tile));
+            }
+        }
+        changed
+    }
+
+    fn anchor_at_target(&self) -> bool {
+        (self.anchor.0 - self.target.0 as f64).abs() < 0.5
+            && (self.anchor.1 - self.target.1 as f64).abs() < 0.5
+    }
+
+    fn advance_anchor(&mut self) {
+        let dx = self.target.0 as f64 - self.anchor.0;
+        let dy = self.target.1 as f64 - self.anchor.1;
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance <= ANCHOR_STEP {
+            self.anchor = (self.target.0 as f64, self.target.1 as f64);
+        } else {
+            self.anchor.0 += dx / distance * ANCHOR_STEP;
+            self.anchor.1 += dy / distance * ANCHOR_STEP;
+        }
+    }
+
+    /// Ideal (unsnapped) slot of each member, in member order
+    fn slot_positions(&self) -> Vec<(f64, f64)> {
+        let count = self.members.len();
+        let (hx, hy) = self.heading;
+        (0..count)
+            .map(|index| match self.formation {
+                Formation::LineAbreast => {
+                    let offset = (index as f64 - (count as f64 - 1.0) / 2.0) * SLOT_SPACING;
+                    (self.anchor.0 - hy * offset, self.anchor.1 + hx * offset)
+                }
+                Formation::Column => {
+                    let offset = index as f64 * SLOT_SPACING;
+                    (self.anchor.0 - hx * offset, self.anchor.1 - hy * offset)
+                }
+            })
+            .collect()
+    }
+}
+
+fn unit_vector(dx: f64, dy: f64) -> (f64, f64) {
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < f64::EPSILON {
+        (1.0, 0.0)
+    } else {
+        (dx / length, dy / length)
+    }
+}
+
+/// Nearest walkable tile to `point` not already used by another slot
+fn snap_to_walkable(
+    map: &Map,
+    point: (f64, f64),
+    taken: &HashSet<(usize, usize)>,
+) -> (usize, usize) {
+    let clamp =
+        |value: f64, max: usize| value.round().clamp(0.0, max.saturating_sub(1) as f64) as usize;
+    let start = (clamp(point.0, map.width), clamp(point.1, map.height));
+
+    let mut queue = VecDeque::from([start]);
+    let mut seen = HashSet::from([start]);
+    while let Some((x, y)) = queue.pop_front() {
+        if !map.is_obstacle(x, y) && !taken.contains(&(x, y)) {
+            return (x, y);
+        }
+        for neighbor in map.neighbors(x, y) {
+            if seen.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_map(width: usize, height: usize) -> Map {
+        let mut map = Map::new(width, height, 1);
+        for y in 0..height {
+            for x in 0..width {
+                map.set_walkable(x, y);
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn test_line_abreast_slots_are_perpendicular_to_travel() {
+        let map = open_map(30, 20);
+        let robots = [(1, (5, 8)), (2, (5, 10)), (3, (5, 12))];
+        let mut formation = FormationMove::new(Formation::LineAbreast, &robots, (20, 10)).unwrap();
+
+        let positions: HashMap<_, _> = robots.iter().copied().collect();
+        let waypoints: HashMap<_, _> = formation.update(&positions, &map).into_iter().collect();
+
+        // Heading east, so the line spreads north-south on the same column
+        assert_eq!(waypoints[&1], (5, 8));
+        assert_eq!(waypoints[&2], (5, 10));
+        assert_eq!(waypoints[&3], (5, 12));
+    }
+
+    #[test]
+    fn test_formation_advances_only_when_members_are_in_position() {
+        let map = open_map(30, 20);
+        let robots = [(1, (5, 9)), (2, (5, 11))];
+        let mut formation = FormationMove::new(Formation::LineAbreast, &robots, (9, 10)).unwrap();
+        let mut positions: HashMap<_, _> = robots.iter().copied().collect();
+
+        formation.update(&positions, &map);
+        // Member 2 lags behind: nothing changes
+        positions.insert(2, (2, 2));
+        assert!(formation.update(&positions, &map).is_empty());
+
+        // Back in position: the anchor moves two tiles east
+        positions.insert(2, (5, 11));
+        let waypoints: HashMap<_, _> = formation.update(&positions, &map).into_iter().collect();
+        assert_eq!(waypoints[&1], (7, 9));
+        assert_eq!(waypoints[&2], (7, 11));
+        positions.extend(waypoints);
+
+        for _ in 0..10 {
+            let next = formation.update(&positions, &map);
+            for (id, tile) in next {
+                positions.insert(id, tile);
+            }
+        }
+        assert!(formation.is_complete());
+        assert_eq!(positions[&1], (9, 9));
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+/// Tracks, per robot, the last tick at which it made productive progress
+/// (a newly explored tile, a collection or an analysis).
+pub struct IdleMonitor {
+    last_progress: HashMap<u32, u64>,
+    threshold_ticks: u64,
+}
+
+impl IdleMonitor {
+    pub fn new(threshold_ticks: u64) -> Self {
+        Self {
+            last_progress: HashMap::new(),
+            threshold_ticks,
+        }
+    }
+
+    /// Starts tracking a robot, counting it as productive at `tick`
+    pub fn track(&mut self, robot_id: u32, tick: u64) {
+        self.last_progress.insert(robot_id, tick);
+    }
+
+    pub fn untrack(&mut self, robot_id: u32) {
+        self.last_progress.remove(&robot_id);
+    }
+
+    pub fn record_progress(&mut self, robot_id: u32, tick: u64) {
+        if let Some(last) = self.last_progress.get_mut(&robot_id) {
+            *last = tick;
+        }
+    }
+
+    /// Ticks elapsed since the robot last made progress
+    pub fn idle_ticks(&self, robot_id: u32, tick: u64) -> Option<u64> {
+        self.last_progress
+            .get(&robot_id)
+            .map(|last| tick.saturating_sub(*last))
+    }
+
+    pub fn is_idle(&self, robot_id: u32, tick: u64) -> bool {
+        self.idle_ticks(robot_id, tick)
+            .is_some_and(|idle| idle >= self.threshold_ticks)
+    }
+
+    /// Robots idle for at least the threshold, sorted by id
+    pub fn idle_robots(&self, tick: u64) -> Vec<u32> {
+        let mut idle: Vec<u32> = self
+            .last_progress
+            .keys()
+            .copied()
+            .filter(|&id| self.is_idle(id, tick))
+            .collect();
+        idle.sort_unstable();
+        idle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robot_becomes_idle_after_threshold_without_progress() {
+        let mut monitor = IdleMonitor::new(10);
+        monitor.track(1, 0);
+        monitor.track(2, 0);
+
+        monitor.record_progress(2, 8);
+        assert!(monitor.idle_robots(9).is_empty());
+        assert_eq!(monitor.idle_robots(10), vec![1]);
+        assert_eq!(monitor.idle_ticks(2, 10), Some(2));
+
+        monitor.untrack(1);
+        assert!(monitor.idle_robots(30).contains(&2));
+        assert!(!monitor.idle_robots(30).contains(&1));
+    }
+}
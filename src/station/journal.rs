@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+
+use crate::robot::utils::config;
+
+/// How much a journal entry matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Alarm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub tick: u64,
+    pub severity: Severity,
+    pub text: String,
+}
+
+/// The run's notable events, the `config::JOURNAL_LEN` most recent ones
+#[derive(Debug, Clone, Default)]
+pub struct EventJournal {
+    entries: VecDeque<JournalEntry>,
+}
+
+impl EventJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, tick: u64, severity: Severity, text: impl Into<String>) {
+        if self.entries.len() == config::JOURNAL_LEN {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(JournalEntry {
+            tick,
+            severity,
+            text: text.into(),
+        });
+    }
+
+    /// Oldest entry first
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &JournalEntry> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::memory;
+
+/// Swarm-wide running totals at some tick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KpiTotals {
+    /// Resources unloaded at the station
+    pub delivered: u64,
+    /// Energy the robots reported using up
+    pub energy_spent: u64,
+    pub explored_tiles: usize,
+}
+
+/// How well the swarm does over some span of ticks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SwarmKpis {
+    /// Resources delivered per tick over energy spent per tick, scaled to 100 energy
+    pub efficiency: f64,
+    /// Resources delivered per 100 ticks
+    pub delivery_rate: f64,
+    /// Tiles explored per 100 ticks
+    pub exploration_velocity: f64,
+}
+
+impl SwarmKpis {
+    fn between((from_tick, from): (u64, KpiTotals), (to_tick, to): (u64, KpiTotals)) -> Self {
+        let per_hundred = |amount: u64, over: u64| {
+            if over == 0 {
+                0.0
+            } else {
+                amount as f64 * 100.0 / over as f64
+            }
+        };
+        let delivered = to.delivered.saturating_sub(from.delivered);
+        let ticks = to_tick.saturating_sub(from_tick);
+        Self {
+            efficiency: per_hundred(delivered, to.energy_spent.saturating_sub(from.energy_spent)),
+            delivery_rate: per_hundred(delivered, ticks),
+            exploration_velocity: per_hundred(
+                to.explored_tiles.saturating_sub(from.explored_tiles) as u64,
+                ticks,
+            ),
+        }
+    }
+}
+
+/// Samples the swarm's totals every tick and rates them over the last `window` ticks, so
+/// a change of parameters shows within the window
+#[derive(Debug, Clone)]
+pub struct KpiTracker {
+    window: u64,
+    /// Oldest first, starting at or just before the window
+    samples: VecDeque<(u64, KpiTotals)>,
+}
+
+impl KpiTracker {
+    pub fn new(window: u64) -> Self {
+        Self {
+            window,
+            samples: VecDeque::from([(0, KpiTotals::default())]),
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, totals: KpiTotals) {
+        self.samples.push_back((tick, totals));
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|&(sampled, _)| sampled + self.window <= tick)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    fn latest(&self) -> (u64, KpiTotals) {
+        self.samples.back().copied().unwrap_or_default()
+    }
+
+    /// Over the last `window` ticks
+    pub fn live(&self) -> SwarmKpis {
+        let oldest = self.samples.front().copied().unwrap_or_default();
+        SwarmKpis::between(oldest, self.latest())
+    }
+
+    /// Since the start of the run
+    pub fn overall(&self) -> SwarmKpis {
+        SwarmKpis::between((0, KpiTotals::default()), self.latest())
+    }
+
+    /// Approximate bytes held, see `crate::memory`
+    pub fn approx_bytes(&self) -> usize {
+        memory::vec_bytes::<(u64, KpiTotals)>(self.samples.capacity())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_figures_follow_the_window() {
+        let mut tracker = KpiTracker::new(100);
+        let totals = |delivered, energy_spent, explored_tiles| KpiTotals {
+            delivered,
+            energy_spent,
+            explored_tiles,
+        };
+        assert_eq!(tracker.live(), SwarmKpis::default());
+
+        for tick in 1..=100 {
+            tracker.record(tick, totals(tick, tick * 10, tick as usize * 2));
+        }
+        let live = tracker.live();
+        assert_eq!(live.efficiency, 10.0);
+        assert_eq!(live.delivery_rate, 100.0);
+        assert_eq!(live.exploration_velocity, 200.0);
+
+        // The swarm stops delivering and exploring but keeps burning energy
+        for tick in 101..=200 {
+            tracker.record(tick, totals(100, tick * 10, 200));
+        }
+        let live = tracker.live();
+        assert_eq!((live.efficiency, live.exploration_velocity), (0.0, 0.0));
+        assert_eq!(tracker.overall().efficiency, 5.0);
+        assert_eq!(tracker.overall().delivery_rate, 50.0);
+    }
+}
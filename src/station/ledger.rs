@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::communication::channels::ResourceType;
+
+/// Follows every collected unit from the deposit to the station, so resources counted twice
+/// or dropped along the way show up
+#[derive(Debug, Clone, Default)]
+pub struct ResourceLedger {
+    /// Reported collected and added to the stock
+    credited: HashMap<ResourceType, u64>,
+    /// Unloaded at the station
+    delivered: HashMap<ResourceType, u64>,
+    /// Still on board, per robot
+    cargo: BTreeMap<u32, HashMap<ResourceType, u64>>,
+    /// On board robots that stopped before unloading
+    lost: HashMap<ResourceType, u64>,
+    /// Left on the map as salvage by robots that stopped in the field
+    dropped: HashMap<ResourceType, u64>,
+}
+
+/// One resource type's balance at the time of an audit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub resource_type: ResourceType,
+    /// Taken from the map's deposits, `None` when robots also collect from remote maps
+    pub removed: Option<u64>,
+    pub credited: u64,
+    pub delivered: u64,
+    pub carried: u64,
+    pub lost: u64,
+    pub dropped: u64,
+}
+
+impl LedgerEntry {
+    /// Every credited unit is delivered, carried, lost or dropped
+    pub fn balances(&self) -> bool {
+        self.credited == self.delivered + self.carried + self.lost + self.dropped
+    }
+
+    /// Credited units match what left the map's deposits
+    pub fn matches_map(&self) -> bool {
+        self.removed.is_none_or(|removed| removed == self.credited)
+    }
+}
+
+impl ResourceLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A robot reported taking `amount` from a deposit
+    pub fn record_collection(&mut self, robot_id: u32, resource_type: &ResourceType, amount: u32) {
+        let amount = u64::from(amount);
+        *self.credited.entry(*resource_type).or_insert(0) += amount;
+        *self
+            .cargo
+            .entry(robot_id)
+            .or_default()
+            .entry(*resource_type)
+            .or_insert(0) += amount;
+    }
+
+    /// The robot unloaded at the station
+    pub fn record_delivery(&mut self, robot_id: u32) {
+        Self::unload(&mut self.cargo, robot_id, &mut self.delivered);
+    }
+
+    /// The robot stopped with whatever it carried
+    pub fn record_loss(&mut self, robot_id: u32) {
+        Self::unload(&mut self.cargo, robot_id, &mut self.lost);
+    }
+
+    /// Units of every type unloaded at the station so far
+    pub fn delivered_total(&self) -> u64 {
+        self.delivered.values().sum()
+    }
+
+    /// What the robot still carries, by type
+    pub fn cargo_of(&self, robot_id: u32) -> Vec<(ResourceType, u64)> {
+        let mut cargo: Vec<_> = self
+            .cargo
+            .get(&robot_id)
+            .into_iter()
+            .flatten()
+            .filter(|(_, amount)| **amount > 0)
+            .map(|(resource_type, amount)| (*resource_type, *amount))
+            .collect();
+        cargo.sort_by_key(|(resource_type, _)| format!("{:?}", resource_type));
+        cargo
+    }
+
+    /// The robot left `amount` of its cargo on the map. Picking it up again credits it anew.
+    pub fn record_drop(&mut self, robot_id: u32, resource_type: &ResourceType, amount: u64) {
+        let Some(carried) = self
+            .cargo
+            .get_mut(&robot_id)
+            .and_then(|cargo| cargo.get_mut(resource_type))
+        else {
+            return;
+        };
+        let amount = amount.min(*carried);
+        *carried -= amount;
+        *self.dropped.entry(*resource_type).or_insert(0) += amount;
+    }
+
+    fn unload(
+        cargo: &mut BTreeMap<u32, HashMap<ResourceType, u64>>,
+        robot_id: u32,
+        into: &mut HashMap<ResourceType, u64>,
+    ) {
+        for (resource_type, amount) in cargo.remove(&robot_id).unwrap_or_default() {
+            *into.entry(resource_type).or_insert(0) += amount;
+        }
+    }
+
+    /// Balance of every resource type seen, against `removed`, what the map says was taken
+    /// from its deposits, when known
+    pub fn audit(&self, removed: Option<&HashMap<ResourceType, u64>>) -> Vec<LedgerEntry> {
+        let total = |amounts: &HashMap<ResourceType, u64>, resource_type: &ResourceType| {
+            amounts.get(resource_type).copied().unwrap_or(0)
+        };
+        let mut types: Vec<ResourceType> = self
+            .credited
+            .keys()
+            .chain(removed.into_iter().flat_map(|removed| removed.keys()))
+            .cloned()
+            .collect();
+        types.sort_by_key(|resource_type| format!("{:?}", resource_type));
+        types.dedup();
+
+        types
+            .into_iter()
+            .map(|resource_type| LedgerEntry {
+                removed: removed.map(|removed| total(removed, &resource_type)),
+                credited: total(&self.credited, &resource_type),
+                delivered: total(&self.delivered, &resource_type),
+                carried: self
+                    .cargo
+                    .values()
+                    .map(|cargo| total(cargo, &resource_type))
+                    .sum(),
+                lost: total(&self.lost, &resource_type),
+                dropped: total(&self.dropped, &resource_type),
+                resource_type,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_follows_cargo_to_the_station_and_flags_divergence() {
+        let mut ledger = ResourceLedger::new();
+        ledger.record_collection(1, &ResourceType::ENERGY, 5);
+        ledger.record_collection(1, &ResourceType::ENERGY, 3);
+        ledger.record_collection(2, &ResourceType::ENERGY, 4);
+        ledger.record_delivery(1);
+        ledger.record_loss(3); // Carried nothing
+
+        let removed = HashMap::from([(ResourceType::ENERGY, 12), (ResourceType::MINERALS, 2)]);
+        let audit = ledger.audit(Some(&removed));
+        assert_eq!(
+            audit[0],
+            LedgerEntry {
+                resource_type: ResourceType::ENERGY,
+                removed: Some(12),
+                credited: 12,
+                delivered: 8,
+                carried: 4,
+                lost: 0,
+                dropped: 0,
+            }
+        );
+        assert!(audit[0].balances() && audit[0].matches_map());
+        // Minerals left the map without ever being credited
+        assert!(!audit[1].matches_map());
+
+        ledger.record_drop(2, &ResourceType::ENERGY, 1);
+        ledger.record_loss(2);
+        let audit = ledger.audit(None);
+        assert_eq!(
+            (audit[0].carried, audit[0].lost, audit[0].dropped),
+            (0, 3, 1)
+        );
+        assert!(audit[0].balances() && audit[0].matches_map());
+    }
+}
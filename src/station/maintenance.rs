@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use crate::robot::registry::RobotType;
+
+/// Where a robot stands in its maintenance cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceState {
+    /// Working, wearing down since its last service
+    Operating,
+    /// Called back to the station for its service at the given tick
+    Recalled { since: u64 },
+    /// Being serviced at the station until the given tick
+    InService { until: u64 },
+}
+
+struct MaintenanceRecord {
+    robot_type: RobotType,
+    serviced_at: u64,
+    wear_percent: u32,
+    state: MaintenanceState,
+}
+
+/// Wear and maintenance of every robot. A robot accrues `wear_step` percent of extra energy
+/// cost for each `interval` ticks of operation, up to `max_wear`, until it is serviced at the
+/// station. Services are staggered: at most `max_share_percent` of the fleet, and of each robot
+/// type, is recalled or in service at once (always at least one robot), so coverage holds. A
+/// recalled robot that does not dock within an interval gives its slot up to the next one.
+pub struct MaintenanceSchedule {
+    robots: HashMap<u32, MaintenanceRecord>,
+    interval: u64,
+    wear_step: u32,
+    max_wear: u32,
+    max_share_percent: usize,
+}
+
+impl MaintenanceSchedule {
+    pub fn new(interval: u64, wear_step: u32, max_wear: u32, max_share_percent: usize) -> Self {
+        Self {
+            robots: HashMap::new(),
+            interval: interval.max(1),
+            wear_step,
+            max_wear,
+            max_share_percent,
+        }
+    }
+
+    /// Starts tracking a robot, fresh from the workshop at `tick`
+    pub fn track(&mut self, robot_id: u32, robot_type: RobotType, tick: u64) {
+        self.robots.insert(
+            robot_id,
+            MaintenanceRecord {
+                robot_type,
+                serviced_at: tick,
+                wear_percent: 0,
+                state: MaintenanceState::Operating,
+            },
+        );
+    }
+
+    pub fn untrack(&mut self, robot_id: u32) {
+        self.robots.remove(&robot_id);
+    }
+
+    pub fn wear_of(&self, robot_id: u32) -> Option<u32> {
+        self.robots.get(&robot_id).map(|record| record.wear_percent)
+    }
+
+    pub fn state_of(&self, robot_id: u32) -> Option<MaintenanceState> {
+        self.robots.get(&robot_id).map(|record| record.state)
+    }
+
+    /// Raises the wear of robots past another interval of operation. Returns the robots whose
+    /// wear changed with their new wear, sorted by id.
+    pub fn accrue(&mut self, tick: u64) -> Vec<(u32, u32)> {
+        let mut changed: Vec<(u32, u32)> = Vec::new();
+        for (&id, record) in self.robots.iter_mut() {
+            if matches!(record.state, MaintenanceState::InService { .. }) {
+                continue;
+            }
+            let intervals = tick.saturating_sub(record.serviced_at) / self.interval;
+            let wear = (intervals as u32)
+                .saturating_mul(self.wear_step)
+                .min(self.max_wear);
+            if wear != record.wear_percent {
+                record.wear_percent = wear;
+                changed.push((id, wear));
+            }
+        }
+        changed.sort_unstable();
+        changed
+    }
+
+    /// Worn robots to call back at `tick`, the most worn first, within the stagger limits
+    pub fn next_recalls(&mut self, tick: u64) -> Vec<u32> {
+        let mut released = Vec::new();
+        for (&id, record) in self.robots.iter_mut() {
+            if let MaintenanceState::Recalled { since } = record.state {
+                if tick.saturating_sub(since) >= self.interval {
+                    record.state = MaintenanceState::Operating;
+                    released.push(id);
+                }
+            }
+        }
+
+        let mut due: Vec<(u32, u32)> = self
+            .robots
+            .iter()
+            .filter(|(id, record)| {
+                record.wear_percent > 0
+                    && record.state == MaintenanceState::Operating
+                    && !released.contains(id)
+            })
+            .map(|(&id, record)| (id, record.wear_percent))
+            .collect();
+        due.sort_unstable_by_key(|&(id, wear)| (std::cmp::Reverse(wear), id));
+
+        let mut recalled = Vec::new();
+        for (id, _) in due {
+            if self.has_slot_for(id) {
+                if let Some(record) = self.robots.get_mut(&id) {
+                    record.state = MaintenanceState::Recalled { since: tick };
+                }
+                recalled.push(id);
+            }
+        }
+        recalled
+    }
+
+    /// Starts the service of a docked robot if it is worn and the stagger limits allow it,
+    /// until `tick + duration`. Returns whether it started.
+    pub fn start_service(&mut self, robot_id: u32, tick: u64, duration: u64) -> bool {
+        let Some(record) = self.robots.get(&robot_id) else {
+            return false;
+        };
+        let admitted = match record.state {
+            MaintenanceState::Recalled { .. } => true,
+            MaintenanceState::Operating => record.wear_percent > 0 && self.has_slot_for(robot_id),
+            MaintenanceState::InService { .. } => false,
+        };
+        if admitted {
+            if let Some(record) = self.robots.get_mut(&robot_id) {
+                record.state = MaintenanceState::InService {
+                    until: tick + duration,
+                };
+            }
+        }
+        admitted
+    }
+
+    /// Puts a recalled robot that could not be serviced back to work, worn as it is
+    pub fn postpone(&mut self, robot_id: u32) {
+        if let Some(record) = self.robots.get_mut(&robot_id) {
+            if let MaintenanceState::Recalled { .. } = record.state {
+                record.state = MaintenanceState::Operating;
+            }
+        }
+    }
+
+    /// Robots whose service is over at `tick`, back to work without wear, sorted by id
+    pub fn finish(&mut self, tick: u64) -> Vec<u32> {
+        let mut done = Vec::new();
+        for (&id, record) in self.robots.iter_mut() {
+            if let MaintenanceState::InService { until } = record.state {
+                if tick >= until {
+                    record.state = MaintenanceState::Operating;
+                    record.serviced_at = tick;
+                    record.wear_percent = 0;
+                    done.push(id);
+                }
+            }
+        }
+        done.sort_unstable();
+        done
+    }
+
+    /// Robots recalled or in service
+    pub fn in_maintenance(&self) -> usize {
+        self.robots
+            .values()
+            .filter(|record| record.state != MaintenanceState::Operating)
+            .count()
+    }
+
+    /// Whether taking `robot_id` off duty keeps the fleet, and its type, within the share
+    fn has_slot_for(&self, robot_id: u32) -> bool {
+        let Some(robot_type) = self.robots.get(&robot_id).map(|record| record.robot_type) else {
+            return false;
+        };
+        let limit = |total: usize| (total * self.max_share_percent / 100).max(1);
+        let of_type = self
+            .robots
+            .values()
+            .filter(|record| record.robot_type == robot_type);
+        let type_total = of_type.clone().count();
+        let type_off = of_type
+            .filter(|record| record.state != MaintenanceState::Operating)
+            .count();
+        self.in_maintenance() < limit(self.robots.len()) && type_off < limit(type_total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worn_robots_are_serviced_a_few_at_a_time() {
+        let mut schedule = MaintenanceSchedule::new(100, 10, 30, 50);
+        for id in 0..3 {
+            schedule.track(id, RobotType::Exploration, 0);
+        }
+        schedule.track(3, RobotType::Collection, 50);
+
+        assert!(schedule.accrue(99).is_empty());
+        assert_eq!(schedule.accrue(100), vec![(0, 10), (1, 10), (2, 10)]);
+        assert_eq!(schedule.accrue(1000).len(), 4);
+        assert_eq!(schedule.wear_of(0), Some(30));
+
+        // Half of the fleet at most, and one of the three explorers
+        assert_eq!(schedule.next_recalls(1000), vec![0, 3]);
+        assert!(schedule.next_recalls(1000).is_empty());
+        assert!(!schedule.start_service(1, 1000, 20));
+        assert!(schedule.start_service(0, 1000, 20));
+        assert_eq!(
+            schedule.state_of(0),
+            Some(MaintenanceState::InService { until: 1020 })
+        );
+
+        schedule.postpone(3);
+        assert!(schedule.finish(1019).is_empty());
+        assert_eq!(schedule.finish(1020), vec![0]);
+        assert_eq!(schedule.wear_of(0), Some(0));
+        assert_eq!(schedule.in_maintenance(), 0);
+        assert_eq!(schedule.next_recalls(1020), vec![1, 3]);
+
+        // Robot 1 never docks, robot 2 gets its turn
+        assert_eq!(schedule.next_recalls(1120), vec![2]);
+    }
+}
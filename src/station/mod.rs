@@ -1,2 +1,17 @@
+pub mod alarms;
+pub mod charging;
+pub mod command_tracker;
 pub mod data_manager;
+pub mod exclusions;
+pub mod formation;
+pub mod idle_monitor;
+pub mod journal;
+pub mod kpis;
+pub mod ledger;
+pub mod maintenance;
+pub mod priorities;
+pub mod rendezvous;
+pub mod squads;
+#[allow(clippy::module_inception)]
 pub mod station;
+pub mod stock_balancer;
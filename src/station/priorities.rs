@@ -0,0 +1,135 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// Tiles the user marked as high priority, and which robot the station sent to each
+#[derive(Debug, Clone, Default)]
+pub struct PriorityZones {
+    tiles: BTreeSet<(usize, usize)>,
+    claims: HashMap<u32, (usize, usize)>,
+}
+
+impl PriorityZones {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the tile, or unmarks it if it was marked. Returns whether it is marked now.
+    pub fn toggle(&mut self, tile: (usize, usize)) -> bool {
+        if self.tiles.remove(&tile) {
+            self.claims.retain(|_, claimed| *claimed != tile);
+            false
+        } else {
+            self.tiles.insert(tile);
+            true
+        }
+    }
+
+    /// Marks every tile of the `area`, or unmarks them all if `center` was marked. Returns
+    /// whether they are marked now.
+    pub fn toggle_area(
+        &mut self,
+        center: (usize, usize),
+        area: impl IntoIterator<Item = (usize, usize)>,
+    ) -> bool {
+        let mark = !self.tiles.contains(&center);
+        for tile in area {
+            if mark {
+                self.tiles.insert(tile);
+            } else {
+                self.tiles.remove(&tile);
+            }
+        }
+        self.claims
+            .retain(|_, claimed| self.tiles.contains(claimed));
+        mark
+    }
+
+    pub fn contains(&self, tile: (usize, usize)) -> bool {
+        self.tiles.contains(&tile)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.tiles.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Tiles other robots than `robot_id` were sent to
+    pub fn claimed_by_others(&self, robot_id: u32) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.claims
+            .iter()
+            .filter(move |(&id, _)| id != robot_id)
+            .map(|(_, &tile)| tile)
+    }
+
+    /// Frees the tile the robot was sent to
+    pub fn release(&mut self, robot_id: u32) {
+        self.claims.remove(&robot_id);
+    }
+
+    /// Closest marked tile, by `distance` from `from`, that is `wanted` and that no other robot
+    /// was sent to; claimed for `robot_id` in place of its previous one
+    pub fn claim_nearest(
+        &mut self,
+        robot_id: u32,
+        from: (usize, usize),
+        wanted: impl Fn((usize, usize)) -> bool,
+        distance: impl Fn((usize, usize), (usize, usize)) -> usize,
+    ) -> Option<(usize, usize)> {
+        self.release(robot_id);
+        let claimed: BTreeSet<(usize, usize)> = self.claims.values().copied().collect();
+        let tile = self
+            .tiles
+            .iter()
+            .copied()
+            .filter(|tile| !claimed.contains(tile) && wanted(*tile))
+            .min_by_key(|&tile| distance(from, tile))?;
+        self.claims.insert(robot_id, tile);
+        Some(tile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_are_sent_to_distinct_marked_tiles() {
+        let mut zones = PriorityZones::new();
+        assert!(zones.toggle((2, 2)));
+        assert!(zones.toggle_area((8, 2), [(7, 2), (8, 2), (9, 2)]));
+        assert_eq!(zones.len(), 4);
+
+        let manhattan =
+            |a: (usize, usize), b: (usize, usize)| a.0.abs_diff(b.0) + a.1.abs_diff(b.1);
+        let not_9 = |(x, _): (usize, usize)| x != 9;
+        assert_eq!(
+            zones.claim_nearest(1, (9, 3), not_9, manhattan),
+            Some((8, 2))
+        );
+        assert_eq!(
+            zones.claim_nearest(2, (9, 3), not_9, manhattan),
+            Some((7, 2))
+        );
+        // Claiming again moves the robot's claim
+        assert_eq!(
+            zones.claim_nearest(1, (0, 2), not_9, manhattan),
+            Some((2, 2))
+        );
+        assert_eq!(
+            zones.claim_nearest(3, (9, 3), not_9, manhattan),
+            Some((8, 2))
+        );
+        assert_eq!(zones.claim_nearest(4, (9, 3), not_9, manhattan), None);
+
+        assert!(!zones.toggle_area((8, 2), [(7, 2), (8, 2), (9, 2)]));
+        assert!(!zones.toggle((2, 2)));
+        assert!(zones.is_empty());
+        assert_eq!(zones.claim_nearest(4, (9, 3), |_| true, manhattan), None);
+    }
+}
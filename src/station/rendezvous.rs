@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+/// A scientist waiting on a joint site, and the collector sent to extract its sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mission {
+    pub scientist: u32,
+    pub collector: Option<u32>,
+    /// Tick the scientist asked for a collector
+    pub opened_at: u64,
+}
+
+/// Joint sites with a scientist on them, each paired with one collector at a time
+#[derive(Debug, Clone, Default)]
+pub struct RendezvousBook {
+    missions: BTreeMap<(usize, usize), Mission>,
+}
+
+impl RendezvousBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a mission for the scientist on `site`, in place of its previous one. Returns
+    /// false if another scientist holds the site.
+    pub fn request(&mut self, site: (usize, usize), scientist: u32, tick: u64) -> bool {
+        if self
+            .missions
+            .get(&site)
+            .is_some_and(|mission| mission.scientist != scientist)
+        {
+            return false;
+        }
+        self.missions
+            .retain(|_, mission| mission.scientist != scientist);
+        self.missions.insert(
+            site,
+            Mission {
+                scientist,
+                collector: None,
+                opened_at: tick,
+            },
+        );
+        true
+    }
+
+    /// Sites still waiting for a collector
+    pub fn unassigned(&self) -> Vec<(usize, usize)> {
+        self.missions
+            .iter()
+            .filter(|(_, mission)| mission.collector.is_none())
+            .map(|(&site, _)| site)
+            .collect()
+    }
+
+    pub fn assign(&mut self, site: (usize, usize), collector: u32) {
+        if let Some(mission) = self.missions.get_mut(&site) {
+            mission.collector = Some(collector);
+        }
+    }
+
+    /// Whether the robot takes part in a mission
+    pub fn is_busy(&self, robot_id: u32) -> bool {
+        self.missions
+            .values()
+            .any(|mission| mission.scientist == robot_id || mission.collector == Some(robot_id))
+    }
+
+    /// Closes the mission on `site` once its sample is extracted, returns its scientist
+    pub fn complete(&mut self, site: (usize, usize)) -> Option<u32> {
+        self.missions.remove(&site).map(|mission| mission.scientist)
+    }
+
+    /// Drops the robot's missions as scientist and sends them another collector in its stead
+    pub fn release_robot(&mut self, robot_id: u32) {
+        self.missions
+            .retain(|_, mission| mission.scientist != robot_id);
+        for mission in self.missions.values_mut() {
+            if mission.collector == Some(robot_id) {
+                mission.collector = None;
+            }
+        }
+    }
+
+    /// Sites held by robots other than `robot_id`
+    pub fn claimed_by_others(&self, robot_id: u32) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.missions
+            .iter()
+            .filter(move |(_, mission)| mission.scientist != robot_id)
+            .map(|(&site, _)| site)
+    }
+
+    /// Closes the missions opened more than `timeout` ticks before `tick`, returns their sites
+    pub fn expire(&mut self, tick: u64, timeout: u64) -> Vec<(usize, usize)> {
+        let expired: Vec<(usize, usize)> = self
+            .missions
+            .iter()
+            .filter(|(_, mission)| tick.saturating_sub(mission.opened_at) > timeout)
+            .map(|(&site, _)| site)
+            .collect();
+        for site in &expired {
+            self.missions.remove(site);
+        }
+        expired
+    }
+
+    pub fn len(&self) -> usize {
+        self.missions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_joint_site_pairs_one_scientist_with_one_collector() {
+        let mut book = RendezvousBook::new();
+        assert!(book.request((4, 4), 1, 10));
+        assert!(!book.request((4, 4), 2, 11));
+        assert!(book.request((9, 2), 2, 12));
+        assert_eq!(book.unassigned(), vec![(4, 4), (9, 2)]);
+
+        book.assign((4, 4), 5);
+        assert!(book.is_busy(5));
+        assert_eq!(book.unassigned(), vec![(9, 2)]);
+        assert_eq!(book.claimed_by_others(1).collect::<Vec<_>>(), vec![(9, 2)]);
+
+        // A collector that stops leaves its mission to another one
+        book.release_robot(5);
+        assert!(!book.is_busy(5));
+        assert_eq!(book.unassigned(), vec![(4, 4), (9, 2)]);
+
+        assert_eq!(book.complete((4, 4)), Some(1));
+        assert_eq!(book.complete((4, 4)), None);
+        assert_eq!(book.expire(100, 50), vec![(9, 2)]);
+        assert!(book.is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Named groups of robots that receive commands together.
+///
+/// A robot belongs to at most one squad; assigning it to another squad moves it.
+/// Squads that lose their last member are dropped.
+#[derive(Debug, Default, Clone)]
+pub struct SquadRoster {
+    squads: BTreeMap<String, BTreeSet<u32>>,
+}
+
+impl SquadRoster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts a robot in the squad `name`, creating the squad if needed
+    pub fn assign(&mut self, robot_id: u32, name: &str) {
+        self.remove_robot(robot_id);
+        self.squads
+            .entry(name.to_string())
+            .or_default()
+            .insert(robot_id);
+    }
+
+    /// Takes a robot out of its squad, if any
+    pub fn remove_robot(&mut self, robot_id: u32) {
+        self.squads.retain(|_, members| {
+            members.remove(&robot_id);
+            !members.is_empty()
+        });
+    }
+
+    pub fn squad_of(&self, robot_id: u32) -> Option<&str> {
+        self.squads
+            .iter()
+            .find(|(_, members)| members.contains(&robot_id))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Members of the squad `name`, sorted; empty if there is no such squad
+    pub fn members(&self, name: &str) -> Vec<u32> {
+        self.squads
+            .get(name)
+            .map(|members| members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Squad names in alphabetical order
+    pub fn names(&self) -> Vec<&str> {
+        self.squads.keys().map(String::as_str).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.squads.is_empty()
+    }
+}
+
+/// Composition and condition of a squad, for display
+#[derive(Debug, Clone, PartialEq)]
+pub struct SquadSummary {
+    pub name: String,
+    pub explorers: usize,
+    pub collectors: usize,
+    pub scientists: usize,
+    pub scouts: usize,
+    /// Average energy of the members, in percent of their maximum
+    pub energy_percent: u32,
+}
+
+impl SquadSummary {
+    pub fn size(&self) -> usize {
+        self.explorers + self.collectors + self.scientists + self.scouts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robot_belongs_to_one_squad_and_empty_squads_are_dropped() {
+        let mut roster = SquadRoster::new();
+        roster.assign(1, "Alpha");
+        roster.assign(2, "Alpha");
+        roster.assign(1, "Bravo");
+
+        assert_eq!(roster.members("Alpha"), vec![2]);
+        assert_eq!(roster.squad_of(1), Some("Bravo"));
+
+        roster.remove_robot(2);
+        assert_eq!(roster.names(), vec!["Bravo"]);
+        assert!(roster.members("Alpha").is_empty());
+    }
+}
@@ -1,57 +1,161 @@
-use log::info;
+use log::{debug, error, info};
 
 pub use crate::station::data_manager::DataManager;
 
-use crate::communication::channels::RobotEvent;
-use std::sync::{mpsc::Sender, Arc, RwLock};
+use crate::communication::channels::{DockingBrief, EventSender, RobotEvent};
+use crate::robot::core::knowledge::Sighting;
+use crate::robot::utils::config;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
 
 pub struct Station {
     pub data_manager: Arc<RwLock<DataManager>>,
-    event_sender: Sender<RobotEvent>,
+    event_sender: EventSender,
+    exploration_complete: AtomicBool,
 }
 
 impl Station {
-    pub fn new(sender: Sender<RobotEvent>, width: usize, height: usize) -> Self {
+    pub fn new(sender: EventSender, width: usize, height: usize) -> Self {
+        Self::docked_at(sender, width, height, (width / 2, height / 2))
+    }
+
+    /// Station standing at `dock` instead of the map center
+    pub fn docked_at(
+        sender: EventSender,
+        width: usize,
+        height: usize,
+        dock: (usize, usize),
+    ) -> Self {
         info!(
             "Initializing Station with DataManager for map size {}x{}",
             width, height
         );
         Self {
-            data_manager: Arc::new(RwLock::new(DataManager::new(width, height))),
+            data_manager: Arc::new(RwLock::new(DataManager::with_station(width, height, dock))),
             event_sender: sender,
+            exploration_complete: AtomicBool::new(false),
         }
     }
 
+    /// The station's knowledge, taken over as left if a thread panicked while holding it
+    pub fn data(&self) -> RwLockReadGuard<'_, DataManager> {
+        self.data_manager
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Like `data`, to change the station's knowledge
+    pub fn data_mut(&self) -> RwLockWriteGuard<'_, DataManager> {
+        self.data_manager
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     pub fn process_event(&self, event: &RobotEvent) {
-        match event {
-            RobotEvent::ArrivedAtStation { id, knowledge } => {
-                println!("Station: Robot {} arrived. Merging knowledge.", id);
-                let merged_knowledge = {
-                    let mut data_manager = self.data_manager.write().unwrap();
-                    data_manager.merge_robot_knowledge(*id, knowledge);
-                    data_manager.get_global_robot_knowledge()
-                };
-
-                let merge_event = RobotEvent::MergeComplete {
-                    id: *id,
-                    merged_knowledge,
-                };
-                if let Err(e) = self.event_sender.send(merge_event) {
-                    eprintln!(
-                        "Station Error: Failed to send MergeComplete to robot {}: {}",
-                        id, e
-                    );
-                } else {
-                    println!("Station: Sent MergeComplete to robot {}.", id);
-                }
+        if let RobotEvent::HostileSighted { sightings, .. } = event {
+            self.data_mut().record_sightings(sightings);
+        }
+        if let RobotEvent::ArrivedAtStation { id, knowledge } = event {
+            info!("Station: Robot {} arrived. Merging knowledge.", id);
+            let (merged_knowledge, exclusions, completion) = {
+                let mut data_manager = self.data_mut();
+                let stats = data_manager.merge_robot_knowledge(*id, knowledge);
+                data_manager.log_merge(self.event_sender.clock().now(), *id, stats);
+                let completion = (!self.is_exploration_complete()
+                    && data_manager.is_exploration_complete())
+                .then(|| data_manager.known_tile_count());
+                let exclusions = data_manager.exclusions().collect();
+                (
+                    data_manager.knowledge_for(knowledge),
+                    exclusions,
+                    completion,
+                )
+            };
+
+            // The app adds the robot's assignments before passing the brief on
+            let merge_event = RobotEvent::MergeComplete {
+                id: *id,
+                merged_knowledge,
+                brief: Box::new(DockingBrief {
+                    exclusions: Some(exclusions),
+                    ..DockingBrief::default()
+                }),
+            };
+            if let Err(e) = self.event_sender.send(merge_event) {
+                error!(
+                    "Station: Failed to send MergeComplete to robot {}: {}",
+                    id, e
+                );
+            } else {
+                debug!("Station: Sent MergeComplete to robot {}.", id);
+            }
+
+            if let Some(known_tiles) = completion {
+                info!(
+                    "Station: Exploration complete, {} tiles known.",
+                    known_tiles
+                );
+                self.exploration_complete.store(true, Ordering::Relaxed);
+                let _ = self
+                    .event_sender
+                    .send(RobotEvent::ExplorationComplete { known_tiles });
             }
-            _ => {}
         }
     }
 
-    pub fn update_simulation_map(&self, map: &Arc<RwLock<crate::map::noise::Map>>) {
-        let data_manager = self.data_manager.read().unwrap();
-        let mut map_guard = map.write().unwrap();
+    /// Forgets tiles whose content changed, so exploration resumes until they are reported
+    /// again
+    pub fn invalidate_tiles(&self, tiles: &[(usize, usize)]) {
+        self.data_mut().forget_tiles(tiles);
+        self.exploration_complete.store(false, Ordering::Relaxed);
+    }
+
+    /// Forgets the terrain no robot has seen again for the configured terrain memory, so
+    /// exploration resumes over it
+    pub fn forget_stale_terrain(&self, now: u64) {
+        let Some(memory) = config::terrain_memory() else {
+            return;
+        };
+        let forgotten = self.data_mut().forget_stale_terrain(now, memory);
+        if forgotten > 0 {
+            debug!(
+                "Station: Forgot {} tiles not seen for {} ticks",
+                forgotten, memory
+            );
+            self.exploration_complete.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// Replaces the no-go tiles passed on to robots when they dock. Exploration is checked
+    /// again, since lifted zones may hide unknown tiles.
+    pub fn set_exclusions(&self, tiles: impl IntoIterator<Item = (usize, usize)>) {
+        self.data_mut().set_exclusions(tiles);
+        self.exploration_complete.store(false, Ordering::Relaxed);
+    }
+
+    /// Limits each merge to the `budget` most important changed tiles each way
+    pub fn set_merge_bandwidth(&self, budget: Option<usize>) {
+        self.data_mut().set_bandwidth(budget);
+    }
+
+    /// Records what the station itself sees at tick `now`, and forgets the sightings that
+    /// became too old
+    pub fn track_entities(&self, sightings: &[Sighting], now: u64) {
+        let mut data_manager = self.data_mut();
+        data_manager.record_sightings(sightings);
+        data_manager.expire_sightings(now);
+    }
+
+    /// Whether every tile reachable from the station has been reported
+    pub fn is_exploration_complete(&self) -> bool {
+        self.exploration_complete.load(Ordering::Relaxed)
+    }
+
+    pub fn update_simulation_map(&self, map: &crate::map::shared::SharedMap) {
+        let data_manager = self.data();
+        let mut map_guard = map.write();
         data_manager.update_simulation_map(&mut map_guard);
     }
 }
@@ -59,8 +163,11 @@ impl Station {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::communication::channels::{create_channel, RobotEvent};
+    use crate::communication::channels::{
+        create_channel, EventSource, ResourceType, RobotEvent, ShutdownReason,
+    };
     use crate::robot::core::knowledge::{RobotKnowledge, TileInfo};
+    use crate::station::data_manager::MergeStats;
 
     #[test]
     fn test_station_merges_knowledge_and_sends_merge_complete() {
@@ -81,20 +188,292 @@ mod tests {
         station.process_event(&event);
 
         // Check that the MergeComplete was sent successfluy
-        let received = rx.recv().expect("Should receive MergeComplete event");
+        let received = rx.recv().expect("Should receive MergeComplete event").event;
         match received {
             RobotEvent::MergeComplete {
                 id,
                 merged_knowledge,
+                brief,
             } => {
                 assert_eq!(id, 42);
                 // The merged knowledge should contain the updated tile !
                 assert_eq!(merged_knowledge.get_tile(1, 1), &TileInfo::Walkable);
+                // No-go zones go with the brief, the assignments are the app's
+                assert_eq!(brief.exclusions, Some(Vec::new()));
+                assert!(brief.assignments.is_empty());
             }
             _ => panic!("Expected MergeComplete event"),
         }
     }
 
+    #[test]
+    fn test_limited_bandwidth_passes_the_most_important_tiles_first() {
+        let (tx, rx) = create_channel();
+        let station = Station::new(tx, 20, 20);
+        station.set_merge_bandwidth(Some(2));
+
+        let mut knowledge = RobotKnowledge::new(20, 20);
+        knowledge.update_tile(0, 0, TileInfo::Walkable);
+        knowledge.update_tile(1, 0, TileInfo::Walkable);
+        knowledge.update_tile(2, 0, TileInfo::Obstacle);
+        knowledge.update_tile(3, 0, TileInfo::Resource(ResourceType::MINERALS, 5));
+        station.process_event(&RobotEvent::ArrivedAtStation { id: 1, knowledge });
+        let _ = rx.recv();
+
+        let global = station
+            .data_manager
+            .read()
+            .unwrap()
+            .get_global_robot_knowledge();
+        assert_eq!(global.get_tile(0, 0), &TileInfo::Unknown);
+        assert_eq!(global.get_tile(2, 0), &TileInfo::Obstacle);
+        // Two walkable tiles held back from the station, and six station tiles from the robot
+        assert_eq!(station.data_manager.read().unwrap().deferred_deltas(), 8);
+
+        // A newcomer gets the resource and the obstacle before the station's own tiles
+        let knowledge = RobotKnowledge::new(20, 20);
+        station.process_event(&RobotEvent::ArrivedAtStation { id: 2, knowledge });
+        match rx.recv().unwrap().event {
+            RobotEvent::MergeComplete {
+                merged_knowledge, ..
+            } => {
+                assert_eq!(
+                    merged_knowledge.get_tile(3, 0),
+                    &TileInfo::Resource(ResourceType::MINERALS, 5)
+                );
+                assert_eq!(merged_knowledge.get_tile(2, 0), &TileInfo::Obstacle);
+                assert_eq!(merged_knowledge.get_tile(9, 9), &TileInfo::Unknown);
+            }
+            other => panic!("Expected MergeComplete event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_station_forgets_terrain_no_robot_saw_again() {
+        let (tx, rx) = create_channel();
+        let station = Station::new(tx, 10, 10);
+
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        knowledge.forget_stale_terrain(20, None);
+        knowledge.update_tile(0, 0, TileInfo::Obstacle);
+        knowledge.forget_stale_terrain(80, None);
+        knowledge.update_tile(1, 0, TileInfo::Walkable);
+        station.process_event(&RobotEvent::ArrivedAtStation { id: 1, knowledge });
+        let _ = rx.recv();
+
+        assert_eq!(station.data_mut().forget_stale_terrain(150, 100), 1);
+        let global = station.data().get_global_robot_knowledge();
+        assert_eq!(global.get_tile(0, 0), &TileInfo::Unknown);
+        assert_eq!(global.get_tile(1, 0), &TileInfo::Walkable);
+        // Robots docking later learn when the tile was seen, not when they docked
+        assert_eq!(global.seen_at(1, 0), Some(80));
+    }
+
+    #[test]
+    fn test_coverage_tracks_worked_deposits() {
+        let (tx, rx) = create_channel();
+        let station = Station::new(tx, 10, 10);
+
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        knowledge.update_tile(0, 0, TileInfo::Resource(ResourceType::MINERALS, 10));
+        knowledge.update_tile(1, 0, TileInfo::Resource(ResourceType::ENERGY, 6));
+        knowledge.update_tile(2, 0, TileInfo::Resource(ResourceType::ENERGY, 4));
+        station.process_event(&RobotEvent::ArrivedAtStation {
+            id: 1,
+            knowledge: knowledge.clone(),
+        });
+        let _ = rx.recv();
+
+        // Later reports: one deposit emptied, one partly taken
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        knowledge.update_tile(0, 0, TileInfo::Walkable);
+        knowledge.update_tile(1, 0, TileInfo::Resource(ResourceType::ENERGY, 2));
+        station.process_event(&RobotEvent::ArrivedAtStation { id: 1, knowledge });
+        let _ = rx.recv();
+
+        let coverage = station.data_manager.read().unwrap().coverage();
+        assert_eq!(coverage.total_tiles, 100);
+        // Three deposits plus the nine station tiles
+        assert_eq!(coverage.known_tiles, 12);
+        assert_eq!(
+            (coverage.deposits, coverage.claimed, coverage.exhausted),
+            (3, 2, 1)
+        );
+        assert_eq!(coverage.claimed_percent(), 66);
+        assert_eq!(coverage.remaining(&ResourceType::ENERGY), 6);
+        assert_eq!(coverage.remaining(&ResourceType::MINERALS), 0);
+    }
+
+    #[test]
+    fn test_knowledge_queries_find_deposits_and_the_frontier() {
+        let (tx, rx) = create_channel();
+        let station = Station::new(tx, 10, 10);
+
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        knowledge.update_tile(0, 0, TileInfo::Resource(ResourceType::MINERALS, 10));
+        knowledge.update_tile(1, 0, TileInfo::Resource(ResourceType::ENERGY, 6));
+        knowledge.update_tile(2, 0, TileInfo::Walkable);
+        knowledge.update_tile(8, 8, TileInfo::Resource(ResourceType::MINERALS, 3));
+        station.process_event(&RobotEvent::ArrivedAtStation { id: 1, knowledge });
+        let _ = rx.recv();
+
+        let data = station.data();
+        assert_eq!(
+            data.nearest_known(&ResourceType::MINERALS, (9, 9)),
+            Some((8, 8))
+        );
+        assert_eq!(
+            data.nearest_known(&ResourceType::MINERALS, (2, 2)),
+            Some((0, 0))
+        );
+        assert_eq!(
+            data.nearest_known(&ResourceType::SCIENCE_POINTS, (2, 2)),
+            None
+        );
+
+        // The twelve unknown tiles around the station and eight around the reports
+        let frontier = data.frontier_tiles();
+        assert_eq!(frontier.len(), 20);
+        assert_eq!(frontier.first(), Some(&(0, 1)));
+        assert!(frontier.contains(&(4, 3)) && frontier.contains(&(9, 8)));
+        assert!(!frontier.contains(&(0, 0)) && !frontier.contains(&(5, 0)));
+        drop(data);
+
+        station.set_exclusions(vec![(8, 8), (9, 8)]);
+        let data = station.data();
+        assert_eq!(
+            data.nearest_known(&ResourceType::MINERALS, (9, 9)),
+            Some((0, 0))
+        );
+        assert!(!data.frontier_tiles().contains(&(9, 8)));
+    }
+
+    #[test]
+    fn test_docking_robots_take_the_user_notes_away() {
+        let (tx, rx) = create_channel();
+        let station = Station::new(tx, 10, 10);
+        station
+            .data_mut()
+            .annotate(3, 4, "rich vein here".to_string());
+        station
+            .data_mut()
+            .annotate(1, 1, "dangerous pass".to_string());
+        station.data_mut().annotate(3, 4, "worked out".to_string());
+
+        // A robot's stale copy does not bring a removed note back
+        let mut knowledge = RobotKnowledge::new(10, 10);
+        knowledge.set_annotations(station.data().entities().annotations());
+        assert!(station.data_mut().clear_annotation(1, 1));
+        station.process_event(&RobotEvent::ArrivedAtStation { id: 1, knowledge });
+        match rx.recv().unwrap().event {
+            RobotEvent::MergeComplete {
+                merged_knowledge, ..
+            } => {
+                assert_eq!(merged_knowledge.annotation(3, 4), Some("worked out"));
+                assert_eq!(merged_knowledge.annotation(1, 1), None);
+                let json = serde_json::to_string(&merged_knowledge).unwrap();
+                let copy: RobotKnowledge = serde_json::from_str(&json).unwrap();
+                assert_eq!(copy.annotations(), merged_knowledge.annotations());
+            }
+            other => panic!("Expected a merge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_merge_log_counts_new_tiles_and_conflicts() {
+        let (tx, rx) = create_channel();
+        let station = Station::new(tx, 10, 10);
+
+        let mut first = RobotKnowledge::new(10, 10);
+        first.update_tile(0, 0, TileInfo::Walkable);
+        first.update_tile(1, 0, TileInfo::Walkable);
+        station.process_event(&RobotEvent::ArrivedAtStation {
+            id: 1,
+            knowledge: first,
+        });
+        let _ = rx.recv();
+
+        // A later report disagrees about one tile, repeats another and adds a third
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let mut second = RobotKnowledge::new(10, 10);
+        second.update_tile(0, 0, TileInfo::Obstacle);
+        second.update_tile(1, 0, TileInfo::Walkable);
+        second.update_tile(2, 0, TileInfo::Walkable);
+        station.process_event(&RobotEvent::ArrivedAtStation {
+            id: 2,
+            knowledge: second,
+        });
+        let _ = rx.recv();
+
+        let data_manager = station.data_manager.read().unwrap();
+        let log: Vec<_> = data_manager
+            .merge_log()
+            .map(|record| (record.robot_id, record.stats))
+            .collect();
+        assert_eq!(
+            log,
+            vec![
+                (
+                    1,
+                    MergeStats {
+                        contributed: 2,
+                        conflicts: 0
+                    }
+                ),
+                (
+                    2,
+                    MergeStats {
+                        contributed: 1,
+                        conflicts: 1
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflicts_are_counted_by_kind() {
+        let (tx, rx) = create_channel();
+        let station = Station::new(tx, 10, 10);
+
+        let mut first = RobotKnowledge::new(10, 10);
+        first.update_tile(0, 0, TileInfo::Walkable);
+        first.update_tile(1, 0, TileInfo::Resource(ResourceType::ENERGY, 5));
+        first.update_tile(2, 0, TileInfo::Resource(ResourceType::ENERGY, 5));
+        first.update_tile(3, 0, TileInfo::Resource(ResourceType::MINERALS, 5));
+        station.process_event(&RobotEvent::ArrivedAtStation {
+            id: 1,
+            knowledge: first.clone(),
+        });
+        let _ = rx.recv();
+
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let mut second = first;
+        second.update_tile(0, 0, TileInfo::Obstacle);
+        second.update_tile(1, 0, TileInfo::Resource(ResourceType::ENERGY, 3));
+        second.update_tile(2, 0, TileInfo::Walkable);
+        second.update_tile(3, 0, TileInfo::Resource(ResourceType::ENERGY, 5));
+        station.process_event(&RobotEvent::ArrivedAtStation {
+            id: 2,
+            knowledge: second,
+        });
+        let _ = rx.recv();
+
+        let stats = station.data_manager.read().unwrap().conflict_stats();
+        assert_eq!(
+            (
+                stats.obstacle_walkable,
+                stats.resource_amount,
+                stats.resource_presence,
+                stats.resource_type
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(stats.total(), 4);
+        // Obstacle and walkable reports never replace each other
+        assert_eq!(stats.overturned, 3);
+    }
+
     #[test]
     fn test_station_handles_unknown_event_gracefully() {
         let (tx, rx) = create_channel();
@@ -103,7 +482,7 @@ mod tests {
         // Send any event
         let event = RobotEvent::Shutdown {
             id: 1,
-            reason: "test".to_string(),
+            reason: ShutdownReason::LoopExited,
         };
 
         // Should not throw an error
@@ -136,7 +515,7 @@ mod tests {
             knowledge: knowledge2,
         };
         station.process_event(&event2);
-        let received = rx.recv().expect("Should receive MergeComplete event");
+        let received = rx.recv().expect("Should receive MergeComplete event").event;
 
         match received {
             RobotEvent::MergeComplete {
@@ -160,7 +539,7 @@ mod tests {
         let event = RobotEvent::ArrivedAtStation { id: 7, knowledge };
         station.process_event(&event);
 
-        let received = rx.recv().expect("Should receive MergeComplete event");
+        let received = rx.recv().expect("Should receive MergeComplete event").event;
         match received {
             RobotEvent::MergeComplete {
                 merged_knowledge, ..
@@ -179,6 +558,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_station_reports_exploration_complete_once() {
+        let (tx, rx) = create_channel();
+        let station = Station::new(tx.with_source(EventSource::Station), 7, 3);
+
+        // Station area covers x 2..=4; left of it is walkable, right of it a wall
+        let mut knowledge = RobotKnowledge::new(7, 3);
+        for y in 0..3 {
+            knowledge.update_tile(0, y, TileInfo::Walkable);
+            knowledge.update_tile(1, y, TileInfo::Walkable);
+            knowledge.update_tile(5, y, TileInfo::Obstacle);
+        }
+        let event = RobotEvent::ArrivedAtStation { id: 1, knowledge };
+
+        station.process_event(&event);
+        assert!(matches!(
+            rx.recv().map(|envelope| envelope.event),
+            Ok(RobotEvent::MergeComplete { .. })
+        ));
+        // The last column is still unknown but lies behind the wall
+        let envelope = rx.recv().expect("Should receive ExplorationComplete event");
+        assert!(matches!(
+            envelope.event,
+            RobotEvent::ExplorationComplete { known_tiles: 18 }
+        ));
+        assert_eq!(envelope.source, EventSource::Station);
+        assert_eq!(envelope.sequence, 2);
+        assert!(station.is_exploration_complete());
+
+        station.process_event(&event);
+        assert!(matches!(
+            rx.recv().map(|envelope| envelope.event),
+            Ok(RobotEvent::MergeComplete { .. })
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
     #[test]
     fn test_station_merge_event_has_correct_id() {
         let (tx, rx) = create_channel();
@@ -188,7 +604,7 @@ mod tests {
         let event = RobotEvent::ArrivedAtStation { id: 99, knowledge };
         station.process_event(&event);
 
-        let received = rx.recv().expect("Should receive MergeComplete event");
+        let received = rx.recv().expect("Should receive MergeComplete event").event;
         match received {
             RobotEvent::MergeComplete { id, .. } => assert_eq!(id, 99),
             _ => panic!("Expected MergeComplete event"),
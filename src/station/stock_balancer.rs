@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::communication::channels::ResourceType;
+
+/// Station policy choosing which resource each collector gathers, so that the
+/// stock of energy and minerals stays balanced.
+pub struct StockBalancer {
+    /// How far ahead (percent) a collector's resource must be before it is moved
+    tolerance_percent: u32,
+    /// Types the station stocks, as the run's catalog has them
+    consumables: Vec<ResourceType>,
+}
+
+impl StockBalancer {
+    pub fn new(tolerance_percent: u32, consumables: Vec<ResourceType>) -> Self {
+        Self {
+            tolerance_percent,
+            consumables,
+        }
+    }
+
+    /// Resource the station is shortest of; ties go to the type fewer collectors work on
+    pub fn neediest(
+        &self,
+        stock: &HashMap<ResourceType, u32>,
+        targets: &HashMap<u32, ResourceType>,
+    ) -> ResourceType {
+        self.consumables
+            .iter()
+            .copied()
+            .min_by_key(|resource_type| {
+                let assigned = targets.values().filter(|&&t| t == *resource_type).count();
+                (stock_of(stock, resource_type), assigned)
+            })
+            .unwrap_or(ResourceType::MINERALS)
+    }
+
+    /// New target for a collector docking while gathering `current`, or `None` to keep it.
+    /// The collector only moves when its resource leads the neediest one by more than the
+    /// tolerance, so small fluctuations do not make collectors flip back and forth.
+    pub fn rebalance(
+        &self,
+        current: &ResourceType,
+        stock: &HashMap<ResourceType, u32>,
+        targets: &HashMap<u32, ResourceType>,
+    ) -> Option<ResourceType> {
+        let neediest = self.neediest(stock, targets);
+        if &neediest == current {
+            return None;
+        }
+        let current_stock = u64::from(stock_of(stock, current));
+        let lead = current_stock.saturating_sub(u64::from(stock_of(stock, &neediest)));
+        (lead * 100 > u64::from(self.tolerance_percent) * current_stock).then_some(neediest)
+    }
+}
+
+fn stock_of(stock: &HashMap<ResourceType, u32>, resource_type: &ResourceType) -> u32 {
+    stock.get(resource_type).copied().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collectors_move_to_the_scarce_resource_beyond_tolerance() {
+        let balancer = StockBalancer::new(25, vec![ResourceType::ENERGY, ResourceType::MINERALS]);
+        let mut targets = HashMap::from([(1, ResourceType::MINERALS)]);
+
+        // Empty stock: spread collectors over both types
+        assert_eq!(
+            balancer.neediest(&HashMap::new(), &targets),
+            ResourceType::ENERGY
+        );
+        targets.insert(2, ResourceType::ENERGY);
+
+        let stock = HashMap::from([(ResourceType::ENERGY, 80), (ResourceType::MINERALS, 100)]);
+        assert_eq!(
+            balancer.rebalance(&ResourceType::MINERALS, &stock, &targets),
+            None
+        );
+
+        let stock = HashMap::from([(ResourceType::ENERGY, 60), (ResourceType::MINERALS, 100)]);
+        assert_eq!(
+            balancer.rebalance(&ResourceType::MINERALS, &stock, &targets),
+            Some(ResourceType::ENERGY)
+        );
+        assert_eq!(
+            balancer.rebalance(&ResourceType::ENERGY, &stock, &targets),
+            None
+        );
+    }
+}
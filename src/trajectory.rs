@@ -0,0 +1,121 @@
+use log::info;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::memory;
+use crate::robot::registry::{RobotEntry, RobotType};
+
+/// Every robot's position at every tick, kept column by column so long runs stay compact,
+/// for coverage plots and displacement curves worked out offline
+#[derive(Debug, Clone, Default)]
+pub struct TrajectoryLog {
+    ticks: Vec<u64>,
+    robot_ids: Vec<u32>,
+    robot_types: Vec<RobotType>,
+    xs: Vec<u32>,
+    ys: Vec<u32>,
+}
+
+impl TrajectoryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records where each of the `robots` stands at `tick`
+    pub fn record<'a>(&mut self, tick: u64, robots: impl Iterator<Item = &'a RobotEntry>) {
+        for robot in robots {
+            self.ticks.push(tick);
+            self.robot_ids.push(robot.state.id);
+            self.robot_types.push(robot.robot_type);
+            self.xs.push(robot.state.x as u32);
+            self.ys.push(robot.state.y as u32);
+        }
+    }
+
+    /// Number of recorded positions
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    /// Approximate bytes held, see `crate::memory`
+    pub fn approx_bytes(&self) -> usize {
+        memory::vec_bytes::<u64>(self.ticks.capacity())
+            + memory::vec_bytes::<u32>(self.robot_ids.capacity())
+            + memory::vec_bytes::<RobotType>(self.robot_types.capacity())
+            + memory::vec_bytes::<u32>(self.xs.capacity() + self.ys.capacity())
+    }
+
+    /// One `tick,robot_id,robot_type,x,y` row per recorded position
+    pub fn to_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "tick,robot_id,robot_type,x,y")?;
+        for row in 0..self.len() {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                self.ticks[row],
+                self.robot_ids[row],
+                format!("{:?}", self.robot_types[row]).to_lowercase(),
+                self.xs[row],
+                self.ys[row]
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn write_csv(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut out = BufWriter::new(file);
+        self.to_csv(&mut out)
+            .and_then(|()| out.flush())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        info!(
+            "{} robot positions exported to {}",
+            self.len(),
+            path.display()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::core::state::RobotStatus;
+    use crate::robot::RobotState;
+    use std::sync::mpsc;
+
+    fn robot(robot_type: RobotType, id: u32, x: usize, y: usize) -> RobotEntry {
+        let state = RobotState::new(id, x, y, RobotStatus::Exploring, 100);
+        RobotEntry::new(robot_type, state, mpsc::channel().0, mpsc::channel().0)
+    }
+
+    #[test]
+    fn test_positions_are_exported_one_row_per_robot_and_tick() {
+        let mut log = TrajectoryLog::new();
+        log.record(
+            1,
+            [
+                robot(RobotType::Exploration, 0, 3, 4),
+                robot(RobotType::Scout, 1, 5, 6),
+            ]
+            .iter(),
+        );
+        log.record(2, [robot(RobotType::Exploration, 0, 4, 4)].iter());
+        assert_eq!(log.len(), 3);
+
+        let mut csv = Vec::new();
+        log.to_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "tick,robot_id,robot_type,x,y\n1,0,exploration,3,4\n1,1,scout,5,6\n2,0,exploration,4,4\n"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use ratatui::{
+    layout::Rect,
+    style::Color,
+    symbols::Marker,
+    widgets::{
+        canvas::{Canvas, Points},
+        Block, Borders,
+    },
+    Frame,
+};
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    app::App,
+    map::noise::Map,
+    robot::{registry::RobotType, RobotState},
+    ui::theme::{RobotGlyph, Theme},
+};
+
+/// Braille dots per terminal cell, horizontally and vertically
+const DOTS_PER_CELL: (u16, u16) = (2, 4);
+
+/// Renders the map on a braille canvas where every terminal cell shows a 2x4 block of tiles.
+///
+/// Tiles map one-to-one onto braille dots, so a map up to twice as wide and four times as
+/// tall as the panel fits on screen. Robots are drawn last as colored points. Only the
+/// `layer` shown by the UI is drawn. Hex tiles take two dots, odd rows shifted by one.
+pub fn render_braille_map(frame: &mut Frame, area: Rect, app: &App, theme: &Theme, layer: usize) {
+    let map_guard = app.map.read();
+    let rows = map_guard.layer_rows(layer);
+    let hex = map_guard.hex;
+    let layers = tile_layers(&map_guard, rows.clone(), theme);
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        "Astro Swarm Map (braille, {})",
+        layer_name(&map_guard, layer)
+    ));
+    drop(map_guard);
+    let inner = block.inner(area);
+    let dots_x = f64::from(inner.width.max(1) * DOTS_PER_CELL.0);
+    let dots_y = f64::from(inner.height.max(1) * DOTS_PER_CELL.1);
+
+    let robot_layers = [
+        (RobotType::Scout, RobotGlyph::Scout),
+        (RobotType::Scientific, RobotGlyph::Scientist),
+        (RobotType::Collection, RobotGlyph::Collector),
+        (RobotType::Exploration, RobotGlyph::Explorer),
+    ]
+    .map(|(robot_type, glyph)| {
+        let color = theme.robot_span(glyph).style.fg.unwrap_or(Color::White);
+        (
+            on_rows(robot_points(app.robots.states_of(robot_type)), &rows),
+            color,
+        )
+    });
+
+    let hostile_layer = [(on_rows(app.hostiles.positions(), &rows), Color::Magenta)];
+
+    // Canvas y grows upwards, tile y grows downwards
+    let first_row = rows.start;
+    let to_canvas = move |&(x, y): &(usize, usize)| {
+        let x = if hex { 2 * x + (first_row + y) % 2 } else { x };
+        (x as f64, dots_y - 1.0 - y as f64)
+    };
+
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds([0.0, dots_x - 1.0])
+        .y_bounds([0.0, dots_y - 1.0])
+        .paint(move |ctx| {
+            for (tiles, color) in layers
+                .iter()
+                .chain(robot_layers.iter())
+                .chain(hostile_layer.iter())
+            {
+                let coords: Vec<(f64, f64)> = tiles.iter().map(to_canvas).collect();
+                ctx.draw(&Points {
+                    coords: &coords,
+                    color: *color,
+                });
+            }
+        });
+
+    frame.render_widget(canvas, area);
+}
+
+/// Title of a map layer
+pub fn layer_name(map: &Map, layer: usize) -> String {
+    let mut name = match (layer, map.layers()) {
+        (_, 1) => "Surface".to_string(),
+        (0, _) => format!("Surface, 1/{}", map.layers()),
+        (_, layers) => format!("Cave {}, {}/{}", layer, layer + 1, layers),
+    };
+    if map.hex {
+        name.push_str(", hex");
+    }
+    if map.wraps {
+        name.push_str(", wrapping");
+    }
+    name
+}
+
+/// Points on `rows`, moved up so the first row is 0
+fn on_rows(points: Vec<(usize, usize)>, rows: &Range<usize>) -> Vec<(usize, usize)> {
+    points
+        .into_iter()
+        .filter(|(_, y)| rows.contains(y))
+        .map(|(x, y)| (x, y - rows.start))
+        .collect()
+}
+
+/// Groups the non-empty tiles of `rows` by the color the theme gives them, the first row
+/// at y 0
+fn tile_layers(map: &Map, rows: Range<usize>, theme: &Theme) -> Vec<(Vec<(usize, usize)>, Color)> {
+    let mut layers: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
+    for y in rows.clone() {
+        for x in 0..map.width {
+            let symbol = map.tile_symbol(x, y);
+            if symbol != ' ' {
+                layers.entry(symbol).or_default().push((x, y - rows.start));
+            }
+        }
+    }
+
+    // Obstacles first so resources and the station stay visible on top
+    let mut layers: Vec<_> = layers.into_iter().collect();
+    layers.sort_by_key(|(symbol, _)| *symbol != '█');
+    layers
+        .into_iter()
+        .map(|(symbol, tiles)| {
+            let color = theme
+                .tile_span(symbol, map.catalog())
+                .style
+                .fg
+                .unwrap_or(Color::White);
+            (tiles, color)
+        })
+        .collect()
+}
+
+fn robot_points<'a>(robots: impl Iterator<Item = &'a RobotState>) -> Vec<(usize, usize)> {
+    robots.map(|robot| (robot.x, robot.y)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_layers_draw_obstacles_first_and_skip_empty_tiles() {
+        let map = Map::new(20, 10, 11);
+        let layers = tile_layers(&map, map.layer_rows(0), &Theme::classic());
+
+        let obstacle_color = Theme::classic()
+            .tile_span('█', map.catalog())
+            .style
+            .fg
+            .unwrap();
+        assert_eq!(layers[0].1, obstacle_color);
+
+        let drawn: usize = layers.iter().map(|(tiles, _)| tiles.len()).sum();
+        let non_empty = (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.tile_symbol(x, y) != ' ')
+            .count();
+        assert_eq!(drawn, non_empty);
+    }
+}
@@ -0,0 +1,166 @@
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Row, Table},
+    Frame,
+};
+
+use crate::{
+    app::App,
+    ui::{
+        braille_renderer::render_braille_map, input::InputAction, keys::KeyAction, state::UiState,
+        theme::Theme,
+    },
+};
+
+/// One simulation of a comparison, with the name of its configuration
+pub struct ComparisonSide<'a> {
+    pub label: &'a str,
+    pub app: &'a App,
+}
+
+/// A figure both runs are compared on
+#[derive(Debug, Clone, PartialEq)]
+pub struct KpiRow {
+    pub label: &'static str,
+    pub left: f64,
+    pub right: f64,
+}
+
+impl KpiRow {
+    /// Which side is ahead, the left one when `Some(true)`, `None` on a tie
+    pub fn left_leads(&self) -> Option<bool> {
+        if self.left == self.right {
+            None
+        } else {
+            Some(self.left > self.right)
+        }
+    }
+}
+
+/// The figures of the comparison bar, higher is better for each
+pub fn kpi_rows(left: &App, right: &App) -> Vec<KpiRow> {
+    let row = |label, figure: fn(&App) -> f64| KpiRow {
+        label,
+        left: figure(left),
+        right: figure(right),
+    };
+    vec![
+        row("Efficiency / 100 energy", |app| app.live_kpis().efficiency),
+        row("Delivered / 100 ticks", |app| app.live_kpis().delivery_rate),
+        row("Explored / 100 ticks", |app| {
+            app.live_kpis().exploration_velocity
+        }),
+        row("Explored tiles", |app| app.total_explored as f64),
+        row("Science value", |app| app.scientific_data as f64),
+        row("Robots", |app| app.robot_ids().len() as f64),
+    ]
+}
+
+/// Rows of the comparison bar, besides its borders and header
+const BAR_ROWS: u16 = 6;
+
+/// Both maps side by side, each under the name of its configuration, above a bar comparing
+/// their figures
+pub fn render_comparison(
+    frame: &mut Frame,
+    area: Rect,
+    left: ComparisonSide,
+    right: ComparisonSide,
+    theme: &Theme,
+) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(BAR_ROWS + 3)])
+        .split(area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[0]);
+
+    for (side, column) in [&left, &right].into_iter().zip(columns.iter()) {
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(*column);
+        frame.render_widget(
+            Line::from(format!(" {} (tick {})", side.label, side.app.tick)).bold(),
+            parts[0],
+        );
+        render_braille_map(frame, parts[1], side.app, theme, 0);
+    }
+
+    let lead_style = Style::default().fg(Color::Green).bold();
+    let table_rows = kpi_rows(left.app, right.app).into_iter().map(|row| {
+        let (left_style, right_style) = match row.left_leads() {
+            Some(true) => (lead_style, Style::default()),
+            Some(false) => (Style::default(), lead_style),
+            None => (Style::default(), Style::default()),
+        };
+        Row::new([
+            Cell::from(row.label),
+            Cell::from(format!("{:.1}", row.left)).style(left_style),
+            Cell::from(format!("{:.1}", row.right)).style(right_style),
+        ])
+    });
+    let table = Table::new(
+        table_rows,
+        [
+            Constraint::Length(24),
+            Constraint::Percentage(30),
+            Constraint::Percentage(30),
+        ],
+    )
+    .header(
+        Row::new([
+            Cell::from(""),
+            Cell::from(left.label),
+            Cell::from(right.label),
+        ])
+        .bold(),
+    )
+    .block(Block::default().borders(Borders::ALL).title(" Comparison "));
+    frame.render_widget(table, rows[1]);
+}
+
+/// Applies a terminal event to a comparison: only quitting and changing the theme apply,
+/// the runs are left to themselves
+pub fn handle_comparison_event(event: &Event, ui: &mut UiState) -> InputAction {
+    let Event::Key(key) = event else {
+        return InputAction::None;
+    };
+    if key.kind != KeyEventKind::Press {
+        return InputAction::None;
+    }
+    match key.code {
+        KeyCode::Esc => InputAction::Quit,
+        KeyCode::Char(key) => match ui.keys.action(key) {
+            Some(KeyAction::Quit) => InputAction::Quit,
+            Some(KeyAction::CycleTheme) => {
+                ui.cycle_theme();
+                InputAction::None
+            }
+            _ => InputAction::None,
+        },
+        _ => InputAction::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_the_higher_figure_leads() {
+        let row = |left, right| KpiRow {
+            label: "Efficiency",
+            left,
+            right,
+        };
+        assert_eq!(row(12.5, 3.0).left_leads(), Some(true));
+        assert_eq!(row(0.0, 0.5).left_leads(), Some(false));
+        assert_eq!(row(4.0, 4.0).left_leads(), None);
+    }
+}
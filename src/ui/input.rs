@@ -0,0 +1,247 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEventKind};
+
+use crate::{
+    app::{App, FAST_FORWARD_TICKS},
+    communication::channels::{ResourceType, RobotCommand},
+    console, profiler,
+    robot::registry::RobotType,
+    station::formation::Formation,
+    ui::{keys::KeyAction, state::UiState},
+};
+
+/// Squads the number keys assign the selected group to
+const SQUAD_NAMES: [&str; 3] = ["Alpha", "Bravo", "Charlie"];
+
+/// What the main loop does after an event reached the simulation screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputAction {
+    None,
+    Quit,
+    /// Pauses the run behind the main menu
+    OpenMenu,
+    /// Starts the run over from its setup, on new random seeds if asked
+    Restart {
+        new_seeds: bool,
+    },
+}
+
+/// Applies a terminal event to the UI and the simulation
+pub fn handle_event(event: &Event, app: &mut App, ui: &mut UiState) -> InputAction {
+    match event {
+        Event::Key(key) if key.kind == KeyEventKind::Press => handle_key(key, app, ui),
+        Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(tile) = ui.tile_at(mouse.column, mouse.row) {
+                ui.cursor = tile;
+            }
+            InputAction::None
+        }
+        _ => InputAction::None,
+    }
+}
+
+fn handle_key(key: &KeyEvent, app: &mut App, ui: &mut UiState) -> InputAction {
+    if ui.console.is_some() {
+        handle_console_key(key, app, ui);
+        return InputAction::None;
+    }
+    let width = app.map_width;
+    let rows = app.map.read().layer_rows(ui.layer);
+    match key.code {
+        KeyCode::Esc => return InputAction::OpenMenu,
+        KeyCode::Left => ui.move_cursor(-1, 0, width, rows),
+        KeyCode::Right => ui.move_cursor(1, 0, width, rows),
+        KeyCode::Up => ui.move_cursor(0, -1, width, rows),
+        KeyCode::Down => ui.move_cursor(0, 1, width, rows),
+        KeyCode::Tab => {
+            let ids = app.robot_ids();
+            ui.retain_robots(&ids);
+            ui.select_next_robot(&ids);
+        }
+        KeyCode::Char(' ') => ui.toggle_selected_in_group(),
+        KeyCode::Char(':') => {
+            ui.console = Some(String::new());
+            ui.console_reply = None;
+        }
+        KeyCode::Char(digit @ '1'..='3') => {
+            let name = SQUAD_NAMES[digit as usize - '1' as usize];
+            assign_group_to_squad(app, ui, name);
+        }
+        KeyCode::Char(key) => {
+            if let Some(action) = ui.keys.action(key) {
+                return run_action(action, app, ui);
+            }
+        }
+        _ => {}
+    }
+    InputAction::None
+}
+
+/// Types into the open console: Enter runs the line, Esc closes it
+fn handle_console_key(key: &KeyEvent, app: &mut App, ui: &mut UiState) {
+    let Some(line) = ui.console.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => ui.console = None,
+        KeyCode::Enter => {
+            let line = ui.console.take().unwrap_or_default();
+            let reply = console::run(&line, app, ui.cursor);
+            match &reply {
+                Ok(message) => log::info!("Console '{}': {}", line, message),
+                Err(e) => log::warn!("Console '{}': {}", line, e),
+            }
+            ui.console_reply = Some(reply);
+        }
+        KeyCode::Backspace => {
+            line.pop();
+        }
+        KeyCode::Char(c) => line.push(c),
+        _ => {}
+    }
+}
+
+/// Carries out an action bound in the key settings
+fn run_action(action: KeyAction, app: &mut App, ui: &mut UiState) -> InputAction {
+    match action {
+        KeyAction::Quit => return InputAction::Quit,
+        KeyAction::CycleTheme => ui.cycle_theme(),
+        KeyAction::ToggleMapView => ui.toggle_map_view(),
+        KeyAction::ToggleVision => ui.vision_overlay = !ui.vision_overlay,
+        KeyAction::ToggleStationView => ui.station_view = !ui.station_view,
+        KeyAction::ToggleProfiler => {
+            // Measuring goes on once started, so the figures keep covering the whole run
+            ui.profiler_overlay = !ui.profiler_overlay;
+            profiler::enable();
+        }
+        KeyAction::CyclePreset => app.set_preset(app.preset.next()),
+        KeyAction::CycleLayer => ui.cycle_layer(&app.map.read()),
+        KeyAction::FormationLine => start_formation(app, ui, Formation::LineAbreast),
+        KeyAction::FormationColumn => start_formation(app, ui, Formation::Column),
+        KeyAction::CycleSquad => ui.cycle_squad(&app.squads.names()),
+        KeyAction::Recall => command_active_squad(app, ui, RobotCommand::ReturnToStation),
+        KeyAction::Relocate => {
+            let (x, y) = ui.cursor;
+            command_active_squad(app, ui, RobotCommand::MoveTo { x, y });
+        }
+        KeyAction::GatherEnergy => command_active_squad(
+            app,
+            ui,
+            RobotCommand::SetTargetResource(ResourceType::ENERGY),
+        ),
+        KeyAction::GatherMinerals => command_active_squad(
+            app,
+            ui,
+            RobotCommand::SetTargetResource(ResourceType::MINERALS),
+        ),
+        KeyAction::AddWaypoint => {
+            if ui.patrol_draft.last() != Some(&ui.cursor) {
+                ui.patrol_draft.push(ui.cursor);
+            }
+        }
+        KeyAction::ClearPatrol => ui.patrol_draft.clear(),
+        KeyAction::Decommission => {
+            ui.retain_robots(&app.robot_ids());
+            for id in ui.command_targets() {
+                app.decommission(id);
+            }
+        }
+        KeyAction::Convert => {
+            ui.retain_robots(&app.robot_ids());
+            for id in ui.command_targets() {
+                let Some(robot_type) = app.robots.get(id).map(|entry| entry.robot_type) else {
+                    continue;
+                };
+                if let Err(e) = app.convert_robot(id, next_behavior(robot_type)) {
+                    log::warn!("{}", e);
+                }
+            }
+        }
+        KeyAction::FastForward => app.fast_forward(FAST_FORWARD_TICKS),
+        KeyAction::MarkPriority => {
+            app.priorities.toggle(ui.cursor);
+        }
+        KeyAction::MarkPriorityArea => app.toggle_priority_area(ui.cursor),
+        KeyAction::MarkNoGoArea => app.toggle_exclusion_area(ui.cursor),
+        KeyAction::Annotate => {
+            ui.console = Some("note ".to_string());
+            ui.console_reply = None;
+        }
+        KeyAction::RestartSameSeeds => return InputAction::Restart { new_seeds: false },
+        KeyAction::RestartNewSeeds => return InputAction::Restart { new_seeds: true },
+        KeyAction::Patrol => assign_patrol(app, ui),
+        KeyAction::StopPatrol => {
+            ui.retain_robots(&app.robot_ids());
+            app.assign_patrol(&ui.command_targets(), &[]);
+        }
+    }
+    InputAction::None
+}
+
+/// Behavior the convert key gives a robot: explorers become collectors, collectors
+/// scientists, scientists scouts and scouts explorers
+fn next_behavior(robot_type: RobotType) -> RobotType {
+    match robot_type {
+        RobotType::Exploration => RobotType::Collection,
+        RobotType::Collection => RobotType::Scientific,
+        RobotType::Scientific => RobotType::Scout,
+        RobotType::Scout => RobotType::Exploration,
+    }
+}
+
+fn start_formation(app: &mut App, ui: &mut UiState, formation: Formation) {
+    ui.retain_robots(&app.robot_ids());
+    if ui.group.is_empty() {
+        log::warn!("No robots selected for the formation");
+        return;
+    }
+    let members: Vec<u32> = ui.group.iter().copied().collect();
+    app.start_formation(&members, formation, ui.cursor);
+}
+
+fn assign_group_to_squad(app: &mut App, ui: &mut UiState, name: &str) {
+    ui.retain_robots(&app.robot_ids());
+    if ui.group.is_empty() {
+        log::warn!("No robots selected for squad {}", name);
+        return;
+    }
+    for &id in &ui.group {
+        app.squads.assign(id, name);
+    }
+    log::info!(
+        "Squad {} now has robots {:?}",
+        name,
+        app.squads.members(name)
+    );
+    ui.active_squad = Some(name.to_string());
+}
+
+fn command_active_squad(app: &mut App, ui: &mut UiState, command: RobotCommand) {
+    match ui.active_squad.clone() {
+        Some(name) if !app.squads.members(&name).is_empty() => {
+            app.command_squad(&name, command);
+        }
+        _ => {
+            ui.active_squad = None;
+            log::warn!("No active squad to receive {:?}", command);
+        }
+    }
+}
+
+fn assign_patrol(app: &mut App, ui: &mut UiState) {
+    if ui.patrol_draft.is_empty() {
+        log::warn!("Patrol route is empty, add waypoints with 'w' first");
+        return;
+    }
+    ui.retain_robots(&app.robot_ids());
+    let delivered = app.assign_patrol(&ui.command_targets(), &ui.patrol_draft);
+    if delivered == 0 {
+        log::warn!("No explorer selected for the patrol");
+        return;
+    }
+    log::info!(
+        "Patrol of {} waypoints assigned to {} explorers",
+        ui.patrol_draft.len(),
+        delivered
+    );
+    ui.patrol_draft.clear();
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Simulation-screen actions bound to a character key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    Quit,
+    CycleTheme,
+    ToggleMapView,
+    ToggleVision,
+    ToggleStationView,
+    ToggleProfiler,
+    CyclePreset,
+    CycleLayer,
+    FormationLine,
+    FormationColumn,
+    CycleSquad,
+    Recall,
+    Relocate,
+    GatherEnergy,
+    GatherMinerals,
+    AddWaypoint,
+    ClearPatrol,
+    Patrol,
+    StopPatrol,
+    Decommission,
+    Convert,
+    FastForward,
+    MarkPriority,
+    MarkPriorityArea,
+    MarkNoGoArea,
+    Annotate,
+    RestartSameSeeds,
+    RestartNewSeeds,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 28] = [
+        KeyAction::Quit,
+        KeyAction::CycleTheme,
+        KeyAction::ToggleMapView,
+        KeyAction::ToggleVision,
+        KeyAction::ToggleStationView,
+        KeyAction::ToggleProfiler,
+        KeyAction::CyclePreset,
+        KeyAction::CycleLayer,
+        KeyAction::FormationLine,
+        KeyAction::FormationColumn,
+        KeyAction::CycleSquad,
+        KeyAction::Recall,
+        KeyAction::Relocate,
+        KeyAction::GatherEnergy,
+        KeyAction::GatherMinerals,
+        KeyAction::AddWaypoint,
+        KeyAction::ClearPatrol,
+        KeyAction::Patrol,
+        KeyAction::StopPatrol,
+        KeyAction::Decommission,
+        KeyAction::Convert,
+        KeyAction::FastForward,
+        KeyAction::MarkPriority,
+        KeyAction::MarkPriorityArea,
+        KeyAction::MarkNoGoArea,
+        KeyAction::Annotate,
+        KeyAction::RestartSameSeeds,
+        KeyAction::RestartNewSeeds,
+    ];
+
+    pub fn default_key(self) -> char {
+        match self {
+            KeyAction::Quit => 'q',
+            KeyAction::CycleTheme => 't',
+            KeyAction::ToggleMapView => 'b',
+            KeyAction::ToggleVision => 'v',
+            KeyAction::ToggleStationView => 'S',
+            KeyAction::ToggleProfiler => 'T',
+            KeyAction::CyclePreset => 'o',
+            KeyAction::CycleLayer => 'z',
+            KeyAction::FormationLine => 'f',
+            KeyAction::FormationColumn => 'F',
+            KeyAction::CycleSquad => 's',
+            KeyAction::Recall => 'r',
+            KeyAction::Relocate => 'g',
+            KeyAction::GatherEnergy => 'e',
+            KeyAction::GatherMinerals => 'm',
+            KeyAction::AddWaypoint => 'w',
+            KeyAction::ClearPatrol => 'c',
+            KeyAction::Patrol => 'p',
+            KeyAction::StopPatrol => 'P',
+            KeyAction::Decommission => 'x',
+            KeyAction::Convert => 'C',
+            KeyAction::FastForward => '>',
+            KeyAction::MarkPriority => '!',
+            KeyAction::MarkPriorityArea => '#',
+            KeyAction::MarkNoGoArea => 'X',
+            KeyAction::Annotate => 'n',
+            KeyAction::RestartSameSeeds => 'R',
+            KeyAction::RestartNewSeeds => 'N',
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyAction::Quit => "Quit",
+            KeyAction::CycleTheme => "Cycle theme",
+            KeyAction::ToggleMapView => "Braille view",
+            KeyAction::ToggleVision => "Vision overlay",
+            KeyAction::ToggleStationView => "Station view",
+            KeyAction::ToggleProfiler => "Profiler overlay",
+            KeyAction::CyclePreset => "Cycle swarm preset",
+            KeyAction::CycleLayer => "Cycle layer",
+            KeyAction::FormationLine => "Line formation",
+            KeyAction::FormationColumn => "Column formation",
+            KeyAction::CycleSquad => "Cycle squad",
+            KeyAction::Recall => "Recall squad",
+            KeyAction::Relocate => "Relocate squad",
+            KeyAction::GatherEnergy => "Gather energy",
+            KeyAction::GatherMinerals => "Gather minerals",
+            KeyAction::AddWaypoint => "Add waypoint",
+            KeyAction::ClearPatrol => "Clear route",
+            KeyAction::Patrol => "Start patrol",
+            KeyAction::StopPatrol => "Stop patrol",
+            KeyAction::Decommission => "Decommission",
+            KeyAction::Convert => "Convert",
+            KeyAction::FastForward => "Fast-forward",
+            KeyAction::MarkPriority => "Mark priority",
+            KeyAction::MarkPriorityArea => "Mark priority area",
+            KeyAction::MarkNoGoArea => "Mark no-go area",
+            KeyAction::Annotate => "Annotate tile",
+            KeyAction::RestartSameSeeds => "Restart, same seeds",
+            KeyAction::RestartNewSeeds => "Restart, new seeds",
+        }
+    }
+}
+
+/// Character keys of the simulation screen; actions missing from the config file keep
+/// their default key
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeyBindings {
+    keys: BTreeMap<KeyAction, char>,
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: KeyAction) -> char {
+        self.keys
+            .get(&action)
+            .copied()
+            .unwrap_or(action.default_key())
+    }
+
+    pub fn action(&self, key: char) -> Option<KeyAction> {
+        KeyAction::ALL
+            .into_iter()
+            .find(|&action| self.key(action) == key)
+    }
+
+    /// Binds `key` to `action`; the action that had the key takes the action's old key, so
+    /// no key ever triggers two actions
+    pub fn bind(&mut self, action: KeyAction, key: char) -> Result<(), String> {
+        if Self::is_reserved(key) {
+            return Err(format!("'{}' is reserved", key));
+        }
+        let previous = self.key(action);
+        if let Some(other) = self.action(key).filter(|&other| other != action) {
+            self.keys.insert(other, previous);
+        }
+        self.keys.insert(action, key);
+        Ok(())
+    }
+
+    /// Keys with a fixed meaning: squad numbers, group selection and the console
+    fn is_reserved(key: char) -> bool {
+        key == ' ' || key == ':' || ('1'..='3').contains(&key) || key.is_control()
+    }
+
+    /// Checks a hand-edited config file
+    pub fn validate(&self) -> Result<(), String> {
+        for action in KeyAction::ALL {
+            let key = self.key(action);
+            if Self::is_reserved(key) {
+                return Err(format!(
+                    "'{}' is reserved, cannot bind it to {:?}",
+                    key, action
+                ));
+            }
+            if self.action(key) != Some(action) {
+                return Err(format!("'{}' is bound to more than one action", key));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebinding_swaps_conflicting_keys() {
+        let mut keys = KeyBindings::default();
+        assert_eq!(keys.action('q'), Some(KeyAction::Quit));
+
+        keys.bind(KeyAction::Quit, 'r').unwrap();
+        assert_eq!(keys.action('r'), Some(KeyAction::Quit));
+        assert_eq!(keys.key(KeyAction::Recall), 'q');
+        assert!(keys.validate().is_ok());
+
+        assert!(keys.bind(KeyAction::Quit, '2').is_err());
+        assert_eq!(keys.key(KeyAction::Quit), 'r');
+    }
+}
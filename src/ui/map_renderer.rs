@@ -1,90 +1,478 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style, Stylize},
+    style::{Color, Modifier, Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Paragraph},
     Frame,
 };
-use std::collections::HashMap;
 
-use crate::{app::App, communication::channels::ResourceType, map::noise::Map, robot::RobotState};
+use crate::{
+    app::{App, CompletionSummary, MissionOutcome},
+    communication::channels::ResourceType,
+    map::noise::Map,
+    memory::{self, MemoryLevel},
+    profiler::{self, Section, TimingStats},
+    robot::core::knowledge::{RobotKnowledge, TileAccuracy},
+    robot::{registry::RobotType, utils::config, RobotState},
+    ui::{
+        braille_renderer::{layer_name, render_braille_map},
+        keys::{KeyAction, KeyBindings},
+        state::{MapView, UiState},
+        station_view::render_station_view,
+        theme::{RobotGlyph, Theme},
+    },
+};
+
+/// Styled terrain lines kept between frames.
+///
+/// Only tiles whose resources changed since the last frame are restyled; the whole
+/// grid is rebuilt when the terrain or theme changes or the change log can no longer catch up.
+#[derive(Default)]
+pub struct MapRenderCache {
+    lines: Vec<Line<'static>>,
+    resource_counter: u64,
+    terrain_version: u64,
+    theme: Option<Theme>,
+    /// Tiles styled by the last refresh
+    restyled: usize,
+}
+
+impl MapRenderCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Brings the cached lines up to date with `map` and returns them
+    pub fn refresh(&mut self, map: &Map, theme: &Theme) -> &[Line<'static>] {
+        let dirty_tiles = if self.theme.as_ref() == Some(theme)
+            && self.terrain_version == map.terrain_version()
+            && self.lines.len() == map.height
+        {
+            map.resource_changes_since(self.resource_counter)
+        } else {
+            None
+        };
+
+        match dirty_tiles {
+            Some(tiles) => {
+                self.restyled = tiles.len();
+                for (x, y) in tiles {
+                    if let Some(span) = self.lines.get_mut(y).and_then(|line| line.spans.get_mut(x))
+                    {
+                        *span = theme.tile_span(map.tile_symbol(x, y), map.catalog());
+                    }
+                }
+            }
+            None => {
+                log::trace!("Rebuilding map render cache");
+                self.lines = create_styled_lines(map, theme);
+                self.restyled = map.width * map.height;
+            }
+        }
 
-pub fn render_app(frame: &mut Frame, area: Rect, app: &App) {
+        self.resource_counter = map.resource_change_counter();
+        self.terrain_version = map.terrain_version();
+        self.theme = Some(theme.clone());
+        &self.lines
+    }
+}
+
+pub fn render_app(frame: &mut Frame, area: Rect, app: &App, ui: &mut UiState) {
+    let console_rows = u16::from(ui.console.is_some() || ui.console_reply.is_some());
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0)])
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(console_rows),
+            Constraint::Length(1),
+        ])
         .split(area);
 
     let top_area = main_chunks[0];
+    render_console(frame, main_chunks[1], ui);
+    render_status_bar(frame, main_chunks[2], app, ui);
+
+    // A new game may have fewer layers than the one the layer was picked on
+    let layers = app.map.read().layers();
+    ui.layer = ui.layer.min(layers - 1);
 
     let horizontal_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(75), Constraint::Percentage(25)])
         .split(top_area);
 
-    render_map_with_robots(frame, horizontal_chunks[0], app);
-    render_sidebar_statistics(frame, horizontal_chunks[1], app);
+    match ui.map_view {
+        _ if ui.station_view => {
+            ui.map_area = None;
+            render_station_view(frame, horizontal_chunks[0], app)
+        }
+        MapView::Grid => render_map_with_robots(frame, horizontal_chunks[0], app, ui),
+        MapView::Braille => {
+            ui.map_area = None;
+            render_braille_map(frame, horizontal_chunks[0], app, ui.theme(), ui.layer)
+        }
+    }
+    let sidebar_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(COVERAGE_GAUGES + 2), Constraint::Min(0)])
+        .split(horizontal_chunks[1]);
+    render_coverage_gauges(frame, sidebar_chunks[0], app);
+    render_sidebar_statistics(frame, sidebar_chunks[1], app, ui);
+
+    if ui.profiler_overlay {
+        render_profiler_overlay(frame, horizontal_chunks[0], app);
+    }
+    if let Some(summary) = &app.completion_summary {
+        render_completion_summary(frame, horizontal_chunks[0], summary, &ui.keys);
+    }
+}
+
+/// Robots listed in the profiler overlay, the slowest deciders first
+const PROFILED_ROBOTS: usize = 5;
+
+/// Top-right box with where the time went since the profiler was enabled
+fn render_profiler_overlay(frame: &mut Frame, area: Rect, app: &App) {
+    let report = profiler::report();
+    let row = |name: String, stats: TimingStats| {
+        format!(
+            "{:<16}{:>8}{:>9}{:>9}",
+            name,
+            stats.count,
+            stats.mean_us(),
+            stats.max_us
+        )
+    };
+    let mut lines = vec![Line::from(format!(
+        "{:<16}{:>8}{:>9}{:>9}",
+        "", "calls", "avg us", "max us"
+    ))
+    .bold()];
+    for section in Section::ALL {
+        lines.push(Line::from(row(
+            section.label().to_string(),
+            report.section(section),
+        )));
+    }
+    let mut robots: Vec<(u32, TimingStats)> = report.robot_steps.into_iter().collect();
+    robots.sort_by_key(|(id, stats)| (std::cmp::Reverse(stats.mean_us()), *id));
+    lines.push(Line::from(format!("Robot steps ({} robots)", robots.len())).bold());
+    for (id, stats) in robots.into_iter().take(PROFILED_ROBOTS) {
+        let name = match app.robots.robot_type(id) {
+            Some(robot_type) => format!("#{} {:?}", id, robot_type),
+            None => format!("#{} (gone)", id),
+        };
+        lines.push(Line::from(row(name, stats)));
+    }
+
+    let width = (lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width - width,
+        y: area.y,
+        width,
+        height,
+    };
+    let widget =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Profiler "));
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
+}
+
+/// The line being typed in the console, or its answer to the last command
+fn render_console(frame: &mut Frame, area: Rect, ui: &UiState) {
+    let line = match (&ui.console, &ui.console_reply) {
+        (Some(typed), _) => Line::from(format!(":{}_", typed)),
+        (None, Some(Ok(message))) => Line::from(message.as_str()).green(),
+        (None, Some(Err(e))) => Line::from(e.as_str()).red(),
+        (None, None) => return,
+    };
+    frame.render_widget(line, area);
+}
+
+/// Bottom line: the UI mode, the highlighted robot, the sim time and speed, the swarm preset,
+/// whether the run falls behind its tick rate, then the keys that matter in the mode
+fn render_status_bar(frame: &mut Frame, area: Rect, app: &App, ui: &UiState) {
+    let mode = ui.mode();
+    let robot = match ui.selected_robot.and_then(|id| app.robots.get(id)) {
+        Some(entry) => format!(
+            "#{} {:?} {:?}",
+            entry.state.id, entry.robot_type, entry.state.status
+        ),
+        None => "No robot".to_string(),
+    };
+    let time = match app.tick_limit {
+        Some(limit) => format!("Tick {}/{}", app.tick, limit),
+        None => format!("Tick {}", app.tick),
+    };
+    let mut spans = vec![
+        Span::styled(
+            format!(" {} ", mode.label()),
+            Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        ),
+        Span::raw(format!(
+            " {} | {} | x{} | {} |",
+            robot,
+            time,
+            config::time_scale(),
+            app.preset.label()
+        )),
+    ];
+    if let Some(interval) = ui.running_behind {
+        spans.push(Span::styled(
+            format!(" Running behind: {} ms/tick |", interval.as_millis()),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    for (key, label) in mode.hints(&ui.keys) {
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(key, Style::default().fg(Color::Yellow)));
+        spans.push(Span::raw(format!(" {}", label)));
+    }
+    frame.render_widget(Line::from(spans), area);
+}
+
+/// Rows of the coverage panel
+const COVERAGE_GAUGES: u16 = 5;
+
+/// Robots at or below this share of their battery are listed in red
+const LOW_ENERGY_PERCENT: u32 = 25;
+
+/// One gauge per row: share of the map known, of the deposits worked and emptied, and the
+/// stock gathered out of all that was found of each resource
+fn render_coverage_gauges(frame: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default().borders(Borders::ALL).title(" Coverage ");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let coverage = app.knowledge_coverage();
+    let stock_percent = |resource_type: ResourceType| {
+        let stock = app
+            .collected_resources
+            .get(&resource_type)
+            .copied()
+            .unwrap_or(0);
+        let found = stock + coverage.remaining(&resource_type);
+        (u64::from(stock) * 100)
+            .checked_div(u64::from(found))
+            .unwrap_or(0)
+            .min(100) as u16
+    };
+    let gauges = [
+        ("Known", coverage.known_percent(), Color::Cyan),
+        ("Claimed", coverage.claimed_percent(), Color::Yellow),
+        ("Exhausted", coverage.exhausted_percent(), Color::Red),
+        ("Energy", stock_percent(ResourceType::ENERGY), Color::Green),
+        (
+            "Minerals",
+            stock_percent(ResourceType::MINERALS),
+            Color::Magenta,
+        ),
+    ];
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1); COVERAGE_GAUGES as usize])
+        .split(inner);
+    for ((label, percent, color), row) in gauges.into_iter().zip(rows.iter()) {
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(color))
+            .percent(percent)
+            .label(format!("{} {}%", label, percent));
+        frame.render_widget(gauge, *row);
+    }
+}
+
+/// Popup shown over the map once every robot retired
+fn render_completion_summary(
+    frame: &mut Frame,
+    area: Rect,
+    summary: &CompletionSummary,
+    keys: &KeyBindings,
+) {
+    let (title, headline) = match summary.outcome {
+        MissionOutcome::Completed => (
+            " Simulation complete ",
+            "All resources collected, map fully explored.".to_string(),
+        ),
+        MissionOutcome::Failed => (
+            " Mission failed ",
+            "Every robot stopped before the mission was complete.".to_string(),
+        ),
+        MissionOutcome::TimedOut => (
+            " Time is up ",
+            format!("The run stopped at its limit of {} ticks.", summary.ticks),
+        ),
+    };
+    let mut lines = vec![
+        Line::from(headline).bold(),
+        Line::from(""),
+        Line::from(format!(
+            "Explored tiles : {} / {}",
+            summary.explored_tiles, summary.total_tiles
+        )),
+    ];
+    let mut resources: Vec<_> = summary.collected_resources.iter().collect();
+    resources.sort_by_key(|(resource_type, _)| format!("{:?}", resource_type));
+    for (resource_type, amount) in resources {
+        lines.push(Line::from(format!(
+            "{:<15}: {}",
+            format!("{:?}", resource_type),
+            amount
+        )));
+    }
+    lines.push(Line::from(format!(
+        "Science value  : {}",
+        summary.scientific_data
+    )));
+    lines.push(Line::from(format!(
+        "Robots retired : {}",
+        summary.retired_robots
+    )));
+    lines.push(Line::from(format!("Ticks          : {}", summary.ticks)));
+    lines.push(Line::from(format!(
+        "Difficulty     : {:?}",
+        summary.difficulty
+    )));
+    lines.push(Line::from(format!(
+        "Seeds          : map {}, resources {}, robots {}",
+        summary.map_seed, summary.resource_seed, summary.robot_seed
+    )));
+    lines.push(Line::from(""));
+    lines.push(
+        Line::from(format!(
+            "Press {} to quit, Esc for the menu",
+            keys.key(KeyAction::Quit)
+        ))
+        .italic(),
+    );
+    lines.push(
+        Line::from(format!(
+            "{} to replay these seeds, {} for new ones",
+            keys.key(KeyAction::RestartSameSeeds),
+            keys.key(KeyAction::RestartNewSeeds)
+        ))
+        .italic(),
+    );
+
+    let width = lines
+        .iter()
+        .map(|line| line.width() as u16 + 4)
+        .max()
+        .unwrap_or(0)
+        .min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let widget = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
 }
 
 /// Renders the map grid and overlays robot symbols based on their current state.
-fn render_map_with_robots(frame: &mut Frame, area: Rect, app: &App) {
-    let map_guard = app.map.read().expect("Map lock poisoned during render");
+fn render_map_with_robots(frame: &mut Frame, area: Rect, app: &App, ui: &mut UiState) {
+    let map_guard = app.map.read();
 
-    let mut display_lines = create_styled_lines(&map_guard);
+    let theme = ui.theme().clone();
+    let mut display_lines = ui.map_cache.refresh(&map_guard, &theme).to_vec();
+    if let Some(knowledge) = vision_knowledge(app, ui) {
+        overlay_knowledge(display_lines.as_mut_slice(), knowledge, &map_guard);
+    }
+    let rows = map_guard.layer_rows(ui.layer);
+    let hex = map_guard.hex;
+    let title = format!(
+        "Astro Swarm Map ({}) seeds map {} res {} robots {}",
+        layer_name(&map_guard, ui.layer),
+        app.map_seed,
+        app.resource_seed,
+        app.robot_seed()
+    );
     drop(map_guard);
 
     overlay_robots(
         display_lines.as_mut_slice(),
-        &app.scientific_robots,
-        'S',
-        Style::default().fg(Color::Gray),
+        app.robots.states_of(RobotType::Scout),
+        theme.robot_span(RobotGlyph::Scout),
+    );
+    overlay_robots(
+        display_lines.as_mut_slice(),
+        app.robots.states_of(RobotType::Scientific),
+        theme.robot_span(RobotGlyph::Scientist),
     );
     overlay_robots(
         display_lines.as_mut_slice(),
-        &app.collection_robots,
-        'C',
-        Style::default().fg(Color::White),
+        app.robots.states_of(RobotType::Collection),
+        theme.robot_span(RobotGlyph::Collector),
     );
     overlay_robots(
         display_lines.as_mut_slice(),
-        &app.exploration_robots,
-        'X',
-        Style::default().fg(Color::Red),
+        app.robots.states_of(RobotType::Exploration),
+        theme.robot_span(RobotGlyph::Explorer),
     );
+    overlay_hostiles(display_lines.as_mut_slice(), app, theme.hostile_span());
 
-    let map_widget = create_map_widget(display_lines);
+    highlight_selection(display_lines.as_mut_slice(), app, ui);
+
+    // Overlays work on every layer, only the one on screen is kept
+    let mut display_lines: Vec<Line<'static>> = display_lines.drain(rows.clone()).collect();
+    if hex {
+        offset_hex_rows(&mut display_lines, rows.start, theme.cell_width());
+    }
+    let map_widget = create_map_widget(display_lines, title);
+    ui.map_area = Some(Block::default().borders(Borders::ALL).inner(area));
+    ui.map_first_row = rows.start;
+    ui.map_hex = hex;
     frame.render_widget(map_widget, area);
 }
 
-fn overlay_robots(
+/// Knowledge shown by the vision overlay, if it is on and the selected robot has docked once
+fn vision_knowledge<'a>(app: &'a App, ui: &UiState) -> Option<&'a RobotKnowledge> {
+    if !ui.vision_overlay {
+        return None;
+    }
+    ui.selected_robot
+        .and_then(|id| app.robots.get(id))
+        .and_then(|entry| entry.telemetry.last_knowledge.as_ref())
+}
+
+/// Hatches tiles the knowledge does not cover and marks tiles it gets wrong in red
+fn overlay_knowledge(display_lines: &mut [Line<'_>], knowledge: &RobotKnowledge, map: &Map) {
+    for (y, line) in display_lines.iter_mut().enumerate() {
+        for (x, span) in line.spans.iter_mut().enumerate() {
+            match knowledge.accuracy_at(x, y, map) {
+                TileAccuracy::Unknown => {
+                    *span = Span::styled("░", Style::default().fg(Color::DarkGray));
+                }
+                TileAccuracy::Wrong => span.style = span.style.bg(Color::Red),
+                TileAccuracy::Accurate => {}
+            }
+        }
+    }
+}
+
+/// Replaces the tile span under each robot. Lines hold exactly one span per tile, so
+/// spans are indexed by tile x regardless of how many columns a glyph occupies.
+fn overlay_robots<'a>(
     display_lines: &mut [Line<'_>],
-    robots: &HashMap<u32, RobotState>,
-    symbol: char,
-    style: Style,
+    robots: impl Iterator<Item = &'a RobotState>,
+    span: Span<'static>,
 ) {
-    for robot_state in robots.values() {
+    for robot_state in robots {
         // Check Y
         if let Some(line) = display_lines.get_mut(robot_state.y) {
             // Check X
-            if robot_state.x < line.width() {
-                if robot_state.x < line.spans.len() {
-                    line.spans[robot_state.x] = Span::styled(symbol.to_string(), style);
-                } else {
-                    log::warn!(
-                        "Robot {} ({},{}) out of bounds for lne spans (len {})",
-                        robot_state.id,
-                        robot_state.x,
-                        robot_state.y,
-                        line.spans.len()
-                    );
-                }
+            if let Some(tile_span) = line.spans.get_mut(robot_state.x) {
+                *tile_span = span.clone();
             } else {
-                log::trace!(
-                    "Robot {} ({},{}) out of bounds for lne width ({})",
+                log::warn!(
+                    "Robot {} ({},{}) out of bounds for line spans (len {})",
                     robot_state.id,
                     robot_state.x,
                     robot_state.y,
-                    line.width()
+                    line.spans.len()
                 );
             }
         } else {
@@ -99,24 +487,91 @@ fn overlay_robots(
     }
 }
 
-fn render_sidebar_statistics(frame: &mut Frame, area: Rect, app: &App) {
+/// Draws the hostiles over robots, so a robot being drained is hidden by its attacker
+fn overlay_hostiles(display_lines: &mut [Line<'_>], app: &App, span: Span<'static>) {
+    for hostile in app.hostiles.hostiles() {
+        if let Some(tile_span) = display_lines
+            .get_mut(hostile.y)
+            .and_then(|line| line.spans.get_mut(hostile.x))
+        {
+            *tile_span = span.clone();
+        }
+    }
+}
+
+/// Marks the cursor tile and the robots selected for group commands
+fn highlight_selection(display_lines: &mut [Line<'_>], app: &App, ui: &UiState) {
+    let mut patch = |(x, y): (usize, usize), style: Style| {
+        if let Some(span) = display_lines
+            .get_mut(y)
+            .and_then(|line| line.spans.get_mut(x))
+        {
+            span.style = span.style.patch(style);
+        }
+    };
+
+    for point in app.priorities.tiles() {
+        patch(point, Style::default().bg(Color::Magenta));
+    }
+    for point in app.exclusions.tiles() {
+        patch(point, Style::default().bg(Color::Red));
+    }
+    for annotation in app.annotations() {
+        patch(
+            (annotation.x, annotation.y),
+            Style::default().bg(Color::Cyan),
+        );
+    }
+    for &point in &ui.patrol_draft {
+        patch(point, Style::default().bg(Color::Yellow));
+    }
+    for robot in ui.group.iter().filter_map(|&id| app.robot_state(id)) {
+        patch((robot.x, robot.y), Style::default().bg(Color::Blue));
+    }
+    if let Some(robot) = ui.selected_robot.and_then(|id| app.robot_state(id)) {
+        patch(
+            (robot.x, robot.y),
+            Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD),
+        );
+    }
+    patch(ui.cursor, Style::default().add_modifier(Modifier::REVERSED));
+}
+
+fn render_sidebar_statistics(frame: &mut Frame, area: Rect, app: &App, ui: &UiState) {
     let mut items = Vec::new();
 
+    for alarm in app.alarms.active() {
+        items.push(ListItem::new(
+            Line::from(format!("ALARM: {}", app.alarms.describe(alarm)))
+                .red()
+                .bold(),
+        ));
+    }
     items.push(ListItem::new(Line::from("--- Totals ---").bold()));
+    items.push(ListItem::new(format!("Difficulty: {:?}", app.difficulty)));
+    let kpis = app.live_kpis();
+    items.push(ListItem::new(
+        Line::from(format!("Efficiency: {:.1} / 100 energy", kpis.efficiency))
+            .cyan()
+            .bold(),
+    ));
+    items.push(ListItem::new(format!(
+        "  Delivering {:.1}, exploring {:.1} tiles / 100 ticks",
+        kpis.delivery_rate, kpis.exploration_velocity
+    )));
 
     items.push(ListItem::new("Collected Resources:"));
 
     let mut sorted_resources: Vec<_> = app.collected_resources.iter().collect();
-    sorted_resources.sort_by_key(|(k, _)| format!("{:?}", k));
+    sorted_resources.sort_by_key(|(k, _)| app.resource_catalog().name(**k).to_string());
 
     if sorted_resources.is_empty() {
         items.push(ListItem::new(Line::from("  None yet").italic()));
     } else {
         for (resource_type, amount) in sorted_resources {
-            let resource_name = match resource_type {
-                ResourceType::Energy => "Energy",
-                ResourceType::Minerals => "Minerals",
-                ResourceType::SciencePoints => "Science Pts (Raw)",
+            let resource_name = match *resource_type {
+                ResourceType::SCIENCE_POINTS => "Science Pts (Raw)",
+                resource_type => app.resource_catalog().name(resource_type),
             };
             items.push(ListItem::new(format!("  {}: {}", resource_name, amount)));
         }
@@ -127,21 +582,178 @@ fn render_sidebar_statistics(frame: &mut Frame, area: Rect, app: &App) {
         "Total Science Value: {}",
         app.scientific_data
     )));
+    if app.drained_energy > 0 {
+        items.push(ListItem::new(format!(
+            "Station upkeep: -{} energy",
+            app.drained_energy
+        )));
+    }
     items.push(ListItem::new(format!(
         "Explored Tiles: {} / {}",
         app.total_explored,
         app.map_width * app.map_height
     )));
+    items.push(ListItem::new(format!(
+        "Events: {} (latency avg {:.1} / max {} ticks)",
+        app.event_stats.received,
+        app.event_stats.average_latency_ticks(),
+        app.event_stats.max_latency_ticks
+    )));
+    if app.event_stats.out_of_order + app.event_stats.missing > 0 {
+        items.push(ListItem::new(
+            Line::from(format!(
+                "  {} out of order, {} missing",
+                app.event_stats.out_of_order, app.event_stats.missing
+            ))
+            .red(),
+        ));
+    }
+    if let Some(until) = app.storm_until {
+        items.push(ListItem::new(
+            Line::from(format!(
+                "Storm: {} ticks left",
+                until.saturating_sub(app.tick)
+            ))
+            .yellow(),
+        ));
+    }
+    if let Some(until) = app.solar_flare_until {
+        items.push(ListItem::new(
+            Line::from(format!(
+                "Solar flare: {} ticks left, radio down",
+                until.saturating_sub(app.tick)
+            ))
+            .light_red()
+            .bold(),
+        ));
+    } else if let Some(ticks) = app.solar_flare_warning() {
+        items.push(ListItem::new(
+            Line::from(format!("Solar flare in {} ticks", ticks)).light_red(),
+        ));
+    }
+    if !app.hostiles.hostiles().is_empty() {
+        items.push(ListItem::new(
+            Line::from(format!("Dust devils: {}", app.hostiles.hostiles().len())).magenta(),
+        ));
+        let entities = app.known_entities();
+        if let Some(latest) = entities.hostiles().map(|sighting| sighting.tick).max() {
+            items.push(ListItem::new(format!(
+                "  Sighted: {} (tick {})",
+                entities.hostiles().count(),
+                latest
+            )));
+        }
+    }
+    if app.meteor_strikes > 0 {
+        items.push(ListItem::new(format!(
+            "Meteor strikes: {}",
+            app.meteor_strikes
+        )));
+    }
+    if let Some(tick) = app.next_world_event_tick() {
+        items.push(ListItem::new(format!("Next world event: tick {}", tick)));
+    }
+    let commands = app.command_tracker.stats;
+    let command_line = Line::from(format!(
+        "Commands: {} acked, {} rejected, {} failed ({} retries)",
+        commands.acked, commands.rejected, commands.failed, commands.retried
+    ));
+    items.push(ListItem::new(if commands.rejected + commands.failed > 0 {
+        command_line.yellow()
+    } else {
+        command_line
+    }));
+    if let Some((id, tick)) = app.last_merge_abandoned {
+        items.push(ListItem::new(
+            Line::from(format!(
+                "Merges abandoned: {} (last #{} at tick {})",
+                app.merges_abandoned, id, tick
+            ))
+            .yellow(),
+        ));
+    }
+    let memory_line = Line::from(format!(
+        "Memory: {} ({} knowledge, {} queues, {} stats) of {} MB",
+        memory::format_mb(app.memory.total()),
+        memory::format_mb(app.memory.knowledge),
+        memory::format_mb(app.memory.queues),
+        memory::format_mb(app.memory.stats),
+        app.memory_limit.limit_mb
+    ));
+    items.push(ListItem::new(match app.memory_level {
+        MemoryLevel::Fine => memory_line,
+        MemoryLevel::Near => memory_line.yellow(),
+        MemoryLevel::Over => memory_line.red().bold(),
+    }));
+    if app.retiring {
+        items.push(ListItem::new(
+            Line::from("Map exhausted, retiring robots").yellow().bold(),
+        ));
+    } else if app.exploration_complete {
+        items.push(ListItem::new(
+            Line::from("Exploration complete").green().bold(),
+        ));
+    }
     items.push(ListItem::new(""));
 
     // --- Robots Section ---
     items.push(ListItem::new(Line::from("--- Robots ---").bold()));
-    let exploration_count = app.exploration_robots.len();
-    let collection_count = app.collection_robots.len();
-    let scientific_count = app.scientific_robots.len();
-    let total_robots = exploration_count + collection_count + scientific_count;
+    let exploration_count = app.robots.count_of(RobotType::Exploration);
+    let collection_count = app.robots.count_of(RobotType::Collection);
+    let scientific_count = app.robots.count_of(RobotType::Scientific);
+    let scout_count = app.robots.count_of(RobotType::Scout);
+    let total_robots = app.robots.len();
 
     items.push(ListItem::new(format!("Active: {}", total_robots)));
+    items.push(ListItem::new(format!(
+        "Idle: {} (reassigned {})",
+        app.idle_robot_count(),
+        app.idle_reassignments
+    )));
+    if app.decommissioned_robots > 0 {
+        items.push(ListItem::new(format!(
+            "Decommissioned: {} (+{} minerals)",
+            app.decommissioned_robots, app.refunded_minerals
+        )));
+    }
+    if let Some(budget) = app.merge_bandwidth() {
+        items.push(ListItem::new(format!(
+            "Bandwidth: {} tiles/dock, {} held back",
+            budget,
+            app.deferred_deltas()
+        )));
+    }
+    if !app.priorities.is_empty() {
+        items.push(ListItem::new(format!(
+            "Priorities: {} tiles",
+            app.priorities.len()
+        )));
+    }
+    if !app.exclusions.is_empty() {
+        items.push(ListItem::new(format!(
+            "No-go: {} tiles",
+            app.exclusions.len()
+        )));
+    }
+    if app.maintenance_services > 0 || app.maintenance.in_maintenance() > 0 {
+        items.push(ListItem::new(format!(
+            "Maintenance: {} now ({} serviced)",
+            app.maintenance.in_maintenance(),
+            app.maintenance_services
+        )));
+    }
+    if app.battery_refits + app.worn_out_robots > 0 {
+        items.push(ListItem::new(format!(
+            "Batteries: {} refitted, {} worn out",
+            app.battery_refits, app.worn_out_robots
+        )));
+    }
+    if !app.parked_robots.is_empty() {
+        items.push(ListItem::new(format!(
+            "Parked: {}",
+            app.parked_robots.len()
+        )));
+    }
 
     items.push(ListItem::new(Line::from(vec![
         Span::raw("  Explorers : "),
@@ -164,42 +776,338 @@ fn render_sidebar_statistics(frame: &mut Frame, area: Rect, app: &App) {
             Style::default().fg(Color::Cyan).bold(),
         ),
     ])));
+    if scout_count > 0 {
+        items.push(ListItem::new(Line::from(vec![
+            Span::raw("  Scouts    : "),
+            Span::styled(
+                scout_count.to_string(),
+                Style::default().fg(Color::Yellow).bold(),
+            ),
+        ])));
+    }
+    if app.shape.cave_layers > 0 {
+        let drills = app
+            .robots
+            .iter()
+            .filter(|entry| entry.telemetry.drill)
+            .count();
+        items.push(ListItem::new(format!("  Drills    : {}", drills)));
+    }
+    {
+        let map = app.map.read();
+        for layer in 1..map.layers() {
+            let underground = app
+                .robots
+                .iter()
+                .filter(|entry| map.layer_of(entry.state.y) == layer)
+                .count();
+            items.push(ListItem::new(format!(
+                "  Cave {}    : {}",
+                layer, underground
+            )));
+        }
+    }
+    if let Some(energy) = app.energy_summary() {
+        items.push(ListItem::new(format!(
+            "Energy: min {} / avg {}",
+            energy.min, energy.average
+        )));
+    }
+    for entry in app.robots.iter() {
+        let state = &entry.state;
+        let line = Line::from(format!(
+            "  #{} {:?}: {}/{} ({:?})",
+            state.id, entry.robot_type, state.energy, state.max_energy, state.status
+        ));
+        items.push(ListItem::new(
+            if state.energy * 100 <= state.max_energy * LOW_ENERGY_PERCENT {
+                line.red()
+            } else {
+                line
+            },
+        ));
+    }
 
-    let stats_list =
-        List::new(items).block(Block::default().borders(Borders::ALL).title(" Statistics "));
+    items.push(ListItem::new(""));
+    items.push(ListItem::new(Line::from("--- Knowledge ---").bold()));
+    let station = app.station_divergence();
+    items.push(ListItem::new(format!(
+        "Station: {} known, {} wrong ({}%)",
+        station.known,
+        station.wrong,
+        station.wrong_percent()
+    )));
+    for (id, divergence) in app.robot_divergences() {
+        items.push(ListItem::new(format!(
+            "  #{}: {} known, {} wrong ({}%)",
+            id,
+            divergence.known,
+            divergence.wrong,
+            divergence.wrong_percent()
+        )));
+    }
+
+    items.push(ListItem::new(""));
+    items.push(ListItem::new(Line::from("--- Selection ---").bold()));
+    items.push(ListItem::new(format!(
+        "Cursor: ({}, {})",
+        ui.cursor.0, ui.cursor.1
+    )));
+    if let Some(annotation) = app
+        .annotations()
+        .into_iter()
+        .find(|annotation| (annotation.x, annotation.y) == ui.cursor)
+    {
+        items.push(ListItem::new(format!("  Note: {}", annotation.text)).cyan());
+    }
+    items.push(ListItem::new(
+        match ui.selected_robot.and_then(|id| app.robot_state(id)) {
+            Some(robot) => format!(
+                "Selected: #{}, battery {}% ({} cycles), wear {}%",
+                robot.id,
+                robot.max_energy * 100 / robot.rated_energy.max(1),
+                robot.charge_cycles,
+                robot.wear_percent
+            ),
+            None => "Selected: none (Tab)".to_string(),
+        },
+    ));
+    if let Some(entry) = ui.selected_robot.and_then(|id| app.robots.get(id)) {
+        let inbox = entry.telemetry.inbox.report();
+        let line = format!(
+            "  Inbox: {} cmd, {} merge, last {}",
+            inbox.commands,
+            inbox.merges,
+            inbox.last_received.unwrap_or("none")
+        );
+        items.push(if inbox.merge_timeouts > 0 {
+            ListItem::new(format!("{}, {} merge timeouts", line, inbox.merge_timeouts)).yellow()
+        } else {
+            ListItem::new(line)
+        });
+    }
+    let group: Vec<String> = ui.group.iter().map(|id| format!("#{}", id)).collect();
+    items.push(ListItem::new(format!("Group: {}", group.join(" "))));
+    if !ui.patrol_draft.is_empty() {
+        items.push(ListItem::new(format!(
+            "Patrol draft: {} waypoints",
+            ui.patrol_draft.len()
+        )));
+    }
+    items.push(ListItem::new(format!("Patrolling: {}", app.patrols.len())));
+    if ui.vision_overlay {
+        items.push(ListItem::new(match ui.selected_robot {
+            Some(id)
+                if app
+                    .robots
+                    .get(id)
+                    .is_some_and(|entry| entry.telemetry.last_knowledge.is_some()) =>
+            {
+                format!("Vision: #{} at last dock", id)
+            }
+            Some(id) => format!("Vision: #{} not docked yet", id),
+            None => "Vision: select a robot".to_string(),
+        }));
+    }
+    if let Some(formation) = &app.formation {
+        items.push(ListItem::new(format!(
+            "Formation: {:?} -> ({}, {})",
+            formation.formation, formation.target.0, formation.target.1
+        )));
+    }
+
+    items.push(ListItem::new(""));
+    items.push(ListItem::new(Line::from("--- Squads ---").bold()));
+    let summaries = app.squad_summaries();
+    if summaries.is_empty() {
+        items.push(ListItem::new(Line::from("  None (1-3 assign)").italic()));
+    }
+    for summary in summaries {
+        let marker = if ui.active_squad.as_deref() == Some(summary.name.as_str()) {
+            ">"
+        } else {
+            " "
+        };
+        items.push(ListItem::new(format!(
+            "{}{}: {} ({}E {}C {}S {}R) {}%",
+            marker,
+            summary.name,
+            summary.size(),
+            summary.explorers,
+            summary.collectors,
+            summary.scientists,
+            summary.scouts,
+            summary.energy_percent
+        )));
+    }
+
+    items.push(ListItem::new(""));
+    items.push(ListItem::new(Line::from("--- Legend ---").bold()));
+    let resources = app.resource_catalog();
+    for resource_type in resources.all() {
+        let spec = resources.spec(resource_type);
+        let role = if spec.consumable {
+            "collected"
+        } else {
+            "analyzed"
+        };
+        items.push(ListItem::new(Line::from(vec![
+            Span::raw("  "),
+            ui.theme().tile_span(spec.symbol, resources),
+            Span::raw(format!(" {} ({}, value {})", spec.name, role, spec.value)),
+        ])));
+    }
+
+    let block = Block::default().borders(Borders::ALL).title(" Statistics ");
+    let stats_list = List::new(items).block(if app.alarms.is_raised() {
+        block.border_style(Style::default().fg(Color::Red))
+    } else {
+        block
+    });
 
     frame.render_widget(stats_list, area);
 }
 
-fn create_styled_lines(map: &Map) -> Vec<Line<'static>> {
-    map.to_string().lines().map(create_styled_line).collect()
+fn create_styled_lines(map: &Map, theme: &Theme) -> Vec<Line<'static>> {
+    (0..map.height)
+        .map(|y| {
+            (0..map.width)
+                .map(|x| theme.tile_span(map.tile_symbol(x, y), map.catalog()))
+                .collect::<Vec<_>>()
+                .into()
+        })
+        .collect()
 }
 
-fn create_styled_line(line_str: &str) -> Line<'static> {
-    line_str
-        .chars()
-        .map(create_styled_span)
-        .collect::<Vec<_>>()
-        .into()
+/// Lays hex tiles out two columns wide and shifts odd rows by one column, half a tile, so
+/// each tile sits between its neighbors on the rows above and below. `first_row` is the map
+/// row of the first line.
+fn offset_hex_rows(lines: &mut [Line<'static>], first_row: usize, cell_width: usize) {
+    for (index, line) in lines.iter_mut().enumerate() {
+        if cell_width < 2 {
+            for span in line.spans.iter_mut() {
+                span.content = format!("{} ", span.content).into();
+            }
+        }
+        if (first_row + index) % 2 == 1 {
+            line.spans.insert(0, Span::raw(" "));
+        }
+    }
 }
 
-fn create_styled_span(c: char) -> Span<'static> {
-    let style = match c {
-        '█' => Style::default().fg(Color::Gray),
-        ' ' => Style::default().fg(Color::Rgb(50, 50, 50)),
-        'E' => Style::default().fg(Color::Yellow),
-        'M' => Style::default().fg(Color::Blue),
-        'S' => Style::default().fg(Color::Green),
-        '⌂' => Style::default().fg(Color::Indexed(208)),
-        _ => Style::default().fg(Color::White),
-    };
-    Span::styled(c.to_string(), style)
+fn create_map_widget(lines: Vec<Line<'static>>, title: String) -> Paragraph<'static> {
+    Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title))
 }
 
-fn create_map_widget(lines: Vec<Line<'static>>) -> Paragraph<'static> {
-    Paragraph::new(lines).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Astro Swarm Map"),
-    )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::robot::core::state::RobotStatus;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_cache_restyles_only_changed_tiles() {
+        let mut map = Map::new(12, 8, 7);
+        map.set_walkable(0, 0);
+        let theme = Theme::classic();
+        let mut cache = MapRenderCache::new();
+        cache.refresh(&map, &theme);
+        assert_eq!(cache.restyled, 12 * 8);
+
+        let counter = map.resource_change_counter();
+        map.add_resource(0, 0, ResourceType::MINERALS, 40);
+        assert_eq!(map.resource_changes_since(counter), Some(vec![(0, 0)]));
+        let lines = cache.refresh(&map, &theme);
+        assert_eq!(lines[0].spans[0].content, "M");
+        assert_eq!(cache.restyled, 1);
+
+        // Nothing changed, nothing restyled
+        cache.refresh(&map, &theme);
+        assert_eq!(cache.restyled, 0);
+
+        map.remove_resource(0, 0);
+        let lines = cache.refresh(&map, &theme);
+        assert_eq!(lines[0].spans[0].content, " ");
+        assert_eq!(cache.restyled, 1);
+
+        let rebuilt = create_styled_lines(&map, &theme);
+        assert_eq!(cache.refresh(&map, &theme), rebuilt.as_slice());
+    }
+
+    #[test]
+    fn test_robot_overlay_with_wide_glyphs_keeps_line_width() {
+        let map = Map::new(10, 6, 3);
+        let theme = Theme::emoji();
+        let mut lines = create_styled_lines(&map, &theme);
+        let expected_width = lines[2].width();
+
+        let robots = [RobotState::new(0, 9, 2, RobotStatus::Exploring, 10)];
+        overlay_robots(
+            &mut lines,
+            robots.iter(),
+            theme.robot_span(RobotGlyph::Explorer),
+        );
+
+        assert_eq!(lines[2].width(), expected_width);
+        assert_eq!(lines[2].spans[9], theme.robot_span(RobotGlyph::Explorer));
+    }
+
+    #[test]
+    fn test_completion_summary_fits_small_areas() {
+        let summary = CompletionSummary {
+            outcome: MissionOutcome::Completed,
+            ticks: 1200,
+            explored_tiles: 80,
+            total_tiles: 100,
+            collected_resources: HashMap::from([(ResourceType::ENERGY, 40)]),
+            scientific_data: 7,
+            retired_robots: 3,
+            difficulty: Difficulty::Normal,
+            map_seed: 34,
+            resource_seed: 45,
+            robot_seed: 34,
+        };
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(20, 6))
+            .expect("test terminal");
+        terminal
+            .draw(|frame| {
+                render_completion_summary(frame, frame.area(), &summary, &KeyBindings::default())
+            })
+            .expect("summary renders");
+    }
+
+    #[test]
+    fn test_completion_summary_reports_the_seeds() {
+        let summary = CompletionSummary {
+            outcome: MissionOutcome::Completed,
+            ticks: 1200,
+            explored_tiles: 80,
+            total_tiles: 100,
+            collected_resources: HashMap::new(),
+            scientific_data: 7,
+            retired_robots: 3,
+            difficulty: Difficulty::Normal,
+            map_seed: 34,
+            resource_seed: 45,
+            robot_seed: 34,
+        };
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 20))
+            .expect("test terminal");
+        terminal
+            .draw(|frame| {
+                render_completion_summary(frame, frame.area(), &summary, &KeyBindings::default())
+            })
+            .expect("summary renders");
+
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(text.contains("map 34, resources 45, robots 34"));
+        assert!(text.contains("R to replay these seeds, N for new ones"));
+    }
 }
@@ -0,0 +1,189 @@
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::Rect,
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Entries of the main menu, in display order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    /// Back to the run in progress, only listed while there is one
+    Resume,
+    NewGame,
+    Load,
+    Replay,
+    Settings,
+    Quit,
+}
+
+impl MenuItem {
+    fn label(self) -> &'static str {
+        match self {
+            MenuItem::Resume => "Resume",
+            MenuItem::NewGame => "New Game",
+            MenuItem::Load => "Load",
+            MenuItem::Replay => "Replay",
+            MenuItem::Settings => "Settings",
+            MenuItem::Quit => "Quit",
+        }
+    }
+
+    /// Entries listed for screens that do not exist yet
+    fn is_available(self) -> bool {
+        !matches!(self, MenuItem::Load | MenuItem::Replay)
+    }
+}
+
+/// What the menu asks the main loop to do after a key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    None,
+    Open(MenuItem),
+}
+
+/// Menu shown at startup, and over the simulation when the player presses Esc
+pub struct MainMenu {
+    items: Vec<MenuItem>,
+    selected: usize,
+    /// Why the last selection was refused
+    message: Option<String>,
+}
+
+impl MainMenu {
+    /// `in_game` adds the Resume entry, selected first
+    pub fn new(in_game: bool) -> Self {
+        let mut items = vec![
+            MenuItem::NewGame,
+            MenuItem::Load,
+            MenuItem::Replay,
+            MenuItem::Settings,
+            MenuItem::Quit,
+        ];
+        if in_game {
+            items.insert(0, MenuItem::Resume);
+        }
+        Self {
+            items,
+            selected: 0,
+            message: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> MenuAction {
+        let Event::Key(key) = event else {
+            return MenuAction::None;
+        };
+        if key.kind != KeyEventKind::Press {
+            return MenuAction::None;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::BackTab => {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len()
+            }
+            KeyCode::Down | KeyCode::Tab => self.selected = (self.selected + 1) % self.items.len(),
+            KeyCode::Enter => return self.open(self.items[self.selected]),
+            KeyCode::Char('q') => return MenuAction::Open(MenuItem::Quit),
+            KeyCode::Esc if self.items.contains(&MenuItem::Resume) => {
+                return MenuAction::Open(MenuItem::Resume)
+            }
+            _ => {}
+        }
+        MenuAction::None
+    }
+
+    fn open(&mut self, item: MenuItem) -> MenuAction {
+        if !item.is_available() {
+            self.message = Some(format!("{} is not available yet", item.label()));
+            return MenuAction::None;
+        }
+        MenuAction::Open(item)
+    }
+}
+
+/// Draws the menu centered in `area`
+pub fn render_menu(frame: &mut Frame, area: Rect, menu: &MainMenu) {
+    let mut lines = vec![Line::from("")];
+    for (index, item) in menu.items.iter().enumerate() {
+        let label = format!("{:^16}", item.label());
+        let line = if index == menu.selected {
+            Line::from(label).bold().reversed()
+        } else if item.is_available() {
+            Line::from(label)
+        } else {
+            Line::from(label).dim()
+        };
+        lines.push(line.centered());
+    }
+    lines.push(Line::from(""));
+    if let Some(message) = &menu.message {
+        lines.push(Line::from(message.as_str()).red().centered());
+    }
+    lines.push(Line::from("Up/Down select, Enter open").italic().centered());
+
+    let width = lines
+        .iter()
+        .map(|line| line.width() as u16 + 4)
+        .max()
+        .unwrap_or(0)
+        .max(24)
+        .min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Astro Swarm "),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+
+    fn press(menu: &mut MainMenu, code: KeyCode) -> MenuAction {
+        menu.handle_event(&Event::Key(KeyEvent::from(code)))
+    }
+
+    #[test]
+    fn test_menu_opens_available_entries_only() {
+        let mut menu = MainMenu::new(false);
+        assert_eq!(press(&mut menu, KeyCode::Esc), MenuAction::None);
+        assert_eq!(
+            press(&mut menu, KeyCode::Enter),
+            MenuAction::Open(MenuItem::NewGame)
+        );
+
+        press(&mut menu, KeyCode::Down);
+        assert_eq!(press(&mut menu, KeyCode::Enter), MenuAction::None);
+        assert!(menu.message.is_some());
+
+        press(&mut menu, KeyCode::Up);
+        press(&mut menu, KeyCode::Up);
+        assert_eq!(
+            press(&mut menu, KeyCode::Enter),
+            MenuAction::Open(MenuItem::Quit)
+        );
+
+        let mut menu = MainMenu::new(true);
+        assert_eq!(
+            press(&mut menu, KeyCode::Enter),
+            MenuAction::Open(MenuItem::Resume)
+        );
+        assert_eq!(
+            press(&mut menu, KeyCode::Esc),
+            MenuAction::Open(MenuItem::Resume)
+        );
+    }
+}
@@ -1 +1,22 @@
+#[cfg(feature = "tui")]
+pub mod braille_renderer;
+#[cfg(feature = "tui")]
+pub mod comparison;
+#[cfg(feature = "tui")]
+pub mod input;
+pub mod keys;
+#[cfg(feature = "tui")]
 pub mod map_renderer;
+#[cfg(feature = "tui")]
+pub mod menu;
+pub mod notifications;
+#[cfg(feature = "tui")]
+pub mod settings_screen;
+#[cfg(feature = "tui")]
+pub mod setup;
+#[cfg(feature = "tui")]
+pub mod state;
+#[cfg(feature = "tui")]
+pub mod station_view;
+#[cfg(feature = "tui")]
+pub mod theme;
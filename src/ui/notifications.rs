@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::communication::{bus::EventSubscriber, channels::RobotEvent};
+
+/// Minimum time between two bells, so a burst of events rings only once
+const BELL_COOLDOWN: Duration = Duration::from_secs(1);
+
+/// Critical situations that can ring the terminal bell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// A robot thread stopped
+    RobotDisabled,
+    /// A robot reported it is running out of energy
+    LowEnergy,
+    /// An alarm on the swarm's health went off
+    SwarmAlarm,
+    /// The run ended, whatever the outcome
+    MissionComplete,
+    /// The station's energy stock fell under its threshold
+    StationEnergyLow,
+}
+
+impl NotificationKind {
+    fn from_event(event: &RobotEvent) -> Option<Self> {
+        match event {
+            RobotEvent::Shutdown { .. } => Some(Self::RobotDisabled),
+            RobotEvent::LowEnergy { .. } => Some(Self::LowEnergy),
+            RobotEvent::SwarmAlarm { raised: true, .. } => Some(Self::SwarmAlarm),
+            RobotEvent::MissionComplete { .. } => Some(Self::MissionComplete),
+            RobotEvent::StationEnergyLow { .. } => Some(Self::StationEnergyLow),
+            _ => None,
+        }
+    }
+}
+
+/// Which notification kinds are enabled, loaded from the settings' `[notifications]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: BTreeSet<NotificationKind>,
+    /// Energy in stock under which the station runs low, 0 never
+    pub station_energy_low: u32,
+}
+
+impl NotificationConfig {
+    pub fn is_enabled(&self, kind: NotificationKind) -> bool {
+        self.enabled.contains(&kind)
+    }
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: BTreeSet::from([
+                NotificationKind::RobotDisabled,
+                NotificationKind::SwarmAlarm,
+                NotificationKind::MissionComplete,
+                NotificationKind::StationEnergyLow,
+            ]),
+            station_energy_low: 20,
+        }
+    }
+}
+
+/// Event bus subscriber ringing the terminal bell on critical events
+pub struct BellNotifier<W: Write> {
+    config: NotificationConfig,
+    output: W,
+    last_bell: Option<Instant>,
+}
+
+impl BellNotifier<io::Stdout> {
+    pub fn stdout(config: NotificationConfig) -> Self {
+        Self::new(config, io::stdout())
+    }
+}
+
+impl<W: Write> BellNotifier<W> {
+    pub fn new(config: NotificationConfig, output: W) -> Self {
+        Self {
+            config,
+            output,
+            last_bell: None,
+        }
+    }
+
+    fn ring(&mut self, kind: NotificationKind) {
+        if self
+            .last_bell
+            .is_some_and(|last| last.elapsed() < BELL_COOLDOWN)
+        {
+            return;
+        }
+        log::debug!("Ringing bell for {:?}", kind);
+        if let Err(e) = self
+            .output
+            .write_all(b"\x07")
+            .and_then(|_| self.output.flush())
+        {
+            log::warn!("Failed to ring terminal bell: {}", e);
+        }
+        self.last_bell = Some(Instant::now());
+    }
+}
+
+impl<W: Write> EventSubscriber for BellNotifier<W> {
+    fn on_event(&mut self, event: &RobotEvent) {
+        if let Some(kind) = NotificationKind::from_event(event) {
+            if self.config.is_enabled(kind) {
+                self.ring(kind);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::MissionOutcome, communication::channels::ShutdownReason};
+
+    #[test]
+    fn test_bell_rings_only_for_enabled_kinds() {
+        let mut notifier = BellNotifier::new(NotificationConfig::default(), Vec::new());
+
+        notifier.on_event(&RobotEvent::LowEnergy {
+            id: 1,
+            remaining: 5,
+        });
+        assert!(notifier.output.is_empty());
+
+        notifier.on_event(&RobotEvent::Shutdown {
+            id: 1,
+            reason: ShutdownReason::LoopExited,
+        });
+        assert_eq!(notifier.output, b"\x07");
+
+        // Cooldown swallows the second bell
+        notifier.on_event(&RobotEvent::Shutdown {
+            id: 2,
+            reason: ShutdownReason::LoopExited,
+        });
+        assert_eq!(notifier.output, b"\x07");
+    }
+
+    #[test]
+    fn test_notifications_are_configured_per_kind() {
+        let config: NotificationConfig =
+            toml::from_str("enabled = [\"mission_complete\"]").unwrap();
+        assert_eq!(config.station_energy_low, 20);
+        let mut notifier = BellNotifier::new(config, Vec::new());
+
+        notifier.on_event(&RobotEvent::StationEnergyLow { stock: 3 });
+        assert!(notifier.output.is_empty());
+        notifier.on_event(&RobotEvent::MissionComplete {
+            outcome: MissionOutcome::Completed,
+        });
+        assert_eq!(notifier.output, b"\x07");
+    }
+}
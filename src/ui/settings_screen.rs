@@ -0,0 +1,244 @@
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::Rect,
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{
+    settings::{InterfaceSettings, Settings},
+    ui::{keys::KeyAction, theme::Theme},
+};
+
+/// Rows of the settings screen, in display order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsField {
+    Theme,
+    MapSeed,
+    ResourceSeed,
+    TickRate,
+    Key(KeyAction),
+}
+
+impl SettingsField {
+    fn all() -> Vec<SettingsField> {
+        let mut fields = vec![
+            SettingsField::Theme,
+            SettingsField::MapSeed,
+            SettingsField::ResourceSeed,
+            SettingsField::TickRate,
+        ];
+        fields.extend(KeyAction::ALL.map(SettingsField::Key));
+        fields
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SettingsField::Theme => "Theme",
+            SettingsField::MapSeed => "Default map seed",
+            SettingsField::ResourceSeed => "Default res. seed",
+            SettingsField::TickRate => "Tick rate (ms)",
+            SettingsField::Key(action) => action.label(),
+        }
+    }
+}
+
+/// What the settings screen asks the main loop to do after a key press
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsAction {
+    None,
+    /// Leave the screen, saving and applying these settings
+    Save(Box<Settings>),
+}
+
+/// Screen editing the interface settings, opened from the main menu
+pub struct SettingsScreen {
+    settings: Settings,
+    fields: Vec<SettingsField>,
+    selected: usize,
+    /// Key row waiting for the key to bind
+    capturing: bool,
+    /// Why the last change was refused
+    error: Option<String>,
+}
+
+impl SettingsScreen {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            fields: SettingsField::all(),
+            selected: 0,
+            capturing: false,
+            error: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> SettingsAction {
+        let Event::Key(key) = event else {
+            return SettingsAction::None;
+        };
+        if key.kind != KeyEventKind::Press {
+            return SettingsAction::None;
+        }
+        if self.capturing {
+            self.capturing = false;
+            if let (SettingsField::Key(action), KeyCode::Char(key)) = (self.field(), key.code) {
+                self.error = self.interface().keys.bind(action, key).err();
+            }
+            return SettingsAction::None;
+        }
+        match key.code {
+            KeyCode::Esc => return SettingsAction::Save(Box::new(self.settings.clone())),
+            KeyCode::Up | KeyCode::BackTab => {
+                self.selected = (self.selected + self.fields.len() - 1) % self.fields.len()
+            }
+            KeyCode::Down | KeyCode::Tab => self.selected = (self.selected + 1) % self.fields.len(),
+            KeyCode::Left | KeyCode::Char('-') => self.adjust(-1),
+            KeyCode::Right | KeyCode::Char('+') => self.adjust(1),
+            KeyCode::Enter if matches!(self.field(), SettingsField::Key(_)) => {
+                self.error = None;
+                self.capturing = true;
+            }
+            _ => {}
+        }
+        SettingsAction::None
+    }
+
+    fn field(&self) -> SettingsField {
+        self.fields[self.selected]
+    }
+
+    fn interface(&mut self) -> &mut InterfaceSettings {
+        &mut self.settings.interface
+    }
+
+    fn adjust(&mut self, step: i64) {
+        let field = self.field();
+        let interface = self.interface();
+        match field {
+            SettingsField::Theme => {
+                let themes = Theme::all();
+                let index = themes
+                    .iter()
+                    .position(|theme| theme.name.eq_ignore_ascii_case(&interface.theme))
+                    .unwrap_or(0);
+                let index = (index as i64 + step).rem_euclid(themes.len() as i64) as usize;
+                interface.theme = themes[index].name.to_string();
+            }
+            SettingsField::MapSeed => {
+                interface.map_seed = interface.map_seed.wrapping_add_signed(step as i32)
+            }
+            SettingsField::ResourceSeed => {
+                interface.resource_seed = interface.resource_seed.wrapping_add_signed(step)
+            }
+            SettingsField::TickRate => {
+                let (min, max) = InterfaceSettings::TICK_RATE_RANGE_MS;
+                interface.tick_rate_ms = interface
+                    .tick_rate_ms
+                    .saturating_add_signed(step * 10)
+                    .clamp(min, max);
+            }
+            SettingsField::Key(_) => {}
+        }
+    }
+
+    fn value(&self, field: SettingsField) -> String {
+        let interface = &self.settings.interface;
+        match field {
+            SettingsField::Theme => interface.theme.clone(),
+            SettingsField::MapSeed => interface.map_seed.to_string(),
+            SettingsField::ResourceSeed => interface.resource_seed.to_string(),
+            SettingsField::TickRate => interface.tick_rate_ms.to_string(),
+            SettingsField::Key(_) if self.capturing && field == self.field() => "?".to_string(),
+            SettingsField::Key(action) => interface.keys.key(action).to_string(),
+        }
+    }
+}
+
+/// Draws the settings screen centered in `area`, scrolled to keep the selected row visible
+pub fn render_settings(frame: &mut Frame, area: Rect, screen: &SettingsScreen) {
+    let mut rows = Vec::new();
+    for (index, field) in screen.fields.iter().enumerate() {
+        let row = format!("{:<18} < {:>8} >", field.label(), screen.value(*field));
+        rows.push(if index == screen.selected {
+            Line::from(format!("> {}", row)).bold().reversed()
+        } else {
+            Line::from(format!("  {}", row))
+        });
+    }
+    let mut footer = vec![Line::from("")];
+    if let Some(error) = &screen.error {
+        footer.push(Line::from(error.as_str()).red());
+    }
+    footer.push(Line::from("Left/Right change, Enter rebind").italic());
+    footer.push(Line::from("Esc save and go back").italic());
+
+    let width = rows
+        .iter()
+        .chain(&footer)
+        .map(|line| line.width() as u16 + 4)
+        .max()
+        .unwrap_or(0)
+        .min(area.width);
+    let height = (rows.len() + footer.len() + 2).min(area.height as usize);
+    let visible_rows = height.saturating_sub(footer.len() + 2).max(1);
+    let first_row = (screen.selected + 1).saturating_sub(visible_rows);
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .skip(first_row)
+        .take(visible_rows)
+        .chain(footer)
+        .collect();
+
+    let height = height as u16;
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let widget =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Settings "));
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+
+    fn press(screen: &mut SettingsScreen, code: KeyCode) -> SettingsAction {
+        screen.handle_event(&Event::Key(KeyEvent::from(code)))
+    }
+
+    #[test]
+    fn test_settings_screen_edits_and_rebinds() {
+        let mut screen = SettingsScreen::new(Settings::default());
+        press(&mut screen, KeyCode::Right);
+        for _ in 0..3 {
+            press(&mut screen, KeyCode::Down);
+        }
+        press(&mut screen, KeyCode::Left);
+
+        // First key row: Quit
+        press(&mut screen, KeyCode::Down);
+        press(&mut screen, KeyCode::Enter);
+        press(&mut screen, KeyCode::Char('Q'));
+
+        let SettingsAction::Save(settings) = press(&mut screen, KeyCode::Esc) else {
+            panic!("Expected the settings to be saved");
+        };
+        assert_eq!(settings.interface.theme, "Emoji");
+        assert_eq!(settings.interface.tick_rate_ms, 90);
+        assert_eq!(settings.interface.keys.action('Q'), Some(KeyAction::Quit));
+
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(30, 8))
+            .expect("test terminal");
+        terminal
+            .draw(|frame| render_settings(frame, frame.area(), &screen))
+            .expect("settings render");
+    }
+}
@@ -0,0 +1,322 @@
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::Rect,
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{app::GameSetup, difficulty::Difficulty};
+
+const WIDTH_RANGE: (usize, usize) = (20, 200);
+const HEIGHT_RANGE: (usize, usize) = (10, 60);
+const MAX_ROBOTS_PER_TYPE: usize = 20;
+const MAX_CAVE_LAYERS: usize = 3;
+
+/// Rows of the setup screen, in display order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetupField {
+    Width,
+    Height,
+    MapSeed,
+    ResourceSeed,
+    Explorers,
+    Collectors,
+    Scientists,
+    Scouts,
+    CaveLayers,
+    Wraps,
+    Hex,
+    Difficulty,
+}
+
+impl SetupField {
+    const ALL: [SetupField; 12] = [
+        SetupField::Width,
+        SetupField::Height,
+        SetupField::MapSeed,
+        SetupField::ResourceSeed,
+        SetupField::Explorers,
+        SetupField::Collectors,
+        SetupField::Scientists,
+        SetupField::Scouts,
+        SetupField::CaveLayers,
+        SetupField::Wraps,
+        SetupField::Hex,
+        SetupField::Difficulty,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            SetupField::Width => "Map width",
+            SetupField::Height => "Map height",
+            SetupField::MapSeed => "Map seed",
+            SetupField::ResourceSeed => "Resource seed",
+            SetupField::Explorers => "Explorers",
+            SetupField::Collectors => "Collectors",
+            SetupField::Scientists => "Scientists",
+            SetupField::Scouts => "Scouts",
+            SetupField::CaveLayers => "Cave layers",
+            SetupField::Wraps => "Wrap edges",
+            SetupField::Hex => "Hex grid",
+            SetupField::Difficulty => "Difficulty",
+        }
+    }
+}
+
+/// What the setup screen asks the main loop to do after a key press
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetupAction {
+    Continue,
+    Launch(GameSetup),
+    /// Back to the menu without starting a game
+    Cancel,
+}
+
+/// New-game screen opened from the menu, prefilled from the command line
+pub struct SetupWizard {
+    setup: GameSetup,
+    /// Seeds rolled at launch instead of taken from the setup
+    random_map_seed: bool,
+    random_resource_seed: bool,
+    selected: usize,
+    /// Why the last launch attempt was refused
+    error: Option<String>,
+}
+
+impl SetupWizard {
+    pub fn new(setup: GameSetup) -> Self {
+        Self {
+            setup,
+            random_map_seed: false,
+            random_resource_seed: false,
+            selected: 0,
+            error: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> SetupAction {
+        let Event::Key(key) = event else {
+            return SetupAction::Continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            return SetupAction::Continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return SetupAction::Cancel,
+            KeyCode::Enter => return self.launch(),
+            KeyCode::Up | KeyCode::BackTab => {
+                self.selected = (self.selected + SetupField::ALL.len() - 1) % SetupField::ALL.len()
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.selected = (self.selected + 1) % SetupField::ALL.len()
+            }
+            KeyCode::Left | KeyCode::Char('-') => self.adjust(-1),
+            KeyCode::Right | KeyCode::Char('+') => self.adjust(1),
+            KeyCode::Char('r') => self.toggle_random_seed(),
+            _ => {}
+        }
+        SetupAction::Continue
+    }
+
+    fn field(&self) -> SetupField {
+        SetupField::ALL[self.selected]
+    }
+
+    fn adjust(&mut self, step: isize) {
+        let field = self.field();
+        let setup = &mut self.setup;
+        let count = |value: &mut usize| {
+            *value = value.saturating_add_signed(step).min(MAX_ROBOTS_PER_TYPE);
+        };
+        match field {
+            SetupField::Width => {
+                setup.width = setup
+                    .width
+                    .saturating_add_signed(step * 5)
+                    .clamp(WIDTH_RANGE.0, WIDTH_RANGE.1)
+            }
+            SetupField::Height => {
+                setup.height = setup
+                    .height
+                    .saturating_add_signed(step)
+                    .clamp(HEIGHT_RANGE.0, HEIGHT_RANGE.1)
+            }
+            SetupField::MapSeed => {
+                self.random_map_seed = false;
+                setup.map_seed = setup.map_seed.wrapping_add_signed(step as i32);
+            }
+            SetupField::ResourceSeed => {
+                self.random_resource_seed = false;
+                setup.resource_seed = setup.resource_seed.wrapping_add_signed(step as i64);
+            }
+            SetupField::Explorers => count(&mut setup.robot_counts.exploration),
+            SetupField::Collectors => count(&mut setup.robot_counts.collection),
+            SetupField::Scientists => count(&mut setup.robot_counts.scientific),
+            SetupField::Scouts => count(&mut setup.robot_counts.scout),
+            SetupField::CaveLayers => {
+                setup.shape.cave_layers = setup
+                    .shape
+                    .cave_layers
+                    .saturating_add_signed(step)
+                    .min(MAX_CAVE_LAYERS)
+            }
+            SetupField::Wraps => setup.shape.wraps = !setup.shape.wraps,
+            SetupField::Hex => setup.shape.hex = !setup.shape.hex,
+            SetupField::Difficulty => {
+                let presets = [Difficulty::Sandbox, Difficulty::Normal, Difficulty::Harsh];
+                let index = presets
+                    .iter()
+                    .position(|&preset| preset == setup.difficulty)
+                    .unwrap_or(1);
+                setup.difficulty =
+                    presets[index.saturating_add_signed(step).min(presets.len() - 1)];
+            }
+        }
+    }
+
+    fn toggle_random_seed(&mut self) {
+        match self.field() {
+            SetupField::MapSeed => self.random_map_seed = !self.random_map_seed,
+            SetupField::ResourceSeed => self.random_resource_seed = !self.random_resource_seed,
+            _ => {}
+        }
+    }
+
+    /// The chosen setup with its random seeds rolled, unless it has no robot
+    fn launch(&mut self) -> SetupAction {
+        if self.setup.robot_counts.total() == 0 {
+            self.error = Some("At least one robot must be spawned".to_string());
+            return SetupAction::Continue;
+        }
+        let mut setup = self.setup.clone();
+        if self.random_map_seed {
+            setup.map_seed = rand::random();
+        }
+        if self.random_resource_seed {
+            setup.resource_seed = rand::random();
+        }
+        log::info!("Launching {:?}", setup);
+        SetupAction::Launch(setup)
+    }
+
+    fn value(&self, field: SetupField) -> String {
+        let setup = &self.setup;
+        match field {
+            SetupField::Width => setup.width.to_string(),
+            SetupField::Height => setup.height.to_string(),
+            SetupField::MapSeed if self.random_map_seed => "random".to_string(),
+            SetupField::MapSeed => setup.map_seed.to_string(),
+            SetupField::ResourceSeed if self.random_resource_seed => "random".to_string(),
+            SetupField::ResourceSeed => setup.resource_seed.to_string(),
+            SetupField::Explorers => setup.robot_counts.exploration.to_string(),
+            SetupField::Collectors => setup.robot_counts.collection.to_string(),
+            SetupField::Scientists => setup.robot_counts.scientific.to_string(),
+            SetupField::Scouts => setup.robot_counts.scout.to_string(),
+            SetupField::CaveLayers => setup.shape.cave_layers.to_string(),
+            SetupField::Wraps => if setup.shape.wraps { "yes" } else { "no" }.to_string(),
+            SetupField::Hex => if setup.shape.hex { "yes" } else { "no" }.to_string(),
+            SetupField::Difficulty => format!("{:?}", setup.difficulty),
+        }
+    }
+}
+
+/// Draws the setup screen centered in `area`
+pub fn render_setup(frame: &mut Frame, area: Rect, wizard: &SetupWizard) {
+    let mut lines = vec![Line::from("")];
+    for (index, field) in SetupField::ALL.iter().enumerate() {
+        let row = format!("{:<14} < {:>10} >", field.label(), wizard.value(*field));
+        lines.push(if index == wizard.selected {
+            Line::from(format!("> {}", row)).bold().reversed()
+        } else {
+            Line::from(format!("  {}", row))
+        });
+    }
+    lines.push(Line::from(""));
+    if let Some(error) = &wizard.error {
+        lines.push(Line::from(error.as_str()).red());
+    }
+    lines.push(Line::from("Up/Down select, Left/Right change").italic());
+    lines.push(Line::from("r random seed, Enter start, Esc back").italic());
+
+    let width = lines
+        .iter()
+        .map(|line| line.width() as u16 + 4)
+        .max()
+        .unwrap_or(0)
+        .min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let widget =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" New game "));
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+
+    fn press(wizard: &mut SetupWizard, code: KeyCode) -> SetupAction {
+        wizard.handle_event(&Event::Key(KeyEvent::from(code)))
+    }
+
+    #[test]
+    fn test_setup_wizard_edits_and_launches() {
+        let mut wizard = SetupWizard::new(GameSetup::default());
+
+        press(&mut wizard, KeyCode::Right);
+        assert_eq!(wizard.setup.width, 95);
+        press(&mut wizard, KeyCode::Up);
+        press(&mut wizard, KeyCode::Right);
+        assert_eq!(wizard.setup.difficulty, Difficulty::Harsh);
+        press(&mut wizard, KeyCode::Right);
+        assert_eq!(wizard.setup.difficulty, Difficulty::Harsh);
+
+        // Down from the last row wraps to the map width, then on to the explorers
+        for _ in 0..5 {
+            press(&mut wizard, KeyCode::Down);
+        }
+        for _ in 0..3 {
+            press(&mut wizard, KeyCode::Left);
+        }
+        assert_eq!(wizard.setup.robot_counts.exploration, 0);
+
+        match press(&mut wizard, KeyCode::Enter) {
+            SetupAction::Launch(setup) => {
+                assert_eq!(setup.width, 95);
+                assert_eq!(setup.map_seed, 34);
+                assert_eq!(setup.robot_counts.total(), 2);
+            }
+            other => panic!("Expected a launch, got {:?}", other),
+        }
+        assert_eq!(press(&mut wizard, KeyCode::Char('q')), SetupAction::Cancel);
+    }
+
+    #[test]
+    fn test_setup_wizard_refuses_a_run_without_robots() {
+        let mut wizard = SetupWizard::new(GameSetup::default());
+        wizard.setup.robot_counts = crate::app::RobotCounts {
+            exploration: 0,
+            collection: 0,
+            scientific: 0,
+            scout: 0,
+        };
+        assert_eq!(press(&mut wizard, KeyCode::Enter), SetupAction::Continue);
+        assert!(wizard.error.is_some());
+
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(20, 6))
+            .expect("test terminal");
+        terminal
+            .draw(|frame| render_setup(frame, frame.area(), &wizard))
+            .expect("setup renders");
+    }
+}
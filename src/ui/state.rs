@@ -0,0 +1,336 @@
+use ratatui::layout::Rect;
+use std::{collections::BTreeSet, ops::Range, time::Duration};
+
+use crate::{
+    map::noise::Map,
+    settings::InterfaceSettings,
+    ui::{
+        keys::{KeyAction, KeyBindings},
+        map_renderer::MapRenderCache,
+        theme::Theme,
+    },
+};
+
+/// How the map panel draws the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapView {
+    /// One terminal cell per tile
+    Grid,
+    /// One braille dot per tile, 2x4 tiles per terminal cell
+    Braille,
+}
+
+/// What the keys of the simulation screen are about, shown in the status bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiMode {
+    Normal,
+    /// Robots are highlighted or grouped for commands
+    Select,
+    /// A patrol route is being drawn
+    Editor,
+    /// The command console is open
+    Console,
+}
+
+impl UiMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            UiMode::Normal => "NORMAL",
+            UiMode::Select => "SELECT",
+            UiMode::Editor => "EDITOR",
+            UiMode::Console => "CONSOLE",
+        }
+    }
+
+    /// Keys worth knowing in this mode, each with what it does
+    pub fn hints(self, keys: &KeyBindings) -> Vec<(String, &'static str)> {
+        let fixed = |key: &str, label| (key.to_string(), label);
+        let bound = |action: KeyAction| (keys.key(action).to_string(), action.label());
+        match self {
+            UiMode::Normal => vec![
+                fixed("Tab", "Select robot"),
+                fixed("Arrows", "Move cursor"),
+                bound(KeyAction::MarkPriority),
+                bound(KeyAction::Annotate),
+                fixed(":", "Console"),
+                fixed("Esc", "Menu"),
+                bound(KeyAction::Quit),
+            ],
+            UiMode::Select => vec![
+                fixed("Space", "Group"),
+                fixed("1-3", "Assign squad"),
+                bound(KeyAction::FormationLine),
+                bound(KeyAction::AddWaypoint),
+                bound(KeyAction::Decommission),
+                bound(KeyAction::Convert),
+            ],
+            UiMode::Editor => vec![
+                bound(KeyAction::AddWaypoint),
+                bound(KeyAction::Patrol),
+                bound(KeyAction::ClearPatrol),
+            ],
+            UiMode::Console => vec![fixed("Enter", "Run"), fixed("Esc", "Close")],
+        }
+    }
+}
+
+/// Presentation state kept by the UI between frames, independent of the simulation
+pub struct UiState {
+    pub map_cache: MapRenderCache,
+    pub map_view: MapView,
+    /// Map tile targeted by formation and relocation commands
+    pub cursor: (usize, usize),
+    /// Robot highlighted for group selection
+    pub selected_robot: Option<u32>,
+    /// Robots selected for group commands
+    pub group: BTreeSet<u32>,
+    /// Patrol route being drawn, in visiting order
+    pub patrol_draft: Vec<(usize, usize)>,
+    /// Squad receiving squad-level commands
+    pub active_squad: Option<String>,
+    /// Area of the map tiles on screen during the last grid render, for mouse clicks
+    pub map_area: Option<Rect>,
+    /// Map row drawn at the top of `map_area`
+    pub map_first_row: usize,
+    /// Whether `map_area` holds hex tiles, two columns wide with odd rows shifted by one
+    pub map_hex: bool,
+    /// Map layer on screen, 0 for the surface
+    pub layer: usize,
+    /// Draws the selected robot's knowledge over the map
+    pub vision_overlay: bool,
+    /// Shows the station's merge log, docking queue and stores in place of the map
+    pub station_view: bool,
+    /// Shows the profiler's figures over the map
+    pub profiler_overlay: bool,
+    /// Time between two ticks while the run cannot keep up with its tick rate
+    pub running_behind: Option<Duration>,
+    /// Character keys of the simulation screen
+    pub keys: KeyBindings,
+    /// Line typed in the command console, while it is open
+    pub console: Option<String>,
+    /// What the console answered to the last command
+    pub console_reply: Option<Result<String, String>>,
+    themes: Vec<Theme>,
+    theme_index: usize,
+}
+
+impl UiState {
+    pub fn new() -> Self {
+        Self {
+            map_cache: MapRenderCache::new(),
+            map_view: MapView::Grid,
+            cursor: (0, 0),
+            selected_robot: None,
+            group: BTreeSet::new(),
+            patrol_draft: Vec::new(),
+            active_squad: None,
+            map_area: None,
+            map_first_row: 0,
+            map_hex: false,
+            layer: 0,
+            vision_overlay: false,
+            station_view: false,
+            profiler_overlay: false,
+            running_behind: None,
+            keys: KeyBindings::default(),
+            console: None,
+            console_reply: None,
+            themes: Theme::all(),
+            theme_index: 0,
+        }
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.themes[self.theme_index]
+    }
+
+    /// Takes the theme and key bindings of the settings
+    pub fn apply_settings(&mut self, settings: &InterfaceSettings) {
+        match self
+            .themes
+            .iter()
+            .position(|theme| theme.name.eq_ignore_ascii_case(&settings.theme))
+        {
+            Some(index) => self.theme_index = index,
+            None => log::warn!(
+                "Unknown theme {}, keeping {}",
+                settings.theme,
+                self.theme().name
+            ),
+        }
+        self.keys = settings.keys.clone();
+    }
+
+    /// Switches to the next built-in theme
+    pub fn cycle_theme(&mut self) {
+        self.theme_index = (self.theme_index + 1) % self.themes.len();
+        log::info!("UI theme set to {}", self.theme().name);
+    }
+
+    /// Switches between the grid and braille map views
+    pub fn toggle_map_view(&mut self) {
+        self.map_view = match self.map_view {
+            MapView::Grid => MapView::Braille,
+            MapView::Braille => MapView::Grid,
+        };
+        log::info!("Map view set to {:?}", self.map_view);
+    }
+
+    /// Moves the cursor by one tile, staying on the `rows` of a `width` wide map
+    pub fn move_cursor(&mut self, dx: isize, dy: isize, width: usize, rows: Range<usize>) {
+        let (x, y) = self.cursor;
+        self.cursor = (
+            x.saturating_add_signed(dx).min(width.saturating_sub(1)),
+            y.saturating_add_signed(dy)
+                .clamp(rows.start, rows.end.saturating_sub(1).max(rows.start)),
+        );
+    }
+
+    /// Shows the next layer of `map`, the cursor keeping its place on it
+    pub fn cycle_layer(&mut self, map: &Map) {
+        let offset = self
+            .cursor
+            .1
+            .saturating_sub(map.layer_rows(self.layer).start);
+        self.layer = (self.layer + 1) % map.layers();
+        let rows = map.layer_rows(self.layer);
+        self.cursor.1 = (rows.start + offset).min(rows.end.saturating_sub(1));
+        log::info!("Showing map layer {}", self.layer);
+    }
+
+    pub fn mode(&self) -> UiMode {
+        if self.console.is_some() {
+            UiMode::Console
+        } else if !self.patrol_draft.is_empty() {
+            UiMode::Editor
+        } else if self.selected_robot.is_some() || !self.group.is_empty() {
+            UiMode::Select
+        } else {
+            UiMode::Normal
+        }
+    }
+
+    /// Highlights the robot after the current one, wrapping around `robot_ids`
+    pub fn select_next_robot(&mut self, robot_ids: &[u32]) {
+        self.selected_robot = match self.selected_robot {
+            Some(current) => robot_ids
+                .iter()
+                .find(|&&id| id > current)
+                .or(robot_ids.first())
+                .copied(),
+            None => robot_ids.first().copied(),
+        };
+    }
+
+    /// Adds the highlighted robot to the group, or removes it if already there
+    pub fn toggle_selected_in_group(&mut self) {
+        if let Some(id) = self.selected_robot {
+            if !self.group.remove(&id) {
+                self.group.insert(id);
+            }
+        }
+    }
+
+    /// Makes the squad after the active one active, wrapping around `names`
+    pub fn cycle_squad(&mut self, names: &[&str]) {
+        let next = match &self.active_squad {
+            Some(current) => names
+                .iter()
+                .find(|&&name| name > current.as_str())
+                .or(names.first()),
+            None => names.first(),
+        };
+        self.active_squad = next.map(|name| name.to_string());
+    }
+
+    /// Robots targeted by group commands: the group, or else the highlighted robot
+    pub fn command_targets(&self) -> Vec<u32> {
+        if self.group.is_empty() {
+            self.selected_robot.into_iter().collect()
+        } else {
+            self.group.iter().copied().collect()
+        }
+    }
+
+    /// Forgets robots that are no longer active
+    pub fn retain_robots(&mut self, robot_ids: &[u32]) {
+        self.group.retain(|id| robot_ids.contains(id));
+        if self
+            .selected_robot
+            .is_some_and(|id| !robot_ids.contains(&id))
+        {
+            self.selected_robot = None;
+        }
+    }
+
+    /// Map tile under a terminal cell of the grid view, if any
+    pub fn tile_at(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        let area = self.map_area?;
+        if column < area.x || row < area.y || column >= area.right() || row >= area.bottom() {
+            return None;
+        }
+        let y = usize::from(row - area.y) + self.map_first_row;
+        let mut column = column - area.x;
+        let mut cell_width = self.theme().cell_width() as u16;
+        if self.map_hex {
+            cell_width = cell_width.max(2);
+            if y % 2 == 1 {
+                column = column.checked_sub(1)?;
+            }
+        }
+        Some((usize::from(column / cell_width), y))
+    }
+}
+
+impl Default for UiState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_selection_cycles_and_toggles() {
+        let mut ui = UiState::new();
+        let ids = [2, 5, 9];
+
+        ui.select_next_robot(&ids);
+        ui.toggle_selected_in_group();
+        ui.select_next_robot(&ids);
+        ui.select_next_robot(&ids);
+        ui.toggle_selected_in_group();
+        assert_eq!(ui.group, BTreeSet::from([2, 9]));
+
+        // Wraps around and deselects
+        ui.select_next_robot(&ids);
+        ui.toggle_selected_in_group();
+        assert_eq!(ui.group, BTreeSet::from([9]));
+
+        assert_eq!(ui.mode(), UiMode::Select);
+        ui.retain_robots(&[5]);
+        assert!(ui.group.is_empty());
+        assert_eq!(ui.selected_robot, None);
+        assert_eq!(ui.mode(), UiMode::Normal);
+        ui.patrol_draft.push((1, 1));
+        assert_eq!(ui.mode(), UiMode::Editor);
+        assert!(UiMode::Editor
+            .hints(&ui.keys)
+            .contains(&("p".to_string(), "Start patrol")));
+    }
+
+    #[test]
+    fn test_clicks_on_hex_maps_land_on_offset_tiles() {
+        let mut ui = UiState::new();
+        ui.map_area = Some(Rect::new(1, 1, 40, 10));
+        ui.map_hex = true;
+
+        // Tiles are two columns wide, odd rows start one column further right
+        assert_eq!(ui.tile_at(4, 1), Some((1, 0)));
+        assert_eq!(ui.tile_at(4, 2), Some((1, 1)));
+        assert_eq!(ui.tile_at(3, 2), Some((0, 1)));
+        assert_eq!(ui.tile_at(1, 2), None);
+    }
+}
@@ -0,0 +1,246 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+
+use crate::{
+    app::App,
+    communication::channels::ResourceType,
+    robot::core::state::RobotStatus,
+    station::{journal::Severity, maintenance::MaintenanceState},
+};
+
+/// The station tab, shown in place of the map: recent merges, the event journal, robots
+/// docking or in service, and what the station holds
+pub fn render_station_view(frame: &mut Frame, area: Rect, app: &App) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let left = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(50),
+            Constraint::Min(0),
+            Constraint::Length(CONFLICT_ROWS + 2),
+        ])
+        .split(columns[0]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(columns[1]);
+
+    frame.render_widget(panel(" Recent merges ", merge_lines(app)), left[0]);
+    frame.render_widget(panel(" Journal ", journal_lines(app)), left[1]);
+    frame.render_widget(panel(" Conflicts ", conflict_lines(app)), left[2]);
+    frame.render_widget(panel(" Docking queue ", docking_lines(app)), right[0]);
+    frame.render_widget(panel(" Stores ", store_lines(app)), right[1]);
+}
+
+fn panel<'a>(title: &'a str, lines: Vec<Line<'a>>) -> List<'a> {
+    List::new(lines.into_iter().map(ListItem::new))
+        .block(Block::default().borders(Borders::ALL).title(title))
+}
+
+/// Newest merge first
+fn merge_lines(app: &App) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::from(format!(
+        "{:>7}  {:>5}  {:>6}  {:>9}",
+        "Tick", "Robot", "Tiles", "Conflicts"
+    ))
+    .bold()];
+    let log = app.merge_log();
+    if log.is_empty() {
+        lines.push(Line::from("No robot docked yet").italic());
+    }
+    for record in log.iter().rev() {
+        lines.push(Line::from(format!(
+            "{:>7}  {:>5}  {:>6}  {:>9}",
+            record.tick, record.robot_id, record.stats.contributed, record.stats.conflicts
+        )));
+    }
+    lines
+}
+
+/// Newest entry first, alarms in red
+fn journal_lines(app: &App) -> Vec<Line<'static>> {
+    if app.journal.is_empty() {
+        return vec![Line::from("Nothing of note yet").italic()];
+    }
+    app.journal
+        .entries()
+        .rev()
+        .map(|entry| {
+            let line = Line::from(format!("{:>7}  {}", entry.tick, entry.text));
+            match entry.severity {
+                Severity::Alarm => line.red(),
+                Severity::Info => line,
+            }
+        })
+        .collect()
+}
+
+/// Rows of the conflicts panel
+const CONFLICT_ROWS: u16 = 5;
+
+fn conflict_lines(app: &App) -> Vec<Line<'static>> {
+    let stats = app.conflict_stats();
+    vec![
+        Line::from(format!("Obstacle or walkable: {}", stats.obstacle_walkable)),
+        Line::from(format!("Resource amount: {}", stats.resource_amount)),
+        Line::from(format!("Resource type: {}", stats.resource_type)),
+        Line::from(format!("Resource or none: {}", stats.resource_presence)),
+        Line::from(format!(
+            "Won by the newer report: {} of {}",
+            stats.overturned,
+            stats.total()
+        )),
+    ]
+}
+
+/// Robots in service, then docked, then on their way in, closest first
+fn docking_lines(app: &App) -> Vec<Line<'static>> {
+    let (station, distance) = {
+        let map = app.map.read();
+        let station = map.station_coords();
+        let distances: Vec<_> = app
+            .robots
+            .iter()
+            .map(|entry| {
+                (
+                    entry.state.id,
+                    map.distance(station, (entry.state.x, entry.state.y)),
+                )
+            })
+            .collect();
+        (station, distances)
+    };
+    let distance_of = |id: u32| {
+        distance
+            .iter()
+            .find(|&&(robot_id, _)| robot_id == id)
+            .map_or(usize::MAX, |&(_, distance)| distance)
+    };
+
+    let charging = app.charging.charging();
+    let waiting = app.charging.queue();
+
+    let mut queue: Vec<(u8, usize, String)> = Vec::new();
+    for entry in app.robots.iter() {
+        let id = entry.state.id;
+        let label = format!("#{} {:?}", id, entry.robot_type);
+        match (app.maintenance.state_of(id), &entry.state.status) {
+            (Some(MaintenanceState::InService { until }), _) => {
+                queue.push((0, 0, format!("{}: in service until tick {}", label, until)))
+            }
+            (_, RobotStatus::AtStation) => {
+                if let Some(on) = charging.iter().find(|on| on.robot_id == id) {
+                    queue.push((1, 0, format!("{}: charging until tick {}", label, on.until)));
+                } else if let Some(place) = waiting.iter().position(|r| r.robot_id == id) {
+                    queue.push((
+                        1,
+                        place + 1,
+                        format!(
+                            "{}: waiting for a charger ({}), {} energy",
+                            label,
+                            place + 1,
+                            entry.state.energy
+                        ),
+                    ));
+                } else {
+                    queue.push((1, usize::MAX, format!("{}: docked", label)))
+                }
+            }
+            (_, RobotStatus::ReturningToStation) => {
+                let distance = distance_of(id);
+                queue.push((
+                    2,
+                    distance,
+                    format!(
+                        "{}: {} tiles out, {} energy",
+                        label, distance, entry.state.energy
+                    ),
+                ));
+            }
+            _ => {}
+        }
+    }
+    queue.sort();
+
+    let mut lines = vec![Line::from(format!("Dock at {:?}", station)).bold()];
+    if let Some(slots) = app.charging.slots() {
+        lines.push(Line::from(format!(
+            "Chargers: {}/{} busy, {} waiting, policy {}",
+            charging.len(),
+            slots,
+            waiting.len(),
+            app.charging.policy().name()
+        )));
+    }
+    if queue.is_empty() {
+        lines.push(Line::from("Nobody heading in").italic());
+    }
+    lines.extend(queue.into_iter().map(|(_, _, line)| Line::from(line)));
+    lines
+}
+
+fn store_lines(app: &App) -> Vec<Line<'static>> {
+    let stock = |resource_type: ResourceType| {
+        app.collected_resources
+            .get(&resource_type)
+            .copied()
+            .unwrap_or(0)
+    };
+    let energy = stock(ResourceType::ENERGY);
+    let resources = app.resource_catalog();
+    let stocked = resources.consumables();
+    let mut lines: Vec<Line> = stocked
+        .iter()
+        .map(|&resource_type| {
+            Line::from(format!(
+                "{}: {}",
+                resources.name(resource_type),
+                stock(resource_type)
+            ))
+        })
+        .collect();
+    let worth: u64 = stocked
+        .iter()
+        .map(|&resource_type| {
+            u64::from(stock(resource_type)) * u64::from(resources.value(resource_type))
+        })
+        .sum();
+    lines.push(Line::from(format!("Stock value: {}", worth)));
+    lines.push(Line::from(format!(
+        "Science value: {}",
+        app.scientific_data
+    )));
+    if app.drained_energy > 0 {
+        lines.push(Line::from(format!(
+            "Upkeep so far: -{} energy",
+            app.drained_energy
+        )));
+    }
+
+    // How many full batteries the energy stock would refill, going by the fleet's average
+    let batteries: Vec<u32> = app
+        .robots
+        .iter()
+        .map(|entry| entry.state.max_energy)
+        .collect();
+    if let Some(average) = (batteries.iter().sum::<u32>()).checked_div(batteries.len() as u32) {
+        lines.push(Line::from(format!(
+            "Recharge capacity: {} full charges",
+            energy.checked_div(average).unwrap_or(0)
+        )));
+    }
+    lines.push(Line::from(format!(
+        "In maintenance: {}",
+        app.maintenance.in_maintenance()
+    )));
+    lines.push(Line::from("Production: the station builds no robots").italic());
+    lines
+}
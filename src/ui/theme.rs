@@ -0,0 +1,171 @@
+use ratatui::{
+    style::{Color, Style},
+    text::Span,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::communication::channels::ResourceType;
+use crate::map::resources::{ResourceCatalog, ResourceSpec};
+
+/// Glyphs used to draw map tiles and robots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphSet {
+    /// Single-column characters, works in every terminal
+    Ascii,
+    /// Emoji and other wide characters, every map cell is two columns wide
+    Emoji,
+}
+
+/// Robot kinds as drawn on the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RobotGlyph {
+    Explorer,
+    Collector,
+    Scientist,
+    Scout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub glyphs: GlyphSet,
+}
+
+impl Theme {
+    pub fn classic() -> Self {
+        Self {
+            name: "Classic",
+            glyphs: GlyphSet::Ascii,
+        }
+    }
+
+    pub fn emoji() -> Self {
+        Self {
+            name: "Emoji",
+            glyphs: GlyphSet::Emoji,
+        }
+    }
+
+    /// All built-in themes, in the order they are cycled through in the UI
+    pub fn all() -> Vec<Self> {
+        vec![Self::classic(), Self::emoji()]
+    }
+
+    /// Number of terminal columns used by a single map tile
+    pub fn cell_width(&self) -> usize {
+        match self.glyphs {
+            GlyphSet::Ascii => 1,
+            GlyphSet::Emoji => 2,
+        }
+    }
+
+    /// Styled span for a tile, keyed by the symbol from `Map::tile_symbol`, deposits going
+    /// by their symbol in `resources`
+    pub fn tile_span(&self, symbol: char, resources: &ResourceCatalog) -> Span<'static> {
+        let resource_type = resources.from_symbol(symbol);
+        let salvage = symbol != 's'
+            && symbol.is_ascii_lowercase()
+            && resources.from_symbol(symbol.to_ascii_uppercase()).is_some();
+        let style = match symbol {
+            '█' => Style::default().fg(Color::Gray),
+            ' ' => Style::default().fg(Color::Rgb(50, 50, 50)),
+            's' => Style::default().fg(Color::LightGreen),
+            _ if salvage => Style::default().fg(Color::LightRed),
+            '⌂' => Style::default().fg(Color::Indexed(208)),
+            '≡' => Style::default().fg(Color::Cyan),
+            _ => Style::default().fg(resource_type.map_or(Color::White, |resource_type| {
+                resource_color(resources.spec(resource_type))
+            })),
+        };
+        let glyph = match (self.glyphs, symbol) {
+            (GlyphSet::Emoji, '█') => "⬛".to_string(),
+            (GlyphSet::Emoji, _) if resource_type == Some(ResourceType::ENERGY) => "⚡".to_string(),
+            (GlyphSet::Emoji, _) if resource_type == Some(ResourceType::MINERALS) => {
+                "💎".to_string()
+            }
+            (GlyphSet::Emoji, _) if resource_type == Some(ResourceType::SCIENCE_POINTS) => {
+                "🧪".to_string()
+            }
+            (GlyphSet::Emoji, 's') => "🧬".to_string(),
+            (GlyphSet::Emoji, _) if salvage => "📦".to_string(),
+            (GlyphSet::Emoji, '⌂') => "🏠".to_string(),
+            (GlyphSet::Emoji, '≡') => "🪜".to_string(),
+            _ => symbol.to_string(),
+        };
+        Span::styled(self.pad(glyph), style)
+    }
+
+    /// Styled span for a robot, padded to the same width as a tile
+    pub fn robot_span(&self, robot: RobotGlyph) -> Span<'static> {
+        let (symbol, emoji, color) = match robot {
+            RobotGlyph::Explorer => ("X", "🧭", Color::Red),
+            RobotGlyph::Collector => ("C", "🚜", Color::White),
+            RobotGlyph::Scientist => ("S", "🔬", Color::Gray),
+            RobotGlyph::Scout => ("R", "🔭", Color::Yellow),
+        };
+        let glyph = match self.glyphs {
+            GlyphSet::Ascii => symbol,
+            GlyphSet::Emoji => emoji,
+        };
+        Span::styled(self.pad(glyph.to_string()), Style::default().fg(color))
+    }
+
+    pub fn hostile_span(&self) -> Span<'static> {
+        let glyph = match self.glyphs {
+            GlyphSet::Ascii => "@",
+            GlyphSet::Emoji => "🌀",
+        };
+        Span::styled(
+            self.pad(glyph.to_string()),
+            Style::default().fg(Color::Magenta),
+        )
+    }
+
+    /// Pads `glyph` with spaces so it fills exactly one map cell
+    fn pad(&self, mut glyph: String) -> String {
+        let width = glyph.width();
+        if width < self.cell_width() {
+            glyph.push_str(&" ".repeat(self.cell_width() - width));
+        }
+        glyph
+    }
+}
+
+/// Color deposits of the type are drawn in, white if its configured one is not a color
+pub fn resource_color(spec: &ResourceSpec) -> Color {
+    spec.color.parse().unwrap_or(Color::White)
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_glyph_fills_exactly_one_cell() {
+        for theme in Theme::all() {
+            for symbol in ['█', ' ', 'E', 'M', 'S', '⌂'] {
+                assert_eq!(
+                    theme.tile_span(symbol, &ResourceCatalog::default()).width(),
+                    theme.cell_width(),
+                    "{} tile {:?}",
+                    theme.name,
+                    symbol
+                );
+            }
+            for robot in [
+                RobotGlyph::Explorer,
+                RobotGlyph::Collector,
+                RobotGlyph::Scientist,
+                RobotGlyph::Scout,
+            ] {
+                assert_eq!(theme.robot_span(robot).width(), theme.cell_width());
+            }
+        }
+    }
+}
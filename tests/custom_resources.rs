@@ -0,0 +1,115 @@
+//! A resource type added by the settings, end to end, next to a run of the same process that
+//! keeps the built-in types.
+
+use std::sync::Arc;
+
+use astro_swarm::{
+    app::{App, RobotCounts, SpawnPolicy},
+    console::ConsoleCommand,
+    difficulty::Difficulty,
+    map::{
+        resources::{Resource, ResourceCatalog, ResourceSpec},
+        topology::MapShape,
+    },
+    settings::{ModuleCatalog, ResourceSettings},
+    ui::{map_renderer::render_app, state::UiState},
+};
+
+fn helium() -> ResourceSettings {
+    ResourceSettings {
+        types: vec![ResourceSpec {
+            name: "Helium".to_string(),
+            symbol: 'H',
+            color: "magenta".to_string(),
+            consumable: true,
+            weight: 2,
+            value: 3,
+        }],
+    }
+}
+
+fn start(resources: ResourceCatalog) -> App {
+    App::try_new(
+        60,
+        30,
+        34,
+        45,
+        RobotCounts {
+            exploration: 1,
+            collection: 0,
+            scientific: 0,
+            scout: 0,
+        },
+        SpawnPolicy::Scattered,
+        ModuleCatalog::default(),
+        Arc::new(resources),
+        Difficulty::Sandbox,
+        MapShape::default(),
+    )
+    .expect("a playable map")
+}
+
+fn legend(app: &App) -> String {
+    let mut terminal =
+        ratatui::Terminal::new(ratatui::backend::TestBackend::new(200, 70)).expect("test terminal");
+    let mut ui = UiState::new();
+    terminal
+        .draw(|frame| render_app(frame, frame.area(), app, &mut ui))
+        .expect("the run renders");
+    terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect()
+}
+
+#[test]
+fn test_a_resource_type_from_the_settings_plays_like_the_built_in_ones() {
+    let catalog = helium().catalog().expect("a valid catalog");
+    let helium = catalog.from_name("helium").expect("in the catalog");
+    assert_eq!(catalog.from_symbol('H'), Some(helium));
+    assert_eq!(catalog.consumables().len(), 3);
+    let app = start(catalog);
+    // Another run of the process keeps the built-in types
+    let plain = start(ResourceCatalog::default());
+    let resources = app.resource_catalog();
+
+    // Generated on the map, and taken from it by its type
+    let deposit = {
+        let map = app.map.read();
+        (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .find(|&(x, y)| matches!(map.get_resource(x, y), Some((found, _)) if found == helium))
+    }
+    .expect("a helium deposit");
+    assert!(app
+        .map
+        .try_collect_at(deposit, &resources.from_name("minerals").unwrap(), 5)
+        .is_none());
+    assert_eq!(app.map.try_collect_at(deposit, &helium, 5), Some(5));
+    assert!(plain
+        .map
+        .read()
+        .get_all_resources()
+        .values()
+        .all(|resource| plain.resource_catalog().name(resource.resource_type) != "Helium"));
+
+    // Collected by name in commands, sent as the type in the cargo robots report
+    assert!(matches!(
+        ConsoleCommand::parse("gather 1 helium", resources),
+        Ok(ConsoleCommand::Gather { id: 1, resource_type }) if resource_type == helium
+    ));
+    assert!(ConsoleCommand::parse("gather 1 helium", plain.resource_catalog()).is_err());
+    let cargo = serde_json::to_string(&Resource::new(helium, 5)).unwrap();
+    let cargo: Resource = serde_json::from_str(&cargo).unwrap();
+    assert_eq!(resources.name(cargo.resource_type), "Helium");
+
+    // Listed in the sidebar legend of its run only
+    assert!(legend(&app).contains("Helium (collected, value 3)"));
+    assert!(!legend(&plain).contains("Helium"));
+
+    app.shutdown();
+    plain.shutdown();
+}
@@ -0,0 +1,167 @@
+//! Golden-run regression checks.
+//!
+//! These tests pin a checksum of the generated world, of the spawn roster, and of the map,
+//! ledger and robot positions after a run of `RUN_TICKS` ticks, for the seeds `main` uses. The
+//! run goes by a stepped clock, which moves the seeded robots in turn, so it plays the same
+//! way every time. When a change is meant to alter it, run
+//! `GOLDEN_UPDATE=1 cargo test --test golden_run -- --nocapture` and copy the printed values.
+
+use astro_swarm::{
+    app::{App, RobotCounts, SpawnPolicy, TICK_RATE},
+    difficulty::Difficulty,
+    map::{noise::Map, topology::MapShape},
+    robot::{registry::RobotType, utils::clock::SteppedClock},
+    settings::ModuleCatalog,
+    simulation::SimulationBuilder,
+};
+use std::{collections::BTreeMap, sync::Arc};
+
+const WIDTH: usize = 90;
+const HEIGHT: usize = 15;
+const MAP_SEED: u32 = 34;
+const RESOURCE_SEED: u64 = 45;
+
+const GOLDEN_WORLD: u64 = 0x449b_6d03_276e_5bb3;
+const GOLDEN_ROSTER: u64 = 0xfad5_14f3_a91f_e559;
+const GOLDEN_RUN: u64 = 0x61e8_bcb0_3098_3766;
+
+const RUN_TICKS: u64 = 600;
+
+/// FNV-1a, stable across platforms and toolchains
+fn checksum(data: &str) -> u64 {
+    data.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn assert_golden(name: &str, actual: u64, golden: u64) {
+    if std::env::var_os("GOLDEN_UPDATE").is_some() {
+        println!("{} = {:#018x}", name, actual);
+        return;
+    }
+    assert_eq!(
+        actual, golden,
+        "{} changed ({:#018x}); if intended, update the golden value",
+        name, actual
+    );
+}
+
+fn world_state(map: &Map) -> String {
+    let mut state = String::new();
+    for y in 0..map.height {
+        for x in 0..map.width {
+            state.push(map.tile_symbol(x, y));
+        }
+        state.push('\n');
+    }
+    let mut resources: Vec<_> = map
+        .get_all_resources()
+        .iter()
+        .map(|(coords, resource)| {
+            format!(
+                "{:?}:{:?}:{}",
+                coords, resource.resource_type, resource.amount
+            )
+        })
+        .collect();
+    resources.sort();
+    state.push_str(&resources.join(","));
+    state
+}
+
+fn roster_state(app: &App) -> String {
+    let mut state: Vec<String> = app
+        .robots
+        .iter()
+        .map(|entry| {
+            let kind = match entry.robot_type {
+                RobotType::Exploration => "exploration",
+                RobotType::Collection => "collection",
+                RobotType::Scientific => "scientific",
+                RobotType::Scout => "scout",
+            };
+            let robot = &entry.state;
+            format!(
+                "{}:{}:{},{}:{}/{}",
+                robot.id, kind, robot.x, robot.y, robot.energy, robot.max_energy
+            )
+        })
+        .collect();
+    let collected: BTreeMap<_, _> = app.collected_resources.iter().collect();
+    state.push(format!(
+        "ledger:{:?}:{}:{}",
+        collected, app.scientific_data, app.total_explored
+    ));
+    state.join("\n")
+}
+
+fn counts() -> RobotCounts {
+    RobotCounts {
+        exploration: 3,
+        collection: 2,
+        scientific: 2,
+        scout: 0,
+    }
+}
+
+#[test]
+fn test_golden_world() {
+    let mut map = Map::new(WIDTH, HEIGHT, MAP_SEED);
+    map.spawn_resources(WIDTH * HEIGHT / 30, RESOURCE_SEED);
+    assert_golden("GOLDEN_WORLD", checksum(&world_state(&map)), GOLDEN_WORLD);
+}
+
+#[test]
+fn test_golden_spawn_roster() {
+    let app = App::new(
+        WIDTH,
+        HEIGHT,
+        MAP_SEED,
+        RESOURCE_SEED,
+        counts(),
+        SpawnPolicy::Scattered,
+        ModuleCatalog::default(),
+        Arc::default(),
+        Difficulty::Normal,
+        MapShape::default(),
+    );
+    assert_golden(
+        "GOLDEN_ROSTER",
+        checksum(&roster_state(&app)),
+        GOLDEN_ROSTER,
+    );
+}
+
+#[test]
+fn test_golden_run() {
+    let clock = Arc::new(SteppedClock::default());
+    let mut app = SimulationBuilder::new()
+        .size(WIDTH, HEIGHT)
+        .seeds(MAP_SEED, RESOURCE_SEED)
+        .robots(counts())
+        .difficulty(Difficulty::Normal)
+        .clock(clock.clone())
+        .build()
+        .expect("a playable map");
+    for _ in 0..RUN_TICKS {
+        app.update();
+        clock.step(TICK_RATE);
+    }
+    let mut ledger: Vec<String> = app
+        .ledger_audit()
+        .iter()
+        .map(|entry| format!("{:?}", entry))
+        .collect();
+    ledger.sort();
+    let state = format!(
+        "{}\n{}\n{}",
+        world_state(&app.map.read()),
+        ledger.join("\n"),
+        roster_state(&app)
+    );
+    assert!(app.total_explored > 0, "the robots never reported back");
+
+    clock.release();
+    app.shutdown();
+    assert_golden("GOLDEN_RUN", checksum(&state), GOLDEN_RUN);
+}
@@ -0,0 +1,47 @@
+//! Headless runs are scripted: their stdout must hold the result and nothing else.
+
+use std::{env, fs, process::Command};
+
+#[test]
+fn test_headless_stdout_is_one_json_line() {
+    // The run writes its log, history and saves next to it, keep those out of the tree
+    let dir = env::temp_dir().join(format!("astro-headless-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_astro-swarm"))
+        .args(["--headless", "--width", "12", "--height", "8"])
+        .args([
+            "--difficulty",
+            "sandbox",
+            "--warmup",
+            "800",
+            "--duration",
+            "800",
+        ])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "stdout was {:?}", stdout);
+    let result: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(result["ticks"], 800);
+}
+
+#[test]
+fn test_headless_setup_failures_are_not_failed_missions() {
+    // A file where the log directory goes keeps the log from opening
+    let dir = env::temp_dir().join(format!("astro-headless-nolog-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("logs"), "").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_astro-swarm"))
+        .args(["--headless", "--width", "12", "--height", "8"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+}
@@ -0,0 +1,75 @@
+//! Nearest-tile lookups on a large map: the spatial index of a knowledge map against a scan of
+//! every tile, as collectors did before. Ignored by default, run it in release mode:
+//! `cargo test --release --test nearest_tile_bench -- --ignored --nocapture`.
+
+use std::time::Instant;
+
+use astro_swarm::{
+    communication::channels::ResourceType,
+    map::topology::Topology,
+    robot::core::knowledge::{RobotKnowledge, TileInfo},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const SIDE: usize = 1000;
+const DEPOSITS: usize = 2000;
+const QUERIES: usize = 200;
+
+#[test]
+#[ignore]
+fn bench_nearest_deposit_on_a_large_map() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut knowledge = RobotKnowledge::new(SIDE, SIDE);
+    // The western half explored, with deposits scattered over it
+    for y in 0..SIDE {
+        for x in 0..SIDE / 2 {
+            knowledge.update_tile(x, y, TileInfo::Walkable);
+        }
+    }
+    for _ in 0..DEPOSITS {
+        let (x, y) = (rng.random_range(0..SIDE / 2), rng.random_range(0..SIDE));
+        knowledge.update_tile(x, y, TileInfo::Resource(ResourceType::MINERALS, 50));
+    }
+    let topology = Topology {
+        width: SIDE,
+        height: SIDE,
+        layer_height: SIDE,
+        wraps: false,
+        hex: false,
+        ..Topology::default()
+    };
+    let positions: Vec<(usize, usize)> = (0..QUERIES)
+        .map(|_| (rng.random_range(0..SIDE), rng.random_range(0..SIDE)))
+        .collect();
+
+    let started = Instant::now();
+    let scanned: Vec<_> = positions
+        .iter()
+        .map(|&from| {
+            knowledge
+                .tiles()
+                .filter(|(_, info)| matches!(info, TileInfo::Resource(ResourceType::MINERALS, _)))
+                .map(|(tile, _)| (topology.distance(from, tile), tile))
+                .min()
+        })
+        .collect();
+    let scan_time = started.elapsed();
+
+    let started = Instant::now();
+    let deposits = knowledge.index().deposits(&ResourceType::MINERALS).unwrap();
+    let indexed: Vec<_> = positions
+        .iter()
+        .map(|&from| {
+            deposits
+                .nearest(from, &topology, |_| true)
+                .map(|(tile, distance)| (distance, tile))
+        })
+        .collect();
+    let index_time = started.elapsed();
+
+    assert_eq!(indexed, scanned);
+    println!(
+        "{} nearest-deposit queries on a {}x{} map: full scan {:?}, index {:?}",
+        QUERIES, SIDE, SIDE, scan_time, index_time
+    );
+}
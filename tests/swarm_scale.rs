@@ -0,0 +1,96 @@
+//! Swarm size scaling check: a run of 240 robots, each on its own thread, sharing the map
+//! lock and the event channel with the station. The robots act in real time: sped up, a swarm
+//! this size keeps a small machine's cores busy on its own and the check would measure that.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use astro_swarm::{
+    app::{App, RobotCounts, SpawnPolicy},
+    difficulty::Difficulty,
+    error::SimError,
+    map::topology::MapShape,
+    robot::{registry::RobotType, utils::config},
+    settings::ModuleCatalog,
+    ui::{map_renderer::render_app, state::UiState},
+};
+
+const ROBOTS_PER_TYPE: usize = 60;
+const ROBOTS: usize = 4 * ROBOTS_PER_TYPE;
+const TICKS: usize = 100;
+
+#[test]
+fn test_a_swarm_of_240_robots_holds_up_to_its_cap() {
+    config::set_max_robots(ROBOTS);
+    let counts = RobotCounts {
+        exploration: ROBOTS_PER_TYPE,
+        collection: ROBOTS_PER_TYPE,
+        scientific: ROBOTS_PER_TYPE,
+        scout: ROBOTS_PER_TYPE,
+    };
+    let mut app = App::try_new(
+        120,
+        60,
+        34,
+        45,
+        counts,
+        SpawnPolicy::Scattered,
+        ModuleCatalog::default(),
+        Arc::default(),
+        Difficulty::Normal,
+        MapShape::default(),
+    )
+    .expect("room for the whole swarm");
+    assert_eq!(app.robots.len(), ROBOTS);
+    let starts: HashMap<u32, (usize, usize)> = app
+        .robots
+        .iter()
+        .map(|entry| (entry.state.id, (entry.state.x, entry.state.y)))
+        .collect();
+
+    // The station keeps up with the events and the map lock is never held for long
+    let mut slowest_update = Duration::ZERO;
+    for _ in 0..TICKS {
+        let started = Instant::now();
+        app.update();
+        slowest_update = slowest_update.max(started.elapsed());
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(
+        slowest_update < Duration::from_millis(500),
+        "an update took {:?}",
+        slowest_update
+    );
+    let moved = app
+        .robots
+        .iter()
+        .filter(|entry| starts.get(&entry.state.id) != Some(&(entry.state.x, entry.state.y)))
+        .count();
+    assert!(moved > ROBOTS / 2, "only {} robots moved", moved);
+
+    // One more robot is turned away, and the run goes on
+    assert!(matches!(
+        app.spawn_robot(RobotType::Scout),
+        Err(SimError::RobotCap(ROBOTS))
+    ));
+    app.update();
+
+    let mut terminal =
+        ratatui::Terminal::new(ratatui::backend::TestBackend::new(200, 70)).expect("test terminal");
+    let mut ui = UiState::new();
+    let started = Instant::now();
+    terminal
+        .draw(|frame| render_app(frame, frame.area(), &app, &mut ui))
+        .expect("a full swarm renders");
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    let started = Instant::now();
+    app.shutdown();
+    assert!(
+        started.elapsed() < Duration::from_secs(10),
+        "joining the robots took {:?}",
+        started.elapsed()
+    );
+}